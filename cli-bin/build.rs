@@ -33,25 +33,20 @@ fn generate_cheatsheet() -> Result<(), Box<dyn std::error::Error>> {
                 {
                     for (action_name_val, action_body_val) in actions {
                         let action_name = action_name_val.as_str().unwrap_or("");
-                        let flags = if let Value::Mapping(action_map) = action_body_val {
-                            match action_map.get(Value::String("flags".into())) {
-                                Some(Value::Sequence(seq)) => seq
-                                    .iter()
-                                    .filter_map(|v| v.as_str())
-                                    .collect::<Vec<_>>()
-                                    .join(", "),
-                                _ => String::new(),
-                            }
-                        } else {
-                            String::new()
-                        };
-
-                        let flags_disp = if flags.is_empty() { "—" } else { &flags };
                         table.push_str(&format!(
                             "| `{} {}` | {} |\n",
-                            cmd_name, action_name, flags_disp
+                            cmd_name,
+                            action_name,
+                            flags_cell(action_body_val)
                         ));
                     }
+                } else {
+                    // top-level command without sub-actions (e.g. `rate`)
+                    table.push_str(&format!(
+                        "| `{}` | {} |\n",
+                        cmd_name,
+                        flags_cell(&Value::Mapping(cmd_details))
+                    ));
                 }
             }
         }
@@ -62,3 +57,20 @@ fn generate_cheatsheet() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Render the `flags` list of a command/action as a table cell.
+fn flags_cell(body: &Value) -> String {
+    let flags = match body.get("flags") {
+        Some(Value::Sequence(seq)) => seq
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => String::new(),
+    };
+    if flags.is_empty() {
+        "—".to_string()
+    } else {
+        flags
+    }
+}
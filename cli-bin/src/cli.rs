@@ -1,20 +1,57 @@
 // src/cli.rs
 
+pub mod action;
 pub mod annotate;
 pub mod backup;
+pub mod bench;
+pub mod classify;
 pub mod coll;
+pub mod config;
+pub mod conflicts;
+pub mod context;
+pub mod db;
+pub mod demo;
+pub mod doctor;
+pub mod du;
+pub mod embed;
 pub mod event;
+pub mod explain;
+pub mod fav;
+pub mod gc;
+pub mod history;
+pub mod label;
 pub mod link;
+pub mod lock;
+pub mod organize;
+pub mod progress;
+pub mod prune;
+pub mod reindex;
 pub mod remind;
+pub mod root;
+pub mod run;
+pub mod search;
+pub mod shell;
+pub mod snapshot;
+pub mod sql;
+pub mod stale;
 pub mod state;
+pub mod stats;
+pub mod status;
+pub mod sync;
+pub mod tags;
 pub mod task;
+pub mod timings;
+pub mod token;
 pub mod version;
 pub mod view;
+pub mod warm;
 pub mod watch;
 
 use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 
+pub use context::Context;
+
 /// Output format for commands.
 #[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum Format {
@@ -30,10 +67,51 @@ pub struct Cli {
     #[arg(long)]
     pub verbose: bool,
 
+    /// Print how long each phase of the command took (also on --verbose)
+    #[arg(long, global = true)]
+    pub timings: bool,
+
+    /// Log every SQL statement run against the index, with its timing
+    /// (with --verbose, nested in the write that ran it)
+    #[arg(long, global = true)]
+    pub trace_sql: bool,
+
     /// Output format (text or JSON)
     #[arg(long, default_value = "text", value_enum, global = true)]
     pub format: Format,
 
+    /// Backups directory (overrides `MARLIN_BACKUPS_DIR` and the default)
+    #[arg(long, global = true)]
+    pub backup_dir: Option<std::path::PathBuf>,
+
+    /// Emit progress events (scan, backup, restore …) as one JSON object
+    /// per line on stderr
+    #[arg(long, value_enum, global = true, value_name = "FORMAT")]
+    pub events: Option<progress::EventsFormat>,
+
+    /// Write progress events to this already-open file descriptor instead
+    /// of stderr (implies `--events json`; Unix only)
+    #[arg(long, global = true, value_name = "N")]
+    pub events_fd: Option<i32>,
+
+    /// Index to use instead of the configured one; give it several times
+    /// to search across databases (`search` only)
+    #[arg(long = "db", global = true, value_name = "PATH")]
+    pub db: Vec<std::path::PathBuf>,
+
+    /// Open an index on a network or FUSE filesystem (NFS, SMB, sshfs …)
+    /// instead of refusing; SQLite's WAL journal can corrupt it there
+    #[arg(long, global = true)]
+    pub allow_network_db: bool,
+
+    /// Show times in UTC instead of the configured `timezone`
+    #[arg(long, global = true)]
+    pub utc: bool,
+
+    /// Change the metadata of locked files anyway (see `marlin lock`)
+    #[arg(long, global = true)]
+    pub ignore_locks: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -51,16 +129,63 @@ pub enum Commands {
 
         /// Directories to scan (defaults to cwd)
         paths: Vec<std::path::PathBuf>,
+
+        /// When another process is scanning one of the directories:
+        /// `wait` for it and scan, `skip` it, or `join` it (wait and use
+        /// its result)
+        #[arg(long, value_name = "POLICY", default_value = "wait")]
+        on_busy: libmarlin::scan_lease::OnBusy,
+
+        /// Also index what `.gitignore` and `.marlinignore` files exclude
+        #[arg(long)]
+        no_ignore: bool,
+
+        /// Afterwards drop indexed files below the directories that are
+        /// gone from disk, with their tags, attributes and links
+        #[arg(long)]
+        prune: bool,
+
+        /// With `--prune`, keep missing files as tombstones instead
+        #[arg(long, requires = "prune")]
+        keep_metadata: bool,
     },
 
+    /// Re-read metadata and hashes of selected files unconditionally
+    Reindex(reindex::ReindexArgs),
+
+    /// Drop indexed files gone from disk, showing what metadata goes too
+    Prune(prune::PruneArgs),
+
     /// Tag files matching a glob pattern (hierarchical tags use `/`)
+    #[command(
+        allow_missing_positional = true,
+        override_usage = "marlin tag <PATTERN> <TAG_PATH>\n       marlin tag --view <VIEW> <TAG_PATH>"
+    )]
     Tag {
         /// Glob or path pattern
-        pattern: String,
+        #[arg(required_unless_present = "view")]
+        pattern: Option<String>,
         /// Hierarchical tag name (`foo/bar`)
         tag_path: String,
+        /// Select files with a saved view instead of a pattern
+        #[arg(long, conflicts_with = "pattern")]
+        view: Option<String>,
+        /// Create the tag if it does not exist, even with `tags.strict` on
+        #[arg(long, conflicts_with = "no_create")]
+        create: bool,
+        /// Fail instead of creating a tag that does not exist yet
+        #[arg(long)]
+        no_create: bool,
     },
 
+    /// Tag housekeeping
+    #[command(subcommand)]
+    Tags(tags::TagsCmd),
+
+    /// Per-index settings such as `tags.strict`
+    #[command(subcommand)]
+    Config(config::ConfigCmd),
+
     /// Manage custom attributes
     Attr {
         #[command(subcommand)]
@@ -68,17 +193,91 @@ pub enum Commands {
     },
 
     /// Full-text search; `--exec CMD` runs CMD on each hit (`{}` placeholder)
-    Search {
-        query: String,
-        #[arg(long)]
-        exec: Option<String>,
+    Search(search::SearchArgs),
+
+    /// Compute embeddings for `search --semantic`
+    Embed(embed::EmbedArgs),
+
+    /// Suggest tags and attributes with external classifiers
+    Classify(classify::ClassifyArgs),
+
+    /// Recorded history (searches)
+    #[command(subcommand)]
+    History(history::HistoryCmd),
+
+    /// Give files matching a glob a 0-5 star rating
+    Rate {
+        /// Rating from 0 (unrated) to 5
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=5))]
+        rating: u8,
+        /// Glob or path pattern
+        pattern: String,
     },
 
+    /// Mark or list favorite files
+    #[command(subcommand)]
+    Fav(fav::FavCmd),
+
+    /// Colour labels (Finder / KDE tag compatible)
+    #[command(subcommand)]
+    Label(label::LabelCmd),
+
+    /// Freeze the tags, attributes and links of matching files
+    Lock(lock::LockArgs),
+
+    /// Let locked files' metadata change again
+    Unlock(lock::UnlockArgs),
+
     /// Create or manage database backups
     Backup(backup::BackupOpts),
 
-    /// Restore from a backup file (overwrites current DB)
-    Restore { backup_path: std::path::PathBuf },
+    /// Restore from a backup file (overwrites current DB unless `--to`)
+    Restore {
+        backup_path: std::path::PathBuf,
+        /// Restore into a new database file instead of the live DB
+        #[arg(long)]
+        to: Option<std::path::PathBuf>,
+        /// Restore even if the backup's schema is newer than this build
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Capture and diff the tag/attribute/link state
+    #[command(subcommand)]
+    Snapshot(snapshot::SnapshotCmd),
+
+    /// Replicate tags, attributes and links through op-log files
+    #[command(subcommand)]
+    Sync(sync::SyncCmd),
+
+    /// Run a script of Marlin commands, optionally as one transaction
+    Run(run::RunArgs),
+
+    /// Interactive prompt with history, completion and live hit counts
+    Shell(shell::ShellArgs),
+
+    /// Inspect the index database (schema, versions)
+    #[command(subcommand)]
+    Db(db::DbCmd),
+
+    /// Remove orphaned tags, attributes and index rows
+    Gc(gc::GcArgs),
+
+    /// Check schema, SQLite integrity and the full-text index
+    Doctor(doctor::DoctorArgs),
+
+    /// Pre-load index pages to speed up the first search after a reboot
+    Warm(warm::WarmArgs),
+
+    /// Run a raw SQL statement (read-only unless --unsafe-write)
+    Sql(sql::SqlArgs),
+
+    /// Measure performance on a synthetic tree
+    #[command(subcommand)]
+    Bench(bench::BenchCmd),
+
+    /// Create a reproducible demo directory tree
+    Demo(demo::DemoArgs),
 
     /// Generate shell completions (hidden)
     #[command(hide = true)]
@@ -100,11 +299,34 @@ pub enum Commands {
     #[command(subcommand)]
     View(view::ViewCmd),
 
+    /// Saved exec pipelines (a query plus a command)
+    #[command(subcommand)]
+    Action(action::ActionCmd),
+
+    /// Move, copy or link files into a layout derived from their metadata
+    Organize(organize::OrganizeArgs),
+
+    /// Disk usage per directory, straight from the index
+    Du(du::DuArgs),
+
+    /// Files not modified (or accessed) within a time window
+    Stale(stale::StaleArgs),
+
+    /// File counts, sizes and recent growth per tag or directory
+    #[command(subcommand)]
+    Stats(stats::StatsCmd),
+
+    /// Database path, schema version and index totals
+    Status(status::StatusArgs),
+
+    /// Everything the index knows about one file
+    ExplainFile(explain::ExplainArgs),
+
     /// Workflow states on files
     #[command(subcommand)]
     State(state::StateCmd),
 
-    /// TODO/tasks management
+    /// Tasks attached to files
     #[command(subcommand)]
     Task(task::TaskCmd),
 
@@ -116,7 +338,7 @@ pub enum Commands {
     #[command(subcommand)]
     Annotate(annotate::AnnotateCmd),
 
-    /// Version diffs
+    /// Content snapshots of files and diffs between them
     #[command(subcommand)]
     Version(version::VersionCmd),
 
@@ -124,17 +346,257 @@ pub enum Commands {
     #[command(subcommand)]
     Event(event::EventCmd),
 
+    /// Per-root indexing profiles
+    #[command(subcommand)]
+    Root(root::RootCmd),
+
+    /// Scoped tokens for the watcher's control channel
+    #[command(subcommand)]
+    Token(token::TokenCmd),
+
     /// Watch directories for changes
     #[command(subcommand)]
     Watch(watch::WatchCmd),
 }
 
+impl Commands {
+    /// Commands that only read the index.  They skip the pre-command
+    /// auto-backup, which costs a full copy of the database.
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Commands::History(_)
+            | Commands::Stats(_)
+            | Commands::Status(_)
+            | Commands::ExplainFile(_)
+            | Commands::Warm(_)
+            | Commands::Stale(_)
+            | Commands::Prune(prune::PruneArgs { dry_run: true, .. })
+            | Commands::Attr {
+                action: AttrCmd::Ls { .. },
+            }
+            | Commands::View(view::ViewCmd::List | view::ViewCmd::Exec(_))
+            | Commands::Coll(
+                coll::CollCmd::List(_) | coll::CollCmd::Stats(_) | coll::CollCmd::Diff(_),
+            )
+            | Commands::Link(
+                link::LinkCmd::List(_)
+                | link::LinkCmd::Backlinks(_)
+                | link::LinkCmd::Export(_)
+                | link::LinkCmd::Type(link::TypeCmd::List),
+            )
+            | Commands::Snapshot(snapshot::SnapshotCmd::List | snapshot::SnapshotCmd::Diff(_))
+            | Commands::Sync(sync::SyncCmd::Export(_) | sync::SyncCmd::Status)
+            | Commands::Db(
+                db::DbCmd::Schema
+                | db::DbCmd::Aliases { .. }
+                | db::DbCmd::Diff { .. }
+                | db::DbCmd::Identity { mode: None }
+                | db::DbCmd::Tuning(_),
+            )
+            | Commands::Fav(fav::FavCmd::List)
+            | Commands::Lock(lock::LockArgs { list: true, .. })
+            | Commands::Token(token::TokenCmd::List)
+            | Commands::Config(config::ConfigCmd::Get { .. } | config::ConfigCmd::List)
+            | Commands::State(state::StateCmd::Log(_))
+            | Commands::Task(task::TaskCmd::List(_))
+            | Commands::Annotate(annotate::AnnotateCmd::List(_))
+            | Commands::Event(event::EventCmd::List(_) | event::EventCmd::Timeline(_))
+            | Commands::Version(version::VersionCmd::List(_) | version::VersionCmd::Diff(_))
+            | Commands::Remind(
+                remind::RemindCmd::Due(_) | remind::RemindCmd::List(_) | remind::RemindCmd::Test(_),
+            )
+            | Commands::Action(action::ActionCmd::List) => true,
+            Commands::Search(a) => a.exec_capture.is_none(),
+            Commands::Du(a) => !a.refresh,
+            Commands::Doctor(a) => !a.repair,
+            Commands::Sql(a) => !a.unsafe_write,
+            _ => false,
+        }
+    }
+}
+
+/// Where a command's files come from: a glob pattern or a saved view.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Pattern(String),
+    View(String),
+}
+
+impl Selection {
+    /// The selection of a command taking `[PATTERN]` and `--view`, which
+    /// clap already keeps mutually exclusive.
+    pub fn new(pattern: Option<String>, view: Option<String>) -> anyhow::Result<Self> {
+        match (pattern, view) {
+            (Some(pattern), None) => Ok(Selection::Pattern(pattern)),
+            (None, Some(view)) => Ok(Selection::View(view)),
+            _ => anyhow::bail!("give either a PATTERN or --view"),
+        }
+    }
+}
+
+/// Run `cmd_tpl` once per path, filling in its placeholders (`{path}`,
+/// `{stem}`, `{attr:key}`, … – see [`libmarlin::exec`]; a template without
+/// any gets the path appended).  Without hits, such a template runs once
+/// on its own.  Tags and attributes are read through `conn`.  Shared by
+/// `search --exec` and `action run`.
+pub fn run_exec(
+    conn: &rusqlite::Connection,
+    paths: &[String],
+    cmd_tpl: &str,
+) -> anyhow::Result<()> {
+    let tpl = libmarlin::exec::ExecTemplate::parse(cmd_tpl);
+
+    if paths.is_empty() && !tpl.has_placeholders() {
+        if let Some(mut parts) = shlex::split(cmd_tpl) {
+            if !parts.is_empty() {
+                let prog = parts.remove(0);
+                let status = std::process::Command::new(&prog).args(parts).status()?;
+                if !status.success() {
+                    tracing::error!(command=%cmd_tpl, code=?status.code(), "command failed");
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    for p in paths {
+        let final_cmd = tpl.render(conn, p)?;
+        if let Some(mut parts) = shlex::split(&final_cmd) {
+            if parts.is_empty() {
+                continue;
+            }
+            let prog = parts.remove(0);
+            let status = std::process::Command::new(&prog).args(parts).status()?;
+            if !status.success() {
+                tracing::error!(file=%p, command=%final_cmd, code=?status.code(), "command failed");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run `cmd_tpl` (a template as for [`run_exec`]) once per path and store
+/// its trimmed stdout as attribute `key` of that file.  Fails before
+/// running anything if one of the files is locked; a command that fails
+/// or prints nothing leaves its file alone.  Returns how many files got
+/// the attribute.  Used by `search --exec-capture`.
+pub fn run_capture(
+    conn: &rusqlite::Connection,
+    paths: &[String],
+    cmd_tpl: &str,
+    key: &str,
+) -> anyhow::Result<usize> {
+    let tpl = libmarlin::exec::ExecTemplate::parse(cmd_tpl);
+    let files = paths
+        .iter()
+        .map(|p| Ok((libmarlin::db::file_id(conn, p)?, p.clone())))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    libmarlin::lock::check_files(conn, &files)?;
+
+    let mut stored = 0;
+    for (fid, p) in &files {
+        let final_cmd = tpl.render(conn, p)?;
+        let Some(mut parts) = shlex::split(&final_cmd).filter(|parts| !parts.is_empty()) else {
+            continue;
+        };
+        let prog = parts.remove(0);
+        let output = std::process::Command::new(&prog)
+            .args(parts)
+            .stderr(std::process::Stdio::inherit())
+            .output()?;
+        if !output.status.success() {
+            tracing::error!(file=%p, command=%final_cmd, code=?output.status.code(), "command failed");
+            continue;
+        }
+        let value = String::from_utf8_lossy(&output.stdout);
+        let value = value.trim();
+        if value.is_empty() {
+            tracing::warn!(file=%p, command=%final_cmd, "command printed nothing");
+            continue;
+        }
+        libmarlin::db::upsert_attr(conn, *fid, key, value)?;
+        tracing::debug!(file=%p, key, value, "captured");
+        stored += 1;
+    }
+    Ok(stored)
+}
+
+/// The process-wide query result cache.  Every command of one process
+/// talks to the same connection, so searches repeated by `view watch` or
+/// inside a `marlin run` script can share it; `status --verbose` reports
+/// its counters.
+pub fn query_cache() -> std::sync::MutexGuard<'static, libmarlin::query_cache::QueryCache> {
+    static CACHE: std::sync::OnceLock<std::sync::Mutex<libmarlin::query_cache::QueryCache>> =
+        std::sync::OnceLock::new();
+    CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// What `--relative` makes printed paths relative to.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelativeTo {
+    /// The current directory (what `git add` and friends expect)
+    Cwd,
+    /// The workspace root (see `marlin db use`), else the current directory
+    Workspace,
+}
+
+/// How commands print indexed paths: as stored, or relative to a base
+/// directory with `--relative`.  Paths that cannot be made relative are
+/// printed unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct PathStyle {
+    base: Option<std::path::PathBuf>,
+}
+
+impl PathStyle {
+    pub fn new(relative: Option<RelativeTo>) -> anyhow::Result<Self> {
+        let Some(to) = relative else {
+            return Ok(Self::default());
+        };
+        let cwd = std::env::current_dir()?;
+        let cwd = cwd.canonicalize().unwrap_or(cwd);
+        let base = match to {
+            RelativeTo::Cwd => cwd,
+            RelativeTo::Workspace => libmarlin::config::find_workspace(&cwd).unwrap_or(cwd),
+        };
+        Ok(Self { base: Some(base) })
+    }
+
+    pub fn show<'a>(&self, path: &'a str) -> std::borrow::Cow<'a, str> {
+        let rel = self
+            .base
+            .as_deref()
+            .and_then(|b| libmarlin::utils::relative_path(std::path::Path::new(path), b));
+        match rel {
+            Some(r) => r.to_string_lossy().into_owned().into(),
+            None => path.into(),
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum AttrCmd {
+    #[command(
+        override_usage = "marlin attr set [--expires <TTL>] <PATTERN> <KEY> <VALUE>\n       marlin attr set [--expires <TTL>] --view <VIEW> <KEY> <VALUE>"
+    )]
     Set {
-        pattern: String,
-        key: String,
-        value: String,
+        /// Glob or path pattern
+        #[arg(required_unless_present = "view")]
+        pattern: Option<String>,
+        #[arg(required_unless_present = "view")]
+        key: Option<String>,
+        #[arg(required_unless_present = "view")]
+        value: Option<String>,
+        /// Set KEY to VALUE on the files of a saved view instead of a pattern
+        #[arg(long, num_args = 3, value_names = ["VIEW", "KEY", "VALUE"],
+              conflicts_with_all = ["pattern", "key", "value"])]
+        view: Option<Vec<String>>,
+        /// Remove the value again after this long (`30d`, `12h`, `2w`)
+        #[arg(long)]
+        expires: Option<String>,
     },
     Ls {
         path: std::path::PathBuf,
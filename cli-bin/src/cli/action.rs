@@ -0,0 +1,102 @@
+//! `marlin action …` – saved exec pipelines: a query and the command to
+//! run on its hits, stored under a name.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db;
+use libmarlin::query::{self, SearchOptions};
+
+#[derive(Subcommand, Debug)]
+pub enum ActionCmd {
+    /// Save (or update) an action
+    Define(ArgsDefine),
+    /// Run the action's command on every current hit of its query
+    Run { name: String },
+    /// List saved actions
+    List,
+    /// Delete an action
+    Rm { name: String },
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsDefine {
+    pub name: String,
+    /// Search query selecting the files
+    #[arg(long, short)]
+    pub query: String,
+    /// Command to run per hit (`{path}`, `{stem}`, `{attr:key}`, … as for `search --exec`)
+    #[arg(long)]
+    pub exec: String,
+}
+
+pub fn run(cmd: &ActionCmd, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        /* ── action define ────────────────────────────────────────── */
+        ActionCmd::Define(a) => {
+            query::Query::parse(&a.query)?;
+            db::save_action(conn, &a.name, &a.query, &a.exec)?;
+            if matches!(fmt, Format::Text) {
+                writeln!(out, "Saved action '{}': {} → {}", a.name, a.query, a.exec)?;
+            }
+        }
+
+        /* ── action run ───────────────────────────────────────────── */
+        ActionCmd::Run { name } => {
+            let action = db::action(conn, name)?;
+            let paths: Vec<String> =
+                query::search_hits(conn, &action.query, &SearchOptions::default())?
+                    .into_iter()
+                    .map(|h| h.path)
+                    .collect();
+            if paths.is_empty() && matches!(fmt, Format::Text) {
+                eprintln!("(action '{name}' has no matches)");
+            }
+            crate::cli::run_exec(conn, &paths, &action.command)?;
+        }
+
+        /* ── action list ──────────────────────────────────────────── */
+        ActionCmd::List => {
+            let actions = db::list_actions(conn)?;
+            match fmt {
+                Format::Text => {
+                    for a in actions {
+                        writeln!(out, "{}: {} → {}", a.name, a.query, a.command)?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = actions
+                            .iter()
+                            .map(|a| {
+                                serde_json::json!({
+                                    "name": a.name,
+                                    "query": a.query,
+                                    "command": a.command,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+
+        /* ── action rm ────────────────────────────────────────────── */
+        ActionCmd::Rm { name } => {
+            db::remove_action(conn, name)?;
+            if matches!(fmt, Format::Text) {
+                writeln!(out, "Removed action '{name}'")?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,11 +1,18 @@
 // src/cli/annotate.rs
-use crate::cli::Format;
+//! `marlin annotate …` – notes and highlights on files or parts of them.
+
+use std::io::Write;
+
+use crate::cli::{Context, Format};
+use anyhow::bail;
 use clap::{Args, Subcommand};
-use rusqlite::Connection;
+use libmarlin::db::{self, AnnotationRange};
 
 #[derive(Subcommand, Debug)]
 pub enum AnnotateCmd {
+    /// Add a note to a file
     Add(ArgsAdd),
+    /// List the notes on files matching a glob
     List(ArgsList),
 }
 
@@ -13,8 +20,10 @@ pub enum AnnotateCmd {
 pub struct ArgsAdd {
     pub file: String,
     pub note: String,
+    /// Lines (`12`, `12-20`) or bytes (`bytes=100-200`) the note is about
     #[arg(long)]
-    pub range: Option<String>,
+    pub range: Option<AnnotationRange>,
+    /// Mark the note as a highlight
     #[arg(long)]
     pub highlight: bool,
 }
@@ -24,9 +33,80 @@ pub struct ArgsList {
     pub file_pattern: String,
 }
 
-pub fn run(cmd: &AnnotateCmd, _conn: &mut Connection, _format: Format) -> anyhow::Result<()> {
+pub fn run(cmd: &AnnotateCmd, ctx: &mut Context) -> anyhow::Result<()> {
     match cmd {
-        AnnotateCmd::Add(a) => todo!("annotate add {:?}", a),
-        AnnotateCmd::List(a) => todo!("annotate list {:?}", a),
+        AnnotateCmd::Add(a) => {
+            let id = ctx
+                .marlin
+                .annotate(&a.file, &a.note, a.range, a.highlight)?;
+            match ctx.format {
+                Format::Text => writeln!(ctx.output, "Added annotation #{id} to {}", a.file)?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(ctx.output, "{}", serde_json::json!({ "added": id }))?;
+                    }
+                }
+            }
+        }
+        AnnotateCmd::List(a) => {
+            let fmt = ctx.format;
+            let time = ctx.time.clone();
+            let (conn, out) = ctx.split();
+            let files = db::match_files(&conn, &a.file_pattern)?;
+            if files.is_empty() {
+                bail!("no indexed file matches `{}`", a.file_pattern);
+            }
+            let mut found = Vec::new();
+            for (fid, path) in files {
+                found.extend(
+                    db::annotations(&conn, fid)?
+                        .into_iter()
+                        .map(|n| (path.clone(), n)),
+                );
+            }
+            match fmt {
+                Format::Text => {
+                    for (path, n) in &found {
+                        let mark = if n.highlight { '*' } else { ' ' };
+                        let range = n.range.map(|r| r.to_string()).unwrap_or_default();
+                        writeln!(
+                            out,
+                            "#{:<4} {mark} {path}  {range:<13} {}  ({})",
+                            n.id,
+                            n.note,
+                            time.epoch(n.created_at).unwrap_or_default()
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = found
+                            .iter()
+                            .map(|(path, n)| {
+                                serde_json::json!({
+                                    "id": n.id,
+                                    "path": path,
+                                    "note": n.note,
+                                    "range": n.range.map(|r| {
+                                        let (start, end) = r.bounds();
+                                        serde_json::json!({
+                                            "unit": r.unit(),
+                                            "start": start,
+                                            "end": end,
+                                        })
+                                    }),
+                                    "highlight": n.highlight,
+                                    "created_at": n.created_at,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
     }
+    Ok(())
 }
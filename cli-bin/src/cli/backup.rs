@@ -1,15 +1,16 @@
 // src/cli/backup.rs
-use crate::cli::Format;
+use crate::cli::{self, Format};
 use anyhow::{Context, Result};
-use clap::Args;
-use libmarlin::backup::BackupManager;
-use rusqlite::Connection;
+use chrono::Utc;
+use clap::{Args, Subcommand};
+use libmarlin::backup::{BackupManager, VerifyReport};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 /// Options for the `backup` command
 #[derive(Args, Debug)]
 pub struct BackupOpts {
-    /// Directory to store backups (defaults next to DB)
+    /// Directory to store backups (defaults to the configured backups dir)
     #[arg(long)]
     pub dir: Option<PathBuf>,
 
@@ -24,44 +25,140 @@ pub struct BackupOpts {
     /// Backup file to verify (used with --verify)
     #[arg(long)]
     pub file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub action: Option<BackupAction>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupAction {
+    /// Integrity-check one backup or all of them
+    Verify(VerifyArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Backup file name (or path) to check
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    pub file: Option<PathBuf>,
+    /// Check every backup and print a summary table
+    #[arg(long)]
+    pub all: bool,
+    /// Concurrent checks with --all (defaults to the CPU count)
+    #[arg(long)]
+    pub jobs: Option<usize>,
 }
 
-pub fn run(opts: &BackupOpts, db_path: &Path, _conn: &mut Connection, _fmt: Format) -> Result<()> {
-    let backups_dir = opts
-        .dir
-        .clone()
-        .unwrap_or_else(|| db_path.parent().unwrap().join("backups"));
-    let manager = BackupManager::new(db_path, &backups_dir)?;
+pub fn run(opts: &BackupOpts, ctx: &mut cli::Context) -> Result<()> {
+    let (cfg, fmt, out) = (&ctx.cfg, ctx.format, &mut ctx.output);
+    let backups_dir = opts.dir.as_ref().unwrap_or(&cfg.backups_dir);
+    let manager = BackupManager::new(&cfg.db_path, backups_dir)?;
+
+    if let Some(BackupAction::Verify(v)) = &opts.action {
+        if v.all {
+            let reports = match v.jobs {
+                Some(j) => manager.verify_all_with(j)?,
+                None => manager.verify_all()?,
+            };
+            print_verify_table(out, &reports, fmt)?;
+            let bad = reports.iter().filter(|r| !r.ok).count();
+            if bad > 0 {
+                anyhow::bail!("{bad} of {} backup(s) failed verification", reports.len());
+            }
+            return Ok(());
+        }
+        let file = v.file.as_ref().context("backup file required")?;
+        return verify_one(out, &manager, file);
+    }
 
     if opts.verify {
         let file = opts
             .file
             .as_ref()
             .context("--file required with --verify")?;
-        let name = file
-            .file_name()
-            .and_then(|n| n.to_str())
-            .context("invalid backup file name")?;
-        let ok = manager.verify_backup(name)?;
-        if ok {
-            println!("Backup OK: {}", name);
-        } else {
-            println!("Backup corrupted: {}", name);
-        }
-        return Ok(());
+        return verify_one(out, &manager, file);
     }
 
     if let Some(n) = opts.prune {
         let result = manager.prune(n)?;
-        println!(
+        writeln!(
+            out,
             "Pruned {} old backups, kept {}",
             result.removed.len(),
             result.kept.len()
-        );
+        )?;
         return Ok(());
     }
 
     let info = manager.create_backup()?;
-    println!("Created backup {}", info.id);
+    writeln!(out, "Created backup {}", info.id)?;
     Ok(())
 }
+
+fn verify_one(out: &mut impl Write, manager: &BackupManager, file: &Path) -> Result<()> {
+    let name = file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("invalid backup file name")?;
+    let ok = manager.verify_backup(name)?;
+    if ok {
+        writeln!(out, "Backup OK: {}", name)?;
+    } else {
+        writeln!(out, "Backup corrupted: {}", name)?;
+    }
+    Ok(())
+}
+
+fn print_verify_table(out: &mut impl Write, reports: &[VerifyReport], fmt: Format) -> Result<()> {
+    match fmt {
+        Format::Text => {
+            writeln!(out, "{:<9} {:>10} {:>8}  BACKUP", "STATUS", "SIZE", "AGE")?;
+            for r in reports {
+                let status = if r.ok { "OK" } else { "CORRUPTED" };
+                writeln!(
+                    out,
+                    "{:<9} {:>10} {:>8}  {}",
+                    status,
+                    r.info.size_bytes,
+                    format_age(Utc::now() - r.info.timestamp),
+                    r.info.id
+                )?;
+                if let Some(e) = &r.error {
+                    writeln!(out, "          ↳ {e}")?;
+                }
+            }
+            let ok = reports.iter().filter(|r| r.ok).count();
+            writeln!(out, "{ok} OK, {} corrupted", reports.len() - ok)?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = reports
+                    .iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "id": r.info.id,
+                            "ok": r.ok,
+                            "size_bytes": r.info.size_bytes,
+                            "timestamp": r.info.timestamp.to_rfc3339(),
+                            "error": r.error,
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compact age such as `45s`, `12m`, `5h`, `3d`.
+fn format_age(age: chrono::Duration) -> String {
+    let secs = age.num_seconds().max(0);
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3_599 => format!("{}m", secs / 60),
+        3_600..=86_399 => format!("{}h", secs / 3_600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
@@ -0,0 +1,123 @@
+//! `marlin bench selftest` – a scaled-down version of the `cargo bench`
+//! suites, run on the user's machine to help tune root profiles and the
+//! watcher.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::{Args, Subcommand};
+
+use crate::cli::Format;
+use libmarlin::bench::{self, BenchResult};
+
+#[derive(Subcommand, Debug)]
+pub enum BenchCmd {
+    /// Time scan, search, tagging and debouncing on a synthetic tree
+    Selftest(SelftestArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct SelftestArgs {
+    /// Number of synthetic files
+    #[arg(long, default_value_t = 2000)]
+    pub files: usize,
+    /// Where to build the tree (defaults to the system temp dir); point it
+    /// at the disk you index to measure that disk
+    #[arg(long)]
+    pub dir: Option<PathBuf>,
+}
+
+/// Scan rates below this (files/s) suggest slow storage.
+const SLOW_SCAN: f64 = 2_000.0;
+/// Average query times above this (ms) suggest an oversized index.
+const SLOW_QUERY_MS: f64 = 20.0;
+
+pub fn run(cmd: &BenchCmd, fmt: Format) -> Result<()> {
+    let BenchCmd::Selftest(a) = cmd;
+    if a.files == 0 {
+        bail!("--files must be at least 1");
+    }
+    let work = a
+        .dir
+        .clone()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("marlin-bench-{}", std::process::id()));
+    let results = bench::selftest(&work, a.files);
+    let _ = std::fs::remove_dir_all(&work);
+    let results = results?;
+
+    match fmt {
+        Format::Text => {
+            println!(
+                "{:<10} {:>8} {:>10} {:>12} {:>12}",
+                "workload", "items", "total", "per item", "items/s"
+            );
+            for r in &results {
+                println!(
+                    "{:<10} {:>8} {:>10} {:>12} {:>12.0}",
+                    r.name,
+                    r.items,
+                    format!("{:.1?}", r.elapsed),
+                    format!("{:.1?}", r.per_item()),
+                    r.throughput()
+                );
+            }
+            for hint in hints(&results) {
+                println!("hint: {hint}");
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let out: Vec<_> = results
+                    .iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "name": r.name,
+                            "items": r.items,
+                            "elapsed_ms": r.elapsed.as_secs_f64() * 1e3,
+                            "per_item_us": r.per_item().as_secs_f64() * 1e6,
+                            "throughput": r.throughput(),
+                        })
+                    })
+                    .collect();
+                println!(
+                    "{}",
+                    serde_json::json!({ "results": out, "hints": hints(&results) })
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Tuning suggestions derived from the measurements.
+fn hints(results: &[BenchResult]) -> Vec<String> {
+    let find = |name: &str| results.iter().find(|r| r.name == name);
+    let mut out = Vec::new();
+    if let Some(scan) = find("scan").filter(|r| r.throughput() < SLOW_SCAN) {
+        out.push(format!(
+            "scanning runs at {:.0} files/s; skip build/cache folders with \
+             `marlin root add <dir> --ignore <glob>` and prefer `marlin scan --dirty`",
+            scan.throughput()
+        ));
+    }
+    if let Some(q) = find("fts query") {
+        let ms = q.per_item().as_secs_f64() * 1e3;
+        if ms > SLOW_QUERY_MS {
+            out.push(format!(
+                "queries average {ms:.1} ms; narrow searches with tag:/attr: filters"
+            ));
+        }
+    }
+    if let Some(d) = find("debounce") {
+        // a window shorter than a full burst only splits it into more passes
+        let burst_ms = d.elapsed.as_millis().max(1);
+        out.push(format!(
+            "a burst of {} watcher events takes {burst_ms} ms to coalesce; \
+             keep `--debounce-ms` above that",
+            d.items
+        ));
+    }
+    out
+}
@@ -0,0 +1,80 @@
+//! `marlin classify` – run the configured classifiers and apply their
+//! tag/attribute suggestions below `auto/`.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::cli::{Context, Format};
+use libmarlin::classify::ClassifyOptions;
+
+#[derive(Args, Debug)]
+pub struct ClassifyArgs {
+    /// Glob of indexed files to classify (all files by default)
+    #[arg(default_value = "*")]
+    pub pattern: String,
+    /// Classify files again even if they have not changed
+    #[arg(long)]
+    pub force: bool,
+    /// Print the suggestions without applying them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub fn run(a: &ClassifyArgs, ctx: &mut Context) -> Result<()> {
+    let opts = ClassifyOptions {
+        force: a.force,
+        dry_run: a.dry_run,
+    };
+    let report = ctx.marlin.classify(&a.pattern, opts)?;
+    let out = &mut ctx.output;
+    match ctx.format {
+        Format::Text => {
+            for (path, suggestions) in &report.classified {
+                writeln!(out, "{path}")?;
+                for s in suggestions {
+                    writeln!(out, "  {s}")?;
+                }
+            }
+            let verb = if a.dry_run {
+                "Would classify"
+            } else {
+                "Classified"
+            };
+            writeln!(
+                out,
+                "{verb} {} file(s) ({} up to date, {} without a classifier, {} locked, {} failed)",
+                report.classified.len(),
+                report.up_to_date,
+                report.unconfigured,
+                report.locked,
+                report.failed
+            )?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let files: Vec<_> = report
+                    .classified
+                    .iter()
+                    .map(|(path, suggestions)| {
+                        serde_json::json!({
+                            "path": path,
+                            "suggestions": suggestions.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                        })
+                    })
+                    .collect();
+                let v = serde_json::json!({
+                    "classified": files,
+                    "up_to_date": report.up_to_date,
+                    "unconfigured": report.unconfigured,
+                    "locked": report.locked,
+                    "failed": report.failed,
+                });
+                writeln!(out, "{v}")?;
+            }
+        }
+    }
+    Ok(())
+}
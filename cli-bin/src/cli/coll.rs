@@ -1,10 +1,12 @@
 //! `marlin coll …` – named collections of files (simple “playlists”).
 
+use std::io::Write;
+
 use clap::{Args, Subcommand};
 use rusqlite::Connection;
 
-use crate::cli::Format; // local enum for text / json output
-use libmarlin::db; // core DB helpers from the library crate
+use crate::cli::{Context, Format, PathStyle, RelativeTo}; // output selectors stay local
+use libmarlin::{db, query}; // core DB helpers from the library crate
 
 #[derive(Subcommand, Debug)]
 pub enum CollCmd {
@@ -14,6 +16,10 @@ pub enum CollCmd {
     Add(AddArgs),
     /// List files inside a collection
     List(ListArgs),
+    /// File count, total size and tag distribution of a collection
+    Stats(StatsArgs),
+    /// Files only in A, only in B, and in both
+    Diff(DiffArgs),
 }
 
 #[derive(Args, Debug)]
@@ -24,12 +30,37 @@ pub struct CreateArgs {
 #[derive(Args, Debug)]
 pub struct AddArgs {
     pub name: String,
-    pub file_pattern: String,
+    #[arg(required_unless_present = "view")]
+    pub file_pattern: Option<String>,
+    /// Add the files matched by a saved view instead of a pattern
+    #[arg(long, conflicts_with = "file_pattern")]
+    pub view: Option<String>,
 }
 
 #[derive(Args, Debug)]
 pub struct ListArgs {
     pub name: String,
+    /// Print paths relative to the current directory, or with
+    /// `=workspace` to the workspace root
+    #[arg(long, value_enum, value_name = "BASE", num_args = 0..=1,
+          require_equals = true, default_missing_value = "cwd")]
+    pub relative: Option<RelativeTo>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    pub name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    pub a: String,
+    pub b: String,
+    /// Print paths relative to the current directory, or with
+    /// `=workspace` to the workspace root
+    #[arg(long, value_enum, value_name = "BASE", num_args = 0..=1,
+          require_equals = true, default_missing_value = "cwd")]
+    pub relative: Option<RelativeTo>,
 }
 
 /// Look-up an existing collection **without** implicitly creating it.
@@ -42,13 +73,16 @@ fn lookup_collection_id(conn: &Connection, name: &str) -> anyhow::Result<i64> {
     .map_err(|_| anyhow::anyhow!("collection not found: {}", name))
 }
 
-pub fn run(cmd: &CollCmd, conn: &mut Connection, fmt: Format) -> anyhow::Result<()> {
+pub fn run(cmd: &CollCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
     match cmd {
         /* ── coll create ──────────────────────────────────────────── */
         CollCmd::Create(a) => {
             db::ensure_collection(conn, &a.name)?;
             if matches!(fmt, Format::Text) {
-                println!("Created collection '{}'", a.name);
+                writeln!(out, "Created collection '{}'", a.name)?;
             }
         }
 
@@ -57,22 +91,37 @@ pub fn run(cmd: &CollCmd, conn: &mut Connection, fmt: Format) -> anyhow::Result<
             // Fail if the target collection does not yet exist
             let coll_id = lookup_collection_id(conn, &a.name)?;
 
-            let like = a.file_pattern.replace('*', "%");
-            let mut stmt = conn.prepare("SELECT id FROM files WHERE path LIKE ?1")?;
-            let ids: Vec<i64> = stmt
-                .query_map([&like], |r| r.get::<_, i64>(0))?
-                .collect::<Result<_, _>>()?;
+            let ids: Vec<i64> = match (&a.view, &a.file_pattern) {
+                (Some(view), _) => query::view_files(conn, view)?
+                    .into_iter()
+                    .map(|(id, _)| id)
+                    .collect(),
+                (None, Some(pattern)) => {
+                    let like = pattern.replace('*', "%");
+                    let mut stmt = conn.prepare("SELECT id FROM files WHERE path LIKE ?1")?;
+                    let ids = stmt
+                        .query_map([&like], |r| r.get::<_, i64>(0))?
+                        .collect::<Result<_, _>>()?;
+                    ids
+                }
+                (None, None) => anyhow::bail!("give either a FILE_PATTERN or --view"),
+            };
 
             for fid in &ids {
                 db::add_file_to_collection(conn, coll_id, *fid)?;
             }
 
             match fmt {
-                Format::Text => println!("Added {} file(s) → '{}'", ids.len(), a.name),
+                Format::Text => writeln!(out, "Added {} file(s) → '{}'", ids.len(), a.name)?,
                 Format::Json => {
                     #[cfg(feature = "json")]
                     {
-                        println!("{{\"collection\":\"{}\",\"added\":{}}}", a.name, ids.len());
+                        writeln!(
+                            out,
+                            "{{\"collection\":\"{}\",\"added\":{}}}",
+                            a.name,
+                            ids.len()
+                        )?;
                     }
                 }
             }
@@ -80,17 +129,101 @@ pub fn run(cmd: &CollCmd, conn: &mut Connection, fmt: Format) -> anyhow::Result<
 
         /* ── coll list ────────────────────────────────────────────── */
         CollCmd::List(a) => {
-            let files = db::list_collection(conn, &a.name)?;
+            let style = PathStyle::new(a.relative)?;
+            let files: Vec<String> = db::list_collection(conn, &a.name)?
+                .iter()
+                .map(|f| style.show(f).into_owned())
+                .collect();
             match fmt {
                 Format::Text => {
                     for f in files {
-                        println!("{f}");
+                        writeln!(out, "{f}")?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::to_string(&files)?)?;
+                    }
+                }
+            }
+        }
+
+        /* ── coll stats ───────────────────────────────────────────── */
+        CollCmd::Stats(a) => {
+            let st = db::collection_stats(conn, &a.name)?;
+            match fmt {
+                Format::Text => {
+                    writeln!(out, "Collection '{}'", a.name)?;
+                    writeln!(out, "  files:      {}", st.files)?;
+                    writeln!(out, "  total size: {} bytes", st.total_size)?;
+                    if !st.tags.is_empty() {
+                        writeln!(out, "  tags:")?;
+                        for (tag, n) in &st.tags {
+                            writeln!(out, "    {n:>5}  {tag}")?;
+                        }
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let tags: serde_json::Map<String, serde_json::Value> = st
+                            .tags
+                            .iter()
+                            .map(|(t, n)| (t.clone(), (*n).into()))
+                            .collect();
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({
+                                "collection": a.name,
+                                "files": st.files,
+                                "total_size": st.total_size,
+                                "tags": tags,
+                            })
+                        )?;
+                    }
+                }
+            }
+        }
+
+        /* ── coll diff ────────────────────────────────────────────── */
+        CollCmd::Diff(a) => {
+            let style = PathStyle::new(a.relative)?;
+            let mut d = db::collection_diff(conn, &a.a, &a.b)?;
+            for f in d
+                .only_a
+                .iter_mut()
+                .chain(&mut d.only_b)
+                .chain(&mut d.shared)
+            {
+                *f = style.show(f).into_owned();
+            }
+            match fmt {
+                Format::Text => {
+                    for (label, files) in [
+                        (format!("only in '{}'", a.a), &d.only_a),
+                        (format!("only in '{}'", a.b), &d.only_b),
+                        ("in both".to_string(), &d.shared),
+                    ] {
+                        writeln!(out, "{label} ({}):", files.len())?;
+                        for f in files {
+                            writeln!(out, "  {f}")?;
+                        }
                     }
                 }
                 Format::Json => {
                     #[cfg(feature = "json")]
                     {
-                        println!("{}", serde_json::to_string(&files)?);
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({
+                                "only_a": d.only_a,
+                                "only_b": d.only_b,
+                                "shared": d.shared,
+                            })
+                        )?;
                     }
                 }
             }
@@ -0,0 +1,136 @@
+//! `marlin config …` – settings stored in the index itself, so every
+//! front-end working on the same database honours them.
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::utils::parse_duration;
+use libmarlin::watcher::{BurstConfig, WatcherConfig};
+use libmarlin::{classify, db, notify, timefmt, validate};
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCmd {
+    /// Print one setting
+    Get { key: String },
+    /// Change a setting (`tags.strict true`, `validate.attr.<key> <regex>`,
+    /// `classify.<mime> <command>`)
+    Set { key: String, value: String },
+    /// Remove a setting, restoring its default
+    Unset { key: String },
+    /// Print every stored setting
+    List,
+}
+
+/// Normalise `value` for `key`, rejecting keys `config set` does not own.
+fn parse_value(key: &str, value: &str) -> Result<String> {
+    match key {
+        "tags.strict" | notify::DESKTOP_KEY | WatcherConfig::KEEP_METADATA_KEY => {
+            match value.to_ascii_lowercase().as_str() {
+                "true" | "on" | "yes" | "1" => Ok("true".into()),
+                "false" | "off" | "no" | "0" => Ok("false".into()),
+                _ => bail!("`{key}` expects true or false, got `{value}`"),
+            }
+        }
+        notify::COMMAND_KEY => {
+            notify::parse_command(value)?;
+            Ok(value.into())
+        }
+        "db.tuning" => Ok(value.parse::<db::Tuning>()?.as_str().into()),
+        "watch.burst_threshold" => match value.parse::<usize>() {
+            Ok(n) => Ok(n.to_string()),
+            Err(_) => bail!("`{key}` expects a number of files (0 turns the guard off)"),
+        },
+        "watch.burst_window" => {
+            parse_duration(value)?;
+            Ok(value.into())
+        }
+        timefmt::ZONE_KEY => Ok(value.parse::<timefmt::Zone>()?.to_string()),
+        timefmt::FORMAT_KEY => {
+            timefmt::check_format(value)?;
+            Ok(value.into())
+        }
+        "identity" => bail!("use `marlin db identity` to change identity semantics"),
+        k if is_rule(k) => {
+            validate::compile(value)?;
+            Ok(value.into())
+        }
+        k if classify::is_setting(k) => match shlex::split(value) {
+            Some(argv) if !argv.is_empty() => Ok(value.into()),
+            _ => bail!("`{key}` expects a command line, got `{value}`"),
+        },
+        _ => bail!("unknown setting `{key}`"),
+    }
+}
+
+/// `validate.attr.<key>` or `validate.tag.<namespace>` with a non-empty name.
+fn is_rule(key: &str) -> bool {
+    [validate::ATTR_PREFIX, validate::TAG_PREFIX]
+        .iter()
+        .any(|p| key.strip_prefix(p).is_some_and(|name| !name.is_empty()))
+}
+
+pub fn run(cmd: &ConfigCmd, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let rows = match cmd {
+        ConfigCmd::Get { key } => match db::setting(conn, key)? {
+            Some(v) => vec![(key.clone(), v)],
+            None if key == "tags.strict" || key == notify::DESKTOP_KEY => {
+                vec![(key.clone(), "false".into())]
+            }
+            None if key == "db.tuning" => vec![(key.clone(), db::Tuning::default().to_string())],
+            None if key == timefmt::ZONE_KEY => {
+                vec![(key.clone(), timefmt::Zone::default().to_string())]
+            }
+            None if key == timefmt::FORMAT_KEY => {
+                vec![(key.clone(), timefmt::DEFAULT_FORMAT.into())]
+            }
+            None if key == WatcherConfig::KEEP_METADATA_KEY => vec![(key.clone(), "true".into())],
+            None if key == BurstConfig::THRESHOLD_KEY => {
+                vec![(key.clone(), BurstConfig::default().threshold.to_string())]
+            }
+            None if key == BurstConfig::WINDOW_KEY => vec![(
+                key.clone(),
+                format!("{}s", BurstConfig::default().window.as_secs()),
+            )],
+            None => bail!("setting `{key}` is not set"),
+        },
+        ConfigCmd::Set { key, value } => {
+            let value = parse_value(key, value)?;
+            db::set_setting(conn, key, &value)?;
+            vec![(key.clone(), value)]
+        }
+        ConfigCmd::Unset { key } => {
+            if !db::unset_setting(conn, key)? {
+                bail!("setting `{key}` is not set");
+            }
+            if matches!(fmt, Format::Text) {
+                writeln!(out, "Unset {key}")?;
+            }
+            return Ok(());
+        }
+        ConfigCmd::List => db::settings(conn)?,
+    };
+
+    match fmt {
+        Format::Text => {
+            for (k, v) in rows {
+                writeln!(out, "{k} = {v}")?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let map: serde_json::Map<String, serde_json::Value> =
+                    rows.into_iter().map(|(k, v)| (k, v.into())).collect();
+                writeln!(out, "{}", serde_json::Value::Object(map))?;
+            }
+        }
+    }
+    Ok(())
+}
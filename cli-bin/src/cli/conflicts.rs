@@ -0,0 +1,88 @@
+//! `--strategy` / `--interactive` of `sync import` and `link import`.
+
+use std::io::{BufRead, Write};
+
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+
+use libmarlin::conflicts::{self, Conflict, OnConflict, Resolution};
+
+#[derive(Args, Debug, Default)]
+pub struct ConflictArgs {
+    /// Settle values and link types that disagree with the index the
+    /// same way every time (recorded in `import_conflicts`)
+    #[arg(long, value_enum)]
+    pub strategy: Option<Strategy>,
+    /// Ask how to settle each conflict: keep mine, theirs, both or edit
+    #[arg(long, short, conflicts_with = "strategy")]
+    pub interactive: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Keep the value (or link types) in this index
+    Mine,
+    /// Take the incoming value (or link type)
+    Theirs,
+    /// Keep ours; store theirs as `<key>.theirs` or as another link
+    Both,
+}
+
+impl From<Strategy> for conflicts::Strategy {
+    fn from(s: Strategy) -> Self {
+        match s {
+            Strategy::Mine => conflicts::Strategy::Mine,
+            Strategy::Theirs => conflicts::Strategy::Theirs,
+            Strategy::Both => conflicts::Strategy::Both,
+        }
+    }
+}
+
+impl ConflictArgs {
+    pub fn on_conflict(&self) -> OnConflict<'static> {
+        match (self.strategy, self.interactive) {
+            (Some(s), _) => OnConflict::Strategy(s.into()),
+            (None, true) => OnConflict::Ask(Box::new(|c| {
+                ask(c, &mut std::io::stdin().lock(), &mut std::io::stderr())
+            })),
+            (None, false) => OnConflict::Default,
+        }
+    }
+}
+
+/// Prompt on `out` until `input` answers m(ine), t(heirs), b(oth) or
+/// e(dit) – the latter followed by the value to use.
+pub fn ask(
+    conflict: &Conflict,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<Resolution> {
+    let mut line = String::new();
+    loop {
+        write!(
+            out,
+            "{} {} `{}`: mine `{}`, theirs `{}` – keep [m]ine, [t]heirs, [b]oth or [e]dit? ",
+            conflict.path, conflict.kind, conflict.name, conflict.mine, conflict.theirs
+        )?;
+        out.flush()?;
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            bail!("no answer for the conflict on {}", conflict.path);
+        }
+        match line.trim() {
+            "m" | "mine" => return Ok(Resolution::Mine),
+            "t" | "theirs" => return Ok(Resolution::Theirs),
+            "b" | "both" => return Ok(Resolution::Both),
+            "e" | "edit" => {
+                write!(out, "value: ")?;
+                out.flush()?;
+                line.clear();
+                if input.read_line(&mut line)? == 0 {
+                    bail!("no value for the conflict on {}", conflict.path);
+                }
+                return Ok(Resolution::Edit(line.trim().to_string()));
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! What every subcommand handler gets: the open index, the resolved
+//! config, the output format, how to show times and where to print.
+//!
+//! Handlers take `&mut Context` instead of a bare connection, so they are
+//! written against the [`Marlin`] handle and can be driven from places
+//! other than `main` – a `marlin run` script, or a test that wants the
+//! output back as a string ([`Output::buffer`]).
+
+use std::io::{self, Write};
+
+use anyhow::Result;
+use libmarlin::config::Config;
+use libmarlin::db::ConnGuard;
+use libmarlin::timefmt::TimeFormatter;
+use libmarlin::Marlin;
+use tracing::warn;
+
+use super::Format;
+
+/// Shared state of one CLI invocation.
+pub struct Context {
+    pub marlin: Marlin,
+    pub cfg: Config,
+    pub format: Format,
+    /// Formats every timestamp a command prints (`timezone`,
+    /// `time_format`, `--utc`).
+    pub time: TimeFormatter,
+    pub output: Output,
+}
+
+impl Context {
+    /// Open the index `cfg` points at; output goes to stdout.
+    pub fn open(cfg: Config, format: Format) -> Result<Self> {
+        let marlin = Marlin::open_with(cfg.clone())?;
+        Ok(Self::new(marlin, format))
+    }
+
+    /// Wrap an already open handle, taking the config and the time
+    /// settings from it.
+    pub fn new(marlin: Marlin, format: Format) -> Self {
+        let time = TimeFormatter::load(&marlin.conn()).unwrap_or_else(|e| {
+            warn!("ignoring time settings: {e:#}");
+            TimeFormatter::default()
+        });
+        Self {
+            cfg: marlin.config().clone(),
+            marlin,
+            format,
+            time,
+            output: Output::stdout(),
+        }
+    }
+
+    /// The connection and the output at once, for handlers that read the
+    /// index while printing.
+    pub fn split(&mut self) -> (ConnGuard<'_>, &mut Output) {
+        (self.marlin.conn(), &mut self.output)
+    }
+
+    /// Print into a buffer instead of stdout (see [`Output::take`]).
+    pub fn buffered(mut self) -> Self {
+        self.output = Output::buffer();
+        self
+    }
+}
+
+/// Where a command's results go.  Diagnostics ("No matches …") still go
+/// to stderr; only what a script would consume comes through here.
+pub struct Output(Sink);
+
+enum Sink {
+    Stdout(io::Stdout),
+    Buffer(Vec<u8>),
+}
+
+impl Output {
+    pub fn stdout() -> Self {
+        Self(Sink::Stdout(io::stdout()))
+    }
+
+    pub fn buffer() -> Self {
+        Self(Sink::Buffer(Vec::new()))
+    }
+
+    /// Everything printed into a buffer so far, which is emptied; always
+    /// empty for stdout.
+    pub fn take(&mut self) -> String {
+        match &mut self.0 {
+            Sink::Stdout(_) => String::new(),
+            Sink::Buffer(buf) => String::from_utf8_lossy(&std::mem::take(buf)).into_owned(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match &mut self.0 {
+            Sink::Stdout(out) => out.write(data),
+            Sink::Buffer(buf) => buf.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.0 {
+            Sink::Stdout(out) => out.flush(),
+            Sink::Buffer(_) => Ok(()),
+        }
+    }
+}
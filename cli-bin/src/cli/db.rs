@@ -0,0 +1,414 @@
+//! `marlin db …` – inspect the index database itself.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Subcommand, ValueEnum};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::backup::BackupManager;
+use libmarlin::config::{self, Config, DbScope};
+use libmarlin::db::{self, schema_info};
+
+#[derive(Subcommand, Debug)]
+pub enum DbCmd {
+    /// Schema version, applied migrations, tables and columns
+    Schema,
+    /// Show or set whether tags and attributes follow paths or contents
+    Identity {
+        /// New semantics; omit to print the current one
+        #[arg(value_enum)]
+        mode: Option<Identity>,
+    },
+    /// Other indexed paths with the same content as FILE
+    Aliases { file: PathBuf },
+    /// Compare the index with a backup: schema, row counts and, with
+    /// `--query`, the metadata of the matching files
+    Diff {
+        /// Backup file name in the backups directory, or a path
+        backup: String,
+        /// Also list tag, attribute and link changes of the files this
+        /// search matches (in either database)
+        #[arg(long)]
+        query: Option<String>,
+    },
+    /// SQLite tuning preset (`db.tuning`) and the values in effect
+    #[command(subcommand)]
+    Tuning(TuningCmd),
+    /// Show or choose the index this workspace uses (stored in
+    /// `.marlin/config.toml`)
+    Use {
+        /// `workspace` keeps the index in `.marlin/`, `global` uses one
+        /// per-user index; omit to print the current choice
+        #[arg(value_enum)]
+        scope: Option<Scope>,
+        /// Workspace root (default: the nearest directory with a
+        /// `.marlin/config.toml`, else the current directory)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TuningCmd {
+    /// Print the configured preset and the live pragma values
+    Show,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Identity {
+    /// Metadata belongs to the path (default)
+    Path,
+    /// Copies and hard links share tags and attributes
+    Content,
+}
+
+impl From<Identity> for db::Identity {
+    fn from(i: Identity) -> Self {
+        match i {
+            Identity::Path => db::Identity::Path,
+            Identity::Content => db::Identity::Content,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// `<workspace>/.marlin/index.db`, shared with the project
+    Workspace,
+    /// One index per user in the data directory
+    Global,
+}
+
+impl From<Scope> for DbScope {
+    fn from(s: Scope) -> Self {
+        match s {
+            Scope::Workspace => DbScope::Workspace,
+            Scope::Global => DbScope::Global,
+        }
+    }
+}
+
+/// `marlin db use` – runs before any index is opened, since it decides
+/// which one that is.
+pub fn run_use(scope: Option<Scope>, dir: Option<&PathBuf>, fmt: Format) -> Result<()> {
+    let out = &mut std::io::stdout();
+    let cwd = std::env::current_dir()?;
+    let root = match dir {
+        Some(d) => d.clone(),
+        None => config::find_workspace(&cwd).unwrap_or(cwd),
+    };
+    let before = Config::load_in(&root)?.db_path;
+    if let Some(scope) = scope {
+        config::set_workspace_scope(&root, scope.into())?;
+    }
+    let current = config::workspace_scope(&root)?;
+    let db_path = Config::load_in(&root)?.db_path;
+
+    match fmt {
+        Format::Text => {
+            match current {
+                Some(s) => writeln!(out, "db: {s} ({})", db_path.display())?,
+                None => writeln!(out, "db: default ({})", db_path.display())?,
+            }
+            if scope.is_some() && before != db_path && before.exists() {
+                writeln!(
+                    out,
+                    "note: the previous index stays at {}; copy it to {} before \
+                     the next marlin command to keep its data",
+                    before.display(),
+                    db_path.display()
+                )?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "scope": current.map(DbScope::as_str),
+                        "workspace": root,
+                        "db_path": db_path,
+                    })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run(cmd: &DbCmd, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let cfg = ctx.cfg.clone();
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        /* ── db schema ────────────────────────────────────────────── */
+        DbCmd::Schema => {
+            let info = schema_info(conn)?;
+            match fmt {
+                Format::Text => {
+                    writeln!(
+                        out,
+                        "Schema version {} (this build expects {}): {}",
+                        info.current_version,
+                        info.expected_version,
+                        if info.is_compatible() {
+                            "compatible"
+                        } else {
+                            "INCOMPATIBLE"
+                        }
+                    )?;
+                    writeln!(out, "\nMigrations:")?;
+                    for m in &info.migrations {
+                        writeln!(
+                            out,
+                            "  {:>4}  {:<25}  {}",
+                            m.version,
+                            m.applied_on.as_deref().unwrap_or("-"),
+                            m.name.as_deref().unwrap_or("(unknown to this build)")
+                        )?;
+                    }
+                    writeln!(out, "\nTables:")?;
+                    for t in &info.tables {
+                        writeln!(out, "  {} ({})", t.name, t.kind)?;
+                        for c in &t.columns {
+                            let mut flags = Vec::new();
+                            if c.primary_key {
+                                flags.push("PK");
+                            }
+                            if c.not_null {
+                                flags.push("NOT NULL");
+                            }
+                            writeln!(
+                                out,
+                                "    {:<16} {:<8} {}",
+                                c.name,
+                                c.decl_type,
+                                flags.join(" ")
+                            )?;
+                        }
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let migrations: Vec<_> = info
+                            .migrations
+                            .iter()
+                            .map(|m| {
+                                serde_json::json!({
+                                    "version": m.version,
+                                    "name": m.name,
+                                    "applied_on": m.applied_on,
+                                })
+                            })
+                            .collect();
+                        let tables: Vec<_> = info
+                            .tables
+                            .iter()
+                            .map(|t| {
+                                let cols: Vec<_> = t
+                                    .columns
+                                    .iter()
+                                    .map(|c| {
+                                        serde_json::json!({
+                                            "name": c.name,
+                                            "type": c.decl_type,
+                                            "not_null": c.not_null,
+                                            "primary_key": c.primary_key,
+                                        })
+                                    })
+                                    .collect();
+                                serde_json::json!({
+                                    "name": t.name,
+                                    "kind": t.kind,
+                                    "columns": cols,
+                                })
+                            })
+                            .collect();
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({
+                                "current_version": info.current_version,
+                                "expected_version": info.expected_version,
+                                "compatible": info.is_compatible(),
+                                "migrations": migrations,
+                                "tables": tables,
+                            })
+                        )?;
+                    }
+                }
+            }
+        }
+
+        /* ── db identity ──────────────────────────────────────────── */
+        DbCmd::Identity { mode } => {
+            if let Some(mode) = mode {
+                db::set_identity(conn, (*mode).into())?;
+            }
+            let current = db::identity(conn)?;
+            match fmt {
+                Format::Text => writeln!(out, "identity: {}", current.as_str())?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{{\"identity\":\"{}\"}}", current.as_str())?;
+                    }
+                }
+            }
+        }
+
+        /* ── db aliases ───────────────────────────────────────────── */
+        DbCmd::Aliases { file } => {
+            let fid = db::file_id(conn, &file.to_string_lossy())?;
+            let aliases = db::aliases(conn, fid)?;
+            match fmt {
+                Format::Text => {
+                    for a in &aliases {
+                        writeln!(out, "{a}")?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::to_string(&aliases)?)?;
+                    }
+                }
+            }
+        }
+
+        DbCmd::Use { .. } => unreachable!("handled before the index is opened"),
+
+        /* ── db diff ──────────────────────────────────────────────── */
+        DbCmd::Diff { backup, query } => {
+            let path = match PathBuf::from(backup) {
+                p if p.is_file() => p,
+                _ => BackupManager::new(&cfg.db_path, &cfg.backups_dir)?.backup_path(backup)?,
+            };
+            let diff = db::diff_against(conn, &path, query.as_deref())?;
+            match fmt {
+                Format::Text => {
+                    writeln!(out, "Changes from {} to the index", path.display())?;
+                    writeln!(out, "\nSchema:")?;
+                    if diff.schema.is_empty() {
+                        writeln!(out, "  unchanged")?;
+                    }
+                    for s in &diff.schema {
+                        writeln!(out, "  {:<8} {:<8} {}", s.delta.as_str(), s.kind, s.name)?;
+                    }
+                    writeln!(out, "\nRows:")?;
+                    let show = |n: Option<i64>| n.map_or("-".to_string(), |n| n.to_string());
+                    let mut same = 0;
+                    for t in &diff.tables {
+                        if t.other == t.live {
+                            same += 1;
+                            continue;
+                        }
+                        writeln!(
+                            out,
+                            "  {:<24} {:>8} -> {:<8} ({:+})",
+                            t.table,
+                            show(t.other),
+                            show(t.live),
+                            t.delta()
+                        )?;
+                    }
+                    writeln!(out, "  {same} table(s) unchanged")?;
+                    if query.is_some() {
+                        writeln!(out, "\nMetadata:")?;
+                        for c in &diff.metadata {
+                            writeln!(out, "  {}", super::snapshot::describe(c))?;
+                        }
+                        writeln!(out, "  {} change(s)", diff.metadata.len())?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let schema: Vec<_> = diff
+                            .schema
+                            .iter()
+                            .map(|s| {
+                                serde_json::json!({
+                                    "kind": s.kind,
+                                    "name": s.name,
+                                    "change": s.delta.as_str(),
+                                })
+                            })
+                            .collect();
+                        let tables: Vec<_> = diff
+                            .tables
+                            .iter()
+                            .map(|t| {
+                                serde_json::json!({
+                                    "table": t.table,
+                                    "backup": t.other,
+                                    "live": t.live,
+                                    "delta": t.delta(),
+                                })
+                            })
+                            .collect();
+                        let metadata: Vec<_> = diff
+                            .metadata
+                            .iter()
+                            .map(|c| {
+                                serde_json::json!({
+                                    "path": c.path,
+                                    "kind": c.kind.as_str(),
+                                    "key": c.key,
+                                    "old": c.old,
+                                    "new": c.new,
+                                })
+                            })
+                            .collect();
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({
+                                "backup": path,
+                                "schema": schema,
+                                "tables": tables,
+                                "metadata": metadata,
+                            })
+                        )?;
+                    }
+                }
+            }
+        }
+
+        /* ── db tuning show ───────────────────────────────────────── */
+        DbCmd::Tuning(TuningCmd::Show) => {
+            let preset = db::tuning(conn)?;
+            let live = db::live_pragmas(conn)?;
+            match fmt {
+                Format::Text => {
+                    writeln!(out, "preset: {preset}")?;
+                    for (name, value) in &live {
+                        writeln!(out, "  {name:<13} {value}")?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let pragmas: serde_json::Map<String, serde_json::Value> = live
+                            .into_iter()
+                            .map(|(k, v)| (k.to_string(), v.into()))
+                            .collect();
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({ "preset": preset.as_str(), "pragmas": pragmas })
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! `marlin doctor` – health checks for the index (schema, SQLite
+//! integrity, full-text index consistency).
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use clap::Args;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db;
+
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Files whose full-text row is checked, picked at random
+    #[arg(long, default_value_t = 200)]
+    pub sample: usize,
+    /// Check every file instead of a sample
+    #[arg(long)]
+    pub full: bool,
+    /// Rewrite mismatched full-text rows
+    #[arg(long)]
+    pub repair: bool,
+}
+
+pub fn run(a: &DoctorArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let info = db::schema_info(conn)?;
+    let integrity: String = conn.query_row("PRAGMA quick_check", [], |r| r.get(0))?;
+    let fts = db::verify_fts_consistency(conn, (!a.full).then_some(a.sample))?;
+    let repaired = if a.repair && !fts.is_consistent() {
+        Some(db::repair_fts(conn, &fts.rowids())?)
+    } else {
+        None
+    };
+
+    let mut problems = 0;
+    if !info.is_compatible() {
+        problems += 1;
+    }
+    if integrity != "ok" {
+        problems += 1;
+    }
+    if !fts.is_consistent() && repaired.is_none() {
+        problems += 1;
+    }
+
+    match fmt {
+        Format::Text => {
+            let mark = |ok: bool| if ok { "ok  " } else { "FAIL" };
+            writeln!(
+                out,
+                "{}  schema v{} (library expects v{})",
+                mark(info.is_compatible()),
+                info.current_version,
+                info.expected_version
+            )?;
+            writeln!(out, "{}  integrity: {integrity}", mark(integrity == "ok"))?;
+            writeln!(
+                out,
+                "{}  full-text index: {} missing, {} orphaned, {} stale ({} file(s) checked)",
+                mark(fts.is_consistent()),
+                fts.missing.len(),
+                fts.orphaned.len(),
+                fts.stale.len(),
+                fts.checked
+            )?;
+            if let Some(n) = repaired {
+                writeln!(out, "      repaired {n} full-text row(s)")?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "schema_version": info.current_version,
+                        "expected_version": info.expected_version,
+                        "integrity": integrity,
+                        "fts": {
+                            "checked": fts.checked,
+                            "missing": fts.missing,
+                            "orphaned": fts.orphaned,
+                            "stale": fts.stale,
+                        },
+                        "repaired": repaired,
+                    })
+                )?;
+            }
+        }
+    }
+
+    if problems > 0 {
+        let hint = if fts.is_consistent() || a.repair {
+            ""
+        } else {
+            "; `marlin doctor --repair` rewrites the full-text rows"
+        };
+        bail!("doctor found {problems} problem(s){hint}");
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! `marlin du` – disk usage per directory, computed from the index instead
+//! of walking the file system.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db::{self, UsageStats};
+use libmarlin::query::{self, SearchOptions};
+use libmarlin::scan;
+use libmarlin::utils::human_size;
+
+#[derive(Args, Debug)]
+pub struct DuArgs {
+    /// Directory to break down (defaults to the current directory)
+    pub path: Option<PathBuf>,
+    /// Only count files matching this search query
+    #[arg(long, short)]
+    pub query: Option<String>,
+    /// How many directory levels to group by
+    #[arg(long, default_value_t = 1)]
+    pub depth: usize,
+    /// Show only the first N directories
+    #[arg(long)]
+    pub top: Option<usize>,
+    /// Order of the rows
+    #[arg(long, value_enum, default_value_t = DuSort::Size)]
+    pub sort: DuSort,
+    /// Re-stat the selected files and update stale sizes first
+    #[arg(long)]
+    pub refresh: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuSort {
+    /// Largest total size first
+    Size,
+    /// Most files first
+    Files,
+    /// Alphabetical by directory
+    Name,
+}
+
+pub fn run(a: &DuArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let root = match &a.path {
+        Some(p) => p.clone(),
+        None => std::env::current_dir()?,
+    };
+    let root = root.canonicalize().unwrap_or(root);
+
+    let ids: Vec<i64> = match &a.query {
+        Some(q) => query::search_hits(conn, q, &SearchOptions::default())?
+            .into_iter()
+            .map(|h| h.file_id)
+            .collect(),
+        None => {
+            let mut stmt = conn.prepare("SELECT id FROM files WHERE missing_since IS NULL")?;
+            let ids = stmt
+                .query_map([], |r| r.get(0))?
+                .collect::<Result<_, _>>()?;
+            ids
+        }
+    };
+    let under_root = |rows: Vec<(String, i64, i64)>| -> Vec<(String, i64, i64)> {
+        rows.into_iter()
+            .filter(|(p, _, _)| Path::new(p).starts_with(&root))
+            .collect()
+    };
+
+    let mut rows = under_root(db::file_usage(conn, &ids)?);
+    if a.refresh {
+        let paths: Vec<&str> = rows.iter().map(|(p, _, _)| p.as_str()).collect();
+        if scan::refresh_files(conn, &paths)? > 0 {
+            rows = under_root(db::file_usage(conn, &ids)?);
+        }
+    }
+
+    let total_files = rows.len();
+    let total_size: i64 = rows.iter().map(|(_, s, _)| s).sum();
+
+    let mut dirs = db::group_by_dir(rows, &root, a.depth, i64::MAX);
+    match a.sort {
+        DuSort::Size => {}
+        DuSort::Files => dirs.sort_by(|x, y| y.files.cmp(&x.files).then(x.name.cmp(&y.name))),
+        DuSort::Name => dirs.sort_by(|x, y| x.name.cmp(&y.name)),
+    }
+    if let Some(n) = a.top {
+        dirs.truncate(n);
+    }
+
+    print_usage(out, &root, &dirs, total_files, total_size, fmt)
+}
+
+fn print_usage(
+    out: &mut impl Write,
+    root: &Path,
+    dirs: &[UsageStats],
+    total_files: usize,
+    total_size: i64,
+    fmt: Format,
+) -> Result<()> {
+    match fmt {
+        Format::Text => {
+            for d in dirs {
+                writeln!(
+                    out,
+                    "{:>10}  {:>7}  {}",
+                    human_size(d.total_size),
+                    d.files,
+                    d.name
+                )?;
+            }
+            writeln!(
+                out,
+                "{:>10}  {:>7}  total ({})",
+                human_size(total_size),
+                total_files,
+                root.display()
+            )?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let entries: Vec<_> = dirs
+                    .iter()
+                    .map(|d| {
+                        serde_json::json!({
+                            "dir": d.name,
+                            "files": d.files,
+                            "size": d.total_size,
+                        })
+                    })
+                    .collect();
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "root": root,
+                        "files": total_files,
+                        "size": total_size,
+                        "dirs": entries,
+                    })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
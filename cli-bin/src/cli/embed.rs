@@ -0,0 +1,75 @@
+//! `marlin embed` – compute the vectors `marlin search --semantic` ranks.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::cli::{Context, Format};
+use libmarlin::embed::{CommandEmbedder, Embedder, HashEmbedder};
+
+#[derive(Args, Debug)]
+pub struct EmbedArgs {
+    /// Glob of indexed files to embed (all files by default)
+    #[arg(default_value = "*")]
+    pub pattern: String,
+    /// Recompute vectors that are already up to date
+    #[arg(long)]
+    pub force: bool,
+    #[command(flatten)]
+    pub embedder: EmbedderArg,
+}
+
+/// The embedding model, shared by `embed` and `search --semantic`.
+#[derive(Args, Debug, Default)]
+pub struct EmbedderArg {
+    /// Program computing embeddings: text on stdin, vector on stdout
+    /// (default: `MARLIN_EMBEDDER`, else the built-in hashing embedder)
+    #[arg(long, value_name = "CMD")]
+    pub embedder: Option<String>,
+}
+
+impl EmbedderArg {
+    pub fn build(&self) -> Result<Box<dyn Embedder>> {
+        let cmd = self
+            .embedder
+            .clone()
+            .or_else(|| std::env::var("MARLIN_EMBEDDER").ok())
+            .filter(|c| !c.trim().is_empty());
+        Ok(match cmd {
+            Some(c) => Box::new(CommandEmbedder::new(&c)?),
+            None => Box::new(HashEmbedder::default()),
+        })
+    }
+}
+
+pub fn run(a: &EmbedArgs, ctx: &mut Context) -> Result<()> {
+    let embedder = a.embedder.build()?;
+    let report = ctx.marlin.embed(embedder.as_ref(), &a.pattern, a.force)?;
+    let out = &mut ctx.output;
+    match ctx.format {
+        Format::Text => {
+            writeln!(
+                out,
+                "Embedded {} file(s) with {} ({} up to date, {} missing)",
+                report.embedded,
+                embedder.model(),
+                report.up_to_date,
+                report.missing
+            )?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let v = serde_json::json!({
+                    "model": embedder.model(),
+                    "embedded": report.embedded,
+                    "up_to_date": report.up_to_date,
+                    "missing": report.missing,
+                });
+                writeln!(out, "{v}")?;
+            }
+        }
+    }
+    Ok(())
+}
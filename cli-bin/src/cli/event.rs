@@ -1,24 +1,200 @@
 // src/cli/event.rs
-use crate::cli::Format;
+//! `marlin event …` – calendar events linked to files.
+
+use std::io::Write;
+
+use crate::cli::{Context, Format};
+use anyhow::bail;
 use clap::{Args, Subcommand};
-use rusqlite::Connection;
+#[cfg(feature = "json")]
+use libmarlin::db::FileEvent;
+use libmarlin::db::{self, EventFilter};
+use libmarlin::query;
+use libmarlin::timefmt::TimeFormatter;
 
 #[derive(Subcommand, Debug)]
 pub enum EventCmd {
+    /// Link an event to files matching a glob
     Add(ArgsAdd),
-    Timeline,
+    /// List events with their ids
+    List(ArgsList),
+    /// Events by date, with the files they belong to
+    Timeline(ArgsTimeline),
 }
 
 #[derive(Args, Debug)]
 pub struct ArgsAdd {
-    pub file: String,
+    pub file_pattern: String,
+    /// YYYY-MM-DD[THH:MM] in local time
     pub date: String,
     pub description: String,
+    /// Last day (or time) of an event spanning several
+    #[arg(long)]
+    pub end: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsList {
+    /// Only events on files matching this glob
+    pub file_pattern: Option<String>,
 }
 
-pub fn run(cmd: &EventCmd, _conn: &mut Connection, _format: Format) -> anyhow::Result<()> {
+#[derive(Args, Debug)]
+pub struct ArgsTimeline {
+    /// Leave out events that ended before this local date
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Leave out events starting after this local date
+    #[arg(long)]
+    pub to: Option<String>,
+}
+
+pub fn run(cmd: &EventCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (conn, out) = ctx.split();
     match cmd {
-        EventCmd::Add(a) => todo!("event add {:?}", a),
-        EventCmd::Timeline => todo!("event timeline"),
+        EventCmd::Add(a) => {
+            let start = query::time_span(&a.date)?.0;
+            let end = a
+                .end
+                .as_deref()
+                .map(|e| query::time_span(e).map(|(start, _)| start))
+                .transpose()?;
+            let files = db::match_files(&conn, &a.file_pattern)?;
+            if files.is_empty() {
+                bail!("no indexed file matches `{}`", a.file_pattern);
+            }
+            let mut ids = Vec::new();
+            for (fid, _) in &files {
+                ids.push(db::add_event(&conn, *fid, start, end, &a.description)?);
+            }
+            match fmt {
+                Format::Text => {
+                    for (id, (_, path)) in ids.iter().zip(&files) {
+                        writeln!(out, "Added event #{id} to {path}")?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::json!({ "added": ids }))?;
+                    }
+                }
+            }
+        }
+        EventCmd::List(a) => {
+            let file_ids = a
+                .file_pattern
+                .as_deref()
+                .map(|p| db::match_files(&conn, p))
+                .transpose()?
+                .map(|files| files.into_iter().map(|(id, _)| id).collect());
+            let events = db::list_events(
+                &conn,
+                &EventFilter {
+                    file_ids,
+                    ..Default::default()
+                },
+            )?;
+            match fmt {
+                Format::Text => {
+                    for e in &events {
+                        let start = time.epoch(e.start_at).unwrap_or_default();
+                        let end = e.end_at.and_then(|t| time.epoch(t)).unwrap_or_default();
+                        writeln!(
+                            out,
+                            "#{:<4} {start:<w$}  {end:<w$}  {}  ({})",
+                            e.id,
+                            e.description,
+                            e.path,
+                            w = time.width()
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::to_string(&json_rows(&events))?)?;
+                    }
+                }
+            }
+        }
+        EventCmd::Timeline(a) => {
+            let events = db::list_events(
+                &conn,
+                &EventFilter {
+                    from: a
+                        .from
+                        .as_deref()
+                        .map(|d| query::time_span(d).map(|(start, _)| start))
+                        .transpose()?,
+                    to: a
+                        .to
+                        .as_deref()
+                        .map(|d| query::time_span(d).map(|(_, end)| end))
+                        .transpose()?,
+                    file_ids: None,
+                },
+            )?;
+            match fmt {
+                Format::Text => {
+                    let day = TimeFormatter::new(time.zone(), "%Y-%m-%d")?;
+                    let clock = TimeFormatter::new(time.zone(), "%H:%M")?;
+                    let mut last_day = None;
+                    // one line per event, listing every file it is linked to
+                    for group in events.chunk_by(|a, b| {
+                        (a.start_at, a.end_at, &a.description)
+                            == (b.start_at, b.end_at, &b.description)
+                    }) {
+                        let e = &group[0];
+                        let date = day.epoch(e.start_at).unwrap_or_default();
+                        if last_day.as_ref() != Some(&date) {
+                            writeln!(out, "{date}")?;
+                            last_day = Some(date);
+                        }
+                        let at = clock
+                            .epoch(e.start_at)
+                            .filter(|t| t != "00:00")
+                            .unwrap_or_default();
+                        let until = e
+                            .end_at
+                            .and_then(|t| day.epoch(t))
+                            .map(|d| format!(" (until {d})"))
+                            .unwrap_or_default();
+                        let paths: Vec<&str> = group.iter().map(|e| e.path.as_str()).collect();
+                        writeln!(
+                            out,
+                            "  {at:<5}  {}{until}  –  {}",
+                            e.description,
+                            paths.join(", ")
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::to_string(&json_rows(&events))?)?;
+                    }
+                }
+            }
+        }
     }
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn json_rows(events: &[FileEvent]) -> serde_json::Value {
+    events
+        .iter()
+        .map(|e| {
+            serde_json::json!({
+                "id": e.id,
+                "path": e.path,
+                "start_at": e.start_at,
+                "end_at": e.end_at,
+                "description": e.description,
+            })
+        })
+        .collect()
 }
@@ -0,0 +1,226 @@
+//! `marlin explain-file <path>` – everything the index knows about one file.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::cli::{Context, Format};
+use libmarlin::db::TaskStatus;
+use libmarlin::utils::human_size;
+
+#[derive(Args, Debug)]
+pub struct ExplainArgs {
+    /// Indexed path (as stored, or any path naming the same file)
+    pub path: String,
+}
+
+pub fn run(a: &ExplainArgs, ctx: &mut Context) -> Result<()> {
+    let r = ctx.marlin.describe(&a.path)?;
+    let time = &ctx.time;
+    let out = &mut ctx.output;
+    match ctx.format {
+        Format::Text => {
+            let when = |t: Option<i64>| t.and_then(|t| time.epoch(t)).unwrap_or_else(|| "-".into());
+            let yes_no = |b: bool| if b { "yes" } else { "no" };
+            writeln!(out, "Path:        {}", r.path)?;
+            writeln!(
+                out,
+                "Size:        {}",
+                r.size.map(human_size).unwrap_or_else(|| "-".into())
+            )?;
+            writeln!(out, "Modified:    {}", when(r.mtime))?;
+            writeln!(out, "Indexed:     {}", when(r.first_indexed_at))?;
+            writeln!(out, "Last seen:   {}", when(r.last_seen_at))?;
+            writeln!(out, "Hash:        {}", r.hash.as_deref().unwrap_or("-"))?;
+            writeln!(out, "Rating:      {}/5", r.rating.unwrap_or(0))?;
+            writeln!(out, "Favorite:    {}", yes_no(r.favorite))?;
+            writeln!(out, "Locked:      {}", yes_no(r.locked))?;
+            writeln!(out, "State:       {}", r.state().unwrap_or("-"))?;
+            if !r.labels.is_empty() {
+                writeln!(out, "Labels:      {}", r.labels.join(", "))?;
+            }
+            if !r.collections.is_empty() {
+                writeln!(out, "Collections: {}", r.collections.join(", "))?;
+            }
+            section(out, "Aliases", r.aliases.iter().cloned())?;
+            section(out, "Tags", r.tags.iter().cloned())?;
+            section(
+                out,
+                "Attributes",
+                r.attributes.iter().map(|a| match a.expires_at {
+                    Some(t) => format!("{} = {}  (expires {})", a.key, a.value, when(Some(t))),
+                    None => format!("{} = {}", a.key, a.value),
+                }),
+            )?;
+            let link = |l: &libmarlin::describe::LinkInfo| match &l.link_type {
+                Some(t) => format!("{}  ({t})", l.path),
+                None => l.path.clone(),
+            };
+            section(out, "Links out", r.links_out.iter().map(link))?;
+            section(out, "Links in", r.links_in.iter().map(link))?;
+            section(
+                out,
+                "State history",
+                r.states
+                    .iter()
+                    .map(|s| format!("{}  {}", when(Some(s.changed_at)), s.state)),
+            )?;
+            section(
+                out,
+                "Tasks",
+                r.tasks.iter().map(|t| {
+                    let mark = if t.status == TaskStatus::Done {
+                        'x'
+                    } else {
+                        ' '
+                    };
+                    match t.due_at {
+                        Some(d) => {
+                            format!("#{} [{mark}] {}  (due {})", t.id, t.title, when(Some(d)))
+                        }
+                        None => format!("#{} [{mark}] {}", t.id, t.title),
+                    }
+                }),
+            )?;
+            section(
+                out,
+                "Reminders",
+                r.reminders
+                    .iter()
+                    .map(|m| format!("#{} {}  {}", m.id, when(Some(m.due_at)), m.note)),
+            )?;
+            section(
+                out,
+                "Events",
+                r.events.iter().map(|e| match e.end_at {
+                    Some(end) => format!(
+                        "{} – {}  {}",
+                        when(Some(e.start_at)),
+                        when(Some(end)),
+                        e.description
+                    ),
+                    None => format!("{}  {}", when(Some(e.start_at)), e.description),
+                }),
+            )?;
+            section(
+                out,
+                "Versions",
+                r.versions.iter().map(|v| {
+                    let hash_only = if v.has_content { "" } else { "  (hash only)" };
+                    format!(
+                        "v{}  {}  {}{hash_only}",
+                        v.version,
+                        when(Some(v.created_at)),
+                        human_size(v.size)
+                    )
+                }),
+            )?;
+            section(
+                out,
+                "Annotations",
+                r.annotations.iter().map(|n| {
+                    let mark = if n.highlight { "* " } else { "" };
+                    match n.range {
+                        Some(range) => format!("#{} {mark}{}  ({range})", n.id, n.note),
+                        None => format!("#{} {mark}{}", n.id, n.note),
+                    }
+                }),
+            )?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                use serde_json::json;
+                let doc = json!({
+                    "id": r.id,
+                    "path": r.path,
+                    "size": r.size,
+                    "mtime": r.mtime,
+                    "hash": r.hash,
+                    "first_indexed_at": r.first_indexed_at,
+                    "last_seen_at": r.last_seen_at,
+                    "rating": r.rating,
+                    "favorite": r.favorite,
+                    "locked": r.locked,
+                    "state": r.state(),
+                    "aliases": r.aliases,
+                    "tags": r.tags,
+                    "attributes": r.attributes.iter().map(|a| json!({
+                        "key": a.key,
+                        "value": a.value,
+                        "expires_at": a.expires_at,
+                    })).collect::<Vec<_>>(),
+                    "labels": r.labels,
+                    "links_out": r.links_out.iter().map(|l| json!({
+                        "path": l.path,
+                        "type": l.link_type,
+                    })).collect::<Vec<_>>(),
+                    "links_in": r.links_in.iter().map(|l| json!({
+                        "path": l.path,
+                        "type": l.link_type,
+                    })).collect::<Vec<_>>(),
+                    "collections": r.collections,
+                    "states": r.states.iter().map(|s| json!({
+                        "state": s.state,
+                        "changed_at": s.changed_at,
+                    })).collect::<Vec<_>>(),
+                    "tasks": r.tasks.iter().map(|t| json!({
+                        "id": t.id,
+                        "title": t.title,
+                        "status": t.status.as_str(),
+                        "due_at": t.due_at,
+                        "priority": t.priority,
+                    })).collect::<Vec<_>>(),
+                    "reminders": r.reminders.iter().map(|m| json!({
+                        "id": m.id,
+                        "due_at": m.due_at,
+                        "note": m.note,
+                        "notified_at": m.notified_at,
+                    })).collect::<Vec<_>>(),
+                    "events": r.events.iter().map(|e| json!({
+                        "id": e.id,
+                        "start_at": e.start_at,
+                        "end_at": e.end_at,
+                        "description": e.description,
+                    })).collect::<Vec<_>>(),
+                    "versions": r.versions.iter().map(|v| json!({
+                        "version": v.version,
+                        "hash": v.hash,
+                        "size": v.size,
+                        "has_content": v.has_content,
+                        "created_at": v.created_at,
+                    })).collect::<Vec<_>>(),
+                    "annotations": r.annotations.iter().map(|n| json!({
+                        "id": n.id,
+                        "note": n.note,
+                        "range": n.range.map(|r| {
+                            let (start, end) = r.bounds();
+                            json!({ "unit": r.unit(), "start": start, "end": end })
+                        }),
+                        "highlight": n.highlight,
+                    })).collect::<Vec<_>>(),
+                });
+                writeln!(out, "{}", serde_json::to_string(&doc)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A titled, indented list; nothing at all when it is empty.
+fn section(
+    out: &mut impl Write,
+    title: &str,
+    lines: impl Iterator<Item = String>,
+) -> std::io::Result<()> {
+    let mut lines = lines.peekable();
+    if lines.peek().is_none() {
+        return Ok(());
+    }
+    writeln!(out, "{title}:")?;
+    for l in lines {
+        writeln!(out, "  {l}")?;
+    }
+    Ok(())
+}
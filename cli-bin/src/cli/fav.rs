@@ -0,0 +1,73 @@
+//! `marlin fav …` – mark files as favorites.
+
+use std::io::Write;
+
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::{db, lock};
+
+#[derive(Subcommand, Debug)]
+pub enum FavCmd {
+    /// Mark files matching a glob as favorites
+    Add(ArgsPattern),
+    /// Clear the favorite flag on files matching a glob
+    Rm(ArgsPattern),
+    /// List all favorite files
+    List,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsPattern {
+    pub pattern: String,
+}
+
+pub fn run(cmd: &FavCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        /* ── fav add / fav rm ─────────────────────────────────────── */
+        FavCmd::Add(a) | FavCmd::Rm(a) => {
+            let on = matches!(cmd, FavCmd::Add(_));
+            let files = db::match_files(conn, &a.pattern)?;
+            lock::check_files(conn, &files)?;
+            for (fid, _) in &files {
+                db::set_favorite(conn, *fid, on)?;
+            }
+
+            match fmt {
+                Format::Text => {
+                    let verb = if on { "Marked" } else { "Unmarked" };
+                    writeln!(out, "{verb} {} file(s) as favorite", files.len())?;
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{{\"favorite\":{},\"updated\":{}}}", on, files.len())?;
+                    }
+                }
+            }
+        }
+
+        /* ── fav list ─────────────────────────────────────────────── */
+        FavCmd::List => {
+            let files = db::list_favorites(conn)?;
+            match fmt {
+                Format::Text => {
+                    for f in files {
+                        writeln!(out, "{f}")?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::to_string(&files)?)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
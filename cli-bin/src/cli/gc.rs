@@ -0,0 +1,52 @@
+//! `marlin gc` – sweep orphaned metadata out of the index.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db;
+use libmarlin::utils::human_size;
+
+#[derive(Args, Debug)]
+pub struct GcArgs {
+    /// Compact the database file afterwards
+    #[arg(long)]
+    pub vacuum: bool,
+}
+
+pub fn run(a: &GcArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let report = db::gc(conn, a.vacuum)?;
+
+    match fmt {
+        Format::Text => {
+            for (kind, n) in report.counts() {
+                if n > 0 {
+                    writeln!(out, "{n:>8}  {kind}")?;
+                }
+            }
+            writeln!(out, "Removed {} orphaned row(s)", report.total())?;
+            if let Some(bytes) = report.vacuumed_bytes {
+                writeln!(out, "Vacuum reclaimed {}", human_size(bytes as i64))?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let mut obj = serde_json::Map::new();
+                for (kind, n) in report.counts() {
+                    obj.insert(kind.into(), n.into());
+                }
+                obj.insert("total".into(), report.total().into());
+                obj.insert("vacuumed_bytes".into(), report.vacuumed_bytes.into());
+                writeln!(out, "{}", serde_json::Value::Object(obj))?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! `marlin history …` – past searches recorded by `marlin search`.
+
+use std::io::Write;
+
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db;
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCmd {
+    /// List recent searches (newest first)
+    Search(ArgsSearch),
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsSearch {
+    /// How many entries to show
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+}
+
+pub fn run(cmd: &HistoryCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        HistoryCmd::Search(a) => {
+            let entries = db::search_history(conn, a.limit)?;
+            match fmt {
+                Format::Text => {
+                    for e in entries {
+                        let when = time.epoch(e.executed_at).unwrap_or_default();
+                        writeln!(
+                            out,
+                            "{:>4}  {when}  {:>5} hit(s)  {}",
+                            e.id, e.hits, e.query
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = entries
+                            .iter()
+                            .map(|e| {
+                                serde_json::json!({
+                                    "id": e.id,
+                                    "query": e.query,
+                                    "executed_at": e.executed_at,
+                                    "hits": e.hits,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
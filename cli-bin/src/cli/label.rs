@@ -0,0 +1,151 @@
+//! `marlin label …` – colour labels, optionally mirrored to Finder / KDE tags.
+
+use std::io::Write;
+use std::path::Path;
+
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+use tracing::warn;
+
+use crate::cli::{Context, Format};
+use libmarlin::{db, labels, lock};
+
+#[derive(Subcommand, Debug)]
+pub enum LabelCmd {
+    /// Add a label (red, orange, …, or #rrggbb) to files matching a glob
+    Add(ArgsLabel),
+    /// Remove a label from files matching a glob
+    Rm(ArgsLabel),
+    /// Show the labels of files matching a glob
+    List(ArgsPattern),
+    /// Merge Marlin labels and OS tags in both directions
+    Sync(ArgsPattern),
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsLabel {
+    pub pattern: String,
+    pub label: String,
+    /// Also update the file's Finder / KDE tags
+    #[arg(long)]
+    pub sync: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsPattern {
+    pub pattern: String,
+}
+
+/// Push the file's current labels to its OS tags, warning when unsupported.
+fn push_os_labels(conn: &Connection, fid: i64, path: &str) -> anyhow::Result<()> {
+    let current = db::file_labels(conn, fid)?;
+    if !labels::write_os_labels(Path::new(path), &current)? {
+        warn!(file = %path, "OS tags not supported here – skipped");
+    }
+    Ok(())
+}
+
+pub fn run(cmd: &LabelCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        /* ── label add / label rm ─────────────────────────────────── */
+        LabelCmd::Add(a) | LabelCmd::Rm(a) => {
+            let add = matches!(cmd, LabelCmd::Add(_));
+            let label = labels::normalize_label(&a.label)?;
+            let files = db::match_files(conn, &a.pattern)?;
+            lock::check_files(conn, &files)?;
+            for (fid, path) in &files {
+                if add {
+                    db::add_label(conn, *fid, &label)?;
+                } else {
+                    db::remove_label(conn, *fid, &label)?;
+                }
+                if a.sync {
+                    push_os_labels(conn, *fid, path)?;
+                }
+            }
+
+            match fmt {
+                Format::Text => {
+                    let verb = if add { "Labelled" } else { "Unlabelled" };
+                    writeln!(out, "{verb} {} file(s) '{label}'", files.len())?;
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(
+                            out,
+                            "{{\"label\":\"{}\",\"action\":\"{}\",\"files\":{}}}",
+                            label,
+                            if add { "add" } else { "rm" },
+                            files.len()
+                        )?;
+                    }
+                }
+            }
+        }
+
+        /* ── label list ───────────────────────────────────────────── */
+        LabelCmd::List(a) => {
+            let mut rows = Vec::new();
+            for (fid, path) in db::match_files(conn, &a.pattern)? {
+                let l = db::file_labels(conn, fid)?;
+                if !l.is_empty() {
+                    rows.push((path, l));
+                }
+            }
+            match fmt {
+                Format::Text => {
+                    for (path, l) in rows {
+                        writeln!(out, "{path}: {}", l.join(", "))?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+
+        /* ── label sync ───────────────────────────────────────────── */
+        LabelCmd::Sync(a) => {
+            let files = db::match_files(conn, &a.pattern)?;
+            lock::check_files(conn, &files)?;
+            let mut imported = 0usize;
+            for (fid, path) in &files {
+                let known = db::file_labels(conn, *fid)?;
+                for l in labels::read_os_labels(Path::new(path))? {
+                    if !known.contains(&l) {
+                        db::add_label(conn, *fid, &l)?;
+                        imported += 1;
+                    }
+                }
+                push_os_labels(conn, *fid, path)?;
+            }
+
+            match fmt {
+                Format::Text => writeln!(
+                    out,
+                    "Synced {} file(s), imported {imported} label(s) from OS tags",
+                    files.len()
+                )?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(
+                            out,
+                            "{{\"files\":{},\"imported\":{}}}",
+                            files.len(),
+                            imported
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,17 +1,35 @@
 //! src/cli/link.rs – manage typed relationships between files
 
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
 use clap::{Args, Subcommand};
 use rusqlite::Connection;
 
-use crate::cli::Format; // output selector
+use crate::cli::conflicts::ConflictArgs;
+use crate::cli::{Context, Format}; // output selector
 use libmarlin::db; // ← switched from `crate::db`
+use libmarlin::describe;
+use libmarlin::links;
 
 #[derive(Subcommand, Debug)]
 pub enum LinkCmd {
     Add(LinkArgs),
     Rm(LinkArgs),
+    /// Register link types and the names of their inverses
+    #[command(subcommand)]
+    Type(TypeCmd),
     List(ListArgs),
     Backlinks(BacklinksArgs),
+    /// Add links from `from,to[,type]` CSV rows in one transaction
+    Import(ImportArgs),
+    /// Key=value metadata on links (`weight`, `note`, …)
+    #[command(subcommand)]
+    Attr(LinkAttrCmd),
+    /// Print links and their attributes as a Graphviz DOT graph
+    Export(ExportArgs),
 }
 
 #[derive(Args, Debug)]
@@ -20,6 +38,9 @@ pub struct LinkArgs {
     pub to: String,
     #[arg(long)]
     pub r#type: Option<String>,
+    /// Also link <TO> back to <FROM> with the registered inverse type
+    #[arg(long, requires = "type")]
+    pub with_inverse: bool,
 }
 
 #[derive(Args, Debug)]
@@ -29,6 +50,9 @@ pub struct ListArgs {
     pub direction: Option<String>,
     #[arg(long)]
     pub r#type: Option<String>,
+    /// Also show each link's attributes
+    #[arg(long)]
+    pub long: bool,
 }
 
 #[derive(Args, Debug)]
@@ -36,18 +60,90 @@ pub struct BacklinksArgs {
     pub pattern: String,
 }
 
-pub fn run(cmd: &LinkCmd, conn: &mut Connection, format: Format) -> anyhow::Result<()> {
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// CSV file, or `-` for stdin
+    pub file: PathBuf,
+    /// Type of links whose row names none
+    #[arg(long)]
+    pub r#type: Option<String>,
+    #[command(flatten)]
+    pub conflicts: ConflictArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TypeCmd {
+    /// Register a type, optionally with the name of its inverse
+    Add {
+        name: String,
+        /// The relation read from the target (`cited-by` for `cites`);
+        /// the type's own name for a symmetric one
+        #[arg(long)]
+        inverse: Option<String>,
+    },
+    List,
+    /// Forget a registration (links keep their types)
+    Rm {
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LinkAttrCmd {
+    /// Set a key on the link from <FROM> to <TO>
+    Set(LinkAttrSetArgs),
+    /// Remove a key from a link
+    Rm(LinkAttrRmArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct LinkAttrSetArgs {
+    pub from: String,
+    pub to: String,
+    pub key: String,
+    pub value: String,
+    /// Type of the link, when the files have several
+    #[arg(long)]
+    pub r#type: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct LinkAttrRmArgs {
+    pub from: String,
+    pub to: String,
+    pub key: String,
+    /// Type of the link, when the files have several
+    #[arg(long)]
+    pub r#type: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Only links from files matching this pattern
+    #[arg(default_value = "*")]
+    pub pattern: String,
+    #[arg(long)]
+    pub r#type: Option<String>,
+}
+
+pub fn run(cmd: &LinkCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let format = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
     match cmd {
         LinkCmd::Add(args) => {
             let src_id = db::file_id(conn, &args.from)?;
             let dst_id = db::file_id(conn, &args.to)?;
-            db::add_link(conn, src_id, dst_id, args.r#type.as_deref())?;
+            match &args.r#type {
+                Some(t) if args.with_inverse => links::add_with_inverse(conn, src_id, dst_id, t)?,
+                t => db::add_link(conn, src_id, dst_id, t.as_deref())?,
+            }
             match format {
                 Format::Text => {
                     if let Some(t) = &args.r#type {
-                        println!("Linked '{}' → '{}' [type='{}']", args.from, args.to, t);
+                        writeln!(out, "Linked '{}' → '{}' [type='{}']", args.from, args.to, t)?;
                     } else {
-                        println!("Linked '{}' → '{}'", args.from, args.to);
+                        writeln!(out, "Linked '{}' → '{}'", args.from, args.to)?;
                     }
                 }
                 Format::Json => {
@@ -56,26 +152,33 @@ pub fn run(cmd: &LinkCmd, conn: &mut Connection, format: Format) -> anyhow::Resu
                         .as_ref()
                         .map(|s| format!("\"{}\"", s))
                         .unwrap_or_else(|| "null".into());
-                    println!(
+                    writeln!(
+                        out,
                         "{{\"from\":\"{}\",\"to\":\"{}\",\"type\":{}}}",
                         args.from, args.to, typ
-                    );
+                    )?;
                 }
             }
         }
         LinkCmd::Rm(args) => {
             let src_id = db::file_id(conn, &args.from)?;
             let dst_id = db::file_id(conn, &args.to)?;
-            db::remove_link(conn, src_id, dst_id, args.r#type.as_deref())?;
+            match &args.r#type {
+                Some(t) if args.with_inverse => {
+                    links::remove_with_inverse(conn, src_id, dst_id, t)?
+                }
+                t => db::remove_link(conn, src_id, dst_id, t.as_deref())?,
+            }
             match format {
                 Format::Text => {
                     if let Some(t) = &args.r#type {
-                        println!(
+                        writeln!(
+                            out,
                             "Removed link '{}' → '{}' [type='{}']",
                             args.from, args.to, t
-                        );
+                        )?;
                     } else {
-                        println!("Removed link '{}' → '{}'", args.from, args.to);
+                        writeln!(out, "Removed link '{}' → '{}'", args.from, args.to)?;
                     }
                 }
                 Format::Json => {
@@ -84,20 +187,64 @@ pub fn run(cmd: &LinkCmd, conn: &mut Connection, format: Format) -> anyhow::Resu
                         .as_ref()
                         .map(|s| format!("\"{}\"", s))
                         .unwrap_or_else(|| "null".into());
-                    println!(
+                    writeln!(
+                        out,
                         "{{\"from\":\"{}\",\"to\":\"{}\",\"type\":{}}}",
                         args.from, args.to, typ
-                    );
+                    )?;
+                }
+            }
+        }
+        LinkCmd::List(args) if args.long => {
+            let mut results = db::link_details(
+                conn,
+                &args.pattern,
+                args.direction.as_deref(),
+                args.r#type.as_deref(),
+            )?;
+            if args.direction.as_deref() == Some("in") {
+                let types = links::LinkTypes::load(conn)?;
+                for l in &mut results {
+                    l.link_type = types
+                        .seen_from_target(l.link_type.as_deref())
+                        .map(str::to_string);
+                }
+            }
+            match format {
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    writeln!(out, "{}", links_json(&results))?;
+                }
+                Format::Text => {
+                    for l in results {
+                        write!(out, "{} → {}", l.path, l.other)?;
+                        if let Some(t) = &l.link_type {
+                            write!(out, " [type='{}']", t)?;
+                        }
+                        if !l.attrs.is_empty() {
+                            let attrs: Vec<String> =
+                                l.attrs.iter().map(|(k, v)| format!("{k}={v}")).collect();
+                            write!(out, " {{{}}}", attrs.join(", "))?;
+                        }
+                        writeln!(out)?;
+                    }
                 }
             }
         }
         LinkCmd::List(args) => {
-            let results = db::list_links(
+            let mut results = db::list_links(
                 conn,
                 &args.pattern,
                 args.direction.as_deref(),
                 args.r#type.as_deref(),
             )?;
+            // incoming links read as their inverse, where one is registered
+            if args.direction.as_deref() == Some("in") {
+                let types = links::LinkTypes::load(conn)?;
+                for (_, _, t) in &mut results {
+                    *t = types.seen_from_target(t.as_deref()).map(str::to_string);
+                }
+            }
             match format {
                 Format::Json => {
                     let items: Vec<String> = results
@@ -113,14 +260,14 @@ pub fn run(cmd: &LinkCmd, conn: &mut Connection, format: Format) -> anyhow::Resu
                             )
                         })
                         .collect();
-                    println!("[{}]", items.join(","));
+                    writeln!(out, "[{}]", items.join(","))?;
                 }
                 Format::Text => {
                     for (src, dst, t) in results {
                         if let Some(t) = t {
-                            println!("{} → {} [type='{}']", src, dst, t);
+                            writeln!(out, "{} → {} [type='{}']", src, dst, t)?;
                         } else {
-                            println!("{} → {}", src, dst);
+                            writeln!(out, "{} → {}", src, dst)?;
                         }
                     }
                 }
@@ -140,20 +287,256 @@ pub fn run(cmd: &LinkCmd, conn: &mut Connection, format: Format) -> anyhow::Resu
                             format!("{{\"from\":\"{}\",\"type\":{}}}", src, typ)
                         })
                         .collect();
-                    println!("[{}]", items.join(","));
+                    writeln!(out, "[{}]", items.join(","))?;
                 }
                 Format::Text => {
                     for (src, t) in results {
                         if let Some(t) = t {
-                            println!("{} [type='{}']", src, t);
+                            writeln!(out, "{} [type='{}']", src, t)?;
                         } else {
-                            println!("{}", src);
+                            writeln!(out, "{}", src)?;
                         }
                     }
                 }
             }
         }
+        LinkCmd::Import(args) => import(args, conn, out, format)?,
+        LinkCmd::Type(cmd) => link_type(cmd, conn, out, format)?,
+        LinkCmd::Attr(LinkAttrCmd::Set(args)) => {
+            let link = find(conn, &args.from, &args.to, args.r#type.as_deref())?;
+            db::set_link_attr(conn, link, &args.key, &args.value)?;
+            if matches!(format, Format::Text) {
+                writeln!(
+                    out,
+                    "Set {}={} on '{}' → '{}'",
+                    args.key, args.value, args.from, args.to
+                )?;
+            }
+        }
+        LinkCmd::Attr(LinkAttrCmd::Rm(args)) => {
+            let link = find(conn, &args.from, &args.to, args.r#type.as_deref())?;
+            if !db::remove_link_attr(conn, link, &args.key)? {
+                anyhow::bail!(
+                    "no `{}` on the link '{}' → '{}'",
+                    args.key,
+                    args.from,
+                    args.to
+                );
+            }
+            if matches!(format, Format::Text) {
+                writeln!(
+                    out,
+                    "Removed {} from '{}' → '{}'",
+                    args.key, args.from, args.to
+                )?;
+            }
+        }
+        LinkCmd::Export(args) => {
+            let results = db::link_details(conn, &args.pattern, None, args.r#type.as_deref())?;
+            let types = links::LinkTypes::load(conn)?;
+            let edges = links::graph_edges(results, &types);
+            match format {
+                Format::Text => write_dot(out, &edges)?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    writeln!(out, "{}", edges_json(&edges, &types))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn import(
+    args: &ImportArgs,
+    conn: &mut Connection,
+    out: &mut dyn Write,
+    format: Format,
+) -> anyhow::Result<()> {
+    let pairs = if args.file.as_os_str() == "-" {
+        if args.conflicts.interactive {
+            anyhow::bail!("--interactive reads its answers from stdin; give the pairs as a file");
+        }
+        links::read_pairs(std::io::stdin().lock())?
+    } else {
+        let file =
+            File::open(&args.file).with_context(|| format!("opening {}", args.file.display()))?;
+        links::read_pairs(file).with_context(|| format!("reading {}", args.file.display()))?
+    };
+    let report = links::import_links_with(
+        conn,
+        &pairs,
+        args.r#type.as_deref(),
+        args.conflicts.on_conflict(),
+    )?;
+    match format {
+        Format::Text => {
+            writeln!(
+                out,
+                "Added {} link(s); {} already there, {} row(s) skipped",
+                report.added,
+                report.existing,
+                report.skipped.len()
+            )?;
+            if report.conflicts > 0 {
+                writeln!(out, "Settled {} conflict(s)", report.conflicts)?;
+            }
+            for s in &report.skipped {
+                writeln!(out, "  line {}: {}", s.line, s.reason)?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let skipped: Vec<_> = report
+                    .skipped
+                    .iter()
+                    .map(|s| serde_json::json!({ "line": s.line, "reason": s.reason }))
+                    .collect();
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "added": report.added,
+                        "existing": report.existing,
+                        "conflicts": report.conflicts,
+                        "skipped": skipped,
+                    })
+                )?;
+            }
+        }
     }
+    Ok(())
+}
 
+/// The link from `from` to `to`, both resolved like `describe` does.
+fn find(conn: &Connection, from: &str, to: &str, link_type: Option<&str>) -> anyhow::Result<i64> {
+    let (src, _) = describe::resolve(conn, from)?;
+    let (dst, _) = describe::resolve(conn, to)?;
+    db::find_link(conn, src, dst, link_type)
+}
+
+/// One edge per relation (see [`links::graph_edges`]); the type is the
+/// edge label and every attribute an edge attribute, all quoted.
+fn write_dot(out: &mut dyn Write, edges: &[links::Edge]) -> anyhow::Result<()> {
+    fn quote(s: &str) -> String {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    writeln!(out, "digraph marlin {{")?;
+    for e in edges {
+        let mut attrs: Vec<String> = e
+            .link_type
+            .iter()
+            .map(|t| format!("label={}", quote(t)))
+            .collect();
+        if e.symmetric {
+            attrs.push("dir=none".into());
+        }
+        attrs.extend(
+            e.attrs
+                .iter()
+                .map(|(k, v)| format!("{}={}", quote(k), quote(v))),
+        );
+        write!(out, "  {} -> {}", quote(&e.from), quote(&e.to))?;
+        if !attrs.is_empty() {
+            write!(out, " [{}]", attrs.join(", "))?;
+        }
+        writeln!(out, ";")?;
+    }
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn links_json(links: &[db::LinkInfo]) -> serde_json::Value {
+    links
+        .iter()
+        .map(|l| {
+            let attrs: serde_json::Map<_, _> = l
+                .attrs
+                .iter()
+                .map(|(k, v)| (k.clone(), serde_json::Value::from(v.as_str())))
+                .collect();
+            serde_json::json!({
+                "from": l.path,
+                "to": l.other,
+                "type": l.link_type,
+                "attrs": attrs,
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "json")]
+fn edges_json(edges: &[links::Edge], types: &links::LinkTypes) -> serde_json::Value {
+    edges
+        .iter()
+        .map(|e| {
+            let attrs: serde_json::Map<_, _> = e
+                .attrs
+                .iter()
+                .map(|(k, v)| (k.clone(), serde_json::Value::from(v.as_str())))
+                .collect();
+            serde_json::json!({
+                "from": e.from,
+                "to": e.to,
+                "type": e.link_type,
+                "inverse": e.link_type.as_deref().and_then(|t| types.inverse_of(t)),
+                "attrs": attrs,
+            })
+        })
+        .collect()
+}
+
+fn link_type(
+    cmd: &TypeCmd,
+    conn: &Connection,
+    out: &mut dyn Write,
+    format: Format,
+) -> anyhow::Result<()> {
+    match cmd {
+        TypeCmd::Add { name, inverse } => {
+            links::add_type(conn, name, inverse.as_deref())?;
+            if matches!(format, Format::Text) {
+                match inverse {
+                    Some(inv) => writeln!(out, "Registered link type '{name}' (inverse '{inv}')")?,
+                    None => writeln!(out, "Registered link type '{name}'")?,
+                }
+            }
+        }
+        TypeCmd::List => {
+            let types = links::LinkTypes::load(conn)?;
+            match format {
+                Format::Text => {
+                    for t in &types.0 {
+                        match &t.inverse {
+                            Some(inv) => writeln!(out, "{} ↔ {}", t.name, inv)?,
+                            None => writeln!(out, "{}", t.name)?,
+                        }
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let items: Vec<_> = types
+                            .0
+                            .iter()
+                            .map(|t| serde_json::json!({ "name": t.name, "inverse": t.inverse }))
+                            .collect();
+                        writeln!(out, "{}", serde_json::Value::from(items))?;
+                    }
+                }
+            }
+        }
+        TypeCmd::Rm { name } => {
+            if !links::remove_type(conn, name)? {
+                anyhow::bail!("link type `{name}` is not registered");
+            }
+            if matches!(format, Format::Text) {
+                writeln!(out, "Removed link type '{name}'")?;
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,82 @@
+//! `marlin lock` / `marlin unlock` – freeze the metadata of archived files.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::cli::{Context, Format};
+use libmarlin::lock;
+
+#[derive(Args, Debug)]
+pub struct LockArgs {
+    /// Glob or path pattern of the files to lock
+    #[arg(required_unless_present = "list")]
+    pub pattern: Option<String>,
+    /// List the locked files instead
+    #[arg(long, conflicts_with = "pattern")]
+    pub list: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct UnlockArgs {
+    /// Glob or path pattern of the files to unlock
+    pub pattern: String,
+}
+
+pub fn run_lock(a: &LockArgs, ctx: &mut Context) -> Result<()> {
+    let Some(pattern) = a.pattern.as_deref().filter(|_| !a.list) else {
+        return list(ctx);
+    };
+    let n = ctx.marlin.lock(pattern)?;
+    report(ctx, true, n)
+}
+
+pub fn run_unlock(a: &UnlockArgs, ctx: &mut Context) -> Result<()> {
+    let n = ctx.marlin.unlock(&a.pattern)?;
+    report(ctx, false, n)
+}
+
+fn report(ctx: &mut Context, locked: bool, n: usize) -> Result<()> {
+    match ctx.format {
+        Format::Text => {
+            let verb = if locked { "Locked" } else { "Unlocked" };
+            writeln!(ctx.output, "{verb} {n} file(s)")?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(ctx.output, "{{\"locked\":{locked},\"updated\":{n}}}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn list(ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (conn, out) = ctx.split();
+    let files = lock::locked_files(&conn)?;
+    match fmt {
+        Format::Text => {
+            for (path, locked_at) in &files {
+                let when = time.epoch(*locked_at).unwrap_or_default();
+                writeln!(out, "{when}  {path}")?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = files
+                    .iter()
+                    .map(|(path, locked_at)| {
+                        serde_json::json!({ "path": path, "locked_at": locked_at })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+            }
+        }
+    }
+    Ok(())
+}
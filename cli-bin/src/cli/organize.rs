@@ -0,0 +1,143 @@
+//! `marlin organize` – move, copy or link files into directories derived
+//! from their tags and attributes.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::organize::{self, Mode, OnConflict, Outcome, Template};
+use libmarlin::query::{self, SearchOptions};
+
+#[derive(Args, Debug)]
+pub struct OrganizeArgs {
+    /// Directory template, e.g. `tag:client` or `{attr:year}/{tag:client}`
+    #[arg(long, value_name = "TEMPLATE")]
+    pub by: String,
+    /// Root of the new layout
+    #[arg(long, value_name = "DIR")]
+    pub into: PathBuf,
+    /// Only organize files matching this search query
+    #[arg(long, short)]
+    pub query: Option<String>,
+    /// How files get there
+    #[arg(long, value_enum, default_value_t = OrganizeMode::Move)]
+    pub mode: OrganizeMode,
+    /// What to do when the destination already exists
+    #[arg(long, value_enum, default_value_t = ConflictArg::Skip)]
+    pub on_conflict: ConflictArg,
+    /// Print the plan without touching any file
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrganizeMode {
+    /// Rename into place; the index follows
+    Move,
+    Copy,
+    /// Hard link
+    Link,
+    Symlink,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictArg {
+    /// Leave the file where it is
+    Skip,
+    /// Add ` (1)`, ` (2)` … to the name
+    Rename,
+    /// Replace the existing file
+    Overwrite,
+}
+
+pub fn run(a: &OrganizeArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let template = Template::parse(&a.by)?;
+    let into = shellexpand::tilde(&a.into.to_string_lossy()).into_owned();
+    let files: Vec<(i64, String)> = match &a.query {
+        Some(q) => query::search_hits(conn, q, &SearchOptions::default())?
+            .into_iter()
+            .map(|h| (h.file_id, h.path))
+            .collect(),
+        None => {
+            let mut stmt = conn.prepare("SELECT id, path FROM files ORDER BY path")?;
+            let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+            rows.collect::<Result<_, _>>()?
+        }
+    };
+    let on_conflict = match a.on_conflict {
+        ConflictArg::Skip => OnConflict::Skip,
+        ConflictArg::Rename => OnConflict::Rename,
+        ConflictArg::Overwrite => OnConflict::Overwrite,
+    };
+    let mode = match a.mode {
+        OrganizeMode::Move => Mode::Move,
+        OrganizeMode::Copy => Mode::Copy,
+        OrganizeMode::Link => Mode::Link,
+        OrganizeMode::Symlink => Mode::Symlink,
+    };
+
+    let plan = organize::plan(conn, &files, &template, into.as_ref(), on_conflict)?;
+    let placed = if a.dry_run {
+        plan.iter().filter(|p| p.outcome == Outcome::Place).count()
+    } else {
+        organize::apply(conn, &plan, mode)?
+    };
+    let count = |o: Outcome| plan.iter().filter(|p| p.outcome == o).count();
+
+    match fmt {
+        Format::Text => {
+            for p in &plan {
+                let to = p.to.as_deref().map(|t| t.display().to_string());
+                match (p.outcome, to) {
+                    (Outcome::Place, Some(to)) => writeln!(out, "{} -> {to}", p.from.display())?,
+                    (Outcome::Conflict, Some(to)) => {
+                        writeln!(out, "skip {} ({to} exists)", p.from.display())?
+                    }
+                    _ => {}
+                }
+            }
+            let verb = if a.dry_run { "Would place" } else { "Placed" };
+            writeln!(
+                out,
+                "{verb} {placed} file(s); {} conflict(s), {} without a value",
+                count(Outcome::Conflict),
+                count(Outcome::NoValue)
+            )?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = plan
+                    .iter()
+                    .filter(|p| matches!(p.outcome, Outcome::Place | Outcome::Conflict))
+                    .map(|p| {
+                        serde_json::json!({
+                            "from": p.from,
+                            "to": p.to,
+                            "conflict": p.outcome == Outcome::Conflict,
+                        })
+                    })
+                    .collect();
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "dry_run": a.dry_run,
+                        "placed": placed,
+                        "conflicts": count(Outcome::Conflict),
+                        "no_value": count(Outcome::NoValue),
+                        "files": rows,
+                    })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
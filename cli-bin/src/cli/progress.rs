@@ -0,0 +1,61 @@
+//! `--events` / `--events-fd` – a machine-readable progress stream for GUI
+//! wrappers, separate from human output (stdout) and logs.
+//!
+//! Every [`libmarlin::events::Event`] becomes one JSON line; the last line
+//! is always `command_finished`.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+
+use libmarlin::events;
+
+/// Wire format of the event stream.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum EventsFormat {
+    /// Newline-delimited JSON
+    Json,
+}
+
+/// Install the event sink requested on the command line.  Returns whether
+/// events are enabled.
+pub fn install(format: Option<EventsFormat>, fd: Option<i32>) -> Result<bool> {
+    let out: Box<dyn Write + Send> = match (format, fd) {
+        (_, Some(fd)) => Box::new(open_fd(fd)?),
+        (Some(EventsFormat::Json), None) => Box::new(io::stderr()),
+        (None, None) => return Ok(false),
+    };
+    let out = Mutex::new(out);
+    events::set_sink(Some(Box::new(move |e: &events::Event| {
+        if let Ok(mut w) = out.lock() {
+            // a closed pipe on the GUI side must not abort the command
+            let _ = writeln!(w, "{}", e.to_json()).and_then(|_| w.flush());
+        }
+    })));
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn open_fd(fd: i32) -> Result<File> {
+    use std::os::fd::FromRawFd;
+
+    if fd <= 2 {
+        bail!("--events-fd {fd}: use --events json for stderr; 0–2 are reserved");
+    }
+    // SAFETY: the caller promised an open descriptor; it is verified below
+    // and owned by the process for its whole remaining lifetime.
+    let file = unsafe { File::from_raw_fd(fd) };
+    if let Err(e) = file.metadata() {
+        std::mem::forget(file); // not ours to close
+        return Err(e).with_context(|| format!("--events-fd {fd} is not an open descriptor"));
+    }
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn open_fd(fd: i32) -> Result<File> {
+    bail!("--events-fd {fd} is only supported on Unix; use --events json")
+}
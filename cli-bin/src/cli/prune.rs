@@ -0,0 +1,123 @@
+//! `marlin prune` – drop indexed files that are gone from disk, after
+//! showing what metadata goes with them.
+
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Args;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::prune::{self, Cascade};
+use libmarlin::vfs::StdFs;
+
+#[derive(Args, Debug)]
+pub struct PruneArgs {
+    /// Directories (or files) to check; defaults to the current directory
+    pub paths: Vec<PathBuf>,
+    /// Keep missing files as tombstones, with all their metadata, until
+    /// a scan finds them again
+    #[arg(long)]
+    pub keep_metadata: bool,
+    /// Only report what would be pruned
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Do not ask before deleting files that have metadata
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+pub fn run(a: &PruneArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let cwd = std::env::current_dir()?;
+    let roots: Vec<PathBuf> = if a.paths.is_empty() {
+        vec![cwd]
+    } else {
+        a.paths.iter().map(|p| cwd.join(p)).collect()
+    };
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+
+    // tombstones are only candidates when they are to be deleted
+    let missing = prune::missing_files(conn, &StdFs, &roots, !a.keep_metadata)?;
+    let ids: Vec<i64> = missing.iter().map(|(id, _)| *id).collect();
+    let impact = prune::cascade(conn, &ids)?;
+    if let Format::Text = fmt {
+        report(out, &missing, &impact)?;
+    }
+
+    let action = if missing.is_empty() || a.dry_run {
+        "none"
+    } else if a.keep_metadata {
+        prune::drop_files(conn, &ids, true)?;
+        "kept"
+    } else if !impact.has_metadata() || a.yes || confirm(missing.len())? {
+        prune::drop_files(conn, &ids, false)?;
+        "deleted"
+    } else {
+        "none"
+    };
+
+    match fmt {
+        Format::Text => match action {
+            "kept" => writeln!(out, "Kept {} file(s) as tombstones", missing.len())?,
+            "deleted" => writeln!(out, "Pruned {} file(s)", missing.len())?,
+            _ if !missing.is_empty() && !a.dry_run => writeln!(out, "Nothing pruned")?,
+            _ => {}
+        },
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let mut counts = serde_json::Map::new();
+                for (name, n) in impact.counts() {
+                    counts.insert(name.into(), n.into());
+                }
+                let paths: Vec<&str> = missing.iter().map(|(_, p)| p.as_str()).collect();
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "missing": paths,
+                        "metadata": counts,
+                        "action": action,
+                    })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn report(out: &mut dyn Write, missing: &[(i64, String)], impact: &Cascade) -> Result<()> {
+    if missing.is_empty() {
+        writeln!(out, "No missing files")?;
+        return Ok(());
+    }
+    writeln!(out, "{} file(s) missing on disk:", missing.len())?;
+    for (_, path) in missing {
+        writeln!(out, "  {path}")?;
+    }
+    let counts: Vec<String> = impact
+        .counts()
+        .iter()
+        .map(|(name, n)| format!("{name}: {n}"))
+        .collect();
+    writeln!(out, "Metadata on them: {}", counts.join(", "))?;
+    Ok(())
+}
+
+/// Ask on the terminal; without one, refuse rather than guess.
+fn confirm(files: usize) -> Result<bool> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        bail!(
+            "the missing files still have metadata; pass --yes to delete it, \
+             or --keep-metadata to keep them as tombstones"
+        );
+    }
+    eprint!("Delete {files} file(s) and their metadata? [y/N] ");
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
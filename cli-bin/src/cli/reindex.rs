@@ -0,0 +1,58 @@
+//! `marlin reindex` – force a metadata and hash refresh of specific files,
+//! ignoring what the index already holds.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db;
+use libmarlin::query::{self, SearchOptions};
+use libmarlin::scan;
+
+#[derive(Args, Debug)]
+pub struct ReindexArgs {
+    /// Glob matched against indexed paths
+    #[arg(required_unless_present = "query")]
+    pub pattern: Option<String>,
+    /// Select files with a search query instead of a pattern
+    #[arg(long, short, conflicts_with = "pattern")]
+    pub query: Option<String>,
+}
+
+pub fn run(a: &ReindexArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let paths: Vec<String> = match (&a.query, &a.pattern) {
+        (Some(q), _) => query::search_hits(conn, q, &SearchOptions::default())?
+            .into_iter()
+            .map(|h| h.path)
+            .collect(),
+        (None, Some(p)) => db::match_files(conn, p)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect(),
+        (None, None) => unreachable!("clap requires a pattern or --query"),
+    };
+    let reindexed = scan::reindex_files(conn, &paths)?;
+    let missing = paths.len().saturating_sub(reindexed);
+
+    match fmt {
+        Format::Text => {
+            writeln!(out, "Reindexed {reindexed} file(s)")?;
+            if missing > 0 {
+                writeln!(out, "{missing} file(s) missing on disk")?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(out, "{{\"reindexed\":{reindexed},\"missing\":{missing}}}")?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,22 +1,181 @@
 // src/cli/remind.rs
-use crate::cli::Format;
+use std::io::Write;
+
+use crate::cli::{Context, Format};
+use anyhow::bail;
+use chrono::Utc;
 use clap::{Args, Subcommand};
-use rusqlite::Connection;
+use libmarlin::db::{self, Reminder};
+use libmarlin::notify::{self, Notification, Notifier};
+use libmarlin::utils::parse_when;
 
 #[derive(Subcommand, Debug)]
 pub enum RemindCmd {
-    Set(ArgsSet),
+    /// Remind about files matching a glob at a time
+    Add(ArgsAdd),
+    /// Reminders due before a time (now by default)
+    Due(ArgsDue),
+    /// All reminders, or those on files matching a glob
+    List(ArgsList),
+    /// Send a notification through the configured channels
+    /// (`notify.desktop`, `notify.command`)
+    Test(ArgsTest),
 }
 
 #[derive(Args, Debug)]
-pub struct ArgsSet {
+pub struct ArgsAdd {
     pub file_pattern: String,
-    pub timestamp: String,
+    /// `2h`, `3d`, or a local YYYY-MM-DD[THH:MM]
+    pub when: String,
+    pub note: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsDue {
+    /// `now`, `2h`, `3d`, or a local YYYY-MM-DD[THH:MM]
+    #[arg(long, default_value = "now")]
+    pub before: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsList {
+    pub file_pattern: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsTest {
+    /// Text of the test notification
+    #[arg(long, default_value = "Reminders reach you this way.")]
     pub message: String,
 }
 
-pub fn run(cmd: &RemindCmd, _conn: &mut Connection, _format: Format) -> anyhow::Result<()> {
+pub fn run(cmd: &RemindCmd, ctx: &mut Context) -> anyhow::Result<()> {
     match cmd {
-        RemindCmd::Set(a) => todo!("remind set {:?}", a),
+        RemindCmd::Add(a) => add(a, ctx),
+        RemindCmd::Due(a) => {
+            let before = parse_when(&a.before, Utc::now().timestamp())?;
+            let due = db::due_reminders(&ctx.marlin.conn(), before)?;
+            print_reminders(&due, ctx)
+        }
+        RemindCmd::List(a) => {
+            let all = {
+                let conn = ctx.marlin.conn();
+                match &a.file_pattern {
+                    None => db::list_reminders(&conn, None)?,
+                    Some(p) => {
+                        let mut all = Vec::new();
+                        for (fid, _) in db::match_files(&conn, p)? {
+                            all.extend(db::list_reminders(&conn, Some(fid))?);
+                        }
+                        all.sort_by_key(|r| (r.due_at, r.id));
+                        all
+                    }
+                }
+            };
+            print_reminders(&all, ctx)
+        }
+        RemindCmd::Test(a) => test(a, ctx),
+    }
+}
+
+fn add(a: &ArgsAdd, ctx: &mut Context) -> anyhow::Result<()> {
+    let due_at = parse_when(&a.when, Utc::now().timestamp())?;
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (conn, out) = ctx.split();
+    let files = db::match_files(&conn, &a.file_pattern)?;
+    if files.is_empty() {
+        bail!("no indexed file matches `{}`", a.file_pattern);
+    }
+    let mut ids = Vec::new();
+    for (fid, _) in &files {
+        ids.push(db::add_reminder(&conn, *fid, due_at, &a.note)?);
+    }
+    match fmt {
+        Format::Text => {
+            let when = time.epoch(due_at).unwrap_or_default();
+            for (id, (_, path)) in ids.iter().zip(&files) {
+                writeln!(out, "Reminder #{id} for {path} at {when}")?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({ "added": ids, "due_at": due_at })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_reminders(list: &[Reminder], ctx: &mut Context) -> anyhow::Result<()> {
+    let time = &ctx.time;
+    match ctx.format {
+        Format::Text => {
+            for r in list {
+                let when = time.epoch(r.due_at).unwrap_or_default();
+                let sent = if r.notified_at.is_some() {
+                    "  (notified)"
+                } else {
+                    ""
+                };
+                writeln!(
+                    ctx.output,
+                    "#{:<4} {when}  {}  ({}){sent}",
+                    r.id, r.note, r.path
+                )?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = list
+                    .iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "id": r.id,
+                            "path": r.path,
+                            "due_at": r.due_at,
+                            "note": r.note,
+                            "notified_at": r.notified_at,
+                        })
+                    })
+                    .collect();
+                writeln!(ctx.output, "{}", serde_json::to_string(&rows)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn test(a: &ArgsTest, ctx: &mut Context) -> anyhow::Result<()> {
+    let notifier = Notifier::from_settings(&ctx.marlin.conn())?;
+    if !notifier.is_enabled() {
+        bail!(
+            "no notification channel configured; set `{} true` or `{} <command>`",
+            notify::DESKTOP_KEY,
+            notify::COMMAND_KEY
+        );
+    }
+    notifier.send(&Notification {
+        title: "Marlin test reminder".into(),
+        body: a.message.clone(),
+        path: None,
+        due_at: None,
+    })?;
+    let channels = notifier.channels();
+    match ctx.format {
+        Format::Text => writeln!(ctx.output, "Sent via {}", channels.join(", "))?,
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(ctx.output, "{}", serde_json::json!({ "sent": channels }))?;
+            }
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,124 @@
+//! `marlin root …` – per-root indexing profiles (ignore globs, content
+//! indexing, hashing, watcher debounce).
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::roots::{self, RootProfile};
+
+#[derive(Subcommand, Debug)]
+pub enum RootCmd {
+    /// Register a root (or replace its profile)
+    Add(AddArgs),
+    /// List registered roots and their settings
+    List,
+    /// Forget a root's profile (indexed files stay)
+    Rm { path: PathBuf },
+}
+
+#[derive(Args, Debug)]
+pub struct AddArgs {
+    /// Root directory
+    pub path: PathBuf,
+    /// Glob to skip, relative to the root (repeatable), e.g. `target`
+    #[arg(long = "ignore", value_name = "GLOB")]
+    pub ignore: Vec<String>,
+    /// Never read file bodies below this root when searching
+    #[arg(long)]
+    pub no_content: bool,
+    /// Store a SHA-256 of every file during `marlin scan`
+    #[arg(long)]
+    pub hash: bool,
+    /// Debounce window for `marlin watch` on this root
+    #[arg(long)]
+    pub debounce_ms: Option<u64>,
+}
+
+/// Roots are stored by canonical path so scan/watch can match them.
+fn canonical_dir(path: &Path) -> Result<PathBuf> {
+    let p = path.canonicalize()?;
+    if !p.is_dir() {
+        bail!("{} is not a directory", p.display());
+    }
+    Ok(p)
+}
+
+pub fn run(cmd: &RootCmd, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        /* ── root add ─────────────────────────────────────────────── */
+        RootCmd::Add(a) => {
+            let profile = RootProfile {
+                path: canonical_dir(&a.path)?,
+                ignore: a.ignore.clone(),
+                index_content: !a.no_content,
+                hash: a.hash,
+                debounce_ms: a.debounce_ms,
+            };
+            roots::save(conn, &profile)?;
+            if matches!(fmt, Format::Text) {
+                writeln!(out, "Saved profile for {}", profile.path.display())?;
+            }
+        }
+
+        /* ── root list ────────────────────────────────────────────── */
+        RootCmd::List => {
+            let all = roots::list(conn)?;
+            match fmt {
+                Format::Text => {
+                    for r in &all {
+                        writeln!(out, "{}", r.path.display())?;
+                        writeln!(
+                            out,
+                            "  content: {}  hash: {}  debounce: {}",
+                            if r.index_content { "on" } else { "off" },
+                            if r.hash { "on" } else { "off" },
+                            r.debounce_ms
+                                .map_or_else(|| "default".to_string(), |d| format!("{d}ms")),
+                        )?;
+                        if !r.ignore.is_empty() {
+                            writeln!(out, "  ignore:  {}", r.ignore.join(", "))?;
+                        }
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let json: Vec<_> = all
+                            .iter()
+                            .map(|r| {
+                                serde_json::json!({
+                                    "path": r.path,
+                                    "ignore": r.ignore,
+                                    "index_content": r.index_content,
+                                    "hash": r.hash,
+                                    "debounce_ms": r.debounce_ms,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&json)?)?;
+                    }
+                }
+            }
+        }
+
+        /* ── root rm ──────────────────────────────────────────────── */
+        RootCmd::Rm { path } => {
+            let p = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if !roots::remove(conn, &p)? {
+                bail!("no profile registered for {}", p.display());
+            }
+            if matches!(fmt, Format::Text) {
+                writeln!(out, "Removed profile for {}", p.display())?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! `marlin run` – execute a script of Marlin commands in one process,
+//! optionally as a single all-or-nothing transaction.
+//!
+//! A script has one command per line, written as on the command line
+//! without the leading `marlin` (it is accepted and skipped if present).
+//! Blank lines and lines starting with `#` are ignored:
+//!
+//! ```text
+//! # retag last year's drafts
+//! tag "~/docs/2024/*.md" archive/2024
+//! attr set "~/docs/2024/*.md" status archived
+//! ```
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser};
+use tracing::{info, warn};
+
+use crate::cli::{Cli, Commands};
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Script file (`-` reads standard input)
+    pub script: PathBuf,
+    /// Run every command inside one transaction: if any fails, none of
+    /// the changes are kept
+    #[arg(long)]
+    pub atomic: bool,
+}
+
+/// One parsed script line.
+#[derive(Debug)]
+pub struct ScriptCommand {
+    pub line: usize,
+    pub text: String,
+    pub command: Commands,
+}
+
+/// Parse a script, rejecting commands that cannot run inside a script
+/// (anything that replaces the DB file, runs forever or nests scripts).
+/// Every line is checked before anything runs.
+pub fn parse_script(src: &str) -> Result<Vec<ScriptCommand>> {
+    let mut out = Vec::new();
+    for (idx, raw) in src.lines().enumerate() {
+        let line = idx + 1;
+        let text = raw.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let command = parse_line(text).map_err(|e| anyhow::anyhow!("line {line}: {e}"))?;
+
+        let name = match &command {
+            Commands::Init => Some("init"),
+            Commands::Backup(_) => Some("backup"),
+            Commands::Restore { .. } => Some("restore"),
+            Commands::Watch(_) => Some("watch"),
+            Commands::Completions { .. } => Some("completions"),
+            Commands::Run(_) => Some("run"),
+            Commands::Shell(_) => Some("shell"),
+            _ => None,
+        };
+        if let Some(name) = name {
+            bail!("line {line}: `{name}` cannot be used in a script");
+        }
+        out.push(ScriptCommand {
+            line,
+            text: text.to_string(),
+            command,
+        });
+    }
+    Ok(out)
+}
+
+/// Parse one command written as on the command line, with or without the
+/// leading `marlin`.  Global flags such as `--format` are accepted but
+/// the caller's settings win.
+pub fn parse_line(text: &str) -> Result<Commands> {
+    let mut words = shlex::split(text).context("unbalanced quotes")?;
+    if words.first().is_some_and(|w| w == "marlin") {
+        words.remove(0);
+    }
+    let cli = Cli::try_parse_from(std::iter::once("marlin".to_string()).chain(words))
+        .map_err(|e| anyhow::anyhow!("{}", e.to_string().trim_end()))?;
+    Ok(cli.command)
+}
+
+/// Run the script in `args`, handing each command to `exec`.
+pub fn run<F>(args: &RunArgs, ctx: &mut super::Context, mut exec: F) -> Result<()>
+where
+    F: FnMut(Commands, &mut super::Context) -> Result<()>,
+{
+    let src = if args.script.as_os_str() == "-" {
+        let mut s = String::new();
+        std::io::stdin().read_to_string(&mut s)?;
+        s
+    } else {
+        std::fs::read_to_string(&args.script)
+            .with_context(|| format!("reading script {}", args.script.display()))?
+    };
+    let script = parse_script(&src)?;
+
+    if args.atomic {
+        ctx.marlin.conn().execute_batch("BEGIN IMMEDIATE")?;
+    }
+    for cmd in script {
+        info!(line = cmd.line, "running `{}`", cmd.text);
+        if let Err(e) = exec(cmd.command, ctx) {
+            if args.atomic {
+                ctx.marlin.conn().execute_batch("ROLLBACK")?;
+                warn!("script failed, all changes rolled back");
+            }
+            return Err(e.context(format!("line {}: `{}` failed", cmd.line, cmd.text)));
+        }
+    }
+    if args.atomic {
+        ctx.marlin.conn().execute_batch("COMMIT")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,379 @@
+//! `marlin search` – run a query against one index, or several at once.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Args;
+use rusqlite::Connection;
+use tracing::{debug, info};
+
+use crate::cli::embed::EmbedderArg;
+use crate::cli::{query_cache, run_capture, run_exec, Context, Format, PathStyle, RelativeTo};
+use libmarlin::query::{self, Query, QueryKind, SearchOptions};
+use libmarlin::timefmt::{TimeFormatter, Zone};
+use libmarlin::{db, embed};
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    #[arg(required_unless_present = "last")]
+    pub query: Option<String>,
+    /// Re-run the most recent search
+    #[arg(long, conflicts_with = "query")]
+    pub last: bool,
+    /// Run a command per hit; `{path}`, `{dir}`, `{name}`, `{stem}`,
+    /// `{ext}`, `{tags}` and `{attr:key}` are filled in, shell-quoted
+    #[arg(long)]
+    pub exec: Option<String>,
+    /// Run a command per hit (placeholders as for `--exec`) and store
+    /// its trimmed output in each file's metadata (see `--into`)
+    #[arg(long, value_name = "CMD", requires = "into", conflicts_with = "exec")]
+    pub exec_capture: Option<String>,
+    /// Where `--exec-capture` output goes: `attr:<key>`
+    #[arg(long, value_name = "attr:KEY", requires = "exec_capture")]
+    pub into: Option<String>,
+    /// Only validate the query and report problems; nothing is run
+    #[arg(long, conflicts_with_all = ["exec", "exec_capture"])]
+    pub check: bool,
+    /// Search for the input as one phrase, ignoring query syntax
+    #[arg(long, conflicts_with = "check")]
+    pub literal: bool,
+    /// Print paths relative to the current directory, or with
+    /// `=workspace` to the workspace root
+    #[arg(long, value_enum, value_name = "BASE", num_args = 0..=1,
+          require_equals = true, default_missing_value = "cwd")]
+    pub relative: Option<RelativeTo>,
+    /// Also match `tag:` filters against the tags of this Marlin
+    /// database, attached read-only (repeatable)
+    #[arg(long, value_name = "DB")]
+    pub attach: Vec<PathBuf>,
+    /// Also show when each file was first indexed and last seen by a
+    /// scan
+    #[arg(long, conflicts_with_all = ["exec", "exec_capture"])]
+    pub long: bool,
+    /// Rank files by similarity of meaning to the query (see `marlin
+    /// embed`); `--long` shows the scores
+    #[arg(long, conflicts_with_all = ["check", "literal", "attach"])]
+    pub semantic: bool,
+    /// Number of `--semantic` hits
+    #[arg(long, default_value_t = 20, requires = "semantic")]
+    pub limit: usize,
+    #[command(flatten)]
+    pub embedder: EmbedderArg,
+}
+
+impl SearchArgs {
+    fn kind(&self) -> QueryKind {
+        if self.literal {
+            QueryKind::Literal
+        } else {
+            QueryKind::Marlin
+        }
+    }
+
+    /// The attribute `--into` names.
+    fn capture_key(&self) -> Result<&str> {
+        let into = self.into.as_deref().unwrap_or_default();
+        match into.strip_prefix("attr:") {
+            Some(key) if !key.is_empty() => Ok(key),
+            _ => bail!("--into takes `attr:<key>`, not `{into}`"),
+        }
+    }
+}
+
+/// `--exec-capture`: store each hit's command output, then report.
+fn capture(conn: &Connection, paths: &[String], a: &SearchArgs, cmd_tpl: &str) -> Result<()> {
+    let key = a.capture_key()?;
+    let n = run_capture(conn, paths, cmd_tpl, key)?;
+    info!(
+        "Attribute '{key}' captured on {n} of {} file(s).",
+        paths.len()
+    );
+    Ok(())
+}
+
+pub fn run(a: &SearchArgs, ctx: &mut Context) -> Result<()> {
+    if a.exec_capture.is_some() {
+        a.capture_key()?;
+    }
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (conn, out) = ctx.split();
+    let query = match &a.query {
+        Some(q) if !a.last => q.clone(),
+        _ => db::history_query(&conn, None)?,
+    };
+    if a.check {
+        return check_search(out, &conn, &query, fmt);
+    }
+    let style = PathStyle::new(a.relative)?;
+    if a.semantic {
+        drop(conn);
+        return run_semantic(ctx, &query, a, &style);
+    }
+    with_attached(&conn, &a.attach, |conn| {
+        run_search(out, conn, &query, a, &style, &time)
+    })
+}
+
+fn run_search(
+    out: &mut impl Write,
+    conn: &Connection,
+    raw_query: &str,
+    a: &SearchArgs,
+    style: &PathStyle,
+    time: &TimeFormatter,
+) -> Result<()> {
+    let kind = a.kind();
+    let q = Query::parse_as(raw_query, kind)?;
+    debug!("FTS MATCH expression: {}", q.fts);
+
+    let opts = SearchOptions {
+        kind,
+        ..Default::default()
+    };
+    let hits = query_cache().search_hits(conn, raw_query, &opts)?;
+    db::record_search(conn, raw_query, hits.len())?;
+
+    if let Some(cmd_tpl) = &a.exec {
+        let paths: Vec<String> = hits.into_iter().map(|h| h.path).collect();
+        run_exec(conn, &paths, cmd_tpl)?;
+    } else if let Some(cmd_tpl) = &a.exec_capture {
+        let paths: Vec<String> = hits.into_iter().map(|h| h.path).collect();
+        capture(conn, &paths, a, cmd_tpl)?;
+    } else if hits.is_empty() {
+        eprintln!(
+            "No matches for query: `{raw_query}` (FTS expr: `{}`)",
+            q.fts
+        );
+    } else {
+        for h in &hits {
+            if a.long {
+                writeln!(
+                    out,
+                    "{}  {}",
+                    seen_columns(conn, h.file_id, time)?,
+                    style.show(&h.path)
+                )?;
+            } else {
+                writeln!(out, "{}", style.show(&h.path))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `search --semantic`: the closest embeddings, best first.
+fn run_semantic(ctx: &mut Context, text: &str, a: &SearchArgs, style: &PathStyle) -> Result<()> {
+    let embedder = a.embedder.build()?;
+    let hits = ctx
+        .marlin
+        .search_semantic(embedder.as_ref(), text, a.limit)?;
+    if hits.is_empty() {
+        if embed::count(&ctx.marlin.conn(), embedder.model())? == 0 {
+            bail!(
+                "no embeddings for model `{}` yet; run `marlin embed` first",
+                embedder.model()
+            );
+        }
+        eprintln!("No semantic matches for `{text}`");
+        return Ok(());
+    }
+    if let Some(cmd_tpl) = &a.exec {
+        let paths: Vec<String> = hits.into_iter().map(|h| h.path).collect();
+        return run_exec(&ctx.marlin.conn(), &paths, cmd_tpl);
+    }
+    if let Some(cmd_tpl) = &a.exec_capture {
+        let paths: Vec<String> = hits.into_iter().map(|h| h.path).collect();
+        return capture(&ctx.marlin.conn(), &paths, a, cmd_tpl);
+    }
+    let out = &mut ctx.output;
+    for h in &hits {
+        if a.long {
+            writeln!(out, "{:.3}  {}", h.score, style.show(&h.path))?;
+        } else {
+            writeln!(out, "{}", style.show(&h.path))?;
+        }
+    }
+    Ok(())
+}
+
+/// `search --long` columns: first indexed and last seen, `-` where
+/// unknown.
+fn seen_columns(conn: &Connection, file_id: i64, time: &TimeFormatter) -> Result<String> {
+    let (added, seen) = db::seen_times(conn, file_id)?;
+    let show = |t: Option<i64>| match t.and_then(|t| time.epoch(t)) {
+        Some(t) => t,
+        None => format!("{:<w$}", "-", w = time.width()),
+    };
+    Ok(format!("{}  {}", show(added), show(seen)))
+}
+
+/// Run `f` with every database in `paths` attached read-only as a shared
+/// tag source; they are detached again afterwards, even on error, so a
+/// `marlin run` script does not keep them for later commands.
+fn with_attached<T>(
+    conn: &Connection,
+    paths: &[PathBuf],
+    f: impl FnOnce(&Connection) -> Result<T>,
+) -> Result<T> {
+    let mut names = Vec::new();
+    let mut result = Ok(());
+    for p in paths {
+        match db::attach_shared(conn, p) {
+            Ok(name) => names.push(name),
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+    let out = result.and_then(|()| f(conn));
+    for name in names {
+        db::detach_shared(conn, &name)?;
+    }
+    out
+}
+
+/// `marlin --db a.db --db b.db search …`: one merged hit list, each path
+/// prefixed with the label of its database.  Runs before (and instead
+/// of) opening a single index, so there is no [`Context`] yet; times
+/// follow the settings of the first database.
+pub fn run_multi(db_paths: &[PathBuf], a: &SearchArgs, format: Format, utc: bool) -> Result<()> {
+    if a.exec_capture.is_some() {
+        bail!("--exec-capture writes to the index; search one --db at a time");
+    }
+    let out = &mut std::io::stdout();
+    let group = libmarlin::Marlin::open_many(db_paths)?;
+    let (_, first) = &group.members()[0];
+    let mut time = TimeFormatter::load(&first.conn())?;
+    if utc {
+        time = time.with_zone(Zone::Utc);
+    }
+    let query = match &a.query {
+        Some(q) if !a.last => q.clone(),
+        _ => db::history_query(&first.conn(), None)?,
+    };
+    if a.check {
+        return check_search(out, &first.conn(), &query, format);
+    }
+    if a.semantic {
+        bail!("--semantic searches one database at a time");
+    }
+
+    let opts = SearchOptions {
+        kind: a.kind(),
+        ..Default::default()
+    };
+    for (_, m) in group.members() {
+        for p in &a.attach {
+            db::attach_shared(&m.conn(), p)?;
+        }
+    }
+    let hits = group.search_hits(&query, &opts)?;
+    for (label, m) in group.members() {
+        let n = hits.iter().filter(|h| &h.source == label).count();
+        db::record_search(&m.conn(), &query, n)?;
+    }
+
+    let style = PathStyle::new(a.relative)?;
+    if let Some(cmd_tpl) = &a.exec {
+        if hits.is_empty() {
+            return run_exec(&first.conn(), &[], cmd_tpl);
+        }
+        // each hit reads its tags and attributes from its own database
+        for h in hits {
+            let (_, m) = group
+                .members()
+                .iter()
+                .find(|(label, _)| *label == h.source)
+                .expect("hit from a member");
+            run_exec(&m.conn(), &[h.hit.path], cmd_tpl)?;
+        }
+        return Ok(());
+    }
+    if hits.is_empty() {
+        eprintln!(
+            "No matches for query: `{query}` in {} databases",
+            db_paths.len()
+        );
+        return Ok(());
+    }
+    match format {
+        Format::Text => {
+            for h in &hits {
+                if a.long {
+                    let (_, m) = group
+                        .members()
+                        .iter()
+                        .find(|(label, _)| *label == h.source)
+                        .expect("hit from a member");
+                    let cols = seen_columns(&m.conn(), h.hit.file_id, &time)?;
+                    writeln!(out, "[{}] {cols}  {}", h.source, style.show(&h.hit.path))?;
+                } else {
+                    writeln!(out, "[{}] {}", h.source, style.show(&h.hit.path))?;
+                }
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = hits
+                    .iter()
+                    .map(|h| {
+                        serde_json::json!({
+                            "source": h.source,
+                            "path": style.show(&h.hit.path),
+                            "rank": h.rank,
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `marlin search --check`: print every diagnostic and fail on errors.
+fn check_search(
+    out: &mut impl Write,
+    conn: &Connection,
+    raw_query: &str,
+    format: Format,
+) -> Result<()> {
+    let diags = query::check(conn, raw_query)?;
+    match format {
+        Format::Text => {
+            for d in &diags {
+                let level = if d.is_error() { "error" } else { "warning" };
+                writeln!(out, "{level}: {d}")?;
+            }
+            if diags.is_empty() {
+                writeln!(out, "Query OK")?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = diags
+                    .iter()
+                    .map(|d| {
+                        serde_json::json!({
+                            "severity": if d.is_error() { "error" } else { "warning" },
+                            "column": d.column,
+                            "message": d.message,
+                            "hint": d.hint,
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+            }
+        }
+    }
+    let errors = diags.iter().filter(|d| d.is_error()).count();
+    if errors > 0 {
+        bail!("query has {errors} error(s)");
+    }
+    Ok(())
+}
@@ -0,0 +1,289 @@
+//! `marlin shell` – an interactive prompt on one open index.
+//!
+//! Every line is a Marlin command written as on the command line, without
+//! the leading `marlin`; anything that does not start with a command name
+//! is run as a search, so `tag:draft ext:md` on its own works.  The index
+//! is opened (and migrated, and backed up) once for the whole session.
+//!
+//! While typing, a query shows its first problem or its hit count as a
+//! hint after the cursor; Tab completes command names, tag paths (after
+//! `tag:` or as the tag of `tag PATTERN TAG`) and file names.  History is
+//! kept across sessions (see [`history_path`]).
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Args, CommandFactory};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Editor, Helper};
+use tracing::warn;
+
+use crate::cli::{run, Cli, Commands, Context};
+use libmarlin::query::{self, ResultSet};
+use libmarlin::{db, Marlin};
+
+#[derive(Args, Debug)]
+pub struct ShellArgs {
+    /// Neither read nor write the history file
+    #[arg(long)]
+    pub no_history: bool,
+}
+
+/// Where the prompt history lives: `MARLIN_SHELL_HISTORY`, else
+/// `shell_history` in the per-user state directory.
+pub fn history_path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os("MARLIN_SHELL_HISTORY") {
+        return Some(PathBuf::from(p));
+    }
+    let dirs = directories::ProjectDirs::from("io", "Marlin", "marlin")?;
+    Some(
+        dirs.state_dir()
+            .unwrap_or(dirs.data_dir())
+            .join("shell_history"),
+    )
+}
+
+/// Read commands until `exit`, `quit` or end of input, handing each to
+/// `exec`.  A failing command is reported and the session goes on.
+pub fn run<F>(args: &ShellArgs, ctx: &mut Context, mut exec: F) -> Result<()>
+where
+    F: FnMut(Commands, &mut Context) -> Result<()>,
+{
+    let mut editor: Editor<ShellHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ShellHelper::new(ctx.marlin.share())));
+
+    let history = if args.no_history {
+        None
+    } else {
+        history_path()
+    };
+    if let Some(p) = &history {
+        if p.exists() {
+            if let Err(e) = editor.load_history(p) {
+                warn!(path = %p.display(), error = %e, "could not read shell history");
+            }
+        }
+    }
+
+    loop {
+        if let Some(h) = editor.helper_mut() {
+            h.refresh();
+        }
+        let line = match editor.readline("marlin> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        editor.add_history_entry(text)?;
+        if matches!(text, "exit" | "quit") {
+            break;
+        }
+
+        let command = match parse(text) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{e}");
+                continue;
+            }
+        };
+        if let Some(name) = refused(&command) {
+            eprintln!("error: `{name}` is not available in the shell");
+            continue;
+        }
+        if let Err(e) = exec(command, ctx) {
+            eprintln!("error: {e:#}");
+        }
+    }
+
+    if let Some(p) = &history {
+        if let Some(dir) = p.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        if let Err(e) = editor.save_history(p) {
+            warn!(path = %p.display(), error = %e, "could not write shell history");
+        }
+    }
+    Ok(())
+}
+
+/// A command line, or a search for lines that do not start with a
+/// command name.
+fn parse(text: &str) -> Result<Commands> {
+    match as_query(text) {
+        Some(q) => {
+            let quoted = shlex::try_quote(q).map_err(|e| anyhow::anyhow!("{e}"))?;
+            run::parse_line(&format!("search {quoted}"))
+        }
+        None => run::parse_line(text),
+    }
+}
+
+/// The query of a bare search line.
+fn as_query(text: &str) -> Option<&str> {
+    let first = text.split_whitespace().next()?;
+    let first = if first == "marlin" {
+        text.split_whitespace().nth(1)?
+    } else {
+        first
+    };
+    let is_command = first.starts_with('-')
+        || first == "help"
+        || Cli::command().find_subcommand(first).is_some();
+    (!is_command).then_some(text)
+}
+
+/// Commands that replace the open index, run until interrupted or nest
+/// another prompt.
+fn refused(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::Shell(_) => Some("shell"),
+        Commands::Completions { .. } => Some("completions"),
+        Commands::Restore { to: None, .. } => Some("restore (without --to)"),
+        Commands::Watch(w) if w.needs_db() => Some("watch start/daemon"),
+        _ => None,
+    }
+}
+
+/* ── completion and hints ────────────────────────────────────────── */
+
+struct ShellHelper {
+    marlin: Marlin,
+    files: FilenameCompleter,
+    commands: Vec<String>,
+    tags: RefCell<Vec<String>>,
+    /// The hits behind the last hint, narrowed as the query grows.
+    last: RefCell<Option<ResultSet>>,
+}
+
+impl ShellHelper {
+    fn new(marlin: Marlin) -> Self {
+        let commands = Cli::command()
+            .get_subcommands()
+            .map(|c| c.get_name().to_string())
+            .chain(["exit".into(), "quit".into()])
+            .collect();
+        Self {
+            marlin,
+            files: FilenameCompleter::new(),
+            commands,
+            tags: RefCell::default(),
+            last: RefCell::default(),
+        }
+    }
+
+    /// Re-read the tag list; commands may have added tags.
+    fn refresh(&mut self) {
+        let tags = db::tag_paths(&self.marlin.conn()).unwrap_or_default();
+        *self.tags.get_mut() = tags;
+    }
+
+    fn tags_starting_with(&self, prefix: &str, lead: &str) -> Vec<Pair> {
+        self.tags
+            .borrow()
+            .iter()
+            .filter(|t| t.starts_with(prefix))
+            .map(|t| Pair {
+                display: t.clone(),
+                replacement: format!("{lead}{t}"),
+            })
+            .collect()
+    }
+
+    /// `  (12 hits)`, or the first problem of the query.
+    fn query_hint(&self, q: &str) -> Option<String> {
+        let conn = self.marlin.conn();
+        let diags = query::check(&conn, q).ok()?;
+        if let Some(d) = diags.iter().find(|d| d.is_error()) {
+            return Some(format!("  ✗ {d}"));
+        }
+        drop(conn);
+        let mut last = self.last.borrow_mut();
+        let hits = self.marlin.search_incremental(last.as_ref(), q).ok()?;
+        let n = hits.hits.len();
+        *last = Some(hits);
+        Some(match n {
+            0 => "  (no hits)".into(),
+            1 => "  (1 hit)".into(),
+            n => format!("  ({n} hits)"),
+        })
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &before[start..];
+        let index = before[..start].split_whitespace().count();
+
+        if index == 0 {
+            let hits: Vec<Pair> = self
+                .commands
+                .iter()
+                .filter(|c| c.starts_with(word))
+                .map(|c| Pair {
+                    display: c.clone(),
+                    replacement: format!("{c} "),
+                })
+                .collect();
+            if !hits.is_empty() {
+                return Ok((start, hits));
+            }
+        }
+        if let Some(prefix) = word.strip_prefix("tag:") {
+            return Ok((start, self.tags_starting_with(prefix, "tag:")));
+        }
+        let is_tag_arg = index == 2 && before.split_whitespace().next() == Some("tag");
+        if is_tag_arg {
+            return Ok((start, self.tags_starting_with(word, "")));
+        }
+        self.files.complete(line, pos, ctx)
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        let q = match line.trim_start().strip_prefix("search ") {
+            Some(q) => q.trim(),
+            None => as_query(line.trim())?,
+        };
+        if q.len() < 2 {
+            return None;
+        }
+        self.query_hint(q)
+    }
+}
+
+impl Highlighter for ShellHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[2m{hint}\x1b[0m"))
+    }
+}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
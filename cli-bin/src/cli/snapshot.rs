@@ -0,0 +1,156 @@
+//! `marlin snapshot …` – capture the tag/attribute/link state and review
+//! what changed between two captures.
+
+use std::io::Write;
+
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::snapshot::{self, Change};
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCmd {
+    /// Capture the current tags, attributes and links as NAME
+    Create(ArgsName),
+    /// List snapshots (oldest first)
+    List,
+    /// Metadata changes from snapshot A to B (default: the live index)
+    Diff(ArgsDiff),
+    /// Delete a snapshot
+    Rm(ArgsName),
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsName {
+    pub name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsDiff {
+    pub a: String,
+    pub b: Option<String>,
+}
+
+pub fn run(cmd: &SnapshotCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        /* ── snapshot create ──────────────────────────────────────── */
+        SnapshotCmd::Create(a) => {
+            let n = snapshot::create(conn, &a.name)?;
+            match fmt {
+                Format::Text => writeln!(out, "Snapshot '{}' captured {n} entr(ies)", a.name)?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({ "snapshot": a.name, "entries": n })
+                        )?;
+                    }
+                }
+            }
+        }
+
+        /* ── snapshot list ────────────────────────────────────────── */
+        SnapshotCmd::List => {
+            let snaps = snapshot::list(conn)?;
+            match fmt {
+                Format::Text => {
+                    for s in snaps {
+                        let when = time.epoch(s.created_at).unwrap_or_default();
+                        writeln!(out, "{:<20}  {when}  {:>6} entr(ies)", s.name, s.entries)?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = snaps
+                            .iter()
+                            .map(|s| {
+                                serde_json::json!({
+                                    "name": s.name,
+                                    "created_at": s.created_at,
+                                    "entries": s.entries,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+
+        /* ── snapshot diff ────────────────────────────────────────── */
+        SnapshotCmd::Diff(a) => {
+            let changes = snapshot::diff(conn, &a.a, a.b.as_deref())?;
+            match fmt {
+                Format::Text => {
+                    for c in &changes {
+                        writeln!(out, "{}", describe(c))?;
+                    }
+                    writeln!(out, "{} change(s)", changes.len())?;
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = changes
+                            .iter()
+                            .map(|c| {
+                                serde_json::json!({
+                                    "path": c.path,
+                                    "kind": c.kind.as_str(),
+                                    "key": c.key,
+                                    "old": c.old,
+                                    "new": c.new,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+
+        /* ── snapshot rm ──────────────────────────────────────────── */
+        SnapshotCmd::Rm(a) => {
+            if !snapshot::remove(conn, &a.name)? {
+                anyhow::bail!("snapshot not found: {}", a.name);
+            }
+            match fmt {
+                Format::Text => writeln!(out, "Removed snapshot '{}'", a.name)?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::json!({ "removed": a.name }))?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `+`/`-`/`~` line for one change, e.g. `~ /a.txt  attr  owner: ann -> bob`.
+/// One change as a `+`/`-`/`~` line (also used by `marlin db diff`).
+pub fn describe(c: &Change) -> String {
+    let (sign, detail) = match (&c.old, &c.new) {
+        (Some(o), Some(n)) => ('~', format!("{}: {o} -> {n}", c.key)),
+        (None, Some(v)) => ('+', with_value(&c.key, v, c.kind.as_str())),
+        (Some(v), None) => ('-', with_value(&c.key, v, c.kind.as_str())),
+        (None, None) => (' ', c.key.clone()),
+    };
+    format!("{sign} {}  {:<4}  {detail}", c.path, c.kind.as_str())
+}
+
+fn with_value(key: &str, value: &str, kind: &str) -> String {
+    match (kind, value.is_empty()) {
+        (_, true) => key.to_string(),
+        ("attr", false) => format!("{key}={value}"),
+        (_, false) => format!("{key} ({value})"),
+    }
+}
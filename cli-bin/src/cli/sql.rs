@@ -0,0 +1,166 @@
+//! `marlin sql` – run a raw SQL statement against the index.
+//!
+//! Statements must be read-only unless `--unsafe-write` is given; SQLite
+//! itself decides (`sqlite3_stmt_readonly`), so `PRAGMA` writes,
+//! `ATTACH` and friends are refused as well.
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use clap::Args;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+
+#[derive(Args, Debug)]
+pub struct SqlArgs {
+    /// A single SQL statement
+    pub query: String,
+    /// Allow statements that modify the database
+    #[arg(long)]
+    pub unsafe_write: bool,
+}
+
+pub fn run(a: &SqlArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let mut stmt = conn.prepare(&a.query)?;
+    if !stmt.readonly() && !a.unsafe_write {
+        bail!("statement modifies the database; pass --unsafe-write to run it anyway");
+    }
+
+    let cols: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    if cols.is_empty() {
+        let n = stmt.execute([])?;
+        if matches!(fmt, Format::Text) {
+            writeln!(out, "{n} row(s) changed")?;
+        }
+        return Ok(());
+    }
+
+    let mut rows: Vec<Vec<Cell>> = Vec::new();
+    let mut q = stmt.query([])?;
+    while let Some(r) = q.next()? {
+        rows.push(
+            (0..cols.len())
+                .map(|i| r.get_ref(i).map(Cell::from))
+                .collect::<rusqlite::Result<_>>()?,
+        );
+    }
+
+    match fmt {
+        Format::Text => print_table(out, &cols, &rows)?,
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let json: Vec<serde_json::Map<String, serde_json::Value>> = rows
+                    .iter()
+                    .map(|row| {
+                        cols.iter()
+                            .cloned()
+                            .zip(row.iter().map(Cell::to_json))
+                            .collect()
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string(&json)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An owned result value.
+enum Cell {
+    Null,
+    Int(i64),
+    Real(f64),
+    Text(String),
+    Blob(usize),
+}
+
+impl From<ValueRef<'_>> for Cell {
+    fn from(v: ValueRef<'_>) -> Self {
+        match v {
+            ValueRef::Null => Cell::Null,
+            ValueRef::Integer(i) => Cell::Int(i),
+            ValueRef::Real(f) => Cell::Real(f),
+            ValueRef::Text(t) => Cell::Text(String::from_utf8_lossy(t).into_owned()),
+            ValueRef::Blob(b) => Cell::Blob(b.len()),
+        }
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Null => f.write_str("NULL"),
+            Cell::Int(i) => write!(f, "{i}"),
+            Cell::Real(r) => write!(f, "{r}"),
+            Cell::Text(t) => f.write_str(t),
+            Cell::Blob(n) => write!(f, "<blob {n} bytes>"),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl Cell {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Cell::Null => serde_json::Value::Null,
+            Cell::Int(i) => (*i).into(),
+            Cell::Real(r) => (*r).into(),
+            Cell::Text(t) => t.clone().into(),
+            Cell::Blob(_) => self.to_string().into(),
+        }
+    }
+}
+
+/// Column-aligned output with a header row; numbers are right-aligned.
+fn print_table(out: &mut impl Write, cols: &[String], rows: &[Vec<Cell>]) -> Result<()> {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|r| {
+            r.iter()
+                .map(|c| c.to_string().replace('\n', "\\n"))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..cols.len())
+        .map(|i| {
+            cells
+                .iter()
+                .map(|r| r[i].chars().count())
+                .chain([cols[i].chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let header: Vec<String> = cols
+        .iter()
+        .zip(&widths)
+        .map(|(c, w)| format!("{c:<w$}"))
+        .collect();
+    writeln!(out, "{}", header.join("  ").trim_end())?;
+    for (row, raw) in cells.iter().zip(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(raw)
+            .zip(&widths)
+            .map(|((s, c), w)| match c {
+                Cell::Int(_) | Cell::Real(_) => format!("{s:>w$}"),
+                _ => format!("{s:<w$}"),
+            })
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    writeln!(
+        out,
+        "({} row{})",
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" }
+    )?;
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! `marlin stale` – indexed files nobody touched for a while, as input
+//! for archival and clean-up decisions.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use clap::Args;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db::{self, StaleFile};
+use libmarlin::query::{self, SearchOptions};
+use libmarlin::utils::{human_size, parse_duration, parse_size};
+
+#[derive(Args, Debug)]
+pub struct StaleArgs {
+    /// Only report files matching this query (e.g. `-tag:keep`)
+    #[arg(allow_hyphen_values = true)]
+    pub query: Option<String>,
+    /// Report files not modified within this window (e.g. 180d, 2y)
+    #[arg(long, value_name = "AGE")]
+    pub older_than: String,
+    /// Skip files smaller than this (e.g. 100M, 1.5G)
+    #[arg(long, value_name = "SIZE")]
+    pub min_size: Option<String>,
+    /// Use the on-disk access time instead of the indexed modification
+    /// time (only meaningful when the file system records atime)
+    #[arg(long)]
+    pub accessed: bool,
+}
+
+pub fn run(a: &StaleArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let cutoff = now.saturating_sub(parse_duration(&a.older_than)?).as_secs() as i64;
+    let min_size = match &a.min_size {
+        Some(s) => parse_size(s)?,
+        None => 0,
+    };
+
+    let mut files = if a.accessed {
+        let mut out: Vec<StaleFile> = db::stale_files(conn, i64::MAX, min_size)?
+            .into_iter()
+            .filter_map(|mut f| {
+                let atime = std::fs::metadata(&f.path).ok()?.accessed().ok()?;
+                f.mtime = atime.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+                (f.mtime < cutoff).then_some(f)
+            })
+            .collect();
+        out.sort_by(|x, y| x.mtime.cmp(&y.mtime).then(x.path.cmp(&y.path)));
+        out
+    } else {
+        db::stale_files(conn, cutoff, min_size)?
+    };
+
+    if let Some(q) = &a.query {
+        let hits: HashSet<i64> = query::search_hits(conn, q, &SearchOptions::default())?
+            .into_iter()
+            .map(|h| h.file_id)
+            .collect();
+        files.retain(|f| hits.contains(&f.id));
+    }
+
+    let now = now.as_secs() as i64;
+    match fmt {
+        Format::Text => {
+            let when = if a.accessed { "ACCESSED" } else { "MODIFIED" };
+            if files.is_empty() {
+                writeln!(out, "No stale files.")?;
+                return Ok(());
+            }
+            writeln!(out, "{when:>8}  {:>10}  PATH", "SIZE")?;
+            for f in &files {
+                writeln!(
+                    out,
+                    "{:>8}  {:>10}  {}",
+                    format_age(now - f.mtime),
+                    human_size(f.size),
+                    f.path
+                )?;
+            }
+            let total: i64 = files.iter().map(|f| f.size).sum();
+            writeln!(out, "{} file(s), {} total", files.len(), human_size(total))?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let json: Vec<_> = files
+                    .iter()
+                    .map(|f| {
+                        serde_json::json!({
+                            "path": f.path,
+                            "size": f.size,
+                            "time": f.mtime,
+                            "age_secs": now - f.mtime,
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string(&json)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `5h`, `40d`, `2.3y` – coarse age for the report column.
+fn format_age(secs: i64) -> String {
+    let days = secs.max(0) / 86_400;
+    match days {
+        0 => format!("{}h", secs.max(0) / 3_600),
+        1..=364 => format!("{days}d"),
+        _ => format!("{:.1}y", days as f64 / 365.0),
+    }
+}
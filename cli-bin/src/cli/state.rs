@@ -1,12 +1,20 @@
 // src/cli/state.rs
-use crate::cli::Format;
+//! `marlin state …` – workflow states on files and the moves allowed
+//! between them.
+
+use std::io::Write;
+
+use crate::cli::{Context, Format};
 use clap::{Args, Subcommand};
-use rusqlite::Connection;
+use libmarlin::db;
 
 #[derive(Subcommand, Debug)]
 pub enum StateCmd {
+    /// Move matching files to a state
     Set(ArgsSet),
+    /// Allow files to move from one state to another
     TransitionsAdd(ArgsTrans),
+    /// State history of matching files
     Log(ArgsLog),
 }
 
@@ -25,10 +33,79 @@ pub struct ArgsLog {
     pub file_pattern: String,
 }
 
-pub fn run(cmd: &StateCmd, _conn: &mut Connection, _format: Format) -> anyhow::Result<()> {
+pub fn run(cmd: &StateCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (conn, out) = ctx.split();
     match cmd {
-        StateCmd::Set(a) => todo!("state set {:?}", a),
-        StateCmd::TransitionsAdd(a) => todo!("state transitions-add {:?}", a),
-        StateCmd::Log(a) => todo!("state log {:?}", a),
+        StateCmd::Set(a) => {
+            let files = db::match_files(&conn, &a.file_pattern)?;
+            let n = db::set_file_states(&conn, &files, &a.new_state)?;
+            match fmt {
+                Format::Text => writeln!(out, "{n} file(s) moved to {}", a.new_state)?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({ "state": a.new_state, "updated": n })
+                        )?;
+                    }
+                }
+            }
+        }
+        StateCmd::TransitionsAdd(a) => {
+            let added = db::add_state_transition(&conn, &a.from_state, &a.to_state)?;
+            match fmt {
+                Format::Text => {
+                    let note = if added { "" } else { " (already allowed)" };
+                    writeln!(out, "{} → {}{note}", a.from_state, a.to_state)?;
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({
+                                "from": a.from_state,
+                                "to": a.to_state,
+                                "added": added,
+                            })
+                        )?;
+                    }
+                }
+            }
+        }
+        StateCmd::Log(a) => {
+            let files = db::match_files(&conn, &a.file_pattern)?;
+            let log = db::state_log(&conn, &files)?;
+            match fmt {
+                Format::Text => {
+                    for c in &log {
+                        let when = time.epoch(c.changed_at).unwrap_or_default();
+                        writeln!(out, "{when}  {:<12}  {}", c.state, c.path)?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = log
+                            .iter()
+                            .map(|c| {
+                                serde_json::json!({
+                                    "path": c.path,
+                                    "state": c.state,
+                                    "changed_at": c.changed_at,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
     }
+    Ok(())
 }
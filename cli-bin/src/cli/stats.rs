@@ -0,0 +1,124 @@
+//! `marlin stats …` – file counts, sizes and recent growth per tag or
+//! directory.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db::{self, UsageStats};
+use libmarlin::utils::{human_size, parse_duration};
+
+#[derive(Subcommand, Debug)]
+pub enum StatsCmd {
+    /// Usage per tag (full tag path)
+    Tags(TagsArgs),
+    /// Usage per directory below a root
+    Dirs(DirsArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct TagsArgs {
+    /// Window for the “recent” columns (e.g. 7d, 12h, 1y)
+    #[arg(long, default_value = "30d")]
+    pub since: String,
+}
+
+#[derive(Args, Debug)]
+pub struct DirsArgs {
+    /// Directory to break down (defaults to the current directory)
+    pub root: Option<PathBuf>,
+    /// How many directory levels to group by
+    #[arg(long, default_value_t = 1)]
+    pub depth: usize,
+    /// Window for the “recent” columns (e.g. 7d, 12h, 1y)
+    #[arg(long, default_value = "30d")]
+    pub since: String,
+}
+
+/// Unix timestamp `window` ago (e.g. `30d` → 30 days before now).
+fn cutoff(window: &str) -> Result<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    Ok(now.saturating_sub(parse_duration(window)?).as_secs() as i64)
+}
+
+pub fn run(cmd: &StatsCmd, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        /* ── stats tags ───────────────────────────────────────────── */
+        StatsCmd::Tags(a) => {
+            let rows = db::tag_stats(conn, cutoff(&a.since)?)?;
+            print_stats(out, "TAG", &a.since, &rows, fmt)
+        }
+
+        /* ── stats dirs ───────────────────────────────────────────── */
+        StatsCmd::Dirs(a) => {
+            let root = match &a.root {
+                Some(p) => p.clone(),
+                None => std::env::current_dir()?,
+            };
+            let root = root.canonicalize().unwrap_or(root);
+            let rows = db::dir_stats(conn, &root, a.depth, cutoff(&a.since)?)?;
+            print_stats(out, "DIRECTORY", &a.since, &rows, fmt)
+        }
+    }
+}
+
+fn print_stats(
+    out: &mut impl Write,
+    label: &str,
+    since: &str,
+    rows: &[UsageStats],
+    fmt: Format,
+) -> Result<()> {
+    match fmt {
+        Format::Text => {
+            if rows.is_empty() {
+                writeln!(out, "No indexed files.")?;
+                return Ok(());
+            }
+            let recent = format!("NEW ({since})");
+            writeln!(
+                out,
+                "{:>10}  {:>7}  {:>18}  {label}",
+                "SIZE", "FILES", recent
+            )?;
+            for r in rows {
+                let growth = format!("+{} / +{}", r.recent_files, human_size(r.recent_size));
+                writeln!(
+                    out,
+                    "{:>10}  {:>7}  {:>18}  {}",
+                    human_size(r.total_size),
+                    r.files,
+                    growth,
+                    r.name
+                )?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let json: Vec<_> = rows
+                    .iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "name": r.name,
+                            "files": r.files,
+                            "total_size": r.total_size,
+                            "recent_files": r.recent_files,
+                            "recent_size": r.recent_size,
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string(&json)?)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! `marlin status` – where the index lives and what is in it.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::cli::{query_cache, Context, Format};
+use libmarlin::db;
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    /// Also show the query result cache counters of this process
+    #[arg(long)]
+    pub verbose: bool,
+}
+
+pub fn run(a: &StatusArgs, ctx: &mut Context) -> Result<()> {
+    let (cfg, fmt) = (&ctx.cfg, ctx.format);
+    let conn = ctx.marlin.conn();
+    let out = &mut ctx.output;
+    let info = db::schema_info(&conn)?;
+    let count = |sql: &str| -> Result<i64> { Ok(conn.query_row(sql, [], |r| r.get(0))?) };
+    let files = count("SELECT COUNT(*) FROM files")?;
+    let tags = count("SELECT COUNT(*) FROM tags")?;
+    let tuning = db::tuning(&conn)?;
+    let cache = query_cache().stats();
+
+    match fmt {
+        Format::Text => {
+            writeln!(out, "Database: {}", cfg.db_path.display())?;
+            writeln!(
+                out,
+                "Schema:   v{} (library expects v{})",
+                info.current_version, info.expected_version
+            )?;
+            writeln!(out, "Tuning:   {tuning}")?;
+            writeln!(out, "Files:    {files}")?;
+            writeln!(out, "Tags:     {tags}")?;
+            if a.verbose {
+                writeln!(
+                    out,
+                    "Query cache: {} hit(s), {} miss(es), {}/{} entries, {} eviction(s)",
+                    cache.hits, cache.misses, cache.entries, cache.capacity, cache.evictions
+                )?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let mut obj = serde_json::json!({
+                    "db_path": cfg.db_path,
+                    "schema_version": info.current_version,
+                    "expected_version": info.expected_version,
+                    "tuning": tuning.as_str(),
+                    "files": files,
+                    "tags": tags,
+                });
+                if a.verbose {
+                    obj["query_cache"] = serde_json::json!({
+                        "hits": cache.hits,
+                        "misses": cache.misses,
+                        "entries": cache.entries,
+                        "capacity": cache.capacity,
+                        "evictions": cache.evictions,
+                    });
+                }
+                writeln!(out, "{obj}")?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,267 @@
+//! `marlin sync …` – replicate metadata through op-log files.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use clap::{Args, Subcommand};
+
+use crate::cli::conflicts::ConflictArgs;
+use crate::cli::{Context, Format};
+use libmarlin::conflicts::Resolution;
+use libmarlin::sync;
+
+#[derive(Subcommand, Debug)]
+pub enum SyncCmd {
+    /// Write the metadata changes after a cursor as op-log lines
+    Export(ArgsExport),
+    /// Apply op-logs written by other replicas
+    Import(ArgsImport),
+    /// Replica id and op-log size
+    Status,
+    /// How import conflicts were settled, newest first
+    Conflicts {
+        /// Show at most N decisions
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsExport {
+    /// Cursor printed by the previous export (default: everything, or
+    /// where the last export to `--out` stopped)
+    #[arg(long)]
+    pub since: Option<i64>,
+    /// Append to this log file instead of printing to stdout
+    #[arg(long, short)]
+    pub out: Option<PathBuf>,
+    /// Only files below DIR, with paths relative to it
+    #[arg(long, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsImport {
+    /// Log files of other replicas
+    #[arg(required = true)]
+    pub logs: Vec<PathBuf>,
+    /// Resolve relative paths in the logs against DIR
+    #[arg(long, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+    #[command(flatten)]
+    pub conflicts: ConflictArgs,
+}
+
+/// Comment line ending each export to a file, so the next one resumes.
+const CURSOR_PREFIX: &str = "# cursor ";
+
+pub fn run(cmd: &SyncCmd, ctx: &mut Context) -> Result<()> {
+    match cmd {
+        SyncCmd::Export(a) => export(a, ctx),
+        SyncCmd::Import(a) => import(a, ctx),
+        SyncCmd::Status => status(ctx),
+        SyncCmd::Conflicts { limit } => conflicts(*limit, ctx),
+    }
+}
+
+fn export(a: &ArgsExport, ctx: &mut Context) -> Result<()> {
+    let root = a.root.as_deref().map(canonical).transpose()?;
+    let since = match (a.since, &a.out) {
+        (Some(n), _) => n,
+        (None, Some(out)) => last_cursor(out)?.unwrap_or(0),
+        (None, None) => 0,
+    };
+    let fmt = ctx.format;
+    let (conn, out) = ctx.split();
+    let export = sync::export(&conn, since, root.as_deref())?;
+
+    let Some(path) = &a.out else {
+        sync::write_ops(out, &export.ops)?;
+        eprintln!(
+            "Exported {} op(s); next cursor {}",
+            export.ops.len(),
+            export.cursor
+        );
+        return Ok(());
+    };
+    let is_new = !path.exists() || std::fs::metadata(path)?.len() == 0;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    if is_new {
+        writeln!(file, "{}", sync::LOG_HEADER)?;
+    }
+    sync::write_ops(&mut file, &export.ops)?;
+    writeln!(file, "{CURSOR_PREFIX}{}", export.cursor)?;
+    file.sync_all()?;
+
+    match fmt {
+        Format::Text => writeln!(
+            out,
+            "Exported {} op(s) to {}; next cursor {}",
+            export.ops.len(),
+            path.display(),
+            export.cursor
+        )?,
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({ "exported": export.ops.len(), "cursor": export.cursor })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Where the last export to `path` stopped, if it exists.
+fn last_cursor(path: &Path) -> Result<Option<i64>> {
+    let Ok(file) = File::open(path) else {
+        return Ok(None);
+    };
+    let mut cursor = None;
+    for line in BufReader::new(file).lines() {
+        if let Some(n) = line?.strip_prefix(CURSOR_PREFIX) {
+            cursor = Some(n.trim().parse()?);
+        }
+    }
+    Ok(cursor)
+}
+
+fn import(a: &ArgsImport, ctx: &mut Context) -> Result<()> {
+    let root = a.root.as_deref().map(canonical).transpose()?;
+    let mut ops = Vec::new();
+    for p in &a.logs {
+        let file = File::open(p).with_context(|| format!("opening {}", p.display()))?;
+        ops.extend(
+            sync::read_ops(BufReader::new(file))
+                .with_context(|| format!("reading {}", p.display()))?,
+        );
+    }
+    let report = {
+        let mut conn = ctx.marlin.conn();
+        sync::import_with(&mut conn, &ops, root.as_deref(), a.conflicts.on_conflict())?
+    };
+    let out = &mut ctx.output;
+    match ctx.format {
+        Format::Text => {
+            writeln!(
+                out,
+                "Applied {} op(s) ({} superseded, {} already seen, {} for files not indexed, {} rejected)",
+                report.applied, report.superseded, report.duplicate, report.missing, report.rejected
+            )?;
+            if report.conflicts > 0 {
+                writeln!(out, "Settled {} conflict(s)", report.conflicts)?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "applied": report.applied,
+                        "superseded": report.superseded,
+                        "duplicate": report.duplicate,
+                        "missing": report.missing,
+                        "rejected": report.rejected,
+                        "conflicts": report.conflicts,
+                    })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn status(ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (conn, out) = ctx.split();
+    let replica = sync::replica_id(&conn)?;
+    let (ops, cursor) = sync::log_stats(&conn)?;
+    match fmt {
+        Format::Text => {
+            writeln!(out, "replica  {replica}")?;
+            writeln!(out, "ops      {ops}")?;
+            writeln!(out, "cursor   {cursor}")?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({ "replica": replica, "ops": ops, "cursor": cursor })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn conflicts(limit: usize, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (conn, out) = ctx.split();
+    let decisions = libmarlin::conflicts::decisions(&conn, limit)?;
+    match fmt {
+        Format::Text => {
+            for d in &decisions {
+                let c = &d.conflict;
+                let choice = match &d.resolution {
+                    Resolution::Mine => "mine".to_string(),
+                    Resolution::Theirs => "theirs".to_string(),
+                    Resolution::Both => "both".to_string(),
+                    Resolution::Edit(v) => format!("edit `{v}`"),
+                };
+                writeln!(
+                    out,
+                    "{}\t{}\t{} {} `{}`: mine `{}`, theirs `{}` -> {choice}",
+                    d.at, d.source, c.path, c.kind, c.name, c.mine, c.theirs
+                )?;
+            }
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = decisions
+                    .iter()
+                    .map(|d| {
+                        let (choice, value) = match &d.resolution {
+                            Resolution::Mine => ("mine", None),
+                            Resolution::Theirs => ("theirs", None),
+                            Resolution::Both => ("both", None),
+                            Resolution::Edit(v) => ("edit", Some(v)),
+                        };
+                        serde_json::json!({
+                            "at": d.at,
+                            "source": d.source,
+                            "kind": d.conflict.kind.as_str(),
+                            "path": d.conflict.path,
+                            "name": d.conflict.name,
+                            "mine": d.conflict.mine,
+                            "theirs": d.conflict.theirs,
+                            "choice": choice,
+                            "value": value,
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::Value::from(rows))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Roots are compared with indexed paths, which are canonical.
+fn canonical(p: &Path) -> Result<PathBuf> {
+    p.canonicalize()
+        .with_context(|| format!("root {} does not exist", p.display()))
+}
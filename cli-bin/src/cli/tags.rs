@@ -0,0 +1,37 @@
+//! `marlin tags …` – housekeeping for the tag hierarchy.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Subcommand;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db;
+
+#[derive(Subcommand, Debug)]
+pub enum TagsCmd {
+    /// Delete tags that no file carries any more
+    PruneUnused,
+}
+
+pub fn run(cmd: &TagsCmd, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        TagsCmd::PruneUnused => {
+            let pruned = db::prune_unused_tags(conn)?;
+            match fmt {
+                Format::Text => writeln!(out, "Pruned {pruned} unused tag(s)")?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{{\"pruned\":{pruned}}}")?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
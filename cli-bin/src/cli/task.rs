@@ -1,27 +1,193 @@
 // src/cli/task.rs
-use crate::cli::Format;
+//! `marlin task …` – to-dos attached to files.
+
+use std::io::Write;
+
+use crate::cli::{Context, Format};
+use anyhow::bail;
+use chrono::{Local, Utc};
 use clap::{Args, Subcommand};
-use rusqlite::Connection;
+use libmarlin::db::{self, TaskFilter, TaskStatus};
+use libmarlin::query;
 
 #[derive(Subcommand, Debug)]
 pub enum TaskCmd {
-    Scan(ArgsScan),
+    /// Add a task to files matching a glob
+    Add(ArgsAdd),
+    /// List tasks (open ones unless --status or --all says otherwise)
     List(ArgsList),
+    /// Mark a task done
+    Done(ArgsId),
+    /// Delete a task
+    Rm(ArgsId),
 }
 
 #[derive(Args, Debug)]
-pub struct ArgsScan {
-    pub directory: String,
+pub struct ArgsAdd {
+    pub file_pattern: String,
+    pub title: String,
+    /// Due date, YYYY-MM-DD[THH:MM] in local time
+    #[arg(long)]
+    pub due: Option<String>,
+    /// Higher comes first
+    #[arg(long, default_value_t = 0)]
+    pub priority: i64,
 }
+
 #[derive(Args, Debug)]
 pub struct ArgsList {
+    /// open or done
+    #[arg(long, conflicts_with = "all")]
+    pub status: Option<TaskStatus>,
+    /// Open and done tasks
+    #[arg(long)]
+    pub all: bool,
+    /// Only tasks due by the end of today
     #[arg(long)]
     pub due_today: bool,
+    /// Only tasks on files matching this glob
+    #[arg(long)]
+    pub file: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsId {
+    pub id: i64,
 }
 
-pub fn run(cmd: &TaskCmd, _conn: &mut Connection, _format: Format) -> anyhow::Result<()> {
+pub fn run(cmd: &TaskCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (conn, out) = ctx.split();
     match cmd {
-        TaskCmd::Scan(a) => todo!("task scan {:?}", a),
-        TaskCmd::List(a) => todo!("task list {:?}", a),
+        TaskCmd::Add(a) => {
+            let due = a
+                .due
+                .as_deref()
+                .map(|d| query::time_span(d).map(|(start, _)| start))
+                .transpose()?;
+            let files = db::match_files(&conn, &a.file_pattern)?;
+            if files.is_empty() {
+                bail!("no indexed file matches `{}`", a.file_pattern);
+            }
+            let mut ids = Vec::new();
+            for (fid, _) in &files {
+                ids.push(db::add_task(&conn, *fid, &a.title, due, a.priority)?);
+            }
+            match fmt {
+                Format::Text => {
+                    for (id, (_, path)) in ids.iter().zip(&files) {
+                        writeln!(out, "Added task #{id} to {path}")?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::json!({ "added": ids }))?;
+                    }
+                }
+            }
+        }
+        TaskCmd::List(a) => {
+            let due_before = if a.due_today {
+                let today = Local::now().format("%Y-%m-%d").to_string();
+                Some(query::time_span(&today)?.1)
+            } else {
+                None
+            };
+            let file_ids = a
+                .file
+                .as_deref()
+                .map(|p| db::match_files(&conn, p))
+                .transpose()?
+                .map(|files| files.into_iter().map(|(id, _)| id).collect());
+            let status = match (a.status, a.all) {
+                (Some(s), _) => Some(s),
+                (None, true) => None,
+                (None, false) => Some(TaskStatus::Open),
+            };
+            let tasks = db::list_tasks(
+                &conn,
+                &TaskFilter {
+                    status,
+                    due_before,
+                    file_ids,
+                },
+            )?;
+            match fmt {
+                Format::Text => {
+                    for t in &tasks {
+                        let mark = if t.status == TaskStatus::Done {
+                            'x'
+                        } else {
+                            ' '
+                        };
+                        let due = t.due_at.and_then(|d| time.epoch(d)).unwrap_or_default();
+                        writeln!(
+                            out,
+                            "#{:<4} [{mark}] {due:<w$}  p{:<3} {}  ({})",
+                            t.id,
+                            t.priority,
+                            t.title,
+                            t.path,
+                            w = time.width()
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = tasks
+                            .iter()
+                            .map(|t| {
+                                serde_json::json!({
+                                    "id": t.id,
+                                    "path": t.path,
+                                    "title": t.title,
+                                    "status": t.status.as_str(),
+                                    "due_at": t.due_at,
+                                    "priority": t.priority,
+                                    "created_at": t.created_at,
+                                    "done_at": t.done_at,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+        TaskCmd::Done(a) => {
+            let changed = db::complete_task(&conn, a.id, Utc::now().timestamp())?;
+            match fmt {
+                Format::Text => {
+                    let note = if changed { "" } else { " (already done)" };
+                    writeln!(out, "Task #{} done{note}", a.id)?;
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(
+                            out,
+                            "{}",
+                            serde_json::json!({ "done": a.id, "changed": changed })
+                        )?;
+                    }
+                }
+            }
+        }
+        TaskCmd::Rm(a) => {
+            db::remove_task(&conn, a.id)?;
+            match fmt {
+                Format::Text => writeln!(out, "Removed task #{}", a.id)?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::json!({ "removed": a.id }))?;
+                    }
+                }
+            }
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,46 @@
+//! `--timings` – wall-clock time per phase of a command (config load,
+//! auto-backup, DB open and migrations, the command itself), printed to
+//! stderr so it never mixes with command output.
+
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+/// Phase timer; [`Timings::mark`] closes the phase that is running.
+#[derive(Debug)]
+pub struct Timings {
+    start: Instant,
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last: now,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Record everything since the previous mark as `phase`.
+    pub fn mark(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+
+    /// One aligned line per phase plus the total.
+    pub fn summary(&self) -> String {
+        let mut out = String::from("timings:\n");
+        let rows = self
+            .phases
+            .iter()
+            .copied()
+            .chain([("total", self.last - self.start)]);
+        for (phase, d) in rows {
+            let _ = writeln!(out, "  {phase:<12} {:>9.1} ms", d.as_secs_f64() * 1000.0);
+        }
+        out
+    }
+}
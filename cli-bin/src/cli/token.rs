@@ -0,0 +1,104 @@
+//! `marlin token …` – scoped tokens for the watcher's control channel.
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::timefmt::TimeFormatter;
+use libmarlin::tokens::{self, Scope};
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCmd {
+    /// Create a token and print its secret (shown only once)
+    Create {
+        /// Name to list and revoke the token by
+        name: String,
+        /// `read`, `tag:<namespace>`, `write` or `admin` (repeatable)
+        #[arg(long = "scope", value_name = "SCOPE", required = true)]
+        scopes: Vec<Scope>,
+    },
+    /// List tokens and their scopes
+    List,
+    /// Delete a token; requests using it are refused from then on
+    Revoke { name: String },
+}
+
+fn when(ts: Option<i64>, time: &TimeFormatter) -> String {
+    ts.and_then(|t| time.epoch(t))
+        .unwrap_or_else(|| "never".into())
+}
+
+pub fn run(cmd: &TokenCmd, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    match cmd {
+        TokenCmd::Create { name, scopes } => {
+            let secret = tokens::create(conn, name, scopes)?;
+            match fmt {
+                Format::Text => {
+                    writeln!(out, "{secret}")?;
+                    eprintln!("Store this secret now; it cannot be shown again.");
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    writeln!(
+                        out,
+                        "{}",
+                        serde_json::json!({ "name": name, "secret": secret })
+                    )?;
+                }
+            }
+        }
+        TokenCmd::List => {
+            let all = tokens::list(conn)?;
+            match fmt {
+                Format::Text => {
+                    if all.is_empty() {
+                        writeln!(out, "No tokens")?;
+                    }
+                    for t in &all {
+                        let scopes: Vec<String> = t.scopes.iter().map(Scope::to_string).collect();
+                        writeln!(
+                            out,
+                            "{:<20} {:<30} last used {}",
+                            t.name,
+                            scopes.join(" "),
+                            when(t.last_used_at, &time)
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let json: Vec<_> = all
+                            .iter()
+                            .map(|t| {
+                                serde_json::json!({
+                                    "name": t.name,
+                                    "scopes": t.scopes.iter().map(Scope::to_string).collect::<Vec<_>>(),
+                                    "created_at": t.created_at,
+                                    "last_used_at": t.last_used_at,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&json)?)?;
+                    }
+                }
+            }
+        }
+        TokenCmd::Revoke { name } => {
+            if !tokens::revoke(conn, name)? {
+                bail!("no token named `{name}`");
+            }
+            if matches!(fmt, Format::Text) {
+                writeln!(out, "Revoked token `{name}`")?;
+            }
+        }
+    }
+    Ok(())
+}
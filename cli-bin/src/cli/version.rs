@@ -1,20 +1,158 @@
 // src/cli/version.rs
-use crate::cli::Format;
+//! `marlin version …` – content snapshots of files and diffs between them.
+
+use std::io::Write;
+
+use crate::cli::{Context, Format};
+use anyhow::bail;
 use clap::{Args, Subcommand};
-use rusqlite::Connection;
+use libmarlin::db;
+use libmarlin::describe::resolve;
+use libmarlin::utils::human_size;
+use libmarlin::versions::{self, Snapshot, SnapshotOptions};
+use tracing::warn;
 
 #[derive(Subcommand, Debug)]
 pub enum VersionCmd {
+    /// Record the current content of files matching a glob
+    Snapshot(ArgsSnapshot),
+    /// List the versions of a file
+    List(ArgsFile),
+    /// Unified diff between two versions (or one and the file on disk)
     Diff(ArgsDiff),
 }
 
+#[derive(Args, Debug)]
+pub struct ArgsSnapshot {
+    pub pattern: String,
+    /// Record only the content hash, not the content
+    #[arg(long)]
+    pub hash_only: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsFile {
+    pub file: String,
+}
+
 #[derive(Args, Debug)]
 pub struct ArgsDiff {
     pub file: String,
+    pub v1: i64,
+    /// Compare with this version instead of the file on disk
+    pub v2: Option<i64>,
 }
 
-pub fn run(cmd: &VersionCmd, _conn: &mut Connection, _format: Format) -> anyhow::Result<()> {
+pub fn run(cmd: &VersionCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    let fmt = ctx.format;
+    let time = ctx.time.clone();
+    let (conn, out) = ctx.split();
     match cmd {
-        VersionCmd::Diff(a) => todo!("version diff {:?}", a),
+        VersionCmd::Snapshot(a) => {
+            let files = db::match_files(&conn, &a.pattern)?;
+            if files.is_empty() {
+                bail!("no indexed file matches `{}`", a.pattern);
+            }
+            let opts = SnapshotOptions {
+                store_content: !a.hash_only,
+                ..Default::default()
+            };
+            let mut taken = Vec::new();
+            for (fid, path) in &files {
+                match versions::snapshot(&conn, *fid, path, &opts) {
+                    Ok(s) => taken.push((path, s)),
+                    Err(e) => warn!(file = %path, error = %e, "no snapshot"),
+                }
+            }
+            match fmt {
+                Format::Text => {
+                    for (path, s) in &taken {
+                        match s {
+                            Snapshot::New { version, stored } => writeln!(
+                                out,
+                                "{path}: version {version}{}",
+                                if *stored { "" } else { " (hash only)" }
+                            )?,
+                            Snapshot::Unchanged { version } => {
+                                writeln!(out, "{path}: unchanged since version {version}")?
+                            }
+                        }
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = taken
+                            .iter()
+                            .map(|(path, s)| match s {
+                                Snapshot::New { version, stored } => serde_json::json!({
+                                    "path": path,
+                                    "version": version,
+                                    "new": true,
+                                    "stored": stored,
+                                }),
+                                Snapshot::Unchanged { version } => serde_json::json!({
+                                    "path": path,
+                                    "version": version,
+                                    "new": false,
+                                }),
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+        VersionCmd::List(a) => {
+            let (fid, _) = resolve(&conn, &a.file)?;
+            let list = db::versions(&conn, fid)?;
+            match fmt {
+                Format::Text => {
+                    for v in &list {
+                        writeln!(
+                            out,
+                            "v{:<4} {}  {:>9}  {}{}",
+                            v.version,
+                            time.epoch(v.created_at).unwrap_or_default(),
+                            human_size(v.size),
+                            &v.hash[..12.min(v.hash.len())],
+                            if v.has_content { "" } else { "  (hash only)" }
+                        )?;
+                    }
+                }
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        let rows: Vec<_> = list
+                            .iter()
+                            .map(|v| {
+                                serde_json::json!({
+                                    "version": v.version,
+                                    "hash": v.hash,
+                                    "size": v.size,
+                                    "mtime": v.mtime,
+                                    "has_content": v.has_content,
+                                    "created_at": v.created_at,
+                                })
+                            })
+                            .collect();
+                        writeln!(out, "{}", serde_json::to_string(&rows)?)?;
+                    }
+                }
+            }
+        }
+        VersionCmd::Diff(a) => {
+            let diff = versions::diff(&conn, &a.file, a.v1, a.v2)?;
+            match fmt {
+                Format::Text => write!(out, "{diff}")?,
+                Format::Json => {
+                    #[cfg(feature = "json")]
+                    {
+                        writeln!(out, "{}", serde_json::json!({ "diff": diff }))?;
+                    }
+                }
+            }
+        }
     }
+    Ok(())
 }
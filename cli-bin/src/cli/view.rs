@@ -1,13 +1,24 @@
 //! `marlin view …` – save & use “smart folders” (named queries).
 
-use std::fs;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use clap::{Args, Subcommand};
+use libmarlin::config::Config;
+use libmarlin::watcher::WatcherConfig;
 use rusqlite::Connection;
+use tracing::{info, warn};
 
-use crate::cli::Format; // output selector stays local
-use libmarlin::db; // ← path switched from `crate::db`
+use crate::cli::{query_cache, Context, Format, PathStyle, RelativeTo}; // output selectors stay local
+use libmarlin::db;
+use libmarlin::exec::ExecTemplate;
+use libmarlin::query::SearchOptions; // search translation lives in the library
 
 #[derive(Subcommand, Debug)]
 pub enum ViewCmd {
@@ -17,6 +28,10 @@ pub enum ViewCmd {
     List,
     /// Execute a view (print matching paths)
     Exec(ArgsExec),
+    /// Save history entry <N> (see `marlin history search`) as a view
+    SaveFromHistory(ArgsSaveFromHistory),
+    /// Re-evaluate a view whenever files change and report new matches
+    Watch(ArgsWatch),
 }
 
 #[derive(Args, Debug)]
@@ -28,31 +43,79 @@ pub struct ArgsSave {
 #[derive(Args, Debug)]
 pub struct ArgsExec {
     pub view_name: String,
+    /// Print paths relative to the current directory, or with
+    /// `=workspace` to the workspace root
+    #[arg(long, value_enum, value_name = "BASE", num_args = 0..=1,
+          require_equals = true, default_missing_value = "cwd")]
+    pub relative: Option<RelativeTo>,
 }
 
-pub fn run(cmd: &ViewCmd, conn: &mut Connection, fmt: Format) -> anyhow::Result<()> {
+#[derive(Args, Debug)]
+pub struct ArgsSaveFromHistory {
+    pub n: i64,
+    pub view_name: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ArgsWatch {
+    pub view_name: String,
+    /// Directory to watch (changed files in it are indexed)
+    #[arg(long, default_value = ".")]
+    pub path: PathBuf,
+    /// Command to run for each new match (placeholders as for `search --exec`)
+    #[arg(long)]
+    pub exec: Option<String>,
+    /// Debounce window in milliseconds
+    #[arg(long, default_value = "500")]
+    pub debounce_ms: u64,
+    /// Print new paths relative to the current directory, or with
+    /// `=workspace` to the workspace root
+    #[arg(long, value_enum, value_name = "BASE", num_args = 0..=1,
+          require_equals = true, default_missing_value = "cwd")]
+    pub relative: Option<RelativeTo>,
+}
+
+pub fn run(cmd: &ViewCmd, ctx: &mut Context) -> anyhow::Result<()> {
+    // the watcher shares the handle, so the loop must not hold its lock
+    if let ViewCmd::Watch(a) = cmd {
+        return watch_view(a, ctx);
+    }
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
     match cmd {
         /* ── view save ───────────────────────────────────────────── */
         ViewCmd::Save(a) => {
             db::save_view(conn, &a.view_name, &a.query)?;
             if matches!(fmt, Format::Text) {
-                println!("Saved view '{}' = {}", a.view_name, a.query);
+                writeln!(out, "Saved view '{}' = {}", a.view_name, a.query)?;
             }
         }
 
+        /* ── view save-from-history ──────────────────────────────── */
+        ViewCmd::SaveFromHistory(a) => {
+            let query = db::history_query(conn, Some(a.n))?;
+            db::save_view(conn, &a.view_name, &query)?;
+            if matches!(fmt, Format::Text) {
+                writeln!(out, "Saved view '{}' = {}", a.view_name, query)?;
+            }
+        }
+
+        ViewCmd::Watch(_) => {} // handled above
+
         /* ── view list ───────────────────────────────────────────── */
         ViewCmd::List => {
             let views = db::list_views(conn)?;
             match fmt {
                 Format::Text => {
                     for (name, q) in views {
-                        println!("{name}: {q}");
+                        writeln!(out, "{name}: {q}")?;
                     }
                 }
                 Format::Json => {
                     #[cfg(feature = "json")]
                     {
-                        println!("{}", serde_json::to_string(&views)?);
+                        writeln!(out, "{}", serde_json::to_string(&views)?)?;
                     }
                 }
             }
@@ -60,34 +123,18 @@ pub fn run(cmd: &ViewCmd, conn: &mut Connection, fmt: Format) -> anyhow::Result<
 
         /* ── view exec ───────────────────────────────────────────── */
         ViewCmd::Exec(a) => {
-            let raw = db::view_query(conn, &a.view_name)?;
-
-            // Re-use the tiny parser from marlin search
-            let fts_expr = build_fts_match(&raw);
-
-            let mut stmt = conn.prepare(
-                r#"
-                SELECT f.path
-                  FROM files_fts
-                  JOIN files f ON f.rowid = files_fts.rowid
-                 WHERE files_fts MATCH ?1
-                 ORDER BY rank
-                "#,
-            )?;
-            let mut paths: Vec<String> = stmt
-                .query_map([fts_expr], |r| r.get::<_, String>(0))?
-                .collect::<Result<_, _>>()?;
-
-            /* ── NEW: graceful fallback when FTS finds nothing ───── */
-            if paths.is_empty() && !raw.contains(':') {
-                paths = naive_search(conn, &raw)?;
-            }
+            let paths: Vec<String> = query_cache()
+                .view_exec(conn, &a.view_name, &SearchOptions::default())?
+                .into_iter()
+                .map(|h| h.path)
+                .collect();
 
             if paths.is_empty() && matches!(fmt, Format::Text) {
                 eprintln!("(view '{}' has no matches)", a.view_name);
             } else {
-                for p in paths {
-                    println!("{p}");
+                let style = PathStyle::new(a.relative)?;
+                for p in &paths {
+                    writeln!(out, "{}", style.show(p))?;
                 }
             }
         }
@@ -95,75 +142,122 @@ pub fn run(cmd: &ViewCmd, conn: &mut Connection, fmt: Format) -> anyhow::Result<
     Ok(())
 }
 
-/* ─── naive substring path/content search (≤ 64 kB files) ───────── */
+/* ─── view watch ────────────────────────────────────────────────── */
+
+/// Foreground loop: the watcher indexes the changed paths through the
+/// shared handle; after each batch the view is re-run and paths that were
+/// not matching before are reported.  Stops on Ctrl+C.
+fn watch_view(a: &ArgsWatch, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let raw = db::view_query(&ctx.marlin.conn(), &a.view_name)?;
+    let root = a.path.canonicalize().unwrap_or_else(|_| a.path.clone());
+    let style = PathStyle::new(a.relative)?;
+
+    let mut seen: HashSet<String> = query_cache()
+        .search(&ctx.marlin.conn(), &raw)?
+        .into_iter()
+        .collect();
+    let keep_metadata = WatcherConfig::keep_metadata_from_settings(&ctx.marlin.conn())?;
+    let config = WatcherConfig {
+        debounce_ms: a.debounce_ms,
+        ignore: own_files(&ctx.cfg, &root),
+        keep_metadata,
+        ..Default::default()
+    };
+    let events = config.feed.subscribe(0);
+    let mut watcher = ctx.marlin.watch(&root, Some(config))?;
+    info!(
+        "Watching {} for new matches of view '{}' ({} current). Ctrl+C to stop.",
+        root.display(),
+        a.view_name,
+        seen.len()
+    );
 
-fn naive_search(conn: &Connection, term: &str) -> Result<Vec<String>> {
-    let term_lc = term.to_lowercase();
-    let mut stmt = conn.prepare("SELECT path FROM files")?;
-    let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || r.store(false, Ordering::SeqCst))?;
 
-    let mut hits = Vec::new();
-    for p in rows {
-        let p = p?;
-        /* path match */
-        if p.to_lowercase().contains(&term_lc) {
-            hits.push(p);
+    while running.load(Ordering::SeqCst) {
+        // events are published once their batch is in the index
+        if events.recv_timeout(Duration::from_millis(200)).is_none() {
             continue;
         }
-        /* small-file content match */
-        if let Ok(meta) = fs::metadata(&p) {
-            if meta.len() > 64_000 {
-                continue;
-            }
-        }
-        if let Ok(content) = fs::read_to_string(&p) {
-            if content.to_lowercase().contains(&term_lc) {
-                hits.push(p);
+        while events.recv_timeout(Duration::ZERO).is_some() {}
+
+        // an event burst that changed nothing in the index is a cache hit
+        let current: HashSet<String> = query_cache()
+            .search(&ctx.marlin.conn(), &raw)?
+            .into_iter()
+            .collect();
+        let mut fresh: Vec<&String> = current.difference(&seen).collect();
+        fresh.sort();
+        for p in fresh {
+            match (&a.exec, fmt) {
+                (Some(cmd), _) => run_hook(&ctx.marlin.conn(), cmd, p),
+                (None, Format::Text) => writeln!(ctx.output, "+ {}", style.show(p))?,
+                (None, Format::Json) => {
+                    #[cfg(feature = "json")]
+                    {
+                        let p = style.show(p);
+                        writeln!(
+                            ctx.output,
+                            "{}",
+                            serde_json::json!({ "view": a.view_name, "new": p })
+                        )?;
+                    }
+                }
             }
         }
+        seen = current;
     }
-    Ok(hits)
+
+    watcher.stop()?;
+    Ok(())
 }
 
-/* ─── minimal copy of search-string → FTS5 translator ───────────── */
-
-fn build_fts_match(raw_query: &str) -> String {
-    use shlex;
-    let mut parts = Vec::new();
-    let toks = shlex::split(raw_query).unwrap_or_else(|| vec![raw_query.to_string()]);
-    for tok in toks {
-        if ["AND", "OR", "NOT"].contains(&tok.as_str()) {
-            parts.push(tok);
-        } else if let Some(tag) = tok.strip_prefix("tag:") {
-            for (i, seg) in tag.split('/').filter(|s| !s.is_empty()).enumerate() {
-                if i > 0 {
-                    parts.push("AND".into());
-                }
-                parts.push(format!("tags_text:{}", escape(seg)));
-            }
-        } else if let Some(attr) = tok.strip_prefix("attr:") {
-            let mut kv = attr.splitn(2, '=');
-            let key = kv.next().unwrap();
-            if let Some(val) = kv.next() {
-                parts.push(format!("attrs_text:{}", escape(key)));
-                parts.push("AND".into());
-                parts.push(format!("attrs_text:{}", escape(val)));
-            } else {
-                parts.push(format!("attrs_text:{}", escape(key)));
-            }
-        } else {
-            parts.push(escape(&tok));
-        }
+/// The index, its WAL and journal, backups and logs: writing them must
+/// not count as a change under `root`.  Their directory is skipped as a
+/// whole unless `root` lies inside it.
+fn own_files(cfg: &Config, root: &Path) -> Vec<PathBuf> {
+    let abs = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let db = abs(&cfg.db_path);
+    let mut ignore: Vec<PathBuf> = ["", "-wal", "-shm", "-journal"]
+        .iter()
+        .map(|s| {
+            let mut p = db.clone().into_os_string();
+            p.push(s);
+            PathBuf::from(p)
+        })
+        .collect();
+    if let Some(dir) = db.parent().filter(|d| !root.starts_with(d)) {
+        ignore.push(dir.to_path_buf());
     }
-    parts.join(" ")
+    ignore.push(abs(&cfg.backups_dir));
+    ignore.push(abs(&libmarlin::logging::log_dir()));
+    ignore
 }
 
-fn escape(term: &str) -> String {
-    if term.contains(|c: char| c.is_whitespace() || "-:()\"".contains(c))
-        || ["AND", "OR", "NOT", "NEAR"].contains(&term.to_uppercase().as_str())
-    {
-        format!("\"{}\"", term.replace('"', "\"\""))
-    } else {
-        term.to_string()
+/// Run `cmd_tpl` for one path (placeholders as for `search --exec`, else
+/// the path is appended).
+fn run_hook(conn: &Connection, cmd_tpl: &str, path: &str) {
+    let cmd = match ExecTemplate::parse(cmd_tpl).render(conn, path) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            warn!(command = %cmd_tpl, error = %e, "could not fill in command");
+            return;
+        }
+    };
+    let Some(mut parts) = shlex::split(&cmd) else {
+        warn!(command = %cmd, "could not parse command");
+        return;
+    };
+    if parts.is_empty() {
+        return;
+    }
+    let prog = parts.remove(0);
+    match Command::new(&prog).args(parts).status() {
+        Ok(st) if !st.success() => warn!(command = %cmd, code = ?st.code(), "command failed"),
+        Ok(_) => {}
+        Err(e) => warn!(command = %cmd, error = %e, "could not run command"),
     }
 }
@@ -0,0 +1,64 @@
+//! `marlin warm` – pre-load the index pages a first search needs.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Args;
+use rusqlite::Connection;
+
+use crate::cli::{Context, Format};
+use libmarlin::db;
+use libmarlin::utils::human_size;
+
+#[derive(Args, Debug)]
+pub struct WarmArgs {
+    /// Read through a memory map of the whole database file
+    #[arg(long)]
+    pub mmap: bool,
+}
+
+pub fn run(a: &WarmArgs, ctx: &mut Context) -> Result<()> {
+    let fmt = ctx.format;
+    let (mut guard, out) = ctx.split();
+    let conn: &mut Connection = &mut guard;
+    let report = db::warm(conn, a.mmap)?;
+
+    match fmt {
+        Format::Text => {
+            for (target, rows) in &report.targets {
+                writeln!(out, "{rows:>8}  {target}")?;
+            }
+            let via = match report.mmap_bytes {
+                Some(b) => format!(" via mmap ({})", human_size(b as i64)),
+                None => String::new(),
+            };
+            writeln!(
+                out,
+                "Warmed {} of index{via} in {} ms",
+                human_size(report.db_bytes as i64),
+                report.elapsed.as_millis()
+            )?;
+        }
+        Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let targets: serde_json::Map<_, _> = report
+                    .targets
+                    .iter()
+                    .map(|(t, n)| (t.to_string(), (*n).into()))
+                    .collect();
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({
+                        "targets": targets,
+                        "db_bytes": report.db_bytes,
+                        "mmap_bytes": report.mmap_bytes,
+                        "elapsed_ms": report.elapsed.as_millis() as u64,
+                    })
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,15 +1,19 @@
 // src/cli/watch.rs
 
-use anyhow::Result;
-use clap::Subcommand;
-use libmarlin::watcher::{WatcherConfig, WatcherState};
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use libmarlin::control::{self, ControlServer, Endpoint, Registry, WatcherEntry};
+use libmarlin::query::SearchOptions;
+use libmarlin::tokens::{self, Access};
+use libmarlin::watcher::{BurstConfig, EventFeed, Subscription, WatcherConfig, WatcherState};
 use rusqlite::Connection;
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
-use tracing::info;
+use tracing::{error, info, warn};
 
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
@@ -31,89 +35,1045 @@ pub enum WatchCmd {
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Debounce window in milliseconds (default: 100ms)
-        #[arg(long, default_value = "100")]
-        debounce_ms: u64,
+        /// Debounce window in milliseconds (default: the root profile's,
+        /// else 100ms)
+        #[arg(long)]
+        debounce_ms: Option<u64>,
+
+        /// Workspace name for `watch list/status/stop` (defaults to the
+        /// directory name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Run the watcher unattended (the entry point used by service managers)
+    Daemon {
+        /// Directory to watch
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Debounce window in milliseconds (default: the root profile's,
+        /// else 100ms)
+        #[arg(long)]
+        debounce_ms: Option<u64>,
+
+        /// Workspace name for `watch list/status/stop` (defaults to the
+        /// directory name)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Serve the control channel on this loopback TCP address instead
+        /// of the unix socket next to the DB (pair with --require-token)
+        #[arg(long, value_name = "ADDR")]
+        control_tcp: Option<SocketAddr>,
+
+        /// Only answer control requests carrying a token from
+        /// `marlin token create`, limited to its scopes
+        #[arg(long)]
+        require_token: bool,
+    },
+
+    /// Write a systemd unit / launchd plist / WinSW config for `watch daemon`
+    InstallService(InstallServiceArgs),
+
+    /// Print the daemon log (`--follow` to keep streaming new lines)
+    Logs {
+        /// Keep the log open and print lines as they are written
+        #[arg(long, short = 'f')]
+        follow: bool,
+
+        /// Number of trailing lines to show first
+        #[arg(long, short = 'n', default_value = "50")]
+        lines: usize,
+    },
+
+    /// List all running watchers on this machine
+    List,
+
+    /// Show status of a watcher (by name, or the one for this index)
+    Status {
+        /// Workspace name as shown by `watch list`
+        #[arg(conflicts_with = "tcp")]
+        name: Option<String>,
+
+        /// Query a watcher started with `--control-tcp ADDR`
+        #[arg(long, value_name = "ADDR")]
+        tcp: Option<SocketAddr>,
+    },
+
+    /// Report DB reachability, restarts and the last error of a watcher
+    Health {
+        /// Workspace name as shown by `watch list`
+        #[arg(conflicts_with = "tcp")]
+        name: Option<String>,
+
+        /// Query a watcher started with `--control-tcp ADDR`
+        #[arg(long, value_name = "ADDR")]
+        tcp: Option<SocketAddr>,
+    },
+
+    /// Print recent file events of a watcher as NDJSON (`--follow` to
+    /// keep streaming)
+    Events {
+        /// Workspace name as shown by `watch list`
+        #[arg(conflicts_with = "tcp")]
+        name: Option<String>,
+
+        /// Query a watcher started with `--control-tcp ADDR`
+        #[arg(long, value_name = "ADDR")]
+        tcp: Option<SocketAddr>,
+
+        /// Keep the connection open and print events as they happen
+        #[arg(long, short = 'f')]
+        follow: bool,
+
+        /// Number of recent events to show first
+        #[arg(long, short = 'n', default_value = "50")]
+        lines: usize,
+    },
+
+    /// Run a query on a watcher's open index, one path per line
+    Search {
+        /// Marlin query, as for `marlin search`
+        query: String,
+
+        /// Workspace name as shown by `watch list`
+        #[arg(long, conflicts_with = "tcp")]
+        name: Option<String>,
+
+        /// Query a watcher started with `--control-tcp ADDR`
+        #[arg(long, value_name = "ADDR")]
+        tcp: Option<SocketAddr>,
     },
 
-    /// Show status of currently active watcher
-    Status,
+    /// Stop a watcher (by name, or the one for this index)
+    Stop {
+        /// Workspace name as shown by `watch list`
+        #[arg(conflicts_with = "tcp")]
+        name: Option<String>,
 
-    /// Stop the currently running watcher
-    Stop,
+        /// Stop a watcher started with `--control-tcp ADDR`
+        #[arg(long, value_name = "ADDR")]
+        tcp: Option<SocketAddr>,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct InstallServiceArgs {
+    /// Directory the service should watch
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+
+    /// Install for the current user instead of system-wide
+    #[arg(long)]
+    pub user: bool,
+
+    /// Write the definition here instead of the platform default location
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Print the definition instead of writing it
+    #[arg(long)]
+    pub print: bool,
 }
 
 /// Run a watch command
-pub fn run(cmd: &WatchCmd, _conn: &mut Connection, _format: super::Format) -> Result<()> {
+pub fn run(cmd: &WatchCmd, ctx: &mut super::Context) -> Result<()> {
+    let marlin = &mut ctx.marlin;
+    match cmd {
+        WatchCmd::Start {
+            path,
+            debounce_ms,
+            name,
+        } => run_watcher(
+            marlin,
+            path,
+            *debounce_ms,
+            name.as_deref(),
+            false,
+            None,
+            false,
+        ),
+        WatchCmd::Daemon {
+            path,
+            debounce_ms,
+            name,
+            control_tcp,
+            require_token,
+        } => run_watcher(
+            marlin,
+            path,
+            *debounce_ms,
+            name.as_deref(),
+            true,
+            *control_tcp,
+            *require_token,
+        ),
+        _ => run_control(cmd, ctx.format),
+    }
+}
+
+impl WatchCmd {
+    /// Only `start`/`daemon` use the index; the rest talk to a running
+    /// watcher or the file system and must not open (or create) the DB.
+    pub fn needs_db(&self) -> bool {
+        matches!(self, WatchCmd::Start { .. } | WatchCmd::Daemon { .. })
+    }
+}
+
+/// Run a watch command that does not need the database.
+pub fn run_control(cmd: &WatchCmd, format: super::Format) -> Result<()> {
     match cmd {
-        WatchCmd::Start { path, debounce_ms } => {
-            let mut marlin = libmarlin::Marlin::open_default()?;
-            let config = WatcherConfig {
-                debounce_ms: *debounce_ms,
-                ..Default::default()
+        WatchCmd::Start { .. } | WatchCmd::Daemon { .. } => {
+            anyhow::bail!("`watch start`/`watch daemon` need the database")
+        }
+        WatchCmd::InstallService(args) => install_service(args),
+        WatchCmd::Logs { follow, lines } => {
+            let path = libmarlin::logging::log_dir().join(libmarlin::logging::DAEMON_LOG);
+            show_logs(&path, *lines, *follow)
+        }
+        WatchCmd::List => list_watchers(&Registry::default_location(), format),
+        WatchCmd::Status { name, tcp } => send_control(name.as_deref(), *tcp, "status"),
+        WatchCmd::Health { name, tcp } => send_control(name.as_deref(), *tcp, "health"),
+        WatchCmd::Stop { name, tcp } => send_control(name.as_deref(), *tcp, "stop"),
+        WatchCmd::Search { query, name, tcp } => {
+            send_control(name.as_deref(), *tcp, &format!("search {query}"))
+        }
+        WatchCmd::Events {
+            name,
+            tcp,
+            follow: false,
+            lines,
+        } => send_control(name.as_deref(), *tcp, &format!("events {lines}")),
+        WatchCmd::Events {
+            name,
+            tcp,
+            follow: true,
+            lines,
+        } => follow_events(name.as_deref(), *tcp, *lines),
+    }
+}
+
+/// `command` with the token from `MARLIN_TOKEN` in front, if set.
+fn authed(command: &str) -> String {
+    let token = std::env::var("MARLIN_TOKEN").ok();
+    control::with_token(command, token.as_deref().filter(|t| !t.is_empty()))
+}
+
+/// Send `command` to the watcher picked by `name`, `tcp`, or – failing
+/// both – the one for the configured index, and print the reply.
+fn send_control(name: Option<&str>, tcp: Option<SocketAddr>, command: &str) -> Result<()> {
+    let (endpoint, what) = pick_endpoint(name, tcp)?;
+    match control::request(&endpoint, &authed(command)) {
+        Ok(reply) => print!("{reply}"),
+        Err(e) if control::is_not_running(&e) => println!("No watcher running for {what}"),
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+/// Stream the watcher's file events as NDJSON until it goes away.
+fn follow_events(name: Option<&str>, tcp: Option<SocketAddr>, lines: usize) -> Result<()> {
+    use std::io::Write;
+
+    let (endpoint, what) = pick_endpoint(name, tcp)?;
+    let mut out = std::io::stdout();
+    let res = control::follow(
+        &endpoint,
+        &authed(&format!("events follow {lines}")),
+        |line| {
+            writeln!(out, "{line}")?;
+            out.flush()?;
+            Ok(())
+        },
+    );
+    match res {
+        Err(e) if control::is_not_running(&e) => println!("No watcher running for {what}"),
+        other => other?,
+    }
+    Ok(())
+}
+
+/// The control endpoint for `name`, `tcp`, or the configured index, plus
+/// a description for messages.
+fn pick_endpoint(name: Option<&str>, tcp: Option<SocketAddr>) -> Result<(Endpoint, String)> {
+    Ok(match (name, tcp) {
+        (Some(n), _) => match Registry::default_location().get(n)? {
+            Some(e) => (e.endpoint, format!("`{n}`")),
+            None => anyhow::bail!("no watcher named `{n}` (see `marlin watch list`)"),
+        },
+        (None, Some(addr)) => (Endpoint::Tcp(addr), format!("tcp://{addr}")),
+        (None, None) => {
+            let cfg = libmarlin::config::Config::load()?;
+            (
+                Endpoint::for_db(&cfg.db_path),
+                cfg.db_path.display().to_string(),
+            )
+        }
+    })
+}
+
+/// Print every live watcher in `registry`, dropping stale entries.
+pub fn list_watchers(registry: &Registry, format: super::Format) -> Result<()> {
+    let entries = registry.list(true)?;
+    let state_of = |e: &control::WatcherEntry| {
+        e.status()
+            .and_then(|s| {
+                s.lines()
+                    .find_map(|l| l.strip_prefix("state: ").map(str::to_string))
+            })
+            .unwrap_or_else(|| "?".into())
+    };
+    match format {
+        super::Format::Text => {
+            if entries.is_empty() {
+                println!("No watchers running");
+                return Ok(());
+            }
+            println!("{:<16} {:>7} {:<10} PATH", "NAME", "PID", "STATE");
+            for e in &entries {
+                println!(
+                    "{:<16} {:>7} {:<10} {}",
+                    e.name,
+                    e.pid,
+                    state_of(e),
+                    e.watch_path.display()
+                );
+            }
+        }
+        super::Format::Json => {
+            #[cfg(feature = "json")]
+            {
+                let rows: Vec<_> = entries
+                    .iter()
+                    .map(|e| {
+                        serde_json::json!({
+                            "name": e.name,
+                            "pid": e.pid,
+                            "state": state_of(e),
+                            "path": e.watch_path,
+                            "db": e.db_path,
+                            "endpoint": e.endpoint.to_string(),
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string(&rows)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/* ── run loop ────────────────────────────────────────────────────── */
+
+fn run_watcher(
+    marlin: &mut libmarlin::Marlin,
+    path: &Path,
+    debounce_ms: Option<u64>,
+    name: Option<&str>,
+    daemon: bool,
+    control_tcp: Option<SocketAddr>,
+    require_token: bool,
+) -> Result<()> {
+    let canon_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    // one guard: a second `marlin.conn()` in the same statement would
+    // lock the connection again and hang
+    let (roots, burst, keep_metadata) = {
+        let conn = marlin.conn();
+        (
+            libmarlin::roots::list(&conn)?,
+            BurstConfig::from_settings(&conn)?,
+            WatcherConfig::keep_metadata_from_settings(&conn)?,
+        )
+    };
+    let profile_debounce =
+        libmarlin::roots::innermost(&roots, &canon_path).and_then(|r| r.debounce_ms);
+    let mut config = WatcherConfig {
+        debounce_ms: debounce_ms
+            .or(profile_debounce)
+            .unwrap_or(WatcherConfig::default().debounce_ms),
+        roots,
+        burst,
+        keep_metadata,
+        ..Default::default()
+    };
+    if daemon {
+        let logs = libmarlin::logging::log_dir();
+        config.ignore.push(logs.canonicalize().unwrap_or(logs));
+    }
+    info!("Starting watcher for directory: {}", canon_path.display());
+
+    // Bind the control channel first: it doubles as the "one watcher per
+    // index" lock.
+    let endpoint = match control_tcp {
+        Some(addr) => Endpoint::Tcp(addr),
+        None => Endpoint::for_db(&marlin.config().db_path),
+    };
+    let server = ControlServer::bind(&endpoint)?;
+    info!("Control channel listening on {}", server.endpoint()?);
+
+    let name = match name {
+        Some(n) => n.to_string(),
+        None => canon_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "root".into()),
+    };
+    let _registration = Registry::default_location().register(WatcherEntry {
+        name: name.clone(),
+        pid: std::process::id(),
+        db_path: marlin.config().db_path.clone(),
+        watch_path: canon_path.clone(),
+        endpoint: server.endpoint()?,
+    })?;
+    info!("Registered watcher as `{name}`");
+
+    // `search` requests repeat a lot (front-ends polling one query);
+    // anything the watcher writes invalidates the cached results.
+    marlin.enable_query_cache(libmarlin::query_cache::DEFAULT_CAPACITY);
+    let db_path = marlin.config().db_path.clone();
+    let mut watcher = marlin.watch(&canon_path, Some(config.clone()))?;
+    let mut health = Health::new();
+
+    let status = watcher.status()?;
+    info!("Watcher started. Press Ctrl+C to stop watching.");
+    info!("Watching {} paths", status.watched_paths.len());
+
+    // Settings are read once; restart the watcher after changing them.
+    let notifier = libmarlin::notify::Notifier::from_settings(&marlin.conn())?;
+    if notifier.is_enabled() {
+        info!(
+            "Due reminders are sent via {}",
+            notifier.channels().join(", ")
+        );
+    }
+    let mut next_reminder_check = Instant::now();
+
+    let start_time = Instant::now();
+    let mut last_status_time = Instant::now();
+    let running = Arc::new(AtomicBool::new(true));
+    let r_clone = running.clone();
+
+    ctrlc::set_handler(move || {
+        info!("Ctrl+C received. Signaling watcher to stop...");
+        r_clone.store(false, Ordering::SeqCst);
+    })?;
+
+    info!("Watcher run loop started. Waiting for Ctrl+C or stop signal...");
+    while running.load(Ordering::SeqCst) {
+        // A stopped watcher here means it died or was torn down for
+        // recovery; the health check below deals with it.
+        let current_status = watcher.status()?;
+
+        // Corrected line: removed the extra closing parenthesis
+        if last_status_time.elapsed() > Duration::from_secs(10) {
+            let uptime = start_time.elapsed();
+            info!(
+                "Watcher running for {}s, processed {} events, queue: {}, state: {:?}",
+                uptime.as_secs(),
+                current_status.events_processed,
+                current_status.queue_size,
+                current_status.state
+            );
+            last_status_time = Instant::now();
+        }
+
+        let now = Instant::now();
+        if now >= health.next_check || (health.watcher_alive && !watcher.is_alive()) {
+            health.check(&db_path, watcher.is_alive(), current_status.last_error);
+        }
+        if health.should_recover(now) {
+            warn!(
+                "Watcher unhealthy ({}); recovery attempt {}/{}",
+                health.problem().unwrap_or_default(),
+                health.failures + 1,
+                MAX_RECOVERY_ATTEMPTS
+            );
+            watcher.stop().ok();
+            // Reopening runs the migration check again; the watcher gets a
+            // fresh connection of its own. Never recreate a vanished index.
+            let attempt = if db_path.exists() {
+                libmarlin::Marlin::open_at(&db_path)
+                    .and_then(|m| m.watch(&canon_path, Some(config.clone())))
+            } else {
+                Err(anyhow::anyhow!("database {} is missing", db_path.display()))
             };
-            let canon_path = path.canonicalize().unwrap_or_else(|_| path.clone());
-            info!("Starting watcher for directory: {}", canon_path.display());
-
-            let mut watcher = marlin.watch(&canon_path, Some(config))?;
-
-            let status = watcher.status()?;
-            info!("Watcher started. Press Ctrl+C to stop watching.");
-            info!("Watching {} paths", status.watched_paths.len());
-
-            let start_time = Instant::now();
-            let mut last_status_time = Instant::now();
-            let running = Arc::new(AtomicBool::new(true));
-            let r_clone = running.clone();
-
-            ctrlc::set_handler(move || {
-                info!("Ctrl+C received. Signaling watcher to stop...");
-                r_clone.store(false, Ordering::SeqCst);
-            })?;
-
-            info!("Watcher run loop started. Waiting for Ctrl+C or stop signal...");
-            while running.load(Ordering::SeqCst) {
-                let current_status = watcher.status()?;
-                if current_status.state == WatcherState::Stopped {
-                    info!("Watcher has stopped (detected by state). Exiting loop.");
-                    break;
+            match attempt {
+                Ok(w) => {
+                    watcher = w;
+                    health.recovered();
+                    info!("Watcher recovered (restart #{})", health.restarts);
                 }
-
-                // Corrected line: removed the extra closing parenthesis
-                if last_status_time.elapsed() > Duration::from_secs(10) {
-                    let uptime = start_time.elapsed();
-                    info!(
-                        "Watcher running for {}s, processed {} events, queue: {}, state: {:?}",
-                        uptime.as_secs(),
-                        current_status.events_processed,
-                        current_status.queue_size,
-                        current_status.state
-                    );
-                    last_status_time = Instant::now();
+                Err(e) => {
+                    health.recovery_failed(&e, Instant::now());
+                    if health.gave_up() {
+                        error!(
+                            "Giving up on recovery after {MAX_RECOVERY_ATTEMPTS} attempts: {e:#}"
+                        );
+                    }
                 }
-                thread::sleep(Duration::from_millis(200));
             }
+            continue;
+        }
+
+        if notifier.is_enabled() && now >= next_reminder_check {
+            let at = chrono::Utc::now().timestamp();
+            if let Err(e) = libmarlin::notify::fire_due(&marlin.conn(), &notifier, at) {
+                warn!("reminder check failed: {e:#}");
+            }
+            next_reminder_check = now + REMINDER_INTERVAL;
+        }
 
-            info!("Watcher run loop ended. Explicitly stopping watcher instance...");
-            watcher.stop()?;
+        loop {
+            let req = match server.poll() {
+                Ok(Some(req)) => req,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("control request failed: {e}");
+                    break;
+                }
+            };
+            let words = request_words(&req.command);
+            let access = required_access(&words);
+            if let Err(denied) =
+                authorize(&marlin.conn(), req.token.as_deref(), access, require_token)
             {
-                let mut guard = LAST_WATCHER_STATE.lock().unwrap();
-                *guard = Some(watcher.status()?.state);
+                warn!("control request `{}` refused: {denied}", access);
+                let _ = req.respond(&format!("error: {denied}"));
+                continue;
+            }
+            if let Some(args) = req.command.strip_prefix("events") {
+                match events_request(args, &config.feed) {
+                    EventsReply::Lines(lines) => {
+                        if let Err(e) = req.respond(&lines) {
+                            warn!("control reply failed: {e}");
+                        }
+                    }
+                    EventsReply::Follow(rx) => {
+                        thread::spawn(move || {
+                            let _ = req.stream(rx.map(|ev| ev.to_json()));
+                        });
+                    }
+                    EventsReply::Error(msg) => {
+                        let _ = req.respond(&msg);
+                    }
+                }
+                continue;
+            }
+            let reply = match req.command.as_str() {
+                "status" => format!(
+                    "name: {name}\nstate: {:?}\nhealth: {}\nuptime: {}s\nevents processed: {}\nqueue: {}\nwatching: {}\n",
+                    current_status.state,
+                    health.summary(),
+                    start_time.elapsed().as_secs(),
+                    current_status.events_processed,
+                    current_status.queue_size,
+                    canon_path.display()
+                ),
+                "ping" => "pong".to_string(),
+                "health" => health.report(),
+                cmd if cmd.starts_with("search ") => search_request(marlin, &cmd["search ".len()..]),
+                _ if matches!(words.first().map(String::as_str), Some("tag" | "attr")) => {
+                    match metadata_request(marlin, &words) {
+                        Ok(reply) => reply,
+                        Err(e) => format!("error: {e:#}"),
+                    }
+                }
+                "stop" => {
+                    info!("Stop requested over the control channel.");
+                    running.store(false, Ordering::SeqCst);
+                    "Stopping watcher".to_string()
+                }
+                other => format!("error: unknown command `{other}`"),
+            };
+            if let Err(e) = req.respond(&reply) {
+                warn!("control reply failed: {e}");
             }
-            info!("Watcher instance fully stopped.");
-            Ok(())
         }
-        WatchCmd::Status => {
-            info!(
-                "Status command: No active watcher process to query in this CLI invocation model."
-            );
-            info!("To see live status, run 'marlin watch start' which prints periodic updates.");
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    info!("Watcher run loop ended. Explicitly stopping watcher instance...");
+    watcher.stop()?;
+    {
+        let mut guard = LAST_WATCHER_STATE.lock().unwrap();
+        *guard = Some(watcher.status()?.state);
+    }
+    info!("Watcher instance fully stopped.");
+    Ok(())
+}
+
+/// A control request split like a shell would, once, so the words it is
+/// authorized by are the words it runs with.  Unbalanced quotes leave the
+/// whole request as one word, which no write command matches.
+pub fn request_words(command: &str) -> Vec<String> {
+    shlex::split(command).unwrap_or_else(|| vec![command.to_string()])
+}
+
+/// What a request (split by [`request_words`]) needs from the token it
+/// came with.
+pub fn required_access(words: &[String]) -> Access<'_> {
+    match words.first().map(String::as_str) {
+        Some("stop") => Access::Admin,
+        Some("tag") => Access::Tag(words.get(1).map_or("", String::as_str)),
+        Some("attr") => Access::Write,
+        _ => Access::Read,
+    }
+}
+
+/// Check the token sent with a request against `access`; without one,
+/// only a watcher that does not `require_token` answers.
+pub fn authorize(
+    conn: &Connection,
+    token: Option<&str>,
+    access: Access<'_>,
+    require_token: bool,
+) -> std::result::Result<(), String> {
+    let Some(secret) = token else {
+        return if require_token {
+            Err("this watcher requires a token (send `auth <token> <command>`)".into())
+        } else {
             Ok(())
+        };
+    };
+    match tokens::verify(conn, secret) {
+        Ok(Some(t)) if t.allows(access) => Ok(()),
+        Ok(Some(t)) => Err(format!("token `{}` lacks the `{access}` scope", t.name)),
+        Ok(None) => Err("unknown token".into()),
+        Err(e) => Err(format!("checking token: {e:#}")),
+    }
+}
+
+/// `tag <tag_path> <glob>` and `attr <glob> <key> <value>` on the control
+/// channel, given the [`request_words`] it was authorized by.
+pub fn metadata_request(marlin: &mut libmarlin::Marlin, words: &[String]) -> Result<String> {
+    match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["tag", tag_path, pattern] => {
+            let n = marlin.tag(pattern, tag_path)?;
+            Ok(format!("tagged {n} file(s) with {tag_path}"))
         }
-        WatchCmd::Stop => {
-            info!("Stop command: No active watcher process to stop in this CLI invocation model.");
-            info!("Please use Ctrl+C in the terminal where 'marlin watch start' is running.");
-            Ok(())
+        ["attr", pattern, key, value] => {
+            let n = marlin.attr_set(pattern, key, value)?;
+            Ok(format!("set {key}={value} on {n} file(s)"))
+        }
+        _ => anyhow::bail!("usage: tag <tag_path> <glob> | attr <glob> <key> <value>"),
+    }
+}
+
+/// `search <query>` on the control channel: the matching paths, one per
+/// line, best first; nothing at all when there are none.
+pub fn search_request(marlin: &libmarlin::Marlin, query: &str) -> String {
+    let query = query.trim();
+    if query.is_empty() {
+        return "error: usage: search <query>".into();
+    }
+    match marlin.search_hits(query, &SearchOptions::default()) {
+        Ok(hits) => hits.into_iter().map(|h| h.path + "\n").collect(),
+        Err(e) => format!("error: {e:#}"),
+    }
+}
+
+enum EventsReply {
+    Lines(String),
+    Follow(Subscription),
+    Error(String),
+}
+
+/// `events [follow] [N]` on the control channel.
+fn events_request(args: &str, feed: &EventFeed) -> EventsReply {
+    let mut words = args.split_whitespace().peekable();
+    let follow = words.next_if_eq(&"follow").is_some();
+    let n = match words.next().map(str::parse::<usize>) {
+        None => 50,
+        Some(Ok(n)) => n,
+        Some(Err(_)) => return EventsReply::Error("error: usage: events [follow] [N]".into()),
+    };
+    if follow {
+        EventsReply::Follow(feed.subscribe(n))
+    } else {
+        EventsReply::Lines(
+            feed.recent(n)
+                .iter()
+                .map(|ev| ev.to_json() + "\n")
+                .collect(),
+        )
+    }
+}
+
+/* ── health ────────────────────────────────────────────────────────── */
+
+/// How often the run loop looks for due reminders.
+const REMINDER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often the run loop re-checks the DB when nothing looks wrong.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Consecutive failed recoveries before the watcher stops trying.
+pub const MAX_RECOVERY_ATTEMPTS: u32 = 5;
+
+/// Self-check bookkeeping for the watcher run loop, reported through the
+/// `health` control command.
+#[derive(Debug)]
+pub struct Health {
+    db_error: Option<String>,
+    watcher_alive: bool,
+    last_error: Option<String>,
+    /// Successful recoveries so far.
+    pub restarts: u32,
+    /// Failed recoveries since the last success.
+    pub failures: u32,
+    next_check: Instant,
+    next_retry: Instant,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Health {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            db_error: None,
+            watcher_alive: true,
+            last_error: None,
+            restarts: 0,
+            failures: 0,
+            next_check: now + HEALTH_INTERVAL,
+            next_retry: now,
+        }
+    }
+
+    /// Probe the DB through a fresh connection (never creating the file)
+    /// and record the watcher thread's liveness and latest error.
+    pub fn check(&mut self, db_path: &Path, watcher_alive: bool, watcher_error: Option<String>) {
+        let probe = || -> Result<()> {
+            let conn = Connection::open_with_flags(
+                db_path,
+                rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE
+                    | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            conn.busy_timeout(Duration::from_secs(1))?;
+            libmarlin::db::check_writable(&conn)
+        };
+        self.db_error = probe().err().map(|e| format!("{e:#}"));
+        self.watcher_alive = watcher_alive;
+        let err = watcher_error
+            .or_else(|| self.db_error.clone())
+            .or_else(|| (!watcher_alive).then(|| "watcher thread exited unexpectedly".to_string()));
+        if err.is_some() {
+            self.last_error = err;
         }
+        self.next_check = Instant::now() + HEALTH_INTERVAL;
+    }
+
+    /// What is currently wrong, if anything.
+    pub fn problem(&self) -> Option<String> {
+        match (&self.db_error, self.watcher_alive) {
+            (Some(e), _) => Some(format!("db: {e}")),
+            (None, false) => Some("watcher thread is not running".into()),
+            (None, true) => None,
+        }
+    }
+
+    pub fn gave_up(&self) -> bool {
+        self.failures >= MAX_RECOVERY_ATTEMPTS
+    }
+
+    /// Unhealthy, still within the attempt budget, and past the backoff.
+    pub fn should_recover(&self, now: Instant) -> bool {
+        self.problem().is_some() && !self.gave_up() && now >= self.next_retry
+    }
+
+    pub fn recovered(&mut self) {
+        self.restarts += 1;
+        self.failures = 0;
+        self.db_error = None;
+        self.watcher_alive = true;
+        self.next_check = Instant::now();
+    }
+
+    /// Record a failed attempt; retries back off 1s, 2s, 4s, … (max 60s).
+    pub fn recovery_failed(&mut self, err: &anyhow::Error, now: Instant) {
+        self.last_error = Some(format!("{err:#}"));
+        self.failures += 1;
+        let backoff = 1u64 << self.failures.saturating_sub(1).min(6);
+        self.next_retry = now + Duration::from_secs(backoff.min(60));
     }
+
+    /// `ok`, `degraded` (recovering) or `failed` (gave up).
+    pub fn summary(&self) -> &'static str {
+        match (self.problem(), self.gave_up()) {
+            (None, _) => "ok",
+            (Some(_), false) => "degraded",
+            (Some(_), true) => "failed",
+        }
+    }
+
+    /// Multi-line report for the `health` control command.
+    pub fn report(&self) -> String {
+        format!(
+            "health: {}\ndb: {}\nwatcher: {}\nrestarts: {}\nfailed recoveries: {}\nlast error: {}\n",
+            self.summary(),
+            self.db_error.as_deref().unwrap_or("ok"),
+            if self.watcher_alive { "alive" } else { "dead" },
+            self.restarts,
+            self.failures,
+            self.last_error.as_deref().unwrap_or("none"),
+        )
+    }
+}
+
+/* ── logs ────────────────────────────────────────────────────────── */
+
+/// Print the last `lines` lines of `path`, then optionally follow it,
+/// reopening the file when the daemon rotates it.
+pub fn show_logs(path: &Path, lines: usize, follow: bool) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    if !path.exists() && !follow {
+        anyhow::bail!(
+            "no daemon log at {} – is `marlin watch daemon` running?",
+            path.display()
+        );
+    }
+
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    let all: Vec<&str> = text.lines().collect();
+    for l in &all[all.len().saturating_sub(lines)..] {
+        println!("{l}");
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut pos = text.len() as u64;
+    let mut out = std::io::stdout();
+    loop {
+        thread::sleep(Duration::from_millis(250));
+        let Ok(mut f) = std::fs::File::open(path) else {
+            continue;
+        };
+        let len = f.metadata()?.len();
+        if len < pos {
+            pos = 0; // rotated – start over on the fresh file
+        }
+        if len > pos {
+            f.seek(SeekFrom::Start(pos))?;
+            let mut buf = Vec::new();
+            f.read_to_end(&mut buf)?;
+            pos += buf.len() as u64;
+            out.write_all(&buf)?;
+            out.flush()?;
+        }
+    }
+}
+
+/* ── install-service ─────────────────────────────────────────────── */
+
+/// Service manager a definition is generated for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceKind {
+    Systemd,
+    Launchd,
+    /// XML config for the WinSW service wrapper.
+    WinSw,
+}
+
+impl ServiceKind {
+    /// The service manager of the platform we are running on.
+    pub fn native() -> Self {
+        if cfg!(target_os = "macos") {
+            ServiceKind::Launchd
+        } else if cfg!(windows) {
+            ServiceKind::WinSw
+        } else {
+            ServiceKind::Systemd
+        }
+    }
+
+    /// Where the definition is installed by default.
+    pub fn default_location(self, user: bool) -> Result<PathBuf> {
+        let base = directories::BaseDirs::new().context("cannot determine home directory")?;
+        Ok(match (self, user) {
+            (ServiceKind::Systemd, true) => {
+                base.config_dir().join("systemd/user/marlin-watch.service")
+            }
+            (ServiceKind::Systemd, false) => "/etc/systemd/system/marlin-watch.service".into(),
+            (ServiceKind::Launchd, true) => base
+                .home_dir()
+                .join("Library/LaunchAgents/com.marlin.watch.plist"),
+            (ServiceKind::Launchd, false) => "/Library/LaunchDaemons/com.marlin.watch.plist".into(),
+            (ServiceKind::WinSw, _) => base.config_dir().join("marlin/marlin-watch.xml"),
+        })
+    }
+
+    /// Command(s) that activate the installed definition.
+    fn activation_hint(self, user: bool, file: &Path) -> String {
+        match (self, user) {
+            (ServiceKind::Systemd, true) => {
+                "systemctl --user daemon-reload && systemctl --user enable --now marlin-watch"
+                    .into()
+            }
+            (ServiceKind::Systemd, false) => {
+                "sudo systemctl daemon-reload && sudo systemctl enable --now marlin-watch".into()
+            }
+            (ServiceKind::Launchd, true) => format!("launchctl load -w {}", file.display()),
+            (ServiceKind::Launchd, false) => format!("sudo launchctl load -w {}", file.display()),
+            (ServiceKind::WinSw, _) => format!(
+                "copy WinSW.exe next to {} as marlin-watch.exe, then run `marlin-watch.exe install`",
+                file.display()
+            ),
+        }
+    }
+}
+
+/// Everything a service definition needs to know.
+#[derive(Debug)]
+pub struct ServiceSpec<'a> {
+    pub exe: &'a Path,
+    pub db_path: &'a Path,
+    pub watch_path: &'a Path,
+    pub user: bool,
+}
+
+/// Render the service definition for `kind`. The service runs
+/// `marlin watch daemon <path>` with `MARLIN_DB_PATH` pinned and is
+/// restarted by the service manager if it exits with an error.
+pub fn render_service(kind: ServiceKind, spec: &ServiceSpec) -> String {
+    let exe = spec.exe.display().to_string();
+    let db = spec.db_path.display().to_string();
+    let dir = spec.watch_path.display().to_string();
+    match kind {
+        ServiceKind::Systemd => {
+            let (after, wanted_by) = if spec.user {
+                ("default.target", "default.target")
+            } else {
+                ("local-fs.target", "multi-user.target")
+            };
+            format!(
+                "[Unit]\n\
+                 Description=Marlin file watcher\n\
+                 After={after}\n\
+                 \n\
+                 [Service]\n\
+                 Type=simple\n\
+                 Environment=\"MARLIN_DB_PATH={db}\"\n\
+                 ExecStart={} watch daemon {}\n\
+                 Restart=on-failure\n\
+                 RestartSec=5\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy={wanted_by}\n",
+                systemd_quote(&exe),
+                systemd_quote(&dir),
+            )
+        }
+        ServiceKind::Launchd => format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>com.marlin.watch</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>watch</string>
+        <string>daemon</string>
+        <string>{}</string>
+    </array>
+    <key>EnvironmentVariables</key>
+    <dict>
+        <key>MARLIN_DB_PATH</key>
+        <string>{}</string>
+    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+</dict>
+</plist>
+"#,
+            xml_escape(&exe),
+            xml_escape(&dir),
+            xml_escape(&db),
+        ),
+        ServiceKind::WinSw => format!(
+            r#"<service>
+  <id>marlin-watch</id>
+  <name>Marlin file watcher</name>
+  <description>Keeps the Marlin index up to date.</description>
+  <executable>{}</executable>
+  <arguments>watch daemon "{}"</arguments>
+  <env name="MARLIN_DB_PATH" value="{}"/>
+  <onfailure action="restart" delay="5 sec"/>
+  <startmode>Automatic</startmode>
+</service>
+"#,
+            xml_escape(&exe),
+            xml_escape(&dir),
+            xml_escape(&db),
+        ),
+    }
+}
+
+fn install_service(args: &InstallServiceArgs) -> Result<()> {
+    let cfg = libmarlin::config::Config::load()?;
+    let exe = std::env::current_exe().context("locating the marlin executable")?;
+    let watch_path = args
+        .path
+        .canonicalize()
+        .with_context(|| format!("cannot watch {}", args.path.display()))?;
+    let db_path = std::path::absolute(&cfg.db_path)?;
+
+    let kind = ServiceKind::native();
+    let body = render_service(
+        kind,
+        &ServiceSpec {
+            exe: &exe,
+            db_path: &db_path,
+            watch_path: &watch_path,
+            user: args.user,
+        },
+    );
+
+    if args.print {
+        print!("{body}");
+        return Ok(());
+    }
+
+    let dest = match &args.output {
+        Some(p) => p.clone(),
+        None => kind.default_location(args.user)?,
+    };
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+    std::fs::write(&dest, body).with_context(|| format!("writing {}", dest.display()))?;
+    println!("Wrote {}", dest.display());
+    println!("Enable it with: {}", kind.activation_hint(args.user, &dest));
+    Ok(())
+}
+
+/// Quote a word for systemd's `ExecStart=` if it contains whitespace.
+fn systemd_quote(s: &str) -> String {
+    if s.chars().any(|c| c.is_whitespace() || c == '"') {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
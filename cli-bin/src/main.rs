@@ -6,31 +6,59 @@
 
 #![deny(warnings)]
 
-mod cli; // sub-command definitions and argument structs
+use marlin_cli::cli; // sub-command definitions and argument structs
 
 /* ── shared modules re-exported from libmarlin ─────────────────── */
-use libmarlin::backup::BackupManager;
-use libmarlin::db::take_dirty;
-use libmarlin::{config, db, logging, scan, utils::determine_scan_root};
+use libmarlin::backup::{BackupManager, BackupMeta};
+use libmarlin::{config, db, lock, logging, query, scan};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context as _, Result};
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
-use glob::Pattern;
-use std::{env, fs, io, path::Path, process::Command};
+use std::io::{self, Write};
+use std::{env, path::Path};
 use tracing::{debug, error, info};
-use walkdir::WalkDir;
 
-use cli::{Cli, Commands};
+use cli::timings::Timings;
+use cli::{Cli, Commands, Context, Selection};
 
 fn main() -> Result<()> {
     /* ── CLI parsing & logging ────────────────────────────────── */
+    let mut timings = Timings::start();
     let args = Cli::parse();
     if args.verbose {
         env::set_var("RUST_LOG", "debug");
     }
-    logging::init();
+    // The daemon has no terminal to write to – log to a rotating file.
+    let _log_guard = match &args.command {
+        Commands::Watch(cli::watch::WatchCmd::Daemon { .. }) => {
+            Some(logging::init_daemon(&logging::log_dir())?)
+        }
+        _ => {
+            logging::init();
+            None
+        }
+    };
+    db::set_trace_sql(args.trace_sql);
+
+    /* ── progress events for GUI wrappers ─────────────────────── */
+    let events = cli::progress::install(args.events, args.events_fd)?;
+    timings.mark("startup");
+    let show_timings = args.timings || args.verbose;
+    let result = try_main(args, &mut timings);
+    if show_timings {
+        eprint!("{}", timings.summary());
+    }
+    if events {
+        libmarlin::events::emit(libmarlin::events::Event::CommandFinished {
+            ok: result.is_ok(),
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+        });
+    }
+    result
+}
 
+fn try_main(args: Cli, timings: &mut Timings) -> Result<()> {
     /* ── shell-completion shortcut ────────────────────────────── */
     if let Commands::Completions { shell } = &args.command {
         let mut cmd = Cli::command();
@@ -38,85 +66,93 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    /* ── config & automatic backup ───────────────────────────── */
-    let cfg = config::Config::load()?; // resolves DB path
-
+    /* ── commands that never touch the index ─────────────────── */
     match &args.command {
-        Commands::Init | Commands::Backup(_) | Commands::Restore { .. } => {}
-        _ => match db::backup(&cfg.db_path) {
-            Ok(p) => info!("Pre-command auto-backup created at {}", p.display()),
-            Err(e) => error!("Failed to create pre-command auto-backup: {e}"),
-        },
+        Commands::Bench(b) => return cli::bench::run(b, args.format),
+        Commands::Demo(d) => return cli::demo::run(d, args.format),
+        _ => {}
     }
 
-    /* ── open DB (runs migrations) ───────────────────────────── */
-    let mut conn = db::open(&cfg.db_path)?;
-
-    /* ── command dispatch ────────────────────────────────────── */
-    match args.command {
-        Commands::Completions { .. } => {} // handled above
+    /* ── choosing the index must not open one ─────────────────── */
+    if let Commands::Db(cli::db::DbCmd::Use { scope, dir }) = &args.command {
+        return cli::db::run_use(*scope, dir.as_ref(), args.format);
+    }
 
-        /* ---- init ------------------------------------------------ */
-        Commands::Init => {
-            info!("Database initialised at {}", cfg.db_path.display());
-            let cwd = env::current_dir().context("getting current directory")?;
-            let count = scan::scan_directory(&mut conn, &cwd).context("initial scan failed")?;
-            info!("Initial scan complete – indexed/updated {count} files");
+    /* ── watcher control needs no DB ──────────────────────────── */
+    if let Commands::Watch(w) = &args.command {
+        if !w.needs_db() {
+            return cli::watch::run_control(w, args.format);
         }
+    }
 
-        /* ---- scan ------------------------------------------------ */
-        Commands::Scan { dirty, paths } => {
-            let scan_paths: Vec<std::path::PathBuf> = if paths.is_empty() {
-                vec![env::current_dir()?]
-            } else {
-                paths.into_iter().collect()
-            };
+    /* ── several indexes: only search fans out ───────────────── */
+    if args.db.len() > 1 {
+        for p in &args.db {
+            ensure_local_db(p, args.allow_network_db)?;
+        }
+        let Commands::Search(search) = &args.command else {
+            bail!("only `search` accepts more than one --db");
+        };
+        return cli::search::run_multi(&args.db, search, args.format, args.utc);
+    }
 
-            if dirty {
-                let dirty_ids = take_dirty(&conn)?;
-                for id in dirty_ids {
-                    let path: String =
-                        conn.query_row("SELECT path FROM files WHERE id = ?1", [id], |r| r.get(0))?;
-                    scan::scan_directory(&mut conn, Path::new(&path))?;
-                }
-            } else {
-                for p in scan_paths {
-                    scan::scan_directory(&mut conn, &p)?;
-                }
+    /* ── config & automatic backup ───────────────────────────── */
+    let mut cfg = match args.db.first() {
+        Some(p) => {
+            let mut cfg = config::Config::for_db_path(p);
+            if let Some(dir) = env::var_os("MARLIN_BACKUPS_DIR") {
+                cfg.backups_dir = dir.into();
             }
+            cfg
         }
+        None => config::Config::load()?, // resolves DB path
+    };
+    if let Some(dir) = &args.backup_dir {
+        cfg.backups_dir = dir.clone();
+    }
+    timings.mark("config");
 
-        /* ---- tag / attribute / search --------------------------- */
-        Commands::Tag { pattern, tag_path } => apply_tag(&conn, &pattern, &tag_path)?,
+    ensure_local_db(&cfg.db_path, args.allow_network_db)?;
 
-        Commands::Attr { action } => match action {
-            cli::AttrCmd::Set {
-                pattern,
-                key,
-                value,
-            } => attr_set(&conn, &pattern, &key, &value)?,
-            cli::AttrCmd::Ls { path } => attr_ls(&conn, &path)?,
+    match &args.command {
+        Commands::Init | Commands::Backup(_) | Commands::Restore { .. } => {}
+        cmd if cmd.is_read_only() => debug!("read-only command, skipping auto-backup"),
+        _ => match db::backup_to(&cfg.db_path, &cfg.backups_dir) {
+            Ok(p) => info!("Pre-command auto-backup created at {}", p.display()),
+            Err(e) => error!("Failed to create pre-command auto-backup: {e}"),
         },
+    }
+    timings.mark("auto-backup");
 
-        Commands::Search { query, exec } => run_search(&conn, &query, exec)?,
-
-        /* ---- maintenance ---------------------------------------- */
-        Commands::Backup(opts) => {
-            cli::backup::run(&opts, &cfg.db_path, &mut conn, args.format)?;
-        }
+    /* ── open DB (runs migrations) ───────────────────────────── */
+    let mut ctx = Context::open(cfg, args.format)?;
+    if args.ignore_locks {
+        libmarlin::lock::set_override(&ctx.marlin.conn(), true)?;
+    }
+    if args.utc {
+        ctx.time = ctx.time.clone().with_zone(libmarlin::timefmt::Zone::Utc);
+    }
+    timings.mark("db open");
 
-        Commands::Restore { backup_path } => {
-            drop(conn); // close connection so the restore can overwrite the DB file
+    /* ── command dispatch ────────────────────────────────────── */
+    match args.command {
+        Commands::Restore {
+            backup_path,
+            to: None,
+            force,
+        } => {
+            let cfg = ctx.cfg.clone();
+            drop(ctx); // close connection so the restore can overwrite the DB file
 
             if backup_path.exists() {
+                BackupMeta::load(&backup_path)?.ensure_compatible(force)?;
                 // User pointed to an actual backup file on disk
                 db::restore(&backup_path, &cfg.db_path).with_context(|| {
                     format!("Failed to restore DB from {}", backup_path.display())
                 })?;
             } else {
-                // Assume they passed just the file-name that lives in the standard backups dir
-                let backups_dir = cfg.db_path.parent().unwrap().join("backups");
-                let manager = BackupManager::new(&cfg.db_path, &backups_dir)?;
+                // Assume they passed just the file-name that lives in the backups dir
+                let manager = BackupManager::new(&cfg.db_path, &cfg.backups_dir)?.force(force);
 
                 let name = backup_path
                     .file_name()
@@ -137,270 +173,267 @@ fn main() -> Result<()> {
             info!("Successfully opened restored database.");
         }
 
-        /* ---- passthrough sub-modules ---------------------------- */
-        Commands::Link(link_cmd) => cli::link::run(&link_cmd, &mut conn, args.format)?,
-        Commands::Coll(coll_cmd) => cli::coll::run(&coll_cmd, &mut conn, args.format)?,
-        Commands::View(view_cmd) => cli::view::run(&view_cmd, &mut conn, args.format)?,
-        Commands::State(state_cmd) => cli::state::run(&state_cmd, &mut conn, args.format)?,
-        Commands::Task(task_cmd) => cli::task::run(&task_cmd, &mut conn, args.format)?,
-        Commands::Remind(rm_cmd) => cli::remind::run(&rm_cmd, &mut conn, args.format)?,
-        Commands::Annotate(a_cmd) => cli::annotate::run(&a_cmd, &mut conn, args.format)?,
-        Commands::Version(v_cmd) => cli::version::run(&v_cmd, &mut conn, args.format)?,
-        Commands::Event(e_cmd) => cli::event::run(&e_cmd, &mut conn, args.format)?,
-        Commands::Watch(watch_cmd) => cli::watch::run(&watch_cmd, &mut conn, args.format)?,
+        cmd => run_command(cmd, &mut ctx)?,
     }
+    timings.mark("command");
 
     Ok(())
 }
 
-/* ─────────────────── helpers & sub-routines ─────────────────── */
+/// Execute one command against the open index. `marlin run` feeds every
+/// line of its script through here as well.
+fn run_command(command: Commands, ctx: &mut Context) -> Result<()> {
+    match command {
+        Commands::Completions { .. } => {} // handled by main
+        Commands::Restore { to: None, .. } => unreachable!("in-place restore is handled by main"),
 
-/* ---------- TAGS ---------- */
-fn apply_tag(conn: &rusqlite::Connection, pattern: &str, tag_path: &str) -> Result<()> {
-    let leaf_tag_id = db::ensure_tag_path(conn, tag_path)?;
-    let mut tag_ids = Vec::new();
-    let mut current = Some(leaf_tag_id);
-    while let Some(id) = current {
-        tag_ids.push(id);
-        current = conn.query_row("SELECT parent_id FROM tags WHERE id=?1", [id], |r| {
-            r.get::<_, Option<i64>>(0)
-        })?;
-    }
-
-    let expanded = shellexpand::tilde(pattern).into_owned();
-    let pat =
-        Pattern::new(&expanded).with_context(|| format!("Invalid glob pattern `{expanded}`"))?;
-    let root = determine_scan_root(&expanded);
-
-    let mut stmt_file = conn.prepare("SELECT id FROM files WHERE path=?1")?;
-    let mut stmt_insert =
-        conn.prepare("INSERT OR IGNORE INTO file_tags(file_id, tag_id) VALUES (?1, ?2)")?;
-
-    let mut count = 0usize;
-    for entry in WalkDir::new(&root)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file())
-    {
-        let p = entry.path().to_string_lossy();
-        if !pat.matches(&p) {
-            continue;
+        /* ---- init ------------------------------------------------ */
+        Commands::Init => {
+            info!("Database initialised at {}", ctx.cfg.db_path.display());
+            let cwd = env::current_dir().context("getting current directory")?;
+            let count = ctx.marlin.scan(&[cwd]).context("initial scan failed")?;
+            info!("Initial scan complete – indexed/updated {count} files");
         }
 
-        match stmt_file.query_row([p.as_ref()], |r| r.get::<_, i64>(0)) {
-            Ok(fid) => {
-                let mut newly = false;
-                for &tid in &tag_ids {
-                    if stmt_insert.execute([fid, tid])? > 0 {
-                        newly = true;
-                    }
-                }
-                if newly {
-                    info!(file=%p, tag=tag_path, "tagged");
-                    count += 1;
+        /* ---- scan ------------------------------------------------ */
+        Commands::Scan {
+            dirty,
+            paths,
+            on_busy,
+            no_ignore,
+            prune,
+            keep_metadata,
+        } => {
+            let scan_paths: Vec<std::path::PathBuf> = if dirty {
+                let conn = ctx.marlin.conn();
+                db::take_dirty(&conn)?
+                    .into_iter()
+                    .map(|id| db::file_path(&conn, id).map(Into::into))
+                    .collect::<Result<_>>()?
+            } else if paths.is_empty() {
+                vec![env::current_dir()?]
+            } else {
+                paths
+            };
+            let opts = libmarlin::scan::ScanOptions {
+                ignore_files: !no_ignore,
+                prune,
+                keep_metadata,
+            };
+            let report = ctx.marlin.scan_opts(&scan_paths, on_busy, &opts)?;
+            let pruned = &report.pruned;
+            if prune && matches!(ctx.format, cli::Format::Text) {
+                if keep_metadata {
+                    writeln!(
+                        ctx.output,
+                        "Kept {} missing file(s) as tombstones",
+                        pruned.files
+                    )?;
+                } else {
+                    let counts: Vec<String> = pruned
+                        .counts()
+                        .iter()
+                        .map(|(name, n)| format!("{name}: {n}"))
+                        .collect();
+                    writeln!(
+                        ctx.output,
+                        "Pruned {} missing file(s) ({})",
+                        pruned.files,
+                        counts.join(", ")
+                    )?;
                 }
             }
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                error!(file=%p, "not indexed – run `marlin scan` first")
-            }
-            Err(e) => error!(file=%p, error=%e, "could not lookup file ID"),
         }
-    }
 
-    info!("Applied tag '{}' to {} file(s).", tag_path, count);
-    Ok(())
-}
+        Commands::Reindex(args) => cli::reindex::run(&args, ctx)?,
+        Commands::Prune(args) => cli::prune::run(&args, ctx)?,
 
-/* ---------- ATTRIBUTES ---------- */
-fn attr_set(conn: &rusqlite::Connection, pattern: &str, key: &str, value: &str) -> Result<()> {
-    let expanded = shellexpand::tilde(pattern).into_owned();
-    let pat =
-        Pattern::new(&expanded).with_context(|| format!("Invalid glob pattern `{expanded}`"))?;
-    let root = determine_scan_root(&expanded);
-
-    let mut stmt_file = conn.prepare("SELECT id FROM files WHERE path=?1")?;
-    let mut count = 0usize;
-
-    for entry in WalkDir::new(&root)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file())
-    {
-        let p = entry.path().to_string_lossy();
-        if !pat.matches(&p) {
-            continue;
+        /* ---- tag / attribute / search --------------------------- */
+        Commands::Tag {
+            pattern,
+            tag_path,
+            view,
+            create,
+            no_create,
+        } => {
+            let sel = Selection::new(pattern, view)?;
+            let conn = ctx.marlin.conn();
+            let files = select_files(&conn, &sel)?;
+            let create = create || !(no_create || db::strict_tags(&conn)?);
+            let count = db::tag_files(&conn, &files, &tag_path, create)?;
+            info!("Applied tag '{}' to {} file(s).", tag_path, count);
         }
 
-        match stmt_file.query_row([p.as_ref()], |r| r.get::<_, i64>(0)) {
-            Ok(fid) => {
-                db::upsert_attr(conn, fid, key, value)?;
-                info!(file=%p, key, value, "attr set");
-                count += 1;
+        Commands::Tags(tags_cmd) => cli::tags::run(&tags_cmd, ctx)?,
+        Commands::Config(config_cmd) => cli::config::run(&config_cmd, ctx)?,
+
+        Commands::Attr { action } => match action {
+            cli::AttrCmd::Set {
+                pattern,
+                key,
+                value,
+                view,
+                expires,
+            } => {
+                let (sel, key, value) = match (view.as_deref(), pattern, key, value) {
+                    (Some([view, key, value]), ..) => {
+                        (Selection::View(view.clone()), key.clone(), value.clone())
+                    }
+                    (None, Some(pattern), Some(key), Some(value)) => {
+                        (Selection::Pattern(pattern), key, value)
+                    }
+                    _ => bail!("give either PATTERN KEY VALUE or --view VIEW KEY VALUE"),
+                };
+                let expires_at = match expires {
+                    Some(ttl) => {
+                        let ttl = libmarlin::utils::parse_duration(&ttl)?.as_secs() as i64;
+                        Some(chrono::Utc::now().timestamp().saturating_add(ttl))
+                    }
+                    None => None,
+                };
+                let conn = ctx.marlin.conn();
+                let files = select_files(&conn, &sel)?;
+                let n = db::attr_set_files(&conn, &files, &key, &value, expires_at)?;
+                info!("Attribute '{}={}' set on {} file(s).", key, value, n);
             }
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                error!(file=%p, "not indexed – run `marlin scan` first")
+            cli::AttrCmd::Ls { path } => {
+                let time = ctx.time.clone();
+                let (conn, out) = ctx.split();
+                let fid = db::file_id(&conn, &path.to_string_lossy())?;
+                for (k, v, expires_at) in db::file_attrs(&conn, fid)? {
+                    match expires_at.and_then(|t| time.epoch(t)) {
+                        Some(t) => writeln!(out, "{k} = {v}  (expires {t})")?,
+                        None => writeln!(out, "{k} = {v}")?,
+                    }
+                }
             }
-            Err(e) => error!(file=%p, error=%e, "could not lookup file ID"),
-        }
-    }
+        },
 
-    info!("Attribute '{}={}' set on {} file(s).", key, value, count);
-    Ok(())
-}
+        Commands::Search(args) => cli::search::run(&args, ctx)?,
+        Commands::Embed(args) => cli::embed::run(&args, ctx)?,
+        Commands::Classify(args) => cli::classify::run(&args, ctx)?,
+        Commands::History(history_cmd) => cli::history::run(&history_cmd, ctx)?,
+
+        /* ---- rating / favorites --------------------------------- */
+        Commands::Rate { rating, pattern } => {
+            let conn = ctx.marlin.conn();
+            let files = db::match_files(&conn, &pattern)?;
+            lock::check_files(&conn, &files)?;
+            for (fid, path) in &files {
+                db::set_rating(&conn, *fid, rating)?;
+                info!(file=%path, rating, "rated");
+            }
+            info!("Rated {} file(s) with {} star(s).", files.len(), rating);
+        }
+        Commands::Fav(fav_cmd) => cli::fav::run(&fav_cmd, ctx)?,
+        Commands::Label(label_cmd) => cli::label::run(&label_cmd, ctx)?,
+        Commands::Lock(args) => cli::lock::run_lock(&args, ctx)?,
+        Commands::Unlock(args) => cli::lock::run_unlock(&args, ctx)?,
 
-fn attr_ls(conn: &rusqlite::Connection, path: &Path) -> Result<()> {
-    let fid = db::file_id(conn, &path.to_string_lossy())?;
-    let mut stmt =
-        conn.prepare("SELECT key, value FROM attributes WHERE file_id=?1 ORDER BY key")?;
-    for row in stmt.query_map([fid], |r| {
-        Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
-    })? {
-        let (k, v) = row?;
-        println!("{k} = {v}");
-    }
-    Ok(())
-}
+        /* ---- maintenance ---------------------------------------- */
+        Commands::Backup(opts) => cli::backup::run(&opts, ctx)?,
 
-/* ---------- SEARCH ---------- */
-fn run_search(conn: &rusqlite::Connection, raw_query: &str, exec: Option<String>) -> Result<()> {
-    let mut parts = Vec::new();
-    let toks = shlex::split(raw_query).unwrap_or_else(|| vec![raw_query.to_string()]);
-    for tok in toks {
-        if ["AND", "OR", "NOT"].contains(&tok.as_str()) {
-            parts.push(tok);
-        } else if let Some(tag) = tok.strip_prefix("tag:") {
-            for (i, seg) in tag.split('/').filter(|s| !s.is_empty()).enumerate() {
-                if i > 0 {
-                    parts.push("AND".into());
+        Commands::Restore {
+            backup_path,
+            to: Some(target),
+            force,
+        } => {
+            if backup_path.exists() {
+                if target.exists() {
+                    anyhow::bail!("Refusing to overwrite existing file: {}", target.display());
                 }
-                parts.push(format!("tags_text:{}", escape_fts(seg)));
-            }
-        } else if let Some(attr) = tok.strip_prefix("attr:") {
-            let mut kv = attr.splitn(2, '=');
-            let key = kv.next().unwrap();
-            if let Some(val) = kv.next() {
-                parts.push(format!("attrs_text:{}", escape_fts(key)));
-                parts.push("AND".into());
-                parts.push(format!("attrs_text:{}", escape_fts(val)));
+                BackupMeta::load(&backup_path)?.ensure_compatible(force)?;
+                db::restore(&backup_path, &target).with_context(|| {
+                    format!(
+                        "Failed to restore {} to {}",
+                        backup_path.display(),
+                        target.display()
+                    )
+                })?;
             } else {
-                parts.push(format!("attrs_text:{}", escape_fts(key)));
+                let manager =
+                    BackupManager::new(&ctx.cfg.db_path, &ctx.cfg.backups_dir)?.force(force);
+                let name = backup_path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .context("invalid backup file name")?;
+                manager.restore_to(name, &target)?;
             }
-        } else {
-            parts.push(escape_fts(&tok));
+            writeln!(
+                ctx.output,
+                "Restored {} as new DB {}",
+                backup_path.display(),
+                target.display()
+            )?;
         }
-    }
-    let fts_expr = parts.join(" ");
-    debug!("FTS MATCH expression: {fts_expr}");
-
-    let mut stmt = conn.prepare(
-        r#"
-        SELECT f.path
-          FROM files_fts
-          JOIN files f ON f.rowid = files_fts.rowid
-         WHERE files_fts MATCH ?1
-         ORDER BY rank
-        "#,
-    )?;
-    let mut hits: Vec<String> = stmt
-        .query_map([&fts_expr], |r| r.get::<_, String>(0))?
-        .filter_map(Result::ok)
-        .collect();
-
-    if hits.is_empty() && !raw_query.contains(':') {
-        hits = naive_substring_search(conn, raw_query)?;
-    }
 
-    if let Some(cmd_tpl) = exec {
-        run_exec(&hits, &cmd_tpl)?;
-    } else if hits.is_empty() {
-        eprintln!("No matches for query: `{raw_query}` (FTS expr: `{fts_expr}`)");
-    } else {
-        for p in hits {
-            println!("{p}");
-        }
+        /* ---- passthrough sub-modules ---------------------------- */
+        Commands::Link(link_cmd) => cli::link::run(&link_cmd, ctx)?,
+        Commands::Coll(coll_cmd) => cli::coll::run(&coll_cmd, ctx)?,
+        Commands::View(view_cmd) => cli::view::run(&view_cmd, ctx)?,
+        Commands::Action(action_cmd) => cli::action::run(&action_cmd, ctx)?,
+        Commands::Snapshot(cmd) => cli::snapshot::run(&cmd, ctx)?,
+        Commands::Sync(cmd) => cli::sync::run(&cmd, ctx)?,
+        Commands::Organize(args) => cli::organize::run(&args, ctx)?,
+        Commands::Du(du_args) => cli::du::run(&du_args, ctx)?,
+        Commands::Stale(stale_args) => cli::stale::run(&stale_args, ctx)?,
+        Commands::Stats(stats_cmd) => cli::stats::run(&stats_cmd, ctx)?,
+        Commands::Status(args) => cli::status::run(&args, ctx)?,
+        Commands::ExplainFile(args) => cli::explain::run(&args, ctx)?,
+        Commands::State(state_cmd) => cli::state::run(&state_cmd, ctx)?,
+        Commands::Task(task_cmd) => cli::task::run(&task_cmd, ctx)?,
+        Commands::Remind(rm_cmd) => cli::remind::run(&rm_cmd, ctx)?,
+        Commands::Annotate(a_cmd) => cli::annotate::run(&a_cmd, ctx)?,
+        Commands::Version(v_cmd) => cli::version::run(&v_cmd, ctx)?,
+        Commands::Event(e_cmd) => cli::event::run(&e_cmd, ctx)?,
+        Commands::Db(db_cmd) => cli::db::run(&db_cmd, ctx)?,
+        Commands::Gc(gc_args) => cli::gc::run(&gc_args, ctx)?,
+        Commands::Doctor(doc_args) => cli::doctor::run(&doc_args, ctx)?,
+        Commands::Warm(warm_args) => cli::warm::run(&warm_args, ctx)?,
+        Commands::Bench(b) => cli::bench::run(&b, ctx.format)?,
+        Commands::Demo(d) => cli::demo::run(&d, ctx.format)?,
+        Commands::Sql(sql_args) => cli::sql::run(&sql_args, ctx)?,
+        Commands::Root(root_cmd) => cli::root::run(&root_cmd, ctx)?,
+        Commands::Token(token_cmd) => cli::token::run(&token_cmd, ctx)?,
+        Commands::Watch(watch_cmd) => cli::watch::run(&watch_cmd, ctx)?,
+        Commands::Run(run_args) => cli::run::run(&run_args, ctx, run_command)?,
+        Commands::Shell(shell_args) => cli::shell::run(&shell_args, ctx, run_command)?,
     }
-    Ok(())
-}
 
-fn naive_substring_search(conn: &rusqlite::Connection, term: &str) -> Result<Vec<String>> {
-    let needle = term.to_lowercase();
-    let mut stmt = conn.prepare("SELECT path FROM files")?;
-    let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
-
-    let mut out = Vec::new();
-    for p in rows {
-        let p = p?;
-        if p.to_lowercase().contains(&needle) {
-            out.push(p.clone());
-            continue;
-        }
-        if let Ok(meta) = fs::metadata(&p) {
-            if meta.len() > 65_536 {
-                continue;
-            }
-        }
-        if let Ok(body) = fs::read_to_string(&p) {
-            if body.to_lowercase().contains(&needle) {
-                out.push(p);
-            }
-        }
-    }
-    Ok(out)
+    Ok(())
 }
 
-fn run_exec(paths: &[String], cmd_tpl: &str) -> Result<()> {
-    let mut ran_without_placeholder = false;
+/* ─────────────────── helpers & sub-routines ─────────────────── */
 
-    if paths.is_empty() && !cmd_tpl.contains("{}") {
-        if let Some(mut parts) = shlex::split(cmd_tpl) {
-            if !parts.is_empty() {
-                let prog = parts.remove(0);
-                let status = Command::new(&prog).args(parts).status()?;
-                if !status.success() {
-                    error!(command=%cmd_tpl, code=?status.code(), "command failed");
-                }
-            }
-        }
-        ran_without_placeholder = true;
-    }
-
-    if !ran_without_placeholder {
-        for p in paths {
-            let quoted = shlex::try_quote(p).unwrap_or_else(|_| p.into());
-            let final_cmd = if cmd_tpl.contains("{}") {
-                cmd_tpl.replace("{}", &quoted)
-            } else {
-                format!("{cmd_tpl} {quoted}")
-            };
-            if let Some(mut parts) = shlex::split(&final_cmd) {
-                if parts.is_empty() {
-                    continue;
-                }
-                let prog = parts.remove(0);
-                let status = Command::new(&prog).args(parts).status()?;
-                if !status.success() {
-                    error!(file=%p, command=%final_cmd, code=?status.code(), "command failed");
-                }
-            }
+/// Refuse an index on a network filesystem unless `allow` is set.
+fn ensure_local_db(db_path: &Path, allow: bool) -> Result<()> {
+    if let Some(fs) = db::network_fs(db_path) {
+        if !allow {
+            bail!(
+                "the index {} is on a network filesystem ({fs}), where SQLite's WAL \
+                 journal can corrupt it\n\
+                 keep it on a local disk (set MARLIN_DB_PATH) and put snapshots on the \
+                 share with `--backup-dir`, or pass --allow-network-db to open it anyway",
+                db_path.display()
+            );
         }
     }
     Ok(())
 }
 
-fn escape_fts(term: &str) -> String {
-    if term.contains(|c: char| c.is_whitespace() || "-:()\"".contains(c))
-        || ["AND", "OR", "NOT", "NEAR"].contains(&term.to_uppercase().as_str())
-    {
-        format!("\"{}\"", term.replace('"', "\"\""))
-    } else {
-        term.to_string()
+/// Resolve a `Selection` to indexed `(id, path)` pairs.
+fn select_files(conn: &rusqlite::Connection, sel: &Selection) -> Result<Vec<(i64, String)>> {
+    match sel {
+        Selection::Pattern(p) => scan::glob_indexed_files(conn, p),
+        Selection::View(v) => query::view_files(conn, v),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{apply_tag, attr_set, escape_fts, naive_substring_search, run_exec};
+    use super::cli::run_exec;
     use assert_cmd::Command;
+    use libmarlin::query::{escape_fts, naive_substring_search};
     use tempfile::tempdir;
 
     #[test]
@@ -493,43 +526,22 @@ mod tests {
             backups_dir.exists(),
             "Backups directory should exist after scan"
         );
-        let backups: Vec<_> = backups_dir.read_dir().unwrap().collect();
+        let backups: Vec<_> = backups_dir
+            .read_dir()
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().extension() == Some("db".as_ref()))
+            .collect();
         assert_eq!(backups.len(), 1, "One backup should be created for scan");
     }
 
-    #[test]
-    fn test_annotate_stub() {
-        let tmp = tempdir().unwrap();
-        let mut cmd = Command::cargo_bin("marlin").unwrap();
-        cmd.env("MARLIN_DB_PATH", tmp.path().join("index.db"));
-        cmd.arg("annotate").arg("add").arg("file.txt").arg("note");
-        cmd.assert()
-            .failure()
-            .stderr(predicates::str::contains("not yet implemented"));
-    }
-
-    #[test]
-    fn test_event_stub() {
-        let tmp = tempdir().unwrap();
-        let mut cmd = Command::cargo_bin("marlin").unwrap();
-        cmd.env("MARLIN_DB_PATH", tmp.path().join("index.db"));
-        cmd.arg("event")
-            .arg("add")
-            .arg("file.txt")
-            .arg("2025-05-20")
-            .arg("desc");
-        cmd.assert()
-            .failure()
-            .stderr(predicates::str::contains("not yet implemented"));
-    }
-
     fn open_mem() -> rusqlite::Connection {
         libmarlin::db::open(":memory:").expect("open in-memory DB")
     }
 
     #[test]
     fn test_tagging_and_attributes_update_db() {
-        use libmarlin::scan::scan_directory;
+        use libmarlin::db::{attr_set_files, tag_files};
+        use libmarlin::scan::{glob_indexed_files, scan_directory};
         use std::fs::File;
 
         let tmp = tempdir().unwrap();
@@ -539,8 +551,10 @@ mod tests {
         let mut conn = open_mem();
         scan_directory(&mut conn, tmp.path()).unwrap();
 
-        apply_tag(&conn, file_path.to_str().unwrap(), "foo/bar").unwrap();
-        attr_set(&conn, file_path.to_str().unwrap(), "k", "v").unwrap();
+        let files = glob_indexed_files(&conn, file_path.to_str().unwrap()).unwrap();
+        assert_eq!(files.len(), 1);
+        tag_files(&conn, &files, "foo/bar", true).unwrap();
+        attr_set_files(&conn, &files, "k", "v", None).unwrap();
 
         let tag: String = conn
             .query_row(
@@ -581,6 +595,7 @@ mod tests {
         std::env::set_var("LOGFILE", &log);
 
         run_exec(
+            &conn,
             &[f1.to_string_lossy().to_string()],
             &format!("sh {} {{}}", script.display()),
         )
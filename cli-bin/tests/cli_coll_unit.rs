@@ -1,19 +1,10 @@
-mod cli {
-    #[derive(Clone, Copy, Debug)]
-    pub enum Format {
-        Text,
-        Json,
-    }
-}
-
-#[path = "../src/cli/coll.rs"]
-mod coll;
-
-use libmarlin::db;
+use libmarlin::{db, Marlin};
+use marlin_cli::cli::{coll, Context, Format};
 
 #[test]
 fn coll_run_creates_and_adds() {
-    let mut conn = db::open(":memory:").unwrap();
+    let mut ctx = Context::new(Marlin::open_at(":memory:").unwrap(), Format::Text).buffered();
+    let conn = ctx.marlin.conn();
     conn.execute(
         "INSERT INTO files(path,size,mtime) VALUES ('a.txt',0,0)",
         [],
@@ -25,10 +16,14 @@ fn coll_run_creates_and_adds() {
     )
     .unwrap();
 
+    drop(conn);
+
     let create = coll::CollCmd::Create(coll::CreateArgs { name: "Set".into() });
-    coll::run(&create, &mut conn, cli::Format::Text).unwrap();
+    coll::run(&create, &mut ctx).unwrap();
 
-    let coll_id: i64 = conn
+    let coll_id: i64 = ctx
+        .marlin
+        .conn()
         .query_row("SELECT id FROM collections WHERE name='Set'", [], |r| {
             r.get(0)
         })
@@ -36,11 +31,14 @@ fn coll_run_creates_and_adds() {
 
     let add = coll::CollCmd::Add(coll::AddArgs {
         name: "Set".into(),
-        file_pattern: "*.txt".into(),
+        file_pattern: Some("*.txt".into()),
+        view: None,
     });
-    coll::run(&add, &mut conn, cli::Format::Text).unwrap();
+    coll::run(&add, &mut ctx).unwrap();
 
-    let cnt: i64 = conn
+    let cnt: i64 = ctx
+        .marlin
+        .conn()
         .query_row(
             "SELECT COUNT(*) FROM collection_files WHERE collection_id=?1",
             [coll_id],
@@ -49,6 +47,20 @@ fn coll_run_creates_and_adds() {
         .unwrap();
     assert_eq!(cnt, 2);
 
-    let list = coll::CollCmd::List(coll::ListArgs { name: "Set".into() });
-    coll::run(&list, &mut conn, cli::Format::Text).unwrap();
+    let list = coll::CollCmd::List(coll::ListArgs {
+        name: "Set".into(),
+        relative: None,
+    });
+    coll::run(&list, &mut ctx).unwrap();
+
+    let stats = coll::CollCmd::Stats(coll::StatsArgs { name: "Set".into() });
+    coll::run(&stats, &mut ctx).unwrap();
+
+    db::ensure_collection(&ctx.marlin.conn(), "Other").unwrap();
+    let diff = coll::CollCmd::Diff(coll::DiffArgs {
+        a: "Set".into(),
+        b: "Other".into(),
+        relative: None,
+    });
+    coll::run(&diff, &mut ctx).unwrap();
 }
@@ -1,19 +1,10 @@
-mod cli {
-    #[derive(Clone, Copy, Debug)]
-    pub enum Format {
-        Text,
-        Json,
-    }
-}
-
-#[path = "../src/cli/link.rs"]
-mod link;
-
-use libmarlin::db;
+use libmarlin::Marlin;
+use marlin_cli::cli::{link, Context, Format};
 
 #[test]
 fn link_run_add_and_rm() {
-    let mut conn = db::open(":memory:").unwrap();
+    let mut ctx = Context::new(Marlin::open_at(":memory:").unwrap(), Format::Text).buffered();
+    let conn = ctx.marlin.conn();
     conn.execute(
         "INSERT INTO files(path,size,mtime) VALUES ('foo.txt',0,0)",
         [],
@@ -25,13 +16,18 @@ fn link_run_add_and_rm() {
     )
     .unwrap();
 
+    drop(conn);
+
     let add = link::LinkCmd::Add(link::LinkArgs {
         from: "foo.txt".into(),
         to: "bar.txt".into(),
         r#type: None,
+        with_inverse: false,
     });
-    link::run(&add, &mut conn, cli::Format::Text).unwrap();
-    let count: i64 = conn
+    link::run(&add, &mut ctx).unwrap();
+    let count: i64 = ctx
+        .marlin
+        .conn()
         .query_row("SELECT COUNT(*) FROM links", [], |r| r.get(0))
         .unwrap();
     assert_eq!(count, 1);
@@ -40,16 +36,20 @@ fn link_run_add_and_rm() {
         pattern: "foo.txt".into(),
         direction: None,
         r#type: None,
+        long: false,
     });
-    link::run(&list, &mut conn, cli::Format::Text).unwrap();
+    link::run(&list, &mut ctx).unwrap();
 
     let rm = link::LinkCmd::Rm(link::LinkArgs {
         from: "foo.txt".into(),
         to: "bar.txt".into(),
         r#type: None,
+        with_inverse: false,
     });
-    link::run(&rm, &mut conn, cli::Format::Text).unwrap();
-    let remaining: i64 = conn
+    link::run(&rm, &mut ctx).unwrap();
+    let remaining: i64 = ctx
+        .marlin
+        .conn()
         .query_row("SELECT COUNT(*) FROM links", [], |r| r.get(0))
         .unwrap();
     assert_eq!(remaining, 0);
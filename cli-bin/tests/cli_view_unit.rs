@@ -1,32 +1,30 @@
-mod cli {
-    #[derive(Clone, Copy, Debug)]
-    pub enum Format {
-        Text,
-        Json,
-    }
-}
-
-#[path = "../src/cli/view.rs"]
-mod view;
+use libmarlin::{db, Marlin};
+use marlin_cli::cli::{view, Context, Format};
 
-use libmarlin::db;
+fn mem_ctx() -> Context {
+    Context::new(Marlin::open_at(":memory:").unwrap(), Format::Text).buffered()
+}
 
 #[test]
 fn view_run_save_and_exec() {
-    let mut conn = db::open(":memory:").unwrap();
-    conn.execute(
-        "INSERT INTO files(path,size,mtime) VALUES ('TODO.txt',0,0)",
-        [],
-    )
-    .unwrap();
+    let mut ctx = mem_ctx();
+    ctx.marlin
+        .conn()
+        .execute(
+            "INSERT INTO files(path,size,mtime) VALUES ('TODO.txt',0,0)",
+            [],
+        )
+        .unwrap();
 
     let save = view::ViewCmd::Save(view::ArgsSave {
         view_name: "tasks".into(),
         query: "TODO".into(),
     });
-    view::run(&save, &mut conn, cli::Format::Text).unwrap();
+    view::run(&save, &mut ctx).unwrap();
 
-    let stored: String = conn
+    let stored: String = ctx
+        .marlin
+        .conn()
         .query_row("SELECT query FROM views WHERE name='tasks'", [], |r| {
             r.get(0)
         })
@@ -34,10 +32,48 @@ fn view_run_save_and_exec() {
     assert_eq!(stored, "TODO");
 
     let list = view::ViewCmd::List;
-    view::run(&list, &mut conn, cli::Format::Text).unwrap();
+    ctx.output.take();
+    view::run(&list, &mut ctx).unwrap();
+    assert_eq!(ctx.output.take(), "tasks: TODO\n");
 
     let exec = view::ViewCmd::Exec(view::ArgsExec {
         view_name: "tasks".into(),
+        relative: None,
+    });
+    view::run(&exec, &mut ctx).unwrap();
+    assert_eq!(ctx.output.take(), "TODO.txt\n");
+}
+
+#[test]
+fn view_watch_runs_exec_for_new_matches() {
+    use std::{fs, thread, time::Duration};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let watched = tmp.path().join("inbox");
+    fs::create_dir(&watched).unwrap();
+    let log = tmp.path().join("hits.log");
+
+    let mut ctx = mem_ctx();
+    db::save_view(&ctx.marlin.conn(), "reports", "report").unwrap();
+
+    let new_file = watched.join("report.txt");
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(400));
+        fs::write(&new_file, "quarterly numbers").unwrap();
+        thread::sleep(Duration::from_millis(1500));
+        unsafe { libc::raise(libc::SIGINT) };
     });
-    view::run(&exec, &mut conn, cli::Format::Text).unwrap();
+
+    let watch = view::ViewCmd::Watch(view::ArgsWatch {
+        view_name: "reports".into(),
+        path: watched.clone(),
+        exec: Some(format!("sh -c 'echo \"$1\" >> {}' _ {{}}", log.display())),
+        debounce_ms: 50,
+        relative: None,
+    });
+    view::run(&watch, &mut ctx).unwrap();
+    t.join().unwrap();
+
+    let logged = fs::read_to_string(&log).unwrap();
+    assert!(logged.contains("report.txt"), "hook output: {logged}");
 }
@@ -4,6 +4,7 @@
 //! Run with `cargo test --test e2e` (CI does) or `cargo test`.
 
 use assert_cmd::prelude::*;
+use libmarlin::test_utils::{fixture_tree, FixtureSpec};
 use predicates::prelude::*;
 use std::{fs, path::PathBuf, process::Command};
 use tempfile::tempdir;
@@ -13,22 +14,6 @@ fn marlin_bin() -> PathBuf {
     PathBuf::from(env!("CARGO_BIN_EXE_marlin"))
 }
 
-/// Create the demo directory structure and seed files.
-fn spawn_demo_tree(root: &PathBuf) {
-    fs::create_dir_all(root.join("Projects/Alpha")).unwrap();
-    fs::create_dir_all(root.join("Projects/Beta")).unwrap();
-    fs::create_dir_all(root.join("Projects/Gamma")).unwrap();
-    fs::create_dir_all(root.join("Logs")).unwrap();
-    fs::create_dir_all(root.join("Reports")).unwrap();
-
-    fs::write(root.join("Projects/Alpha/draft1.md"), "- [ ] TODO foo\n").unwrap();
-    fs::write(root.join("Projects/Alpha/draft2.md"), "- [x] TODO foo\n").unwrap();
-    fs::write(root.join("Projects/Beta/final.md"), "done\n").unwrap();
-    fs::write(root.join("Projects/Gamma/TODO.txt"), "TODO bar\n").unwrap();
-    fs::write(root.join("Logs/app.log"), "ERROR omg\n").unwrap();
-    fs::write(root.join("Reports/Q1.pdf"), "PDF\n").unwrap();
-}
-
 /// Shorthand for “run and must succeed”.
 fn ok(cmd: &mut Command) -> assert_cmd::assert::Assert {
     cmd.assert().success()
@@ -40,7 +25,7 @@ fn full_cli_flow() -> Result<(), Box<dyn std::error::Error>> {
 
     let tmp = tempdir()?; // wiped on drop
     let demo_dir = tmp.path().join("marlin_demo");
-    spawn_demo_tree(&demo_dir);
+    fixture_tree(&demo_dir, &FixtureSpec::demo())?;
 
     let db_path = demo_dir.join("index.db");
 
@@ -76,6 +76,48 @@ fn coll_add_unknown_collection_should_fail() {
         .failure();
 }
 
+/* ───────────────────────── --view ───────────────────────────── */
+
+#[test]
+fn tag_with_view_and_pattern_should_fail() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["view", "save", "v", "foo"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["tag", "--view", "v", "*.txt", "a/b"])
+        .assert()
+        .failure()
+        .stderr(str::contains("cannot be used with"));
+
+    marlin(&tmp)
+        .args(["attr", "set", "--view", "v", "*.txt", "k", "x"])
+        .assert()
+        .failure()
+        .stderr(str::contains("cannot be used with"));
+
+    marlin(&tmp)
+        .args(["attr", "set", "--view", "v", "k"])
+        .assert()
+        .failure()
+        .stderr(str::contains("3 values required"));
+
+    marlin(&tmp)
+        .args(["tag", "--view", "missing", "a/b"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no view called 'missing'"));
+}
+
 /* ───────────────────── RESTORE (bad file) ───────────────────── */
 
 #[test]
@@ -91,3 +133,374 @@ fn restore_with_nonexistent_backup_should_fail() {
         .failure()
         .stderr(str::contains("Failed to restore"));
 }
+
+/* ───────────────────── WATCH (unknown name) ─────────────────── */
+
+#[test]
+fn watch_stop_unknown_name_should_fail() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .env("MARLIN_WATCH_REGISTRY", tmp.path().join("watchers"))
+        .args(["watch", "stop", "nope"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no watcher named `nope`"));
+}
+
+/* ───────────────────── TAG (strict mode) ───────────────────── */
+
+#[test]
+fn tag_typo_is_rejected_in_strict_mode() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("a.md");
+    std::fs::write(&file, "").unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["tag", "--no-create", file.to_str().unwrap(), "proejct/md"])
+        .assert()
+        .failure()
+        .stderr(str::contains("unknown tag `proejct/md`"));
+
+    marlin(&tmp)
+        .args(["config", "set", "tags.strict", "true"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", file.to_str().unwrap(), "proejct/md"])
+        .assert()
+        .failure()
+        .stderr(str::contains("pass --create"));
+}
+
+#[test]
+fn unsupported_extended_glob_is_explained() {
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["tag", &format!("{}/@(a|b).md", tmp.path().display()), "x"])
+        .assert()
+        .failure()
+        .stderr(str::contains("`@(…)` in glob pattern"));
+    marlin(&tmp)
+        .args(["attr", "set", "*.{md", "k", "v"])
+        .assert()
+        .failure()
+        .stderr(str::contains("unclosed `{`"));
+}
+
+/* ───────────────────── VALIDATION RULES ───────────────────── */
+
+#[test]
+fn values_breaking_validation_rules_are_rejected() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("inv.pdf");
+    std::fs::write(&file, "").unwrap();
+    let file = file.to_str().unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["config", "set", "validate.attr.invoice/amount", "^(\\d+"])
+        .assert()
+        .failure()
+        .stderr(str::contains("invalid validation regex"));
+
+    marlin(&tmp)
+        .args([
+            "config",
+            "set",
+            "validate.attr.invoice/amount",
+            "^\\d+(\\.\\d{2})?$",
+        ])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["config", "set", "validate.tag.year", "^\\d{4}$"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["attr", "set", file, "invoice/amount", "12.5"])
+        .assert()
+        .failure()
+        .stderr(str::contains(
+            "invalid value `12.5` for attribute `invoice/amount`",
+        ));
+    marlin(&tmp)
+        .args(["tag", file, "year/24"])
+        .assert()
+        .failure()
+        .stderr(str::contains("invalid tag `year/24`"));
+
+    marlin(&tmp)
+        .args(["config", "unset", "validate.tag.year"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", file, "year/24"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["config", "unset", "validate.tag.year"])
+        .assert()
+        .failure()
+        .stderr(str::contains("is not set"));
+}
+
+/* ───────────────────── ROOT (unknown path) ──────────────────── */
+
+#[test]
+fn root_rm_unregistered_path_should_fail() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["root", "rm", tmp.path().to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(str::contains("no profile registered"));
+}
+
+/* ───────────────────── RUN (forbidden command) ──────────────── */
+
+#[test]
+fn run_script_with_watch_should_fail_before_running() {
+    use predicates::prelude::*;
+
+    let tmp = tempdir().unwrap();
+    let script = tmp.path().join("bad.marlin");
+    std::fs::write(&script, "view save x foo\nwatch start .\n").unwrap();
+
+    marlin(&tmp)
+        .args(["run", script.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(str::contains("line 2: `watch` cannot be used in a script"));
+
+    marlin(&tmp)
+        .args(["view", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("x:").not());
+}
+
+#[test]
+fn events_fd_must_be_open() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["--events-fd", "987", "view", "list"])
+        .assert()
+        .failure()
+        .stderr(str::contains("--events-fd 987 is not an open descriptor"));
+}
+
+/* ───────────────────────── SEARCH ─────────────────────────────── */
+
+#[test]
+fn several_dbs_only_work_for_search() {
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args(["--db", "a.db", "--db", "b.db", "tag", "*.md", "x"])
+        .current_dir(tmp.path())
+        .assert()
+        .failure()
+        .stderr(str::contains("only `search` accepts more than one --db"));
+}
+
+#[test]
+fn search_check_reports_diagnostics() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["search", "--check", "report tags:draft"])
+        .assert()
+        .failure()
+        .stdout(str::contains(
+            "error: unknown field 'tags' at column 8 (did you mean `tag:`?)",
+        ))
+        .stderr(str::contains("query has 1 error(s)"));
+
+    marlin(&tmp)
+        .args(["search", "--check", "report \"q3"])
+        .assert()
+        .failure()
+        .stdout(str::contains("error: unbalanced quote at column 8"));
+
+    marlin(&tmp)
+        .args(["search", "--check", "report tag:draft"])
+        .assert()
+        .success()
+        .stdout(str::contains("Query OK"));
+
+    // a normal search fails with the same diagnostic instead of no hits
+    marlin(&tmp)
+        .args(["search", "OR report"])
+        .assert()
+        .failure()
+        .stderr(str::contains("`OR` has no left-hand term at column 1"));
+}
+
+/* ───────────────────── WATCH (burst guard) ──────────────────── */
+
+#[test]
+fn burst_guard_settings_are_validated() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["config", "set", "watch.burst_threshold", "many"])
+        .assert()
+        .failure()
+        .stderr(str::contains("expects a number of files"));
+    marlin(&tmp)
+        .args(["config", "set", "watch.burst_window", "soon"])
+        .assert()
+        .failure();
+
+    // unset keys report the built-in thresholds
+    marlin(&tmp)
+        .args(["config", "get", "watch.burst_threshold"])
+        .assert()
+        .success()
+        .stdout(str::contains("200"));
+    marlin(&tmp)
+        .args(["config", "set", "watch.burst_window", "2m"])
+        .assert()
+        .success();
+}
+
+/* ───────────────────────── TIME FORMAT ───────────────────────── */
+
+#[test]
+fn time_settings_are_validated() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["config", "set", "timezone", "Europe/Berlin"])
+        .assert()
+        .failure()
+        .stderr(str::contains("unknown time zone"));
+    marlin(&tmp)
+        .args(["config", "set", "time_format", "%Q"])
+        .assert()
+        .failure()
+        .stderr(str::contains("invalid time format"));
+    marlin(&tmp)
+        .args(["config", "get", "timezone"])
+        .assert()
+        .success()
+        .stdout(str::contains("timezone = local"));
+}
+
+/* ─────────────────────────── REMINDERS ───────────────────────── */
+
+#[test]
+fn remind_test_needs_a_channel() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["remind", "test"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no notification channel configured"));
+    marlin(&tmp)
+        .args(["config", "set", "notify.desktop", "maybe"])
+        .assert()
+        .failure()
+        .stderr(str::contains("expects true or false"));
+    marlin(&tmp)
+        .args(["config", "set", "notify.command", "'unterminated"])
+        .assert()
+        .failure()
+        .stderr(str::contains("cannot parse notify command"));
+}
+
+#[test]
+fn remind_add_rejects_bad_times_and_unknown_files() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["remind", "add", "nothing.md", "2h", "note"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no indexed file matches"));
+    marlin(&tmp)
+        .args(["remind", "add", "nothing.md", "someday", "note"])
+        .assert()
+        .failure()
+        .stderr(str::contains("invalid time `someday`"));
+}
+
+/* ───────────────────────── EXEC CAPTURE ──────────────────────── */
+
+#[test]
+fn exec_capture_needs_an_attr_target_and_unlocked_files() {
+    let tmp = tempdir().unwrap();
+    let docs = tmp.path().join("docs");
+    std::fs::create_dir_all(&docs).unwrap();
+    std::fs::write(docs.join("a.txt"), "x").unwrap();
+    marlin(&tmp)
+        .args(["scan", docs.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "a", "--exec-capture", "cat {}", "--into", "tag:x"])
+        .assert()
+        .failure()
+        .stderr(str::contains("--into takes `attr:<key>`"));
+    marlin(&tmp)
+        .args(["search", "a", "--exec-capture", "cat {}"])
+        .assert()
+        .failure();
+
+    marlin(&tmp)
+        .args(["lock", docs.join("a.txt").to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args([
+            "search",
+            "a",
+            "--exec-capture",
+            "cat {}",
+            "--into",
+            "attr:body",
+        ])
+        .assert()
+        .failure()
+        .stderr(str::contains("locked"));
+}
+
+#[test]
+fn import_conflict_options_exclude_each_other_and_piped_pairs() {
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args([
+            "sync",
+            "import",
+            "x.log",
+            "--strategy",
+            "mine",
+            "--interactive",
+        ])
+        .assert()
+        .failure()
+        .stderr(str::contains("cannot be used with"));
+    marlin(&tmp)
+        .args(["link", "import", "-", "--interactive"])
+        .write_stdin("/a,/b\n")
+        .assert()
+        .failure()
+        .stderr(str::contains("give the pairs as a file"));
+}
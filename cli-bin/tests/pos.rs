@@ -35,6 +35,44 @@ fn tag_should_add_hierarchical_tag_and_search_finds_it() {
         .stdout(str::contains("foo.md"));
 }
 
+#[test]
+fn tag_patterns_take_braces_and_negation() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("notes");
+    fs::create_dir_all(&dir).unwrap();
+    for f in ["a.md", "b.txt", "c.bak", "d.pdf"] {
+        fs::write(dir.join(f), "").unwrap();
+    }
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["tag", &format!("{}/*.{{md,txt}}", dir.display()), "text"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", &format!("{}/!(*.bak)", dir.display()), "keep"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "tag:text"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("a.md")
+                .and(str::contains("b.txt"))
+                .and(str::contains("d.pdf").not()),
+        );
+    marlin(&tmp)
+        .args(["search", "tag:keep"])
+        .assert()
+        .success()
+        .stdout(str::contains("d.pdf").and(str::contains("c.bak").not()));
+}
+
 /* ─────────────────────────── ATTR ────────────────────────────── */
 
 #[test]
@@ -61,6 +99,34 @@ fn attr_set_then_ls_roundtrip() {
         .stdout(str::contains("reviewed = yes"));
 }
 
+#[test]
+fn attr_with_expiry_disappears_after_ttl() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("film.mkv");
+    fs::write(&file, "").unwrap();
+    let file = file.to_str().unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["attr", "set", "--expires", "30d", file, "hot", "yes"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["attr", "set", "--expires", "0s", file, "downloaded", "yes"])
+        .assert()
+        .success();
+
+    // the next command's open sweeps the already expired marker
+    marlin(&tmp)
+        .args(["attr", "ls", file])
+        .assert()
+        .success()
+        .stdout(str::contains("hot = yes  (expires ").and(str::contains("downloaded").not()));
+}
+
 /* ─────────────────────── COLLECTIONS ────────────────────────── */
 
 #[test]
@@ -96,6 +162,296 @@ fn coll_create_add_and_list() {
         .stdout(str::contains("a.txt").and(str::contains("b.txt")));
 }
 
+/* ─────────────────────────── STATS ───────────────────────────── */
+
+#[test]
+fn stats_tags_and_dirs_report_usage() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::create_dir_all(root.join("media")).unwrap();
+    fs::write(root.join("docs/a.md"), "hello").unwrap();
+    fs::write(root.join("media/b.bin"), vec![0u8; 4096]).unwrap();
+
+    marlin(&tmp)
+        .current_dir(&root)
+        .arg("init")
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", root.join("docs/*.md").to_str().unwrap(), "notes"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["stats", "tags"])
+        .assert()
+        .success()
+        .stdout(str::contains("notes").and(str::contains("+1 / +5 B")));
+
+    marlin(&tmp)
+        .args(["stats", "dirs", root.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("4.0 KiB")
+                .and(str::contains("media"))
+                .and(str::contains("docs")),
+        );
+}
+
+#[test]
+fn du_reports_index_sizes_and_refreshes() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+
+    fs::create_dir_all(root.join("big")).unwrap();
+    fs::create_dir_all(root.join("small")).unwrap();
+    fs::write(root.join("big/a.bin"), vec![0u8; 2048]).unwrap();
+    fs::write(root.join("small/b.txt"), "tiny").unwrap();
+
+    marlin(&tmp)
+        .current_dir(&root)
+        .arg("init")
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["du", root.to_str().unwrap(), "--top", "1"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("2.0 KiB")
+                .and(str::contains("big"))
+                .and(str::contains("small").not()),
+        );
+
+    // grow a file without rescanning: only --refresh picks it up
+    fs::write(root.join("small/b.txt"), vec![b'x'; 4096]).unwrap();
+    marlin(&tmp)
+        .args(["du", root.to_str().unwrap(), "--refresh", "--sort", "name"])
+        .assert()
+        .success()
+        .stdout(str::contains("4.0 KiB").and(str::contains("6.0 KiB")));
+}
+
+#[test]
+fn stale_lists_old_files_except_kept_ones() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    for name in ["old.log", "keep.log", "fresh.log"] {
+        fs::write(root.join(name), vec![0u8; 2048]).unwrap();
+    }
+
+    marlin(&tmp)
+        .current_dir(&root)
+        .arg("init")
+        .assert()
+        .success();
+
+    // back-date two of the files in the index
+    let db = rusqlite::Connection::open(tmp.path().join("index.db")).unwrap();
+    db.execute(
+        "UPDATE files SET mtime = 0 WHERE path LIKE '%old.log' OR path LIKE '%keep.log'",
+        [],
+    )
+    .unwrap();
+    drop(db);
+
+    marlin(&tmp)
+        .args(["tag", root.join("keep.log").to_str().unwrap(), "keep"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args([
+            "stale",
+            "--older-than",
+            "1y",
+            "--min-size",
+            "1K",
+            "-tag:keep",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("old.log")
+                .and(str::contains("keep.log").not())
+                .and(str::contains("fresh.log").not())
+                .and(str::contains("1 file(s), 2.0 KiB total")),
+        );
+}
+
+/* ─────────────────────────── ROOTS ───────────────────────────── */
+
+#[test]
+fn root_profile_ignores_files_on_scan() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("src");
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("target/app.bin"), "bin").unwrap();
+
+    marlin(&tmp)
+        .args(["root", "add", root.to_str().unwrap(), "--ignore", "target"])
+        .arg("--hash")
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["root", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("hash: on").and(str::contains("ignore:  target")));
+
+    marlin(&tmp)
+        .args(["scan", root.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["search", "app.bin"])
+        .assert()
+        .success()
+        .stdout(str::contains("app.bin").not());
+    marlin(&tmp)
+        .args(["search", "main.rs"])
+        .assert()
+        .success()
+        .stdout(str::contains("main.rs"));
+}
+
+/* ─────────────────────────── SCRIPTS ─────────────────────────── */
+
+#[test]
+fn run_atomic_rolls_back_whole_script_on_failure() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    fs::write(root.join("a.md"), "").unwrap();
+
+    marlin(&tmp)
+        .current_dir(&root)
+        .arg("init")
+        .assert()
+        .success();
+
+    let pattern = root.join("*.md");
+    let script = root.join("retag.marlin");
+    fs::write(
+        &script,
+        format!(
+            "# retag drafts\nmarlin tag '{p}' draft\nattr set '{p}' status done\nview exec no-such-view\n",
+            p = pattern.display()
+        ),
+    )
+    .unwrap();
+
+    marlin(&tmp)
+        .args(["run", "--atomic", script.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(str::contains("line 4"));
+    marlin(&tmp)
+        .args(["search", "tag:draft"])
+        .assert()
+        .success()
+        .stdout(str::contains("a.md").not());
+
+    // without --atomic the commands before the failure stick
+    marlin(&tmp)
+        .args(["run", script.to_str().unwrap()])
+        .assert()
+        .failure();
+    marlin(&tmp)
+        .args(["search", "tag:draft"])
+        .assert()
+        .success()
+        .stdout(str::contains("a.md"));
+}
+
+/* ─────────────────────────── SHELL ───────────────────────────── */
+
+#[test]
+fn shell_runs_commands_and_bare_queries_on_one_index() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    fs::write(root.join("a.md"), "hello").unwrap();
+    fs::write(root.join("b.txt"), "world").unwrap();
+    let history = root.join("history");
+
+    let input = format!(
+        "scan '{r}'\ntag '{p}' project/x\ntag:project\nshell\nbogus --flag\nexit\nsearch world\n",
+        r = root.display(),
+        p = root.join("*.md").display()
+    );
+    marlin(&tmp)
+        .env("MARLIN_SHELL_HISTORY", &history)
+        .arg("shell")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(str::contains("a.md").and(str::contains("b.txt").not()))
+        .stderr(str::contains("`shell` is not available in the shell"));
+
+    // everything up to `exit` is remembered for the next session
+    let saved = fs::read_to_string(&history).unwrap();
+    assert!(saved.contains("tag:project"), "{saved}");
+    assert!(!saved.contains("search world"), "{saved}");
+}
+
+/* ─────────────────────────── SQL ─────────────────────────────── */
+
+#[test]
+fn sql_reads_by_default_and_writes_when_allowed() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("a.txt"), "abc").unwrap();
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args([
+            "sql",
+            "SELECT size, path FROM files WHERE path LIKE '%a.txt'",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("size  path")
+                .and(str::contains("   3  "))
+                .and(str::contains("(1 row)")),
+        );
+
+    marlin(&tmp)
+        .args(["sql", "UPDATE files SET rating = 5"])
+        .assert()
+        .failure()
+        .stderr(str::contains("--unsafe-write"));
+    marlin(&tmp)
+        .args(["sql", "--unsafe-write", "UPDATE files SET rating = 5"])
+        .assert()
+        .success()
+        .stdout(str::contains("1 row(s) changed"));
+}
+
+#[test]
+fn db_schema_lists_migrations_and_tables() {
+    let tmp = tempdir().unwrap();
+
+    marlin(&tmp)
+        .args(["db", "schema"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("compatible")
+                .and(str::contains("0001_initial_schema.sql"))
+                .and(str::contains("files (table)"))
+                .and(str::contains("files_fts (virtual)")),
+        );
+}
+
 /* ─────────────────────────── VIEWS ───────────────────────────── */
 
 #[test]
@@ -130,6 +486,137 @@ fn view_save_list_and_exec() {
         .stdout(str::contains("TODO.txt"));
 }
 
+/* ─────────────────────────── ACTIONS ─────────────────────────── */
+
+#[test]
+fn search_exec_fills_in_named_placeholders() {
+    let tmp = tempdir().unwrap();
+    let docs = tmp.path().join("docs");
+    let out = tmp.path().join("out dir");
+    fs::create_dir_all(&docs).unwrap();
+    fs::create_dir_all(&out).unwrap();
+    fs::write(docs.join("summer trip.txt"), "sun").unwrap();
+    marlin(&tmp)
+        .args(["scan", docs.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args([
+            "attr",
+            "set",
+            docs.join("*.txt").to_str().unwrap(),
+            "owner",
+            "ana",
+        ])
+        .assert()
+        .success();
+
+    let exec = format!(
+        "cp {{path}} '{}'/{{stem}}-{{attr:owner}}.{{ext}}",
+        out.display()
+    );
+    marlin(&tmp)
+        .args(["search", "attr:owner=ana", "--exec", &exec])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(out.join("summer trip-ana.txt")).unwrap(),
+        "sun"
+    );
+}
+
+#[test]
+fn search_exec_capture_stores_output_as_attribute() {
+    let tmp = tempdir().unwrap();
+    let docs = tmp.path().join("docs");
+    fs::create_dir_all(&docs).unwrap();
+    let a = docs.join("a.jpg");
+    let b = docs.join("b.jpg");
+    fs::write(&a, "  2024-01-02 10:00\n").unwrap();
+    fs::write(&b, "").unwrap();
+    marlin(&tmp)
+        .args(["scan", docs.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", docs.join("*.jpg").to_str().unwrap(), "photos"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "tag:photos", "--exec-capture", "cat {}"])
+        .args(["--into", "attr:meta/taken_at"])
+        .assert()
+        .success()
+        .stderr(str::contains("captured on 1 of 2 file(s)"));
+    marlin(&tmp)
+        .args(["attr", "ls", a.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(str::contains("meta/taken_at = 2024-01-02 10:00\n"));
+    // empty output leaves the file alone
+    marlin(&tmp)
+        .args(["attr", "ls", b.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(str::contains("meta/taken_at").not());
+}
+
+#[test]
+fn action_define_list_run_and_rm() {
+    let tmp = tempdir().unwrap();
+    let docs = tmp.path().join("docs");
+    let out = tmp.path().join("out");
+    fs::create_dir_all(&docs).unwrap();
+    fs::create_dir_all(&out).unwrap();
+    fs::write(docs.join("inbox.pdf"), "%PDF\n").unwrap();
+    fs::write(docs.join("other.pdf"), "%PDF\n").unwrap();
+    marlin(&tmp)
+        .args(["scan", docs.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", docs.join("inbox.pdf").to_str().unwrap(), "inbox"])
+        .assert()
+        .success();
+
+    let exec = format!("cp {{}} {}", out.display());
+    marlin(&tmp)
+        .args([
+            "action",
+            "define",
+            "grab",
+            "--query",
+            "tag:inbox",
+            "--exec",
+            &exec,
+        ])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["action", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("grab: tag:inbox"));
+
+    marlin(&tmp)
+        .args(["action", "run", "grab"])
+        .assert()
+        .success();
+    assert!(out.join("inbox.pdf").exists());
+    assert!(!out.join("other.pdf").exists());
+
+    marlin(&tmp)
+        .args(["action", "rm", "grab"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["action", "run", "grab"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no action called 'grab'"));
+}
+
 /* ─────────────────────────── LINKS ───────────────────────────── */
 
 #[test]
@@ -172,15 +659,1221 @@ fn link_add_rm_and_list() {
         .stdout(str::is_empty());
 }
 
-/* ─────────────────────── SCAN (multi-path) ───────────────────── */
-
 #[test]
-fn scan_with_multiple_paths_indexes_all() {
+fn link_import_from_csv_and_stdin() {
     let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("papers");
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["a.pdf", "b.pdf", "c.pdf"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
 
-    let dir_a = tmp.path().join("A");
-    let dir_b = tmp.path().join("B");
-    std::fs::create_dir_all(&dir_a).unwrap();
+    let p = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    let csv = tmp.path().join("pairs.csv");
+    fs::write(
+        &csv,
+        format!(
+            "from,to,type\n{},{}\n{},{},related\n{},{}\n",
+            p("a.pdf"),
+            p("b.pdf"),
+            p("a.pdf"),
+            p("c.pdf"),
+            p("b.pdf"),
+            p("missing.pdf")
+        ),
+    )
+    .unwrap();
+    marlin(&tmp)
+        .args(["link", "import", csv.to_str().unwrap(), "--type", "cites"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("Added 2 link(s); 0 already there, 1 row(s) skipped")
+                .and(str::contains("line 4: file not indexed")),
+        );
+    marlin(&tmp)
+        .args(["link", "list", &p("a.pdf"), "--type", "cites"])
+        .assert()
+        .success()
+        .stdout(str::contains("b.pdf").and(str::contains("c.pdf").not()));
+
+    marlin(&tmp)
+        .args(["link", "import", "-"])
+        .write_stdin(format!("{},{}\n", p("c.pdf"), p("b.pdf")))
+        .assert()
+        .success()
+        .stdout(str::contains("Added 1 link(s)"));
+    marlin(&tmp)
+        .args(["link", "backlinks", &p("b.pdf")])
+        .assert()
+        .success()
+        .stdout(str::contains("a.pdf").and(str::contains("c.pdf")));
+}
+
+#[test]
+fn link_attrs_show_in_long_list_and_export() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("papers");
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["a.pdf", "b.pdf"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    let a = dir.join("a.pdf").to_string_lossy().into_owned();
+    let b = dir.join("b.pdf").to_string_lossy().into_owned();
+    marlin(&tmp)
+        .args(["link", "add", &a, &b, "--type", "cites"])
+        .assert()
+        .success();
+
+    for (key, value) in [("weight", "2"), ("note", "see \"Methods\"")] {
+        marlin(&tmp)
+            .args(["link", "attr", "set", &a, &b, key, value])
+            .assert()
+            .success();
+    }
+    marlin(&tmp)
+        .args(["link", "list", &a, "--long"])
+        .assert()
+        .success()
+        .stdout(str::contains(
+            "[type='cites'] {note=see \"Methods\", weight=2}",
+        ));
+    marlin(&tmp)
+        .args(["link", "export"])
+        .assert()
+        .success()
+        .stdout(str::starts_with("digraph marlin {").and(str::contains(
+            "[label=\"cites\", \"note\"=\"see \\\"Methods\\\"\", \"weight\"=\"2\"];",
+        )));
+
+    marlin(&tmp)
+        .args(["link", "attr", "rm", &a, &b, "note"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["link", "attr", "rm", &a, &b, "note"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no `note` on the link"));
+    marlin(&tmp)
+        .args(["link", "attr", "set", &b, &a, "weight", "1"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no link from"));
+}
+
+#[test]
+fn link_types_name_inverses_in_list_and_export() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("papers");
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["a.pdf", "b.pdf"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    let a = dir.join("a.pdf").to_string_lossy().into_owned();
+    let b = dir.join("b.pdf").to_string_lossy().into_owned();
+
+    marlin(&tmp)
+        .args(["link", "type", "add", "cites", "--inverse", "cited-by"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["link", "type", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("cites ↔ cited-by"));
+    marlin(&tmp)
+        .args(["link", "add", &a, &b, "--type", "cites"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["link", "list", &b, "--direction", "in"])
+        .assert()
+        .success()
+        .stdout(str::contains("[type='cited-by']"));
+    marlin(&tmp)
+        .args(["link", "list", &b])
+        .assert()
+        .success()
+        .stdout(str::is_empty());
+
+    marlin(&tmp)
+        .args(["link", "add", &a, &b, "--type", "cites", "--with-inverse"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["link", "list", &b])
+        .assert()
+        .success()
+        .stdout(str::contains("[type='cited-by']"));
+    let export = marlin(&tmp)
+        .args(["link", "export"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let export = String::from_utf8(export).unwrap();
+    assert_eq!(export.matches(" -> ").count(), 1, "{export}");
+    assert!(export.contains(&format!("\"{a}\" -> \"{b}\" [label=\"cites\"]")));
+
+    marlin(&tmp)
+        .args([
+            "link",
+            "add",
+            &a,
+            &b,
+            "--type",
+            "mentions",
+            "--with-inverse",
+        ])
+        .assert()
+        .failure()
+        .stderr(str::contains("no registered inverse"));
+}
+
+/* ──────────────────── RATING & FAVORITES ───────────────────── */
+
+#[test]
+fn rate_and_fav_are_searchable() {
+    let tmp = tempdir().unwrap();
+    let good = tmp.path().join("good.txt");
+    let meh = tmp.path().join("meh.txt");
+    fs::write(&good, "good\n").unwrap();
+    fs::write(&meh, "meh\n").unwrap();
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["rate", "5", good.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["rate", "2", meh.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["fav", "add", "meh.*"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "rating:>=4"])
+        .assert()
+        .success()
+        .stdout(str::contains("good.txt").and(str::contains("meh.txt").not()));
+
+    marlin(&tmp)
+        .args(["search", "fav:true"])
+        .assert()
+        .success()
+        .stdout(str::contains("meh.txt").and(str::contains("good.txt").not()));
+
+    marlin(&tmp)
+        .args(["fav", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("meh.txt"));
+}
+
+/* ─────────────────────────── LABELS ──────────────────────────── */
+
+#[test]
+fn label_add_list_and_search() {
+    let tmp = tempdir().unwrap();
+    let img = tmp.path().join("beach.jpg");
+    fs::write(&img, "jpg").unwrap();
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["label", "add", "*.jpg", "Red"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["label", "list", "*.jpg"])
+        .assert()
+        .success()
+        .stdout(str::contains("beach.jpg: red"));
+
+    marlin(&tmp)
+        .args(["search", "label:red"])
+        .assert()
+        .success()
+        .stdout(str::contains("beach.jpg"));
+}
+
+/* ─────────────────────── VIEWS AS FILTERS ──────────────────── */
+
+#[test]
+fn view_selects_files_for_tag_attr_and_coll() {
+    let tmp = tempdir().unwrap();
+    let inbox = tmp.path().join("inbox.pdf");
+    let other = tmp.path().join("other.pdf");
+    fs::write(&inbox, "%PDF\n").unwrap();
+    fs::write(&other, "%PDF\n").unwrap();
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", inbox.to_str().unwrap(), "inbox"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["view", "save", "in", "tag:inbox"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["tag", "--view", "in", "todo/read"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["attr", "set", "--view", "in", "status", "new"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["coll", "create", "Reading"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["coll", "add", "Reading", "--view", "in"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "tag:todo/read attr:status=new"])
+        .assert()
+        .success()
+        .stdout(str::contains("inbox.pdf").and(str::contains("other.pdf").not()));
+    marlin(&tmp)
+        .args(["coll", "list", "Reading"])
+        .assert()
+        .success()
+        .stdout(str::contains("inbox.pdf").and(str::contains("other.pdf").not()));
+}
+
+/* ─────────────────────── RELATIVE OUTPUT ───────────────────── */
+
+#[test]
+fn relative_flag_prints_paths_from_cwd_or_workspace() {
+    let tmp = tempdir().unwrap();
+    let ws = tmp.path().canonicalize().unwrap().join("ws");
+    fs::create_dir_all(ws.join("src")).unwrap();
+    fs::create_dir_all(ws.join("docs")).unwrap();
+    fs::create_dir_all(ws.join(".marlin")).unwrap();
+    fs::write(ws.join(".marlin/config.toml"), "# workspace\n").unwrap();
+    fs::write(ws.join("src/zebra.txt"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", ws.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .current_dir(ws.join("docs"))
+        .args(["search", "--relative", "zebra"])
+        .assert()
+        .success()
+        .stdout(str::diff("../src/zebra.txt\n"));
+    marlin(&tmp)
+        .current_dir(ws.join("docs"))
+        .args(["search", "--relative=workspace", "zebra"])
+        .assert()
+        .success()
+        .stdout(str::diff("src/zebra.txt\n"));
+
+    marlin(&tmp)
+        .args(["coll", "create", "Z"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["coll", "add", "Z", ws.join("src/*").to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .current_dir(&ws)
+        .args(["coll", "list", "Z", "--relative"])
+        .assert()
+        .success()
+        .stdout(str::diff("src/zebra.txt\n"));
+
+    marlin(&tmp)
+        .args(["view", "save", "z", "zebra"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .current_dir(ws.join("src"))
+        .args(["view", "exec", "z", "--relative"])
+        .assert()
+        .success()
+        .stdout(str::diff("zebra.txt\n"));
+}
+
+/* ─────────────────────── FIRST / LAST SEEN ─────────────────── */
+
+#[test]
+fn search_filters_and_shows_when_files_were_added() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("fresh.txt"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    marlin(&tmp)
+        .args(["search", "added:1d"])
+        .assert()
+        .success()
+        .stdout(str::contains("fresh.txt"));
+    marlin(&tmp)
+        .args(["search", "added:<2000-01-01"])
+        .assert()
+        .success()
+        .stdout(str::contains("fresh.txt").not());
+    marlin(&tmp)
+        .args(["search", "--long", "fresh"])
+        .assert()
+        .success()
+        .stdout(str::starts_with(today).and(str::contains("fresh.txt")));
+}
+
+/* ─────────────────────────── TOKENS ───────────────────────────── */
+
+#[test]
+fn token_create_list_and_revoke() {
+    let tmp = tempdir().unwrap();
+    let out = marlin(&tmp)
+        .args(["token", "create", "photos-app", "--scope", "read"])
+        .args(["--scope", "tag:photos"])
+        .assert()
+        .success()
+        .stderr(str::contains("cannot be shown again"))
+        .get_output()
+        .stdout
+        .clone();
+    let secret = String::from_utf8(out).unwrap();
+    assert!(secret.starts_with("mrl_"));
+
+    marlin(&tmp)
+        .args(["token", "list"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("photos-app")
+                .and(str::contains("read tag:photos"))
+                .and(str::contains("never"))
+                .and(str::contains(secret.trim()).not()),
+        );
+    marlin(&tmp)
+        .args(["token", "revoke", "photos-app"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["token", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("No tokens"));
+}
+
+#[test]
+fn search_finds_words_in_file_bodies() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.md"), "minutes of the steering meeting").unwrap();
+    fs::write(dir.join("b.md"), "grocery list").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "steering"])
+        .assert()
+        .success()
+        .stdout(str::contains("a.md").and(str::contains("b.md").not()));
+    marlin(&tmp)
+        .args(["search", "content:grocery"])
+        .assert()
+        .success()
+        .stdout(str::contains("b.md").and(str::contains("a.md").not()));
+}
+
+/* ─────────────────────── MULTI-DB SEARCH ───────────────────── */
+
+#[test]
+fn search_across_several_dbs_labels_each_hit() {
+    let tmp = tempdir().unwrap();
+    let (work, home) = (tmp.path().join("w"), tmp.path().join("h"));
+    fs::create_dir_all(&work).unwrap();
+    fs::create_dir_all(&home).unwrap();
+    fs::write(work.join("invoice-acme.txt"), "").unwrap();
+    fs::write(home.join("invoice-rent.txt"), "").unwrap();
+    let work_db = tmp.path().join("work.db");
+    let home_db = tmp.path().join("home.db");
+
+    for (db, dir) in [(&work_db, &work), (&home_db, &home)] {
+        marlin(&tmp)
+            .arg("--db")
+            .arg(db)
+            .args(["scan", dir.to_str().unwrap()])
+            .assert()
+            .success();
+    }
+
+    marlin(&tmp)
+        .arg("--db")
+        .arg(&work_db)
+        .arg("--db")
+        .arg(&home_db)
+        .args(["search", "invoice"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("[work] ")
+                .and(str::contains("invoice-acme.txt"))
+                .and(str::contains("[home] "))
+                .and(str::contains("invoice-rent.txt")),
+        );
+
+    // a single --db replaces the configured index
+    marlin(&tmp)
+        .arg("--db")
+        .arg(&home_db)
+        .args(["search", "invoice"])
+        .assert()
+        .success()
+        .stdout(str::contains("invoice-rent.txt").and(str::contains("acme").not()));
+}
+
+/* ─────────────────────── SHARED TAG DB ─────────────────────── */
+
+#[test]
+fn search_attach_adds_tags_from_a_shared_db() {
+    let tmp = tempdir().unwrap();
+    let docs = tmp.path().join("docs");
+    fs::create_dir_all(&docs).unwrap();
+    fs::write(docs.join("plan.md"), "").unwrap();
+    fs::write(docs.join("notes.md"), "").unwrap();
+    let team = tmp.path().join("team.db");
+
+    marlin(&tmp)
+        .arg("--db")
+        .arg(&team)
+        .args(["scan", docs.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .arg("--db")
+        .arg(&team)
+        .args(["tag", docs.join("plan.md").to_str().unwrap(), "review"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["scan", docs.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "tag:review"])
+        .assert()
+        .success()
+        .stdout(str::contains("plan.md").not());
+    marlin(&tmp)
+        .args(["search", "--attach", team.to_str().unwrap(), "tag:review"])
+        .assert()
+        .success()
+        .stdout(str::contains("plan.md").and(str::contains("notes.md").not()));
+}
+
+/* ─────────────────────── SEARCH HISTORY ────────────────────── */
+
+#[test]
+fn search_history_last_and_save_from_history() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("alpha.md");
+    fs::write(&file, "alpha\n").unwrap();
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", file.to_str().unwrap(), "proj"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "tag:proj"])
+        .assert()
+        .success()
+        .stdout(str::contains("alpha.md"));
+
+    marlin(&tmp)
+        .args(["search", "--last"])
+        .assert()
+        .success()
+        .stdout(str::contains("alpha.md"));
+
+    marlin(&tmp)
+        .args(["history", "search"])
+        .assert()
+        .success()
+        .stdout(str::contains("1 hit(s)  tag:proj"));
+
+    marlin(&tmp)
+        .args(["view", "save-from-history", "1", "proj"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["view", "exec", "proj"])
+        .assert()
+        .success()
+        .stdout(str::contains("alpha.md"));
+}
+
+/* ─────────────────────── LITERAL SEARCH ────────────────────── */
+
+#[test]
+fn search_literal_matches_names_with_query_syntax() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("meeting (draft).md"), "x\n").unwrap();
+    fs::write(tmp.path().join("meeting.md"), "x\n").unwrap();
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "--literal", "meeting (draft)"])
+        .assert()
+        .success()
+        .stdout(str::contains("meeting (draft).md"))
+        .stdout(str::contains("meeting.md\n").not());
+}
+
+/* ─────────────────────── SEMANTIC SEARCH ───────────────────── */
+
+#[test]
+fn search_semantic_ranks_embedded_files() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir(&dir).unwrap();
+    fs::write(
+        dir.join("q3.txt"),
+        "Quarterly financial summary with revenue and costs.\n",
+    )
+    .unwrap();
+    fs::write(dir.join("trip.txt"), "Holiday photos from the beach.\n").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["search", "--semantic", "financial summary"])
+        .assert()
+        .failure()
+        .stderr(str::contains("run `marlin embed` first"));
+
+    marlin(&tmp)
+        .arg("embed")
+        .assert()
+        .success()
+        .stdout(str::contains("Embedded 2 file(s) with hash-256"));
+
+    marlin(&tmp)
+        .args(["search", "--semantic", "--limit", "1", "quarterly finances"])
+        .assert()
+        .success()
+        .stdout(str::contains("q3.txt"))
+        .stdout(str::contains("trip.txt").not());
+}
+
+/* ─────────────────────── CLASSIFIERS ───────────────────────── */
+
+#[cfg(unix)]
+#[test]
+fn classify_applies_suggestions_of_the_configured_command() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("bill.md"), "Invoice no. 42\n").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args([
+            "config",
+            "set",
+            "classify.text/markdown",
+            "sh -c 'grep -q Invoice && echo 0.9 tag invoice'",
+        ])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["classify", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(str::contains("tag auto/invoice (0.90)"))
+        .stdout(str::contains("Would classify 1 file(s)"));
+    marlin(&tmp)
+        .args(["search", "tag:auto/invoice"])
+        .assert()
+        .success()
+        .stdout(str::contains("bill.md").not());
+
+    marlin(&tmp).arg("classify").assert().success();
+    marlin(&tmp)
+        .args([
+            "search",
+            "tag:auto/invoice attr:auto/confidence/tag/invoice>=0.8",
+        ])
+        .assert()
+        .success()
+        .stdout(str::contains("bill.md"));
+}
+
+/* ─────────────────────────── SYNC ─────────────────────────────── */
+
+#[test]
+fn sync_log_carries_tags_to_another_replica() {
+    let (tmp_a, tmp_b) = (tempdir().unwrap(), tempdir().unwrap());
+    let mut roots = Vec::new();
+    for tmp in [&tmp_a, &tmp_b] {
+        let docs = tmp.path().join("docs");
+        fs::create_dir(&docs).unwrap();
+        fs::write(docs.join("plan.md"), "x").unwrap();
+        marlin(tmp)
+            .args(["scan", docs.to_str().unwrap()])
+            .assert()
+            .success();
+        roots.push(docs);
+    }
+    let log = tmp_a.path().join("a.log");
+    let plan = format!("{}/plan.md", roots[0].display());
+
+    marlin(&tmp_a)
+        .args(["tag", &plan, "project/alpha"])
+        .assert()
+        .success();
+    marlin(&tmp_a)
+        .args(["sync", "export", "--root", roots[0].to_str().unwrap()])
+        .arg("--out")
+        .arg(&log)
+        .assert()
+        .success()
+        .stdout(str::contains("Exported 2 op(s)"));
+    // the next export resumes after the cursor written to the log
+    marlin(&tmp_a)
+        .args(["sync", "export", "--out", log.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(str::contains("Exported 0 op(s)"));
+
+    marlin(&tmp_b)
+        .args(["sync", "import", log.to_str().unwrap()])
+        .args(["--root", roots[1].to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(str::contains("Applied 2 op(s)"));
+    marlin(&tmp_b)
+        .args(["search", "tag:project/alpha"])
+        .assert()
+        .success()
+        .stdout(str::contains(roots[1].join("plan.md").to_str().unwrap()));
+}
+
+#[test]
+fn sync_import_asks_about_conflicting_attrs_and_records_the_answer() {
+    let (tmp_a, tmp_b) = (tempdir().unwrap(), tempdir().unwrap());
+    let mut plans = Vec::new();
+    for (tmp, status) in [(&tmp_a, "done"), (&tmp_b, "draft")] {
+        let docs = tmp.path().join("docs");
+        fs::create_dir(&docs).unwrap();
+        fs::write(docs.join("plan.md"), "x").unwrap();
+        marlin(tmp)
+            .args(["scan", docs.to_str().unwrap()])
+            .assert()
+            .success();
+        let plan = docs.join("plan.md").to_string_lossy().into_owned();
+        marlin(tmp)
+            .args(["attr", "set", &plan, "status", status])
+            .assert()
+            .success();
+        plans.push(plan);
+    }
+    let log = tmp_a.path().join("a.log");
+    marlin(&tmp_a)
+        .args(["sync", "export", "--root"])
+        .arg(tmp_a.path().join("docs"))
+        .arg("--out")
+        .arg(&log)
+        .assert()
+        .success();
+
+    // an unknown answer is asked again
+    marlin(&tmp_b)
+        .args(["sync", "import", "--interactive", "--root"])
+        .arg(tmp_b.path().join("docs"))
+        .arg(&log)
+        .write_stdin("x\nb\n")
+        .assert()
+        .success()
+        .stdout(str::contains("Settled 1 conflict(s)"))
+        .stderr(str::contains("mine `draft`, theirs `done`").count(2));
+    marlin(&tmp_b)
+        .args(["attr", "ls", &plans[1]])
+        .assert()
+        .success()
+        .stdout(str::contains("status = draft").and(str::contains("status.theirs = done")));
+    marlin(&tmp_b)
+        .args(["sync", "conflicts"])
+        .assert()
+        .success()
+        .stdout(str::contains("sync import").and(str::contains("-> both")));
+}
+
+/* ─────────────────────────── LOCK ─────────────────────────────── */
+
+#[test]
+fn locked_files_keep_their_tags_until_unlocked() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("archive");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("old.pdf"), "x").unwrap();
+    fs::write(dir.join("new.pdf"), "x").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    let pdfs = format!("{}/*.pdf", dir.display());
+
+    marlin(&tmp)
+        .args(["lock", "old.pdf"])
+        .assert()
+        .success()
+        .stdout(str::contains("Locked 1 file(s)"));
+    marlin(&tmp)
+        .args(["lock", "--list"])
+        .assert()
+        .success()
+        .stdout(str::contains("old.pdf"));
+
+    marlin(&tmp)
+        .args(["tag", &pdfs, "draft"])
+        .assert()
+        .failure()
+        .stderr(str::contains("is locked"));
+    marlin(&tmp)
+        .args(["search", "tag:draft"])
+        .assert()
+        .success()
+        .stdout(str::contains("new.pdf").not());
+
+    marlin(&tmp)
+        .args(["--ignore-locks", "tag", &pdfs, "draft"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["search", "tag:draft"])
+        .assert()
+        .success()
+        .stdout(str::contains("old.pdf"));
+
+    marlin(&tmp)
+        .args(["unlock", &pdfs])
+        .assert()
+        .success()
+        .stdout(str::contains("Unlocked 1 file(s)"));
+    marlin(&tmp)
+        .args(["attr", "set", &pdfs, "year", "2020"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn one_locked_file_stops_bulk_rate_fav_and_label() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir(&dir).unwrap();
+    for f in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(dir.join(f), f).unwrap();
+    }
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp).args(["lock", "b.txt"]).assert().success();
+    let txts = format!("{}/*.txt", dir.display());
+
+    for args in [
+        vec!["fav", "add", &txts],
+        vec!["rate", "4", &txts],
+        vec!["label", "add", &txts, "red"],
+    ] {
+        marlin(&tmp)
+            .args(&args)
+            .assert()
+            .failure()
+            .stderr(str::contains("b.txt is locked"));
+    }
+
+    marlin(&tmp)
+        .args(["fav", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("a.txt").not());
+    marlin(&tmp)
+        .args(["search", "rating:4"])
+        .assert()
+        .stdout(str::contains("a.txt").not());
+    marlin(&tmp)
+        .args(["label", "list", &txts])
+        .assert()
+        .success()
+        .stdout(str::contains("a.txt").not());
+}
+
+/* ─────────────────────── BACKUP DIR ────────────────────────── */
+
+#[test]
+fn read_only_commands_skip_auto_backup() {
+    let tmp = tempdir().unwrap();
+    let snaps = tmp.path().join("snaps");
+    let file = tmp.path().join("a.md");
+    fs::write(&file, "").unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    for args in [
+        vec!["search", "a"],
+        vec!["sql", "SELECT COUNT(*) FROM files"],
+        vec!["view", "list"],
+    ] {
+        marlin(&tmp)
+            .args(["--backup-dir", snaps.to_str().unwrap()])
+            .args(&args)
+            .assert()
+            .success();
+    }
+    assert!(!snaps.exists(), "read-only commands must not back up");
+
+    marlin(&tmp)
+        .args(["--backup-dir", snaps.to_str().unwrap()])
+        .args(["tag", file.to_str().unwrap(), "project"])
+        .assert()
+        .success();
+    assert!(snaps.exists(), "writing commands still back up first");
+}
+
+#[test]
+fn backup_dir_flag_redirects_backups() {
+    let tmp = tempdir().unwrap();
+    let snaps = tmp.path().join("snaps");
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["--backup-dir", snaps.to_str().unwrap(), "backup"])
+        .assert()
+        .success()
+        .stdout(str::contains("Created backup"));
+
+    let dbs: Vec<_> = fs::read_dir(&snaps)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|n| n.ends_with(".db"))
+        .collect();
+    assert_eq!(dbs.len(), 1, "backup should land in --backup-dir");
+    assert!(snaps.join(format!("{}.meta", dbs[0])).exists());
+    assert!(!tmp.path().join("backups").exists());
+
+    // restore that snapshot as a separate database
+    let fork = tmp.path().join("fork.db");
+    marlin(&tmp)
+        .args([
+            "--backup-dir",
+            snaps.to_str().unwrap(),
+            "restore",
+            &dbs[0],
+            "--to",
+            fork.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(str::contains("as new DB"));
+    assert!(fork.exists());
+
+    marlin(&tmp)
+        .args([
+            "--backup-dir",
+            snaps.to_str().unwrap(),
+            "backup",
+            "verify",
+            "--all",
+        ])
+        .assert()
+        .success()
+        .stdout(str::contains("1 OK, 0 corrupted"));
+}
+
+/* ─────────────────────── DAEMON ────────────────────────────── */
+
+#[test]
+fn watch_logs_prints_tail_of_daemon_log() {
+    let tmp = tempdir().unwrap();
+    let logs = tmp.path().join("logs");
+    fs::create_dir_all(&logs).unwrap();
+    let body: String = (1..=5).map(|i| format!("INFO event {i}\n")).collect();
+    fs::write(logs.join("daemon.log"), body).unwrap();
+
+    marlin(&tmp)
+        .env("MARLIN_LOG_DIR", &logs)
+        .args(["watch", "logs", "-n", "2"])
+        .assert()
+        .success()
+        .stdout(str::contains("event 4"))
+        .stdout(str::contains("event 5"))
+        .stdout(str::contains("event 3").not());
+}
+
+#[test]
+fn watch_status_without_daemon_reports_not_running() {
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args(["watch", "status"])
+        .assert()
+        .success()
+        .stdout(str::contains("No watcher running"));
+
+    marlin(&tmp)
+        .args(["watch", "events", "--follow"])
+        .assert()
+        .success()
+        .stdout(str::contains("No watcher running"));
+
+    marlin(&tmp)
+        .env("MARLIN_WATCH_REGISTRY", tmp.path().join("watchers"))
+        .args(["watch", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("No watchers running"));
+}
+
+/* ─────────────────────── SCAN (multi-path) ───────────────────── */
+
+#[test]
+fn scan_honours_ignore_files_unless_no_ignore() {
+    let tmp = tempdir().unwrap();
+    let repo = tmp.path().join("repo");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    fs::create_dir_all(repo.join("target")).unwrap();
+    fs::write(repo.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+    fs::write(repo.join(".marlinignore"), "*.bak\n").unwrap();
+    fs::write(repo.join("target/app.bin"), "").unwrap();
+    fs::write(repo.join("main.rs"), "").unwrap();
+    fs::write(repo.join("main.rs.bak"), "").unwrap();
+
+    marlin(&tmp)
+        .args(["scan", repo.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["search", "main"])
+        .assert()
+        .success()
+        .stdout(str::contains("main.rs").and(str::contains("main.rs.bak").not()));
+    marlin(&tmp)
+        .args(["search", "app"])
+        .assert()
+        .stdout(str::contains("app.bin").not());
+
+    marlin(&tmp)
+        .args(["scan", "--no-ignore", repo.to_str().unwrap()])
+        .assert()
+        .success();
+    for name in ["app.bin", "main.rs.bak", "HEAD"] {
+        marlin(&tmp)
+            .args(["search", name])
+            .assert()
+            .success()
+            .stdout(str::contains(name));
+    }
+}
+
+#[test]
+fn prune_reports_metadata_and_keeps_tombstones() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("notes");
+    fs::create_dir_all(&dir).unwrap();
+    let note = dir.join("idea.md");
+    fs::write(&note, "an idea\n").unwrap();
+    fs::write(dir.join("plain.md"), "plain\n").unwrap();
+    let d = dir.to_str().unwrap();
+    marlin(&tmp).args(["scan", d]).assert().success();
+    marlin(&tmp)
+        .args(["attr", "set", note.to_str().unwrap(), "status", "draft"])
+        .assert()
+        .success();
+
+    fs::remove_file(&note).unwrap();
+    marlin(&tmp)
+        .args(["prune", d, "--dry-run"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("1 file(s) missing on disk")
+                .and(str::contains("attributes: 1"))
+                .and(str::contains("Pruned").not()),
+        );
+    // no terminal to ask on: refuse instead of deleting metadata
+    marlin(&tmp)
+        .args(["prune", d])
+        .assert()
+        .failure()
+        .stderr(str::contains("--keep-metadata"));
+    marlin(&tmp)
+        .args(["prune", d, "--keep-metadata"])
+        .assert()
+        .success()
+        .stdout(str::contains("Kept 1 file(s) as tombstones"));
+    marlin(&tmp)
+        .args(["prune", d, "--keep-metadata"])
+        .assert()
+        .success()
+        .stdout(str::contains("No missing files"));
+
+    fs::write(&note, "an idea\n").unwrap();
+    marlin(&tmp).args(["scan", d]).assert().success();
+    marlin(&tmp)
+        .args(["attr", "ls", note.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(str::contains("status = draft"));
+
+    fs::remove_file(&note).unwrap();
+    marlin(&tmp)
+        .args(["prune", d, "--yes"])
+        .assert()
+        .success()
+        .stdout(str::contains("Pruned 1 file(s)"));
+    marlin(&tmp)
+        .args(["search", "idea"])
+        .assert()
+        .stdout(str::contains("idea.md").not());
+}
+
+#[test]
+fn scan_prune_drops_files_gone_from_disk() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("notes");
+    fs::create_dir_all(&dir).unwrap();
+    let note = dir.join("idea.md");
+    fs::write(&note, "an idea\n").unwrap();
+    fs::write(dir.join("plain.md"), "plain\n").unwrap();
+    let d = dir.to_str().unwrap();
+    marlin(&tmp).args(["scan", d]).assert().success();
+    marlin(&tmp)
+        .args(["tag", note.to_str().unwrap(), "project/alpha"])
+        .assert()
+        .success();
+
+    fs::remove_file(&note).unwrap();
+    marlin(&tmp)
+        .args(["scan", d, "--prune", "--keep-metadata"])
+        .assert()
+        .success()
+        .stdout(str::contains("Kept 1 missing file(s) as tombstones"));
+    marlin(&tmp)
+        .args(["scan", d, "--prune"])
+        .assert()
+        .success()
+        .stdout(str::contains("Pruned 1 missing file(s) (tags: 2,"));
+    marlin(&tmp)
+        .args(["search", "idea"])
+        .assert()
+        .stdout(str::contains("idea.md").not());
+    marlin(&tmp)
+        .args(["scan", d, "--keep-metadata"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn trace_sql_logs_statements_of_writes() {
+    let tmp = tempdir().unwrap();
+    let note = tmp.path().join("idea.md");
+    fs::write(&note, "an idea\n").unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args([
+            "--trace-sql",
+            "attr",
+            "set",
+            note.to_str().unwrap(),
+            "k",
+            "v",
+        ])
+        .assert()
+        .success()
+        .stderr(str::contains("sql: INSERT INTO attributes").and(str::contains("elapsed_ms")));
+    marlin(&tmp)
+        .args(["attr", "set", note.to_str().unwrap(), "k", "w"])
+        .assert()
+        .success()
+        .stderr(str::contains("sql:").not());
+}
+
+#[test]
+fn scan_with_multiple_paths_indexes_all() {
+    let tmp = tempdir().unwrap();
+
+    let dir_a = tmp.path().join("A");
+    let dir_b = tmp.path().join("B");
+    std::fs::create_dir_all(&dir_a).unwrap();
     std::fs::create_dir_all(&dir_b).unwrap();
     let f1 = dir_a.join("one.txt");
     let f2 = dir_b.join("two.txt");
@@ -188,23 +1881,1151 @@ fn scan_with_multiple_paths_indexes_all() {
     fs::write(&f2, "").unwrap();
 
     marlin(&tmp)
-        .current_dir(tmp.path())
-        .arg("init")
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    // multi-path scan
+    marlin(&tmp)
+        .args(["scan", dir_a.to_str().unwrap(), dir_b.to_str().unwrap()])
+        .assert()
+        .success();
+
+    // both files findable
+    for term in ["one.txt", "two.txt"] {
+        marlin(&tmp)
+            .args(["search", term])
+            .assert()
+            .success()
+            .stdout(str::contains(term));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn scan_on_busy_skip_leaves_a_tree_to_the_running_scan() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap().join("docs");
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("one.txt"), "").unwrap();
+
+    // another process (pid 1 is always alive) is scanning the tree
+    marlin(&tmp).arg("init").assert().success();
+    marlin(&tmp)
+        .args([
+            "sql",
+            "--unsafe-write",
+            &format!(
+                "INSERT INTO active_scans VALUES ('{}', 1, 0, 0)",
+                root.display()
+            ),
+        ])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["scan", "--on-busy", "skip", root.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(str::contains("already being scanned by"));
+    marlin(&tmp)
+        .args(["search", "one.txt"])
+        .assert()
+        .success()
+        .stdout(str::contains("one.txt").not());
+}
+
+/* ─────────────────────── CONTENT IDENTITY ─────────────────────── */
+
+#[test]
+fn content_identity_carries_tags_to_copies() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    let orig = dir.join("report.txt");
+    fs::write(&orig, "quarterly numbers").unwrap();
+
+    marlin(&tmp)
+        .args(["db", "identity", "content"])
+        .assert()
+        .success()
+        .stdout(str::contains("identity: content"));
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", orig.to_str().unwrap(), "finance"])
+        .assert()
+        .success();
+
+    let copy = dir.join("copy-of-report.txt");
+    fs::copy(&orig, &copy).unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["search", "tag:finance"])
+        .assert()
+        .success()
+        .stdout(str::contains("copy-of-report.txt"));
+    marlin(&tmp)
+        .args(["db", "aliases", orig.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(str::contains("copy-of-report.txt"));
+}
+
+/* ─────────────────────────── SNAPSHOTS ───────────────────────── */
+
+#[test]
+fn snapshot_diff_shows_retagging() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("memo.txt");
+    fs::write(&file, "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", file.to_str().unwrap(), "inbox"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["snapshot", "create", "before"])
+        .assert()
+        .success()
+        .stdout(str::contains("captured 1 entr(ies)"));
+    marlin(&tmp)
+        .args(["tag", file.to_str().unwrap(), "done"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["snapshot", "diff", "before"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("+ ")
+                .and(str::contains("memo.txt  tag   done"))
+                .and(str::contains("1 change(s)")),
+        );
+    marlin(&tmp)
+        .args(["snapshot", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("before"));
+}
+
+/* ───────────────────────── TIME FORMAT ───────────────────────── */
+
+#[test]
+fn listed_times_follow_time_format_and_utc() {
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args(["snapshot", "create", "s1"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["config", "set", "time_format", "taken %Y (%z)"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["config", "set", "timezone", "+0530"])
+        .assert()
+        .success()
+        .stdout(str::contains("timezone = +05:30"));
+
+    marlin(&tmp)
+        .args(["snapshot", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("taken ").and(str::contains("(+0530)")));
+    marlin(&tmp)
+        .args(["--utc", "snapshot", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("(+0000)"));
+}
+
+/* ─────────────────────────── DB DIFF ─────────────────────────── */
+
+#[test]
+fn db_diff_compares_the_index_with_a_backup() {
+    let tmp = tempdir().unwrap();
+    let snaps = tmp.path().join("snaps");
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("plan.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    let out = marlin(&tmp)
+        .args(["--backup-dir", snaps.to_str().unwrap(), "backup"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let id = stdout.trim().strip_prefix("Created backup ").unwrap();
+
+    marlin(&tmp)
+        .args(["tag", &format!("{}/*.md", dir.display()), "todo"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args([
+            "--backup-dir",
+            snaps.to_str().unwrap(),
+            "db",
+            "diff",
+            id,
+            "--query",
+            "plan",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("file_tags")
+                .and(str::contains("(+1)"))
+                .and(str::contains("plan.md  tag   todo"))
+                .and(str::contains("1 change(s)")),
+        );
+}
+
+/* ─────────────────────────── STATES ──────────────────────────── */
+
+#[test]
+fn state_set_follows_transitions_and_logs_history() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("spec.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["state", "set", "spec.md", "draft"])
+        .assert()
+        .success()
+        .stdout(str::contains("1 file(s) moved to draft"));
+    marlin(&tmp)
+        .args(["state", "transitions-add", "draft", "review"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["state", "set", "spec.md", "done"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no transition from `draft` to `done`"));
+    marlin(&tmp)
+        .args(["state", "set", "spec.md", "review"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["state", "log", "spec.md"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("draft")
+                .and(str::contains("review"))
+                .and(str::contains("spec.md")),
+        );
+}
+
+/* ─────────────────────────── TASKS ───────────────────────────── */
+
+#[test]
+fn task_add_list_done_and_search() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("spec.md"), "").unwrap();
+    fs::write(dir.join("notes.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["task", "add", "spec.md", "Review the API"])
+        .args(["--due", "2025-06-01", "--priority", "2"])
+        .assert()
+        .success()
+        .stdout(str::contains("Added task #1"));
+    marlin(&tmp)
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("Review the API")
+                .and(str::contains("2025-06-01"))
+                .and(str::contains("spec.md")),
+        );
+    marlin(&tmp)
+        .args(["search", "task:open"])
+        .assert()
+        .success()
+        .stdout(str::contains("spec.md").and(str::contains("notes.md").not()));
+
+    marlin(&tmp)
+        .args(["task", "done", "1"])
+        .assert()
+        .success()
+        .stdout(str::contains("Task #1 done"));
+    marlin(&tmp)
+        .args(["task", "list"])
+        .assert()
+        .success()
+        .stdout(str::contains("Review the API").not());
+    marlin(&tmp)
+        .args(["task", "list", "--all"])
+        .assert()
+        .success()
+        .stdout(str::contains("[x]"));
+    marlin(&tmp).args(["task", "rm", "1"]).assert().success();
+    marlin(&tmp)
+        .args(["task", "rm", "1"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no task #1"));
+}
+
+/* ─────────────────────────── REMINDERS ───────────────────────── */
+
+#[test]
+fn remind_add_then_due_lists_it() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("spec.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["remind", "add", "spec.md", "2h", "Send it to review"])
+        .assert()
+        .success()
+        .stdout(str::contains("Reminder #1 for").and(str::contains("spec.md")));
+    marlin(&tmp)
+        .args(["remind", "due"])
+        .assert()
+        .success()
+        .stdout(str::contains("Send it to review").not());
+    marlin(&tmp)
+        .args(["remind", "due", "--before", "1d"])
+        .assert()
+        .success()
+        .stdout(str::contains("Send it to review").and(str::contains("spec.md")));
+    marlin(&tmp)
+        .args(["remind", "list", "spec.md"])
+        .assert()
+        .success()
+        .stdout(str::contains("Send it to review"));
+}
+
+#[cfg(unix)]
+#[test]
+fn remind_test_runs_the_notify_command() {
+    let tmp = tempdir().unwrap();
+    let log = tmp.path().join("notified.txt");
+    let hook = format!(
+        r#"sh -c 'echo "$MARLIN_TITLE: $MARLIN_BODY" > {}'"#,
+        log.display()
+    );
+    marlin(&tmp)
+        .args(["config", "set", "notify.command", &hook])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["remind", "test", "--message", "hello"])
+        .assert()
+        .success()
+        .stdout(str::contains("Sent via command"));
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        "Marlin test reminder: hello\n"
+    );
+}
+
+/* ─────────────────────────── ANNOTATIONS ─────────────────────── */
+
+#[test]
+fn annotate_add_list_and_search_notes() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    let spec = dir.join("spec.md");
+    fs::write(&spec, "a\nb\nc\n").unwrap();
+    fs::write(dir.join("notes.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["annotate", "add", spec.to_str().unwrap(), "unclear wording"])
+        .args(["--range", "2-3", "--highlight"])
+        .assert()
+        .success()
+        .stdout(str::contains("Added annotation #1"));
+    marlin(&tmp)
+        .args(["annotate", "add", spec.to_str().unwrap(), "x"])
+        .args(["--range", "3-2"])
+        .assert()
+        .failure()
+        .stderr(str::contains("invalid range"));
+    marlin(&tmp)
+        .args(["annotate", "list", "*.md"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("unclear wording")
+                .and(str::contains("lines 2-3"))
+                .and(str::contains("notes.md").not()),
+        );
+    marlin(&tmp)
+        .args(["search", "note:unclear"])
+        .assert()
+        .success()
+        .stdout(str::contains("spec.md").and(str::contains("notes.md").not()));
+}
+
+/* ─────────────────────────── EVENTS ──────────────────────────── */
+
+#[test]
+fn event_add_list_and_timeline() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("spec.md"), "").unwrap();
+    fs::write(dir.join("plan.md"), "").unwrap();
+    fs::write(dir.join("notes.txt"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["event", "add", "*.md", "2025-05-20", "Launch review"])
+        .assert()
+        .success()
+        .stdout(str::contains("Added event #1").and(str::contains("Added event #2")));
+    marlin(&tmp)
+        .args(["event", "add", "notes.txt", "2025-05-02", "Offsite"])
+        .args(["--end", "2025-05-03"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["event", "add", "notes.txt", "2025-05-04", "Bad"])
+        .args(["--end", "2025-05-01"])
+        .assert()
+        .failure()
+        .stderr(str::contains("ends before it starts"));
+
+    let out = marlin(&tmp)
+        .args(["event", "timeline"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    let offsite = out.find("2025-05-02").expect("first day");
+    let launch = out.find("2025-05-20").expect("second day");
+    assert!(offsite < launch, "{out}");
+    assert!(out.contains("Offsite (until 2025-05-03)"), "{out}");
+    // one line for the launch, naming both files
+    let line = out.lines().find(|l| l.contains("Launch review")).unwrap();
+    assert!(
+        line.contains("plan.md") && line.contains("spec.md"),
+        "{out}"
+    );
+
+    marlin(&tmp)
+        .args(["event", "timeline", "--from", "2025-05-10"])
+        .assert()
+        .success()
+        .stdout(str::contains("Launch review").and(str::contains("Offsite").not()));
+    marlin(&tmp)
+        .args(["event", "list", "notes.txt"])
+        .assert()
+        .success()
+        .stdout(str::contains("#3").and(str::contains("Launch").not()));
+}
+
+/* ─────────────────────────── VERSIONS ────────────────────────── */
+
+#[test]
+fn version_snapshot_list_and_diff() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    let spec = dir.join("spec.md");
+    fs::write(&spec, "intro\nold line\n").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["version", "snapshot", "*.md"])
+        .assert()
+        .success()
+        .stdout(str::contains("spec.md: version 1"));
+    marlin(&tmp)
+        .args(["version", "snapshot", "*.md"])
+        .assert()
+        .success()
+        .stdout(str::contains("unchanged since version 1"));
+    fs::write(&spec, "intro\nnew line\n").unwrap();
+    marlin(&tmp)
+        .args(["version", "snapshot", "*.md"])
+        .assert()
+        .success()
+        .stdout(str::contains("version 2"));
+
+    let file = spec.to_str().unwrap();
+    marlin(&tmp)
+        .args(["version", "list", file])
+        .assert()
+        .success()
+        .stdout(str::contains("v1").and(str::contains("v2")));
+    marlin(&tmp)
+        .args(["version", "diff", file, "1", "2"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("-old line")
+                .and(str::contains("+new line"))
+                .and(str::contains(" intro")),
+        );
+    marlin(&tmp)
+        .args(["version", "diff", file, "2"])
+        .assert()
+        .success()
+        .stdout(str::is_empty());
+    marlin(&tmp)
+        .args(["version", "diff", file, "1", "7"])
+        .assert()
+        .failure()
+        .stderr(str::contains("no version 7"));
+}
+
+/* ─────────────────────────── EXPLAIN FILE ────────────────────── */
+
+#[test]
+fn explain_file_reports_tags_attrs_links_and_tasks() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("spec.md"), "spec").unwrap();
+    fs::write(dir.join("ref.md"), "ref").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    let spec = dir.join("spec.md").canonicalize().unwrap();
+    let spec = spec.to_str().unwrap();
+    let refd = dir.join("ref.md").canonicalize().unwrap();
+
+    marlin(&tmp)
+        .args(["tag", spec, "project/alpha"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["attr", "set", spec, "owner", "ana"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["link", "add", spec, refd.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["task", "add", "spec.md", "Review it"])
         .assert()
         .success();
 
-    // multi-path scan
     marlin(&tmp)
-        .args(["scan", dir_a.to_str().unwrap(), dir_b.to_str().unwrap()])
+        .args(["explain-file", spec])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("project/alpha")
+                .and(str::contains("owner = ana"))
+                .and(str::contains("Links out:"))
+                .and(str::contains("ref.md"))
+                .and(str::contains("Review it")),
+        );
+    marlin(&tmp)
+        .args(["explain-file", dir.join("missing.md").to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(str::contains("file not indexed"));
+}
+
+/* ─────────────────────────── ORGANIZE ──────────────────────────── */
+
+#[test]
+fn organize_moves_files_by_tag() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("inbox");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("invoice.pdf");
+    fs::write(&file, "pdf").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", file.to_str().unwrap(), "client/acme"])
         .assert()
         .success();
 
-    // both files findable
-    for term in ["one.txt", "two.txt"] {
+    let sorted = tmp.path().join("Sorted");
+    let organize = |dry: bool| {
+        let mut cmd = marlin(&tmp);
+        cmd.args(["organize", "--by", "tag:client", "--into"])
+            .arg(&sorted);
+        if dry {
+            cmd.arg("--dry-run");
+        }
+        cmd.assert().success()
+    };
+    organize(true).stdout(str::contains("Would place 1 file(s)"));
+    assert!(file.exists());
+
+    organize(false).stdout(str::contains("Placed 1 file(s)"));
+    let dest = sorted.join("acme/invoice.pdf");
+    assert!(dest.exists() && !file.exists());
+    marlin(&tmp)
+        .args(["search", "tag:client/acme"])
+        .assert()
+        .success()
+        .stdout(str::contains(dest.to_str().unwrap()));
+}
+
+/* ─────────────────────────── REINDEX ─────────────────────────── */
+
+#[test]
+fn reindex_refreshes_selected_files() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    let note = dir.join("note.txt");
+    fs::write(&note, "short").unwrap();
+    fs::write(dir.join("other.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+
+    fs::write(&note, "a much longer body").unwrap();
+    let pattern = format!("{}/*.txt", dir.display());
+    marlin(&tmp)
+        .args(["reindex", &pattern])
+        .assert()
+        .success()
+        .stdout(str::contains("Reindexed 1 file(s)"));
+    marlin(&tmp)
+        .args(["sql", "SELECT size FROM files WHERE path LIKE '%note.txt'"])
+        .assert()
+        .success()
+        .stdout(str::contains("18"));
+
+    fs::remove_file(&note).unwrap();
+    marlin(&tmp)
+        .args(["reindex", "--query", "note"])
+        .assert()
+        .success()
+        .stdout(str::contains("Reindexed 0 file(s)").and(str::contains("1 file(s) missing")));
+}
+
+/* ─────────────────────────── STRICT TAGS ─────────────────────────── */
+
+#[test]
+fn strict_tags_need_create_and_prune_unused() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("a.md");
+    fs::write(&file, "").unwrap();
+    let file = file.to_str().unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["config", "set", "tags.strict", "on"])
+        .assert()
+        .success()
+        .stdout(str::contains("tags.strict = true"));
+    marlin(&tmp)
+        .args(["tag", "--create", file, "project/md"])
+        .assert()
+        .success();
+    // existing tags (and their parents) need no flag
+    marlin(&tmp)
+        .args(["tag", file, "project"])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args([
+            "sql",
+            "--unsafe-write",
+            "DELETE FROM file_tags WHERE tag_id = (SELECT id FROM tags WHERE name = 'md')",
+        ])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tags", "prune-unused"])
+        .assert()
+        .success()
+        .stdout(str::contains("Pruned 1 unused tag(s)"));
+}
+
+/* ─────────────────────────── DB USE ───────────────────────────── */
+
+#[test]
+fn db_use_workspace_puts_the_index_in_dot_marlin() {
+    let tmp = tempdir().unwrap();
+    let ws = tmp.path().join("project");
+    fs::create_dir_all(ws.join("src")).unwrap();
+    let index = ws.join(".marlin").join("index.db");
+    let in_dir = |dir: &std::path::Path| {
+        let mut cmd = marlin(&tmp);
+        cmd.env_remove("MARLIN_DB_PATH")
+            .env("XDG_DATA_HOME", tmp.path().join("data"))
+            .current_dir(dir);
+        cmd
+    };
+
+    in_dir(&ws)
+        .args(["db", "use", "workspace"])
+        .assert()
+        .success()
+        .stdout(str::contains("db: workspace"));
+    assert!(fs::read_to_string(ws.join(".marlin/config.toml"))
+        .unwrap()
+        .contains("db = \"workspace\""));
+
+    // subdirectories find the workspace config
+    in_dir(&ws.join("src"))
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(str::contains(index.to_str().unwrap()));
+    assert!(index.exists());
+
+    in_dir(&ws.join("src"))
+        .args(["db", "use", "global"])
+        .assert()
+        .success()
+        .stdout(str::contains("db: global").and(str::contains("previous index stays")));
+}
+
+/* ─────────────────────────── DB TUNING ─────────────────────────── */
+
+#[test]
+fn tuning_preset_shows_up_in_db_tuning_show() {
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args(["db", "tuning", "show"])
+        .assert()
+        .success()
+        .stdout(str::contains("preset: safe").and(str::contains("FULL")));
+
+    marlin(&tmp)
+        .args(["config", "set", "db.tuning", "balanced"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["db", "tuning", "show"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("preset: balanced")
+                .and(str::contains("NORMAL"))
+                .and(str::contains("67108864")),
+        );
+}
+
+/* ───────────────────────────── GC ──────────────────────────────── */
+
+#[test]
+fn gc_drops_tags_left_without_files() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("old.txt"), "").unwrap();
+    fs::write(dir.join("new.txt"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", dir.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", dir.join("old.txt").to_str().unwrap(), "archive/2019"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["tag", dir.join("new.txt").to_str().unwrap(), "project"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args([
+            "sql",
+            "--unsafe-write",
+            "DELETE FROM files WHERE path LIKE '%old.txt'",
+        ])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["gc", "--vacuum"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("2  tags")
+                .and(str::contains("Removed 2 orphaned row(s)"))
+                .and(str::contains("Vacuum reclaimed")),
+        );
+    marlin(&tmp)
+        .args(["sql", "SELECT name FROM tags ORDER BY name"])
+        .assert()
+        .success()
+        .stdout(str::contains("project").and(str::contains("archive").not()));
+}
+
+/* ─────────────────────────── DOCTOR ───────────────────────────── */
+
+#[test]
+fn doctor_reports_and_repairs_fts_drift() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("a.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(str::contains("ok    full-text index"));
+
+    marlin(&tmp)
+        .args(["sql", "--unsafe-write", "DELETE FROM files_fts"])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(str::contains("FAIL  full-text index: 1 missing"))
+        .stderr(str::contains("--repair"));
+    marlin(&tmp)
+        .args(["doctor", "--repair"])
+        .assert()
+        .success()
+        .stdout(str::contains("repaired 1 full-text row(s)"));
+    marlin(&tmp).args(["doctor", "--full"]).assert().success();
+}
+
+/* ─────────────────────────── WARM ─────────────────────────────── */
+
+#[test]
+fn warm_reports_rows_per_target() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("a.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    marlin(&tmp)
+        .args(["warm", "--mmap"])
+        .assert()
+        .success()
+        .stdout(
+            str::contains("1  files.path index")
+                .and(str::contains("fts segments"))
+                .and(str::contains("via mmap")),
+        );
+}
+
+/* ─────────────────────────── TIMINGS ──────────────────────────── */
+
+#[test]
+fn timings_summary_goes_to_stderr() {
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args(["--timings", "sql", "SELECT 1"])
+        .assert()
+        .success()
+        .stdout(str::contains("timings").not())
+        .stderr(
+            str::contains("auto-backup")
+                .and(str::contains("db open"))
+                .and(str::contains("command"))
+                .and(str::contains("total")),
+        );
+}
+
+/* ─────────────────────────── STATUS ───────────────────────────── */
+
+#[test]
+fn status_verbose_reports_query_cache_hits() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("report.md"), "").unwrap();
+    marlin(&tmp)
+        .args(["scan", tmp.path().to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["view", "save", "reports", "report"])
+        .assert()
+        .success();
+
+    marlin(&tmp).arg("status").assert().success().stdout(
+        str::contains("Files:    1")
+            .and(str::contains("Schema:"))
+            .and(str::contains("Query cache").not()),
+    );
+
+    let script = tmp.path().join("twice.marlin");
+    fs::write(
+        &script,
+        "view exec reports\nview exec reports\nstatus --verbose\n",
+    )
+    .unwrap();
+    marlin(&tmp)
+        .args(["run", script.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(str::contains("Query cache: 1 hit(s), 1 miss(es)"));
+}
+
+/* ─────────────────────── PROGRESS EVENTS ───────────────────────── */
+
+#[test]
+fn events_json_streams_scan_progress_on_stderr() {
+    let tmp = tempdir().unwrap();
+    let docs = tmp.path().join("docs");
+    fs::create_dir_all(&docs).unwrap();
+    fs::write(docs.join("a.txt"), "").unwrap();
+    fs::write(docs.join("b.txt"), "").unwrap();
+
+    let out = marlin(&tmp)
+        .env("RUST_LOG", "off")
+        .args(["--events", "json", "scan", docs.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    let lines: Vec<&str> = std::str::from_utf8(&out.stderr).unwrap().lines().collect();
+    assert!(
+        lines.iter().all(|l| l.starts_with("{\"event\":")),
+        "{lines:?}"
+    );
+    assert!(lines
+        .iter()
+        .any(|l| l.contains("\"scan_started\"") && l.contains("\"total\":2")));
+    assert!(lines
+        .iter()
+        .any(|l| l.contains("\"scan_finished\"") && l.contains("\"indexed\":2")));
+    assert!(lines.last().unwrap().contains("\"command_finished\""));
+    assert!(lines.last().unwrap().contains("\"ok\":true"));
+}
+
+/* ─────────────────────── BENCH SELFTEST ────────────────────────── */
+
+#[test]
+fn bench_selftest_reports_every_workload() {
+    let tmp = tempdir().unwrap();
+    let work = tmp.path().join("scratch");
+    fs::create_dir_all(&work).unwrap();
+
+    let assert = marlin(&tmp)
+        .args([
+            "bench",
+            "selftest",
+            "--files",
+            "40",
+            "--dir",
+            work.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let out = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    for name in ["scan", "rescan", "fts query", "tag", "debounce"] {
+        assert!(out.contains(name), "missing {name}: {out}");
+    }
+
+    // scratch tree and DB are cleaned up; the user's DB is never created
+    assert_eq!(fs::read_dir(&work).unwrap().count(), 0);
+    assert!(!tmp.path().join("index.db").exists());
+}
+
+/* ─────────────────────── DEMO TREE ─────────────────────────────── */
+
+#[test]
+fn demo_generate_is_reproducible_and_scannable() {
+    let tmp = tempdir().unwrap();
+    let a = tmp.path().join("a");
+    let b = tmp.path().join("b");
+    for dir in [&a, &b] {
         marlin(&tmp)
-            .args(["search", term])
+            .args(["demo", "--generate", dir.to_str().unwrap()])
+            .args(["--files", "30", "--seed", "9"])
             .assert()
             .success()
-            .stdout(str::contains(term));
+            .stdout(str::contains("Generated 36 files"));
+    }
+    let tree = |root: &std::path::Path| {
+        let mut files: Vec<_> = walkdir::WalkDir::new(root)
+            .into_iter()
+            .map(|e| e.unwrap())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let rel = e.path().strip_prefix(root).unwrap().to_path_buf();
+                (rel, fs::read(e.path()).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    assert_eq!(tree(&a).len(), 36);
+    assert_eq!(tree(&a), tree(&b));
+    assert!(a.join("Projects/Alpha/draft1.md").is_file());
+
+    // refuses to write into a non-empty directory
+    marlin(&tmp)
+        .args(["demo", "--generate", a.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(str::contains("is not empty"));
+
+    marlin(&tmp)
+        .args(["scan", a.to_str().unwrap()])
+        .assert()
+        .success();
+    marlin(&tmp)
+        .args(["search", "draft1"])
+        .assert()
+        .success()
+        .stdout(str::contains("draft1.md"));
+}
+
+/* ─────────────────────── WATCH START/STOP ──────────────────────── */
+
+#[cfg(unix)]
+#[test]
+fn watch_start_comes_up_and_stops_by_name() {
+    use std::time::{Duration, Instant};
+
+    let tmp = tempdir().unwrap();
+    let registry = tmp.path().join("watchers");
+
+    marlin(&tmp)
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success();
+    // start-up reads this (and the burst guard) before binding the socket
+    marlin(&tmp)
+        .args(["config", "set", "watch.keep_metadata", "false"])
+        .assert()
+        .success();
+
+    let mut child = std::process::Command::new(util::bin())
+        .env("MARLIN_DB_PATH", tmp.path().join("index.db"))
+        .env("MARLIN_WATCH_REGISTRY", &registry)
+        .args([
+            "watch",
+            "start",
+            tmp.path().to_str().unwrap(),
+            "--name",
+            "ws",
+        ])
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(20);
+    let status = || {
+        marlin(&tmp)
+            .env("MARLIN_WATCH_REGISTRY", &registry)
+            .args(["watch", "status", "ws"])
+            .output()
+            .unwrap()
+            .status
+            .success()
+    };
+    while !status() {
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("watcher never answered on its control channel");
+        }
+        std::thread::sleep(Duration::from_millis(100));
     }
+
+    marlin(&tmp)
+        .env("MARLIN_WATCH_REGISTRY", &registry)
+        .args(["watch", "stop", "ws"])
+        .assert()
+        .success();
+    while child.try_wait().unwrap().is_none() {
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("watcher did not exit after `watch stop`");
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn view_watch_indexes_new_files_with_the_index_under_the_root() {
+    use std::io::{BufRead, BufReader};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let tmp = tempdir().unwrap();
+    marlin(&tmp)
+        .args(["view", "save", "tasks", "milk"])
+        .assert()
+        .success();
+
+    // the index sits in the watched directory, so every write to it is an
+    // event the view must not react to
+    let mut child = std::process::Command::new(util::bin())
+        .env("MARLIN_DB_PATH", tmp.path().join("index.db"))
+        .env("MARLIN_LOG_DIR", tmp.path().join("logs"))
+        .args(["view", "watch", "tasks", "--debounce-ms", "50", "--path"])
+        .arg(tmp.path())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = tx.send(line);
+        }
+    });
+
+    std::thread::sleep(Duration::from_secs(1));
+    fs::write(tmp.path().join("milk.txt"), "buy").unwrap();
+    let line = rx.recv_timeout(Duration::from_secs(20));
+    std::thread::sleep(Duration::from_millis(500));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(line.unwrap().ends_with("milk.txt"));
+    assert!(rx.try_iter().next().is_none());
 }
@@ -2,27 +2,25 @@ use std::thread;
 use std::time::Duration;
 use tempfile::tempdir;
 
-use libc;
 use libmarlin::watcher::WatcherState;
 use libmarlin::{self as marlin, db};
 use marlin_cli::cli::watch::WatchCmd;
-use marlin_cli::cli::{watch, Format};
+use marlin_cli::cli::{watch, Context, Format};
 
 #[test]
 fn watch_start_and_stop_quickly() {
     let tmp = tempdir().unwrap();
     let db_path = tmp.path().join("index.db");
     std::env::set_var("MARLIN_DB_PATH", &db_path);
+    std::env::set_var("MARLIN_WATCH_REGISTRY", tmp.path().join("watchers"));
 
-    // create database
-    let _m = marlin::Marlin::open_default().unwrap();
-
-    let mut conn = db::open(&db_path).unwrap();
+    let mut ctx = Context::new(marlin::Marlin::open_default().unwrap(), Format::Text);
 
     let path = tmp.path().to_path_buf();
     let cmd = WatchCmd::Start {
         path: path.clone(),
-        debounce_ms: 50,
+        debounce_ms: Some(50),
+        name: None,
     };
 
     // send SIGINT shortly after watcher starts
@@ -31,8 +29,145 @@ fn watch_start_and_stop_quickly() {
         unsafe { libc::raise(libc::SIGINT) };
     });
 
-    watch::run(&cmd, &mut conn, Format::Text).unwrap();
+    watch::run(&cmd, &mut ctx).unwrap();
     t.join().unwrap();
 
     assert_eq!(watch::last_watcher_state(), Some(WatcherState::Stopped));
 }
+
+#[test]
+fn render_systemd_unit_points_at_watch_daemon() {
+    let spec = watch::ServiceSpec {
+        exe: std::path::Path::new("/usr/local/bin/marlin"),
+        db_path: std::path::Path::new("/home/me/.local/share/marlin/index.db"),
+        watch_path: std::path::Path::new("/home/me/My Docs"),
+        user: true,
+    };
+    let unit = watch::render_service(watch::ServiceKind::Systemd, &spec);
+    assert!(unit.contains("ExecStart=/usr/local/bin/marlin watch daemon \"/home/me/My Docs\""));
+    assert!(unit.contains("Environment=\"MARLIN_DB_PATH=/home/me/.local/share/marlin/index.db\""));
+    assert!(unit.contains("Restart=on-failure"));
+    assert!(unit.contains("WantedBy=default.target"));
+
+    let plist = watch::render_service(watch::ServiceKind::Launchd, &spec);
+    assert!(plist.contains("<string>daemon</string>"));
+    assert!(plist.contains("<key>MARLIN_DB_PATH</key>"));
+
+    let winsw = watch::render_service(watch::ServiceKind::WinSw, &spec);
+    assert!(winsw.contains("<onfailure action=\"restart\""));
+}
+
+#[test]
+fn health_backs_off_and_gives_up_on_missing_db() {
+    use std::time::Instant;
+    use watch::{Health, MAX_RECOVERY_ATTEMPTS};
+
+    let tmp = tempdir().unwrap();
+    let db_path = tmp.path().join("index.db");
+    let _m = marlin::Marlin::open_at(&db_path).unwrap();
+
+    let mut h = Health::new();
+    h.check(&db_path, true, None);
+    assert_eq!(h.summary(), "ok");
+    assert!(!h.should_recover(Instant::now()));
+
+    // the DB disappears underneath the daemon
+    std::fs::remove_file(&db_path).unwrap();
+    h.check(&db_path, true, None);
+    assert!(!db_path.exists(), "health probe must not recreate the DB");
+    assert_eq!(h.summary(), "degraded");
+    assert!(h.report().contains("db: "));
+
+    let mut now = Instant::now();
+    for _ in 0..MAX_RECOVERY_ATTEMPTS {
+        assert!(h.should_recover(now));
+        h.recovery_failed(&anyhow::anyhow!("still broken"), now);
+        assert!(!h.should_recover(now), "retries are backed off");
+        now += Duration::from_secs(120);
+    }
+    assert!(!h.should_recover(now));
+    assert_eq!(h.summary(), "failed");
+    assert!(h.report().contains("last error: still broken"));
+
+    // a dead watcher thread alone is enough to be unhealthy
+    let mut h = Health::new();
+    let _m = marlin::Marlin::open_at(&db_path).unwrap();
+    h.check(&db_path, false, None);
+    assert_eq!(h.summary(), "degraded");
+    h.recovered();
+    assert_eq!(h.summary(), "ok");
+    assert_eq!(h.restarts, 1);
+}
+
+#[test]
+fn control_requests_need_a_token_with_the_right_scope() {
+    use libmarlin::tokens::{self, Scope};
+
+    let conn = db::open(":memory:").unwrap();
+    let photos = tokens::create(&conn, "photos", &[Scope::Tags("photos".into())]).unwrap();
+    let check = |token: Option<&str>, command: &str, required: bool| {
+        let words = watch::request_words(command);
+        watch::authorize(&conn, token, watch::required_access(&words), required)
+    };
+    let access = |command: &str| {
+        let words = watch::request_words(command);
+        watch::required_access(&words).to_string()
+    };
+
+    assert_eq!(access("stop"), "admin");
+    assert_eq!(access("tag photos/2025 *.jpg"), "tag:photos/2025");
+    assert_eq!(access("tag 'photos/my trip' *.jpg"), "tag:photos/my trip");
+    assert_eq!(access("events follow 5"), "read");
+
+    // without --require-token, anonymous requests keep working
+    assert!(check(None, "stop", false).is_ok());
+    assert!(check(None, "status", true).is_err());
+
+    assert!(check(Some(&photos), "status", true).is_ok());
+    assert!(check(Some(&photos), "tag photos/2025 *.jpg", true).is_ok());
+    let err = check(Some(&photos), "tag work/x *.jpg", true).unwrap_err();
+    assert!(err.contains("lacks the `tag:work/x` scope"), "{err}");
+    assert!(check(Some(&photos), "stop", false).is_err());
+    assert!(check(Some("mrl_bogus"), "status", false).is_err());
+}
+
+#[test]
+fn tag_requests_are_authorized_for_the_tag_they_apply() {
+    use libmarlin::tokens::{self, Scope};
+
+    let tmp = tempdir().unwrap();
+    std::fs::write(tmp.path().join("a.jpg"), "").unwrap();
+    let mut m = marlin::Marlin::open_at(":memory:").unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+    let photos = tokens::create(&m.conn(), "photos", &[Scope::Tags("photos".into())]).unwrap();
+
+    // a no-break space is not a word break for the shell split
+    let words = watch::request_words("tag photos\u{00A0}work/x *.jpg");
+    let access = watch::required_access(&words);
+    assert_eq!(access.to_string(), "tag:photos\u{00A0}work/x");
+    assert!(watch::authorize(&m.conn(), Some(&photos), access, true).is_err());
+
+    // and it is that very tag path the request would apply
+    let reply = watch::metadata_request(&mut m, &words).unwrap();
+    assert_eq!(reply, "tagged 1 file(s) with photos\u{00A0}work/x");
+}
+
+#[test]
+fn search_request_answers_from_the_open_index() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(tmp.path().join("plan.md"), "alpha").unwrap();
+    std::fs::write(tmp.path().join("notes.md"), "beta").unwrap();
+    let mut m = marlin::Marlin::open_at(":memory:").unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+
+    assert_eq!(
+        watch::required_access(&watch::request_words("search alpha")),
+        libmarlin::tokens::Access::Read
+    );
+    let reply = watch::search_request(&m, " alpha ");
+    assert!(reply.ends_with("plan.md\n"), "{reply}");
+    assert_eq!(reply.lines().count(), 1);
+    assert_eq!(watch::search_request(&m, "tag:none"), "");
+    assert!(watch::search_request(&m, "").starts_with("error: usage"));
+    assert!(watch::search_request(&m, "frobnicate:x").starts_with("error:"));
+}
@@ -0,0 +1,26 @@
+// libmarlin/benches/debounce.rs
+//
+// Watcher debouncing: coalescing a burst of four modify events per file.
+
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use libmarlin::bench::bench_sizes;
+use libmarlin::watcher::debounce_burst;
+
+fn debouncing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("debounce");
+    for files in bench_sizes() {
+        let events: Vec<PathBuf> = (0..files * 4)
+            .map(|i| PathBuf::from(format!("/bench/tree/f{}.txt", i % files)))
+            .collect();
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(format!("burst/{files}"), |b| {
+            b.iter(|| debounce_burst(&events))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, debouncing);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+// libmarlin/benches/scan.rs
+//
+// Scan throughput: a cold scan into an empty DB and a re-scan of an
+// unchanged tree.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use libmarlin::bench::{bench_sizes, synthetic_tree};
+use libmarlin::{db, scan};
+
+fn scan_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+    for files in bench_sizes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tree = tmp.path().join("tree");
+        synthetic_tree(&tree, files).unwrap();
+        group.throughput(Throughput::Elements(files as u64));
+
+        group.bench_function(format!("cold/{files}"), |b| {
+            b.iter_batched(
+                || db::open(":memory:").unwrap(),
+                |mut conn| scan::scan_directory(&mut conn, &tree).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+
+        let mut conn = db::open(":memory:").unwrap();
+        scan::scan_directory(&mut conn, &tree).unwrap();
+        group.bench_function(format!("unchanged/{files}"), |b| {
+            b.iter(|| scan::scan_directory(&mut conn, &tree).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scan_throughput);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+// libmarlin/benches/search.rs
+//
+// Query latency through the shared query engine: a plain FTS term, a tag
+// filter and a term that misses the index.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use libmarlin::bench::{bench_sizes, synthetic_tree};
+use libmarlin::{query, Marlin};
+
+fn query_latency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    for files in bench_sizes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tree = tmp.path().join("tree");
+        synthetic_tree(&tree, files).unwrap();
+        let mut m = Marlin::open_at(tmp.path().join("bench.db")).unwrap();
+        m.scan(&[&tree]).unwrap();
+        m.tag("invoice-*", "finance/invoice").unwrap();
+
+        for (name, q) in [
+            ("term", "report"),
+            ("tag", "tag:finance/invoice"),
+            ("miss", "zzzz"),
+        ] {
+            group.bench_function(format!("{name}/{files}"), |b| {
+                b.iter(|| query::search(&m.conn(), q).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, query_latency);
+criterion_main!(benches);
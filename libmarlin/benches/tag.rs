@@ -0,0 +1,38 @@
+// libmarlin/benches/tag.rs
+//
+// Tag application: matching a glob against every indexed path and
+// inserting the tag rows (one eighth of the tree matches).
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use libmarlin::bench::{bench_sizes, synthetic_tree, WORDS};
+use libmarlin::Marlin;
+
+fn tag_application(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tag");
+    group.sample_size(10);
+    for files in bench_sizes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tree = tmp.path().join("tree");
+        synthetic_tree(&tree, files).unwrap();
+        let mut m = Marlin::open_at(tmp.path().join("bench.db")).unwrap();
+        m.scan(&[&tree]).unwrap();
+        group.throughput(Throughput::Elements(files.div_ceil(WORDS.len()) as u64));
+
+        // a fresh tag every iteration so rows are really inserted
+        let mut n = 0u64;
+        group.bench_function(format!("glob/{files}"), |b| {
+            b.iter_batched(
+                || {
+                    n += 1;
+                    format!("bench/run{n}")
+                },
+                |tag| m.tag("report-*", &tag).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tag_application);
+criterion_main!(benches);
@@ -8,6 +8,11 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use crate::error as marlin_error;
+use crate::events::{self, Event};
+
+/// Time part of snapshot names, `backup_<stamp>.db`; always UTC so
+/// listing does not depend on the zone (or daylight saving) in effect.
+pub const STAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S_%fZ";
 
 #[derive(Debug, Clone)]
 pub struct BackupInfo {
@@ -17,16 +22,130 @@ pub struct BackupInfo {
     pub hash: Option<String>,
 }
 
+/// Outcome of verifying one snapshot.
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub info: BackupInfo,
+    pub ok: bool,
+    /// Why the check failed to run at all (unreadable / not a database).
+    pub error: Option<String>,
+}
+
+/// Small manifest written next to each snapshot (`<id>.meta`).
+///
+/// Lets `restore` notice a snapshot taken by a newer Marlin whose schema
+/// this build cannot read, instead of silently downgrading the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupMeta {
+    pub schema_version: i32,
+    pub files: i64,
+    /// `CARGO_PKG_VERSION` of the libmarlin that wrote the snapshot, or
+    /// `unknown` for snapshots predating manifests.
+    pub created_by: String,
+}
+
+impl BackupMeta {
+    /// Path of the manifest belonging to `snapshot`.
+    pub fn path_for(snapshot: &Path) -> PathBuf {
+        let mut p = snapshot.as_os_str().to_owned();
+        p.push(".meta");
+        PathBuf::from(p)
+    }
+
+    /// Inspect `snapshot` and write its manifest.
+    pub fn write_for(snapshot: &Path) -> Result<Self> {
+        let mut meta = Self::inspect(snapshot)?;
+        meta.created_by = env!("CARGO_PKG_VERSION").to_string();
+        let body = format!(
+            "schema_version={}\nfiles={}\ncreated_by={}\n",
+            meta.schema_version, meta.files, meta.created_by
+        );
+        let path = Self::path_for(snapshot);
+        fs::write(&path, body).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(meta)
+    }
+
+    /// Manifest for `snapshot`, falling back to reading the snapshot itself
+    /// when no (valid) manifest exists.
+    pub fn load(snapshot: &Path) -> Result<Self> {
+        if let Ok(text) = fs::read_to_string(Self::path_for(snapshot)) {
+            if let Some(meta) = Self::parse(&text) {
+                return Ok(meta);
+            }
+        }
+        Self::inspect(snapshot)
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let (mut schema, mut files, mut created_by) = (None, None, None);
+        for line in text.lines() {
+            match line.split_once('=') {
+                Some(("schema_version", v)) => schema = v.trim().parse().ok(),
+                Some(("files", v)) => files = v.trim().parse().ok(),
+                Some(("created_by", v)) => created_by = Some(v.trim().to_string()),
+                _ => {}
+            }
+        }
+        Some(Self {
+            schema_version: schema?,
+            files: files?,
+            created_by: created_by.unwrap_or_else(|| "unknown".into()),
+        })
+    }
+
+    fn inspect(snapshot: &Path) -> Result<Self> {
+        let conn = rusqlite::Connection::open_with_flags(
+            snapshot,
+            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+        )
+        .with_context(|| format!("Failed to open snapshot {}", snapshot.display()))?;
+        let schema_version = crate::db::current_schema_version(&conn).unwrap_or(0);
+        let files = conn
+            .query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0))
+            .unwrap_or(0);
+        Ok(Self {
+            schema_version,
+            files,
+            created_by: "unknown".into(),
+        })
+    }
+
+    /// Refuse snapshots whose schema is newer than this build understands,
+    /// unless `force` is set.
+    pub fn ensure_compatible(&self, force: bool) -> Result<()> {
+        if self.schema_version > crate::db::SCHEMA_VERSION && !force {
+            return Err(anyhow!(
+                "Backup has schema version {} (written by Marlin {}) but this build only \
+                 supports up to {}; restoring it would leave an unreadable index. \
+                 Use --force to restore anyway.",
+                self.schema_version,
+                self.created_by,
+                crate::db::SCHEMA_VERSION
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct PruneResult {
     pub kept: Vec<BackupInfo>,
     pub removed: Vec<BackupInfo>,
 }
 
+/// Forward SQLite's page counts while a snapshot is copied.
+fn report_progress(p: rusqlite::backup::Progress) {
+    events::emit(Event::BackupProgress {
+        remaining: p.remaining,
+        total: p.pagecount,
+    });
+}
+
 #[derive(Debug)]
 pub struct BackupManager {
     live_db_path: PathBuf,
     backups_dir: PathBuf,
+    force: bool,
 }
 
 impl BackupManager {
@@ -51,12 +170,18 @@ impl BackupManager {
         Ok(Self {
             live_db_path: live_db_path.as_ref().to_path_buf(),
             backups_dir: backups_dir_path,
+            force: false,
         })
     }
 
+    /// Allow restoring snapshots with a newer schema than this build.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
     pub fn create_backup(&self) -> Result<BackupInfo> {
-        let stamp = Local::now().format("%Y-%m-%d_%H-%M-%S_%f");
-        let backup_file_name = format!("backup_{stamp}.db");
+        let backup_file_name = format!("backup_{}.db", Utc::now().format(STAMP_FORMAT));
         let backup_file_path = self.backups_dir.join(&backup_file_name);
 
         if !self.live_db_path.exists() {
@@ -98,8 +223,14 @@ impl BackupManager {
             })?;
 
         backup_op
-            .run_to_completion(100, Duration::from_millis(250), None)
+            .run_to_completion(100, Duration::from_millis(250), Some(report_progress))
             .map_err(|e| anyhow::Error::new(e).context("SQLite backup operation failed"))?;
+        drop(backup_op);
+        drop(dst_conn);
+        BackupMeta::write_for(&backup_file_path)?;
+        events::emit(Event::BackupCreated {
+            path: backup_file_path.clone(),
+        });
 
         let metadata = fs::metadata(&backup_file_path).with_context(|| {
             format!(
@@ -144,14 +275,24 @@ impl BackupManager {
                                 .trim_start_matches("backup_")
                                 .trim_end_matches(".db");
 
+                            // Names ending in `Z` are UTC; older ones were
+                            // stamped in local time.
+                            let utc_dt = ts_str.strip_suffix('Z').and_then(|s| {
+                                NaiveDateTime::parse_from_str(s, "%Y-%m-%d_%H-%M-%S_%f")
+                                    .or_else(|_| {
+                                        NaiveDateTime::parse_from_str(s, "%Y-%m-%d_%H-%M-%S")
+                                    })
+                                    .ok()
+                            });
                             let parsed_dt =
                                 NaiveDateTime::parse_from_str(ts_str, "%Y-%m-%d_%H-%M-%S_%f")
                                     .or_else(|_| {
                                         NaiveDateTime::parse_from_str(ts_str, "%Y-%m-%d_%H-%M-%S")
                                     });
 
-                            let timestamp_utc = match parsed_dt {
-                                Ok(naive_dt) => {
+                            let timestamp_utc = match (utc_dt, parsed_dt) {
+                                (Some(naive_dt), _) => Utc.from_utc_datetime(&naive_dt),
+                                (None, Ok(naive_dt)) => {
                                     let local_dt_result = Local.from_local_datetime(&naive_dt);
                                     let local_dt = match local_dt_result {
                                         chrono::LocalResult::Single(dt) => dt,
@@ -169,7 +310,7 @@ impl BackupManager {
                                     };
                                     DateTime::<Utc>::from(local_dt)
                                 }
-                                Err(_) => DateTime::<Utc>::from(metadata.modified()?),
+                                (None, Err(_)) => DateTime::<Utc>::from(metadata.modified()?),
                             };
 
                             backup_infos.push(BackupInfo {
@@ -209,6 +350,7 @@ impl BackupManager {
                             )
                         })?;
                     }
+                    let _ = fs::remove_file(BackupMeta::path_for(&backup_file_path));
                     removed.push(backup_info);
                 }
             }
@@ -229,7 +371,55 @@ impl BackupManager {
         Ok(res == "ok")
     }
 
-    pub fn restore_from_backup(&self, backup_id: &str) -> Result<()> {
+    /// Integrity-check every snapshot, newest first, using one worker per
+    /// available CPU.
+    pub fn verify_all(&self) -> Result<Vec<VerifyReport>> {
+        let jobs = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        self.verify_all_with(jobs)
+    }
+
+    /// Like [`verify_all`](Self::verify_all) with at most `jobs` checks
+    /// running concurrently (`1` = sequential).
+    pub fn verify_all_with(&self, jobs: usize) -> Result<Vec<VerifyReport>> {
+        let backups = self.list_backups()?;
+        let jobs = jobs.max(1);
+        let chunk = backups.len().div_ceil(jobs).max(1);
+
+        let check = |info: &BackupInfo| match self.verify_backup(&info.id) {
+            Ok(ok) => VerifyReport {
+                info: info.clone(),
+                ok,
+                error: None,
+            },
+            Err(e) => VerifyReport {
+                info: info.clone(),
+                ok: false,
+                error: Some(e.to_string()),
+            },
+        };
+
+        let reports = std::thread::scope(|s| {
+            let handles: Vec<_> = backups
+                .chunks(chunk)
+                .map(|part| s.spawn(move || part.iter().map(check).collect::<Vec<_>>()))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("verify worker panicked"))
+                .collect()
+        });
+        Ok(reports)
+    }
+
+    /// Directory the snapshots live in.
+    pub fn backups_dir(&self) -> &Path {
+        &self.backups_dir
+    }
+
+    /// Full path of an existing backup.
+    pub fn backup_path(&self, backup_id: &str) -> Result<PathBuf> {
         let backup_file_path = self.backups_dir.join(backup_id);
         if !backup_file_path.exists() || !backup_file_path.is_file() {
             return Err(anyhow::Error::new(marlin_error::Error::NotFound(format!(
@@ -237,6 +427,17 @@ impl BackupManager {
                 backup_file_path.display()
             ))));
         }
+        Ok(backup_file_path)
+    }
+
+    /// Manifest of an existing backup.
+    pub fn metadata(&self, backup_id: &str) -> Result<BackupMeta> {
+        BackupMeta::load(&self.backup_path(backup_id)?)
+    }
+
+    pub fn restore_from_backup(&self, backup_id: &str) -> Result<()> {
+        let backup_file_path = self.backup_path(backup_id)?;
+        BackupMeta::load(&backup_file_path)?.ensure_compatible(self.force)?;
 
         fs::copy(&backup_file_path, &self.live_db_path).with_context(|| {
             format!(
@@ -245,6 +446,36 @@ impl BackupManager {
                 self.live_db_path.display()
             )
         })?;
+        events::emit(Event::RestoreFinished {
+            from: backup_file_path,
+            to: self.live_db_path.clone(),
+        });
+        Ok(())
+    }
+
+    /// Restore snapshot `backup_id` as a new database at `target_path`,
+    /// leaving the live DB untouched. Fails if `target_path` exists.
+    pub fn restore_to<P: AsRef<Path>>(&self, backup_id: &str, target_path: P) -> Result<()> {
+        let backup_file_path = self.backup_path(backup_id)?;
+        BackupMeta::load(&backup_file_path)?.ensure_compatible(self.force)?;
+        let target = target_path.as_ref();
+        if target.exists() {
+            return Err(anyhow!(
+                "Refusing to overwrite existing file: {}",
+                target.display()
+            ));
+        }
+        if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::copy(&backup_file_path, target).with_context(|| {
+            format!(
+                "Failed to copy backup {} to {}",
+                backup_file_path.display(),
+                target.display()
+            )
+        })?;
         Ok(())
     }
 }
@@ -464,6 +695,95 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_restore_to_new_path_leaves_live_db() {
+        let tmp = tempdir().unwrap();
+        let live_db_path = tmp.path().join("live_for_restore_to.db");
+        let _conn = create_valid_live_db(&live_db_path);
+
+        let manager = BackupManager::new(&live_db_path, tmp.path().join("bk")).unwrap();
+        let info = manager.create_backup().unwrap();
+        _conn
+            .execute("INSERT INTO test_table (data) VALUES ('after')", [])
+            .unwrap();
+
+        let fork = tmp.path().join("fork").join("copy.db");
+        manager.restore_to(&info.id, &fork).unwrap();
+
+        let count = |p: &Path| -> i64 {
+            rusqlite::Connection::open(p)
+                .unwrap()
+                .query_row("SELECT COUNT(*) FROM test_table", [], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(count(&fork), 1);
+        assert_eq!(count(&live_db_path), 2);
+
+        let err = manager.restore_to(&info.id, &fork).unwrap_err();
+        assert!(err.to_string().contains("Refusing to overwrite"));
+    }
+
+    #[test]
+    fn test_verify_all_flags_corrupted_snapshots() {
+        let tmp = tempdir().unwrap();
+        let live_db_path = tmp.path().join("live_for_verify_all.db");
+        let _conn = create_valid_live_db(&live_db_path);
+
+        let backups_dir = tmp.path().join("bk");
+        let manager = BackupManager::new(&live_db_path, &backups_dir).unwrap();
+        let good = manager.create_backup().unwrap();
+        std::fs::write(
+            backups_dir.join("backup_2000-01-01_00-00-00.db"),
+            b"definitely not sqlite",
+        )
+        .unwrap();
+
+        for jobs in [1, 4] {
+            let reports = manager.verify_all_with(jobs).unwrap();
+            assert_eq!(reports.len(), 2);
+            let ok: Vec<_> = reports.iter().filter(|r| r.ok).collect();
+            assert_eq!(ok.len(), 1);
+            assert_eq!(ok[0].info.id, good.id);
+            let bad = reports.iter().find(|r| !r.ok).unwrap();
+            assert!(bad.error.is_some());
+        }
+    }
+
+    #[test]
+    fn test_backup_meta_written_and_newer_schema_refused() {
+        let tmp = tempdir().unwrap();
+        let live_db_path = tmp.path().join("live_for_meta.db");
+        let _conn = create_valid_live_db(&live_db_path);
+
+        let manager = BackupManager::new(&live_db_path, tmp.path().join("bk")).unwrap();
+        let info = manager.create_backup().unwrap();
+        let meta = manager.metadata(&info.id).unwrap();
+        assert_eq!(meta.schema_version, crate::db::SCHEMA_VERSION);
+        assert_eq!(meta.files, 0);
+        assert_eq!(meta.created_by, env!("CARGO_PKG_VERSION"));
+
+        // pretend a future Marlin wrote this snapshot
+        let snap = manager.backup_path(&info.id).unwrap();
+        std::fs::write(
+            BackupMeta::path_for(&snap),
+            format!(
+                "schema_version={}\nfiles=0\ncreated_by=99.0.0\n",
+                crate::db::SCHEMA_VERSION + 1
+            ),
+        )
+        .unwrap();
+
+        let err = manager.restore_from_backup(&info.id).unwrap_err();
+        assert!(err.to_string().contains("--force"), "{err}");
+        let fork = tmp.path().join("fork.db");
+        assert!(manager.restore_to(&info.id, &fork).is_err());
+        assert!(!fork.exists());
+
+        let manager = manager.force(true);
+        manager.restore_to(&info.id, &fork).unwrap();
+        manager.restore_from_backup(&info.id).unwrap();
+    }
+
     #[test]
     fn test_restore_non_existent_backup() {
         let tmp = tempdir().unwrap();
@@ -546,6 +866,31 @@ mod tests {
         assert_eq!(info.timestamp, expected_ts);
     }
 
+    #[test]
+    fn list_backups_reads_utc_stamps_as_utc() {
+        let tmp = tempdir().unwrap();
+        let live_db = tmp.path().join("live_for_utc.db");
+        let _conn = create_valid_live_db(&live_db);
+
+        let backups_dir = tmp.path().join("backups_utc_test");
+        let manager = BackupManager::new(&live_db, &backups_dir).unwrap();
+        std::fs::write(
+            backups_dir.join("backup_2024-03-31_01-30-00_000000000Z.db"),
+            b"x",
+        )
+        .unwrap();
+        let created = manager.create_backup().unwrap();
+        assert!(created.id.ends_with("Z.db"), "{}", created.id);
+
+        let listed = manager.list_backups().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].id, created.id);
+        assert_eq!(
+            listed[1].timestamp,
+            Utc.with_ymd_and_hms(2024, 3, 31, 1, 30, 0).unwrap()
+        );
+    }
+
     #[test]
     fn verify_backup_ok() {
         let tmp = tempdir().unwrap();
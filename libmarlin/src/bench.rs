@@ -0,0 +1,121 @@
+//! Synthetic workloads for performance measurements.
+//!
+//! The criterion suites under `libmarlin/benches/` (`cargo bench`) and
+//! `marlin bench selftest` share these helpers, so numbers from a user's
+//! machine are comparable with the ones we track in CI.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::test_utils::{fixture_tree, FixtureSpec};
+use crate::{query, Marlin};
+
+pub use crate::test_utils::WORDS;
+
+/// Files per leaf directory in [`synthetic_tree`] (on average).
+pub const FILES_PER_DIR: usize = 100;
+
+/// Create `files` small files below `dir`, two directory levels deep with
+/// about [`FILES_PER_DIR`] files per leaf (`d03/d17/report-1742.md`).  A
+/// thin wrapper over [`fixture_tree`] with a fixed seed, so two trees of
+/// the same size are identical.  Returns the created paths.
+pub fn synthetic_tree(dir: &Path, files: usize) -> Result<Vec<PathBuf>> {
+    let fanout = ((files / FILES_PER_DIR) as f64).sqrt().ceil().max(1.0) as usize;
+    let spec = FixtureSpec::new()
+        .files(files)
+        .depth(2)
+        .fanout(fanout)
+        .sizes(8, 64)
+        .types(["md", "txt", "rs", "log", "json"]);
+    fixture_tree(dir, &spec)
+}
+
+/// Tree sizes for the criterion suites: `MARLIN_BENCH_SIZES`
+/// (comma-separated file counts), 10 000 by default.
+pub fn bench_sizes() -> Vec<usize> {
+    std::env::var("MARLIN_BENCH_SIZES")
+        .ok()
+        .map(|v| {
+            v.split(',')
+                .filter_map(|n| n.trim().replace('_', "").parse().ok())
+                .collect::<Vec<usize>>()
+        })
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| vec![10_000])
+}
+
+/// Timing of one workload.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub name: &'static str,
+    /// Items processed (files, queries, events …).
+    pub items: usize,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Items per second.
+    pub fn throughput(&self) -> f64 {
+        self.items as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Average time per item.
+    pub fn per_item(&self) -> Duration {
+        self.elapsed / self.items.max(1) as u32
+    }
+}
+
+fn timed<T>(
+    name: &'static str,
+    items: usize,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<BenchResult> {
+    let start = Instant::now();
+    f()?;
+    Ok(BenchResult {
+        name,
+        items,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Run the scaled-down suite inside `work_dir` (a synthetic tree of
+/// `files` files plus a scratch database) and return one result per
+/// workload: initial scan, unchanged re-scan, FTS queries, tagging and –
+/// with the `watcher` feature – event debouncing.
+pub fn selftest(work_dir: &Path, files: usize) -> Result<Vec<BenchResult>> {
+    let tree = work_dir.join("tree");
+    synthetic_tree(&tree, files)?;
+    let mut m = Marlin::open_at(work_dir.join("bench.db"))?;
+
+    let mut results = vec![
+        timed("scan", files, || m.scan(&[&tree]))?,
+        timed("rescan", files, || m.scan(&[&tree]))?,
+    ];
+
+    const QUERIES: usize = 200;
+    results.push(timed("fts query", QUERIES, || {
+        for i in 0..QUERIES {
+            query::search(&m.conn(), WORDS[i % WORDS.len()])?;
+        }
+        Ok(())
+    })?);
+
+    let tagged = files.div_ceil(WORDS.len());
+    results.push(timed("tag", tagged, || m.tag("report-*", "bench/report"))?);
+
+    #[cfg(feature = "watcher")]
+    {
+        let events = files * 4;
+        let paths: Vec<PathBuf> = (0..events)
+            .map(|i| tree.join(format!("burst-{}.tmp", i % files.max(1))))
+            .collect();
+        results.push(timed("debounce", events, || {
+            Ok(crate::watcher::debounce_burst(&paths))
+        })?);
+    }
+
+    Ok(results)
+}
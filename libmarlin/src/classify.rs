@@ -0,0 +1,419 @@
+//! External classifiers that suggest tags and attributes
+//! (`marlin classify`).
+//!
+//! A classifier is any program registered for a MIME type with the setting
+//! `classify.<type>` (`classify.text/markdown`, `classify.image/*`, or
+//! `classify.*` for everything).  It is run once per file with the text of
+//! the file on stdin (the first 64 KiB, empty for binary files) and
+//! `MARLIN_PATH` / `MARLIN_MIME` in its environment, and prints one
+//! suggestion per line, optionally preceded by a confidence between 0 and 1:
+//!
+//! ```text
+//! 0.92 tag finance/invoice
+//! attr vendor=ACME Corp
+//! ```
+//!
+//! Suggestions are applied below the `auto/` namespace, so they never mix
+//! with tags and attributes set by hand: the tag above becomes
+//! `auto/finance/invoice`, the attribute `auto/vendor`.  Confidences are
+//! stored as `auto/confidence/tag/<tag>` and `auto/confidence/attr/<key>`.
+//! Every run replaces a file's earlier suggestions.
+//!
+//! Files already classified at their current mtime by the same command are
+//! skipped unless forced.
+
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::{debug, info, warn};
+
+use crate::db::{self, Mutation};
+use crate::lock;
+use crate::utils::read_text_sample;
+
+/// Namespace of applied suggestions.
+pub const AUTO_NS: &str = "auto";
+/// Settings naming the classifier for a MIME type.
+pub const SETTING_PREFIX: &str = "classify.";
+/// How much of a body a classifier receives.
+pub const SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// MIME types by file extension; files with other extensions are
+/// `text/plain` if they read as text and `application/octet-stream`
+/// otherwise.
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("txt", "text/plain"),
+    ("log", "text/plain"),
+    ("md", "text/markdown"),
+    ("markdown", "text/markdown"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("csv", "text/csv"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("toml", "application/toml"),
+    ("rs", "text/x-rust"),
+    ("py", "text/x-python"),
+    ("sh", "text/x-shellscript"),
+    ("pdf", "application/pdf"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("rtf", "application/rtf"),
+    ("epub", "application/epub+zip"),
+    ("zip", "application/zip"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("png", "image/png"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("heic", "image/heic"),
+    ("svg", "image/svg+xml"),
+    ("mp3", "audio/mpeg"),
+    ("flac", "audio/flac"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("mkv", "video/x-matroska"),
+    ("mov", "video/quicktime"),
+];
+
+/// The MIME type of `path`, from its extension or, failing that, `text`
+/// (whether its start reads as text).
+pub fn mime_type(path: &Path, text: bool) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    ext.and_then(|e| MIME_TYPES.iter().find(|(x, _)| *x == e))
+        .map(|(_, mime)| *mime)
+        .unwrap_or(if text {
+            "text/plain"
+        } else {
+            "application/octet-stream"
+        })
+}
+
+/// The command configured for `mime`: `classify.<mime>`, else
+/// `classify.<major>/*`, else `classify.*`.
+pub fn classifier_for(conn: &Connection, mime: &str) -> Result<Option<String>> {
+    let major = mime.split('/').next().unwrap_or(mime);
+    for key in [
+        format!("{SETTING_PREFIX}{mime}"),
+        format!("{SETTING_PREFIX}{major}/*"),
+        format!("{SETTING_PREFIX}*"),
+    ] {
+        if let Some(cmd) = db::setting(conn, &key)? {
+            return Ok(Some(cmd));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `key` is a `classify.<mime>` setting.
+pub fn is_setting(key: &str) -> bool {
+    key.strip_prefix(SETTING_PREFIX).is_some_and(|mime| {
+        mime == "*"
+            || mime
+                .split_once('/')
+                .is_some_and(|(a, b)| !a.is_empty() && !b.is_empty())
+    })
+}
+
+/// One line of classifier output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Suggestion {
+    Tag {
+        path: String,
+        confidence: Option<f32>,
+    },
+    Attr {
+        key: String,
+        value: String,
+        confidence: Option<f32>,
+    },
+}
+
+impl std::fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (what, confidence) = match self {
+            Suggestion::Tag { path, confidence } => (format!("tag {AUTO_NS}/{path}"), confidence),
+            Suggestion::Attr {
+                key,
+                value,
+                confidence,
+            } => (format!("attr {AUTO_NS}/{key}={value}"), confidence),
+        };
+        match confidence {
+            Some(c) => write!(f, "{what} ({c:.2})"),
+            None => f.write_str(&what),
+        }
+    }
+}
+
+/// Parse classifier output; blank lines and `#` comments are skipped.
+pub fn parse_output(out: &str) -> Result<Vec<Suggestion>> {
+    let mut suggestions = Vec::new();
+    for (n, line) in out.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = || format!("classifier output line {}: `{line}`", n + 1);
+        let (confidence, rest) = match line.split_once(char::is_whitespace) {
+            Some((first, rest)) if first.parse::<f32>().is_ok() => {
+                let c: f32 = first.parse()?;
+                if !(0.0..=1.0).contains(&c) {
+                    bail!("{}: confidence must be between 0 and 1", bad());
+                }
+                (Some(c), rest.trim_start())
+            }
+            _ => (None, line),
+        };
+        let suggestion = match rest.split_once(char::is_whitespace) {
+            Some(("tag", path)) => {
+                let path = path.trim().trim_matches('/');
+                if path.is_empty() {
+                    bail!("{}: missing tag", bad());
+                }
+                Suggestion::Tag {
+                    path: path.to_string(),
+                    confidence,
+                }
+            }
+            Some(("attr", kv)) => {
+                let Some((key, value)) = kv.trim().split_once('=') else {
+                    bail!("{}: expected `attr <key>=<value>`", bad());
+                };
+                let key = key.trim().trim_matches('/');
+                if key.is_empty() {
+                    bail!("{}: missing attribute key", bad());
+                }
+                Suggestion::Attr {
+                    key: key.to_string(),
+                    value: value.trim().to_string(),
+                    confidence,
+                }
+            }
+            _ => bail!("{}: expected `tag …` or `attr …`", bad()),
+        };
+        suggestions.push(suggestion);
+    }
+    Ok(suggestions)
+}
+
+/// Run `command` on `path` and parse what it prints.
+pub fn run_classifier(
+    command: &str,
+    path: &Path,
+    mime: &str,
+    text: &str,
+) -> Result<Vec<Suggestion>> {
+    let argv = shlex::split(command)
+        .filter(|a| !a.is_empty())
+        .with_context(|| format!("cannot parse classifier command `{command}`"))?;
+    let mut child = Command::new(&argv[0])
+        .args(&argv[1..])
+        .env("MARLIN_PATH", path)
+        .env("MARLIN_MIME", mime)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("starting classifier `{}`", argv[0]))?;
+    // fed from a thread: a classifier may answer before reading everything
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    let input = text.to_owned();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut out = String::new();
+    child
+        .stdout
+        .take()
+        .expect("piped stdout")
+        .read_to_string(&mut out)?;
+    let status = child.wait()?;
+    let _ = writer.join();
+    if !status.success() {
+        bail!("classifier `{}` failed ({status})", argv[0]);
+    }
+    parse_output(&out)
+}
+
+/// Replace the `auto/` tags and attributes of `file_id` with
+/// `suggestions`.  Suggestions a validation rule rejects are skipped with
+/// a warning; returns how many were applied.  Locked files are an error.
+pub fn apply(conn: &Connection, file_id: i64, suggestions: &[Suggestion]) -> Result<usize> {
+    lock::check(conn, file_id)?;
+    let m = Mutation::new("apply_suggestions").file_id(file_id);
+    clear(conn, file_id)?;
+    let mut applied = 0;
+    for s in suggestions {
+        let result = match s {
+            Suggestion::Tag { path, confidence } => {
+                let tag = format!("{AUTO_NS}/{path}");
+                db::ensure_tag_path(conn, &tag)
+                    .and_then(|leaf| {
+                        for tid in db::tag_ancestry(conn, leaf)? {
+                            conn.execute(
+                                "INSERT OR IGNORE INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+                                [file_id, tid],
+                            )?;
+                        }
+                        Ok(())
+                    })
+                    .and_then(|()| store_confidence(conn, file_id, "tag", path, *confidence))
+            }
+            Suggestion::Attr {
+                key,
+                value,
+                confidence,
+            } => db::upsert_attr(conn, file_id, &format!("{AUTO_NS}/{key}"), value)
+                .and_then(|()| store_confidence(conn, file_id, "attr", key, *confidence)),
+        };
+        match result {
+            Ok(()) => applied += 1,
+            Err(e) => warn!(file_id, suggestion = %s, error = %e, "suggestion not applied"),
+        }
+    }
+    Ok(m.done(applied))
+}
+
+fn store_confidence(
+    conn: &Connection,
+    file_id: i64,
+    kind: &str,
+    name: &str,
+    confidence: Option<f32>,
+) -> Result<()> {
+    match confidence {
+        Some(c) => db::upsert_attr(
+            conn,
+            file_id,
+            &format!("{AUTO_NS}/confidence/{kind}/{name}"),
+            &format!("{c}"),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Drop every `auto/` tag and attribute of `file_id`.
+fn clear(conn: &Connection, file_id: i64) -> Result<()> {
+    conn.execute(
+        r#"WITH RECURSIVE auto_tags(id) AS (
+               SELECT id FROM tags WHERE name = ?2 AND parent_id IS NULL
+               UNION ALL
+               SELECT t.id FROM tags t JOIN auto_tags a ON t.parent_id = a.id
+           )
+           DELETE FROM file_tags
+            WHERE file_id = ?1 AND tag_id IN (SELECT id FROM auto_tags)"#,
+        params![file_id, AUTO_NS],
+    )?;
+    conn.execute(
+        "DELETE FROM attributes WHERE file_id = ?1 AND substr(key, 1, ?3) = ?2",
+        params![file_id, format!("{AUTO_NS}/"), AUTO_NS.len() + 1],
+    )?;
+    Ok(())
+}
+
+/// How [`classify_files`] runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClassifyOptions {
+    /// Classify files again even if nothing changed since the last run.
+    pub force: bool,
+    /// Only collect the suggestions; nothing is written.
+    pub dry_run: bool,
+}
+
+/// Outcome of [`classify_files`].
+#[derive(Debug, Default, Clone)]
+pub struct ClassifyReport {
+    /// Suggestions per classified file.
+    pub classified: Vec<(String, Vec<Suggestion>)>,
+    /// Files skipped because they were classified at their current mtime.
+    pub up_to_date: usize,
+    /// Files with no classifier configured for their MIME type.
+    pub unconfigured: usize,
+    /// Files gone from disk or whose classifier failed.
+    pub failed: usize,
+    /// Locked files (see [`crate::lock`]), left alone.
+    pub locked: usize,
+}
+
+/// Run the configured classifier on each of `files` (`(id, path)` pairs
+/// as returned by [`crate::db::match_files`]) and apply its suggestions.
+/// A failing classifier is logged and the next file classified; each
+/// file's suggestions are applied atomically.
+pub fn classify_files(
+    conn: &mut Connection,
+    files: &[(i64, String)],
+    opts: ClassifyOptions,
+) -> Result<ClassifyReport> {
+    let mut report = ClassifyReport::default();
+    let override_locks = lock::overridden(conn)?;
+    for (id, path) in files {
+        if !override_locks && lock::is_locked(conn, *id)? {
+            debug!(file = %path, "locked, not classified");
+            report.locked += 1;
+            continue;
+        }
+        let p = Path::new(path);
+        let Ok(file) = std::fs::File::open(p) else {
+            warn!(file = %path, "cannot read, not classified");
+            report.failed += 1;
+            continue;
+        };
+        let text = read_text_sample(file, SAMPLE_BYTES);
+        let mime = mime_type(p, text.is_some());
+        let Some(command) = classifier_for(conn, mime)? else {
+            debug!(file = %path, mime, "no classifier configured");
+            report.unconfigured += 1;
+            continue;
+        };
+
+        let mtime: i64 =
+            conn.query_row("SELECT mtime FROM files WHERE id = ?1", [id], |r| r.get(0))?;
+        let done = conn
+            .query_row(
+                "SELECT 1 FROM classified WHERE file_id = ?1 AND mtime = ?2 AND command = ?3",
+                params![id, mtime, command],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if done && !opts.force {
+            report.up_to_date += 1;
+            continue;
+        }
+
+        let suggestions = match run_classifier(&command, p, mime, text.as_deref().unwrap_or("")) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!(file = %path, error = %format!("{e:#}"), "classifier failed");
+                report.failed += 1;
+                continue;
+            }
+        };
+        if !opts.dry_run {
+            let m = Mutation::new("classify_file").file_id(*id).path(path);
+            let tx = conn.savepoint()?;
+            let n = apply(&tx, *id, &suggestions)?;
+            tx.execute(
+                "INSERT INTO classified(file_id, mtime, command) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(file_id) DO UPDATE
+                    SET mtime = excluded.mtime, command = excluded.command",
+                params![id, mtime, command],
+            )?;
+            tx.commit()?;
+            m.done(n);
+            info!(file = %path, applied = n, "classified");
+        }
+        report.classified.push((path.clone(), suggestions));
+    }
+    Ok(report)
+}
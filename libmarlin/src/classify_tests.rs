@@ -0,0 +1,140 @@
+// libmarlin/src/classify_tests.rs
+
+use std::path::Path;
+
+use super::classify::{self, mime_type, parse_output, ClassifyOptions, Suggestion};
+use super::db;
+use super::scan::scan_directory;
+use tempfile::tempdir;
+
+/// Full paths of the tags on `file_id`.
+fn file_tags(conn: &rusqlite::Connection, file_id: i64) -> Vec<String> {
+    conn.prepare(
+        r#"WITH RECURSIVE tree(id, path) AS (
+               SELECT id, name FROM tags WHERE parent_id IS NULL
+               UNION ALL
+               SELECT t.id, tree.path || '/' || t.name FROM tags t JOIN tree ON t.parent_id = tree.id
+           )
+           SELECT path FROM tree JOIN file_tags ft ON ft.tag_id = tree.id
+            WHERE ft.file_id = ?1 ORDER BY path"#,
+    )
+    .unwrap()
+    .query_map([file_id], |r| r.get(0))
+    .unwrap()
+    .map(Result::unwrap)
+    .collect()
+}
+
+#[test]
+fn mime_type_uses_the_extension_then_the_content() {
+    assert_eq!(mime_type(Path::new("a/Notes.MD"), false), "text/markdown");
+    assert_eq!(mime_type(Path::new("scan.pdf"), true), "application/pdf");
+    assert_eq!(mime_type(Path::new("README"), true), "text/plain");
+    assert_eq!(
+        mime_type(Path::new("blob"), false),
+        "application/octet-stream"
+    );
+}
+
+#[test]
+fn classifier_for_falls_back_from_type_to_major_type_to_any() {
+    let conn = db::open(":memory:").unwrap();
+    assert_eq!(classify::classifier_for(&conn, "text/plain").unwrap(), None);
+    db::set_setting(&conn, "classify.*", "any").unwrap();
+    db::set_setting(&conn, "classify.text/*", "texts").unwrap();
+    db::set_setting(&conn, "classify.text/markdown", "md").unwrap();
+    let c = |m: &str| classify::classifier_for(&conn, m).unwrap().unwrap();
+    assert_eq!(c("text/markdown"), "md");
+    assert_eq!(c("text/plain"), "texts");
+    assert_eq!(c("image/png"), "any");
+
+    assert!(classify::is_setting("classify.image/*"));
+    assert!(classify::is_setting("classify.*"));
+    assert!(!classify::is_setting("classify.text"));
+}
+
+#[test]
+fn parse_output_reads_tags_attrs_and_confidences() {
+    let s = parse_output(
+        "# header\n0.9 tag finance/invoice\n\nattr vendor = ACME Corp\n0.5 attr year=2025\n",
+    )
+    .unwrap();
+    assert_eq!(
+        s,
+        vec![
+            Suggestion::Tag {
+                path: "finance/invoice".into(),
+                confidence: Some(0.9)
+            },
+            Suggestion::Attr {
+                key: "vendor".into(),
+                value: "ACME Corp".into(),
+                confidence: None
+            },
+            Suggestion::Attr {
+                key: "year".into(),
+                value: "2025".into(),
+                confidence: Some(0.5)
+            },
+        ]
+    );
+    assert!(parse_output("1.5 tag x").is_err());
+    assert!(parse_output("label red").is_err());
+    assert!(parse_output("attr novalue").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn classify_files_applies_suggestions_under_auto_and_replaces_them() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::write(dir.join("invoice.txt"), "Invoice from ACME").unwrap();
+    std::fs::write(dir.join("photo.jpg"), [0xffu8, 0xd8, 0x00]).unwrap();
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, &dir).unwrap();
+    let files = db::match_files(&conn, "*").unwrap();
+    let invoice = db::file_id(&conn, &files[0].1).unwrap();
+    db::tag_files(&conn, &files[..1], "mine", true).unwrap();
+
+    // the classifier sees the text and the MIME type
+    db::set_setting(
+        &conn,
+        "classify.text/*",
+        r#"sh -c 'grep -q ACME && echo "0.75 tag finance/invoice"; echo "attr mime=$MARLIN_MIME"'"#,
+    )
+    .unwrap();
+    let report = classify::classify_files(&mut conn, &files, ClassifyOptions::default()).unwrap();
+    assert_eq!(report.classified.len(), 1);
+    assert_eq!(report.unconfigured, 1);
+
+    let tags = file_tags(&conn, invoice);
+    assert!(
+        tags.contains(&"auto/finance/invoice".to_string()),
+        "{tags:?}"
+    );
+    assert!(tags.contains(&"mine".to_string()));
+    let attrs: Vec<(String, String)> = db::file_attrs(&conn, invoice)
+        .unwrap()
+        .into_iter()
+        .map(|(k, v, _)| (k, v))
+        .collect();
+    assert!(attrs.contains(&("auto/mime".into(), "text/plain".into())));
+    assert!(attrs.contains(&("auto/confidence/tag/finance/invoice".into(), "0.75".into())));
+
+    // unchanged: skipped; forced with a new answer: old suggestions go
+    let again = classify::classify_files(&mut conn, &files, ClassifyOptions::default()).unwrap();
+    assert_eq!((again.classified.len(), again.up_to_date), (0, 1));
+    db::set_setting(&conn, "classify.text/*", "echo tag misc").unwrap();
+    classify::classify_files(&mut conn, &files, ClassifyOptions::default()).unwrap();
+    let tags = file_tags(&conn, invoice);
+    assert!(tags.contains(&"auto/misc".to_string()));
+    assert!(
+        !tags.iter().any(|t| t.starts_with("auto/finance")),
+        "{tags:?}"
+    );
+    assert!(!db::file_attrs(&conn, invoice)
+        .unwrap()
+        .iter()
+        .any(|(k, _, _)| k == "auto/mime"));
+}
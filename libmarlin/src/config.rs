@@ -1,15 +1,133 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use directories::ProjectDirs;
 use std::{
     collections::hash_map::DefaultHasher,
+    fmt,
     hash::{Hash, Hasher},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
-/// Runtime configuration (currently just the DB path).
+/// Directory holding a workspace's Marlin files.
+pub const WORKSPACE_DIR: &str = ".marlin";
+/// Workspace config file, relative to the workspace root.
+pub const WORKSPACE_CONFIG: &str = ".marlin/config.toml";
+
+/// Runtime configuration.
 #[derive(Debug, Clone)]
 pub struct Config {
     pub db_path: PathBuf,
+    /// Where snapshots are written and looked up.
+    pub backups_dir: PathBuf,
+}
+
+/// Which index a workspace uses, chosen with `marlin db use` and stored as
+/// `db = "workspace"|"global"` in [`WORKSPACE_CONFIG`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbScope {
+    /// `<workspace>/.marlin/index.db`, next to the config and shareable
+    /// with the project.
+    Workspace,
+    /// One index per user in the XDG data dir
+    /// (`~/.local/share/marlin/index.db`).
+    Global,
+}
+
+impl DbScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DbScope::Workspace => "workspace",
+            DbScope::Global => "global",
+        }
+    }
+}
+
+impl fmt::Display for DbScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DbScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "workspace" => Ok(DbScope::Workspace),
+            "global" => Ok(DbScope::Global),
+            other => bail!("unknown db scope `{other}` (expected workspace or global)"),
+        }
+    }
+}
+
+/// Nearest directory from `start` upwards that holds a
+/// [`WORKSPACE_CONFIG`].
+pub fn find_workspace(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|d| d.join(WORKSPACE_CONFIG).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The `db` choice stored in the workspace config below `root`, if any.
+pub fn workspace_scope(root: &Path) -> Result<Option<DbScope>> {
+    let path = root.join(WORKSPACE_CONFIG);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("{}:{}: expected `key = \"value\"`", path.display(), n + 1);
+        };
+        if key.trim() == "db" {
+            let value = value.trim().trim_matches('"');
+            return value
+                .parse()
+                .map(Some)
+                .with_context(|| format!("{}:{}", path.display(), n + 1));
+        }
+    }
+    Ok(None)
+}
+
+/// Store `scope` in the workspace config below `root`, creating
+/// `.marlin/` (with a `.gitignore` for backups and SQLite's side files)
+/// on first use.  Other lines of an existing config are kept.
+pub fn set_workspace_scope(root: &Path, scope: DbScope) -> Result<()> {
+    let dir = root.join(WORKSPACE_DIR);
+    std::fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    let ignore = dir.join(".gitignore");
+    if !ignore.exists() {
+        std::fs::write(&ignore, "backups/\n*.db-wal\n*.db-shm\n")?;
+    }
+
+    let path = root.join(WORKSPACE_CONFIG);
+    let old = std::fs::read_to_string(&path).unwrap_or_default();
+    let entry = format!("db = \"{scope}\"");
+    let mut lines: Vec<String> = Vec::new();
+    let mut replaced = false;
+    for line in old.lines() {
+        let is_db = line
+            .split_once('=')
+            .is_some_and(|(k, _)| k.trim() == "db" && !line.trim_start().starts_with('#'));
+        if is_db && !replaced {
+            lines.push(entry.clone());
+            replaced = true;
+        } else if !is_db {
+            lines.push(line.to_string());
+        }
+    }
+    if !replaced {
+        lines.push(entry);
+    }
+    std::fs::write(&path, lines.join("\n") + "\n")
+        .with_context(|| format!("writing {}", path.display()))
 }
 
 impl Config {
@@ -17,38 +135,102 @@ impl Config {
     ///
     /// Priority:
     /// 1. `MARLIN_DB_PATH` env-var (explicit override)
-    /// 2. *Workspace-local* file under XDG data dir
+    /// 2. the `db` choice of the nearest workspace config
+    ///    ([`WORKSPACE_CONFIG`], see [`DbScope`])
+    /// 3. *Workspace-local* file under XDG data dir
     ///    (`~/.local/share/marlin/index_<hash>.db`)
-    /// 3. Fallback to   `./index.db`  when we cannot locate an XDG dir
+    /// 4. Fallback to   `./index.db`  when we cannot locate an XDG dir
+    ///
+    /// The backups directory defaults to `<db dir>/backups` for explicit
+    /// DB paths and to the XDG state dir
+    /// (`~/.local/state/marlin/backups/index_<hash>/`) otherwise;
+    /// `MARLIN_BACKUPS_DIR` overrides both.
     pub fn load() -> Result<Self> {
+        Self::load_in(&std::env::current_dir()?)
+    }
+
+    /// [`load`](Self::load) as if run from `cwd`.
+    pub fn load_in(cwd: &Path) -> Result<Self> {
+        let mut cfg = Self::load_db_path(cwd)?;
+        if let Some(val) = std::env::var_os("MARLIN_BACKUPS_DIR") {
+            cfg.backups_dir = PathBuf::from(val);
+        }
+        Ok(cfg)
+    }
+
+    /// Config for an explicit DB path; backups go to `<db dir>/backups`.
+    pub fn for_db_path<P: AsRef<Path>>(db_path: P) -> Self {
+        let db_path = db_path.as_ref().to_path_buf();
+        let backups_dir = db_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("backups");
+        Self {
+            db_path,
+            backups_dir,
+        }
+    }
+
+    /// Config for `scope` in the workspace rooted at `root`.
+    pub fn for_scope(root: &Path, scope: DbScope) -> Result<Self> {
+        match scope {
+            DbScope::Workspace => {
+                let dir = root.join(WORKSPACE_DIR);
+                std::fs::create_dir_all(&dir)?;
+                Ok(Self::for_db_path(dir.join("index.db")))
+            }
+            DbScope::Global => Self::in_data_dir("index.db")?
+                .context("`db = \"global\"` needs a per-user data directory (set HOME)"),
+        }
+    }
+
+    fn load_db_path(cwd: &Path) -> Result<Self> {
         // 1) explicit override
         if let Some(val) = std::env::var_os("MARLIN_DB_PATH") {
             let p = PathBuf::from(val);
             std::fs::create_dir_all(p.parent().expect("has parent"))?;
-            return Ok(Self { db_path: p });
+            return Ok(Self::for_db_path(p));
+        }
+
+        // 2) the workspace says where its index lives
+        if let Some(root) = find_workspace(cwd) {
+            if let Some(scope) = workspace_scope(&root)? {
+                return Self::for_scope(&root, scope);
+            }
         }
 
-        // 2) derive per-workspace DB name from CWD hash
-        let cwd = std::env::current_dir()?;
+        // 3) derive per-workspace DB name from CWD hash
         let mut h = DefaultHasher::new();
         cwd.hash(&mut h);
         let digest = h.finish(); // 64-bit
         let file_name = format!("index_{digest:016x}.db");
-
-        // If HOME and XDG_DATA_HOME are missing we can't resolve an XDG path
-        if std::env::var_os("HOME").is_some() || std::env::var_os("XDG_DATA_HOME").is_some() {
-            if let Some(dirs) = ProjectDirs::from("io", "Marlin", "marlin") {
-                let dir = dirs.data_dir();
-                std::fs::create_dir_all(dir)?;
-                return Ok(Self {
-                    db_path: dir.join(file_name),
-                });
-            }
+        if let Some(cfg) = Self::in_data_dir(&file_name)? {
+            return Ok(cfg);
         }
 
-        // 3) very last resort – workspace-relative DB
-        Ok(Self {
-            db_path: Path::new(&file_name).to_path_buf(),
-        })
+        // 4) very last resort – workspace-relative DB
+        Ok(Self::for_db_path(&file_name))
+    }
+
+    /// `file_name` in the XDG data dir, with backups in the state dir;
+    /// `None` when neither HOME nor XDG_DATA_HOME is set.
+    fn in_data_dir(file_name: &str) -> Result<Option<Self>> {
+        if std::env::var_os("HOME").is_none() && std::env::var_os("XDG_DATA_HOME").is_none() {
+            return Ok(None);
+        }
+        let Some(dirs) = ProjectDirs::from("io", "Marlin", "marlin") else {
+            return Ok(None);
+        };
+        let dir = dirs.data_dir();
+        std::fs::create_dir_all(dir)?;
+        // Backups are state, not data: XDG state dir where the
+        // platform has one, one sub-dir per index.
+        let state = dirs.state_dir().unwrap_or(dir);
+        Ok(Some(Self {
+            db_path: dir.join(file_name),
+            backups_dir: state
+                .join("backups")
+                .join(file_name.trim_end_matches(".db")),
+        }))
     }
 }
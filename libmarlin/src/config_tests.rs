@@ -13,6 +13,19 @@ fn load_env_override() {
     env::set_var("MARLIN_DB_PATH", &db);
     let cfg = Config::load().unwrap();
     assert_eq!(cfg.db_path, db);
+    assert_eq!(cfg.backups_dir, tmp.path().join("backups"));
+    env::remove_var("MARLIN_DB_PATH");
+}
+
+#[test]
+fn load_backups_dir_override() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let tmp = tempdir().unwrap();
+    env::set_var("MARLIN_DB_PATH", tmp.path().join("custom.db"));
+    env::set_var("MARLIN_BACKUPS_DIR", tmp.path().join("snaps"));
+    let cfg = Config::load().unwrap();
+    assert_eq!(cfg.backups_dir, tmp.path().join("snaps"));
+    env::remove_var("MARLIN_BACKUPS_DIR");
     env::remove_var("MARLIN_DB_PATH");
 }
 
@@ -63,3 +76,46 @@ fn load_fallback_current_dir() {
         None => env::remove_var("XDG_DATA_HOME"),
     }
 }
+
+#[test]
+fn workspace_scope_round_trips_and_keeps_other_lines() {
+    use super::config::{set_workspace_scope, workspace_scope, DbScope, WORKSPACE_CONFIG};
+    let tmp = tempdir().unwrap();
+    assert_eq!(workspace_scope(tmp.path()).unwrap(), None);
+
+    set_workspace_scope(tmp.path(), DbScope::Global).unwrap();
+    assert_eq!(workspace_scope(tmp.path()).unwrap(), Some(DbScope::Global));
+    assert!(tmp.path().join(".marlin/.gitignore").exists());
+
+    let cfg = tmp.path().join(WORKSPACE_CONFIG);
+    std::fs::write(&cfg, "# team setup\ndb = \"global\"\nother = 1\n").unwrap();
+    set_workspace_scope(tmp.path(), DbScope::Workspace).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&cfg).unwrap(),
+        "# team setup\ndb = \"workspace\"\nother = 1\n"
+    );
+
+    std::fs::write(&cfg, "db = \"shared\"\n").unwrap();
+    assert!(workspace_scope(tmp.path()).is_err());
+}
+
+#[test]
+fn workspace_config_selects_the_index_from_subdirectories() {
+    use super::config::{set_workspace_scope, DbScope};
+    let _guard = ENV_MUTEX.lock().unwrap();
+    env::remove_var("MARLIN_DB_PATH");
+    let tmp = tempdir().unwrap();
+    let sub = tmp.path().join("src/deep");
+    std::fs::create_dir_all(&sub).unwrap();
+
+    set_workspace_scope(tmp.path(), DbScope::Workspace).unwrap();
+    let cfg = Config::load_in(&sub).unwrap();
+    assert_eq!(cfg.db_path, tmp.path().join(".marlin/index.db"));
+    assert_eq!(cfg.backups_dir, tmp.path().join(".marlin/backups"));
+
+    // the env override still wins
+    env::set_var("MARLIN_DB_PATH", tmp.path().join("custom.db"));
+    let cfg = Config::load_in(&sub).unwrap();
+    assert_eq!(cfg.db_path, tmp.path().join("custom.db"));
+    env::remove_var("MARLIN_DB_PATH");
+}
@@ -0,0 +1,292 @@
+//! Conflicts met while importing metadata (`marlin sync import`,
+//! `marlin link import`) and the audit trail of how each was settled.
+//!
+//! An attribute conflicts when the incoming value differs from the one
+//! the file already has; a link when the file already links to the same
+//! target, but only with other types.  Left alone ([`OnConflict::Default`])
+//! each import keeps its own rule – last writer wins for sync, links of
+//! every type side by side for link import – and records nothing.  With a
+//! [`Strategy`] or a resolver that asks, every conflict is settled by a
+//! [`Resolution`] and written to the `import_conflicts` table, which
+//! [`decisions`] reads back.  Decisions change this index only.
+
+use std::fmt;
+
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+use tracing::debug;
+
+use crate::db;
+
+/// What sort of metadata conflicts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    Attr,
+    Link,
+}
+
+impl ConflictKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConflictKind::Attr => "attr",
+            ConflictKind::Link => "link",
+        }
+    }
+}
+
+impl fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Incoming metadata disagreeing with the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// The file (a link's source).
+    pub path: String,
+    /// Attribute key, or the link's target.
+    pub name: String,
+    /// The local value, or the local link types joined by `, `.
+    pub mine: String,
+    /// The incoming value or link type.
+    pub theirs: String,
+}
+
+/// How one conflict is settled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keep the index as it is.
+    Mine,
+    /// Replace the local value (or link types) with the incoming one.
+    Theirs,
+    /// Keep both: an incoming value goes to attribute `<key>.theirs`, an
+    /// incoming link type is added next to the local ones.
+    Both,
+    /// Replace the local value (or link types) with this one.
+    Edit(String),
+}
+
+impl Resolution {
+    fn choice(&self) -> &'static str {
+        match self {
+            Resolution::Mine => "mine",
+            Resolution::Theirs => "theirs",
+            Resolution::Both => "both",
+            Resolution::Edit(_) => "edit",
+        }
+    }
+}
+
+/// One answer for every conflict (`--strategy`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Mine,
+    Theirs,
+    Both,
+}
+
+impl From<Strategy> for Resolution {
+    fn from(s: Strategy) -> Self {
+        match s {
+            Strategy::Mine => Resolution::Mine,
+            Strategy::Theirs => Resolution::Theirs,
+            Strategy::Both => Resolution::Both,
+        }
+    }
+}
+
+/// Asks how to settle a conflict, e.g. on a terminal.
+pub type Ask<'a> = Box<dyn FnMut(&Conflict) -> Result<Resolution> + 'a>;
+
+/// How an import settles conflicts.
+#[derive(Default)]
+pub enum OnConflict<'a> {
+    /// The import's own rule; nothing is recorded.
+    #[default]
+    Default,
+    Strategy(Strategy),
+    /// An error from the resolver aborts the import.
+    Ask(Ask<'a>),
+}
+
+impl OnConflict<'_> {
+    fn is_default(&self) -> bool {
+        matches!(self, OnConflict::Default)
+    }
+
+    /// Settle `conflict` and record the decision, as part of the import
+    /// called `source`.
+    fn settle(
+        &mut self,
+        conn: &Connection,
+        source: &str,
+        conflict: &Conflict,
+    ) -> Result<Resolution> {
+        let res = match self {
+            OnConflict::Default => bail!("no conflict resolution configured"),
+            OnConflict::Strategy(s) => Resolution::from(*s),
+            OnConflict::Ask(ask) => ask(conflict)?,
+        };
+        debug!(path = %conflict.path, name = %conflict.name, choice = res.choice(), "conflict settled");
+        let value = match &res {
+            Resolution::Edit(v) => Some(v.as_str()),
+            _ => None,
+        };
+        conn.execute(
+            "INSERT INTO import_conflicts(at, source, kind, path, name, mine, theirs, choice, value)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                chrono::Utc::now().timestamp(),
+                source,
+                conflict.kind.as_str(),
+                conflict.path,
+                conflict.name,
+                conflict.mine,
+                conflict.theirs,
+                res.choice(),
+                value
+            ],
+        )?;
+        Ok(res)
+    }
+
+    /// Set attribute `key` of `file_id` (stored at `path`) to `theirs`,
+    /// unless that conflicts with its value: then settle it and return
+    /// how.  `None` leaves the write to the caller.
+    pub(crate) fn settle_attr(
+        &mut self,
+        conn: &Connection,
+        source: &str,
+        file_id: i64,
+        path: &str,
+        key: &str,
+        theirs: &str,
+    ) -> Result<Option<Resolution>> {
+        if self.is_default() {
+            return Ok(None);
+        }
+        let Some(mine) = db::attr_value(conn, file_id, key)?.filter(|v| v != theirs) else {
+            return Ok(None);
+        };
+        let conflict = Conflict {
+            kind: ConflictKind::Attr,
+            path: path.to_string(),
+            name: key.to_string(),
+            mine,
+            theirs: theirs.to_string(),
+        };
+        let res = self.settle(conn, source, &conflict)?;
+        match &res {
+            Resolution::Mine => {}
+            Resolution::Theirs => db::upsert_attr(conn, file_id, key, theirs)?,
+            Resolution::Both => db::upsert_attr(conn, file_id, &format!("{key}.theirs"), theirs)?,
+            Resolution::Edit(v) => db::upsert_attr(conn, file_id, key, v)?,
+        }
+        Ok(Some(res))
+    }
+
+    /// Link `src` to `dst` (stored at `path` and `target`) as `link_type`,
+    /// unless the two are linked with other types only: then settle it and
+    /// return how.  `None` leaves the link to the caller.
+    pub(crate) fn settle_link(
+        &mut self,
+        conn: &Connection,
+        source: &str,
+        (src, dst): (i64, i64),
+        path: &str,
+        target: &str,
+        link_type: Option<&str>,
+    ) -> Result<Option<Resolution>> {
+        if self.is_default() {
+            return Ok(None);
+        }
+        let mine = link_types(conn, src, dst)?;
+        if mine.is_empty() || mine.iter().any(|t| t.as_deref() == link_type) {
+            return Ok(None);
+        }
+        let conflict = Conflict {
+            kind: ConflictKind::Link,
+            path: path.to_string(),
+            name: target.to_string(),
+            mine: mine
+                .iter()
+                .map(|t| t.as_deref().unwrap_or(""))
+                .collect::<Vec<_>>()
+                .join(", "),
+            theirs: link_type.unwrap_or("").to_string(),
+        };
+        let res = self.settle(conn, source, &conflict)?;
+        let replace_with = match &res {
+            Resolution::Mine => return Ok(Some(res)),
+            Resolution::Both => {
+                db::add_link(conn, src, dst, link_type)?;
+                return Ok(Some(res));
+            }
+            Resolution::Theirs => link_type,
+            Resolution::Edit(t) => Some(t.as_str()).filter(|t| !t.is_empty()),
+        };
+        for t in &mine {
+            db::remove_link(conn, src, dst, t.as_deref())?;
+        }
+        db::add_link(conn, src, dst, replace_with)?;
+        Ok(Some(res))
+    }
+}
+
+fn link_types(conn: &Connection, src: i64, dst: i64) -> Result<Vec<Option<String>>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT type FROM links WHERE src_file_id = ?1 AND dst_file_id = ?2 ORDER BY type",
+    )?;
+    let types = stmt
+        .query_map([src, dst], |r| r.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(types)
+}
+
+/// A recorded decision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    /// Unix seconds.
+    pub at: i64,
+    /// The import that met it (`sync import`, `link import`).
+    pub source: String,
+    pub conflict: Conflict,
+    pub resolution: Resolution,
+}
+
+/// The last `limit` decisions, newest first.
+pub fn decisions(conn: &Connection, limit: usize) -> Result<Vec<Decision>> {
+    let mut stmt = conn.prepare(
+        "SELECT at, source, kind, path, name, mine, theirs, choice, value
+           FROM import_conflicts ORDER BY id DESC LIMIT ?1",
+    )?;
+    let mut rows = stmt.query([limit as i64])?;
+    let mut out = Vec::new();
+    while let Some(r) = rows.next()? {
+        let kind = match r.get_ref(2)?.as_str()? {
+            "link" => ConflictKind::Link,
+            _ => ConflictKind::Attr,
+        };
+        let resolution = match r.get_ref(7)?.as_str()? {
+            "mine" => Resolution::Mine,
+            "theirs" => Resolution::Theirs,
+            "both" => Resolution::Both,
+            _ => Resolution::Edit(r.get::<_, Option<String>>(8)?.unwrap_or_default()),
+        };
+        out.push(Decision {
+            at: r.get(0)?,
+            source: r.get(1)?,
+            conflict: Conflict {
+                kind,
+                path: r.get(3)?,
+                name: r.get(4)?,
+                mine: r.get(5)?,
+                theirs: r.get(6)?,
+            },
+            resolution,
+        });
+    }
+    Ok(out)
+}
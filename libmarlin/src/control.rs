@@ -0,0 +1,501 @@
+//! Control channel between a running watcher and `marlin watch status/stop`.
+//!
+//! The watcher listens on a unix domain socket next to its database
+//! (`index.db` → `index.db.sock`). The socket is created `0600`, so only
+//! the owner of the index can talk to it – no ports, no collisions between
+//! indexes, nothing reachable by other local users. A loopback TCP
+//! endpoint is available behind an explicit flag for platforms (or
+//! sandboxes) without unix sockets; on its own it carries no
+//! authentication, so pair it with scoped tokens ([`crate::tokens`]).
+//!
+//! The protocol is one request line per connection (`status`, `stop`,
+//! `search <query>` …) answered with plain text, after which the server
+//! closes the stream.
+//! A line may start with `auth <secret> ` to present a token; the server
+//! hands it to the handler as [`ControlRequest::token`].
+//! `events follow` is the exception: the server keeps writing one line
+//! per file event until either side hangs up (see [`follow`]).
+//!
+//! Every running watcher also drops a small file into a per-user
+//! [`Registry`] directory so `marlin watch list` can find all of them and
+//! `status`/`stop` can address one by workspace name.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+/// How long either side waits on a silent peer.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a watcher's control channel lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Unix domain socket at this path.
+    Unix(PathBuf),
+    /// Loopback TCP fallback.
+    Tcp(SocketAddr),
+}
+
+impl Endpoint {
+    /// The default endpoint for the index at `db_path`.
+    pub fn for_db(db_path: &Path) -> Self {
+        Endpoint::Unix(socket_path(db_path))
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Unix(p) => write!(f, "{}", p.display()),
+            Endpoint::Tcp(a) => write!(f, "tcp://{a}"),
+        }
+    }
+}
+
+/// Control socket path for the index at `db_path`.
+pub fn socket_path(db_path: &Path) -> PathBuf {
+    let mut p = db_path.as_os_str().to_owned();
+    p.push(".sock");
+    PathBuf::from(p)
+}
+
+/* ─── server ──────────────────────────────────────────────────────── */
+
+enum Listener {
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener, PathBuf),
+    Tcp(TcpListener),
+}
+
+/// Non-blocking listener polled from the watcher's run loop.
+pub struct ControlServer {
+    listener: Listener,
+}
+
+impl ControlServer {
+    /// Start listening on `endpoint`.
+    ///
+    /// A leftover unix socket from a crashed watcher is replaced; a live
+    /// one means another watcher already owns this index and is an error.
+    pub fn bind(endpoint: &Endpoint) -> Result<Self> {
+        let listener = match endpoint {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                use std::os::unix::fs::PermissionsExt;
+                use std::os::unix::net::{UnixListener, UnixStream};
+
+                if path.exists() {
+                    if UnixStream::connect(path).is_ok() {
+                        return Err(anyhow!(
+                            "a watcher is already running for this index ({})",
+                            path.display()
+                        ));
+                    }
+                    std::fs::remove_file(path)
+                        .with_context(|| format!("removing stale socket {}", path.display()))?;
+                }
+                let l = UnixListener::bind(path)
+                    .with_context(|| format!("binding control socket {}", path.display()))?;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+                l.set_nonblocking(true)?;
+                Listener::Unix(l, path.clone())
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(path) => {
+                return Err(anyhow!(
+                    "unix sockets are not available on this platform ({}); \
+                     use the TCP control endpoint instead",
+                    path.display()
+                ));
+            }
+            Endpoint::Tcp(addr) => {
+                if !addr.ip().is_loopback() {
+                    return Err(anyhow!("refusing non-loopback control address {addr}"));
+                }
+                let l = TcpListener::bind(addr)
+                    .with_context(|| format!("binding control port {addr}"))?;
+                l.set_nonblocking(true)?;
+                Listener::Tcp(l)
+            }
+        };
+        Ok(Self { listener })
+    }
+
+    /// The endpoint actually bound (resolves TCP port `0`).
+    pub fn endpoint(&self) -> Result<Endpoint> {
+        Ok(match &self.listener {
+            #[cfg(unix)]
+            Listener::Unix(_, p) => Endpoint::Unix(p.clone()),
+            Listener::Tcp(l) => Endpoint::Tcp(l.local_addr()?),
+        })
+    }
+
+    /// Accept one pending request, if any. Never blocks on accept.
+    ///
+    /// Connections that close without sending a command (such as the
+    /// liveness probe in [`bind`](Self::bind)) are skipped.
+    pub fn poll(&self) -> Result<Option<ControlRequest>> {
+        while let Some(req) = self.accept()? {
+            if !req.command.is_empty() || req.token.is_some() {
+                return Ok(Some(req));
+            }
+        }
+        Ok(None)
+    }
+
+    fn accept(&self) -> Result<Option<ControlRequest>> {
+        let stream: Box<dyn Stream> = match &self.listener {
+            #[cfg(unix)]
+            Listener::Unix(l, _) => match l.accept() {
+                Ok((s, _)) => {
+                    s.set_nonblocking(false)?;
+                    s.set_read_timeout(Some(IO_TIMEOUT))?;
+                    s.set_write_timeout(Some(IO_TIMEOUT))?;
+                    Box::new(s)
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e.into()),
+            },
+            Listener::Tcp(l) => match l.accept() {
+                Ok((s, _)) => {
+                    s.set_nonblocking(false)?;
+                    s.set_read_timeout(Some(IO_TIMEOUT))?;
+                    s.set_write_timeout(Some(IO_TIMEOUT))?;
+                    Box::new(s)
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e.into()),
+            },
+        };
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim();
+        let (token, command) = match line.strip_prefix("auth ") {
+            Some(rest) => {
+                let (secret, command) = rest.trim_start().split_once(' ').unwrap_or((rest, ""));
+                (Some(secret.to_string()), command.trim())
+            }
+            None => (None, line),
+        };
+        Ok(Some(ControlRequest {
+            command: command.to_string(),
+            token,
+            stream: reader.into_inner(),
+        }))
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = &self.listener {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+trait Stream: io::Read + io::Write + Send {}
+impl<T: io::Read + io::Write + Send> Stream for T {}
+
+/// One command received on the control channel.
+pub struct ControlRequest {
+    pub command: String,
+    /// Secret sent with `auth <secret> <command>`, not yet checked.
+    pub token: Option<String>,
+    stream: Box<dyn Stream>,
+}
+
+impl ControlRequest {
+    /// Send the reply and close the connection.  An empty `body` closes
+    /// it without a word (e.g. a search without hits).
+    pub fn respond(mut self, body: &str) -> Result<()> {
+        self.stream.write_all(body.as_bytes())?;
+        if !body.is_empty() && !body.ends_with('\n') {
+            self.stream.write_all(b"\n")?;
+        }
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+impl ControlRequest {
+    /// Write each of `lines` as it arrives, until the iterator ends or the
+    /// client goes away.  Blocks, so servers run it on its own thread.
+    pub fn stream(mut self, lines: impl IntoIterator<Item = String>) -> Result<()> {
+        for line in lines {
+            self.stream.write_all(line.as_bytes())?;
+            self.stream.write_all(b"\n")?;
+            self.stream.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/* ─── client ──────────────────────────────────────────────────────── */
+
+/// Send `command` to the watcher at `endpoint` and return its reply.
+///
+/// Fails with [`io::ErrorKind::NotFound`] / `ConnectionRefused` in the
+/// chain when nothing is listening.
+pub fn request(endpoint: &Endpoint, command: &str) -> Result<String> {
+    let mut stream = connect(endpoint)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    writeln!(stream, "{command}")?;
+    stream.flush()?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
+
+/// `command` with `token` presented in front of it, if there is one.
+pub fn with_token(command: &str, token: Option<&str>) -> String {
+    match token {
+        Some(t) => format!("auth {t} {command}"),
+        None => command.to_string(),
+    }
+}
+
+/// A client connection with the read timeout left to the caller.
+fn connect(endpoint: &Endpoint) -> Result<Box<dyn ClientStream>> {
+    Ok(match endpoint {
+        #[cfg(unix)]
+        Endpoint::Unix(path) => Box::new(
+            std::os::unix::net::UnixStream::connect(path)
+                .with_context(|| format!("connecting to {}", path.display()))?,
+        ),
+        #[cfg(not(unix))]
+        Endpoint::Unix(path) => {
+            return Err(anyhow!(
+                "unix sockets are not available on this platform ({})",
+                path.display()
+            ));
+        }
+        Endpoint::Tcp(addr) => Box::new(
+            TcpStream::connect_timeout(addr, IO_TIMEOUT)
+                .with_context(|| format!("connecting to {addr}"))?,
+        ),
+    })
+}
+
+trait ClientStream: Stream {
+    fn set_read_timeout(&self, t: Option<Duration>) -> io::Result<()>;
+}
+
+#[cfg(unix)]
+impl ClientStream for std::os::unix::net::UnixStream {
+    fn set_read_timeout(&self, t: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, t)
+    }
+}
+
+impl ClientStream for TcpStream {
+    fn set_read_timeout(&self, t: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, t)
+    }
+}
+
+/// Send `command` and hand every reply line to `on_line` as it arrives,
+/// without a read timeout, until the watcher closes the stream.
+pub fn follow(
+    endpoint: &Endpoint,
+    command: &str,
+    mut on_line: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut stream = connect(endpoint)?;
+    writeln!(stream, "{command}")?;
+    stream.flush()?;
+    for line in BufReader::new(stream).lines() {
+        on_line(&line?)?;
+    }
+    Ok(())
+}
+
+/// `true` when `err` means nobody is listening at the endpoint.
+pub fn is_not_running(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| {
+        e.downcast_ref::<io::Error>().is_some_and(|io| {
+            matches!(
+                io.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            )
+        })
+    })
+}
+
+/* ─── registry of running watchers ────────────────────────────────── */
+
+/// One running watcher as recorded in the [`Registry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatcherEntry {
+    /// Workspace name used to address the watcher.
+    pub name: String,
+    pub pid: u32,
+    pub db_path: PathBuf,
+    pub watch_path: PathBuf,
+    pub endpoint: Endpoint,
+}
+
+impl WatcherEntry {
+    fn render(&self) -> String {
+        let endpoint = match &self.endpoint {
+            Endpoint::Unix(p) => format!("unix:{}", p.display()),
+            Endpoint::Tcp(a) => format!("tcp:{a}"),
+        };
+        format!(
+            "name={}\npid={}\ndb={}\npath={}\nendpoint={}\n",
+            self.name,
+            self.pid,
+            self.db_path.display(),
+            self.watch_path.display(),
+            endpoint
+        )
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let get = |key: &str| {
+            text.lines()
+                .find_map(|l| l.strip_prefix(key)?.strip_prefix('='))
+                .map(str::to_string)
+        };
+        let endpoint = get("endpoint")?;
+        let endpoint = if let Some(p) = endpoint.strip_prefix("unix:") {
+            Endpoint::Unix(PathBuf::from(p))
+        } else {
+            Endpoint::Tcp(endpoint.strip_prefix("tcp:")?.parse().ok()?)
+        };
+        Some(Self {
+            name: get("name")?,
+            pid: get("pid")?.parse().ok()?,
+            db_path: get("db")?.into(),
+            watch_path: get("path")?.into(),
+            endpoint,
+        })
+    }
+
+    /// Ask the watcher for its status; `None` if it no longer answers.
+    pub fn status(&self) -> Option<String> {
+        request(&self.endpoint, "status").ok()
+    }
+}
+
+/// Directory of `<name>.watcher` files, one per running watcher.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    dir: PathBuf,
+}
+
+impl Registry {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The per-user registry: `MARLIN_WATCH_REGISTRY`, else
+    /// `$XDG_RUNTIME_DIR/marlin/watchers`, else the state dir.
+    pub fn default_location() -> Self {
+        if let Some(dir) = std::env::var_os("MARLIN_WATCH_REGISTRY") {
+            return Self::new(dir);
+        }
+        let dir = match directories::ProjectDirs::from("io", "Marlin", "marlin") {
+            Some(d) => d
+                .runtime_dir()
+                .or(d.state_dir())
+                .unwrap_or(d.data_dir())
+                .join("watchers"),
+            None => PathBuf::from("watchers"),
+        };
+        Self::new(dir)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn file_for(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.watcher"))
+    }
+
+    /// Record `entry`; the returned guard removes it again when dropped.
+    ///
+    /// Fails if a *live* watcher already uses the same name; entries left
+    /// behind by crashed watchers are replaced.
+    pub fn register(&self, entry: WatcherEntry) -> Result<Registration> {
+        if entry.name.is_empty()
+            || entry
+                .name
+                .contains(|c: char| c == '/' || c == '\\' || c.is_control())
+        {
+            return Err(anyhow!("invalid watcher name `{}`", entry.name));
+        }
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("creating {}", self.dir.display()))?;
+        if let Some(existing) = self.get(&entry.name)? {
+            if existing.status().is_some() {
+                return Err(anyhow!(
+                    "a watcher named `{}` is already running (pid {}); pick another with --name",
+                    entry.name,
+                    existing.pid
+                ));
+            }
+        }
+        let file = self.file_for(&entry.name);
+        fs::write(&file, entry.render()).with_context(|| format!("writing {}", file.display()))?;
+        Ok(Registration { file })
+    }
+
+    /// The entry registered under `name`, if any (live or stale).
+    pub fn get(&self, name: &str) -> Result<Option<WatcherEntry>> {
+        match fs::read_to_string(self.file_for(name)) {
+            Ok(text) => Ok(WatcherEntry::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// All entries, sorted by name. With `prune`, entries whose watcher no
+    /// longer answers are deleted and left out.
+    pub fn list(&self, prune: bool) -> Result<Vec<WatcherEntry>> {
+        let mut out = Vec::new();
+        let rd = match fs::read_dir(&self.dir) {
+            Ok(rd) => rd,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(out),
+            Err(e) => return Err(e.into()),
+        };
+        for ent in rd {
+            let path = ent?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("watcher") {
+                continue;
+            }
+            let Some(entry) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|t| WatcherEntry::parse(&t))
+            else {
+                continue;
+            };
+            if prune && entry.status().is_none() {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            out.push(entry);
+        }
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(out)
+    }
+}
+
+/// Keeps a watcher listed in the [`Registry`] while alive.
+#[derive(Debug)]
+pub struct Registration {
+    file: PathBuf,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.file);
+    }
+}
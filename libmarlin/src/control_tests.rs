@@ -0,0 +1,171 @@
+// libmarlin/src/control_tests.rs
+
+use super::control::{self, ControlServer, Endpoint};
+use std::thread;
+use std::time::Duration;
+
+/// Serve requests on `server` until `n` have been answered.
+fn serve(server: &ControlServer, n: usize) {
+    let mut answered = 0;
+    while answered < n {
+        match server.poll().unwrap() {
+            Some(req) => {
+                let reply = format!("echo {}", req.command);
+                req.respond(&reply).unwrap();
+                answered += 1;
+            }
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_socket_roundtrip_and_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let ep = Endpoint::for_db(&tmp.path().join("index.db"));
+    let Endpoint::Unix(sock) = &ep else {
+        unreachable!()
+    };
+    assert!(sock.ends_with("index.db.sock"));
+
+    let server = ControlServer::bind(&ep).unwrap();
+    let mode = std::fs::metadata(sock).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // a second watcher on the same index must not steal the socket
+    assert!(ControlServer::bind(&ep).is_err());
+
+    let client_ep = ep.clone();
+    let client = thread::spawn(move || control::request(&client_ep, "status").unwrap());
+    serve(&server, 1);
+    assert_eq!(client.join().unwrap(), "echo status\n");
+
+    drop(server);
+    assert!(!sock.exists(), "socket removed on shutdown");
+    let err = control::request(&ep, "status").unwrap_err();
+    assert!(control::is_not_running(&err));
+}
+
+#[cfg(unix)]
+#[test]
+fn stale_unix_socket_is_replaced() {
+    let tmp = tempfile::tempdir().unwrap();
+    let sock = tmp.path().join("index.db.sock");
+    // a socket file nobody listens on, as left behind by a crash
+    drop(std::os::unix::net::UnixListener::bind(&sock).unwrap());
+    assert!(sock.exists());
+
+    ControlServer::bind(&Endpoint::Unix(sock)).unwrap();
+}
+
+#[test]
+fn tcp_fallback_is_loopback_only() {
+    let ep = Endpoint::Tcp("127.0.0.1:0".parse().unwrap());
+    let server = ControlServer::bind(&ep).unwrap();
+    let bound = server.endpoint().unwrap();
+
+    let client = thread::spawn(move || control::request(&bound, "stop").unwrap());
+    serve(&server, 1);
+    assert_eq!(client.join().unwrap(), "echo stop\n");
+
+    assert!(ControlServer::bind(&Endpoint::Tcp("0.0.0.0:0".parse().unwrap())).is_err());
+}
+
+#[test]
+fn auth_prefix_is_split_off_the_command() {
+    let ep = Endpoint::Tcp("127.0.0.1:0".parse().unwrap());
+    let server = ControlServer::bind(&ep).unwrap();
+    let bound = server.endpoint().unwrap();
+
+    let line = control::with_token("tag a/b *.md", Some("mrl_secret"));
+    assert_eq!(line, "auth mrl_secret tag a/b *.md");
+    let client = thread::spawn(move || control::request(&bound, &line).unwrap());
+    let req = loop {
+        match server.poll().unwrap() {
+            Some(req) => break req,
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    assert_eq!(req.token.as_deref(), Some("mrl_secret"));
+    assert_eq!(req.command, "tag a/b *.md");
+    req.respond("ok").unwrap();
+    assert_eq!(client.join().unwrap(), "ok\n");
+}
+
+#[test]
+fn registry_lists_live_watchers_and_prunes_stale_ones() {
+    use control::{Registry, WatcherEntry};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let reg = Registry::new(tmp.path().join("watchers"));
+    assert!(reg.list(true).unwrap().is_empty());
+
+    let server = ControlServer::bind(&Endpoint::Tcp("127.0.0.1:0".parse().unwrap())).unwrap();
+    let live = WatcherEntry {
+        name: "notes".into(),
+        pid: std::process::id(),
+        db_path: tmp.path().join("notes.db"),
+        watch_path: tmp.path().join("notes"),
+        endpoint: server.endpoint().unwrap(),
+    };
+    let live_reg = reg.register(live.clone()).unwrap();
+    // an entry left behind by a crashed watcher
+    std::fs::write(
+        reg.dir().join("photos.watcher"),
+        format!(
+            "name=photos\npid=1\ndb=/x.db\npath=/x\nendpoint=unix:{}\n",
+            tmp.path().join("gone.sock").display()
+        ),
+    )
+    .unwrap();
+
+    let handle = thread::spawn(move || {
+        serve(&server, 2);
+        server
+    });
+    assert_eq!(reg.get("notes").unwrap(), Some(live.clone()));
+    // the live name is taken …
+    assert!(reg.register(live.clone()).is_err());
+    // … and listing drops the stale entry
+    let names: Vec<_> = reg
+        .list(true)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(names, ["notes"]);
+    assert!(!reg.dir().join("photos.watcher").exists());
+    drop(handle.join().unwrap());
+
+    drop(live_reg);
+    assert!(reg.get("notes").unwrap().is_none());
+}
+
+#[test]
+fn follow_streams_lines_until_server_hangs_up() {
+    let server = ControlServer::bind(&Endpoint::Tcp("127.0.0.1:0".parse().unwrap())).unwrap();
+    let ep = server.endpoint().unwrap();
+
+    let client = thread::spawn(move || {
+        let mut lines = Vec::new();
+        control::follow(&ep, "events follow 0", |l| {
+            lines.push(l.to_string());
+            Ok(())
+        })
+        .unwrap();
+        lines
+    });
+    let req = loop {
+        match server.poll().unwrap() {
+            Some(req) => break req,
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    assert_eq!(req.command, "events follow 0");
+    req.stream((1..=3).map(|i| format!("line {i}"))).unwrap();
+
+    assert_eq!(client.join().unwrap(), ["line 1", "line 2", "line 3"]);
+}
@@ -0,0 +1,119 @@
+//! Shared tag databases attached read-only next to the main index.
+//!
+//! A team can keep one Marlin database holding nothing but tags and
+//! attach it to everybody's personal index with [`attach_shared`]: `tag:`
+//! filters then also match files the shared database tags, by path or by
+//! content hash where both sides hashed the file.  Nothing is copied; the
+//! attachment lasts until [`detach_shared`] or the connection closes.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use rusqlite::Connection;
+use tracing::debug;
+
+/// Tables a database needs to contribute tags.
+const TAG_TABLES: [&str; 3] = ["files", "tags", "file_tags"];
+
+/// Attach `db_path` read-only and return its schema name, derived from
+/// the file stem (`team-tags.db` → `team_tags`, numbered on clashes).
+/// Fails unless the file is a Marlin index.
+pub fn attach_shared(conn: &Connection, db_path: &Path) -> Result<String> {
+    if !db_path.is_file() {
+        bail!("shared database {} does not exist", db_path.display());
+    }
+    let stem: String = db_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let stem = match stem.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => stem,
+        _ => format!("shared_{stem}"),
+    };
+    let taken = schemas(conn)?;
+    let mut name = stem.clone();
+    let mut n = 2;
+    while taken.iter().any(|t| t.eq_ignore_ascii_case(&name))
+        || ["main", "temp"].contains(&name.to_ascii_lowercase().as_str())
+    {
+        name = format!("{stem}{n}");
+        n += 1;
+    }
+
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS \"{name}\""),
+        [read_only_uri(db_path)],
+    )
+    .with_context(|| format!("attaching {}", db_path.display()))?;
+
+    if !has_tag_tables(conn, &name)? {
+        detach_shared(conn, &name)?;
+        bail!(
+            "{} is not a Marlin index (needs tables {})",
+            db_path.display(),
+            TAG_TABLES.join(", ")
+        );
+    }
+    debug!(schema = %name, path = %db_path.display(), "shared database attached");
+    Ok(name)
+}
+
+/// Detach a database attached with [`attach_shared`].
+pub fn detach_shared(conn: &Connection, schema: &str) -> Result<()> {
+    conn.execute("DETACH DATABASE ?1", [schema])?;
+    Ok(())
+}
+
+/// Schema names of every attached database that can contribute tags, in
+/// attach order.
+pub fn shared_schemas(conn: &Connection) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    for name in schemas(conn)? {
+        if has_tag_tables(conn, &name)? {
+            out.push(name);
+        }
+    }
+    Ok(out)
+}
+
+fn has_tag_tables(conn: &Connection, schema: &str) -> Result<bool> {
+    let found: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM \"{schema}\".sqlite_master \
+             WHERE type = 'table' AND name IN ('files', 'tags', 'file_tags')"
+        ),
+        [],
+        |r| r.get(0),
+    )?;
+    Ok(found == TAG_TABLES.len() as i64)
+}
+
+/// Attached schemas other than `main` and `temp`.
+fn schemas(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA database_list")?;
+    let rows = stmt.query_map([], |r| r.get::<_, String>(1))?;
+    Ok(rows
+        .collect::<std::result::Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|n| n != "main" && n != "temp")
+        .collect())
+}
+
+/// `file:` URI opening `path` read-only; `?`, `#` and `%` are escaped.
+fn read_only_uri(path: &Path) -> String {
+    let mut uri = String::from("file:");
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '?' => uri.push_str("%3f"),
+            '#' => uri.push_str("%23"),
+            '%' => uri.push_str("%25"),
+            '\\' => uri.push('/'),
+            c => uri.push(c),
+        }
+    }
+    uri.push_str("?mode=ro");
+    uri
+}
@@ -4,20 +4,35 @@
 //! and provides methods for common database operations.
 
 use anyhow::Result;
-use rusqlite::Connection;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tracing::debug;
+
+use crate::lock;
+use crate::prune::{self, Cascade};
+use crate::utils::{normalize_path, PathNorm};
+use crate::vfs::StdFs;
 
 /// Options for indexing files
 #[derive(Debug, Clone)]
 pub struct IndexOptions {
-    /// Only update files marked as dirty
+    /// Only update files marked as dirty (see `db::mark_dirty`)
     pub dirty_only: bool,
 
-    /// Index file contents (not just metadata)
+    /// Read file bodies where the root profile asks for it (hashing, and
+    /// text for full-text search); `false` stores metadata only
     pub index_contents: bool,
 
-    /// Maximum file size to index (in bytes)
+    /// Never read bodies of larger files (in bytes); they still get
+    /// their metadata indexed
     pub max_size: Option<u64>,
+
+    /// Also recompute hashes the index already holds outside `hash` roots
+    /// (`marlin reindex`)
+    pub force: bool,
 }
 
 impl Default for IndexOptions {
@@ -26,52 +41,101 @@ impl Default for IndexOptions {
             dirty_only: false,
             index_contents: true,
             max_size: Some(1_000_000), // 1MB default limit
+            force: false,
         }
     }
 }
 
 /// Database wrapper for Marlin
-pub struct Database {
-    /// The SQLite connection
-    conn: Connection,
+pub struct Database<S = Connection> {
+    /// The SQLite connection, or another [`crate::store::Store`]
+    conn: S,
 }
 
-impl Database {
+impl<S> Database<S> {
     /// Create a new database wrapper around an existing connection
-    pub fn new(conn: Connection) -> Self {
+    pub fn new(conn: S) -> Self {
         Self { conn }
     }
 
     /// Get a reference to the underlying connection
-    pub fn conn(&self) -> &Connection {
+    pub fn conn(&self) -> &S {
         &self.conn
     }
 
     /// Get a mutable reference to the underlying connection
-    pub fn conn_mut(&mut self) -> &mut Connection {
+    pub fn conn_mut(&mut self) -> &mut S {
         &mut self.conn
     }
 
-    /// Index one or more files
-    pub fn index_files(&mut self, paths: &[PathBuf], _options: &IndexOptions) -> Result<usize> {
-        // In a real implementation, this would index the files
-        // For now, we just return the number of files "indexed"
-        if paths.is_empty() {
-            // Add a branch for coverage
-            return Ok(0);
-        }
-        Ok(paths.len())
+    /// Lock a shared database and borrow its connection.  A poisoned
+    /// mutex is recovered: SQLite keeps the connection consistent even if
+    /// a holder panicked mid-statement.
+    pub fn lock(shared: &Mutex<Database<S>>) -> ConnGuard<'_, S> {
+        ConnGuard(shared.lock().unwrap_or_else(|e| e.into_inner()))
     }
+}
 
-    /// Remove files from the index
-    pub fn remove_files(&mut self, paths: &[PathBuf]) -> Result<usize> {
-        // In a real implementation, this would remove the files
-        // For now, we just return the number of files "removed"
-        if paths.is_empty() {
-            // Add a branch for coverage
-            return Ok(0);
+impl Database {
+    /// Index (or re-index) files the way a scan does – root profiles,
+    /// ignore globs and hashing included.  Directories are walked and
+    /// vanished paths skipped.  Returns the number of files written.
+    pub fn index_files(&mut self, paths: &[PathBuf], options: &IndexOptions) -> Result<usize> {
+        crate::scan::index_files_with(&mut self.conn, &StdFs, paths, options)
+    }
+
+    /// Drop files from the index – and, for directories, everything
+    /// below them – via [`crate::prune::drop_files`]: with
+    /// `keep_metadata` they become tombstones that keep their tags,
+    /// attributes and links, otherwise all of it goes with them.  Locked
+    /// files are left alone.  Returns what was dropped (or kept).
+    pub fn remove_files(&mut self, paths: &[PathBuf], keep_metadata: bool) -> Result<Cascade> {
+        let sep = std::path::MAIN_SEPARATOR;
+        let mut ids = Vec::new();
+        let mut seen = HashSet::new();
+        {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, path FROM files
+                  WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2",
+            )?;
+            for p in paths {
+                let path = normalize_path(p, &PathNorm::default());
+                let prefix = format!("{}{sep}", path.trim_end_matches(sep));
+                let rows = stmt.query_map(params![path, prefix], |r| {
+                    Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?))
+                })?;
+                for row in rows {
+                    let (id, path) = row?;
+                    if lock::is_locked(&self.conn, id)? && !lock::overridden(&self.conn)? {
+                        debug!(%path, "locked, left in the index");
+                    } else if seen.insert(id) {
+                        ids.push(id);
+                    }
+                }
+            }
         }
-        Ok(paths.len())
+        let report = prune::drop_files(&mut self.conn, &ids, keep_metadata)?;
+        debug!(removed = report.files, keep_metadata, "removed from index");
+        Ok(report)
+    }
+}
+
+/// Exclusive access to the connection of a shared [`Database`]; see
+/// [`Database::lock`].  Other users of the same database (e.g. a running
+/// watcher) wait while it is held, so keep it short-lived.
+pub struct ConnGuard<'a, S = Connection>(MutexGuard<'a, Database<S>>);
+
+impl<S> Deref for ConnGuard<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.0.conn()
+    }
+}
+
+impl<S> DerefMut for ConnGuard<'_, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.0.conn_mut()
     }
 }
 
@@ -79,7 +143,7 @@ impl Database {
 mod tests {
     use super::*;
     use crate::db::open as open_marlin_db; // Use your project's DB open function
-    use std::fs::File;
+    use std::fs;
     use tempfile::tempdir;
 
     fn setup_db() -> Database {
@@ -95,31 +159,138 @@ mod tests {
         // Just checking they don't panic and can be called.
     }
 
+    fn count(db: &Database, sql: &str) -> i64 {
+        db.conn().query_row(sql, [], |r| r.get(0)).unwrap()
+    }
+
     #[test]
-    fn test_index_files_stub() {
+    fn test_index_files_upserts_metadata() {
         let mut db = setup_db();
         let tmp = tempdir().unwrap();
         let file1 = tmp.path().join("file1.txt");
-        File::create(&file1).unwrap();
-
-        let paths = vec![file1.to_path_buf()];
+        fs::write(&file1, "hello").unwrap();
+        let sub = tmp.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        fs::write(sub.join("two.txt"), "2").unwrap();
         let options = IndexOptions::default();
 
-        assert_eq!(db.index_files(&paths, &options).unwrap(), 1);
-        assert_eq!(db.index_files(&[], &options).unwrap(), 0); // Test empty case
+        let paths = vec![file1.clone(), sub, tmp.path().join("gone.txt")];
+        assert_eq!(db.index_files(&paths, &options).unwrap(), 2);
+        assert_eq!(db.index_files(&[], &options).unwrap(), 0);
+        let size: i64 = db
+            .conn()
+            .query_row(
+                "SELECT size FROM files WHERE path = ?1",
+                [file1.to_string_lossy()],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(size, 5);
     }
 
     #[test]
-    fn test_remove_files_stub() {
+    fn test_index_files_honours_options() {
         let mut db = setup_db();
         let tmp = tempdir().unwrap();
+        let root = crate::roots::RootProfile {
+            hash: true,
+            ..crate::roots::RootProfile::default_for(tmp.path().canonicalize().unwrap())
+        };
+        crate::roots::save(db.conn(), &root).unwrap();
+        let small = tmp.path().join("small.txt");
+        let big = tmp.path().join("big.txt");
+        fs::write(&small, "x").unwrap();
+        fs::write(&big, "x".repeat(64)).unwrap();
+        let paths = vec![small.clone(), big.clone()];
+
+        let no_bodies = IndexOptions {
+            index_contents: false,
+            ..Default::default()
+        };
+        assert_eq!(db.index_files(&paths, &no_bodies).unwrap(), 2);
+        assert_eq!(count(&db, "SELECT COUNT(hash) FROM files"), 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM file_text"), 0);
+
+        let capped = IndexOptions {
+            max_size: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(db.index_files(&paths, &capped).unwrap(), 2);
+        assert_eq!(count(&db, "SELECT COUNT(hash) FROM files"), 1);
+        assert_eq!(count(&db, "SELECT COUNT(body) FROM file_text"), 1);
+
+        let small_id = crate::db::file_id(db.conn(), &small.to_string_lossy()).unwrap();
+        crate::db::mark_dirty(db.conn(), small_id).unwrap();
+        let dirty = IndexOptions {
+            dirty_only: true,
+            ..Default::default()
+        };
+        assert_eq!(db.index_files(&paths, &dirty).unwrap(), 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM file_changes"), 0);
+        assert_eq!(db.index_files(&paths, &dirty).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_remove_files_deletes_rows_and_children() {
+        let mut db = setup_db();
+        let tmp = tempdir().unwrap();
+        let sub = tmp.path().join("sub");
+        fs::create_dir(&sub).unwrap();
         let file1 = tmp.path().join("file1.txt");
-        File::create(&file1).unwrap(); // File doesn't need to be in DB for this stub
+        fs::write(&file1, "").unwrap();
+        fs::write(sub.join("a.txt"), "").unwrap();
+        fs::write(sub.join("b.txt"), "").unwrap();
+        fs::write(tmp.path().join("sub2.txt"), "").unwrap();
+        let options = IndexOptions::default();
+        db.index_files(&[tmp.path().to_path_buf()], &options)
+            .unwrap();
+
+        let removed =
+            |db: &mut Database, paths: &[PathBuf]| db.remove_files(paths, false).unwrap().files;
+        assert_eq!(removed(&mut db, std::slice::from_ref(&file1)), 1);
+        assert_eq!(removed(&mut db, &[sub]), 2);
+        assert_eq!(removed(&mut db, &[file1]), 0);
+        assert_eq!(removed(&mut db, &[]), 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM files"), 1);
+        assert_eq!(
+            count(
+                &db,
+                "SELECT COUNT(*) FROM files_fts WHERE files_fts MATCH 'file1'"
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_remove_files_keeps_metadata_and_locked_files() {
+        let mut db = setup_db();
+        let tmp = tempdir().unwrap();
+        let gone = tmp.path().join("gone.txt");
+        let frozen = tmp.path().join("frozen.txt");
+        fs::write(&gone, "").unwrap();
+        fs::write(&frozen, "").unwrap();
+        db.index_files(&[tmp.path().to_path_buf()], &IndexOptions::default())
+            .unwrap();
+        let gone_id = crate::db::file_id(db.conn(), &gone.to_string_lossy()).unwrap();
+        let frozen_id = crate::db::file_id(db.conn(), &frozen.to_string_lossy()).unwrap();
+        let tag = crate::db::ensure_tag_path(db.conn(), "keep").unwrap();
+        crate::db::attach_tag(db.conn(), gone_id, tag, 0).unwrap();
+        lock::lock_files(db.conn(), &[(frozen_id, String::new())]).unwrap();
 
-        let paths = vec![file1.to_path_buf()];
+        let report = db.remove_files(&[tmp.path().to_path_buf()], true).unwrap();
+        assert_eq!((report.files, report.tags), (1, 1));
+        assert_eq!(
+            count(
+                &db,
+                "SELECT COUNT(*) FROM files WHERE missing_since IS NOT NULL"
+            ),
+            1
+        );
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM file_tags"), 1);
 
-        assert_eq!(db.remove_files(&paths).unwrap(), 1);
-        assert_eq!(db.remove_files(&[]).unwrap(), 0); // Test empty case
+        db.remove_files(&[tmp.path().to_path_buf()], false).unwrap();
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM files"), 1);
+        assert!(crate::db::file_id(db.conn(), &frozen.to_string_lossy()).is_ok());
     }
 
     #[test]
@@ -0,0 +1,189 @@
+//! Comparing the index with another database file – usually a backup –
+//! before deciding whether to restore it (`marlin db diff`).
+//!
+//! The other file is opened read-only and never migrated, so backups
+//! taken by older builds can be compared as they are.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::query;
+use crate::snapshot::{self, Change};
+
+/// How a schema object differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delta {
+    /// Only in the index.
+    Added,
+    /// Only in the other database.
+    Removed,
+    /// In both, with different SQL.
+    Changed,
+}
+
+impl Delta {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Delta::Added => "added",
+            Delta::Removed => "removed",
+            Delta::Changed => "changed",
+        }
+    }
+}
+
+/// A table, index, trigger or view that differs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChange {
+    /// `table`, `index`, `trigger` or `view`
+    pub kind: String,
+    pub name: String,
+    pub delta: Delta,
+}
+
+/// Row counts of one table; `None` where the table does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableCount {
+    pub table: String,
+    pub other: Option<i64>,
+    pub live: Option<i64>,
+}
+
+impl TableCount {
+    /// Rows gained by the index since the other database.
+    pub fn delta(&self) -> i64 {
+        self.live.unwrap_or(0) - self.other.unwrap_or(0)
+    }
+}
+
+/// Everything [`diff_against`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbDiff {
+    pub schema: Vec<SchemaChange>,
+    /// Every table of either database, by name.
+    pub tables: Vec<TableCount>,
+    /// Tag, attribute and link changes from the other database to the
+    /// index; only computed for a scope query.
+    pub metadata: Vec<Change>,
+}
+
+/// Compare the index `live` with the database at `other`: schema objects,
+/// row counts and, when `scope` is given, the tags, attributes and links
+/// of the files that query matches in either database.
+pub fn diff_against(live: &Connection, other: &Path, scope: Option<&str>) -> Result<DbDiff> {
+    let old = Connection::open_with_flags(other, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("opening {}", other.display()))?;
+
+    let old_schema = schema_objects(&old)
+        .with_context(|| format!("{} is not a SQLite database", other.display()))?;
+    let new_schema = schema_objects(live)?;
+    let mut schema = Vec::new();
+    for ((kind, name), sql) in &old_schema {
+        match new_schema.get(&(kind.clone(), name.clone())) {
+            None => schema.push(SchemaChange {
+                kind: kind.clone(),
+                name: name.clone(),
+                delta: Delta::Removed,
+            }),
+            Some(new_sql) if normalize(new_sql) != normalize(sql) => schema.push(SchemaChange {
+                kind: kind.clone(),
+                name: name.clone(),
+                delta: Delta::Changed,
+            }),
+            Some(_) => {}
+        }
+    }
+    for (kind, name) in new_schema.keys() {
+        if !old_schema.contains_key(&(kind.clone(), name.clone())) {
+            schema.push(SchemaChange {
+                kind: kind.clone(),
+                name: name.clone(),
+                delta: Delta::Added,
+            });
+        }
+    }
+
+    let old_tables = data_tables(&old_schema);
+    let new_tables = data_tables(&new_schema);
+    let mut tables = Vec::new();
+    for table in old_tables.union(&new_tables) {
+        let count = |conn: &Connection, present: bool| -> Result<Option<i64>> {
+            if !present {
+                return Ok(None);
+            }
+            Ok(Some(conn.query_row(
+                &format!("SELECT COUNT(*) FROM \"{table}\""),
+                [],
+                |r| r.get(0),
+            )?))
+        };
+        tables.push(TableCount {
+            table: table.clone(),
+            other: count(&old, old_tables.contains(table))?,
+            live: count(live, new_tables.contains(table))?,
+        });
+    }
+
+    let metadata = match scope {
+        Some(q) => {
+            let mut paths: BTreeSet<String> = query::search(live, q)?.into_iter().collect();
+            // the other side may predate tables the query needs
+            match query::search(&old, q) {
+                Ok(hits) => paths.extend(hits),
+                Err(e) => tracing::warn!("scope query failed on {}: {e:#}", other.display()),
+            }
+            snapshot::diff_databases(&old, live)?
+                .into_iter()
+                .filter(|c| paths.contains(&c.path))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    Ok(DbDiff {
+        schema,
+        tables,
+        metadata,
+    })
+}
+
+type Objects = BTreeMap<(String, String), String>;
+
+/// `(type, name) → sql` of every schema object SQLite did not create
+/// itself.
+fn schema_objects(conn: &Connection) -> Result<Objects> {
+    let mut stmt = conn.prepare(
+        "SELECT type, name, IFNULL(sql, '') FROM sqlite_master
+          WHERE name NOT LIKE 'sqlite_%'",
+    )?;
+    let rows = stmt.query_map([], |r| Ok(((r.get(0)?, r.get(1)?), r.get(2)?)))?;
+    Ok(rows.collect::<std::result::Result<_, _>>()?)
+}
+
+/// Tables worth counting: not the shadow tables behind FTS indexes.
+fn data_tables(objects: &Objects) -> BTreeSet<String> {
+    let virtual_tables: Vec<&str> = objects
+        .iter()
+        .filter(|(_, sql)| sql.starts_with("CREATE VIRTUAL TABLE"))
+        .map(|((_, name), _)| name.as_str())
+        .collect();
+    objects
+        .keys()
+        .filter(|(kind, _)| kind == "table")
+        .map(|(_, name)| name)
+        .filter(|name| {
+            !virtual_tables.iter().any(|v| {
+                name.strip_prefix(v)
+                    .is_some_and(|rest| rest.starts_with('_'))
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// SQL with whitespace runs collapsed, so reformatting is no change.
+fn normalize(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
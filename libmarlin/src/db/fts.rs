@@ -0,0 +1,174 @@
+//! Consistency checks for the trigger-maintained `files_fts` index.
+//!
+//! `files_fts` is contentless, so its rows cannot be read back.  Instead
+//! [`verify_fts_consistency`] compares rowids with `files` and, per file,
+//! asks FTS5 whether the row still matches the file's current path, full
+//! tag paths, attributes and annotation notes as phrases (file text is
+//! too long to compare this way and is not checked).  A row written
+//! before a rename, a tag rename or a trigger bug fails that match.
+//! [`repair_fts`] rewrites the listed rows from the `files_fts_source`
+//! view the triggers also read.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::{debug, info};
+
+use super::Mutation;
+
+/// Full paths of the tags on file `?1`.
+const TAG_PATHS: &str = "
+    WITH RECURSIVE tag_tree(id, parent_id, path) AS (
+      SELECT t.id, t.parent_id, t.name FROM tags t WHERE t.parent_id IS NULL
+      UNION ALL
+      SELECT t.id, t.parent_id, tt.path || '/' || t.name
+        FROM tags t JOIN tag_tree tt ON t.parent_id = tt.id
+    )
+    SELECT DISTINCT tag_tree.path AS tag_path
+      FROM file_tags ft JOIN tag_tree ON ft.tag_id = tag_tree.id
+     WHERE ft.file_id = ?1";
+
+/// Result of [`verify_fts_consistency`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FtsReport {
+    /// Files looked at (all of them, or the sample).
+    pub checked: usize,
+    /// Files without an FTS row.
+    pub missing: Vec<i64>,
+    /// FTS rows without a file.
+    pub orphaned: Vec<i64>,
+    /// Files whose FTS row no longer matches their path, tags, attributes
+    /// or notes.
+    pub stale: Vec<i64>,
+}
+
+impl FtsReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty() && self.stale.is_empty()
+    }
+
+    /// Every mismatched rowid, sorted – the input for [`repair_fts`].
+    pub fn rowids(&self) -> Vec<i64> {
+        let all: BTreeSet<i64> = self
+            .missing
+            .iter()
+            .chain(&self.orphaned)
+            .chain(&self.stale)
+            .copied()
+            .collect();
+        all.into_iter().collect()
+    }
+}
+
+/// Compare `files_fts` with the base tables.  Missing and orphaned rows
+/// are always found; the per-row content check covers every file, or a
+/// random `sample` of them (what `marlin doctor` does by default).
+pub fn verify_fts_consistency(conn: &Connection, sample: Option<usize>) -> Result<FtsReport> {
+    let ids = |sql: &str| -> Result<Vec<i64>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    };
+    let mut report = FtsReport {
+        missing: ids(
+            "SELECT id FROM files WHERE id NOT IN (SELECT rowid FROM files_fts) ORDER BY id",
+        )?,
+        orphaned: ids(
+            "SELECT rowid FROM files_fts WHERE rowid NOT IN (SELECT id FROM files) ORDER BY rowid",
+        )?,
+        ..Default::default()
+    };
+
+    let files: Vec<(i64, String)> = {
+        let (sql, limit) = match sample {
+            Some(n) => (
+                "SELECT id, path FROM files ORDER BY random() LIMIT ?1",
+                n as i64,
+            ),
+            None => ("SELECT id, path FROM files ORDER BY id LIMIT ?1", -1),
+        };
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([limit], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect::<std::result::Result<_, _>>()?
+    };
+    report.checked = files.len();
+
+    let mut tag_paths = conn.prepare(TAG_PATHS)?;
+    let mut attrs = conn.prepare(
+        "SELECT key || '=' || value FROM attributes WHERE file_id = ?1 AND value IS NOT NULL",
+    )?;
+    let mut notes = conn.prepare("SELECT note FROM annotations WHERE file_id = ?1")?;
+    let mut matches =
+        conn.prepare("SELECT 1 FROM files_fts WHERE files_fts MATCH ?1 AND rowid = ?2")?;
+
+    for (id, path) in files {
+        if report.missing.binary_search(&id).is_ok() {
+            continue;
+        }
+        let mut terms = vec![column_phrase("path", &path)];
+        for t in tag_paths.query_map([id], |r| r.get::<_, String>(0))? {
+            terms.push(column_phrase("tags_text", &t?));
+        }
+        for a in attrs.query_map([id], |r| r.get::<_, String>(0))? {
+            terms.push(column_phrase("attrs_text", &a?));
+        }
+        for n in notes.query_map([id], |r| r.get::<_, String>(0))? {
+            terms.push(column_phrase("notes_text", &n?));
+        }
+        let expr = terms
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        if expr.is_empty() {
+            continue; // nothing tokenisable to compare
+        }
+        if matches
+            .query_row(params![expr, id], |_| Ok(()))
+            .optional()?
+            .is_none()
+        {
+            debug!(file_id = id, path = %path, "stale FTS row");
+            report.stale.push(id);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Rewrite the FTS rows for `rowids` from the base tables; rows whose file
+/// is gone are deleted.  Returns the number
+/// of rows repaired.
+pub fn repair_fts(conn: &mut Connection, rowids: &[i64]) -> Result<usize> {
+    let m = Mutation::new("repair_fts");
+    let tx = conn.savepoint()?;
+    let mut repaired = 0;
+    {
+        let mut exists = tx.prepare("SELECT 1 FROM files WHERE id = ?1")?;
+        let mut delete = tx.prepare("DELETE FROM files_fts WHERE rowid = ?1")?;
+        let mut rewrite = tx.prepare(
+            "INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+             SELECT id, path, tags_text, attrs_text, notes_text, content
+               FROM files_fts_source WHERE id = ?1",
+        )?;
+        for &id in rowids {
+            repaired += if exists.exists([id])? {
+                rewrite.execute([id])?
+            } else {
+                delete.execute([id])?
+            };
+        }
+    }
+    tx.commit()?;
+    info!(repaired, "FTS rows rewritten");
+    Ok(m.done(repaired))
+}
+
+/// `column : "phrase"`, or `None` when `text` has nothing FTS5 would
+/// tokenise (an empty phrase never matches).
+fn column_phrase(column: &str, text: &str) -> Option<String> {
+    text.chars()
+        .any(char::is_alphanumeric)
+        .then(|| format!("{column} : \"{}\"", text.replace('"', "\"\"")))
+}
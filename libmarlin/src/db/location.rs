@@ -0,0 +1,112 @@
+//! Where the index lives: detecting network and FUSE filesystems.
+//!
+//! SQLite's WAL journal keeps its index in a shared-memory file and relies
+//! on POSIX locks; NFS, SMB and most FUSE mounts implement neither
+//! reliably, and two machines writing through them corrupt the database.
+//! [`network_fs`] names the filesystem type when the DB path sits on one
+//! of those, so [`open`](super::open) can warn and the CLI can refuse.
+
+use std::path::Path;
+
+/// Filesystem type of `db_path` when it is a network or FUSE mount,
+/// e.g. `"nfs"`, `"cifs"` or `"fuse"`; `None` for local disks and for
+/// platforms we cannot ask.  A DB file that does not exist yet is judged
+/// by its nearest existing parent directory.
+pub fn network_fs(db_path: &Path) -> Option<&'static str> {
+    if db_path == Path::new(":memory:") {
+        return None;
+    }
+    let dir = db_path
+        .ancestors()
+        .map(|p| {
+            if p.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                p
+            }
+        })
+        .find(|p| p.exists())?;
+    detect(dir)
+}
+
+/// Linux `statfs.f_type` magic numbers of shared filesystems.
+#[cfg(target_os = "linux")]
+const LINUX_MAGIC: &[(u32, &str)] = &[
+    (0x0000_6969, "nfs"),
+    (0x0000_517b, "smb"),
+    (0xff53_4d42, "cifs"),
+    (0xfe53_4d42, "smb2"),
+    (0x6573_5546, "fuse"),
+    (0x0102_1997, "9p"),
+    (0x00c3_6400, "ceph"),
+    (0x5346_414f, "afs"),
+    (0x7375_7245, "coda"),
+    (0x0bd0_0bd0, "lustre"),
+    (0x0116_1970, "gfs2"),
+    (0x7461_636f, "ocfs2"),
+];
+
+/// Name of a shared filesystem by its Linux magic number.
+#[cfg(target_os = "linux")]
+pub(crate) fn linux_fs_name(magic: u32) -> Option<&'static str> {
+    LINUX_MAGIC
+        .iter()
+        .find(|(m, _)| *m == magic)
+        .map(|(_, name)| *name)
+}
+
+#[cfg(target_os = "linux")]
+fn detect(dir: &Path) -> Option<&'static str> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is NUL-terminated and `st` is a valid out-pointer.
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+    // `f_type` is signed on some targets; the magics are 32-bit patterns.
+    #[allow(clippy::unnecessary_cast)]
+    linux_fs_name(st.f_type as u32)
+}
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+fn detect(dir: &Path) -> Option<&'static str> {
+    use std::os::unix::ffi::OsStrExt;
+
+    const SHARED: &[&str] = &[
+        "nfs", "smbfs", "afpfs", "webdav", "cifs", "macfuse", "osxfuse", "fusefs",
+    ];
+    let c_path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `c_path` is NUL-terminated and `st` is a valid out-pointer.
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(st.f_fstypename.as_ptr()) };
+    let name = name.to_str().ok()?;
+    SHARED
+        .iter()
+        .find(|s| name == **s || name.starts_with(&format!("{s}.")))
+        .copied()
+}
+
+/// UNC paths (`\\server\share\…`) are the only remote locations we can
+/// tell without Win32 calls; mapped drive letters go unnoticed.
+#[cfg(windows)]
+fn detect(dir: &Path) -> Option<&'static str> {
+    let s = dir.to_string_lossy();
+    let unc = s.starts_with(r"\\?\UNC\")
+        || (s.starts_with(r"\\") && !s.starts_with(r"\\?\") && !s.starts_with(r"\\.\"));
+    unc.then_some("smb")
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    windows
+)))]
+fn detect(_dir: &Path) -> Option<&'static str> {
+    None
+}
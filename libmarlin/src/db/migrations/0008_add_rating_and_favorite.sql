@@ -0,0 +1,10 @@
+-- src/db/migrations/0008_add_rating_and_favorite.sql
+PRAGMA foreign_keys = ON;
+PRAGMA journal_mode = WAL;
+
+-- Built-in star rating (0-5, NULL = unrated) and favorite flag per file
+ALTER TABLE files ADD COLUMN rating   INTEGER CHECK (rating BETWEEN 0 AND 5);
+ALTER TABLE files ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_files_rating   ON files(rating);
+CREATE INDEX IF NOT EXISTS idx_files_favorite ON files(favorite);
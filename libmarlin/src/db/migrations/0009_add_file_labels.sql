@@ -0,0 +1,11 @@
+PRAGMA foreign_keys = ON;
+PRAGMA journal_mode = WAL;
+
+-- Colour labels (Finder / KDE style); named colours or `#rrggbb`
+CREATE TABLE IF NOT EXISTS file_labels (
+  file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  label   TEXT    NOT NULL,
+  PRIMARY KEY (file_id, label)
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_labels_label ON file_labels(label);
@@ -0,0 +1,10 @@
+PRAGMA foreign_keys = ON;
+PRAGMA journal_mode = WAL;
+
+-- Every executed `marlin search`, newest last
+CREATE TABLE IF NOT EXISTS search_history (
+  id          INTEGER PRIMARY KEY,
+  query       TEXT    NOT NULL,
+  executed_at INTEGER NOT NULL,            -- UNIX timestamp
+  hits        INTEGER NOT NULL
+);
@@ -0,0 +1,11 @@
+PRAGMA foreign_keys = ON;
+
+-- Per-root indexing profiles (`marlin root add`)
+CREATE TABLE IF NOT EXISTS roots (
+  id            INTEGER PRIMARY KEY,
+  path          TEXT    NOT NULL UNIQUE,
+  ignore_globs  TEXT    NOT NULL DEFAULT '',  -- newline-separated, relative to path
+  index_content INTEGER NOT NULL DEFAULT 1,
+  hash_files    INTEGER NOT NULL DEFAULT 0,
+  debounce_ms   INTEGER                       -- NULL = watcher default
+);
@@ -0,0 +1,41 @@
+PRAGMA foreign_keys = ON;
+
+-- A plain contentless FTS5 table rejects DELETE, so removing a row from
+-- `files` failed in `files_fts_ad_file`. Rebuild it with
+-- contentless_delete=1 and repopulate it from the base tables. The
+-- triggers on `files`, `file_tags` and `attributes` refer to the table by
+-- name and carry over unchanged.
+DROP TABLE IF EXISTS files_fts;
+CREATE VIRTUAL TABLE files_fts
+USING fts5(
+    path,
+    tags_text,
+    attrs_text,
+    content='',
+    contentless_delete=1,
+    tokenize="unicode61 remove_diacritics 2"
+);
+
+INSERT INTO files_fts(rowid, path, tags_text, attrs_text)
+SELECT f.id,
+       f.path,
+       (SELECT IFNULL(GROUP_CONCAT(tag_path, ' '), '')
+          FROM (
+            WITH RECURSIVE tag_tree(id, parent_id, path) AS (
+              SELECT t.id, t.parent_id, t.name
+                FROM tags t
+               WHERE t.parent_id IS NULL
+              UNION ALL
+              SELECT t.id, t.parent_id, tt.path || '/' || t.name
+                FROM tags t
+                JOIN tag_tree tt ON t.parent_id = tt.id
+            )
+            SELECT DISTINCT tag_tree.path AS tag_path
+              FROM file_tags ft
+              JOIN tag_tree ON ft.tag_id = tag_tree.id
+             WHERE ft.file_id = f.id
+          )),
+       (SELECT IFNULL(GROUP_CONCAT(a.key || '=' || a.value, ' '), '')
+          FROM attributes a
+         WHERE a.file_id = f.id)
+  FROM files f;
@@ -0,0 +1,121 @@
+PRAGMA foreign_keys = ON;
+
+-- Index-wide settings (`db::identity` and friends)
+CREATE TABLE IF NOT EXISTS settings (
+  key   TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
+INSERT OR IGNORE INTO settings(key, value) VALUES ('identity', 'path');
+
+-- One row per distinct file body; hard links and copies share it
+CREATE TABLE IF NOT EXISTS contents (
+  id   INTEGER PRIMARY KEY,
+  hash TEXT    NOT NULL UNIQUE           -- SHA-256, as in files.hash
+);
+
+ALTER TABLE files ADD COLUMN content_id INTEGER REFERENCES contents(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_files_content_id ON files(content_id);
+
+INSERT OR IGNORE INTO contents(hash)
+  SELECT DISTINCT hash FROM files WHERE hash IS NOT NULL;
+UPDATE files
+   SET content_id = (SELECT c.id FROM contents c WHERE c.hash = files.hash)
+ WHERE hash IS NOT NULL;
+
+-- Link every stored hash to its content row
+CREATE TRIGGER IF NOT EXISTS files_content_ai
+AFTER INSERT ON files
+WHEN NEW.hash IS NOT NULL
+BEGIN
+  INSERT OR IGNORE INTO contents(hash) VALUES (NEW.hash);
+  UPDATE files SET content_id = (SELECT id FROM contents WHERE hash = NEW.hash)
+   WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS files_content_au
+AFTER UPDATE OF hash ON files
+WHEN NEW.hash IS NOT OLD.hash
+BEGIN
+  INSERT OR IGNORE INTO contents(hash) SELECT NEW.hash WHERE NEW.hash IS NOT NULL;
+  UPDATE files SET content_id = (SELECT id FROM contents WHERE hash = NEW.hash)
+   WHERE id = NEW.id;
+END;
+
+/* ── content identity ──────────────────────────────────────────────
+   With settings.identity = 'content', tags and attributes belong to
+   the body rather than the path: a file joining a content group picks
+   up the group's metadata, and later changes are mirrored to every
+   alias. Removing a file from the index leaves its aliases alone. */
+
+CREATE TRIGGER IF NOT EXISTS files_content_inherit
+AFTER UPDATE OF content_id ON files
+WHEN NEW.content_id IS NOT NULL
+ AND NEW.content_id IS NOT OLD.content_id
+ AND (SELECT value FROM settings WHERE key = 'identity') = 'content'
+BEGIN
+  INSERT OR IGNORE INTO file_tags(file_id, tag_id)
+    SELECT NEW.id, ft.tag_id
+      FROM file_tags ft JOIN files f ON f.id = ft.file_id
+     WHERE f.content_id = NEW.content_id AND f.id != NEW.id;
+  INSERT OR IGNORE INTO attributes(file_id, key, value)
+    SELECT NEW.id, a.key, a.value
+      FROM attributes a JOIN files f ON f.id = a.file_id
+     WHERE f.content_id = NEW.content_id AND f.id != NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS file_tags_content_ai
+AFTER INSERT ON file_tags
+WHEN (SELECT value FROM settings WHERE key = 'identity') = 'content'
+BEGIN
+  INSERT OR IGNORE INTO file_tags(file_id, tag_id)
+    SELECT f.id, NEW.tag_id FROM files f
+     WHERE f.content_id = (SELECT content_id FROM files WHERE id = NEW.file_id)
+       AND f.id != NEW.file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS file_tags_content_ad
+AFTER DELETE ON file_tags
+WHEN (SELECT value FROM settings WHERE key = 'identity') = 'content'
+ AND EXISTS (SELECT 1 FROM files WHERE id = OLD.file_id)
+BEGIN
+  DELETE FROM file_tags
+   WHERE tag_id = OLD.tag_id
+     AND file_id IN (SELECT f.id FROM files f
+                      WHERE f.content_id = (SELECT content_id FROM files WHERE id = OLD.file_id)
+                        AND f.id != OLD.file_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS attributes_content_ai
+AFTER INSERT ON attributes
+WHEN (SELECT value FROM settings WHERE key = 'identity') = 'content'
+BEGIN
+  INSERT INTO attributes(file_id, key, value)
+    SELECT f.id, NEW.key, NEW.value FROM files f
+     WHERE f.content_id = (SELECT content_id FROM files WHERE id = NEW.file_id)
+       AND f.id != NEW.file_id
+  ON CONFLICT(file_id, key) DO UPDATE SET value = excluded.value;
+END;
+
+CREATE TRIGGER IF NOT EXISTS attributes_content_au
+AFTER UPDATE OF value ON attributes
+WHEN (SELECT value FROM settings WHERE key = 'identity') = 'content'
+BEGIN
+  UPDATE attributes SET value = NEW.value
+   WHERE key = NEW.key
+     AND value IS NOT NEW.value
+     AND file_id IN (SELECT f.id FROM files f
+                      WHERE f.content_id = (SELECT content_id FROM files WHERE id = NEW.file_id)
+                        AND f.id != NEW.file_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS attributes_content_ad
+AFTER DELETE ON attributes
+WHEN (SELECT value FROM settings WHERE key = 'identity') = 'content'
+ AND EXISTS (SELECT 1 FROM files WHERE id = OLD.file_id)
+BEGIN
+  DELETE FROM attributes
+   WHERE key = OLD.key
+     AND file_id IN (SELECT f.id FROM files f
+                      WHERE f.content_id = (SELECT content_id FROM files WHERE id = OLD.file_id)
+                        AND f.id != OLD.file_id);
+END;
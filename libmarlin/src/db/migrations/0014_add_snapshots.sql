@@ -0,0 +1,18 @@
+PRAGMA foreign_keys = ON;
+
+-- Named copies of the tag/attribute/link state (`marlin snapshot`)
+CREATE TABLE IF NOT EXISTS snapshots (
+  id         INTEGER PRIMARY KEY,
+  name       TEXT    NOT NULL UNIQUE,
+  created_at INTEGER NOT NULL             -- UNIX timestamp
+);
+
+-- Entries are keyed by path, not file id, so they outlive the files
+CREATE TABLE IF NOT EXISTS snapshot_entries (
+  snapshot_id INTEGER NOT NULL REFERENCES snapshots(id) ON DELETE CASCADE,
+  path        TEXT    NOT NULL,
+  kind        TEXT    NOT NULL,           -- 'tag' | 'attr' | 'link'
+  key         TEXT    NOT NULL,           -- tag path, attr key, link target
+  value       TEXT    NOT NULL DEFAULT '' -- attr value, link type
+);
+CREATE INDEX IF NOT EXISTS idx_snapshot_entries_snapshot ON snapshot_entries(snapshot_id);
@@ -0,0 +1,8 @@
+PRAGMA foreign_keys = ON;
+
+-- Optional TTL for attributes (`marlin attr set --expires 30d`);
+-- rows past `expires_at` (UNIX timestamp) are swept on open
+ALTER TABLE attributes ADD COLUMN expires_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_attributes_expires_at
+  ON attributes(expires_at) WHERE expires_at IS NOT NULL;
@@ -0,0 +1,9 @@
+PRAGMA foreign_keys = ON;
+
+-- Saved exec pipelines: a query plus the command run on its hits
+CREATE TABLE IF NOT EXISTS actions (
+  id      INTEGER PRIMARY KEY,
+  name    TEXT    NOT NULL UNIQUE,
+  query   TEXT    NOT NULL,
+  command TEXT    NOT NULL
+);
@@ -0,0 +1,12 @@
+PRAGMA foreign_keys = ON;
+
+-- Where the platform exposes them (Unix), scans record the device and
+-- inode of every file plus its status-change time in nanoseconds. An
+-- unchanged (inode, ctime) pair lets a re-scan skip the file; a known
+-- inode under a new path is a rename. NULL means "not available".
+ALTER TABLE files ADD COLUMN dev   INTEGER;
+ALTER TABLE files ADD COLUMN inode INTEGER;
+ALTER TABLE files ADD COLUMN ctime INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_files_inode
+  ON files(inode) WHERE inode IS NOT NULL;
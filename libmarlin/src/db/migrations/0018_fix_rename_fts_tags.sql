@@ -0,0 +1,34 @@
+PRAGMA foreign_keys = ON;
+
+-- The rename trigger from 0007 rebuilt `tags_text` from bare tag names,
+-- so a renamed file lost its full tag paths (`project/md` became
+-- `project md`) until the next tag change. Build it the way the insert
+-- triggers do. Rows that already drifted are found and rewritten by
+-- `marlin doctor --repair`.
+DROP TRIGGER IF EXISTS files_fts_au_file;
+CREATE TRIGGER files_fts_au_file
+AFTER UPDATE OF path ON files
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text)
+        SELECT NEW.id,
+               NEW.path,
+               (SELECT IFNULL(GROUP_CONCAT(tag_path, ' '), '')
+                  FROM (
+                    WITH RECURSIVE tag_tree(id, parent_id, path) AS (
+                      SELECT t.id, t.parent_id, t.name
+                        FROM tags t
+                       WHERE t.parent_id IS NULL
+                      UNION ALL
+                      SELECT t.id, t.parent_id, tt.path || '/' || t.name
+                        FROM tags t
+                        JOIN tag_tree tt ON t.parent_id = tt.id
+                    )
+                    SELECT DISTINCT tag_tree.path AS tag_path
+                      FROM file_tags ft
+                      JOIN tag_tree ON ft.tag_id = tag_tree.id
+                     WHERE ft.file_id = NEW.id
+                  )),
+               (SELECT IFNULL(GROUP_CONCAT(a.key || '=' || a.value, ' '), '')
+                  FROM attributes a
+                 WHERE a.file_id = NEW.id);
+END;
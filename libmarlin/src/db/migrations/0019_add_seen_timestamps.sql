@@ -0,0 +1,11 @@
+PRAGMA foreign_keys = ON;
+
+-- Unix seconds at which a scan first indexed the file and last found it
+-- on disk; `added:` and `seen:` search filters read them.  Rows indexed
+-- before this migration keep NULL in first_indexed_at – we do not know
+-- when they arrived – and get last_seen_at on their next scan.
+ALTER TABLE files ADD COLUMN first_indexed_at INTEGER;
+ALTER TABLE files ADD COLUMN last_seen_at     INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_files_first_indexed
+  ON files(first_indexed_at) WHERE first_indexed_at IS NOT NULL;
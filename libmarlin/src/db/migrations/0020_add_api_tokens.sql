@@ -0,0 +1,13 @@
+PRAGMA foreign_keys = ON;
+
+-- Tokens for the watcher's control channel (`marlin token`).  Only a
+-- SHA-256 of each secret is stored; `scopes` is a space-separated list
+-- such as `read tag:projects`.
+CREATE TABLE IF NOT EXISTS api_tokens (
+  id           INTEGER PRIMARY KEY,
+  name         TEXT    NOT NULL UNIQUE,
+  secret_hash  TEXT    NOT NULL UNIQUE,
+  scopes       TEXT    NOT NULL,
+  created_at   INTEGER NOT NULL,
+  last_used_at INTEGER
+);
@@ -0,0 +1,11 @@
+PRAGMA foreign_keys = ON;
+
+-- Scans in progress, one row per root being walked, so a second `marlin
+-- scan` of the same tree can wait for (or skip) the first instead of
+-- redoing its work.  `heartbeat_at` is refreshed after every root.
+CREATE TABLE IF NOT EXISTS active_scans (
+  root         TEXT    PRIMARY KEY,
+  pid          INTEGER NOT NULL,
+  started_at   INTEGER NOT NULL,
+  heartbeat_at INTEGER NOT NULL
+);
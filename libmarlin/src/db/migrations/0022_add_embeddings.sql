@@ -0,0 +1,12 @@
+PRAGMA foreign_keys = ON;
+
+-- One vector per file and embedding model (`marlin embed`), ranked by
+-- cosine similarity in `marlin search --semantic`.  `mtime` is the
+-- files.mtime the vector was computed from, so stale ones are redone.
+CREATE TABLE IF NOT EXISTS embeddings (
+  file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  model   TEXT    NOT NULL,
+  vector  BLOB    NOT NULL,   -- little-endian f32s
+  mtime   INTEGER NOT NULL,
+  PRIMARY KEY (file_id, model)
+);
@@ -0,0 +1,9 @@
+PRAGMA foreign_keys = ON;
+
+-- When `marlin classify` last ran a file through which classifier, so
+-- unchanged files are not sent again.  `mtime` is files.mtime then.
+CREATE TABLE IF NOT EXISTS classified (
+  file_id INTEGER PRIMARY KEY REFERENCES files(id) ON DELETE CASCADE,
+  mtime   INTEGER NOT NULL,
+  command TEXT    NOT NULL
+);
@@ -0,0 +1,7 @@
+PRAGMA foreign_keys = ON;
+
+-- Files whose metadata `marlin lock` froze; see src/lock.rs.
+CREATE TABLE IF NOT EXISTS locked_files (
+  file_id   INTEGER PRIMARY KEY REFERENCES files(id) ON DELETE CASCADE,
+  locked_at INTEGER NOT NULL
+);
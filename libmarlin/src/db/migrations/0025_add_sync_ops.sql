@@ -0,0 +1,156 @@
+PRAGMA foreign_keys = ON;
+
+-- Op-log of tag, attribute and link changes for `marlin sync`; see
+-- src/sync.rs.  Local changes are recorded by the triggers below with this
+-- replica as origin and no origin_seq (their own seq stands in); imported
+-- ops keep the origin and number they had there.  Triggers stay quiet
+-- while `sync.applying` is set, which only an import does.
+INSERT OR IGNORE INTO settings(key, value)
+  VALUES ('sync.replica', lower(hex(randomblob(8))));
+
+CREATE TABLE IF NOT EXISTS sync_ops (
+  seq        INTEGER PRIMARY KEY AUTOINCREMENT,
+  origin     TEXT    NOT NULL,
+  origin_seq INTEGER,
+  ts         INTEGER NOT NULL,  -- Unix milliseconds
+  op         TEXT    NOT NULL,  -- tag+ tag- attr= attr- link+ link-
+  path       TEXT    NOT NULL,
+  name       TEXT    NOT NULL,  -- tag path, attribute key or link target
+  value      TEXT,              -- attribute value or link type
+  UNIQUE (origin, origin_seq)
+);
+CREATE INDEX IF NOT EXISTS idx_sync_ops_target ON sync_ops(path, name);
+
+-- the state so far, so a first export carries everything
+WITH RECURSIVE tag_tree(id, path) AS (
+  SELECT id, name FROM tags WHERE parent_id IS NULL
+  UNION ALL
+  SELECT t.id, tt.path || '/' || t.name FROM tags t JOIN tag_tree tt ON t.parent_id = tt.id
+)
+INSERT INTO sync_ops(origin, ts, op, path, name)
+  SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+         CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+         'tag+', f.path, tt.path
+    FROM file_tags ft
+    JOIN files f ON f.id = ft.file_id
+    JOIN tag_tree tt ON tt.id = ft.tag_id
+   ORDER BY f.path, tt.path;
+
+INSERT INTO sync_ops(origin, ts, op, path, name, value)
+  SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+         CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+         'attr=', f.path, a.key, a.value
+    FROM attributes a JOIN files f ON f.id = a.file_id
+   WHERE a.key NOT LIKE 'meta/%'
+   ORDER BY f.path, a.key;
+
+INSERT INTO sync_ops(origin, ts, op, path, name, value)
+  SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+         CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+         'link+', s.path, d.path, l.type
+    FROM links l
+    JOIN files s ON s.id = l.src_file_id
+    JOIN files d ON d.id = l.dst_file_id
+   ORDER BY s.path, d.path;
+
+-- Rows removed because their file left the index are not changes to
+-- replicate, hence the EXISTS checks on delete.
+
+CREATE TRIGGER IF NOT EXISTS file_tags_sync_ai
+AFTER INSERT ON file_tags
+WHEN (SELECT value FROM settings WHERE key = 'sync.applying') IS NULL
+BEGIN
+  INSERT INTO sync_ops(origin, ts, op, path, name)
+    SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+           CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+           'tag+', f.path,
+           (WITH RECURSIVE up(parent_id, path) AS (
+              SELECT parent_id, name FROM tags WHERE id = NEW.tag_id
+              UNION ALL
+              SELECT t.parent_id, t.name || '/' || up.path FROM tags t JOIN up ON t.id = up.parent_id
+            )
+            SELECT path FROM up WHERE parent_id IS NULL)
+      FROM files f WHERE f.id = NEW.file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS file_tags_sync_ad
+AFTER DELETE ON file_tags
+WHEN (SELECT value FROM settings WHERE key = 'sync.applying') IS NULL
+ AND EXISTS (SELECT 1 FROM files WHERE id = OLD.file_id)
+ AND EXISTS (SELECT 1 FROM tags WHERE id = OLD.tag_id)
+BEGIN
+  INSERT INTO sync_ops(origin, ts, op, path, name)
+    SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+           CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+           'tag-', f.path,
+           (WITH RECURSIVE up(parent_id, path) AS (
+              SELECT parent_id, name FROM tags WHERE id = OLD.tag_id
+              UNION ALL
+              SELECT t.parent_id, t.name || '/' || up.path FROM tags t JOIN up ON t.id = up.parent_id
+            )
+            SELECT path FROM up WHERE parent_id IS NULL)
+      FROM files f WHERE f.id = OLD.file_id;
+END;
+
+-- `meta/` attributes are derived from the file itself on every machine
+CREATE TRIGGER IF NOT EXISTS attributes_sync_ai
+AFTER INSERT ON attributes
+WHEN (SELECT value FROM settings WHERE key = 'sync.applying') IS NULL
+ AND NEW.key NOT LIKE 'meta/%'
+BEGIN
+  INSERT INTO sync_ops(origin, ts, op, path, name, value)
+    SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+           CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+           'attr=', f.path, NEW.key, NEW.value
+      FROM files f WHERE f.id = NEW.file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS attributes_sync_au
+AFTER UPDATE OF value ON attributes
+WHEN (SELECT value FROM settings WHERE key = 'sync.applying') IS NULL
+ AND NEW.key NOT LIKE 'meta/%'
+ AND NEW.value IS NOT OLD.value
+BEGIN
+  INSERT INTO sync_ops(origin, ts, op, path, name, value)
+    SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+           CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+           'attr=', f.path, NEW.key, NEW.value
+      FROM files f WHERE f.id = NEW.file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS attributes_sync_ad
+AFTER DELETE ON attributes
+WHEN (SELECT value FROM settings WHERE key = 'sync.applying') IS NULL
+ AND OLD.key NOT LIKE 'meta/%'
+ AND EXISTS (SELECT 1 FROM files WHERE id = OLD.file_id)
+BEGIN
+  INSERT INTO sync_ops(origin, ts, op, path, name)
+    SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+           CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+           'attr-', f.path, OLD.key
+      FROM files f WHERE f.id = OLD.file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS links_sync_ai
+AFTER INSERT ON links
+WHEN (SELECT value FROM settings WHERE key = 'sync.applying') IS NULL
+BEGIN
+  INSERT INTO sync_ops(origin, ts, op, path, name, value)
+    SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+           CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+           'link+', s.path, d.path, NEW.type
+      FROM files s, files d
+     WHERE s.id = NEW.src_file_id AND d.id = NEW.dst_file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS links_sync_ad
+AFTER DELETE ON links
+WHEN (SELECT value FROM settings WHERE key = 'sync.applying') IS NULL
+BEGIN
+  INSERT INTO sync_ops(origin, ts, op, path, name, value)
+    SELECT (SELECT value FROM settings WHERE key = 'sync.replica'),
+           CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER),
+           'link-', s.path, d.path, OLD.type
+      FROM files s, files d
+     WHERE s.id = OLD.src_file_id AND d.id = OLD.dst_file_id;
+END;
@@ -0,0 +1,47 @@
+PRAGMA foreign_keys = ON;
+
+-- Tag attach/detach as a last-writer-wins set (see db::attach_tag): when a
+-- file got a tag, and when one was last taken away.  Unix milliseconds;
+-- tags attached before this migration have no time and lose to any detach.
+CREATE TABLE IF NOT EXISTS file_tag_stamps (
+  file_id  INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  tag_id   INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  added_at INTEGER NOT NULL,
+  PRIMARY KEY (file_id, tag_id)
+);
+
+CREATE TABLE IF NOT EXISTS file_tag_tombstones (
+  file_id    INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  tag_id     INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  removed_at INTEGER NOT NULL,
+  PRIMARY KEY (file_id, tag_id)
+);
+
+-- Plain inserts and deletes (scripts, content identity, gc of auto/ tags …)
+-- count as changes made now.  db::attach_tag / detach_tag stamp their
+-- own times.  Rows removed with their file or tag leave no tombstone.
+CREATE TRIGGER IF NOT EXISTS file_tags_stamp_ai
+AFTER INSERT ON file_tags
+BEGIN
+  INSERT OR REPLACE INTO file_tag_stamps(file_id, tag_id, added_at)
+    VALUES (NEW.file_id, NEW.tag_id,
+            CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER));
+  DELETE FROM file_tag_tombstones WHERE file_id = NEW.file_id AND tag_id = NEW.tag_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS file_tags_unstamp_ad
+AFTER DELETE ON file_tags
+BEGIN
+  DELETE FROM file_tag_stamps WHERE file_id = OLD.file_id AND tag_id = OLD.tag_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS file_tags_tombstone_ad
+AFTER DELETE ON file_tags
+WHEN EXISTS (SELECT 1 FROM files WHERE id = OLD.file_id)
+ AND EXISTS (SELECT 1 FROM tags WHERE id = OLD.tag_id)
+BEGIN
+  INSERT INTO file_tag_tombstones(file_id, tag_id, removed_at)
+    VALUES (OLD.file_id, OLD.tag_id,
+            CAST((julianday('now') - 2440587.5) * 86400000 AS INTEGER))
+  ON CONFLICT(file_id, tag_id) DO UPDATE SET removed_at = max(removed_at, excluded.removed_at);
+END;
@@ -0,0 +1,15 @@
+PRAGMA foreign_keys = ON;
+
+-- Reminders on files.  Times are Unix seconds (UTC); `notified_at` is set
+-- once a notification went out (see src/notify.rs).
+CREATE TABLE IF NOT EXISTS reminders (
+  id          INTEGER PRIMARY KEY,
+  file_id     INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  due_at      INTEGER NOT NULL,
+  note        TEXT    NOT NULL,
+  created_at  INTEGER NOT NULL,
+  notified_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_reminders_pending
+  ON reminders(due_at) WHERE notified_at IS NULL;
@@ -0,0 +1,20 @@
+PRAGMA foreign_keys = ON;
+
+-- Workflow states (`marlin state`).  Every change is a row, so a file's
+-- current state is its newest one and the rows are its history.
+CREATE TABLE IF NOT EXISTS file_states (
+  id         INTEGER PRIMARY KEY,
+  file_id    INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  state      TEXT    NOT NULL,
+  changed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_states_file ON file_states(file_id, id);
+
+-- Allowed moves between states.  While the table is empty any move is
+-- allowed; a file's first state is never restricted.
+CREATE TABLE IF NOT EXISTS state_transitions (
+  from_state TEXT NOT NULL,
+  to_state   TEXT NOT NULL,
+  PRIMARY KEY (from_state, to_state)
+);
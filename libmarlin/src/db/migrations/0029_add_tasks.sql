@@ -0,0 +1,18 @@
+PRAGMA foreign_keys = ON;
+
+-- Tasks attached to files.  Times are Unix seconds (UTC); a task is
+-- `open` until marked `done`, which stamps `done_at`.  Higher `priority`
+-- comes first.
+CREATE TABLE IF NOT EXISTS tasks (
+  id          INTEGER PRIMARY KEY,
+  file_id     INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  title       TEXT    NOT NULL,
+  status      TEXT    NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'done')),
+  due_at      INTEGER,
+  priority    INTEGER NOT NULL DEFAULT 0,
+  created_at  INTEGER NOT NULL,
+  done_at     INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_file   ON tasks(file_id);
+CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status, due_at);
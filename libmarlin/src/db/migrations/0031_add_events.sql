@@ -0,0 +1,15 @@
+PRAGMA foreign_keys = ON;
+
+-- Calendar events linked to files.  Times are Unix seconds (UTC); an
+-- event without `end_at` is a single moment or day.
+CREATE TABLE IF NOT EXISTS events (
+  id          INTEGER PRIMARY KEY,
+  file_id     INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  start_at    INTEGER NOT NULL,
+  end_at      INTEGER CHECK (end_at IS NULL OR end_at >= start_at),
+  description TEXT    NOT NULL,
+  created_at  INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_file  ON events(file_id);
+CREATE INDEX IF NOT EXISTS idx_events_start ON events(start_at);
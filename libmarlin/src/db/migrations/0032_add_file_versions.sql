@@ -0,0 +1,16 @@
+PRAGMA foreign_keys = ON;
+
+-- Content snapshots taken by `marlin version snapshot`.  `version` counts
+-- from 1 per file; `content` is the zlib-compressed file body, or NULL
+-- when only the hash was recorded.
+CREATE TABLE IF NOT EXISTS file_versions (
+  id          INTEGER PRIMARY KEY,
+  file_id     INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+  version     INTEGER NOT NULL,
+  hash        TEXT    NOT NULL,
+  size        INTEGER NOT NULL,
+  mtime       INTEGER,
+  content     BLOB,
+  created_at  INTEGER NOT NULL,
+  UNIQUE (file_id, version)
+);
@@ -0,0 +1,160 @@
+PRAGMA foreign_keys = ON;
+
+-- The text of files whose body was read while indexing (UTF-8 files up
+-- to the size cap).  `body` is NULL for files that were read but are not
+-- text, so they are not read again until they change.
+CREATE TABLE IF NOT EXISTS file_text (
+  file_id INTEGER PRIMARY KEY REFERENCES files(id) ON DELETE CASCADE,
+  body    TEXT
+);
+
+-- `files_fts` gains a `content` column read from here.
+DROP VIEW IF EXISTS files_fts_source;
+CREATE VIEW files_fts_source(id, path, tags_text, attrs_text, notes_text, content) AS
+SELECT f.id,
+       f.path,
+       (SELECT IFNULL(GROUP_CONCAT(tag_path, ' '), '')
+          FROM (
+            WITH RECURSIVE tag_tree(id, parent_id, path) AS (
+              SELECT t.id, t.parent_id, t.name
+                FROM tags t
+               WHERE t.parent_id IS NULL
+              UNION ALL
+              SELECT t.id, t.parent_id, tt.path || '/' || t.name
+                FROM tags t
+                JOIN tag_tree tt ON t.parent_id = tt.id
+            )
+            SELECT DISTINCT tag_tree.path AS tag_path
+              FROM file_tags ft
+              JOIN tag_tree ON ft.tag_id = tag_tree.id
+             WHERE ft.file_id = f.id
+          )),
+       (SELECT IFNULL(GROUP_CONCAT(a.key || '=' || a.value, ' '), '')
+          FROM attributes a
+         WHERE a.file_id = f.id),
+       (SELECT IFNULL(GROUP_CONCAT(n.note, ' '), '')
+          FROM annotations n
+         WHERE n.file_id = f.id),
+       (SELECT IFNULL(t.body, '')
+          FROM file_text t
+         WHERE t.file_id = f.id)
+  FROM files f;
+
+-- FTS5 tables cannot gain a column; rebuild with `content`.
+DROP TABLE IF EXISTS files_fts;
+CREATE VIRTUAL TABLE files_fts
+USING fts5(
+    path,
+    tags_text,
+    attrs_text,
+    notes_text,
+    content,
+    content='',
+    contentless_delete=1,
+    tokenize="unicode61 remove_diacritics 2"
+);
+
+INSERT INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source;
+
+DROP TRIGGER IF EXISTS files_fts_ai_file;
+CREATE TRIGGER files_fts_ai_file
+AFTER INSERT ON files
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.id;
+END;
+
+DROP TRIGGER IF EXISTS files_fts_au_file;
+CREATE TRIGGER files_fts_au_file
+AFTER UPDATE OF path ON files
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.id;
+END;
+
+DROP TRIGGER IF EXISTS file_tags_fts_ai;
+CREATE TRIGGER file_tags_fts_ai
+AFTER INSERT ON file_tags
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.file_id;
+END;
+
+DROP TRIGGER IF EXISTS file_tags_fts_ad;
+CREATE TRIGGER file_tags_fts_ad
+AFTER DELETE ON file_tags
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = OLD.file_id;
+END;
+
+DROP TRIGGER IF EXISTS attributes_fts_ai;
+CREATE TRIGGER attributes_fts_ai
+AFTER INSERT ON attributes
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.file_id;
+END;
+
+DROP TRIGGER IF EXISTS attributes_fts_au;
+CREATE TRIGGER attributes_fts_au
+AFTER UPDATE OF value ON attributes
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.file_id;
+END;
+
+DROP TRIGGER IF EXISTS attributes_fts_ad;
+CREATE TRIGGER attributes_fts_ad
+AFTER DELETE ON attributes
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = OLD.file_id;
+END;
+
+DROP TRIGGER IF EXISTS annotations_fts_ai;
+CREATE TRIGGER annotations_fts_ai
+AFTER INSERT ON annotations
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.file_id;
+END;
+
+DROP TRIGGER IF EXISTS annotations_fts_au;
+CREATE TRIGGER annotations_fts_au
+AFTER UPDATE OF note ON annotations
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.file_id;
+END;
+
+DROP TRIGGER IF EXISTS annotations_fts_ad;
+CREATE TRIGGER annotations_fts_ad
+AFTER DELETE ON annotations
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = OLD.file_id;
+END;
+
+-- a cascade from a deleted file finds no `files` row and writes nothing
+CREATE TRIGGER IF NOT EXISTS file_text_fts_ai
+AFTER INSERT ON file_text
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS file_text_fts_au
+AFTER UPDATE OF body ON file_text
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = NEW.file_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS file_text_fts_ad
+AFTER DELETE ON file_text
+BEGIN
+    INSERT OR REPLACE INTO files_fts(rowid, path, tags_text, attrs_text, notes_text, content)
+    SELECT id, path, tags_text, attrs_text, notes_text, content FROM files_fts_source WHERE id = OLD.file_id;
+END;
@@ -0,0 +1,9 @@
+PRAGMA foreign_keys = ON;
+
+-- key=value metadata on links (`weight`, `note`, `created_by`, …)
+CREATE TABLE IF NOT EXISTS link_attrs (
+  link_id INTEGER NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+  key     TEXT    NOT NULL,
+  value   TEXT    NOT NULL,
+  PRIMARY KEY (link_id, key)
+);
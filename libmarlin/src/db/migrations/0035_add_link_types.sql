@@ -0,0 +1,8 @@
+PRAGMA foreign_keys = ON;
+
+-- registered link types and the name of their inverse
+-- (`cites` / `cited-by`); a symmetric type is its own inverse
+CREATE TABLE IF NOT EXISTS link_types (
+  name    TEXT PRIMARY KEY,
+  inverse TEXT UNIQUE
+);
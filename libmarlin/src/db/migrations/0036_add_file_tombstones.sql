@@ -0,0 +1,8 @@
+PRAGMA foreign_keys = ON;
+
+-- Set (unix seconds) on files gone from disk whose row and metadata were
+-- kept (`marlin prune --keep-metadata`); a scan finding the file clears it.
+ALTER TABLE files ADD COLUMN missing_since INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_files_missing_since
+  ON files(missing_since) WHERE missing_since IS NOT NULL;
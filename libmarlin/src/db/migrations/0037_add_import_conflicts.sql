@@ -0,0 +1,17 @@
+PRAGMA foreign_keys = ON;
+
+-- Audit trail of conflicts met by `marlin sync import` / `link import`
+-- and how each was settled.  `at` is Unix seconds; `name` is the
+-- attribute key or link target; `value` holds an edited answer.
+CREATE TABLE IF NOT EXISTS import_conflicts (
+  id      INTEGER PRIMARY KEY,
+  at      INTEGER NOT NULL,
+  source  TEXT    NOT NULL,
+  kind    TEXT    NOT NULL CHECK (kind IN ('attr', 'link')),
+  path    TEXT    NOT NULL,
+  name    TEXT    NOT NULL,
+  mine    TEXT    NOT NULL,
+  theirs  TEXT    NOT NULL,
+  choice  TEXT    NOT NULL CHECK (choice IN ('mine', 'theirs', 'both', 'edit')),
+  value   TEXT
+);
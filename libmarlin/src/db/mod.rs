@@ -1,23 +1,41 @@
 //! Central DB helper – connection bootstrap, migrations **and** most
 //! data-access helpers (tags, links, collections, saved views, …).
 
+mod attach;
 mod database;
-pub use database::{Database, IndexOptions};
-
-use std::{
-    fs,
-    path::{Path, PathBuf},
-};
+mod diff;
+mod fts;
+pub(crate) mod location;
+mod trace;
+mod tuning;
+mod warm;
+pub use attach::{attach_shared, detach_shared, shared_schemas};
+pub use database::{ConnGuard, Database, IndexOptions};
+pub use diff::{diff_against, DbDiff, Delta, SchemaChange, TableCount};
+pub use fts::{repair_fts, verify_fts_consistency, FtsReport};
+pub use location::network_fs;
+pub use trace::{set_trace_sql, trace_connection, trace_sql};
+pub use tuning::{live_pragmas, tuning, Tuning, TuningPragmas};
+pub use warm::{warm, WarmReport};
+
+use std::path::Path;
+#[cfg(feature = "backup")]
+use std::{fs, path::PathBuf};
 
 use anyhow::{Context, Result};
-use chrono::Local;
+use chrono::Utc;
+#[cfg(feature = "backup")]
 use rusqlite::{
     backup::{Backup, StepResult},
-    params, Connection, OpenFlags, OptionalExtension, TransactionBehavior,
+    OpenFlags,
 };
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
 use std::result::Result as StdResult;
 use tracing::{debug, info, warn};
 
+use crate::utils::{normalize_path, PathNorm};
+pub(crate) use trace::Mutation;
+
 /* ─── schema version ───────────────────────────────────────────────── */
 
 /// Current library schema version.
@@ -54,6 +72,126 @@ const MIGRATIONS: &[(&str, &str)] = &[
         "0007_fix_rename_trigger.sql",
         include_str!("migrations/0007_fix_rename_trigger.sql"),
     ),
+    (
+        "0008_add_rating_and_favorite.sql",
+        include_str!("migrations/0008_add_rating_and_favorite.sql"),
+    ),
+    (
+        "0009_add_file_labels.sql",
+        include_str!("migrations/0009_add_file_labels.sql"),
+    ),
+    (
+        "0010_add_search_history.sql",
+        include_str!("migrations/0010_add_search_history.sql"),
+    ),
+    (
+        "0011_add_roots.sql",
+        include_str!("migrations/0011_add_roots.sql"),
+    ),
+    (
+        "0012_fts_contentless_delete.sql",
+        include_str!("migrations/0012_fts_contentless_delete.sql"),
+    ),
+    (
+        "0013_add_contents.sql",
+        include_str!("migrations/0013_add_contents.sql"),
+    ),
+    (
+        "0014_add_snapshots.sql",
+        include_str!("migrations/0014_add_snapshots.sql"),
+    ),
+    (
+        "0015_add_attr_expiry.sql",
+        include_str!("migrations/0015_add_attr_expiry.sql"),
+    ),
+    (
+        "0016_add_actions.sql",
+        include_str!("migrations/0016_add_actions.sql"),
+    ),
+    (
+        "0017_add_file_identity.sql",
+        include_str!("migrations/0017_add_file_identity.sql"),
+    ),
+    (
+        "0018_fix_rename_fts_tags.sql",
+        include_str!("migrations/0018_fix_rename_fts_tags.sql"),
+    ),
+    (
+        "0019_add_seen_timestamps.sql",
+        include_str!("migrations/0019_add_seen_timestamps.sql"),
+    ),
+    (
+        "0020_add_api_tokens.sql",
+        include_str!("migrations/0020_add_api_tokens.sql"),
+    ),
+    (
+        "0021_add_active_scans.sql",
+        include_str!("migrations/0021_add_active_scans.sql"),
+    ),
+    (
+        "0022_add_embeddings.sql",
+        include_str!("migrations/0022_add_embeddings.sql"),
+    ),
+    (
+        "0023_add_classified.sql",
+        include_str!("migrations/0023_add_classified.sql"),
+    ),
+    (
+        "0024_add_locks.sql",
+        include_str!("migrations/0024_add_locks.sql"),
+    ),
+    (
+        "0025_add_sync_ops.sql",
+        include_str!("migrations/0025_add_sync_ops.sql"),
+    ),
+    (
+        "0026_add_tag_tombstones.sql",
+        include_str!("migrations/0026_add_tag_tombstones.sql"),
+    ),
+    (
+        "0027_add_reminders.sql",
+        include_str!("migrations/0027_add_reminders.sql"),
+    ),
+    (
+        "0028_add_file_states.sql",
+        include_str!("migrations/0028_add_file_states.sql"),
+    ),
+    (
+        "0029_add_tasks.sql",
+        include_str!("migrations/0029_add_tasks.sql"),
+    ),
+    (
+        "0030_add_annotations.sql",
+        include_str!("migrations/0030_add_annotations.sql"),
+    ),
+    (
+        "0031_add_events.sql",
+        include_str!("migrations/0031_add_events.sql"),
+    ),
+    (
+        "0032_add_file_versions.sql",
+        include_str!("migrations/0032_add_file_versions.sql"),
+    ),
+    (
+        "0033_add_file_text.sql",
+        include_str!("migrations/0033_add_file_text.sql"),
+    ),
+    (
+        "0034_add_link_attrs.sql",
+        include_str!("migrations/0034_add_link_attrs.sql"),
+    ),
+    (
+        "0035_add_link_types.sql",
+        include_str!("migrations/0035_add_link_types.sql"),
+    ),
+    (
+        "0036_add_file_tombstones.sql",
+        include_str!("migrations/0036_add_file_tombstones.sql"),
+    ),
+    (
+        "0037_add_import_conflicts.sql",
+        include_str!("migrations/0037_add_import_conflicts.sql"),
+    ),
 ];
 
 /* ─── schema helpers ─────────────────────────────────────────────── */
@@ -68,26 +206,212 @@ pub fn current_schema_version(conn: &Connection) -> Result<i32> {
     Ok(version)
 }
 
+/// Fail unless the Marlin schema is readable through `conn` and the write
+/// lock can be taken (within the connection's busy timeout).
+pub fn check_writable(conn: &Connection) -> Result<()> {
+    conn.query_row("SELECT COUNT(*) FROM schema_version", [], |r| {
+        r.get::<_, i64>(0)
+    })
+    .context("database not readable")?;
+    // A no-op UPDATE still opens a write transaction, so it trips over
+    // read-only files/mounts as well as a held lock.
+    conn.execute_batch(
+        "BEGIN IMMEDIATE;
+         UPDATE schema_version SET version = version WHERE 0;
+         ROLLBACK;",
+    )
+    .map_err(|e| {
+        let _ = conn.execute_batch("ROLLBACK;");
+        anyhow::Error::new(e).context("database not writable")
+    })?;
+    Ok(())
+}
+
+/// A migration recorded in `schema_version`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: i32,
+    /// File name of the embedded migration (`None` if this binary does
+    /// not know the version, i.e. the DB was migrated by a newer Marlin).
+    pub name: Option<String>,
+    /// RFC 3339 timestamp; `None` for rows written before it was tracked.
+    pub applied_on: Option<String>,
+}
+
+/// One column as reported by `PRAGMA table_info`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub decl_type: String,
+    pub not_null: bool,
+    pub primary_key: bool,
+}
+
+/// A table or view in the Marlin schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableInfo {
+    pub name: String,
+    /// `table`, `view` or `virtual` (FTS5).
+    pub kind: String,
+    pub columns: Vec<ColumnInfo>,
+}
+
+/// Everything an external tool needs to check compatibility before
+/// using the raw connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaInfo {
+    pub current_version: i32,
+    pub expected_version: i32,
+    pub migrations: Vec<AppliedMigration>,
+    pub tables: Vec<TableInfo>,
+}
+
+impl SchemaInfo {
+    /// `true` when the DB is at exactly the schema this library expects.
+    pub fn is_compatible(&self) -> bool {
+        self.current_version == self.expected_version
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableInfo> {
+        self.tables.iter().find(|t| t.name == name)
+    }
+}
+
+/// Introspect the schema: versions, applied migrations and every user
+/// table/view with its columns. FTS5 shadow tables are left out.
+pub fn schema_info(conn: &Connection) -> Result<SchemaInfo> {
+    let mut stmt =
+        conn.prepare("SELECT version, applied_on FROM schema_version ORDER BY version")?;
+    let migrations = stmt
+        .query_map([], |r| {
+            let version: i32 = r.get(0)?;
+            Ok(AppliedMigration {
+                version,
+                name: MIGRATIONS
+                    .iter()
+                    .map(|(f, _)| *f)
+                    .find(|f| f.split('_').next().and_then(|n| n.parse().ok()) == Some(version))
+                    .map(str::to_string),
+                applied_on: r.get(1)?,
+            })
+        })?
+        .collect::<StdResult<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT name, type, IFNULL(sql, '') FROM sqlite_master
+          WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+          ORDER BY name",
+    )?;
+    let objects = stmt
+        .query_map([], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<StdResult<Vec<_>, _>>()?;
+    let virtuals: Vec<&str> = objects
+        .iter()
+        .filter(|(_, _, sql)| sql.to_uppercase().starts_with("CREATE VIRTUAL TABLE"))
+        .map(|(n, _, _)| n.as_str())
+        .collect();
+
+    let mut col_stmt =
+        conn.prepare(r#"SELECT name, type, "notnull", pk FROM pragma_table_info(?1)"#)?;
+    let mut tables = Vec::new();
+    for (name, kind, _) in &objects {
+        if virtuals
+            .iter()
+            .any(|v| name != v && name.starts_with(&format!("{v}_")))
+        {
+            continue;
+        }
+        let columns = col_stmt
+            .query_map([name], |r| {
+                Ok(ColumnInfo {
+                    name: r.get(0)?,
+                    decl_type: r.get(1)?,
+                    not_null: r.get(2)?,
+                    primary_key: r.get::<_, i64>(3)? > 0,
+                })
+            })?
+            .collect::<StdResult<Vec<_>, _>>()?;
+        tables.push(TableInfo {
+            name: name.clone(),
+            kind: if virtuals.contains(&name.as_str()) {
+                "virtual".into()
+            } else {
+                kind.clone()
+            },
+            columns,
+        });
+    }
+
+    Ok(SchemaInfo {
+        current_version: current_schema_version(conn)?,
+        expected_version: SCHEMA_VERSION,
+        migrations,
+        tables,
+    })
+}
+
 /* ─── connection bootstrap ────────────────────────────────────────── */
 
+/// Open (creating if needed) the index at `db_path` and run pending
+/// migrations.  A path on a network or FUSE filesystem still opens but
+/// logs a warning; see [`network_fs`].
 pub fn open<P: AsRef<Path>>(db_path: P) -> Result<Connection> {
     let db_path_ref = db_path.as_ref();
+    if let Some(fs) = network_fs(db_path_ref) {
+        warn!(
+            path = %db_path_ref.display(),
+            fs,
+            "database is on a network filesystem; SQLite's WAL journal is not safe there – keep the index on a local disk"
+        );
+    }
     let mut conn = Connection::open(db_path_ref)
         .with_context(|| format!("failed to open DB at {}", db_path_ref.display()))?;
+    trace::install(&mut conn);
 
-    conn.pragma_update(None, "journal_mode", "WAL")?;
+    // switching modes needs an exclusive lock, reading the mode does not
+    let mode: String = conn.query_row("PRAGMA journal_mode", [], |r| r.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+    }
     conn.pragma_update(None, "foreign_keys", "ON")?;
 
-    // Wait up to 30 s for a competing writer before giving up
+    // Wait up to 30 s for a competing writer before giving up.  Read-only
+    // work never waits: the migration fast path and the expiry check below
+    // take no write lock.
     conn.busy_timeout(std::time::Duration::from_secs(30))?;
 
     apply_migrations(&mut conn)?;
+    match tuning(&conn) {
+        Ok(preset) => preset.apply(&conn)?,
+        Err(e) => warn!(error = %e, "ignoring db.tuning, using SQLite defaults"),
+    }
+
+    // temporary markers should not outlive their TTL just because nothing
+    // swept them; failing here must not keep the index from opening
+    match expire_attrs(&conn, Utc::now().timestamp()) {
+        Ok(0) => {}
+        Ok(n) => debug!(removed = n, "expired attributes swept"),
+        Err(e) => warn!(error = %e, "could not sweep expired attributes"),
+    }
     Ok(conn)
 }
 
 /* ─── migration runner ────────────────────────────────────────────── */
 
 pub(crate) fn apply_migrations(conn: &mut Connection) -> Result<()> {
+    // Fast path: `user_version` lives in the file header, so an up-to-date
+    // index is confirmed without touching a table or taking the write lock.
+    let user_version: i32 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    if user_version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
     // Ensure schema_version bookkeeping table exists
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS schema_version (
@@ -128,7 +452,7 @@ pub(crate) fn apply_migrations(conn: &mut Connection) -> Result<()> {
 
         tx.execute(
             "INSERT INTO schema_version (version, applied_on) VALUES (?1, ?2)",
-            params![version, Local::now().to_rfc3339()],
+            params![version, Utc::now().to_rfc3339()],
         )?;
     }
 
@@ -150,74 +474,1185 @@ pub(crate) fn apply_migrations(conn: &mut Connection) -> Result<()> {
             missing.push(v);
         }
     }
-    if !missing.is_empty() {
-        warn!("migrations not applied: {:?}", missing);
+    if !missing.is_empty() {
+        warn!("migrations not applied: {:?}", missing);
+    }
+
+    let current = current_schema_version(conn)?;
+    if current != SCHEMA_VERSION {
+        anyhow::bail!(
+            "database schema version {} does not match library version {}",
+            current,
+            SCHEMA_VERSION
+        );
+    }
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+    Ok(())
+}
+
+/* ─── tag helpers ─────────────────────────────────────────────────── */
+
+/// Find or create every segment of `path`; returns the leaf's id.
+/// Rejected by a matching `validate.tag.*` rule (see [`crate::validate`]).
+pub fn ensure_tag_path(conn: &Connection, path: &str) -> Result<i64> {
+    crate::validate::check_tag(conn, path)?;
+    let m = Mutation::new("ensure_tag_path");
+    let mut created = 0;
+    let mut parent: Option<i64> = None;
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        created += conn.execute(
+            "INSERT OR IGNORE INTO tags(name, parent_id) VALUES (?1, ?2)",
+            params![segment, parent],
+        )?;
+        let id: i64 = conn.query_row(
+            "SELECT id FROM tags WHERE name = ?1 AND (parent_id IS ?2 OR parent_id = ?2)",
+            params![segment, parent],
+            |r| r.get(0),
+        )?;
+        parent = Some(id);
+    }
+    m.done(created);
+    parent.ok_or_else(|| anyhow::anyhow!("empty tag path"))
+}
+
+/// Look up an existing tag path without creating anything.
+pub fn find_tag_path(conn: &Connection, path: &str) -> Result<Option<i64>> {
+    let mut parent: Option<i64> = None;
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        let id: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM tags WHERE name = ?1 AND (parent_id IS ?2 OR parent_id = ?2)",
+                params![segment, parent],
+                |r| r.get(0),
+            )
+            .optional()?;
+        match id {
+            Some(id) => parent = Some(id),
+            None => return Ok(None),
+        }
+    }
+    Ok(parent)
+}
+
+/// `leaf` followed by its ancestors up to the root tag; tagging a file
+/// attaches all of them so `tag:foo` also finds files tagged `foo/bar`.
+pub fn tag_ancestry(conn: &Connection, leaf: i64) -> Result<Vec<i64>> {
+    let mut ids = Vec::new();
+    let mut current = Some(leaf);
+    while let Some(id) = current {
+        ids.push(id);
+        current = conn.query_row("SELECT parent_id FROM tags WHERE id = ?1", [id], |r| {
+            r.get::<_, Option<i64>>(0)
+        })?;
+    }
+    Ok(ids)
+}
+
+/// Outcome of [`attach_tag`] and [`detach_tag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagMerge {
+    /// The file gained or lost the tag.
+    Changed,
+    /// It already had (or lacked) it.
+    Unchanged,
+    /// A later opposite change wins; nothing was done.
+    Superseded,
+}
+
+/// Attach tag `tag_id` to a file as of `at` (Unix milliseconds).
+///
+/// Together with [`detach_tag`] this makes a file's tags a
+/// last-writer-wins set: a detach leaves a tombstone with its time, and
+/// attaches or detaches older than the latest opposite change are
+/// ignored, ties going to the attach.  Applying the same changes in any
+/// order, any number of times, gives the same tags – which is what
+/// replaying operation logs from several machines needs.
+pub fn attach_tag(conn: &Connection, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge> {
+    let m = Mutation::new("attach_tag").file_id(file_id);
+    let removed: Option<i64> = conn
+        .prepare_cached(
+            "SELECT removed_at FROM file_tag_tombstones WHERE file_id = ?1 AND tag_id = ?2",
+        )?
+        .query_row([file_id, tag_id], |r| r.get(0))
+        .optional()?;
+    if removed.is_some_and(|r| r > at) {
+        m.done(0);
+        return Ok(TagMerge::Superseded);
+    }
+    let added = tag_added_at(conn, file_id, tag_id)?;
+    if let Some(added) = added {
+        let mut rows = 0;
+        if added.is_none_or(|a| a < at) {
+            rows = stamp_tag(conn, file_id, tag_id, at)?;
+        }
+        m.done(rows);
+        return Ok(TagMerge::Unchanged);
+    }
+    // the insert trigger clears the tombstone and stamps "now"
+    let rows = conn
+        .prepare_cached("INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)")?
+        .execute([file_id, tag_id])?;
+    m.done(rows + stamp_tag(conn, file_id, tag_id, at)?);
+    Ok(TagMerge::Changed)
+}
+
+/// `Some(added_at)` if the file has the tag; `None` inside when it was
+/// attached before tags were stamped.
+fn tag_added_at(conn: &Connection, file_id: i64, tag_id: i64) -> Result<Option<Option<i64>>> {
+    Ok(conn
+        .prepare_cached(
+            "SELECT s.added_at
+               FROM file_tags t
+               LEFT JOIN file_tag_stamps s ON s.file_id = t.file_id AND s.tag_id = t.tag_id
+              WHERE t.file_id = ?1 AND t.tag_id = ?2",
+        )?
+        .query_row([file_id, tag_id], |r| r.get(0))
+        .optional()?)
+}
+
+fn stamp_tag(conn: &Connection, file_id: i64, tag_id: i64, at: i64) -> Result<usize> {
+    Ok(conn
+        .prepare_cached(
+            "INSERT OR REPLACE INTO file_tag_stamps(file_id, tag_id, added_at) VALUES (?1, ?2, ?3)",
+        )?
+        .execute([file_id, tag_id, at])?)
+}
+
+/// Detach tag `tag_id` from a file as of `at`; see [`attach_tag`].  The
+/// tombstone is kept even if the file did not have the tag, so an older
+/// attach arriving later stays undone.
+pub fn detach_tag(conn: &Connection, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge> {
+    let m = Mutation::new("detach_tag").file_id(file_id);
+    let added = tag_added_at(conn, file_id, tag_id)?;
+    if added.flatten().is_some_and(|a| a >= at) {
+        m.done(0);
+        return Ok(TagMerge::Superseded);
+    }
+    let removed: Option<i64> = conn
+        .prepare_cached(
+            "SELECT removed_at FROM file_tag_tombstones WHERE file_id = ?1 AND tag_id = ?2",
+        )?
+        .query_row([file_id, tag_id], |r| r.get(0))
+        .optional()?;
+    let mut rows = 0;
+    if added.is_some() {
+        rows = conn
+            .prepare_cached("DELETE FROM file_tags WHERE file_id = ?1 AND tag_id = ?2")?
+            .execute([file_id, tag_id])?;
+    }
+    // replaces the delete trigger's "now" with the time of this detach
+    let at = removed.map_or(at, |r| r.max(at));
+    conn.prepare_cached(
+        "INSERT INTO file_tag_tombstones(file_id, tag_id, removed_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(file_id, tag_id) DO UPDATE SET removed_at = excluded.removed_at",
+    )?
+    .execute([file_id, tag_id, at])?;
+    m.done(rows);
+    Ok(if added.is_some() {
+        TagMerge::Changed
+    } else {
+        TagMerge::Unchanged
+    })
+}
+
+/// The current time in Unix milliseconds, as [`attach_tag`] takes it.
+pub fn now_millis() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// Tag `files` with `tag_path` and its ancestors.  An unknown tag is
+/// created when `create` is set and an error otherwise, as is any locked
+/// file (see [`crate::lock`]).  Returns the number of files that gained
+/// a tag.
+pub fn tag_files(
+    conn: &Connection,
+    files: &[(i64, String)],
+    tag_path: &str,
+    create: bool,
+) -> Result<usize> {
+    let leaf = if create {
+        ensure_tag_path(conn, tag_path)?
+    } else {
+        find_tag_path(conn, tag_path)?
+            .ok_or_else(|| anyhow::anyhow!("unknown tag `{tag_path}` – pass --create to add it"))?
+    };
+    let tag_ids = tag_ancestry(conn, leaf)?;
+    crate::lock::check_files(conn, files)?;
+
+    let m = Mutation::new("tag_files");
+    let now = now_millis();
+    let mut count = 0;
+    for (fid, path) in files {
+        let mut newly = false;
+        for &tid in &tag_ids {
+            if attach_tag(conn, *fid, tid, now)? == TagMerge::Changed {
+                newly = true;
+            }
+        }
+        if newly {
+            info!(file = %path, tag = tag_path, "tagged");
+            count += 1;
+        }
+    }
+    Ok(m.done(count))
+}
+
+/// Full path of every tag (`project/alpha`), sorted.
+pub fn tag_paths(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        r#"WITH RECURSIVE tag_tree(id, path) AS (
+               SELECT id, name FROM tags WHERE parent_id IS NULL
+               UNION ALL
+               SELECT t.id, tt.path || '/' || t.name
+                 FROM tags t
+                 JOIN tag_tree tt ON t.parent_id = tt.id
+           )
+           SELECT path FROM tag_tree ORDER BY path"#,
+    )?;
+    let rows = stmt.query_map([], |r| r.get(0))?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/// Full paths of the tags on file `file_id`, sorted.
+pub fn file_tag_paths(conn: &Connection, file_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(
+        r#"WITH RECURSIVE tag_tree(id, path) AS (
+               SELECT id, name FROM tags WHERE parent_id IS NULL
+               UNION ALL
+               SELECT t.id, tt.path || '/' || t.name
+                 FROM tags t
+                 JOIN tag_tree tt ON t.parent_id = tt.id
+           )
+           SELECT tt.path FROM file_tags ft JOIN tag_tree tt ON tt.id = ft.tag_id
+            WHERE ft.file_id = ?1
+            ORDER BY tt.path"#,
+    )?;
+    let rows = stmt.query_map([file_id], |r| r.get(0))?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/// Whether `tags.strict` is on, i.e. tagging must not invent new tags.
+pub fn strict_tags(conn: &Connection) -> Result<bool> {
+    Ok(setting(conn, "tags.strict")?.as_deref() == Some("true"))
+}
+
+/// Delete tags no file carries, leaves first, so a parent goes once its
+/// last child has.  Returns the number of tags removed.
+pub fn prune_unused_tags(conn: &Connection) -> Result<usize> {
+    let m = Mutation::new("prune_unused_tags");
+    let mut total = 0;
+    loop {
+        let n = conn.execute(
+            "DELETE FROM tags
+              WHERE id NOT IN (SELECT tag_id FROM file_tags)
+                AND id NOT IN (SELECT parent_id FROM tags WHERE parent_id IS NOT NULL)",
+            [],
+        )?;
+        if n == 0 {
+            return Ok(m.done(total));
+        }
+        total += n;
+    }
+}
+
+/// Id of the file stored at `path`, if it is indexed.
+pub fn find_file_id(conn: &Connection, path: &str) -> Result<Option<i64>> {
+    Ok(conn
+        .prepare_cached("SELECT id FROM files WHERE path = ?1")?
+        .query_row([path], |r| r.get(0))
+        .optional()?)
+}
+
+pub fn file_id(conn: &Connection, path: &str) -> Result<i64> {
+    conn.query_row("SELECT id FROM files WHERE path = ?1", [path], |r| r.get(0))
+        .map_err(|_| anyhow::anyhow!("file not indexed: {}", path))
+}
+
+/// Stored path of the file with id `file_id`.
+pub fn file_path(conn: &Connection, file_id: i64) -> Result<String> {
+    conn.query_row("SELECT path FROM files WHERE id = ?1", [file_id], |r| {
+        r.get(0)
+    })
+    .with_context(|| format!("no indexed file with id {file_id}"))
+}
+
+/// Resolve a glob (see [`crate::globs`]) against the *indexed* paths.
+///
+/// Patterns containing a path separator are matched against the full stored
+/// path; bare patterns such as `*.md` only against the file name.
+pub fn match_files(conn: &Connection, pattern: &str) -> Result<Vec<(i64, String)>> {
+    let expanded = shellexpand::tilde(pattern).into_owned();
+    let pat = crate::globs::FileGlob::new(&expanded)?;
+    let full_path = expanded.contains(std::path::MAIN_SEPARATOR);
+
+    let mut stmt = conn.prepare("SELECT id, path FROM files ORDER BY path")?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        let (fid, path) = row?;
+        let is_match = if full_path {
+            pat.matches(&path)
+        } else {
+            Path::new(&path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| pat.matches(n))
+                .unwrap_or(false)
+        };
+        if is_match {
+            out.push((fid, path));
+        }
+    }
+    Ok(out)
+}
+
+/* ─── attributes ──────────────────────────────────────────────────── */
+
+/// Set attribute `key` on a file.  Values a `validate.attr.*` rule
+/// rejects are an error (see [`crate::validate`]), as are locked files
+/// (see [`crate::lock`]).
+pub fn upsert_attr(conn: &Connection, file_id: i64, key: &str, value: &str) -> Result<()> {
+    upsert_attr_expiring(conn, file_id, key, value, None)
+}
+
+/// Like [`upsert_attr`], but the value disappears once the Unix time
+/// `expires_at` has passed (`None` makes it permanent again).
+pub fn upsert_attr_expiring(
+    conn: &Connection,
+    file_id: i64,
+    key: &str,
+    value: &str,
+    expires_at: Option<i64>,
+) -> Result<()> {
+    crate::validate::check_attr(conn, key, value)?;
+    crate::lock::check(conn, file_id)?;
+    let m = Mutation::new("upsert_attr").file_id(file_id);
+    m.done(conn.execute(
+        r#"
+        INSERT INTO attributes(file_id, key, value, expires_at)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(file_id, key) DO UPDATE
+           SET value = excluded.value, expires_at = excluded.expires_at
+        "#,
+        params![file_id, key, value, expires_at],
+    )?);
+    Ok(())
+}
+
+/// Set `key=value` on every file in `files`; see [`upsert_attr_expiring`].
+/// Nothing is set if any of them is locked.
+pub fn attr_set_files(
+    conn: &Connection,
+    files: &[(i64, String)],
+    key: &str,
+    value: &str,
+    expires_at: Option<i64>,
+) -> Result<usize> {
+    crate::lock::check_files(conn, files)?;
+    let m = Mutation::new("attr_set_files");
+    for (fid, path) in files {
+        upsert_attr_expiring(conn, *fid, key, value, expires_at)?;
+        info!(file = %path, key, value, "attr set");
+    }
+    Ok(m.done(files.len()))
+}
+
+/// The value of attribute `key` on a file, if set.
+pub fn attr_value(conn: &Connection, file_id: i64, key: &str) -> Result<Option<String>> {
+    Ok(conn
+        .prepare_cached("SELECT value FROM attributes WHERE file_id = ?1 AND key = ?2")?
+        .query_row(params![file_id, key], |r| r.get::<_, Option<String>>(0))
+        .optional()?
+        .flatten())
+}
+
+/// `(key, value, expires_at)` of every attribute on a file, by key.
+pub fn file_attrs(conn: &Connection, file_id: i64) -> Result<Vec<(String, String, Option<i64>)>> {
+    let mut stmt = conn
+        .prepare("SELECT key, value, expires_at FROM attributes WHERE file_id = ?1 ORDER BY key")?;
+    let rows = stmt.query_map([file_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/// Delete attributes whose expiry is at or before the Unix time `now`.
+/// Returns the number of values removed.
+pub fn expire_attrs(conn: &Connection, now: i64) -> Result<usize> {
+    // a DELETE takes the write lock even when it matches nothing; look first
+    let due: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM attributes
+                        WHERE expires_at IS NOT NULL AND expires_at <= ?1)",
+        [now],
+        |r| r.get(0),
+    )?;
+    if !due {
+        return Ok(0);
+    }
+    let m = Mutation::new("expire_attrs");
+    Ok(m.done(conn.execute(
+        "DELETE FROM attributes WHERE expires_at IS NOT NULL AND expires_at <= ?1",
+        [now],
+    )?))
+}
+
+/* ─── rating & favorites ──────────────────────────────────────────── */
+
+/// Set the 0–5 star rating of a file.
+pub fn set_rating(conn: &Connection, file_id: i64, rating: u8) -> Result<()> {
+    if rating > 5 {
+        anyhow::bail!("rating must be between 0 and 5, got {}", rating);
+    }
+    crate::lock::check(conn, file_id)?;
+    let m = Mutation::new("set_rating").file_id(file_id);
+    m.done(conn.execute(
+        "UPDATE files SET rating = ?1 WHERE id = ?2",
+        params![rating, file_id],
+    )?);
+    Ok(())
+}
+
+/// Mark or unmark a file as favorite.
+pub fn set_favorite(conn: &Connection, file_id: i64, favorite: bool) -> Result<()> {
+    crate::lock::check(conn, file_id)?;
+    let m = Mutation::new("set_favorite").file_id(file_id);
+    m.done(conn.execute(
+        "UPDATE files SET favorite = ?1 WHERE id = ?2",
+        params![favorite, file_id],
+    )?);
+    Ok(())
+}
+
+pub fn list_favorites(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT path FROM files WHERE favorite = 1 ORDER BY path")?;
+    let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+    let list = rows.collect::<StdResult<Vec<_>, _>>()?;
+    Ok(list)
+}
+
+/* ─── first indexed / last seen ───────────────────────────────────── */
+
+/// When a scan first indexed file `file_id` and when one last found it
+/// on disk, as Unix seconds.  `None` where unknown: files indexed before
+/// these were recorded have no first-indexed time.
+pub fn seen_times(conn: &Connection, file_id: i64) -> Result<(Option<i64>, Option<i64>)> {
+    Ok(conn.query_row(
+        "SELECT first_indexed_at, last_seen_at FROM files WHERE id = ?1",
+        [file_id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?)
+}
+
+/* ─── reminders ───────────────────────────────────────────────────── */
+
+/// One row of `reminders`, with the path of its file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reminder {
+    pub id: i64,
+    pub file_id: i64,
+    pub path: String,
+    /// Unix seconds
+    pub due_at: i64,
+    pub note: String,
+    /// When a notification for it went out, if one did.
+    pub notified_at: Option<i64>,
+}
+
+/// Add a reminder on file `file_id`, due at `due_at` (Unix seconds).
+/// Returns its id.
+pub fn add_reminder(conn: &Connection, file_id: i64, due_at: i64, note: &str) -> Result<i64> {
+    if note.trim().is_empty() {
+        anyhow::bail!("reminder note is empty");
+    }
+    let m = Mutation::new("add_reminder").file_id(file_id);
+    m.done(conn.execute(
+        "INSERT INTO reminders(file_id, due_at, note, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![file_id, due_at, note.trim(), Utc::now().timestamp()],
+    )?);
+    Ok(conn.last_insert_rowid())
+}
+
+const REMINDER_COLUMNS: &str = "SELECT r.id, r.file_id, f.path, r.due_at, r.note, r.notified_at
+       FROM reminders r JOIN files f ON f.id = r.file_id";
+
+fn reminders_where(conn: &Connection, clause: &str, arg: Option<i64>) -> Result<Vec<Reminder>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "{REMINDER_COLUMNS} WHERE {clause} ORDER BY r.due_at, r.id"
+    ))?;
+    let rows = stmt.query_map(params![arg], |r| {
+        Ok(Reminder {
+            id: r.get(0)?,
+            file_id: r.get(1)?,
+            path: r.get(2)?,
+            due_at: r.get(3)?,
+            note: r.get(4)?,
+            notified_at: r.get(5)?,
+        })
+    })?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/// Every reminder, or only those on file `file_id`, soonest first.
+pub fn list_reminders(conn: &Connection, file_id: Option<i64>) -> Result<Vec<Reminder>> {
+    reminders_where(conn, "(?1 IS NULL OR r.file_id = ?1)", file_id)
+}
+
+/// Reminders due before `before` (Unix seconds), delivered or not,
+/// soonest first.
+pub fn due_reminders(conn: &Connection, before: i64) -> Result<Vec<Reminder>> {
+    reminders_where(conn, "r.due_at < ?1", Some(before))
+}
+
+/// Reminders due at `now` or earlier that nobody was notified of yet,
+/// oldest first.
+pub fn pending_reminders(conn: &Connection, now: i64) -> Result<Vec<Reminder>> {
+    reminders_where(conn, "r.notified_at IS NULL AND r.due_at <= ?1", Some(now))
+}
+
+/// Record that reminder `id` was delivered at `at`.
+pub fn mark_reminded(conn: &Connection, id: i64, at: i64) -> Result<()> {
+    let m = Mutation::new("mark_reminded");
+    m.done(conn.execute(
+        "UPDATE reminders SET notified_at = ?2 WHERE id = ?1",
+        params![id, at],
+    )?);
+    Ok(())
+}
+
+/* ─── workflow states ─────────────────────────────────────────────── */
+
+/// One entry of a file's state history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateChange {
+    pub path: String,
+    pub state: String,
+    /// Unix seconds
+    pub changed_at: i64,
+}
+
+fn check_state_name(state: &str) -> Result<()> {
+    if state.is_empty() || state.chars().any(char::is_whitespace) {
+        anyhow::bail!("invalid state name `{state}` (no spaces, not empty)");
+    }
+    Ok(())
+}
+
+/// Allow files to move from state `from` to state `to`.  Returns whether
+/// the transition is new.
+pub fn add_state_transition(conn: &Connection, from: &str, to: &str) -> Result<bool> {
+    check_state_name(from)?;
+    check_state_name(to)?;
+    let m = Mutation::new("add_state_transition");
+    Ok(m.done(conn.execute(
+        "INSERT OR IGNORE INTO state_transitions(from_state, to_state) VALUES (?1, ?2)",
+        [from, to],
+    )?) > 0)
+}
+
+/// Every allowed transition, sorted.
+pub fn state_transitions(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT from_state, to_state FROM state_transitions ORDER BY from_state, to_state",
+    )?;
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/// Current state of file `file_id`, if it ever got one.
+pub fn file_state(conn: &Connection, file_id: i64) -> Result<Option<String>> {
+    Ok(conn
+        .prepare_cached(
+            "SELECT state FROM file_states WHERE file_id = ?1 ORDER BY id DESC LIMIT 1",
+        )?
+        .query_row([file_id], |r| r.get(0))
+        .optional()?)
+}
+
+/// Move `files` to `state`.  Once transitions are defined, a file that
+/// already has a state may only take a transition leading from it to
+/// `state`; every file is checked (as are locks, see [`crate::lock`])
+/// before any is changed.  Files already in `state` are left alone.
+/// Returns how many changed.
+pub fn set_file_states(conn: &Connection, files: &[(i64, String)], state: &str) -> Result<usize> {
+    check_state_name(state)?;
+    crate::lock::check_files(conn, files)?;
+    let restricted: bool =
+        conn.query_row("SELECT EXISTS(SELECT 1 FROM state_transitions)", [], |r| {
+            r.get(0)
+        })?;
+    let mut moves = Vec::new();
+    for (id, path) in files {
+        let current = file_state(conn, *id)?;
+        match current.as_deref() {
+            Some(cur) if cur == state => continue,
+            Some(cur) if restricted => {
+                let allowed: bool = conn.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM state_transitions
+                                    WHERE from_state = ?1 AND to_state = ?2)",
+                    [cur, state],
+                    |r| r.get(0),
+                )?;
+                if !allowed {
+                    anyhow::bail!(
+                        "{path}: no transition from `{cur}` to `{state}` \
+                         (add one with `marlin state transitions-add {cur} {state}`)"
+                    );
+                }
+            }
+            _ => {}
+        }
+        moves.push(*id);
+    }
+    let m = Mutation::new("set_file_states");
+    let now = Utc::now().timestamp();
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO file_states(file_id, state, changed_at) VALUES (?1, ?2, ?3)",
+    )?;
+    let mut rows = 0;
+    for id in &moves {
+        rows += stmt.execute(params![id, state, now])?;
+    }
+    Ok(m.done(rows))
+}
+
+/// State history of `files`, by path and then oldest first.
+pub fn state_log(conn: &Connection, files: &[(i64, String)]) -> Result<Vec<StateChange>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT state, changed_at FROM file_states WHERE file_id = ?1 ORDER BY id",
+    )?;
+    let mut files = files.to_vec();
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    let mut log = Vec::new();
+    for (id, path) in &files {
+        let rows = stmt.query_map([id], |r| {
+            Ok(StateChange {
+                path: path.clone(),
+                state: r.get(0)?,
+                changed_at: r.get(1)?,
+            })
+        })?;
+        for row in rows {
+            log.push(row?);
+        }
+    }
+    Ok(log)
+}
+
+/* ─── tasks ───────────────────────────────────────────────────────── */
+
+/// Whether a task still needs doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Open,
+    Done,
+}
+
+impl TaskStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::Open => "open",
+            TaskStatus::Done => "done",
+        }
+    }
+}
+
+impl std::str::FromStr for TaskStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" => Ok(TaskStatus::Open),
+            "done" => Ok(TaskStatus::Done),
+            _ => anyhow::bail!("invalid task status `{s}` (expected open or done)"),
+        }
+    }
+}
+
+/// One row of `tasks`, with the path of its file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub id: i64,
+    pub file_id: i64,
+    pub path: String,
+    pub title: String,
+    pub status: TaskStatus,
+    /// Unix seconds
+    pub due_at: Option<i64>,
+    pub priority: i64,
+    pub created_at: i64,
+    pub done_at: Option<i64>,
+}
+
+/// Which tasks [`list_tasks`] returns; the default is every task.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskFilter {
+    pub status: Option<TaskStatus>,
+    /// Only tasks due before this Unix second.
+    pub due_before: Option<i64>,
+    /// Only tasks on these files.
+    pub file_ids: Option<Vec<i64>>,
+}
+
+/// Add a task to file `file_id`.  Returns its id.
+pub fn add_task(
+    conn: &Connection,
+    file_id: i64,
+    title: &str,
+    due_at: Option<i64>,
+    priority: i64,
+) -> Result<i64> {
+    if title.trim().is_empty() {
+        anyhow::bail!("task title is empty");
+    }
+    let m = Mutation::new("add_task").file_id(file_id);
+    m.done(conn.execute(
+        "INSERT INTO tasks(file_id, title, due_at, priority, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            file_id,
+            title.trim(),
+            due_at,
+            priority,
+            Utc::now().timestamp()
+        ],
+    )?);
+    Ok(conn.last_insert_rowid())
+}
+
+/// Tasks matching `filter`: open ones first, then by due date (undated
+/// last), priority (highest first) and id.
+pub fn list_tasks(conn: &Connection, filter: &TaskFilter) -> Result<Vec<Task>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT t.id, t.file_id, f.path, t.title, t.status, t.due_at, t.priority,
+                t.created_at, t.done_at
+           FROM tasks t JOIN files f ON f.id = t.file_id
+          WHERE (?1 IS NULL OR t.status = ?1)
+            AND (?2 IS NULL OR t.due_at < ?2)
+          ORDER BY t.status = 'done', t.due_at IS NULL, t.due_at, t.priority DESC, t.id",
+    )?;
+    let status = filter.status.map(TaskStatus::as_str);
+    let rows = stmt.query_map(params![status, filter.due_before], |r| {
+        let status: String = r.get(4)?;
+        Ok(Task {
+            id: r.get(0)?,
+            file_id: r.get(1)?,
+            path: r.get(2)?,
+            title: r.get(3)?,
+            status: if status == "done" {
+                TaskStatus::Done
+            } else {
+                TaskStatus::Open
+            },
+            due_at: r.get(5)?,
+            priority: r.get(6)?,
+            created_at: r.get(7)?,
+            done_at: r.get(8)?,
+        })
+    })?;
+    let mut tasks = rows.collect::<StdResult<Vec<_>, _>>()?;
+    if let Some(ids) = &filter.file_ids {
+        tasks.retain(|t| ids.contains(&t.file_id));
+    }
+    Ok(tasks)
+}
+
+/// Mark task `id` done at `at`.  Returns `false` when it already was;
+/// fails for an unknown id.
+pub fn complete_task(conn: &Connection, id: i64, at: i64) -> Result<bool> {
+    let status: Option<String> = conn
+        .query_row("SELECT status FROM tasks WHERE id = ?1", [id], |r| r.get(0))
+        .optional()?;
+    match status.as_deref() {
+        None => anyhow::bail!("no task #{id}"),
+        Some("done") => Ok(false),
+        Some(_) => {
+            let m = Mutation::new("complete_task");
+            m.done(conn.execute(
+                "UPDATE tasks SET status = 'done', done_at = ?2 WHERE id = ?1",
+                params![id, at],
+            )?);
+            Ok(true)
+        }
+    }
+}
+
+/// Delete task `id`; fails for an unknown id.
+pub fn remove_task(conn: &Connection, id: i64) -> Result<()> {
+    let m = Mutation::new("remove_task");
+    if m.done(conn.execute("DELETE FROM tasks WHERE id = ?1", [id])?) == 0 {
+        anyhow::bail!("no task #{id}");
+    }
+    Ok(())
+}
+
+/* ─── annotations ─────────────────────────────────────────────────── */
+
+/// The part of a file an annotation is about.  Both ends are inclusive;
+/// lines count from 1, bytes from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationRange {
+    Lines(u64, u64),
+    Bytes(u64, u64),
+}
+
+impl AnnotationRange {
+    /// `lines` or `bytes`, as stored.
+    pub fn unit(self) -> &'static str {
+        match self {
+            AnnotationRange::Lines(..) => "lines",
+            AnnotationRange::Bytes(..) => "bytes",
+        }
+    }
+
+    pub fn bounds(self) -> (u64, u64) {
+        match self {
+            AnnotationRange::Lines(a, b) | AnnotationRange::Bytes(a, b) => (a, b),
+        }
+    }
+}
+
+/// `12`, `12-20` and `lines=12-20` are lines; `bytes=100-200` is bytes.
+impl std::str::FromStr for AnnotationRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (unit, span) = match s.split_once('=') {
+            Some((unit, span)) => (unit.trim(), span.trim()),
+            None => ("lines", s.trim()),
+        };
+        let num = |n: &str| {
+            n.trim()
+                .parse::<u64>()
+                .with_context(|| format!("invalid range `{s}` (expected N, N-M or bytes=N-M)"))
+        };
+        let (start, end) = match span.split_once('-') {
+            Some((a, b)) => (num(a)?, num(b)?),
+            None => (num(span)?, num(span)?),
+        };
+        if start > end {
+            anyhow::bail!("invalid range `{s}`: {start} is past {end}");
+        }
+        match unit.to_ascii_lowercase().as_str() {
+            "lines" | "line" => {
+                if start == 0 {
+                    anyhow::bail!("invalid range `{s}`: lines count from 1");
+                }
+                Ok(AnnotationRange::Lines(start, end))
+            }
+            "bytes" | "byte" => Ok(AnnotationRange::Bytes(start, end)),
+            _ => anyhow::bail!("invalid range unit `{unit}` (expected lines or bytes)"),
+        }
+    }
+}
+
+impl std::fmt::Display for AnnotationRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            AnnotationRange::Lines(a, b) if a == b => write!(f, "line {a}"),
+            AnnotationRange::Lines(a, b) => write!(f, "lines {a}-{b}"),
+            AnnotationRange::Bytes(a, b) => write!(f, "bytes {a}-{b}"),
+        }
+    }
+}
+
+/// One row of `annotations`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub id: i64,
+    pub file_id: i64,
+    pub note: String,
+    /// `None` when the note is about the whole file.
+    pub range: Option<AnnotationRange>,
+    pub highlight: bool,
+    /// Unix seconds
+    pub created_at: i64,
+}
+
+/// Annotate file `file_id`.  Returns the annotation's id.
+pub fn add_annotation(
+    conn: &Connection,
+    file_id: i64,
+    note: &str,
+    range: Option<AnnotationRange>,
+    highlight: bool,
+) -> Result<i64> {
+    if note.trim().is_empty() {
+        anyhow::bail!("annotation note is empty");
+    }
+    let (start, end) = match range.map(AnnotationRange::bounds) {
+        Some((a, b)) => (Some(a as i64), Some(b as i64)),
+        None => (None, None),
+    };
+    let m = Mutation::new("add_annotation").file_id(file_id);
+    m.done(conn.execute(
+        "INSERT INTO annotations(file_id, note, range_unit, range_start, range_end,
+                                 highlight, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            file_id,
+            note.trim(),
+            range.map(AnnotationRange::unit),
+            start,
+            end,
+            highlight,
+            Utc::now().timestamp()
+        ],
+    )?);
+    Ok(conn.last_insert_rowid())
+}
+
+/// The annotations on file `file_id`: whole-file notes first, then by
+/// where their range starts.
+pub fn annotations(conn: &Connection, file_id: i64) -> Result<Vec<Annotation>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, file_id, note, range_unit, range_start, range_end, highlight, created_at
+           FROM annotations WHERE file_id = ?1
+          ORDER BY range_unit IS NOT NULL, range_unit, range_start, range_end, id",
+    )?;
+    let rows = stmt.query_map([file_id], |r| {
+        let unit: Option<String> = r.get(3)?;
+        let (a, b): (i64, i64) = match unit {
+            Some(_) => (r.get(4)?, r.get(5)?),
+            None => (0, 0),
+        };
+        Ok(Annotation {
+            id: r.get(0)?,
+            file_id: r.get(1)?,
+            note: r.get(2)?,
+            range: match unit.as_deref() {
+                Some("lines") => Some(AnnotationRange::Lines(a as u64, b as u64)),
+                Some(_) => Some(AnnotationRange::Bytes(a as u64, b as u64)),
+                None => None,
+            },
+            highlight: r.get::<_, i64>(6)? != 0,
+            created_at: r.get(7)?,
+        })
+    })?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/* ─── calendar events ─────────────────────────────────────────────── */
+
+/// One row of `events` – a dated event linked to a file – with the path
+/// of its file.  (Not to be confused with the progress events of
+/// [`crate::events`].)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEvent {
+    pub id: i64,
+    pub file_id: i64,
+    pub path: String,
+    /// Unix seconds
+    pub start_at: i64,
+    pub end_at: Option<i64>,
+    pub description: String,
+}
+
+/// Which events [`list_events`] returns; the default is every event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// Only events that have not ended before this Unix second.
+    pub from: Option<i64>,
+    /// Only events starting before this Unix second.
+    pub to: Option<i64>,
+    /// Only events on these files.
+    pub file_ids: Option<Vec<i64>>,
+}
+
+/// Link an event from `start_at` (to `end_at`, if given) to file
+/// `file_id`.  Returns its id.
+pub fn add_event(
+    conn: &Connection,
+    file_id: i64,
+    start_at: i64,
+    end_at: Option<i64>,
+    description: &str,
+) -> Result<i64> {
+    if description.trim().is_empty() {
+        anyhow::bail!("event description is empty");
+    }
+    if end_at.is_some_and(|end| end < start_at) {
+        anyhow::bail!("event ends before it starts");
     }
+    let m = Mutation::new("add_event").file_id(file_id);
+    m.done(conn.execute(
+        "INSERT INTO events(file_id, start_at, end_at, description, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            file_id,
+            start_at,
+            end_at,
+            description.trim(),
+            Utc::now().timestamp()
+        ],
+    )?);
+    Ok(conn.last_insert_rowid())
+}
 
-    let current = current_schema_version(conn)?;
-    if current != SCHEMA_VERSION {
-        anyhow::bail!(
-            "database schema version {} does not match library version {}",
-            current,
-            SCHEMA_VERSION
-        );
+/// Events matching `filter`, in date order (events sharing a start, end
+/// and description are next to each other, by path).
+pub fn list_events(conn: &Connection, filter: &EventFilter) -> Result<Vec<FileEvent>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT e.id, e.file_id, f.path, e.start_at, e.end_at, e.description
+           FROM events e JOIN files f ON f.id = e.file_id
+          WHERE (?1 IS NULL OR COALESCE(e.end_at, e.start_at) >= ?1)
+            AND (?2 IS NULL OR e.start_at < ?2)
+          ORDER BY e.start_at, e.end_at, e.description, f.path, e.id",
+    )?;
+    let rows = stmt.query_map(params![filter.from, filter.to], |r| {
+        Ok(FileEvent {
+            id: r.get(0)?,
+            file_id: r.get(1)?,
+            path: r.get(2)?,
+            start_at: r.get(3)?,
+            end_at: r.get(4)?,
+            description: r.get(5)?,
+        })
+    })?;
+    let mut events = rows.collect::<StdResult<Vec<_>, _>>()?;
+    if let Some(ids) = &filter.file_ids {
+        events.retain(|e| ids.contains(&e.file_id));
     }
+    Ok(events)
+}
 
-    Ok(())
+/* ─── file versions ───────────────────────────────────────────────── */
+
+/// One row of `file_versions`, without its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileVersion {
+    pub id: i64,
+    pub file_id: i64,
+    /// 1 for the first snapshot of the file, then 2, 3, …
+    pub version: i64,
+    /// SHA-256 of the content, hex
+    pub hash: String,
+    pub size: i64,
+    pub mtime: Option<i64>,
+    /// Whether the content itself was stored.
+    pub has_content: bool,
+    pub created_at: i64,
 }
 
-/* ─── tag helpers ─────────────────────────────────────────────────── */
+/// Record the next version of file `file_id`; `content` is stored as
+/// given (see [`crate::versions`] for the compression).  Returns the new
+/// version number.
+pub fn add_version(
+    conn: &Connection,
+    file_id: i64,
+    hash: &str,
+    size: i64,
+    mtime: Option<i64>,
+    content: Option<&[u8]>,
+) -> Result<i64> {
+    let version: i64 = conn.query_row(
+        "SELECT IFNULL(MAX(version), 0) + 1 FROM file_versions WHERE file_id = ?1",
+        [file_id],
+        |r| r.get(0),
+    )?;
+    let m = Mutation::new("add_version").file_id(file_id);
+    m.done(conn.execute(
+        "INSERT INTO file_versions(file_id, version, hash, size, mtime, content, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            file_id,
+            version,
+            hash,
+            size,
+            mtime,
+            content,
+            Utc::now().timestamp()
+        ],
+    )?);
+    Ok(version)
+}
 
-pub fn ensure_tag_path(conn: &Connection, path: &str) -> Result<i64> {
-    let mut parent: Option<i64> = None;
-    for segment in path.split('/').filter(|s| !s.is_empty()) {
-        conn.execute(
-            "INSERT OR IGNORE INTO tags(name, parent_id) VALUES (?1, ?2)",
-            params![segment, parent],
-        )?;
-        let id: i64 = conn.query_row(
-            "SELECT id FROM tags WHERE name = ?1 AND (parent_id IS ?2 OR parent_id = ?2)",
-            params![segment, parent],
+/// Every version of file `file_id`, oldest first.
+pub fn versions(conn: &Connection, file_id: i64) -> Result<Vec<FileVersion>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, file_id, version, hash, size, mtime, content IS NOT NULL, created_at
+           FROM file_versions WHERE file_id = ?1 ORDER BY version",
+    )?;
+    let rows = stmt.query_map([file_id], |r| {
+        Ok(FileVersion {
+            id: r.get(0)?,
+            file_id: r.get(1)?,
+            version: r.get(2)?,
+            hash: r.get(3)?,
+            size: r.get(4)?,
+            mtime: r.get(5)?,
+            has_content: r.get(6)?,
+            created_at: r.get(7)?,
+        })
+    })?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/// The stored (compressed) content of `version` of file `file_id`:
+/// `None` when only its hash was recorded; fails for an unknown version.
+pub fn version_content(conn: &Connection, file_id: i64, version: i64) -> Result<Option<Vec<u8>>> {
+    let content = conn
+        .query_row(
+            "SELECT content FROM file_versions WHERE file_id = ?1 AND version = ?2",
+            params![file_id, version],
             |r| r.get(0),
-        )?;
-        parent = Some(id);
+        )
+        .optional()?;
+    match content {
+        Some(content) => Ok(content),
+        None => anyhow::bail!("no version {version} of {}", file_path(conn, file_id)?),
     }
-    parent.ok_or_else(|| anyhow::anyhow!("empty tag path"))
 }
 
-pub fn file_id(conn: &Connection, path: &str) -> Result<i64> {
-    conn.query_row("SELECT id FROM files WHERE path = ?1", [path], |r| r.get(0))
-        .map_err(|_| anyhow::anyhow!("file not indexed: {}", path))
-}
+/* ─── colour labels ───────────────────────────────────────────────── */
 
-/* ─── attributes ──────────────────────────────────────────────────── */
+/// Attach an (already normalised) colour label to a file.
+pub fn add_label(conn: &Connection, file_id: i64, label: &str) -> Result<()> {
+    crate::lock::check(conn, file_id)?;
+    let m = Mutation::new("add_label").file_id(file_id);
+    m.done(conn.execute(
+        "INSERT OR IGNORE INTO file_labels(file_id, label) VALUES (?1, ?2)",
+        params![file_id, label],
+    )?);
+    Ok(())
+}
 
-pub fn upsert_attr(conn: &Connection, file_id: i64, key: &str, value: &str) -> Result<()> {
-    conn.execute(
-        r#"
-        INSERT INTO attributes(file_id, key, value)
-        VALUES (?1, ?2, ?3)
-        ON CONFLICT(file_id, key) DO UPDATE SET value = excluded.value
-        "#,
-        params![file_id, key, value],
-    )?;
+pub fn remove_label(conn: &Connection, file_id: i64, label: &str) -> Result<()> {
+    crate::lock::check(conn, file_id)?;
+    let m = Mutation::new("remove_label").file_id(file_id);
+    m.done(conn.execute(
+        "DELETE FROM file_labels WHERE file_id = ?1 AND label = ?2",
+        params![file_id, label],
+    )?);
     Ok(())
 }
 
+pub fn file_labels(conn: &Connection, file_id: i64) -> Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT label FROM file_labels WHERE file_id = ?1 ORDER BY label")?;
+    let rows = stmt.query_map([file_id], |r| r.get::<_, String>(0))?;
+    let list = rows.collect::<StdResult<Vec<_>, _>>()?;
+    Ok(list)
+}
+
 /* ─── links ───────────────────────────────────────────────────────── */
 
+/// Link two files.  A link is part of the metadata of both ends, so
+/// neither may be locked.
 pub fn add_link(
     conn: &Connection,
     src_file_id: i64,
     dst_file_id: i64,
     link_type: Option<&str>,
 ) -> Result<()> {
-    conn.execute(
+    crate::lock::check(conn, src_file_id)?;
+    crate::lock::check(conn, dst_file_id)?;
+    let m = Mutation::new("add_link").file_id(src_file_id);
+    m.done(conn.execute(
         "INSERT INTO links(src_file_id, dst_file_id, type)
          VALUES (?1, ?2, ?3)
          ON CONFLICT(src_file_id, dst_file_id, type) DO NOTHING",
         params![src_file_id, dst_file_id, link_type],
-    )?;
+    )?);
     Ok(())
 }
 
@@ -227,22 +1662,55 @@ pub fn remove_link(
     dst_file_id: i64,
     link_type: Option<&str>,
 ) -> Result<()> {
-    conn.execute(
+    crate::lock::check(conn, src_file_id)?;
+    crate::lock::check(conn, dst_file_id)?;
+    let m = Mutation::new("remove_link").file_id(src_file_id);
+    m.done(conn.execute(
         "DELETE FROM links
          WHERE src_file_id = ?1
            AND dst_file_id = ?2
            AND (type IS ?3 OR type = ?3)",
         params![src_file_id, dst_file_id, link_type],
-    )?;
+    )?);
     Ok(())
 }
 
+/// `(path, other end, type)` of the links of files whose path matches
+/// `pattern` (`*` wildcards); see [`link_details`].
 pub fn list_links(
     conn: &Connection,
     pattern: &str,
     direction: Option<&str>,
     link_type: Option<&str>,
 ) -> Result<Vec<(String, String, Option<String>)>> {
+    Ok(link_details(conn, pattern, direction, link_type)?
+        .into_iter()
+        .map(|l| (l.path, l.other, l.link_type))
+        .collect())
+}
+
+/// A link as seen from one of its ends, with its metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkInfo {
+    pub id: i64,
+    /// The end that matched the pattern.
+    pub path: String,
+    /// The far end.
+    pub other: String,
+    pub link_type: Option<String>,
+    /// `(key, value)` pairs, by key.
+    pub attrs: Vec<(String, String)>,
+}
+
+/// The links of files whose path matches `pattern` (`*` wildcards):
+/// outgoing ones, or incoming with `direction` `in`; only those of
+/// `link_type` if given.
+pub fn link_details(
+    conn: &Connection,
+    pattern: &str,
+    direction: Option<&str>,
+    link_type: Option<&str>,
+) -> Result<Vec<LinkInfo>> {
     let like_pattern = pattern.replace('*', "%");
 
     // Files matching pattern
@@ -253,35 +1721,123 @@ pub fn list_links(
         })?
         .collect::<StdResult<Vec<_>, _>>()?;
 
+    let (src_col, dst_col) = match direction {
+        Some("in") => ("dst_file_id", "src_file_id"),
+        _ => ("src_file_id", "dst_file_id"),
+    };
+    let sql = format!(
+        "SELECT l.id, f2.path, l.type
+           FROM links l
+           JOIN files f2 ON f2.id = l.{dst_col}
+          WHERE l.{src_col} = ?1
+            AND (?2 IS NULL OR l.type = ?2)",
+    );
+    let mut stmt2 = conn.prepare(&sql)?;
+
     let mut out = Vec::new();
     for (fid, fpath) in rows {
-        let (src_col, dst_col) = match direction {
-            Some("in") => ("dst_file_id", "src_file_id"),
-            _ => ("src_file_id", "dst_file_id"),
-        };
-
-        let sql = format!(
-            "SELECT f2.path, l.type
-               FROM links l
-               JOIN files f2 ON f2.id = l.{dst_col}
-              WHERE l.{src_col} = ?1
-                AND (?2 IS NULL OR l.type = ?2)",
-        );
-
-        let mut stmt2 = conn.prepare(&sql)?;
         let links = stmt2
             .query_map(params![fid, link_type], |r| {
-                Ok((r.get::<_, String>(0)?, r.get::<_, Option<String>>(1)?))
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, String>(1)?,
+                    r.get::<_, Option<String>>(2)?,
+                ))
             })?
             .collect::<StdResult<Vec<_>, _>>()?;
 
-        for (other, typ) in links {
-            out.push((fpath.clone(), other, typ));
+        for (id, other, typ) in links {
+            out.push(LinkInfo {
+                id,
+                path: fpath.clone(),
+                other,
+                link_type: typ,
+                attrs: link_attrs(conn, id)?,
+            });
         }
     }
     Ok(out)
 }
 
+/// Id of the link from `src_file_id` to `dst_file_id` of `link_type`.
+/// Without a type this is the only link between them, or else the
+/// untyped one.
+pub fn find_link(
+    conn: &Connection,
+    src_file_id: i64,
+    dst_file_id: i64,
+    link_type: Option<&str>,
+) -> Result<i64> {
+    let mut stmt = conn.prepare(
+        "SELECT id, type FROM links
+          WHERE src_file_id = ?1 AND dst_file_id = ?2 AND (?3 IS NULL OR type = ?3)",
+    )?;
+    let links = stmt
+        .query_map(params![src_file_id, dst_file_id, link_type], |r| {
+            Ok((r.get::<_, i64>(0)?, r.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<StdResult<Vec<_>, _>>()?;
+    let untyped = links.iter().find(|(_, t)| t.is_none());
+    match (&links[..], untyped) {
+        ([(id, _)], _) | (_, Some((id, _))) => Ok(*id),
+        ([], _) => Err(anyhow::anyhow!(
+            "no {}link from {} to {}",
+            link_type.map(|t| format!("`{t}` ")).unwrap_or_default(),
+            file_path(conn, src_file_id)?,
+            file_path(conn, dst_file_id)?
+        )),
+        _ => Err(anyhow::anyhow!(
+            "{} links from {} to {}; pick one by its type",
+            links.len(),
+            file_path(conn, src_file_id)?,
+            file_path(conn, dst_file_id)?
+        )),
+    }
+}
+
+/// Set `key` on a link.  Like the link itself, this is metadata of both
+/// ends, so neither may be locked.
+pub fn set_link_attr(conn: &Connection, link_id: i64, key: &str, value: &str) -> Result<()> {
+    check_link_ends(conn, link_id)?;
+    let m = Mutation::new("set_link_attr");
+    m.done(conn.execute(
+        "INSERT INTO link_attrs(link_id, key, value) VALUES (?1, ?2, ?3)
+         ON CONFLICT(link_id, key) DO UPDATE SET value = excluded.value",
+        params![link_id, key, value],
+    )?);
+    Ok(())
+}
+
+/// Remove `key` from a link; returns `false` if it was not set.
+pub fn remove_link_attr(conn: &Connection, link_id: i64, key: &str) -> Result<bool> {
+    check_link_ends(conn, link_id)?;
+    let m = Mutation::new("remove_link_attr");
+    Ok(m.done(conn.execute(
+        "DELETE FROM link_attrs WHERE link_id = ?1 AND key = ?2",
+        params![link_id, key],
+    )?) > 0)
+}
+
+/// `(key, value)` of every attribute on a link, by key.
+pub fn link_attrs(conn: &Connection, link_id: i64) -> Result<Vec<(String, String)>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT key, value FROM link_attrs WHERE link_id = ?1 ORDER BY key")?;
+    let rows = stmt.query_map([link_id], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+fn check_link_ends(conn: &Connection, link_id: i64) -> Result<()> {
+    let (src, dst): (i64, i64) = conn
+        .query_row(
+            "SELECT src_file_id, dst_file_id FROM links WHERE id = ?1",
+            [link_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .with_context(|| format!("no link with id {link_id}"))?;
+    crate::lock::check(conn, src)?;
+    crate::lock::check(conn, dst)
+}
+
 pub fn find_backlinks(conn: &Connection, pattern: &str) -> Result<Vec<(String, Option<String>)>> {
     let like = pattern.replace('*', "%");
 
@@ -304,10 +1860,11 @@ pub fn find_backlinks(conn: &Connection, pattern: &str) -> Result<Vec<(String, O
 /* ─── collections helpers ────────────────────────────────────────── */
 
 pub fn ensure_collection(conn: &Connection, name: &str) -> Result<i64> {
-    conn.execute(
+    let m = Mutation::new("ensure_collection");
+    m.done(conn.execute(
         "INSERT OR IGNORE INTO collections(name) VALUES (?1)",
         params![name],
-    )?;
+    )?);
     conn.query_row(
         "SELECT id FROM collections WHERE name = ?1",
         params![name],
@@ -317,11 +1874,12 @@ pub fn ensure_collection(conn: &Connection, name: &str) -> Result<i64> {
 }
 
 pub fn add_file_to_collection(conn: &Connection, coll_id: i64, file_id: i64) -> Result<()> {
-    conn.execute(
+    let m = Mutation::new("add_file_to_collection").file_id(file_id);
+    m.done(conn.execute(
         "INSERT OR IGNORE INTO collection_files(collection_id, file_id)
          VALUES (?1, ?2)",
         params![coll_id, file_id],
-    )?;
+    )?);
     Ok(())
 }
 
@@ -340,15 +1898,105 @@ pub fn list_collection(conn: &Connection, name: &str) -> Result<Vec<String>> {
     Ok(list)
 }
 
+/// Summary numbers for `marlin coll stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionStats {
+    pub files: i64,
+    pub total_size: i64,
+    /// `(tag path, file count)`, most frequent first.
+    pub tags: Vec<(String, i64)>,
+}
+
+/// Set comparison of two collections (`marlin coll diff`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionDiff {
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    pub shared: Vec<String>,
+}
+
+fn collection_id(conn: &Connection, name: &str) -> Result<i64> {
+    conn.query_row("SELECT id FROM collections WHERE name = ?1", [name], |r| {
+        r.get(0)
+    })
+    .optional()?
+    .ok_or_else(|| anyhow::anyhow!("collection not found: {}", name))
+}
+
+pub fn collection_stats(conn: &Connection, name: &str) -> Result<CollectionStats> {
+    let cid = collection_id(conn, name)?;
+    let (files, total_size) = conn.query_row(
+        r#"SELECT COUNT(*), IFNULL(SUM(f.size), 0)
+            FROM collection_files cf
+            JOIN files            f ON f.id = cf.file_id
+           WHERE cf.collection_id = ?1"#,
+        [cid],
+        |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)),
+    )?;
+
+    let mut stmt = conn.prepare(
+        r#"WITH RECURSIVE tag_tree(id, path) AS (
+               SELECT id, name FROM tags WHERE parent_id IS NULL
+               UNION ALL
+               SELECT t.id, tt.path || '/' || t.name
+                 FROM tags t
+                 JOIN tag_tree tt ON t.parent_id = tt.id
+           )
+           SELECT tt.path, COUNT(DISTINCT cf.file_id) AS n
+             FROM collection_files cf
+             JOIN file_tags        ft ON ft.file_id = cf.file_id
+             JOIN tag_tree         tt ON tt.id      = ft.tag_id
+            WHERE cf.collection_id = ?1
+            GROUP BY tt.path
+            ORDER BY n DESC, tt.path"#,
+    )?;
+    let tags = stmt
+        .query_map([cid], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?)))?
+        .collect::<StdResult<Vec<_>, _>>()?;
+
+    Ok(CollectionStats {
+        files,
+        total_size,
+        tags,
+    })
+}
+
+pub fn collection_diff(conn: &Connection, a: &str, b: &str) -> Result<CollectionDiff> {
+    let a_id = collection_id(conn, a)?;
+    let b_id = collection_id(conn, b)?;
+
+    let run = |op: &str, lhs: i64, rhs: i64| -> Result<Vec<String>> {
+        let sql = format!(
+            r#"SELECT f.path FROM files f
+                WHERE f.id IN (
+                    SELECT file_id FROM collection_files WHERE collection_id = ?1
+                    {op}
+                    SELECT file_id FROM collection_files WHERE collection_id = ?2
+                )
+                ORDER BY f.path"#
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([lhs, rhs], |r| r.get::<_, String>(0))?;
+        Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+    };
+
+    Ok(CollectionDiff {
+        only_a: run("EXCEPT", a_id, b_id)?,
+        only_b: run("EXCEPT", b_id, a_id)?,
+        shared: run("INTERSECT", a_id, b_id)?,
+    })
+}
+
 /* ─── saved views (smart folders) ───────────────────────────────── */
 
 pub fn save_view(conn: &Connection, name: &str, query: &str) -> Result<()> {
-    conn.execute(
+    let m = Mutation::new("save_view");
+    m.done(conn.execute(
         "INSERT INTO views(name, query)
          VALUES (?1, ?2)
          ON CONFLICT(name) DO UPDATE SET query = excluded.query",
         params![name, query],
-    )?;
+    )?);
     Ok(())
 }
 
@@ -366,20 +2014,308 @@ pub fn view_query(conn: &Connection, name: &str) -> Result<String> {
     .context(format!("no view called '{}'", name))
 }
 
+/* ─── saved actions ───────────────────────────────────────────────── */
+
+/// A named query plus the command run on its hits (`marlin action`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedAction {
+    pub name: String,
+    pub query: String,
+    /// Command template; `{}` is replaced by each hit's path.
+    pub command: String,
+}
+
+pub fn save_action(conn: &Connection, name: &str, query: &str, command: &str) -> Result<()> {
+    let m = Mutation::new("save_action");
+    m.done(conn.execute(
+        "INSERT INTO actions(name, query, command)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(name) DO UPDATE
+            SET query = excluded.query, command = excluded.command",
+        params![name, query, command],
+    )?);
+    Ok(())
+}
+
+pub fn list_actions(conn: &Connection) -> Result<Vec<SavedAction>> {
+    let mut stmt = conn.prepare("SELECT name, query, command FROM actions ORDER BY name")?;
+    let rows = stmt.query_map([], |r| {
+        Ok(SavedAction {
+            name: r.get(0)?,
+            query: r.get(1)?,
+            command: r.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+pub fn action(conn: &Connection, name: &str) -> Result<SavedAction> {
+    conn.query_row(
+        "SELECT name, query, command FROM actions WHERE name = ?1",
+        [name],
+        |r| {
+            Ok(SavedAction {
+                name: r.get(0)?,
+                query: r.get(1)?,
+                command: r.get(2)?,
+            })
+        },
+    )
+    .context(format!("no action called '{}'", name))
+}
+
+pub fn remove_action(conn: &Connection, name: &str) -> Result<()> {
+    let m = Mutation::new("remove_action");
+    if m.done(conn.execute("DELETE FROM actions WHERE name = ?1", [name])?) == 0 {
+        anyhow::bail!("no action called '{}'", name);
+    }
+    Ok(())
+}
+
+/* ─── usage statistics ────────────────────────────────────────────── */
+
+/// File count and size for one tag or directory (`marlin stats`).
+///
+/// `recent_*` only count files whose `mtime` is at or after the `since`
+/// cut-off passed to [`tag_stats`] / [`dir_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageStats {
+    pub name: String,
+    pub files: i64,
+    pub total_size: i64,
+    pub recent_files: i64,
+    pub recent_size: i64,
+}
+
+impl UsageStats {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            files: 0,
+            total_size: 0,
+            recent_files: 0,
+            recent_size: 0,
+        }
+    }
+}
+
+/// Usage per (full-path) tag, largest first. `since` is a Unix timestamp.
+/// Tombstones (see [`crate::prune`]) are left out here and in the other
+/// usage reports.
+pub fn tag_stats(conn: &Connection, since: i64) -> Result<Vec<UsageStats>> {
+    let mut stmt = conn.prepare(
+        r#"WITH RECURSIVE tag_tree(id, path) AS (
+               SELECT id, name FROM tags WHERE parent_id IS NULL
+               UNION ALL
+               SELECT t.id, tt.path || '/' || t.name
+                 FROM tags t
+                 JOIN tag_tree tt ON t.parent_id = tt.id
+           )
+           SELECT tt.path,
+                  COUNT(*),
+                  IFNULL(SUM(f.size), 0),
+                  SUM(f.mtime >= ?1),
+                  IFNULL(SUM(CASE WHEN f.mtime >= ?1 THEN f.size END), 0)
+             FROM file_tags ft
+             JOIN files     f  ON f.id  = ft.file_id
+             JOIN tag_tree  tt ON tt.id = ft.tag_id
+            WHERE f.missing_since IS NULL
+            GROUP BY tt.path
+            ORDER BY 3 DESC, tt.path"#,
+    )?;
+    let rows = stmt.query_map([since], |r| {
+        Ok(UsageStats {
+            name: r.get(0)?,
+            files: r.get(1)?,
+            total_size: r.get(2)?,
+            recent_files: r.get::<_, Option<i64>>(3)?.unwrap_or(0),
+            recent_size: r.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/// Usage per directory under `root`, grouped by the first `depth` path
+/// components (files directly in `root` are reported as `.`), largest
+/// first. `since` is a Unix timestamp.
+pub fn dir_stats(
+    conn: &Connection,
+    root: &Path,
+    depth: usize,
+    since: i64,
+) -> Result<Vec<UsageStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, IFNULL(size, 0), IFNULL(mtime, 0) FROM files WHERE missing_since IS NULL",
+    )?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+        .collect::<StdResult<Vec<(String, i64, i64)>, _>>()?;
+    Ok(group_by_dir(rows, root, depth, since))
+}
+
+/// Group `(path, size, mtime)` rows the way [`dir_stats`] does. Rows
+/// outside `root` are ignored.
+pub fn group_by_dir<I>(rows: I, root: &Path, depth: usize, since: i64) -> Vec<UsageStats>
+where
+    I: IntoIterator<Item = (String, i64, i64)>,
+{
+    use std::collections::HashMap;
+
+    let mut groups: HashMap<String, UsageStats> = HashMap::new();
+    for (path, size, mtime) in rows {
+        let Ok(rel) = Path::new(&path).strip_prefix(root) else {
+            continue;
+        };
+        let dirs: Vec<_> = rel
+            .parent()
+            .map(|p| p.components().take(depth.max(1)).collect())
+            .unwrap_or_default();
+        let key = if dirs.is_empty() {
+            ".".to_string()
+        } else {
+            dirs.iter()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+
+        let e = groups
+            .entry(key.clone())
+            .or_insert_with(|| UsageStats::new(key));
+        e.files += 1;
+        e.total_size += size;
+        if mtime >= since {
+            e.recent_files += 1;
+            e.recent_size += size;
+        }
+    }
+
+    let mut out: Vec<_> = groups.into_values().collect();
+    out.sort_by(|a, b| b.total_size.cmp(&a.total_size).then(a.name.cmp(&b.name)));
+    out
+}
+
+/// `(path, size, mtime)` of the given files, in the order of `ids`.
+pub fn file_usage(conn: &Connection, ids: &[i64]) -> Result<Vec<(String, i64, i64)>> {
+    let mut stmt =
+        conn.prepare("SELECT path, IFNULL(size, 0), IFNULL(mtime, 0) FROM files WHERE id = ?1")?;
+    let mut out = Vec::with_capacity(ids.len());
+    for id in ids {
+        out.push(stmt.query_row([id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?);
+    }
+    Ok(out)
+}
+
+/// An indexed file that has not been modified for a while.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleFile {
+    pub id: i64,
+    pub path: String,
+    pub size: i64,
+    pub mtime: i64,
+}
+
+/// Files last modified before `before` (Unix timestamp) and at least
+/// `min_size` bytes large, oldest first.
+pub fn stale_files(conn: &Connection, before: i64, min_size: i64) -> Result<Vec<StaleFile>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path, IFNULL(size, 0), IFNULL(mtime, 0)
+           FROM files
+          WHERE IFNULL(mtime, 0) < ?1 AND IFNULL(size, 0) >= ?2
+            AND missing_since IS NULL
+          ORDER BY mtime, path",
+    )?;
+    let rows = stmt.query_map([before, min_size], |r| {
+        Ok(StaleFile {
+            id: r.get(0)?,
+            path: r.get(1)?,
+            size: r.get(2)?,
+            mtime: r.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/* ─── search history ──────────────────────────────────────────────── */
+
+/// One row of `search_history`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHistoryEntry {
+    pub id: i64,
+    pub query: String,
+    /// UNIX timestamp
+    pub executed_at: i64,
+    pub hits: i64,
+}
+
+/// Remember an executed search and its hit count.
+pub fn record_search(conn: &Connection, query: &str, hits: usize) -> Result<()> {
+    let m = Mutation::new("record_search");
+    m.done(conn.execute(
+        "INSERT INTO search_history(query, executed_at, hits)
+         VALUES (?1, strftime('%s','now'), ?2)",
+        params![query, hits as i64],
+    )?);
+    Ok(())
+}
+
+/// The `limit` most recent searches, newest first.
+pub fn search_history(conn: &Connection, limit: usize) -> Result<Vec<SearchHistoryEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, query, executed_at, hits FROM search_history
+          ORDER BY id DESC LIMIT ?1",
+    )?;
+    let rows = stmt.query_map([limit as i64], |r| {
+        Ok(SearchHistoryEntry {
+            id: r.get(0)?,
+            query: r.get(1)?,
+            executed_at: r.get(2)?,
+            hits: r.get(3)?,
+        })
+    })?;
+    let list = rows.collect::<StdResult<Vec<_>, _>>()?;
+    Ok(list)
+}
+
+/// Query text of history entry `id`, or of the latest search when `None`.
+pub fn history_query(conn: &Connection, id: Option<i64>) -> Result<String> {
+    let q = match id {
+        Some(id) => conn
+            .query_row(
+                "SELECT query FROM search_history WHERE id = ?1",
+                [id],
+                |r| r.get::<_, String>(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("no search history entry #{}", id))?,
+        None => conn
+            .query_row(
+                "SELECT query FROM search_history ORDER BY id DESC LIMIT 1",
+                [],
+                |r| r.get::<_, String>(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("search history is empty"))?,
+    };
+    Ok(q)
+}
+
 /* ─── dirty‐scan helpers ─────────────────────────────────────────── */
 
 /// Mark a file as “dirty” so it’ll be picked up by `scan_dirty`.
 pub fn mark_dirty(conn: &Connection, file_id: i64) -> Result<()> {
-    conn.execute(
+    let m = Mutation::new("mark_dirty").file_id(file_id);
+    m.done(conn.execute(
         "INSERT OR IGNORE INTO file_changes(file_id, marked_at)
          VALUES (?1, strftime('%s','now'))",
         params![file_id],
-    )?;
+    )?);
     Ok(())
 }
 
 /// Take and clear all dirty file IDs for incremental re-scan.
 pub fn take_dirty(conn: &Connection) -> Result<Vec<i64>> {
+    let m = Mutation::new("take_dirty");
     let mut ids = Vec::new();
     {
         let mut stmt = conn.prepare("SELECT file_id FROM file_changes")?;
@@ -387,54 +2323,280 @@ pub fn take_dirty(conn: &Connection) -> Result<Vec<i64>> {
             ids.push(row?);
         }
     }
-    conn.execute("DELETE FROM file_changes", [])?;
+    m.done(conn.execute("DELETE FROM file_changes", [])?);
     Ok(ids)
 }
 
+/* ─── settings ─────────────────────────────────────────────────── */
+
+/// Read a per-index setting such as `tags.strict`.
+pub fn setting(conn: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |r| {
+            r.get(0)
+        })
+        .optional()?)
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    let m = Mutation::new("set_setting");
+    m.done(conn.execute(
+        "INSERT INTO settings(key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?);
+    Ok(())
+}
+
+/// Remove a setting; returns `false` if it was not set.
+pub fn unset_setting(conn: &Connection, key: &str) -> Result<bool> {
+    let m = Mutation::new("unset_setting");
+    Ok(m.done(conn.execute("DELETE FROM settings WHERE key = ?1", [key])?) > 0)
+}
+
+/// Every stored setting, sorted by key.
+pub fn settings(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    Ok(rows.collect::<StdResult<Vec<_>, _>>()?)
+}
+
+/* ─── content identity ─────────────────────────────────────────── */
+
+/// Whether tags and attributes belong to a path or to a file body.
+///
+/// Files whose hashes match share a `contents` row either way. Under
+/// [`Identity::Content`] their metadata is kept in sync by triggers, so a
+/// copy or hard link carries the original's tags and attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Identity {
+    #[default]
+    Path,
+    Content,
+}
+
+impl Identity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Identity::Path => "path",
+            Identity::Content => "content",
+        }
+    }
+}
+
+/// The identity semantics of this index.
+pub fn identity(conn: &Connection) -> Result<Identity> {
+    Ok(match setting(conn, "identity")?.as_deref() {
+        Some("content") => Identity::Content,
+        _ => Identity::Path,
+    })
+}
+
+/// Switch identity semantics.  Turning content identity on merges the
+/// tags and attributes already present within each content group.
+pub fn set_identity(conn: &mut Connection, identity: Identity) -> Result<()> {
+    let m = Mutation::new("set_identity");
+    let tx = conn.savepoint()?;
+    set_setting(&tx, "identity", identity.as_str())?;
+    let mut rows = 0;
+    if identity == Identity::Content {
+        rows += tx.execute(
+            "INSERT OR IGNORE INTO file_tags(file_id, tag_id)
+               SELECT f.id, ft.tag_id
+                 FROM files f
+                 JOIN files g ON g.content_id = f.content_id AND g.id != f.id
+                 JOIN file_tags ft ON ft.file_id = g.id",
+            [],
+        )?;
+        rows += tx.execute(
+            "INSERT OR IGNORE INTO attributes(file_id, key, value)
+               SELECT f.id, a.key, a.value
+                 FROM files f
+                 JOIN files g ON g.content_id = f.content_id AND g.id != f.id
+                 JOIN attributes a ON a.file_id = g.id",
+            [],
+        )?;
+    }
+    tx.commit()?;
+    m.done(rows);
+    info!(identity = identity.as_str(), "identity semantics changed");
+    Ok(())
+}
+
+/// Other indexed paths with the same content as `file_id` (copies and
+/// hard links), sorted.  Empty while the file has no hash.
+pub fn aliases(conn: &Connection, file_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT g.path FROM files f
+           JOIN files g ON g.content_id = f.content_id AND g.id != f.id
+          WHERE f.id = ?1
+          ORDER BY g.path",
+    )?;
+    let rows = stmt.query_map([file_id], |r| r.get(0))?;
+    Ok(rows.collect::<StdResult<Vec<String>, _>>()?)
+}
+
+/* ─── garbage collection ───────────────────────────────────────── */
+
+/// Rows removed by [`gc`], per kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Tags no file carries and with no used descendants.
+    pub tags: usize,
+    pub file_tags: usize,
+    pub attributes: usize,
+    pub links: usize,
+    pub labels: usize,
+    pub collection_entries: usize,
+    /// Full-text rows whose file is gone.
+    pub fts_rows: usize,
+    /// `file_changes` entries whose file is gone.
+    pub dirty: usize,
+    /// `contents` rows no file points at any more.
+    pub contents: usize,
+    /// Bytes returned to the file system, when `VACUUM` ran.
+    pub vacuumed_bytes: Option<u64>,
+}
+
+impl GcReport {
+    /// `(kind, removed rows)` in display order.
+    pub fn counts(&self) -> [(&'static str, usize); 9] {
+        [
+            ("tags", self.tags),
+            ("file_tags", self.file_tags),
+            ("attributes", self.attributes),
+            ("links", self.links),
+            ("labels", self.labels),
+            ("collection_entries", self.collection_entries),
+            ("fts_rows", self.fts_rows),
+            ("dirty", self.dirty),
+            ("contents", self.contents),
+        ]
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts().iter().map(|(_, n)| n).sum()
+    }
+}
+
+/// Remove metadata that no longer belongs to anything: unused tags,
+/// rows pointing at deleted files, dangling full-text entries and unused
+/// content records.
+///
+/// Foreign keys already cascade most of this; the sweep catches what
+/// slipped past them (databases edited with `foreign_keys` off, older
+/// builds).  With `vacuum` the file is compacted afterwards.
+pub fn gc(conn: &mut Connection, vacuum: bool) -> Result<GcReport> {
+    let m = Mutation::new("gc");
+    let mut report = GcReport::default();
+    let tx = conn.savepoint()?;
+
+    let orphaned = |table: &str, column: &str| {
+        format!("DELETE FROM {table} WHERE {column} NOT IN (SELECT id FROM files)")
+    };
+    report.file_tags = tx.execute(&orphaned("file_tags", "file_id"), [])?;
+    report.attributes = tx.execute(&orphaned("attributes", "file_id"), [])?;
+    report.links = tx.execute(
+        "DELETE FROM links
+          WHERE src_file_id NOT IN (SELECT id FROM files)
+             OR dst_file_id NOT IN (SELECT id FROM files)",
+        [],
+    )?;
+    report.labels = tx.execute(&orphaned("file_labels", "file_id"), [])?;
+    report.collection_entries = tx.execute(&orphaned("collection_files", "file_id"), [])?;
+    report.dirty = tx.execute(&orphaned("file_changes", "file_id"), [])?;
+    report.fts_rows = tx.execute(&orphaned("files_fts", "rowid"), [])?;
+    report.contents = tx.execute(
+        "DELETE FROM contents
+          WHERE id NOT IN (SELECT content_id FROM files WHERE content_id IS NOT NULL)",
+        [],
+    )?;
+    report.tags = prune_unused_tags(&tx)?;
+    tx.commit()?;
+
+    if vacuum {
+        let size = |c: &Connection| -> Result<u64> {
+            let pages: u64 = c.query_row("PRAGMA page_count", [], |r| r.get(0))?;
+            let page_size: u64 = c.query_row("PRAGMA page_size", [], |r| r.get(0))?;
+            Ok(pages * page_size)
+        };
+        let before = size(conn)?;
+        conn.execute_batch("VACUUM")?;
+        report.vacuumed_bytes = Some(before.saturating_sub(size(conn)?));
+    }
+
+    m.done(report.total());
+    info!(removed = report.total(), "garbage collection finished");
+    Ok(report)
+}
+
 /* ─── rename helpers ────────────────────────────────────────────── */
 
 pub fn update_file_path(conn: &Connection, old_path: &str, new_path: &str) -> Result<()> {
-    let file_id: i64 = conn.query_row("SELECT id FROM files WHERE path = ?1", [old_path], |r| {
-        r.get(0)
-    })?;
-    conn.execute(
+    let old_path = normalize_path(old_path, &PathNorm::default());
+    let new_path = normalize_path(new_path, &PathNorm::default());
+    let file_id: i64 =
+        conn.query_row("SELECT id FROM files WHERE path = ?1", [&old_path], |r| {
+            r.get(0)
+        })?;
+    let m = Mutation::new("update_file_path")
+        .file_id(file_id)
+        .path(&old_path);
+    m.done(conn.execute(
         "UPDATE files SET path = ?1 WHERE id = ?2",
         params![new_path, file_id],
-    )?;
+    )?);
     mark_dirty(conn, file_id)?;
     Ok(())
 }
 
+/// Move every indexed path below `old_dir` to `new_dir`.
 pub fn rename_directory(conn: &mut Connection, old_dir: &str, new_dir: &str) -> Result<()> {
-    let like_pattern = format!("{}/%", old_dir.trim_end_matches('/'));
+    let sep = std::path::MAIN_SEPARATOR;
+    let old_prefix = format!("{}{sep}", normalize_path(old_dir, &PathNorm::default()));
+    let new_prefix = format!("{}{sep}", normalize_path(new_dir, &PathNorm::default()));
+    // prefix comparison rather than LIKE: `_` and `%` are legal in names
     let ids = {
-        let mut stmt = conn.prepare("SELECT id FROM files WHERE path LIKE ?1")?;
-        let rows = stmt.query_map([&like_pattern], |r| r.get::<_, i64>(0))?;
+        let mut stmt =
+            conn.prepare("SELECT id FROM files WHERE substr(path, 1, length(?1)) = ?1")?;
+        let rows = stmt.query_map([&old_prefix], |r| r.get::<_, i64>(0))?;
         rows.collect::<StdResult<Vec<_>, _>>()?
     };
-    let tx = conn.transaction()?;
-    tx.execute(
-        "UPDATE files SET path = REPLACE(path, ?1, ?2) WHERE path LIKE ?3",
-        params![old_dir, new_dir, like_pattern],
+    let m = Mutation::new("rename_directory").path(old_dir);
+    let tx = conn.savepoint()?;
+    let moved = tx.execute(
+        "UPDATE files SET path = ?2 || substr(path, length(?1) + 1)
+          WHERE substr(path, 1, length(?1)) = ?1",
+        params![old_prefix, new_prefix],
     )?;
     for fid in ids {
         mark_dirty(&tx, fid)?;
     }
     tx.commit()?;
+    m.done(moved);
     Ok(())
 }
 
 /* ─── backup / restore helpers ────────────────────────────────────── */
 
+#[cfg(feature = "backup")]
 pub fn backup<P: AsRef<Path>>(db_path: P) -> Result<PathBuf> {
     let src = db_path.as_ref();
     let dir = src
         .parent()
         .ok_or_else(|| anyhow::anyhow!("invalid DB path: {}", src.display()))?
         .join("backups");
-    fs::create_dir_all(&dir)?;
+    backup_to(src, dir)
+}
+
+/// Like [`backup`] but into an explicit backups directory.
+#[cfg(feature = "backup")]
+pub fn backup_to<P: AsRef<Path>, D: AsRef<Path>>(db_path: P, dir: D) -> Result<PathBuf> {
+    let src = db_path.as_ref();
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
 
-    let stamp = Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let stamp = Utc::now().format(crate::backup::STAMP_FORMAT);
     let dst = dir.join(format!("backup_{stamp}.db"));
 
     let src_conn = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
@@ -442,11 +2604,20 @@ pub fn backup<P: AsRef<Path>>(db_path: P) -> Result<PathBuf> {
 
     let bk = Backup::new(&src_conn, &mut dst_conn)?;
     while let StepResult::More = bk.step(100)? {}
+    drop(bk);
+    drop(dst_conn);
+    crate::backup::BackupMeta::write_for(&dst)?;
+    crate::events::emit(crate::events::Event::BackupCreated { path: dst.clone() });
     Ok(dst)
 }
 
+#[cfg(feature = "backup")]
 pub fn restore<P: AsRef<Path>>(backup_path: P, live_db_path: P) -> Result<()> {
     fs::copy(&backup_path, &live_db_path)?;
+    crate::events::emit(crate::events::Event::RestoreFinished {
+        from: backup_path.as_ref().to_path_buf(),
+        to: live_db_path.as_ref().to_path_buf(),
+    });
     Ok(())
 }
 
@@ -0,0 +1,96 @@
+//! Tracing of database writes, for chasing metadata that drifted.
+//!
+//! Every helper that changes what the index holds – in [`crate::db`] and in
+//! the modules writing their own SQL (scans, links, locks, classification,
+//! snapshots, tokens, …) – runs inside a `db` span carrying `op` (the
+//! helper), `file_id` and `path` where it touches one file, and
+//! `rows_affected` once it is done.  Bookkeeping is left out: migrations,
+//! scan-lease heartbeats and the probe of [`crate::db::check_writable`].
+//! The spans are at `debug` level, so `RUST_LOG=debug` (or `marlin
+//! --verbose`) shows each write as
+//!
+//! ```text
+//! DEBUG db{op="upsert_attr" file_id=12 rows_affected=1}: db write
+//! ```
+//!
+//! [`trace_connection`] makes one connection also log each statement it
+//! executes and how long it took, nested in the span of the helper that
+//! ran it.  With [`set_trace_sql`] every connection [`crate::db::open`]
+//! makes does (`marlin --trace-sql`).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use rusqlite::Connection;
+use tracing::field::Empty;
+use tracing::span::EnteredSpan;
+use tracing::{debug, debug_span, info};
+
+static TRACE_SQL: AtomicBool = AtomicBool::new(false);
+
+/// Log the SQL of connections [`crate::db::open`] makes from now on, with
+/// timings.
+pub fn set_trace_sql(on: bool) {
+    TRACE_SQL.store(on, Ordering::Relaxed);
+}
+
+/// Whether [`set_trace_sql`] is on.
+pub fn trace_sql() -> bool {
+    TRACE_SQL.load(Ordering::Relaxed)
+}
+
+/// Start (or stop) logging the SQL `conn` executes, with timings.
+pub fn trace_connection(conn: &mut Connection, on: bool) {
+    conn.profile(on.then_some(log_sql as fn(&str, Duration)));
+}
+
+/// Install the SQL logger on `conn` if [`set_trace_sql`] is on.
+pub(crate) fn install(conn: &mut Connection) {
+    if trace_sql() {
+        trace_connection(conn, true);
+    }
+}
+
+// SQLite times statements to the millisecond
+fn log_sql(sql: &str, took: Duration) {
+    info!(
+        elapsed_ms = took.as_millis() as u64,
+        "sql: {}",
+        sql.split_whitespace().collect::<Vec<_>>().join(" ")
+    );
+}
+
+/// The `db` span of one mutating helper, entered until it is done.
+pub(crate) struct Mutation(EnteredSpan);
+
+impl Mutation {
+    pub(crate) fn new(op: &'static str) -> Self {
+        Self(
+            debug_span!(
+                "db",
+                op,
+                file_id = Empty,
+                path = Empty,
+                rows_affected = Empty
+            )
+            .entered(),
+        )
+    }
+
+    pub(crate) fn file_id(self, id: i64) -> Self {
+        self.0.record("file_id", id);
+        self
+    }
+
+    pub(crate) fn path(self, path: &str) -> Self {
+        self.0.record("path", path);
+        self
+    }
+
+    /// Record and log the rows the helper changed; returns `rows`.
+    pub(crate) fn done(self, rows: usize) -> usize {
+        self.0.record("rows_affected", rows as u64);
+        debug!("db write");
+        rows
+    }
+}
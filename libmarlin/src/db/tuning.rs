@@ -0,0 +1,145 @@
+//! SQLite tuning presets (`db.tuning`): how much memory, durability and
+//! temp-file I/O the connection trades for speed.
+//!
+//! | preset     | synchronous | cache     | mmap     | temp_store |
+//! |------------|-------------|-----------|----------|------------|
+//! | `safe`     | FULL        | 2 MiB     | off      | file       |
+//! | `balanced` | NORMAL      | 16 MiB    | 64 MiB   | file       |
+//! | `fast`     | OFF         | 64 MiB    | 256 MiB  | memory     |
+//!
+//! `safe` is SQLite's own defaults and what Marlin always used.
+//! `balanced` cannot corrupt the WAL-mode index, but a power cut may lose
+//! the last few commits.  `fast` can lose recent commits even when only
+//! the OS crashes, and needs more RAM.  The index can be rebuilt from the
+//! file system, but tags and attributes cannot, so keep backups on.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use rusqlite::types::Value;
+use rusqlite::Connection;
+
+use super::setting;
+
+/// A named set of connection pragmas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tuning {
+    #[default]
+    Safe,
+    Balanced,
+    Fast,
+}
+
+/// The pragma values behind a [`Tuning`] preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TuningPragmas {
+    /// `PRAGMA synchronous` (`FULL`, `NORMAL` or `OFF`).
+    pub synchronous: &'static str,
+    /// `PRAGMA cache_size` in KiB (passed negated, as SQLite expects).
+    pub cache_kib: i64,
+    /// `PRAGMA mmap_size` in bytes; 0 disables memory mapping.
+    pub mmap_bytes: i64,
+    /// `PRAGMA temp_store` (`DEFAULT` or `MEMORY`).
+    pub temp_store: &'static str,
+}
+
+impl Tuning {
+    pub const ALL: [Tuning; 3] = [Tuning::Safe, Tuning::Balanced, Tuning::Fast];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tuning::Safe => "safe",
+            Tuning::Balanced => "balanced",
+            Tuning::Fast => "fast",
+        }
+    }
+
+    pub fn pragmas(self) -> TuningPragmas {
+        match self {
+            Tuning::Safe => TuningPragmas {
+                synchronous: "FULL",
+                cache_kib: 2_000,
+                mmap_bytes: 0,
+                temp_store: "DEFAULT",
+            },
+            Tuning::Balanced => TuningPragmas {
+                synchronous: "NORMAL",
+                cache_kib: 16 * 1024,
+                mmap_bytes: 64 << 20,
+                temp_store: "DEFAULT",
+            },
+            Tuning::Fast => TuningPragmas {
+                synchronous: "OFF",
+                cache_kib: 64 * 1024,
+                mmap_bytes: 256 << 20,
+                temp_store: "MEMORY",
+            },
+        }
+    }
+
+    /// Set this preset's pragmas on `conn` (they last for the connection).
+    pub fn apply(self, conn: &Connection) -> Result<()> {
+        let p = self.pragmas();
+        conn.pragma_update(None, "synchronous", p.synchronous)?;
+        conn.pragma_update(None, "cache_size", -p.cache_kib)?;
+        // reports the new value as a row, which `pragma_update` rejects
+        conn.execute_batch(&format!("PRAGMA mmap_size = {}", p.mmap_bytes))?;
+        conn.pragma_update(None, "temp_store", p.temp_store)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tuning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Tuning {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "safe" => Ok(Tuning::Safe),
+            "balanced" => Ok(Tuning::Balanced),
+            "fast" => Ok(Tuning::Fast),
+            _ => bail!("unknown tuning preset `{s}` (use safe, balanced or fast)"),
+        }
+    }
+}
+
+/// The preset configured for this index (`db.tuning`), `safe` if unset.
+pub fn tuning(conn: &Connection) -> Result<Tuning> {
+    match setting(conn, "db.tuning")? {
+        Some(v) => v.parse(),
+        None => Ok(Tuning::default()),
+    }
+}
+
+/// The pragma values live on `conn`, as `(pragma, value)` pairs.
+pub fn live_pragmas(conn: &Connection) -> Result<Vec<(&'static str, String)>> {
+    let mut out = Vec::new();
+    for name in [
+        "synchronous",
+        "cache_size",
+        "mmap_size",
+        "temp_store",
+        "journal_mode",
+    ] {
+        let v: Value = conn.query_row(&format!("PRAGMA {name}"), [], |r| r.get(0))?;
+        let v = match (name, v) {
+            ("synchronous", Value::Integer(i)) => ["OFF", "NORMAL", "FULL", "EXTRA"]
+                .get(i as usize)
+                .map_or_else(|| i.to_string(), |s| s.to_string()),
+            ("temp_store", Value::Integer(i)) => ["DEFAULT", "FILE", "MEMORY"]
+                .get(i as usize)
+                .map_or_else(|| i.to_string(), |s| s.to_string()),
+            (_, Value::Integer(i)) => i.to_string(),
+            (_, Value::Text(t)) => t,
+            (_, other) => format!("{other:?}"),
+        };
+        out.push((name, v));
+    }
+    Ok(out)
+}
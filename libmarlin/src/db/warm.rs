@@ -0,0 +1,91 @@
+//! Cache warm-up (`marlin warm`): read the pages a first search needs so
+//! they are in the OS page cache before anybody waits on them.
+//!
+//! After a reboot the first query on a spinning disk or network share pays
+//! for every page it touches.  [`warm`] walks the tables and indexes search
+//! and tagging read – files and their path index, tags, tag links,
+//! attributes and the FTS5 shadow tables – with cheap aggregate queries.
+//! With `mmap` the reads go through a memory map of the whole file instead
+//! of SQLite's own page cache, which leaves that cache free for the real
+//! work.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rusqlite::Connection;
+use tracing::debug;
+
+/// What each warm-up query reads, in order.
+const TARGETS: &[(&str, &str)] = &[
+    (
+        "files",
+        "SELECT COUNT(*) FROM files NOT INDEXED WHERE length(path) >= 0",
+    ),
+    (
+        "files.path index",
+        "SELECT COUNT(*) FROM files INDEXED BY idx_files_path WHERE path >= ''",
+    ),
+    (
+        "tags",
+        "SELECT COUNT(*) FROM tags NOT INDEXED WHERE length(name) >= 0",
+    ),
+    (
+        "file_tags",
+        "SELECT COUNT(*) FROM file_tags NOT INDEXED WHERE tag_id > 0",
+    ),
+    (
+        "attributes",
+        "SELECT COUNT(*) FROM attributes NOT INDEXED WHERE length(key) >= 0",
+    ),
+    (
+        "fts terms",
+        "SELECT COUNT(*) FROM files_fts_idx NOT INDEXED WHERE length(term) >= 0",
+    ),
+    (
+        "fts segments",
+        "SELECT COUNT(*) FROM files_fts_data NOT INDEXED WHERE length(block) >= 0",
+    ),
+];
+
+/// Outcome of [`warm`].
+#[derive(Debug, Clone, Default)]
+pub struct WarmReport {
+    /// Rows read per target (see the module docs), in warm-up order.
+    pub targets: Vec<(&'static str, u64)>,
+    /// Size of the database file.
+    pub db_bytes: u64,
+    /// Bytes memory-mapped for the warm-up, if `mmap` was requested.
+    pub mmap_bytes: Option<u64>,
+    pub elapsed: Duration,
+}
+
+impl WarmReport {
+    pub fn rows(&self) -> u64 {
+        self.targets.iter().map(|(_, n)| n).sum()
+    }
+}
+
+/// Touch the hot pages of the index; see the module docs.
+pub fn warm(conn: &Connection, mmap: bool) -> Result<WarmReport> {
+    let start = Instant::now();
+    let pages: u64 = conn.query_row("PRAGMA page_count", [], |r| r.get(0))?;
+    let page_size: u64 = conn.query_row("PRAGMA page_size", [], |r| r.get(0))?;
+    let mut report = WarmReport {
+        db_bytes: pages * page_size,
+        ..Default::default()
+    };
+
+    if mmap {
+        conn.execute_batch(&format!("PRAGMA mmap_size = {}", report.db_bytes))?;
+        report.mmap_bytes = Some(conn.query_row("PRAGMA mmap_size", [], |r| r.get(0))?);
+    }
+
+    for &(name, sql) in TARGETS {
+        let rows: u64 = conn.query_row(sql, [], |r| r.get(0))?;
+        debug!(target = name, rows, "warmed");
+        report.targets.push((name, rows));
+    }
+
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
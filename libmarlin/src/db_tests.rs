@@ -1,6 +1,7 @@
 // libmarlin/src/db_tests.rs
 
-use super::db;
+use super::db::{self, AnnotationRange, EventFilter, TaskFilter, TaskStatus};
+use super::test_utils::db_with_files;
 use rusqlite::Connection;
 use tempfile::tempdir;
 
@@ -145,6 +146,52 @@ fn add_and_remove_links_and_backlinks() {
     assert!(empty.is_empty());
 }
 
+#[test]
+fn link_attrs_live_and_die_with_their_link() {
+    let conn = db_with_files(&["one.txt", "two.txt"]);
+    let src = db::file_id(&conn, "one.txt").unwrap();
+    let dst = db::file_id(&conn, "two.txt").unwrap();
+    assert!(db::find_link(&conn, src, dst, None).is_err());
+
+    db::add_link(&conn, src, dst, Some("cites")).unwrap();
+    let cites = db::find_link(&conn, src, dst, None).unwrap();
+    db::add_link(&conn, src, dst, Some("ref")).unwrap();
+    let err = db::find_link(&conn, src, dst, None).unwrap_err();
+    assert!(err.to_string().contains("pick one by its type"));
+    assert_eq!(
+        db::find_link(&conn, src, dst, Some("cites")).unwrap(),
+        cites
+    );
+    db::add_link(&conn, src, dst, None).unwrap();
+    let untyped = db::find_link(&conn, src, dst, None).unwrap();
+    assert_ne!(untyped, cites);
+
+    db::set_link_attr(&conn, cites, "weight", "1").unwrap();
+    db::set_link_attr(&conn, cites, "weight", "2").unwrap();
+    db::set_link_attr(&conn, cites, "note", "seminal").unwrap();
+    let attrs = vec![
+        ("note".to_string(), "seminal".to_string()),
+        ("weight".to_string(), "2".to_string()),
+    ];
+    assert_eq!(db::link_attrs(&conn, cites).unwrap(), attrs);
+    let details = db::link_details(&conn, "one%", None, Some("cites")).unwrap();
+    assert_eq!(details.len(), 1);
+    assert_eq!(details[0].other, "two.txt");
+    assert_eq!(details[0].attrs, attrs);
+    let back = db::link_details(&conn, "two%", Some("in"), Some("cites")).unwrap();
+    assert_eq!(back[0].other, "one.txt");
+    assert_eq!(back[0].attrs, attrs);
+
+    assert!(db::remove_link_attr(&conn, cites, "note").unwrap());
+    assert!(!db::remove_link_attr(&conn, cites, "note").unwrap());
+
+    db::remove_link(&conn, src, dst, Some("cites")).unwrap();
+    let left: i64 = conn
+        .query_row("SELECT COUNT(*) FROM link_attrs", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(left, 0);
+}
+
 #[test]
 fn collections_roundtrip() {
     let conn = open_mem();
@@ -166,6 +213,91 @@ fn collections_roundtrip() {
     assert_eq!(files, vec!["f.txt".to_string()]);
 }
 
+#[test]
+fn collection_stats_and_diff() {
+    let conn = open_mem();
+    for (p, size) in [("a.jpg", 10), ("b.jpg", 20), ("c.jpg", 30)] {
+        conn.execute(
+            "INSERT INTO files(path, size, mtime) VALUES (?1, ?2, 0)",
+            rusqlite::params![p, size],
+        )
+        .unwrap();
+    }
+    let id = |p: &str| db::file_id(&conn, p).unwrap();
+    let x = db::ensure_collection(&conn, "X").unwrap();
+    let y = db::ensure_collection(&conn, "Y").unwrap();
+    db::add_file_to_collection(&conn, x, id("a.jpg")).unwrap();
+    db::add_file_to_collection(&conn, x, id("b.jpg")).unwrap();
+    db::add_file_to_collection(&conn, y, id("b.jpg")).unwrap();
+    db::add_file_to_collection(&conn, y, id("c.jpg")).unwrap();
+
+    let tag = db::ensure_tag_path(&conn, "trip/beach").unwrap();
+    conn.execute(
+        "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+        [id("a.jpg"), tag],
+    )
+    .unwrap();
+
+    let stats = db::collection_stats(&conn, "X").unwrap();
+    assert_eq!(stats.files, 2);
+    assert_eq!(stats.total_size, 30);
+    assert_eq!(stats.tags, vec![("trip/beach".to_string(), 1)]);
+
+    let diff = db::collection_diff(&conn, "X", "Y").unwrap();
+    assert_eq!(diff.only_a, vec!["a.jpg"]);
+    assert_eq!(diff.only_b, vec!["c.jpg"]);
+    assert_eq!(diff.shared, vec!["b.jpg"]);
+
+    assert!(db::collection_stats(&conn, "missing").is_err());
+}
+
+#[test]
+fn search_history_records_and_recalls() {
+    let conn = open_mem();
+    assert!(db::history_query(&conn, None).is_err());
+
+    db::record_search(&conn, "tag:a", 3).unwrap();
+    db::record_search(&conn, "fav:true", 0).unwrap();
+
+    let hist = db::search_history(&conn, 10).unwrap();
+    assert_eq!(hist.len(), 2);
+    assert_eq!(hist[0].query, "fav:true");
+    assert_eq!(hist[1].hits, 3);
+
+    assert_eq!(db::history_query(&conn, None).unwrap(), "fav:true");
+    assert_eq!(db::history_query(&conn, Some(hist[1].id)).unwrap(), "tag:a");
+    assert!(db::history_query(&conn, Some(99)).is_err());
+}
+
+#[test]
+fn rating_and_favorite_roundtrip() {
+    let conn = open_mem();
+    conn.execute(
+        "INSERT INTO files(path, size, mtime) VALUES ('/x/a.txt',0,0), ('/y/b.md',0,0)",
+        [],
+    )
+    .unwrap();
+
+    let txt = db::match_files(&conn, "*.txt").unwrap();
+    assert_eq!(txt.len(), 1);
+    assert_eq!(txt[0].1, "/x/a.txt");
+    assert_eq!(db::match_files(&conn, "/y/*").unwrap().len(), 1);
+
+    db::set_rating(&conn, txt[0].0, 3).unwrap();
+    assert!(db::set_rating(&conn, txt[0].0, 6).is_err());
+    let rating: Option<i64> = conn
+        .query_row("SELECT rating FROM files WHERE path='/x/a.txt'", [], |r| {
+            r.get(0)
+        })
+        .unwrap();
+    assert_eq!(rating, Some(3));
+
+    db::set_favorite(&conn, txt[0].0, true).unwrap();
+    assert_eq!(db::list_favorites(&conn).unwrap(), vec!["/x/a.txt"]);
+    db::set_favorite(&conn, txt[0].0, false).unwrap();
+    assert!(db::list_favorites(&conn).unwrap().is_empty());
+}
+
 #[test]
 fn views_save_and_query() {
     let conn = open_mem();
@@ -178,6 +310,7 @@ fn views_save_and_query() {
 }
 
 #[test]
+#[cfg(feature = "backup")]
 fn backup_and_restore_cycle() {
     let tmp = tempdir().unwrap();
     let db_path = tmp.path().join("data.db");
@@ -270,8 +403,8 @@ fn tables_exist_and_fts_triggers() {
     marlin.scan(&[&file_dir]).unwrap();
     marlin.tag("*.txt", "foo/bar").unwrap();
 
-    let fid = db::file_id(marlin.conn(), file_path.to_str().unwrap()).unwrap();
-    db::upsert_attr(marlin.conn(), fid, "color", "blue").unwrap();
+    let fid = db::file_id(&marlin.conn(), file_path.to_str().unwrap()).unwrap();
+    db::upsert_attr(&marlin.conn(), fid, "color", "blue").unwrap();
 
     // The FTS index is contentless, so columns return empty strings. Instead
     // verify that searching for our tag and attribute yields the file path.
@@ -295,3 +428,1056 @@ fn tables_exist_and_fts_triggers() {
         .unwrap();
     assert!(hits_attr.contains(&file_path.to_string_lossy().into_owned()));
 }
+
+#[test]
+fn check_writable_detects_read_only_db() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("index.db");
+    let conn = db::open(&path).unwrap();
+    db::check_writable(&conn).unwrap();
+
+    let ro =
+        Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+    let err = db::check_writable(&ro).unwrap_err();
+    assert!(format!("{err:#}").contains("not writable"));
+
+    let empty = Connection::open_in_memory().unwrap();
+    assert!(db::check_writable(&empty).is_err());
+}
+
+#[test]
+fn tag_and_dir_usage_stats() {
+    let conn = open_mem();
+    for (path, size, mtime) in [
+        ("/w/docs/a.md", 100, 10),
+        ("/w/docs/sub/b.md", 200, 1_000),
+        ("/w/media/c.mp4", 5_000, 1_000),
+        ("/w/top.txt", 1, 1_000),
+        ("/elsewhere/x", 9, 1_000),
+    ] {
+        conn.execute(
+            "INSERT INTO files(path, size, mtime) VALUES (?1, ?2, ?3)",
+            rusqlite::params![path, size, mtime],
+        )
+        .unwrap();
+    }
+    let id = |p: &str| -> i64 {
+        conn.query_row("SELECT id FROM files WHERE path = ?1", [p], |r| r.get(0))
+            .unwrap()
+    };
+    let md = db::ensure_tag_path(&conn, "project/md").unwrap();
+    for p in ["/w/docs/a.md", "/w/docs/sub/b.md"] {
+        conn.execute(
+            "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+            [id(p), md],
+        )
+        .unwrap();
+    }
+
+    let tags = db::tag_stats(&conn, 500).unwrap();
+    assert_eq!(
+        tags,
+        vec![db::UsageStats {
+            name: "project/md".into(),
+            files: 2,
+            total_size: 300,
+            recent_files: 1,
+            recent_size: 200,
+        }]
+    );
+
+    let dirs = db::dir_stats(&conn, std::path::Path::new("/w"), 1, 500).unwrap();
+    let names: Vec<_> = dirs.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, ["media", "docs", "."]);
+    assert_eq!((dirs[1].files, dirs[1].total_size), (2, 300));
+    assert_eq!((dirs[1].recent_files, dirs[1].recent_size), (1, 200));
+
+    let deep = db::dir_stats(&conn, std::path::Path::new("/w"), 2, 0).unwrap();
+    assert!(deep.iter().any(|d| d.name == "docs/sub" && d.files == 1));
+}
+
+#[test]
+fn stale_files_filters_by_age_and_size() {
+    let conn = open_mem();
+    for (path, size, mtime) in [
+        ("/old-big", 500, 10),
+        ("/old-small", 5, 20),
+        ("/new-big", 500, 1_000),
+    ] {
+        conn.execute(
+            "INSERT INTO files(path, size, mtime) VALUES (?1, ?2, ?3)",
+            rusqlite::params![path, size, mtime],
+        )
+        .unwrap();
+    }
+
+    let paths = |v: Vec<db::StaleFile>| v.into_iter().map(|f| f.path).collect::<Vec<_>>();
+    assert_eq!(
+        paths(db::stale_files(&conn, 100, 0).unwrap()),
+        ["/old-big", "/old-small"]
+    );
+    assert_eq!(
+        paths(db::stale_files(&conn, 100, 100).unwrap()),
+        ["/old-big"]
+    );
+}
+
+#[test]
+fn rename_directory_moves_only_that_subtree() {
+    let mut conn = open_mem();
+    for p in [
+        "/w/a_b/x.txt",
+        "/w/axb/y.txt",
+        "/w/a_b2/z.txt",
+        "/w/a_b/deep/a_b/q.txt",
+    ] {
+        conn.execute("INSERT INTO files(path) VALUES (?1)", [p])
+            .unwrap();
+    }
+
+    // trailing slash and doubled separators are normalized away
+    db::rename_directory(&mut conn, "/w//a_b/", "/w/c").unwrap();
+
+    let mut stmt = conn
+        .prepare("SELECT path FROM files ORDER BY path")
+        .unwrap();
+    let paths: Vec<String> = stmt
+        .query_map([], |r| r.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "/w/a_b2/z.txt",
+            "/w/axb/y.txt",
+            "/w/c/deep/a_b/q.txt",
+            "/w/c/x.txt"
+        ]
+    );
+}
+
+/* ─── content identity ─────────────────────────────────────────────── */
+
+fn tags_of(conn: &Connection, fid: i64) -> Vec<String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.name FROM file_tags ft JOIN tags t ON t.id = ft.tag_id
+              WHERE ft.file_id = ?1 ORDER BY t.name",
+        )
+        .unwrap();
+    stmt.query_map([fid], |r| r.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+fn attr_of(conn: &Connection, fid: i64, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM attributes WHERE file_id = ?1 AND key = ?2",
+        rusqlite::params![fid, key],
+        |r| r.get(0),
+    )
+    .ok()
+}
+
+fn insert_hashed(conn: &Connection, path: &str, hash: &str) -> i64 {
+    conn.execute(
+        "INSERT INTO files(path, size, mtime, hash) VALUES (?1, 0, 0, ?2)",
+        [path, hash],
+    )
+    .unwrap();
+    conn.last_insert_rowid()
+}
+
+#[test]
+fn equal_hashes_share_content_but_not_tags_by_default() {
+    let conn = open_mem();
+    assert_eq!(db::identity(&conn).unwrap(), db::Identity::Path);
+    let a = insert_hashed(&conn, "/a.txt", "h1");
+    let b = insert_hashed(&conn, "/copy/a.txt", "h1");
+    insert_hashed(&conn, "/other.txt", "h2");
+
+    assert_eq!(db::aliases(&conn, a).unwrap(), vec!["/copy/a.txt"]);
+    let tag = db::ensure_tag_path(&conn, "keep").unwrap();
+    conn.execute("INSERT INTO file_tags VALUES (?1, ?2)", [a, tag])
+        .unwrap();
+    assert!(tags_of(&conn, b).is_empty());
+}
+
+#[test]
+fn content_identity_mirrors_metadata_across_aliases() {
+    let mut conn = open_mem();
+    let a = insert_hashed(&conn, "/a.txt", "h1");
+    let tag = db::ensure_tag_path(&conn, "keep").unwrap();
+    conn.execute("INSERT INTO file_tags VALUES (?1, ?2)", [a, tag])
+        .unwrap();
+    db::upsert_attr(&conn, a, "owner", "ann").unwrap();
+    let b = insert_hashed(&conn, "/b.txt", "h1");
+    assert!(tags_of(&conn, b).is_empty());
+
+    // switching merges what is already there …
+    db::set_identity(&mut conn, db::Identity::Content).unwrap();
+    assert_eq!(tags_of(&conn, b), vec!["keep"]);
+    assert_eq!(attr_of(&conn, b, "owner").as_deref(), Some("ann"));
+
+    // … new copies inherit, and changes are mirrored
+    let c = insert_hashed(&conn, "/c.txt", "h1");
+    assert_eq!(tags_of(&conn, c), vec!["keep"]);
+    db::upsert_attr(&conn, c, "owner", "bob").unwrap();
+    assert_eq!(attr_of(&conn, a, "owner").as_deref(), Some("bob"));
+    conn.execute("DELETE FROM file_tags WHERE file_id = ?1", [b])
+        .unwrap();
+    assert!(tags_of(&conn, a).is_empty() && tags_of(&conn, c).is_empty());
+
+    // dropping a file from the index keeps its aliases' metadata
+    db::upsert_attr(&conn, a, "state", "done").unwrap();
+    conn.execute("DELETE FROM files WHERE id = ?1", [a])
+        .unwrap();
+    assert_eq!(attr_of(&conn, c, "state").as_deref(), Some("done"));
+}
+
+/* ─── startup fast path ─────────────────────────────────────────────── */
+
+#[test]
+fn open_records_schema_version_in_header() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("fast.db");
+    let conn = db::open(&path).unwrap();
+    let v: i32 = conn
+        .query_row("PRAGMA user_version", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(v, db::SCHEMA_VERSION);
+    drop(conn);
+
+    // an index from before the header was written still goes through
+    // the full check once
+    let conn = Connection::open(&path).unwrap();
+    conn.pragma_update(None, "user_version", 0).unwrap();
+    drop(conn);
+    let conn = db::open(&path).unwrap();
+    let v: i32 = conn
+        .query_row("PRAGMA user_version", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(v, db::SCHEMA_VERSION);
+    assert_eq!(
+        db::current_schema_version(&conn).unwrap(),
+        db::SCHEMA_VERSION
+    );
+}
+
+/* ─── tuning presets ───────────────────────────────────────────────── */
+
+fn live(conn: &Connection, pragma: &str) -> String {
+    db::live_pragmas(conn)
+        .unwrap()
+        .into_iter()
+        .find(|(k, _)| *k == pragma)
+        .map(|(_, v)| v)
+        .unwrap()
+}
+
+#[test]
+fn tuning_preset_is_applied_on_open() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("tuned.db");
+    let conn = db::open(&path).unwrap();
+    assert_eq!(db::tuning(&conn).unwrap(), db::Tuning::Safe);
+    assert_eq!(live(&conn, "synchronous"), "FULL");
+
+    db::set_setting(&conn, "db.tuning", "fast").unwrap();
+    drop(conn);
+    let conn = db::open(&path).unwrap();
+    assert_eq!(live(&conn, "synchronous"), "OFF");
+    assert_eq!(live(&conn, "temp_store"), "MEMORY");
+    assert_eq!(live(&conn, "cache_size"), "-65536");
+
+    // a broken value must not lock users out of their index
+    db::set_setting(&conn, "db.tuning", "turbo").unwrap();
+    drop(conn);
+    let conn = db::open(&path).unwrap();
+    assert!(db::tuning(&conn).is_err());
+}
+
+#[test]
+fn tuning_names_round_trip() {
+    for t in db::Tuning::ALL {
+        assert_eq!(t.as_str().parse::<db::Tuning>().unwrap(), t);
+    }
+    assert!("turbo".parse::<db::Tuning>().is_err());
+}
+
+/* ─── FTS consistency ──────────────────────────────────────────────── */
+
+fn tag_ids(conn: &Connection, fid: i64, ids: &[i64]) {
+    for id in ids {
+        conn.execute(
+            "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+            [fid, *id],
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn verify_fts_finds_and_repairs_drift() {
+    let mut conn = open_mem();
+    let a = insert_hashed(&conn, "/docs/alpha.md", "h1");
+    let b = insert_hashed(&conn, "/docs/beta.md", "h2");
+    insert_hashed(&conn, "/docs/gamma.md", "h3");
+    let tag = db::ensure_tag_path(&conn, "project/md").unwrap();
+    tag_ids(&conn, a, &[tag]);
+    db::upsert_attr(&conn, b, "status", "draft").unwrap();
+    assert!(db::verify_fts_consistency(&conn, None)
+        .unwrap()
+        .is_consistent());
+
+    // tag renames have no trigger; rows missing or left behind
+    conn.execute("UPDATE tags SET name = 'markdown' WHERE id = ?1", [tag])
+        .unwrap();
+    conn.execute("DELETE FROM files_fts WHERE rowid = ?1", [b])
+        .unwrap();
+    conn.execute(
+        "INSERT INTO files_fts(rowid, path, tags_text, attrs_text) VALUES (99, '/gone', '', '')",
+        [],
+    )
+    .unwrap();
+
+    let report = db::verify_fts_consistency(&conn, None).unwrap();
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.missing, vec![b]);
+    assert_eq!(report.orphaned, vec![99]);
+    assert_eq!(report.stale, vec![a]);
+    assert_eq!(report.rowids(), vec![a, b, 99]);
+    assert_eq!(
+        db::verify_fts_consistency(&conn, Some(1)).unwrap().checked,
+        1
+    );
+
+    assert_eq!(db::repair_fts(&mut conn, &report.rowids()).unwrap(), 3);
+    assert!(db::verify_fts_consistency(&conn, None)
+        .unwrap()
+        .is_consistent());
+    let hit: i64 = conn
+        .query_row(
+            "SELECT rowid FROM files_fts WHERE files_fts MATCH 'tags_text:markdown'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(hit, a);
+}
+
+#[test]
+fn rename_trigger_keeps_full_tag_paths() {
+    let conn = open_mem();
+    let fid = insert_hashed(&conn, "/docs/a.md", "h1");
+    // `x` and `w` exist before `x/y`, so bare names would read "x w y"
+    let x = db::ensure_tag_path(&conn, "x").unwrap();
+    let w = db::ensure_tag_path(&conn, "w").unwrap();
+    let y = db::ensure_tag_path(&conn, "x/y").unwrap();
+    tag_ids(&conn, fid, &[x, w, y]);
+
+    db::update_file_path(&conn, "/docs/a.md", "/docs/b.md").unwrap();
+    let report = db::verify_fts_consistency(&conn, None).unwrap();
+    assert!(report.is_consistent(), "{report:?}");
+}
+
+/* ─── cache warm-up ────────────────────────────────────────────────── */
+
+#[test]
+fn warm_touches_every_target() {
+    let dir = tempdir().unwrap();
+    let conn = db::open(dir.path().join("warm.db")).unwrap();
+    let fid = insert_hashed(&conn, "/docs/report.md", "h1");
+    insert_hashed(&conn, "/docs/notes.md", "h2");
+    let tag = db::ensure_tag_path(&conn, "project").unwrap();
+    conn.execute(
+        "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+        [fid, tag],
+    )
+    .unwrap();
+    db::upsert_attr(&conn, fid, "status", "draft").unwrap();
+
+    let report = db::warm(&conn, false).unwrap();
+    let rows = |t: &str| report.targets.iter().find(|(n, _)| *n == t).unwrap().1;
+    assert_eq!(rows("files"), 2);
+    assert_eq!(rows("files.path index"), 2);
+    assert_eq!(rows("tags"), 1);
+    assert_eq!(rows("file_tags"), 1);
+    assert_eq!(rows("attributes"), 1);
+    assert!(rows("fts segments") > 0);
+    assert!(report.db_bytes > 0);
+    assert_eq!(report.mmap_bytes, None);
+
+    let mapped = db::warm(&conn, true).unwrap();
+    assert_eq!(mapped.mmap_bytes, Some(mapped.db_bytes));
+}
+
+/* ─── shared tag databases ─────────────────────────────────────────── */
+
+#[test]
+fn attached_databases_feed_tag_filters() {
+    use super::query;
+    let dir = tempdir().unwrap();
+    let shared_path = dir.path().join("team-tags.db");
+    {
+        let shared = db::open(&shared_path).unwrap();
+        let by_path = insert_hashed(&shared, "/p/plan.md", "h-plan");
+        let by_hash = insert_hashed(&shared, "/elsewhere/spec.md", "h-spec");
+        let tag = db::ensure_tag_path(&shared, "review/urgent").unwrap();
+        tag_ids(&shared, by_path, &[tag]);
+        tag_ids(&shared, by_hash, &[tag]);
+    }
+
+    let conn = open_mem();
+    insert_hashed(&conn, "/p/plan.md", "h-other");
+    insert_hashed(&conn, "/p/spec.md", "h-spec");
+    insert_hashed(&conn, "/p/notes.md", "h-notes");
+    assert!(query::search(&conn, "tag:review").unwrap().is_empty());
+
+    let name = db::attach_shared(&conn, &shared_path).unwrap();
+    assert_eq!(name, "team_tags");
+    assert_eq!(db::shared_schemas(&conn).unwrap(), ["team_tags"]);
+    let mut hits = query::search(&conn, "tag:review").unwrap();
+    hits.sort();
+    assert_eq!(hits, ["/p/plan.md", "/p/spec.md"]);
+    assert_eq!(
+        query::search(&conn, "-tag:review").unwrap(),
+        ["/p/notes.md"]
+    );
+
+    // read-only: writes through the attachment fail
+    assert!(conn.execute("DELETE FROM team_tags.file_tags", []).is_err());
+
+    db::detach_shared(&conn, &name).unwrap();
+    assert!(query::search(&conn, "tag:review").unwrap().is_empty());
+}
+
+#[test]
+fn attaching_a_non_marlin_database_fails() {
+    let dir = tempdir().unwrap();
+    let other = dir.path().join("other.db");
+    Connection::open(&other)
+        .unwrap()
+        .execute_batch("CREATE TABLE t(x)")
+        .unwrap();
+    let conn = open_mem();
+    let err = db::attach_shared(&conn, &other).unwrap_err();
+    assert!(err.to_string().contains("not a Marlin index"), "{err}");
+    assert!(db::shared_schemas(&conn).unwrap().is_empty());
+}
+
+/* ─── database location ────────────────────────────────────────────── */
+
+#[test]
+fn local_db_paths_are_not_network_filesystems() {
+    let dir = tempdir().unwrap();
+    assert_eq!(db::network_fs(&dir.path().join("index.db")), None);
+    // not created yet: judged by the nearest existing parent
+    assert_eq!(db::network_fs(&dir.path().join("a/b/index.db")), None);
+    assert_eq!(db::network_fs(std::path::Path::new(":memory:")), None);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn linux_statfs_magics_name_shared_filesystems() {
+    use super::db::location::linux_fs_name;
+    assert_eq!(linux_fs_name(0x6969), Some("nfs"));
+    assert_eq!(linux_fs_name(0xff53_4d42), Some("cifs"));
+    assert_eq!(linux_fs_name(0x6573_5546), Some("fuse"));
+    assert_eq!(linux_fs_name(0xef53), None); // ext4
+}
+
+/* ─── saved actions ────────────────────────────────────────────────── */
+
+#[test]
+fn actions_save_update_and_remove() {
+    let conn = open_mem();
+    db::save_action(&conn, "open-pdfs", "ext:pdf", "xdg-open {}").unwrap();
+    db::save_action(&conn, "open-pdfs", "tag:inbox", "xdg-open {}").unwrap();
+    db::save_action(&conn, "archive", "tag:done", "mv {} ~/archive").unwrap();
+
+    let names: Vec<String> = db::list_actions(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|a| a.name)
+        .collect();
+    assert_eq!(names, vec!["archive", "open-pdfs"]);
+    assert_eq!(db::action(&conn, "open-pdfs").unwrap().query, "tag:inbox");
+
+    db::remove_action(&conn, "archive").unwrap();
+    assert!(db::action(&conn, "archive").is_err());
+    assert!(db::remove_action(&conn, "archive").is_err());
+}
+
+/* ─── attribute expiry ─────────────────────────────────────────────── */
+
+#[test]
+fn expire_attrs_drops_only_past_values() {
+    let conn = open_mem();
+    let fid = insert_hashed(&conn, "/a.txt", "h1");
+    db::upsert_attr_expiring(&conn, fid, "hot", "yes", Some(100)).unwrap();
+    db::upsert_attr_expiring(&conn, fid, "review", "sprint", Some(200)).unwrap();
+    db::upsert_attr(&conn, fid, "owner", "ann").unwrap();
+
+    assert_eq!(db::expire_attrs(&conn, 150).unwrap(), 1);
+    assert_eq!(attr_of(&conn, fid, "hot"), None);
+    assert_eq!(attr_of(&conn, fid, "review").as_deref(), Some("sprint"));
+
+    // setting a value without TTL makes it permanent again
+    db::upsert_attr(&conn, fid, "review", "kept").unwrap();
+    assert_eq!(db::expire_attrs(&conn, i64::MAX).unwrap(), 0);
+    assert_eq!(attr_of(&conn, fid, "owner").as_deref(), Some("ann"));
+}
+
+#[test]
+fn open_sweeps_expired_attributes() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("ttl.db");
+    {
+        let conn = db::open(&path).unwrap();
+        let fid = insert_hashed(&conn, "/a.txt", "h1");
+        db::upsert_attr_expiring(&conn, fid, "downloaded", "yes", Some(1)).unwrap();
+    }
+    let conn = db::open(&path).unwrap();
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM attributes"), 0);
+}
+
+/* ─── strict tags ──────────────────────────────────────────────────── */
+
+fn count(conn: &Connection, sql: &str) -> i64 {
+    conn.query_row(sql, [], |r| r.get(0)).unwrap()
+}
+
+#[test]
+fn find_tag_path_never_creates() {
+    let conn = open_mem();
+    assert_eq!(db::find_tag_path(&conn, "project/md").unwrap(), None);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM tags"), 0);
+
+    let leaf = db::ensure_tag_path(&conn, "project/md").unwrap();
+    assert_eq!(db::find_tag_path(&conn, "project/md").unwrap(), Some(leaf));
+    assert_eq!(db::find_tag_path(&conn, "proejct/md").unwrap(), None);
+    assert!(db::find_tag_path(&conn, "project").unwrap().is_some());
+}
+
+#[test]
+fn strict_tags_setting_round_trips() {
+    let conn = open_mem();
+    assert!(!db::strict_tags(&conn).unwrap());
+    db::set_setting(&conn, "tags.strict", "true").unwrap();
+    assert!(db::strict_tags(&conn).unwrap());
+    let keys: Vec<String> = db::settings(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, vec!["identity", "sync.replica", "tags.strict"]);
+}
+
+#[test]
+fn tag_attach_and_detach_converge_in_any_order() {
+    use db::TagMerge;
+
+    // (attach?, at): attach@10, detach@20, attach@15, detach@20 again
+    let ops = [(true, 10), (false, 20), (true, 15), (false, 20)];
+    let permutations = [
+        [0, 1, 2, 3],
+        [3, 2, 1, 0],
+        [2, 0, 3, 1],
+        [1, 3, 0, 2],
+        [2, 3, 0, 0],
+    ];
+    for order in permutations {
+        let conn = open_mem();
+        let fid = insert_hashed(&conn, "/a.txt", "h");
+        let tag = db::ensure_tag_path(&conn, "draft").unwrap();
+        for i in order {
+            let (attach, at) = ops[i];
+            if attach {
+                db::attach_tag(&conn, fid, tag, at).unwrap();
+            } else {
+                db::detach_tag(&conn, fid, tag, at).unwrap();
+            }
+        }
+        let tagged: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM file_tags WHERE file_id = ?1)",
+                [fid],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!(!tagged, "order {order:?}");
+    }
+
+    let conn = open_mem();
+    let fid = insert_hashed(&conn, "/a.txt", "h");
+    let tag = db::ensure_tag_path(&conn, "draft").unwrap();
+    assert_eq!(
+        db::detach_tag(&conn, fid, tag, 5).unwrap(),
+        TagMerge::Unchanged
+    );
+    assert_eq!(
+        db::attach_tag(&conn, fid, tag, 4).unwrap(),
+        TagMerge::Superseded
+    );
+    // ties go to the attach
+    assert_eq!(
+        db::attach_tag(&conn, fid, tag, 5).unwrap(),
+        TagMerge::Changed
+    );
+    assert_eq!(
+        db::attach_tag(&conn, fid, tag, 5).unwrap(),
+        TagMerge::Unchanged
+    );
+    assert_eq!(
+        db::detach_tag(&conn, fid, tag, 5).unwrap(),
+        TagMerge::Superseded
+    );
+    assert_eq!(
+        db::detach_tag(&conn, fid, tag, 6).unwrap(),
+        TagMerge::Changed
+    );
+
+    // plain deletes leave a tombstone stamped with the current time
+    db::tag_files(&conn, &[(fid, "/a.txt".into())], "draft", false).unwrap();
+    conn.execute("DELETE FROM file_tags WHERE file_id = ?1", [fid])
+        .unwrap();
+    assert_eq!(
+        db::attach_tag(&conn, fid, tag, db::now_millis() - 60_000).unwrap(),
+        TagMerge::Superseded
+    );
+}
+
+#[test]
+fn prune_unused_tags_keeps_used_branches() {
+    let conn = open_mem();
+    let fid = insert_hashed(&conn, "/a.txt", "h1");
+    let used = db::ensure_tag_path(&conn, "project/alpha").unwrap();
+    db::ensure_tag_path(&conn, "archive/2019/old").unwrap();
+    conn.execute("INSERT INTO file_tags VALUES (?1, ?2)", [fid, used])
+        .unwrap();
+
+    // `project` itself is kept alive only by its used child
+    assert_eq!(db::prune_unused_tags(&conn).unwrap(), 3);
+    assert!(db::find_tag_path(&conn, "project/alpha").unwrap().is_some());
+    assert!(db::find_tag_path(&conn, "archive").unwrap().is_none());
+}
+
+/* ─── garbage collection ───────────────────────────────────────────── */
+
+#[test]
+fn gc_removes_orphans_and_unused_tags() {
+    let mut conn = open_mem();
+    let keep = insert_hashed(&conn, "/keep.txt", "h1");
+    let gone = insert_hashed(&conn, "/gone.txt", "h2");
+    let used = db::ensure_tag_path(&conn, "project/alpha").unwrap();
+    let stale = db::ensure_tag_path(&conn, "old/unused/leaf").unwrap();
+    for fid in [keep, gone] {
+        conn.execute("INSERT INTO file_tags VALUES (?1, ?2)", [fid, used])
+            .unwrap();
+        db::upsert_attr(&conn, fid, "owner", "ann").unwrap();
+    }
+    db::add_link(&conn, keep, gone, None).unwrap();
+    db::mark_dirty(&conn, gone).unwrap();
+
+    // simulate a deletion that bypassed the cascades
+    conn.pragma_update(None, "foreign_keys", "OFF").unwrap();
+    conn.execute("DELETE FROM files WHERE id = ?1", [gone])
+        .unwrap();
+    conn.pragma_update(None, "foreign_keys", "ON").unwrap();
+    conn.execute(
+        "INSERT INTO files_fts(rowid, path) VALUES (999, '/ghost')",
+        [],
+    )
+    .unwrap();
+
+    let report = db::gc(&mut conn, true).unwrap();
+    assert_eq!(report.tags, 3, "old/unused/leaf goes, project/alpha stays");
+    assert_eq!(report.file_tags, 1);
+    assert_eq!(report.attributes, 1);
+    assert_eq!(report.links, 1);
+    assert_eq!(report.fts_rows, 1);
+    assert_eq!(report.dirty, 1);
+    assert_eq!(report.contents, 1);
+    assert!(report.vacuumed_bytes.is_some());
+
+    assert_eq!(tags_of(&conn, keep), vec!["alpha"]);
+    assert_eq!(attr_of(&conn, keep, "owner").as_deref(), Some("ann"));
+    let missing: Option<i64> = conn
+        .query_row("SELECT id FROM tags WHERE id = ?1", [stale], |r| r.get(0))
+        .ok();
+    assert!(missing.is_none());
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT COUNT(*) FROM files_fts WHERE rowid NOT IN (SELECT id FROM files)"
+        ),
+        0
+    );
+
+    // nothing left to do the second time round
+    assert_eq!(db::gc(&mut conn, false).unwrap().total(), 0);
+}
+
+#[test]
+fn states_follow_the_transition_graph() {
+    let conn = db_with_files(&["/a.md", "/b.md"]);
+    let all = db::match_files(&conn, "*.md").unwrap();
+    let a = &all[..1];
+
+    // without transitions anything goes, and the first state is free
+    assert_eq!(db::set_file_states(&conn, &all, "draft").unwrap(), 2);
+    assert_eq!(db::set_file_states(&conn, &all, "draft").unwrap(), 0);
+    assert_eq!(db::set_file_states(&conn, a, "review").unwrap(), 1);
+
+    assert!(db::add_state_transition(&conn, "draft", "review").unwrap());
+    assert!(!db::add_state_transition(&conn, "draft", "review").unwrap());
+    db::add_state_transition(&conn, "review", "done").unwrap();
+    assert!(db::add_state_transition(&conn, "in review", "done").is_err());
+    assert_eq!(
+        db::state_transitions(&conn).unwrap(),
+        vec![
+            ("draft".to_string(), "review".to_string()),
+            ("review".to_string(), "done".to_string())
+        ]
+    );
+
+    // /b.md cannot skip review, so nothing changes
+    let err = db::set_file_states(&conn, &all, "done").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("/b.md: no transition from `draft` to `done`"));
+    assert_eq!(
+        db::file_state(&conn, all[0].0).unwrap().as_deref(),
+        Some("review")
+    );
+
+    assert_eq!(db::set_file_states(&conn, a, "done").unwrap(), 1);
+    let log: Vec<_> = db::state_log(&conn, &all)
+        .unwrap()
+        .into_iter()
+        .map(|c| format!("{} {}", c.path, c.state))
+        .collect();
+    assert_eq!(
+        log,
+        ["/a.md draft", "/a.md review", "/a.md done", "/b.md draft"]
+    );
+}
+
+#[test]
+fn reminders_are_listed_and_due_by_time() {
+    let conn = db_with_files(&["/a.md", "/b.md"]);
+    let a = db::file_id(&conn, "/a.md").unwrap();
+    let b = db::file_id(&conn, "/b.md").unwrap();
+    let late = db::add_reminder(&conn, a, 300, "late").unwrap();
+    let early = db::add_reminder(&conn, b, 100, " early ").unwrap();
+    assert!(db::add_reminder(&conn, a, 100, "").is_err());
+
+    let notes = |rs: Vec<db::Reminder>| rs.into_iter().map(|r| r.note).collect::<Vec<_>>();
+    assert_eq!(
+        notes(db::list_reminders(&conn, None).unwrap()),
+        ["early", "late"]
+    );
+    assert_eq!(notes(db::list_reminders(&conn, Some(a)).unwrap()), ["late"]);
+    assert_eq!(notes(db::due_reminders(&conn, 300).unwrap()), ["early"]);
+
+    // delivered reminders still count as due, but are no longer pending
+    db::mark_reminded(&conn, early, 150).unwrap();
+    assert_eq!(
+        notes(db::due_reminders(&conn, 301).unwrap()),
+        ["early", "late"]
+    );
+    let pending = db::pending_reminders(&conn, 301).unwrap();
+    assert_eq!(pending.iter().map(|r| r.id).collect::<Vec<_>>(), [late]);
+}
+
+#[test]
+fn tasks_are_listed_completed_and_searchable() {
+    let conn = db_with_files(&["/a.md", "/b.md"]);
+    let a = db::file_id(&conn, "/a.md").unwrap();
+    let b = db::file_id(&conn, "/b.md").unwrap();
+    let later = db::add_task(&conn, a, "later", Some(500), 0).unwrap();
+    let soon = db::add_task(&conn, b, " soon ", Some(100), 0).unwrap();
+    let undated = db::add_task(&conn, a, "someday", None, 5).unwrap();
+    assert!(db::add_task(&conn, a, "  ", None, 0).is_err());
+
+    let titles = |f: &TaskFilter| -> Vec<String> {
+        db::list_tasks(&conn, f)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.title)
+            .collect()
+    };
+    assert_eq!(titles(&TaskFilter::default()), ["soon", "later", "someday"]);
+    let due = TaskFilter {
+        due_before: Some(200),
+        ..Default::default()
+    };
+    assert_eq!(titles(&due), ["soon"]);
+
+    assert!(db::complete_task(&conn, soon, 150).unwrap());
+    assert!(!db::complete_task(&conn, soon, 160).unwrap());
+    assert!(db::complete_task(&conn, 999, 0).is_err());
+    let open = TaskFilter {
+        status: Some(TaskStatus::Open),
+        ..Default::default()
+    };
+    assert_eq!(titles(&open), ["later", "someday"]);
+    let done = db::list_tasks(
+        &conn,
+        &TaskFilter {
+            status: Some(TaskStatus::Done),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(done[0].done_at, Some(150));
+
+    assert_eq!(crate::query::search(&conn, "task:open").unwrap(), ["/a.md"]);
+    assert_eq!(crate::query::search(&conn, "task:done").unwrap(), ["/b.md"]);
+    assert_eq!(
+        crate::query::search(&conn, "task:overdue").unwrap(),
+        ["/a.md"]
+    );
+
+    db::remove_task(&conn, later).unwrap();
+    db::remove_task(&conn, undated).unwrap();
+    assert!(db::remove_task(&conn, later).is_err());
+    assert!(crate::query::search(&conn, "task:open").unwrap().is_empty());
+}
+
+#[test]
+fn diff_against_a_backup_reports_schema_counts_and_metadata() {
+    let tmp = tempdir().unwrap();
+    let conn = db::open(tmp.path().join("index.db")).unwrap();
+    for p in ["/docs/a.txt", "/docs/b.txt"] {
+        conn.execute(
+            "INSERT INTO files(path, size, mtime) VALUES (?1, 0, 0)",
+            [p],
+        )
+        .unwrap();
+    }
+    let files = db::match_files(&conn, "a.txt").unwrap();
+    db::tag_files(&conn, &files, "keep", true).unwrap();
+    let backup = tmp.path().join("backup.db");
+    conn.execute("VACUUM INTO ?1", [backup.to_str().unwrap()])
+        .unwrap();
+
+    db::tag_files(&conn, &files, "fresh", true).unwrap();
+    db::tag_files(
+        &conn,
+        &db::match_files(&conn, "b.txt").unwrap(),
+        "fresh",
+        true,
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO files(path, size, mtime) VALUES ('/docs/c.txt', 0, 0)",
+        [],
+    )
+    .unwrap();
+    conn.execute_batch("CREATE INDEX idx_files_size ON files(size)")
+        .unwrap();
+
+    let diff = db::diff_against(&conn, &backup, None).unwrap();
+    assert_eq!(
+        diff.schema,
+        vec![db::SchemaChange {
+            kind: "index".into(),
+            name: "idx_files_size".into(),
+            delta: db::Delta::Added,
+        }]
+    );
+    let files_count = diff.tables.iter().find(|t| t.table == "files").unwrap();
+    assert_eq!((files_count.other, files_count.live), (Some(2), Some(3)));
+    assert_eq!(files_count.delta(), 1);
+    assert!(diff
+        .tables
+        .iter()
+        .all(|t| !t.table.starts_with("files_fts_")));
+    assert!(diff.metadata.is_empty(), "metadata only for a scope");
+
+    // scoped to a.txt: b.txt's new tag stays out of the report
+    let diff = db::diff_against(&conn, &backup, Some("tag:keep")).unwrap();
+    let changes: Vec<_> = diff
+        .metadata
+        .iter()
+        .map(|c| format!("{} {} {}", c.path, c.key, c.new.is_some()))
+        .collect();
+    assert_eq!(changes, ["/docs/a.txt fresh true"]);
+}
+
+#[test]
+fn annotation_ranges_parse_lines_and_bytes() {
+    let parse = |s: &str| s.parse::<AnnotationRange>();
+    assert_eq!(parse("12").unwrap(), AnnotationRange::Lines(12, 12));
+    assert_eq!(parse("12-20").unwrap(), AnnotationRange::Lines(12, 20));
+    assert_eq!(parse("lines=3-4").unwrap(), AnnotationRange::Lines(3, 4));
+    assert_eq!(parse("bytes=0-99").unwrap(), AnnotationRange::Bytes(0, 99));
+    for bad in ["", "0", "20-12", "x-3", "pages=1-2"] {
+        assert!(parse(bad).is_err(), "{bad} parsed");
+    }
+    assert_eq!(AnnotationRange::Lines(7, 7).to_string(), "line 7");
+    assert_eq!(AnnotationRange::Bytes(1, 9).to_string(), "bytes 1-9");
+}
+
+#[test]
+fn annotations_are_listed_and_searchable_by_note() {
+    let conn = db_with_files(&["/a.md", "/b.md"]);
+    let a = db::file_id(&conn, "/a.md").unwrap();
+    let b = db::file_id(&conn, "/b.md").unwrap();
+    db::add_annotation(
+        &conn,
+        a,
+        "typo here",
+        Some(AnnotationRange::Lines(9, 9)),
+        false,
+    )
+    .unwrap();
+    db::add_annotation(&conn, a, " whole file ", None, false).unwrap();
+    db::add_annotation(
+        &conn,
+        a,
+        "key quote",
+        Some(AnnotationRange::Lines(2, 4)),
+        true,
+    )
+    .unwrap();
+    db::add_annotation(
+        &conn,
+        b,
+        "intro",
+        Some(AnnotationRange::Bytes(0, 80)),
+        false,
+    )
+    .unwrap();
+    assert!(db::add_annotation(&conn, a, " ", None, false).is_err());
+
+    let on_a = db::annotations(&conn, a).unwrap();
+    let notes: Vec<_> = on_a.iter().map(|n| n.note.as_str()).collect();
+    assert_eq!(notes, ["whole file", "key quote", "typo here"]);
+    assert!(on_a[1].highlight && !on_a[2].highlight);
+    assert_eq!(
+        db::annotations(&conn, b).unwrap()[0].range,
+        Some(AnnotationRange::Bytes(0, 80))
+    );
+
+    let search = |q: &str| crate::query::search(&conn, q).unwrap();
+    assert_eq!(search("typo"), ["/a.md"]);
+    assert_eq!(search("note:intro"), ["/b.md"]);
+    // tags and attributes still reach the rebuilt FTS row
+    db::upsert_attr(&conn, b, "status", "final").unwrap();
+    assert_eq!(search("intro final"), ["/b.md"]);
+    assert!(db::verify_fts_consistency(&conn, None)
+        .unwrap()
+        .is_consistent());
+
+    conn.execute("DELETE FROM annotations WHERE file_id = ?1", [a])
+        .unwrap();
+    assert!(search("typo").is_empty());
+}
+
+#[test]
+fn events_are_listed_in_date_order_and_by_window() {
+    let conn = db_with_files(&["/a.md", "/b.md"]);
+    let a = db::file_id(&conn, "/a.md").unwrap();
+    let b = db::file_id(&conn, "/b.md").unwrap();
+    db::add_event(&conn, a, 300, None, "retro").unwrap();
+    db::add_event(&conn, b, 100, Some(250), "offsite").unwrap();
+    db::add_event(&conn, a, 100, Some(250), " offsite ").unwrap();
+    assert!(db::add_event(&conn, a, 100, Some(50), "backwards").is_err());
+    assert!(db::add_event(&conn, a, 100, None, "").is_err());
+
+    let list = |f: &EventFilter| -> Vec<(String, String)> {
+        db::list_events(&conn, f)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.description, e.path))
+            .collect()
+    };
+    let pair = |d: &str, p: &str| (d.to_string(), p.to_string());
+    assert_eq!(
+        list(&EventFilter::default()),
+        [
+            pair("offsite", "/a.md"),
+            pair("offsite", "/b.md"),
+            pair("retro", "/a.md")
+        ]
+    );
+    // an event still running at `from` is kept
+    let window = EventFilter {
+        from: Some(200),
+        to: Some(300),
+        ..Default::default()
+    };
+    assert_eq!(
+        list(&window),
+        [pair("offsite", "/a.md"), pair("offsite", "/b.md")]
+    );
+    let on_b = EventFilter {
+        file_ids: Some(vec![b]),
+        ..Default::default()
+    };
+    assert_eq!(list(&on_b), [pair("offsite", "/b.md")]);
+
+    conn.execute("DELETE FROM files WHERE id = ?1", [a])
+        .unwrap();
+    assert_eq!(list(&EventFilter::default()).len(), 1);
+}
+
+#[test]
+fn writes_are_traced_with_their_file_and_rows() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+    impl std::io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let log = Capture::default();
+    let writer = log.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, || {
+        let mut conn = open_mem();
+        db::trace_connection(&mut conn, true);
+        conn.execute(
+            "INSERT INTO files(path, size, mtime) VALUES ('/n/a.md', 0, 0)",
+            [],
+        )
+        .unwrap();
+        let fid = db::file_id(&conn, "/n/a.md").unwrap();
+        db::upsert_attr(&conn, fid, "status", "draft").unwrap();
+        db::update_file_path(&conn, "/n/a.md", "/n/b.md").unwrap();
+        crate::lock::lock_files(&conn, &[(fid, "/n/b.md".into())]).unwrap();
+    });
+
+    let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+    let write = log
+        .lines()
+        .find(|l| l.contains(r#"op="upsert_attr""#) && l.contains("db write"))
+        .expect("upsert_attr traced");
+    assert!(write.contains("rows_affected=1"), "{write}");
+    assert!(log
+        .lines()
+        .any(|l| l.contains(r#"op="update_file_path""#) && l.contains(r#"path="/n/a.md""#)));
+    assert!(log
+        .lines()
+        .any(|l| l.contains(r#"op="lock_files""#) && l.contains("rows_affected=1")));
+    // with trace_sql the statements show up too, inside the write's span
+    assert!(log
+        .lines()
+        .any(|l| l.contains(r#"op="upsert_attr""#) && l.contains("sql: INSERT INTO attributes")));
+}
@@ -0,0 +1,182 @@
+//! Everything the index knows about one file, gathered in one place
+//! (`marlin explain-file`, [`Marlin::describe`](crate::Marlin::describe)).
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db::{
+    self, Annotation, EventFilter, FileEvent, FileVersion, Reminder, StateChange, Task, TaskFilter,
+};
+use crate::utils::{normalize_path, PathNorm};
+
+/// A link to or from the described file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkInfo {
+    /// The file at the other end.
+    pub path: String,
+    pub link_type: Option<String>,
+}
+
+/// An attribute, with its expiry (Unix seconds) if it has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrInfo {
+    pub key: String,
+    pub value: String,
+    pub expires_at: Option<i64>,
+}
+
+/// What [`describe`] found.  Times are Unix seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    pub id: i64,
+    pub path: String,
+    pub size: Option<i64>,
+    pub mtime: Option<i64>,
+    pub hash: Option<String>,
+    pub first_indexed_at: Option<i64>,
+    pub last_seen_at: Option<i64>,
+    pub rating: Option<u8>,
+    pub favorite: bool,
+    pub locked: bool,
+    /// Other paths with the same content.
+    pub aliases: Vec<String>,
+    /// Full tag paths (`project/alpha`), sorted.
+    pub tags: Vec<String>,
+    pub attributes: Vec<AttrInfo>,
+    pub labels: Vec<String>,
+    pub links_out: Vec<LinkInfo>,
+    pub links_in: Vec<LinkInfo>,
+    pub collections: Vec<String>,
+    /// The current state is the last entry.
+    pub states: Vec<StateChange>,
+    pub tasks: Vec<Task>,
+    pub reminders: Vec<Reminder>,
+    pub annotations: Vec<Annotation>,
+    /// Calendar events, in date order.
+    pub events: Vec<FileEvent>,
+    /// Content versions, oldest first.
+    pub versions: Vec<FileVersion>,
+}
+
+impl FileReport {
+    /// The file's current workflow state.
+    pub fn state(&self) -> Option<&str> {
+        self.states.last().map(|s| s.state.as_str())
+    }
+}
+
+/// Find `path` in the index – as stored, or else as the absolute path it
+/// names on disk.
+pub fn resolve(conn: &Connection, path: &str) -> Result<(i64, String)> {
+    let lookup = |p: &str| -> Result<Option<i64>> {
+        Ok(conn
+            .query_row("SELECT id FROM files WHERE path = ?1", [p], |r| r.get(0))
+            .optional()?)
+    };
+    if let Some(id) = lookup(path)? {
+        return Ok((id, path.to_string()));
+    }
+    if let Ok(abs) = Path::new(path).canonicalize() {
+        let stored = normalize_path(&abs, &PathNorm::default());
+        if let Some(id) = lookup(&stored)? {
+            return Ok((id, stored));
+        }
+    }
+    bail!("file not indexed: {path}")
+}
+
+/// Everything known about the indexed file at `path` (see [`resolve`]).
+pub fn describe(conn: &Connection, path: &str) -> Result<FileReport> {
+    let (id, path) = resolve(conn, path)?;
+    let (size, mtime, hash, first_indexed_at, last_seen_at, rating, favorite) = conn.query_row(
+        "SELECT size, mtime, hash, first_indexed_at, last_seen_at, rating, favorite
+           FROM files WHERE id = ?1",
+        [id],
+        |r| {
+            Ok((
+                r.get(0)?,
+                r.get(1)?,
+                r.get(2)?,
+                r.get(3)?,
+                r.get(4)?,
+                r.get(5)?,
+                r.get::<_, i64>(6)? != 0,
+            ))
+        },
+    )?;
+
+    let links = |sql: &str| -> Result<Vec<LinkInfo>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([id], |r| {
+            Ok(LinkInfo {
+                path: r.get(0)?,
+                link_type: r.get(1)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    };
+    let links_out = links(
+        "SELECT d.path, l.type FROM links l JOIN files d ON d.id = l.dst_file_id
+          WHERE l.src_file_id = ?1 ORDER BY d.path",
+    )?;
+    let links_in = links(
+        "SELECT s.path, l.type FROM links l JOIN files s ON s.id = l.src_file_id
+          WHERE l.dst_file_id = ?1 ORDER BY s.path",
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT c.name FROM collection_files cf JOIN collections c ON c.id = cf.collection_id
+          WHERE cf.file_id = ?1 ORDER BY c.name",
+    )?;
+    let collections = stmt
+        .query_map([id], |r| r.get(0))?
+        .collect::<std::result::Result<Vec<String>, _>>()?;
+
+    let file = [(id, path.clone())];
+    Ok(FileReport {
+        id,
+        size,
+        mtime,
+        hash,
+        first_indexed_at,
+        last_seen_at,
+        rating,
+        favorite,
+        locked: crate::lock::is_locked(conn, id)?,
+        aliases: db::aliases(conn, id)?,
+        tags: db::file_tag_paths(conn, id)?,
+        attributes: db::file_attrs(conn, id)?
+            .into_iter()
+            .map(|(key, value, expires_at)| AttrInfo {
+                key,
+                value,
+                expires_at,
+            })
+            .collect(),
+        labels: db::file_labels(conn, id)?,
+        links_out,
+        links_in,
+        collections,
+        states: db::state_log(conn, &file)?,
+        tasks: db::list_tasks(
+            conn,
+            &TaskFilter {
+                file_ids: Some(vec![id]),
+                ..Default::default()
+            },
+        )?,
+        reminders: db::list_reminders(conn, Some(id))?,
+        annotations: db::annotations(conn, id)?,
+        versions: db::versions(conn, id)?,
+        events: db::list_events(
+            conn,
+            &EventFilter {
+                file_ids: Some(vec![id]),
+                ..Default::default()
+            },
+        )?,
+        path,
+    })
+}
@@ -0,0 +1,85 @@
+// libmarlin/src/describe_tests.rs
+
+use super::db;
+use super::describe::{describe, LinkInfo};
+use super::Marlin;
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn report_gathers_every_kind_of_metadata() {
+    let conn = db::open(":memory:").unwrap();
+    for p in ["/n/spec.md", "/n/ref.md"] {
+        conn.execute(
+            "INSERT INTO files(path, size, mtime) VALUES (?1, 42, 0)",
+            [p],
+        )
+        .unwrap();
+    }
+    let spec = db::file_id(&conn, "/n/spec.md").unwrap();
+    let refd = db::file_id(&conn, "/n/ref.md").unwrap();
+
+    let tag = db::ensure_tag_path(&conn, "project/alpha").unwrap();
+    db::attach_tag(&conn, spec, tag, 1).unwrap();
+    db::upsert_attr(&conn, spec, "owner", "ana").unwrap();
+    db::add_label(&conn, spec, "red").unwrap();
+    db::add_link(&conn, spec, refd, Some("cites")).unwrap();
+    db::add_link(&conn, refd, spec, None).unwrap();
+    let coll = db::ensure_collection(&conn, "reading").unwrap();
+    db::add_file_to_collection(&conn, coll, spec).unwrap();
+    db::set_file_states(&conn, &[(spec, "/n/spec.md".into())], "draft").unwrap();
+    db::add_task(&conn, spec, "review", None, 0).unwrap();
+    db::add_reminder(&conn, spec, 100, "send it").unwrap();
+    db::add_annotation(&conn, spec, "check the numbers", None, true).unwrap();
+    db::add_event(&conn, spec, 200, None, "launch").unwrap();
+
+    let r = describe(&conn, "/n/spec.md").unwrap();
+    assert_eq!(r.id, spec);
+    assert_eq!(r.size, Some(42));
+    assert_eq!(r.tags, ["project/alpha"]);
+    assert_eq!(r.attributes[0].key, "owner");
+    assert_eq!(r.labels, ["red"]);
+    assert_eq!(
+        r.links_out,
+        [LinkInfo {
+            path: "/n/ref.md".into(),
+            link_type: Some("cites".into())
+        }]
+    );
+    assert_eq!(r.links_in[0].path, "/n/ref.md");
+    assert_eq!(r.collections, ["reading"]);
+    assert_eq!(r.state(), Some("draft"));
+    assert_eq!(r.tasks[0].title, "review");
+    assert_eq!(r.reminders[0].note, "send it");
+    assert!(r.annotations[0].highlight);
+    assert_eq!(r.events[0].description, "launch");
+    assert!(!r.locked && !r.favorite);
+
+    let other = describe(&conn, "/n/ref.md").unwrap();
+    assert!(other.tags.is_empty() && other.state().is_none());
+    assert!(describe(&conn, "/n/missing.md")
+        .unwrap_err()
+        .to_string()
+        .contains("file not indexed"));
+}
+
+#[test]
+fn marlin_describe_accepts_paths_on_disk() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path().join("docs");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("a.txt");
+    fs::write(&file, "hello").unwrap();
+
+    let mut m = Marlin::open_at(tmp.path().join("index.db")).unwrap();
+    m.scan(&[&dir]).unwrap();
+    m.tag("*.txt", "inbox").unwrap();
+
+    // a path with `..` in it names the same file as the stored one
+    let indirect = dir.join("..").join("docs").join("a.txt");
+    let r = m.describe(&indirect.to_string_lossy()).unwrap();
+    assert!(r.path.ends_with("a.txt"));
+    assert_eq!(r.size, Some(5));
+    assert_eq!(r.tags, ["inbox"]);
+    assert!(r.first_indexed_at.is_some());
+}
@@ -0,0 +1,359 @@
+//! Vector embeddings of indexed files for concept search
+//! (`marlin search --semantic`).
+//!
+//! An [`Embedder`] turns text into a vector.  [`embed_files`] stores one
+//! vector per file and model in the `embeddings` table, computed from the
+//! file name and the start of the body (where the root profile allows
+//! reading it); [`semantic_search`] embeds the query with the same model
+//! and ranks every stored vector by cosine similarity – brute force, which
+//! is fine up to a few hundred thousand files.
+//!
+//! Two embedders ship with Marlin:
+//!
+//! * [`HashEmbedder`] needs no model at all.  It hashes words and word
+//!   fragments into a fixed number of buckets, so it finds texts sharing
+//!   vocabulary (`invoice` ~ `invoices`), not meaning.
+//! * [`CommandEmbedder`] runs a program for every text, which is how local
+//!   embedding models are plugged in: the text arrives on stdin and the
+//!   program prints the vector as numbers separated by whitespace or
+//!   commas (a JSON array works).
+//!
+//! Vectors of different models never mix; each model's vectors are
+//! computed and searched on their own.
+
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::{debug, warn};
+
+use crate::db::Mutation;
+use crate::roots;
+use crate::utils::read_text_sample;
+
+/// How much of a body goes into its embedding.
+pub const SAMPLE_BYTES: u64 = 16 * 1024;
+
+/// Turns text into a vector.  Vectors of one model must all have the same
+/// length.
+pub trait Embedder {
+    /// Name the vectors are stored under; changing the model (or its
+    /// settings) must change the name.
+    fn model(&self) -> &str;
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+}
+
+/// Feature-hashing embedder: words and character trigrams hashed into
+/// `dims` signed buckets, L2-normalised.  Needs no model files.
+#[derive(Debug, Clone)]
+pub struct HashEmbedder {
+    dims: usize,
+    model: String,
+}
+
+impl HashEmbedder {
+    pub fn new(dims: usize) -> Self {
+        Self {
+            dims: dims.max(1),
+            model: format!("hash-{dims}"),
+        }
+    }
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl Embedder for HashEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut v = vec![0f32; self.dims];
+        let mut add = |feature: &str, weight: f32| {
+            let h = fnv1a(feature);
+            let sign = if h >> 63 == 0 { 1.0 } else { -1.0 };
+            v[(h % self.dims as u64) as usize] += sign * weight;
+        };
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+        {
+            let word = word.to_lowercase();
+            add(&word, 1.0);
+            let padded: Vec<char> = format!("^{word}$").chars().collect();
+            for tri in padded.windows(3) {
+                add(&tri.iter().collect::<String>(), 0.5);
+            }
+        }
+        normalize(&mut v);
+        Ok(v)
+    }
+}
+
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Runs a command line once per text: the text on stdin, the vector on
+/// stdout.  The model name is `cmd:` plus the command line.
+#[derive(Debug, Clone)]
+pub struct CommandEmbedder {
+    argv: Vec<String>,
+    model: String,
+}
+
+impl CommandEmbedder {
+    pub fn new(command: &str) -> Result<Self> {
+        let argv = shlex::split(command)
+            .filter(|a| !a.is_empty())
+            .with_context(|| format!("cannot parse embedder command `{command}`"))?;
+        Ok(Self {
+            argv,
+            model: format!("cmd:{command}"),
+        })
+    }
+}
+
+impl Embedder for CommandEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut child = Command::new(&self.argv[0])
+            .args(&self.argv[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("starting embedder `{}`", self.argv[0]))?;
+        // written from a thread so a program that prints before it has
+        // read everything cannot deadlock us
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        let input = text.to_owned();
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let mut out = String::new();
+        child
+            .stdout
+            .take()
+            .expect("piped stdout")
+            .read_to_string(&mut out)?;
+        let status = child.wait()?;
+        // a program that ignores its input closes the pipe early; only its
+        // exit status counts
+        let _ = writer.join();
+        if !status.success() {
+            bail!("embedder `{}` failed ({status})", self.argv[0]);
+        }
+        parse_vector(&out)
+    }
+}
+
+/// Numbers separated by whitespace or commas, optionally in brackets.
+pub fn parse_vector(s: &str) -> Result<Vec<f32>> {
+    let v = s
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            t.parse::<f32>()
+                .with_context(|| format!("embedder printed `{t}`, not a number"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if v.is_empty() {
+        bail!("embedder printed no vector");
+    }
+    Ok(v)
+}
+
+/// Cosine similarity, 0 for vectors of different length or zero vectors.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let na = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let nb = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot / (na * nb)
+    }
+}
+
+fn to_blob(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn from_blob(b: &[u8]) -> Vec<f32> {
+    b.chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Outcome of [`embed_files`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EmbedReport {
+    /// Vectors computed and stored.
+    pub embedded: usize,
+    /// Files whose vector was computed from the current mtime already.
+    pub up_to_date: usize,
+    /// Files gone from disk.
+    pub missing: usize,
+}
+
+/// Store `embedder`'s vector for each of `files` (`(id, path)` pairs as
+/// returned by [`crate::db::match_files`]).  Files whose vector was
+/// computed at their current indexed mtime are skipped unless `force`.  Every vector is
+/// committed on its own, so an interrupted run keeps its progress.
+pub fn embed_files(
+    conn: &Connection,
+    embedder: &dyn Embedder,
+    files: &[(i64, String)],
+    force: bool,
+) -> Result<EmbedReport> {
+    let profiles = roots::list(conn)?;
+    let model = embedder.model();
+    let mut report = EmbedReport::default();
+    for (id, path) in files {
+        let mtime: i64 =
+            conn.query_row("SELECT mtime FROM files WHERE id = ?1", [id], |r| r.get(0))?;
+        let stored: Option<i64> = conn
+            .query_row(
+                "SELECT mtime FROM embeddings WHERE file_id = ?1 AND model = ?2",
+                params![id, model],
+                |r| r.get(0),
+            )
+            .optional()?;
+        if !force && stored == Some(mtime) {
+            report.up_to_date += 1;
+            continue;
+        }
+
+        let p = Path::new(path);
+        if !p.exists() {
+            debug!(file = %path, "missing, not embedded");
+            report.missing += 1;
+            continue;
+        }
+        let may_read = roots::innermost(&profiles, p).is_none_or(|r| r.index_content);
+        let body = may_read
+            .then(|| std::fs::File::open(p).ok())
+            .flatten()
+            .and_then(|f| read_text_sample(f, SAMPLE_BYTES));
+        let text = embed_text(p, body.as_deref());
+
+        let v = embedder
+            .embed(&text)
+            .with_context(|| format!("embedding {path}"))?;
+        let m = Mutation::new("embed_file").file_id(*id).path(path);
+        m.done(conn.execute(
+            "INSERT INTO embeddings(file_id, model, vector, mtime) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(file_id, model) DO UPDATE
+                SET vector = excluded.vector, mtime = excluded.mtime",
+            params![id, model, to_blob(&v), mtime],
+        )?);
+        report.embedded += 1;
+    }
+    Ok(report)
+}
+
+/// The file name with its separators turned into spaces, then the body.
+fn embed_text(path: &Path, body: Option<&str>) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().replace(['_', '-', '.'], " "))
+        .unwrap_or_default();
+    match body {
+        Some(b) => format!("{name}\n{b}"),
+        None => name,
+    }
+}
+
+/// A file ranked by [`semantic_search`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SemanticHit {
+    pub file_id: i64,
+    pub path: String,
+    /// Cosine similarity to the query, at most 1.
+    pub score: f32,
+}
+
+/// The `limit` files whose `embedder` vectors are closest to `text`, best
+/// first.  Files without a vector of this model are not considered, nor
+/// are those not similar at all (score ≤ 0).
+pub fn semantic_search(
+    conn: &Connection,
+    embedder: &dyn Embedder,
+    text: &str,
+    limit: usize,
+) -> Result<Vec<SemanticHit>> {
+    let model = embedder.model();
+    let q = embedder.embed(text)?;
+    let mut stmt = conn.prepare(
+        "SELECT e.file_id, f.path, e.vector
+           FROM embeddings e JOIN files f ON f.id = e.file_id
+          WHERE e.model = ?1",
+    )?;
+    let mut hits = Vec::new();
+    let mut mismatched = 0usize;
+    let rows = stmt.query_map([model], |r| {
+        Ok((
+            r.get::<_, i64>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, Vec<u8>>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (file_id, path, blob) = row?;
+        let v = from_blob(&blob);
+        if v.len() != q.len() {
+            mismatched += 1;
+            continue;
+        }
+        let score = cosine(&q, &v);
+        if score > 0.0 {
+            hits.push(SemanticHit {
+                file_id,
+                path,
+                score,
+            });
+        }
+    }
+    if mismatched > 0 {
+        warn!(
+            model,
+            files = mismatched,
+            "stored vectors differ in length from the query's; re-run `marlin embed --force`"
+        );
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.path.cmp(&b.path)));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+/// Number of files with a vector of `model`.
+pub fn count(conn: &Connection, model: &str) -> Result<usize> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM embeddings WHERE model = ?1",
+        [model],
+        |r| r.get::<_, i64>(0),
+    )? as usize)
+}
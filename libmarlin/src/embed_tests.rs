@@ -0,0 +1,91 @@
+// libmarlin/src/embed_tests.rs
+
+use super::db;
+use super::embed::{
+    self, cosine, parse_vector, CommandEmbedder, EmbedReport, Embedder, HashEmbedder,
+};
+use super::scan::scan_directory;
+use tempfile::tempdir;
+
+#[test]
+fn hash_embedder_is_normalised_and_favours_shared_words() {
+    let e = HashEmbedder::default();
+    assert_eq!(e.model(), "hash-256");
+    let a = e.embed("quarterly financial summary").unwrap();
+    let b = e.embed("Summary of the quarterly finances").unwrap();
+    let c = e.embed("holiday photos from the beach").unwrap();
+    assert_eq!(a.len(), 256);
+    assert!((cosine(&a, &a) - 1.0).abs() < 1e-5);
+    assert!(cosine(&a, &b) > cosine(&a, &c));
+    assert_eq!(cosine(&a, &[1.0]), 0.0);
+}
+
+#[test]
+fn parse_vector_accepts_plain_and_json_output() {
+    assert_eq!(parse_vector("0.5 -1\n2").unwrap(), vec![0.5, -1.0, 2.0]);
+    assert_eq!(
+        parse_vector("[0.5, -1, 2]\n").unwrap(),
+        vec![0.5, -1.0, 2.0]
+    );
+    assert!(parse_vector("").is_err());
+    assert!(parse_vector("0.5 nope").is_err());
+}
+
+#[test]
+fn embed_files_stores_vectors_and_semantic_search_ranks_them() {
+    let tmp = tempdir().unwrap();
+    let dir = tmp.path();
+    std::fs::write(
+        dir.join("q3-report.txt"),
+        "Quarterly financial summary: revenue and costs for the third quarter.",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("trip.txt"),
+        "Holiday photos from the beach in Crete.",
+    )
+    .unwrap();
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, dir).unwrap();
+
+    let e = HashEmbedder::default();
+    let files = db::match_files(&conn, "*.txt").unwrap();
+    let report = embed::embed_files(&conn, &e, &files, false).unwrap();
+    assert_eq!(
+        report,
+        EmbedReport {
+            embedded: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(embed::count(&conn, "hash-256").unwrap(), 2);
+
+    // nothing changed: nothing recomputed
+    let again = embed::embed_files(&conn, &e, &files, false).unwrap();
+    assert_eq!((again.embedded, again.up_to_date), (0, 2));
+
+    let hits = embed::semantic_search(&conn, &e, "financial summary of the quarter", 10).unwrap();
+    assert!(hits[0].path.ends_with("q3-report.txt"), "{hits:?}");
+    assert!(hits[0].score > hits.get(1).map_or(0.0, |h| h.score));
+
+    let top = embed::semantic_search(&conn, &e, "beach holiday", 1).unwrap();
+    assert_eq!(top.len(), 1);
+    assert!(top[0].path.ends_with("trip.txt"));
+
+    // other models' vectors are not considered
+    let other = HashEmbedder::new(64);
+    assert!(embed::semantic_search(&conn, &other, "beach", 10)
+        .unwrap()
+        .is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn command_embedder_reads_the_vector_from_stdout() {
+    let e = CommandEmbedder::new("sh -c 'cat >/dev/null; echo \"[1, 0, 0.5]\"'").unwrap();
+    assert!(e.model().starts_with("cmd:sh -c"));
+    assert_eq!(e.embed("anything").unwrap(), vec![1.0, 0.0, 0.5]);
+
+    let failing = CommandEmbedder::new("sh -c 'exit 3'").unwrap();
+    assert!(failing.embed("x").is_err());
+}
@@ -12,6 +12,7 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     Io(io::Error),
     Database(rusqlite::Error),
+    #[cfg(feature = "watcher")]
     Watch(notify::Error),
     InvalidState(String),
     NotFound(String),
@@ -24,6 +25,7 @@ impl fmt::Display for Error {
         match self {
             Self::Io(err) => write!(f, "IO error: {}", err),
             Self::Database(err) => write!(f, "Database error: {}", err),
+            #[cfg(feature = "watcher")]
             Self::Watch(err) => write!(f, "Watch error: {}", err),
             Self::InvalidState(msg) => write!(f, "Invalid state: {}", msg),
             Self::NotFound(path) => write!(f, "Not found: {}", path),
@@ -38,6 +40,7 @@ impl std::error::Error for Error {
         match self {
             Self::Io(err) => Some(err),
             Self::Database(err) => Some(err),
+            #[cfg(feature = "watcher")]
             Self::Watch(err) => Some(err),
             Self::InvalidState(_) | Self::NotFound(_) | Self::Config(_) | Self::Other(_) => None,
         }
@@ -56,6 +59,7 @@ impl From<rusqlite::Error> for Error {
     }
 }
 
+#[cfg(feature = "watcher")]
 impl From<notify::Error> for Error {
     fn from(err: notify::Error) -> Self {
         Self::Watch(err)
@@ -104,21 +108,24 @@ mod tests {
         }
 
         // Test Watch variant
-        let notify_raw_err_inner_for_source_check =
-            notify::Error::new(notify::ErrorKind::Generic("test watch error".to_string()));
-        let watch_err_marlin = Error::from(notify::Error::new(notify::ErrorKind::Generic(
-            "test watch error".to_string(),
-        )));
-        assert!(watch_err_marlin
-            .to_string()
-            .contains("Watch error: test watch error"));
-        let source = watch_err_marlin.source();
-        assert!(source.is_some(), "Watch error should have a source");
-        if let Some(s) = source {
-            assert_eq!(
-                s.to_string(),
-                notify_raw_err_inner_for_source_check.to_string()
-            );
+        #[cfg(feature = "watcher")]
+        {
+            let notify_raw_err_inner_for_source_check =
+                notify::Error::new(notify::ErrorKind::Generic("test watch error".to_string()));
+            let watch_err_marlin = Error::from(notify::Error::new(notify::ErrorKind::Generic(
+                "test watch error".to_string(),
+            )));
+            assert!(watch_err_marlin
+                .to_string()
+                .contains("Watch error: test watch error"));
+            let source = watch_err_marlin.source();
+            assert!(source.is_some(), "Watch error should have a source");
+            if let Some(s) = source {
+                assert_eq!(
+                    s.to_string(),
+                    notify_raw_err_inner_for_source_check.to_string()
+                );
+            }
         }
 
         let invalid_state_err = Error::InvalidState("bad state".to_string());
@@ -0,0 +1,173 @@
+//! Machine-readable progress events for long-running operations.
+//!
+//! Library code calls [`emit`]; nothing happens unless a front-end has
+//! installed a sink with [`set_sink`] (the CLI does for `--events`).
+//! [`Event::to_json`] gives the one-line wire format GUI wrappers parse:
+//!
+//! ```text
+//! {"event":"scan_progress","ts":1718000000123,"root":"/home/me/docs","indexed":500,"total":1200}
+//! ```
+
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Emit a `scan_progress` event every this many files.
+pub const SCAN_PROGRESS_EVERY: usize = 250;
+
+/// Something worth reporting to a progress UI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A directory walk finished; `total` files will be indexed.
+    ScanStarted {
+        root: PathBuf,
+        total: usize,
+    },
+    ScanProgress {
+        root: PathBuf,
+        indexed: usize,
+        total: usize,
+    },
+    ScanFinished {
+        root: PathBuf,
+        indexed: usize,
+    },
+    /// SQLite backup pages copied so far (only for snapshots that report
+    /// progress).
+    BackupProgress {
+        remaining: i32,
+        total: i32,
+    },
+    BackupCreated {
+        path: PathBuf,
+    },
+    RestoreFinished {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// The watcher saw more than the configured number of deletions and
+    /// renames within `window_secs`.
+    DeletionBurst {
+        count: usize,
+        window_secs: u64,
+    },
+    /// Emitted by front-ends once the whole command is done.
+    CommandFinished {
+        ok: bool,
+        error: Option<String>,
+    },
+}
+
+type Sink = Box<dyn Fn(&Event) + Send + Sync>;
+
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+/// Install (or with `None`, remove) the process-wide event sink.
+pub fn set_sink(sink: Option<Sink>) {
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = sink;
+}
+
+/// Whether anybody is listening; lets callers skip building events.
+pub fn enabled() -> bool {
+    SINK.read().map(|s| s.is_some()).unwrap_or(false)
+}
+
+/// Hand `event` to the installed sink, if any.
+pub fn emit(event: Event) {
+    if let Ok(guard) = SINK.read() {
+        if let Some(sink) = guard.as_ref() {
+            sink(&event);
+        }
+    }
+}
+
+impl Event {
+    /// The `event` field of the wire format.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::ScanStarted { .. } => "scan_started",
+            Event::ScanProgress { .. } => "scan_progress",
+            Event::ScanFinished { .. } => "scan_finished",
+            Event::BackupProgress { .. } => "backup_progress",
+            Event::BackupCreated { .. } => "backup_created",
+            Event::RestoreFinished { .. } => "restore_finished",
+            Event::DeletionBurst { .. } => "deletion_burst",
+            Event::CommandFinished { .. } => "command_finished",
+        }
+    }
+
+    /// One JSON object on a single line (no trailing newline), stamped
+    /// with the current time in Unix milliseconds.
+    pub fn to_json(&self) -> String {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let mut out = format!("{{\"event\":\"{}\",\"ts\":{ts}", self.name());
+        let mut field = |k: &str, v: String| {
+            out.push_str(&format!(",\"{k}\":{v}"));
+        };
+        match self {
+            Event::ScanStarted { root, total } => {
+                field("root", json_str(&root.to_string_lossy()));
+                field("total", total.to_string());
+            }
+            Event::ScanProgress {
+                root,
+                indexed,
+                total,
+            } => {
+                field("root", json_str(&root.to_string_lossy()));
+                field("indexed", indexed.to_string());
+                field("total", total.to_string());
+            }
+            Event::ScanFinished { root, indexed } => {
+                field("root", json_str(&root.to_string_lossy()));
+                field("indexed", indexed.to_string());
+            }
+            Event::BackupProgress { remaining, total } => {
+                field("remaining", remaining.to_string());
+                field("total", total.to_string());
+            }
+            Event::BackupCreated { path } => {
+                field("path", json_str(&path.to_string_lossy()));
+            }
+            Event::RestoreFinished { from, to } => {
+                field("from", json_str(&from.to_string_lossy()));
+                field("to", json_str(&to.to_string_lossy()));
+            }
+            Event::DeletionBurst { count, window_secs } => {
+                field("count", count.to_string());
+                field("window_secs", window_secs.to_string());
+            }
+            Event::CommandFinished { ok, error } => {
+                field("ok", ok.to_string());
+                field(
+                    "error",
+                    error.as_deref().map_or_else(|| "null".into(), json_str),
+                );
+            }
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// Quote and escape a JSON string.
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
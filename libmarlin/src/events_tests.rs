@@ -0,0 +1,71 @@
+// libmarlin/src/events_tests.rs
+
+use super::db;
+use super::events::{self, Event};
+use super::scan::scan_directory;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+#[test]
+fn json_line_escapes_and_orders_fields() {
+    let line = Event::ScanFinished {
+        root: PathBuf::from("/tmp/a \"b\""),
+        indexed: 3,
+    }
+    .to_json();
+    assert!(line.starts_with("{\"event\":\"scan_finished\",\"ts\":"));
+    assert!(line.ends_with(",\"root\":\"/tmp/a \\\"b\\\"\",\"indexed\":3}"));
+    assert!(!line.contains('\n'));
+
+    let done = Event::CommandFinished {
+        ok: true,
+        error: None,
+    }
+    .to_json();
+    assert!(done.ends_with(",\"ok\":true,\"error\":null}"));
+
+    let burst = Event::DeletionBurst {
+        count: 250,
+        window_secs: 60,
+    }
+    .to_json();
+    assert!(burst.starts_with("{\"event\":\"deletion_burst\""));
+    assert!(burst.ends_with(",\"count\":250,\"window_secs\":60}"));
+}
+
+#[test]
+fn scan_reports_start_progress_and_finish() {
+    let tmp = tempdir().unwrap();
+    let n = events::SCAN_PROGRESS_EVERY + 1;
+    for i in 0..n {
+        fs::write(tmp.path().join(format!("f{i}.txt")), "").unwrap();
+    }
+
+    // the sink is process-wide; only keep events for our own root
+    let root = tmp.path().to_path_buf();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink_seen = Arc::clone(&seen);
+    events::set_sink(Some(Box::new(move |e: &Event| {
+        let ours = match e {
+            Event::ScanStarted { root: r, .. }
+            | Event::ScanProgress { root: r, .. }
+            | Event::ScanFinished { root: r, .. } => *r == root,
+            _ => false,
+        };
+        if ours {
+            sink_seen.lock().unwrap().push(e.name());
+        }
+    })));
+    assert!(events::enabled());
+
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    events::set_sink(None);
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["scan_started", "scan_progress", "scan_finished"]
+    );
+}
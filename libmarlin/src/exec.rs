@@ -0,0 +1,167 @@
+//! Command templates for `search --exec`, `action run` and `view watch
+//! --exec`: placeholders in a command line stand for one file.
+//!
+//! * `{}`, `{path}` – the full path
+//! * `{dir}` – the directory it is in
+//! * `{name}`, `{stem}`, `{ext}` – `a.tar.gz`, `a.tar`, `gz` (no dot;
+//!   empty without an extension)
+//! * `{tags}` – the file's full tag paths, comma-separated
+//! * `{attr:key}` – the value of attribute `key`, empty when unset
+//!
+//! Each value is shell-quoted on its own, so `thumbs/{stem}.png` stays
+//! one word whatever the name contains – do not quote placeholders
+//! yourself.  Braces that are not a placeholder, as in
+//! `awk '{print $1}'`, are left alone.  A template without any
+//! placeholder gets the quoted path appended.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::store::Store;
+
+/// What a placeholder stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Path,
+    Dir,
+    Name,
+    Stem,
+    Ext,
+    Tags,
+    Attr(String),
+}
+
+impl Field {
+    fn parse(inner: &str) -> Option<Self> {
+        Some(match inner {
+            "" | "path" => Field::Path,
+            "dir" => Field::Dir,
+            "name" => Field::Name,
+            "stem" => Field::Stem,
+            "ext" => Field::Ext,
+            "tags" => Field::Tags,
+            _ => match inner.strip_prefix("attr:") {
+                Some(key) if !key.is_empty() => Field::Attr(key.to_string()),
+                _ => return None,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+/// A parsed command template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecTemplate {
+    source: String,
+    pieces: Vec<Piece>,
+}
+
+impl ExecTemplate {
+    pub fn parse(template: &str) -> Self {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let field = rest[open + 1..]
+                .find('}')
+                .and_then(|close| Some((close, Field::parse(&rest[open + 1..open + 1 + close])?)));
+            match field {
+                Some((close, f)) => {
+                    text.push_str(&rest[..open]);
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(f));
+                    rest = &rest[open + close + 2..];
+                }
+                None => {
+                    text.push_str(&rest[..=open]);
+                    rest = &rest[open + 1..];
+                }
+            }
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Self {
+            source: template.to_string(),
+            pieces,
+        }
+    }
+
+    /// Whether any placeholder appears (else the path is appended).
+    pub fn has_placeholders(&self) -> bool {
+        self.fields().next().is_some()
+    }
+
+    /// The placeholders, in order.
+    pub fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.pieces.iter().filter_map(|p| match p {
+            Piece::Field(f) => Some(f),
+            Piece::Text(_) => None,
+        })
+    }
+
+    /// The command line for `path`.  Tags and attributes come from
+    /// `store`; a path it does not know has neither.
+    pub fn render(&self, store: &dyn Store, path: &str) -> Result<String> {
+        if !self.has_placeholders() {
+            return Ok(format!("{} {}", self.source, quote(path)));
+        }
+        let p = Path::new(path);
+        let lossy = |s: Option<&std::ffi::OsStr>| {
+            s.map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        // looked up once, and only for `{tags}` and `{attr:…}`
+        let mut file_id = None;
+        let mut indexed = || -> Result<Option<i64>> {
+            if file_id.is_none() {
+                file_id = Some(store.find_file(path)?);
+            }
+            Ok(file_id.flatten())
+        };
+        let mut line = String::new();
+        for piece in &self.pieces {
+            let value = match piece {
+                Piece::Text(t) => {
+                    line.push_str(t);
+                    continue;
+                }
+                Piece::Field(Field::Path) => path.to_string(),
+                Piece::Field(Field::Dir) => lossy(p.parent().map(Path::as_os_str)),
+                Piece::Field(Field::Name) => lossy(p.file_name()),
+                Piece::Field(Field::Stem) => lossy(p.file_stem()),
+                Piece::Field(Field::Ext) => lossy(p.extension()),
+                Piece::Field(Field::Tags) => match indexed()? {
+                    Some(id) => store.file_tag_paths(id)?.join(","),
+                    None => String::new(),
+                },
+                Piece::Field(Field::Attr(key)) => match indexed()? {
+                    Some(id) => store.attr(id, key)?.unwrap_or_default(),
+                    None => String::new(),
+                },
+            };
+            line.push_str(&quote(&value));
+        }
+        Ok(line)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+/// `s` as one shell word (as is, if it cannot be quoted: a NUL byte).
+fn quote(s: &str) -> String {
+    shlex::try_quote(s)
+        .map(|q| q.into_owned())
+        .unwrap_or_else(|_| s.to_string())
+}
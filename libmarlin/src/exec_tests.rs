@@ -0,0 +1,73 @@
+// libmarlin/src/exec_tests.rs
+
+use super::db;
+use super::exec::{ExecTemplate, Field};
+use super::test_utils::db_with_files;
+
+#[test]
+fn placeholders_are_parsed_and_other_braces_kept() {
+    let t = ExecTemplate::parse("awk '{print $1}' {path} {attr:meta/author} {nope}");
+    assert_eq!(
+        t.fields().collect::<Vec<_>>(),
+        [&Field::Path, &Field::Attr("meta/author".into())]
+    );
+    assert!(!ExecTemplate::parse("echo {attr:} {x}").has_placeholders());
+    assert!(ExecTemplate::parse("cat {}").has_placeholders());
+}
+
+#[test]
+fn each_value_is_quoted_on_its_own() {
+    let path = "/photos/summer trip/img 1.jpeg";
+    let conn = db_with_files(&[path]);
+    let t = ExecTemplate::parse("convert {path} thumbs/{stem}.png");
+    let line = t.render(&conn, path).unwrap();
+    assert_eq!(
+        shlex::split(&line).unwrap(),
+        ["convert", path, "thumbs/img 1.png"]
+    );
+
+    let t = ExecTemplate::parse("x {dir} {name} {ext} {}");
+    let words = shlex::split(&t.render(&conn, path).unwrap()).unwrap();
+    assert_eq!(
+        words,
+        ["x", "/photos/summer trip", "img 1.jpeg", "jpeg", path]
+    );
+
+    // quotes and `$` in a name stay literal
+    let odd = "/tmp/it's $HOME.txt";
+    let words = shlex::split(
+        &ExecTemplate::parse("echo {name}")
+            .render(&conn, odd)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(words, ["echo", "it's $HOME.txt"]);
+}
+
+#[test]
+fn tags_and_attributes_come_from_the_index() {
+    let conn = db_with_files(&["/n/a.md"]);
+    let fid = db::file_id(&conn, "/n/a.md").unwrap();
+    for tag in ["project/alpha", "inbox"] {
+        let id = db::ensure_tag_path(&conn, tag).unwrap();
+        db::attach_tag(&conn, fid, id, 0).unwrap();
+    }
+    db::upsert_attr(&conn, fid, "owner", "Ana Lima").unwrap();
+
+    let t = ExecTemplate::parse("tool {tags} {attr:owner} {attr:missing}");
+    let words = shlex::split(&t.render(&conn, "/n/a.md").unwrap()).unwrap();
+    assert_eq!(words, ["tool", "inbox,project/alpha", "Ana Lima", ""]);
+
+    // a file the index does not know has neither
+    let words = shlex::split(&t.render(&conn, "/elsewhere.md").unwrap()).unwrap();
+    assert_eq!(words, ["tool", "", "", ""]);
+}
+
+#[test]
+fn template_without_placeholders_gets_the_path_appended() {
+    let conn = db_with_files(&["/a b.txt"]);
+    let line = ExecTemplate::parse("wc -l")
+        .render(&conn, "/a b.txt")
+        .unwrap();
+    assert_eq!(shlex::split(&line).unwrap(), ["wc", "-l", "/a b.txt"]);
+}
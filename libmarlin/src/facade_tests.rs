@@ -57,6 +57,42 @@ fn tag_and_search_by_tag() {
     env::remove_var("MARLIN_DB_PATH");
 }
 
+#[test]
+fn open_many_labels_and_merges_databases() {
+    let tmp = tempdir().unwrap();
+    let (work_dir, home_dir) = (tmp.path().join("w"), tmp.path().join("h"));
+    fs::create_dir_all(&work_dir).unwrap();
+    fs::create_dir_all(&home_dir).unwrap();
+    fs::write(work_dir.join("budget.txt"), "").unwrap();
+    fs::write(home_dir.join("budget-2024.txt"), "").unwrap();
+
+    let dbs = [tmp.path().join("work.db"), tmp.path().join("home.db")];
+    Marlin::open_at(&dbs[0])
+        .unwrap()
+        .scan(&[&work_dir])
+        .unwrap();
+    Marlin::open_at(&dbs[1])
+        .unwrap()
+        .scan(&[&home_dir])
+        .unwrap();
+
+    let group = Marlin::open_many(&dbs).unwrap();
+    let labels: Vec<&str> = group.members().iter().map(|(l, _)| l.as_str()).collect();
+    assert_eq!(labels, ["work", "home"]);
+
+    let hits = group
+        .search_hits("budget", &query::SearchOptions::default())
+        .unwrap();
+    let mut sources: Vec<&str> = hits.iter().map(|h| h.source.as_str()).collect();
+    sources.sort();
+    assert_eq!(sources, ["home", "work"]);
+
+    // clashing stems fall back to full paths
+    let other = tmp.path().join("h").join("work.db");
+    let group = Marlin::open_many(&[&dbs[0], &other]).unwrap();
+    assert_eq!(group.members()[1].0, other.display().to_string());
+}
+
 #[test]
 fn open_default_fallback_config() {
     let _guard = ENV_MUTEX.lock().unwrap();
@@ -75,3 +111,201 @@ fn open_default_fallback_config() {
     // Clean up
     env::remove_var("HOME");
 }
+
+#[test]
+fn view_exec_runs_saved_view() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let tmp = tempdir().unwrap();
+    for name in ["one.log", "two.log", "three.txt"] {
+        fs::write(tmp.path().join(name), "x").unwrap();
+    }
+
+    let mut m = Marlin::open_at(tmp.path().join("views.db")).unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+    m.tag("*.log", "logs").unwrap();
+    db::save_view(&m.conn(), "logs", "tag:logs").unwrap();
+
+    let hits = m
+        .view_exec("logs", query::SearchOptions::default())
+        .unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits.iter().all(|h| h.path.ends_with(".log")));
+    let id = db::file_id(&m.conn(), &hits[0].path).unwrap();
+    assert_eq!(hits[0].file_id, id);
+
+    let page = m
+        .view_exec(
+            "logs",
+            query::SearchOptions {
+                limit: Some(1),
+                offset: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(page, vec![hits[1].clone()]);
+
+    assert!(m
+        .view_exec("nope", query::SearchOptions::default())
+        .is_err());
+}
+
+#[test]
+#[cfg(feature = "backup")]
+fn backup_list_and_restore_via_facade() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("keep.txt"), "x").unwrap();
+
+    let mut m = Marlin::open_at(tmp.path().join("bk.db")).unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+
+    let info = m.backup().unwrap();
+    assert!(tmp.path().join("backups").join(&info.id).exists());
+    let listed = m.list_backups().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, info.id);
+
+    m.conn()
+        .execute(
+            "INSERT INTO files(path, size, mtime) VALUES ('/later.txt', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+    m.restore(&info.id).unwrap();
+    let n: i64 = m
+        .conn()
+        .query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(n, 1);
+
+    assert!(m.restore("backup_missing.db").is_err());
+}
+
+#[test]
+fn schema_info_reports_versions_tables_and_columns() {
+    let _guard = ENV_MUTEX.lock().unwrap();
+    let tmp = tempdir().unwrap();
+    let m = Marlin::open_at(tmp.path().join("schema.db")).unwrap();
+
+    let info = m.schema_info().unwrap();
+    assert!(info.is_compatible());
+    assert_eq!(info.current_version, db::SCHEMA_VERSION);
+    assert_eq!(info.migrations.len(), db::SCHEMA_VERSION as usize);
+    assert_eq!(
+        info.migrations[0].name.as_deref(),
+        Some("0001_initial_schema.sql")
+    );
+    assert!(info.migrations.iter().all(|m| m.applied_on.is_some()));
+
+    let files = info.table("files").expect("files table");
+    assert_eq!(files.kind, "table");
+    let path = files.columns.iter().find(|c| c.name == "path").unwrap();
+    assert!(path.not_null);
+    assert!(files
+        .columns
+        .iter()
+        .any(|c| c.name == "id" && c.primary_key));
+
+    assert_eq!(
+        info.table("files_fts").map(|t| t.kind.as_str()),
+        Some("virtual")
+    );
+    assert!(
+        info.table("files_fts_data").is_none(),
+        "shadow tables hidden"
+    );
+}
+
+#[test]
+fn tag_respects_strict_mode() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("a.md"), "").unwrap();
+    let mut m = Marlin::open_at(tmp.path().join("strict.db")).unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+    m.tag("*.md", "project").unwrap();
+
+    db::set_setting(&m.conn(), "tags.strict", "true").unwrap();
+    let err = m.tag("*.md", "proejct").unwrap_err();
+    assert!(err.to_string().contains("unknown tag"), "{err}");
+    assert_eq!(m.tag("*.md", "project").unwrap(), 0, "already tagged");
+}
+
+#[test]
+fn tag_pages_through_files_in_chunks() {
+    let tmp = tempdir().unwrap();
+    for name in ["a.md", "b.txt", "c.md", "d.txt", "e.md"] {
+        fs::write(tmp.path().join(name), "").unwrap();
+    }
+    let mut m = Marlin::open_at(tmp.path().join("chunks.db")).unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+
+    // chunk boundaries fall between matches and past the last file
+    {
+        let conn = m.conn();
+        let tag = |pattern, tag_path, chunk| {
+            tag_in_chunks(&*conn, &conn, pattern, tag_path, chunk).unwrap()
+        };
+        assert_eq!(tag("*.md", "notes/md", 2), 3);
+        assert_eq!(tag("*.md", "notes/md", 2), 0);
+        assert_eq!(tag("*.txt", "notes", 1), 2);
+    }
+    assert_eq!(m.search("tags_text:notes").unwrap().len(), 5);
+}
+
+#[test]
+fn query_cache_is_opt_in_and_sees_tagging() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("a.md"), "").unwrap();
+    let mut m = Marlin::open_at(tmp.path().join("cache.db")).unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+    let opts = query::SearchOptions::default();
+
+    assert!(m.query_cache_stats().is_none());
+    m.enable_query_cache(16);
+    assert!(m.search_hits("tag:draft", &opts).unwrap().is_empty());
+    assert!(m.search_hits("tag:draft", &opts).unwrap().is_empty());
+
+    m.tag("*.md", "draft").unwrap();
+    assert_eq!(m.search_hits("tag:draft", &opts).unwrap().len(), 1);
+    let stats = m.query_cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses), (1, 2));
+}
+
+#[test]
+fn shared_handle_sees_the_same_index() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("a.md"), "").unwrap();
+    let mut m = Marlin::open_at(":memory:").unwrap();
+    let reader = m.share();
+    m.scan(&[tmp.path()]).unwrap();
+    m.tag("*.md", "project/alpha").unwrap();
+
+    assert_eq!(
+        db::tag_paths(&reader.conn()).unwrap(),
+        ["project", "project/alpha"]
+    );
+    let hits = reader
+        .search_hits("tag:project", &query::SearchOptions::default())
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+}
+
+#[test]
+fn search_incremental_follows_a_growing_query() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("plan.md"), "alpha beta").unwrap();
+    fs::write(tmp.path().join("notes.md"), "alpha").unwrap();
+    let mut m = Marlin::open_at(":memory:").unwrap();
+    m.scan(&[tmp.path()]).unwrap();
+
+    let mut prev = None;
+    for q in ["alpha", "alpha beta", "alpha beta gamma"] {
+        let rs = m.search_incremental(prev.as_ref(), q).unwrap();
+        let full = m.search_hits(q, &query::SearchOptions::default()).unwrap();
+        assert_eq!(rs.hits, full, "{q}");
+        prev = Some(rs);
+    }
+    assert!(prev.unwrap().hits.is_empty());
+}
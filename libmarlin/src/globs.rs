@@ -0,0 +1,247 @@
+//! File-selection globs shared by `tag`, `attr set`, scans and the other
+//! commands that take a pattern (see [`db::match_files`](crate::db::match_files)),
+//! and by root ignore lists.
+//!
+//! Besides `*`, `?`, `**` and `[…]` (`[!…]` negates), a pattern may use
+//!
+//! * `{md,txt}` – alternatives, which may nest (`*.{md,tx{t,x}}`);
+//! * `!(draft|tmp)` – anything `*` would match, except what one of the
+//!   alternatives matches: `notes/!(*.bak)` is every file in `notes`
+//!   but the backups.
+//!
+//! The other extended globs of bash and zsh (`@(…)`, `+(…)`, `*(…)`,
+//! `?(…)`) are rejected with an error.  As with the `glob` crate, `*`
+//! also crosses `/`, and special characters are matched literally inside
+//! brackets: `[{]`, `[(]`.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Brace alternatives may expand to at most this many patterns.
+pub const MAX_EXPANSIONS: usize = 1024;
+
+/// A compiled pattern.
+#[derive(Debug, Clone)]
+pub struct FileGlob {
+    source: String,
+    /// The pattern with every `!(…)` read as `*`.
+    include: GlobSet,
+    /// Per `!(…)`: the pattern with that group read as its alternatives.
+    exclude: Vec<GlobSet>,
+}
+
+/// A piece of a pattern between `!(…)` groups.
+enum Piece {
+    Text(String),
+    Not(Vec<String>),
+}
+
+impl FileGlob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let pieces = split_negations(pattern)?;
+        let render = |negated: Option<usize>| -> String {
+            let mut out = String::new();
+            let mut n = 0;
+            for p in &pieces {
+                match p {
+                    Piece::Text(t) => out.push_str(t),
+                    Piece::Not(alts) => {
+                        if negated == Some(n) {
+                            out.push('{');
+                            out.push_str(&alts.join(","));
+                            out.push('}');
+                        } else {
+                            out.push('*');
+                        }
+                        n += 1;
+                    }
+                }
+            }
+            out
+        };
+        let groups = pieces.iter().filter(|p| matches!(p, Piece::Not(_))).count();
+        let include = compile(pattern, &render(None))?;
+        let exclude = (0..groups)
+            .map(|i| compile(pattern, &render(Some(i))))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            source: pattern.to_string(),
+            include,
+            exclude,
+        })
+    }
+
+    /// Whether `text` (a path or file name) matches.
+    pub fn matches(&self, text: &str) -> bool {
+        self.include.is_match(text) && !self.exclude.iter().any(|s| s.is_match(text))
+    }
+
+    pub fn matches_path(&self, path: &Path) -> bool {
+        self.matches(&path.to_string_lossy())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+/// Expand the braces of `text` and build the set of the results.
+fn compile(pattern: &str, text: &str) -> Result<GlobSet> {
+    let mut set = GlobSetBuilder::new();
+    for alt in expand_braces(text).with_context(|| format!("invalid glob pattern `{pattern}`"))? {
+        let glob = GlobBuilder::new(&alt)
+            .literal_separator(false)
+            .backslash_escape(false)
+            .build()
+            .with_context(|| format!("invalid glob pattern `{pattern}`"))?;
+        set.add(glob);
+    }
+    set.build()
+        .with_context(|| format!("invalid glob pattern `{pattern}`"))
+}
+
+/// Length of the `[…]` class starting at `chars[i]`, or `None` when it is
+/// not closed (the `[` is then left for globset to reject).
+fn class_len(chars: &[char], i: usize) -> Option<usize> {
+    let mut j = i + 1;
+    if chars.get(j) == Some(&'!') || chars.get(j) == Some(&'^') {
+        j += 1;
+    }
+    // a `]` right after the opening is part of the class
+    if chars.get(j) == Some(&']') {
+        j += 1;
+    }
+    while j < chars.len() {
+        if chars[j] == ']' {
+            return Some(j - i + 1);
+        }
+        j += 1;
+    }
+    None
+}
+
+/// Cut `pattern` at its `!(…)` groups and refuse the extended globs we do
+/// not support.
+fn split_negations(pattern: &str) -> Result<Vec<Piece>> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '[' {
+            if let Some(len) = class_len(&chars, i) {
+                text.extend(&chars[i..i + len]);
+                i += len;
+                continue;
+            }
+        }
+        if chars.get(i + 1) == Some(&'(') {
+            if c == '!' {
+                let close = chars[i + 2..]
+                    .iter()
+                    .position(|&c| c == ')')
+                    .map(|p| p + i + 2)
+                    .with_context(|| format!("unclosed `!(` in glob pattern `{pattern}`"))?;
+                let inner: String = chars[i + 2..close].iter().collect();
+                if inner.contains(['(', '!']) {
+                    bail!("`!(…)` cannot nest in glob pattern `{pattern}`");
+                }
+                if inner.is_empty() {
+                    bail!("empty `!()` in glob pattern `{pattern}`");
+                }
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+                pieces.push(Piece::Not(inner.split('|').map(String::from).collect()));
+                i = close + 1;
+                continue;
+            }
+            if ['@', '+', '*', '?'].contains(&c) {
+                bail!(
+                    "`{c}(…)` in glob pattern `{pattern}` is not supported; use `{{a,b}}` for \
+                     alternatives or `!(…)` to exclude (write `[(]` for a literal parenthesis)"
+                );
+            }
+        }
+        text.push(c);
+        i += 1;
+    }
+    pieces.push(Piece::Text(text));
+    Ok(pieces)
+}
+
+/// Every pattern the braces of `text` stand for, in order.
+pub fn expand_braces(text: &str) -> Result<Vec<String>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    expand_into(&chars, &mut out)?;
+    Ok(out)
+}
+
+fn expand_into(chars: &[char], out: &mut Vec<String>) -> Result<()> {
+    // the first top-level `{`, skipping bracket classes
+    let mut i = 0;
+    let mut open = None;
+    while i < chars.len() {
+        match chars[i] {
+            '[' => {
+                if let Some(len) = class_len(chars, i) {
+                    i += len;
+                    continue;
+                }
+            }
+            '{' => {
+                open = Some(i);
+                break;
+            }
+            '}' => bail!("unmatched `}}`"),
+            _ => {}
+        }
+        i += 1;
+    }
+    let Some(open) = open else {
+        if out.len() >= MAX_EXPANSIONS {
+            bail!("braces expand to more than {MAX_EXPANSIONS} patterns");
+        }
+        out.push(chars.iter().collect());
+        return Ok(());
+    };
+
+    // its matching `}` and the top-level commas in between
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut close = None;
+    let mut j = open + 1;
+    while j < chars.len() {
+        match chars[j] {
+            '[' => {
+                if let Some(len) = class_len(chars, j) {
+                    j += len;
+                    continue;
+                }
+            }
+            '{' => depth += 1,
+            '}' if depth == 0 => {
+                close = Some(j);
+                break;
+            }
+            '}' => depth -= 1,
+            ',' if depth == 0 => commas.push(j),
+            _ => {}
+        }
+        j += 1;
+    }
+    let close = close.context("unclosed `{`")?;
+
+    let mut bounds = vec![open];
+    bounds.extend(commas);
+    bounds.push(close);
+    for w in bounds.windows(2) {
+        let mut next: Vec<char> = chars[..open].to_vec();
+        next.extend(&chars[w[0] + 1..w[1]]);
+        next.extend(&chars[close + 1..]);
+        expand_into(&next, out)?;
+    }
+    Ok(())
+}
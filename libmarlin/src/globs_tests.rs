@@ -0,0 +1,63 @@
+// libmarlin/src/globs_tests.rs
+
+use super::globs::{expand_braces, FileGlob};
+
+fn m(pattern: &str, text: &str) -> bool {
+    FileGlob::new(pattern).unwrap().matches(text)
+}
+
+#[test]
+fn braces_expand_in_order_and_nest() {
+    assert_eq!(expand_braces("*.{md,txt}").unwrap(), ["*.md", "*.txt"]);
+    assert_eq!(
+        expand_braces("{a,b{1,2}}/x").unwrap(),
+        ["a/x", "b1/x", "b2/x"]
+    );
+    // braces inside a class are literal
+    assert_eq!(
+        expand_braces("[{]x}").unwrap_err().to_string(),
+        "unmatched `}`"
+    );
+    assert_eq!(expand_braces("[{}]x").unwrap(), ["[{}]x"]);
+    assert!(expand_braces("*.{md").is_err());
+    assert!(expand_braces(&"{a,b}".repeat(11)).is_err());
+}
+
+#[test]
+fn plain_globs_behave_as_before() {
+    assert!(m("*.md", "notes.md"));
+    assert!(!m("*.md", "notes.txt"));
+    assert!(m("/docs/*.md", "/docs/sub/a.md"));
+    assert!(m("/docs/**/*.md", "/docs/a/b/c.md"));
+    assert!(m("report-[0-9].pdf", "report-7.pdf"));
+    assert!(m("[!a]*", "bcd"));
+}
+
+#[test]
+fn alternatives_and_negation() {
+    assert!(m("*.{md,txt}", "a.txt"));
+    assert!(!m("*.{md,txt}", "a.pdf"));
+    assert!(m("/docs/{a,b}/*.md", "/docs/b/x.md"));
+
+    assert!(m("!(*.bak)", "notes.md"));
+    assert!(!m("!(*.bak)", "notes.bak"));
+    assert!(m("/docs/!(draft|tmp)/*.md", "/docs/final/a.md"));
+    assert!(!m("/docs/!(draft|tmp)/*.md", "/docs/tmp/a.md"));
+    assert!(m("*.!(md)", "a.txt"));
+    assert!(!m("*.!(md)", "a.md"));
+}
+
+#[test]
+fn unsupported_syntax_is_explained() {
+    for p in ["@(a|b)", "*.+(md)", "x*(y)", "?(a)"] {
+        let err = FileGlob::new(p).unwrap_err().to_string();
+        assert!(err.contains("is not supported"), "{p}: {err}");
+    }
+    assert!(FileGlob::new("!(a")
+        .unwrap_err()
+        .to_string()
+        .contains("unclosed `!(`"));
+    assert!(FileGlob::new("!(!(a))").is_err());
+    // a literal parenthesis in brackets is fine
+    assert!(m("x[*](y)", "x*(y)"));
+}
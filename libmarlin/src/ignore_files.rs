@@ -0,0 +1,180 @@
+//! `.gitignore` and `.marlinignore` files.
+//!
+//! Scans skip what the ignore files of a tree exclude, the way git does:
+//!
+//! * the rules of a directory's `.gitignore`, then its `.marlinignore`,
+//!   apply to everything below it; of all rules matching a path, the
+//!   last one in the deepest file wins;
+//! * `!pattern` includes again what an earlier rule excluded – but not
+//!   below an excluded directory, which is never entered;
+//! * `pattern/` only matches directories;
+//! * a pattern with a `/` (other than a trailing one) is anchored to the
+//!   directory of its file, any other matches names at any depth; `*`
+//!   does not cross `/`, `**` does.
+//!
+//! `.git` directories are always skipped.  Unlike root ignore globs (see
+//! [`crate::roots`]) these rules live in the tree, so a checkout brings
+//! its own.  [`IgnoreFiles`] reads each directory's files once, as the
+//! walk reaches it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use globset::{GlobBuilder, GlobMatcher};
+use tracing::{debug, warn};
+
+use crate::vfs::Vfs;
+
+/// Ignore files read in every directory, in order of precedence (later
+/// wins).
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".marlinignore"];
+
+/// One line of an ignore file.
+#[derive(Debug)]
+struct Rule {
+    glob: GlobMatcher,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Result<Self, globset::Error>> {
+        let mut pat = line.trim_end_matches(['\r', '\n']);
+        // trailing spaces are dropped unless escaped
+        let trimmed = pat.trim_end_matches(' ');
+        pat = if trimmed.ends_with('\\') && trimmed.len() < pat.len() {
+            &pat[..trimmed.len() + 1]
+        } else {
+            trimmed
+        };
+        if pat.is_empty() || pat.starts_with('#') {
+            return None;
+        }
+        let negated = pat.starts_with('!');
+        if negated {
+            pat = &pat[1..];
+        }
+        let dir_only = pat.ends_with('/');
+        let pat = pat.trim_end_matches('/');
+        let anchored = pat.contains('/');
+        let pat = pat.trim_start_matches('/');
+        if pat.is_empty() {
+            return None;
+        }
+        Some(
+            GlobBuilder::new(pat)
+                .literal_separator(true)
+                .backslash_escape(true)
+                .build()
+                .map(|g| Rule {
+                    glob: g.compile_matcher(),
+                    negated,
+                    dir_only,
+                    anchored,
+                }),
+        )
+    }
+
+    /// `rel` is the path relative to the rule's directory, `/`-separated.
+    fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            self.glob.is_match(rel)
+        } else {
+            self.glob.is_match(rel.rsplit('/').next().unwrap_or(rel))
+        }
+    }
+}
+
+/// The ignore files of one tree, read on demand.
+pub struct IgnoreFiles<'f> {
+    fs: &'f dyn Vfs,
+    base: PathBuf,
+    rules: RefCell<HashMap<PathBuf, Rc<[Rule]>>>,
+}
+
+impl<'f> IgnoreFiles<'f> {
+    /// Rules are read from `base` and the directories below it.
+    pub fn new(fs: &'f dyn Vfs, base: impl Into<PathBuf>) -> Self {
+        Self {
+            fs,
+            base: base.into(),
+            rules: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The top of the git work tree `path` lies in, if any.
+    pub fn work_tree(fs: &dyn Vfs, path: &Path) -> Option<PathBuf> {
+        path.ancestors()
+            .find(|d| {
+                let git = d.join(".git");
+                fs.is_dir(&git) || fs.metadata(&git).is_ok()
+            })
+            .map(Path::to_path_buf)
+    }
+
+    /// Whether the ignore files between the base and `path` exclude it.
+    /// Paths outside the base are never ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        if is_dir && path.file_name().is_some_and(|n| n == ".git") {
+            return true;
+        }
+        let parts: Vec<String> = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let mut dir = self.base.clone();
+        let mut ignored = false;
+        for (i, part) in parts.iter().enumerate() {
+            let below = parts[i..].join("/");
+            for rule in self.rules_in(&dir).iter() {
+                if rule.matches(&below, is_dir) {
+                    ignored = !rule.negated;
+                }
+            }
+            dir.push(part);
+        }
+        ignored
+    }
+
+    fn rules_in(&self, dir: &Path) -> Rc<[Rule]> {
+        if let Some(rules) = self.rules.borrow().get(dir) {
+            return Rc::clone(rules);
+        }
+        let mut rules = Vec::new();
+        for name in IGNORE_FILES {
+            let file = dir.join(name);
+            let mut text = String::new();
+            let Ok(mut reader) = self.fs.open(&file) else {
+                continue;
+            };
+            if reader.read_to_string(&mut text).is_err() {
+                continue;
+            }
+            debug!(file = %file.display(), "reading ignore file");
+            for (n, line) in text.lines().enumerate() {
+                match Rule::parse(line) {
+                    Some(Ok(rule)) => rules.push(rule),
+                    Some(Err(e)) => {
+                        warn!(file = %file.display(), line = n + 1, error = %e, "bad ignore pattern")
+                    }
+                    None => {}
+                }
+            }
+        }
+        let rules: Rc<[Rule]> = rules.into();
+        self.rules
+            .borrow_mut()
+            .insert(dir.to_path_buf(), Rc::clone(&rules));
+        rules
+    }
+}
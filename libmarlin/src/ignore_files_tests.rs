@@ -0,0 +1,104 @@
+// libmarlin/src/ignore_files_tests.rs
+
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use super::db;
+use super::ignore_files::IgnoreFiles;
+use super::scan::{scan_directory_opts, scan_directory_with, ScanOptions};
+use super::vfs::{MemFs, Vfs};
+
+fn tree(files: &[(&str, &str)]) -> MemFs {
+    let fs = MemFs::new();
+    for (path, body) in files {
+        fs.insert(*path, *body, UNIX_EPOCH);
+    }
+    fs
+}
+
+fn ignored(fs: &MemFs, path: &str) -> bool {
+    let path = Path::new(path);
+    IgnoreFiles::new(fs, "/r").is_ignored(path, fs.is_dir(path))
+}
+
+#[test]
+fn rules_follow_gitignore_semantics() {
+    let fs = tree(&[
+        (
+            "/r/.gitignore",
+            "# build output\ntarget/\n*.log\n!keep.log\n/only-top.txt\ndocs/*.tmp\n",
+        ),
+        ("/r/target/a.o", ""),
+        ("/r/src/target", "a file, not the directory"),
+        ("/r/x.log", ""),
+        ("/r/sub/keep.log", ""),
+        ("/r/only-top.txt", ""),
+        ("/r/sub/only-top.txt", ""),
+        ("/r/docs/a.tmp", ""),
+        ("/r/docs/deep/b.tmp", ""),
+        ("/r/.git/HEAD", ""),
+    ]);
+    assert!(ignored(&fs, "/r/target"));
+    assert!(!ignored(&fs, "/r/src/target"));
+    assert!(ignored(&fs, "/r/x.log"));
+    assert!(!ignored(&fs, "/r/sub/keep.log"));
+    assert!(ignored(&fs, "/r/only-top.txt"));
+    assert!(!ignored(&fs, "/r/sub/only-top.txt"));
+    assert!(ignored(&fs, "/r/docs/a.tmp"));
+    assert!(!ignored(&fs, "/r/docs/deep/b.tmp"));
+    assert!(ignored(&fs, "/r/.git"));
+    assert!(!ignored(&fs, "/elsewhere/x.log"));
+}
+
+#[test]
+fn deeper_and_marlin_files_win() {
+    let fs = tree(&[
+        ("/r/.gitignore", "*.csv\n"),
+        ("/r/.marlinignore", "!data.csv\n"),
+        ("/r/sub/.gitignore", "!*.csv\nbig.csv\n"),
+        ("/r/a.csv", ""),
+        ("/r/data.csv", ""),
+        ("/r/sub/b.csv", ""),
+        ("/r/sub/big.csv", ""),
+    ]);
+    assert!(ignored(&fs, "/r/a.csv"));
+    assert!(!ignored(&fs, "/r/data.csv"));
+    assert!(!ignored(&fs, "/r/sub/b.csv"));
+    assert!(ignored(&fs, "/r/sub/big.csv"));
+}
+
+#[test]
+fn scans_skip_ignored_files_unless_told_otherwise() {
+    let fs = tree(&[
+        ("/r/.git/config", ""),
+        ("/r/.gitignore", "target/\n"),
+        ("/r/target/debug/app", ""),
+        ("/r/sub/.marlinignore", "*.bak\n"),
+        ("/r/sub/notes.md", ""),
+        ("/r/sub/notes.md.bak", ""),
+    ]);
+    let mut conn = db::open(":memory:").unwrap();
+    // scanning a sub-directory still sees the work tree's top-level rules
+    assert_eq!(
+        scan_directory_with(&mut conn, &fs, Path::new("/r/sub")).unwrap(),
+        2
+    );
+    assert_eq!(
+        scan_directory_with(&mut conn, &fs, Path::new("/r")).unwrap(),
+        3
+    );
+    assert!(db::find_file_id(&conn, "/r/target/debug/app")
+        .unwrap()
+        .is_none());
+
+    let all = ScanOptions {
+        ignore_files: false,
+        ..ScanOptions::default()
+    };
+    assert_eq!(
+        scan_directory_opts(&mut conn, &fs, Path::new("/r"), &all)
+            .unwrap()
+            .indexed,
+        6
+    );
+}
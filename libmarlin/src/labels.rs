@@ -0,0 +1,212 @@
+//! Colour labels compatible with OS-native tagging.
+//!
+//! A label is one of the Finder colour names (`red`, `orange`, `yellow`,
+//! `green`, `blue`, `purple`, `gray`) or a custom `#rrggbb` hex value.
+//! Labels live in the `file_labels` table; [`read_os_labels`] and
+//! [`write_os_labels`] mirror them to the file's extended attributes:
+//!
+//! * macOS – Finder tags in `com.apple.metadata:_kMDItemUserTags`
+//!   (binary plist, `"Red\n6"` style entries).
+//! * other Unix – `user.xdg.tags`, the comma-separated list KDE
+//!   Dolphin/Baloo reads and writes.
+//!
+//! Tags that are not labels are left untouched when writing.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+/// Named labels with their Finder colour index.
+pub const NAMED_LABELS: &[(&str, u8)] = &[
+    ("gray", 1),
+    ("green", 2),
+    ("purple", 3),
+    ("blue", 4),
+    ("yellow", 5),
+    ("red", 6),
+    ("orange", 7),
+];
+
+/// Validate a user supplied label and return its canonical form
+/// (lower-case name or lower-case `#rrggbb`). `grey` is accepted as an
+/// alias for `gray`.
+pub fn normalize_label(raw: &str) -> Result<String> {
+    let l = raw.trim().to_lowercase();
+    let l = if l == "grey" { "gray".to_string() } else { l };
+
+    if NAMED_LABELS.iter().any(|(n, _)| *n == l) {
+        return Ok(l);
+    }
+    if let Some(hex) = l.strip_prefix('#') {
+        if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(l);
+        }
+    }
+    bail!(
+        "invalid label `{raw}` (expected one of {} or #rrggbb)",
+        NAMED_LABELS
+            .iter()
+            .map(|(n, _)| *n)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Map an OS tag (`Red`, `Red\n6`, `#FF8800`) to a label, if it is one.
+fn label_from_os_tag(tag: &str) -> Option<String> {
+    let name = tag.split('\n').next().unwrap_or(tag);
+    normalize_label(name).ok()
+}
+
+/// Render a label the way file managers display it (`Red`, `#ff8800`).
+fn os_tag_name(label: &str) -> String {
+    let mut chars = label.chars();
+    match chars.next() {
+        Some(c) if c != '#' => c.to_uppercase().chain(chars).collect(),
+        _ => label.to_string(),
+    }
+}
+
+/// Labels currently stored in the file's OS tags.
+///
+/// Returns an empty list when the platform or file system has no tag
+/// support.
+pub fn read_os_labels(path: &Path) -> Result<Vec<String>> {
+    let mut out: Vec<String> = os::read_tags(path)?
+        .iter()
+        .filter_map(|t| label_from_os_tag(t))
+        .collect();
+    out.sort();
+    out.dedup();
+    Ok(out)
+}
+
+/// Replace the label entries in the file's OS tags with `labels`,
+/// preserving all other tags.
+///
+/// Returns `false` when tags are not supported for this file.
+pub fn write_os_labels(path: &Path, labels: &[String]) -> Result<bool> {
+    if !os::SUPPORTED {
+        return Ok(false);
+    }
+    let mut tags: Vec<String> = os::read_tags(path)?
+        .into_iter()
+        .filter(|t| label_from_os_tag(t).is_none())
+        .collect();
+    for l in labels {
+        tags.push(os::encode_label(l));
+    }
+    os::write_tags(path, &tags)
+}
+
+/* ─── platform back-ends ───────────────────────────────────────────── */
+
+/// `true` when the error means “this file system has no xattrs”.
+#[cfg(unix)]
+fn unsupported(e: &std::io::Error) -> bool {
+    e.raw_os_error()
+        .is_some_and(|c| c == libc::ENOTSUP || c == libc::EOPNOTSUPP)
+}
+
+#[cfg(target_os = "macos")]
+mod os {
+    use super::{os_tag_name, unsupported, NAMED_LABELS};
+    use anyhow::Result;
+    use std::path::Path;
+
+    pub const SUPPORTED: bool = true;
+    const ATTR: &str = "com.apple.metadata:_kMDItemUserTags";
+
+    pub fn read_tags(path: &Path) -> Result<Vec<String>> {
+        match xattr::get(path, ATTR) {
+            Ok(Some(raw)) => Ok(plist::from_bytes::<Vec<String>>(&raw)?),
+            Ok(None) => Ok(Vec::new()),
+            Err(e) if unsupported(&e) => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn write_tags(path: &Path, tags: &[String]) -> Result<bool> {
+        let mut buf = Vec::new();
+        plist::to_writer_binary(&mut buf, &tags)?;
+        match xattr::set(path, ATTR, &buf) {
+            Ok(()) => Ok(true),
+            Err(e) if unsupported(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Finder stores the colour index after a newline (`Red\n6`).
+    pub fn encode_label(label: &str) -> String {
+        match NAMED_LABELS.iter().find(|(n, _)| *n == label) {
+            Some((_, idx)) => format!("{}\n{}", os_tag_name(label), idx),
+            None => os_tag_name(label),
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod os {
+    use super::{os_tag_name, unsupported};
+    use anyhow::Result;
+    use std::path::Path;
+
+    pub const SUPPORTED: bool = true;
+    const ATTR: &str = "user.xdg.tags";
+
+    pub fn read_tags(path: &Path) -> Result<Vec<String>> {
+        match xattr::get(path, ATTR) {
+            Ok(Some(raw)) => Ok(String::from_utf8_lossy(&raw)
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect()),
+            Ok(None) => Ok(Vec::new()),
+            Err(e) if unsupported(&e) => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn write_tags(path: &Path, tags: &[String]) -> Result<bool> {
+        let res = if tags.is_empty() {
+            match xattr::get(path, ATTR) {
+                Ok(Some(_)) => xattr::remove(path, ATTR),
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            }
+        } else {
+            xattr::set(path, ATTR, tags.join(",").as_bytes())
+        };
+        match res {
+            Ok(()) => Ok(true),
+            Err(e) if unsupported(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn encode_label(label: &str) -> String {
+        os_tag_name(label)
+    }
+}
+
+#[cfg(not(unix))]
+mod os {
+    use super::os_tag_name;
+    use anyhow::Result;
+    use std::path::Path;
+
+    pub const SUPPORTED: bool = false;
+
+    pub fn read_tags(_path: &Path) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    pub fn write_tags(_path: &Path, _tags: &[String]) -> Result<bool> {
+        Ok(false)
+    }
+
+    pub fn encode_label(label: &str) -> String {
+        os_tag_name(label)
+    }
+}
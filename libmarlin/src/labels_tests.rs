@@ -0,0 +1,52 @@
+// libmarlin/src/labels_tests.rs
+
+use super::labels::{normalize_label, read_os_labels, write_os_labels};
+use super::{db, query};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn normalize_accepts_names_and_hex() {
+    assert_eq!(normalize_label("Red").unwrap(), "red");
+    assert_eq!(normalize_label("grey").unwrap(), "gray");
+    assert_eq!(normalize_label("#FF8800").unwrap(), "#ff8800");
+    assert!(normalize_label("magenta").is_err());
+    assert!(normalize_label("#12345").is_err());
+}
+
+#[test]
+fn label_query_filters_files() {
+    let conn = db::open(":memory:").unwrap();
+    conn.execute(
+        "INSERT INTO files(path, size, mtime) VALUES ('/a.txt',0,0), ('/b.txt',0,0)",
+        [],
+    )
+    .unwrap();
+    let a = db::file_id(&conn, "/a.txt").unwrap();
+    db::add_label(&conn, a, "red").unwrap();
+    db::add_label(&conn, a, "red").unwrap(); // idempotent
+    assert_eq!(db::file_labels(&conn, a).unwrap(), vec!["red"]);
+
+    assert_eq!(query::search(&conn, "label:Red").unwrap(), vec!["/a.txt"]);
+    assert!(query::search(&conn, "label:blue").unwrap().is_empty());
+    assert!(query::search(&conn, "label:nope").is_err());
+
+    db::remove_label(&conn, a, "red").unwrap();
+    assert!(query::search(&conn, "label:red").unwrap().is_empty());
+}
+
+#[test]
+fn os_labels_roundtrip_when_supported() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("photo.jpg");
+    fs::write(&file, b"jpg").unwrap();
+
+    let labels = vec!["#00ff00".to_string(), "red".to_string()];
+    if !write_os_labels(&file, &labels).unwrap() {
+        return; // no xattr support on this file system
+    }
+    assert_eq!(read_os_labels(&file).unwrap(), labels);
+
+    write_os_labels(&file, &[]).unwrap();
+    assert!(read_os_labels(&file).unwrap().is_empty());
+}
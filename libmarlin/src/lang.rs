@@ -0,0 +1,206 @@
+//! Primary language of a text, as an ISO 639-1 code.
+//!
+//! Scans read the start of every new or changed text file (where the root
+//! profile allows reading bodies) and store the result as the attribute
+//! [`LANG_KEY`], which `lang:de` queries match against.  Content search
+//! can use the same code to pick a tokenizer or stemmer per document.
+//!
+//! Detection is deliberately small and dependency free:
+//!
+//! * texts mostly in a script used by one language (Greek, Hebrew,
+//!   Hangul, kana, Han, Thai, Arabic) get that language; Cyrillic is
+//!   split into Russian, Ukrainian and Bulgarian by their own letters
+//! * Latin-script texts are scored by how many of their words are common
+//!   function words of each supported language (`en`, `de`, `fr`, `es`,
+//!   `it`, `nl`, `pt`, `sv`)
+//!
+//! Anything too short or too mixed to call is `None`.
+
+use std::io::Read;
+
+use crate::utils::read_text_sample;
+
+/// Attribute the detected language is stored under.
+pub const LANG_KEY: &str = "meta/lang";
+
+/// How much of a file is read to detect its language.
+pub const SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Latin-script texts need at least this many function words …
+const MIN_HITS: usize = 3;
+/// … making up at least 1 in this many words.
+const MIN_RATIO: usize = 10;
+
+const STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "of", "to", "is", "that", "it", "for", "was", "with", "as", "on", "be",
+            "this", "are", "by", "not", "have", "from", "you", "or", "but", "which", "they", "at",
+            "an", "we", "were", "has", "will", "would", "there", "their", "what", "can",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "und", "den", "von", "zu", "das", "mit", "sich", "des", "auf", "für",
+            "ist", "im", "dem", "nicht", "ein", "eine", "als", "auch", "werden", "aus", "er",
+            "hat", "dass", "sie", "nach", "wird", "bei", "einer", "um", "sind", "noch", "wie",
+            "über", "einen", "zum", "war", "haben", "nur", "oder", "aber", "zur", "ich", "wir",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "des", "et", "un", "une", "du", "est", "qui", "dans", "pour", "pas",
+            "au", "sur", "ne", "ce", "il", "elle", "avec", "par", "sont", "mais", "nous", "vous",
+            "ou", "leur", "aux", "été", "cette", "être", "je", "ont",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "y", "en", "que", "un", "una", "es", "por", "con", "para",
+            "del", "se", "no", "al", "lo", "como", "más", "pero", "sus", "ya", "este", "fue", "ha",
+            "porque", "esta", "son", "entre", "cuando", "muy", "también", "yo",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "lo", "la", "gli", "le", "di", "che", "un", "una", "per", "non", "con", "del",
+            "della", "sono", "è", "si", "da", "al", "alla", "come", "ma", "più", "anche", "questo",
+            "nel", "nella", "dei", "delle", "ha", "essere", "io",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "van", "is", "dat", "op", "te", "zijn", "voor", "met", "die",
+            "niet", "aan", "er", "om", "ook", "als", "bij", "maar", "wordt", "door", "naar", "dan",
+            "wat", "nog", "kan", "worden", "hij", "zij", "deze", "ik",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "as", "de", "e", "do", "da", "dos", "das", "em", "um", "uma", "que", "para",
+            "com", "não", "por", "no", "na", "mais", "como", "mas", "ao", "foi", "é", "são", "seu",
+            "sua", "ou", "também", "pelo", "pela", "isso", "está", "eu",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "att", "det", "som", "en", "på", "är", "av", "för", "med", "till", "den", "har",
+            "inte", "om", "ett", "var", "jag", "men", "så", "vi", "kan", "han", "hon", "från",
+            "vid", "eller", "sig", "när", "efter", "nu",
+        ],
+    ),
+];
+
+/// The primary language of `text`, if it can be told.
+pub fn detect(text: &str) -> Option<&'static str> {
+    by_script(text).or_else(|| by_words(text))
+}
+
+/// [`detect`] on the first [`SAMPLE_BYTES`] of `r`, if they look like
+/// text (see [`read_text_sample`]).
+pub fn detect_reader(r: impl Read) -> Option<&'static str> {
+    detect(&read_text_sample(r, SAMPLE_BYTES)?)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Hebrew,
+    Arabic,
+    Thai,
+    Hangul,
+    Kana,
+    Han,
+    Other,
+}
+
+fn script(c: char) -> Script {
+    match c as u32 {
+        0x41..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+        0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x400..=0x52F => Script::Cyrillic,
+        0x590..=0x5FF => Script::Hebrew,
+        0x600..=0x6FF | 0x750..=0x77F => Script::Arabic,
+        0xE00..=0xE7F => Script::Thai,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        0x3040..=0x30FF => Script::Kana,
+        0x4E00..=0x9FFF | 0x3400..=0x4DBF => Script::Han,
+        _ => Script::Other,
+    }
+}
+
+/// Languages told apart by their script alone.
+fn by_script(text: &str) -> Option<&'static str> {
+    let mut counts = [0usize; 10];
+    let mut letters = 0usize;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        counts[script(c) as usize] += 1;
+        letters += 1;
+    }
+    let n = |s: Script| counts[s as usize];
+    if letters == 0 || n(Script::Latin) * 2 >= letters {
+        return None;
+    }
+    // Japanese mixes kana into Han; Chinese has none
+    if n(Script::Kana) > 0 && (n(Script::Kana) + n(Script::Han)) * 2 > letters {
+        return Some("ja");
+    }
+    let lang = [
+        (Script::Han, "zh"),
+        (Script::Hangul, "ko"),
+        (Script::Greek, "el"),
+        (Script::Hebrew, "he"),
+        (Script::Arabic, "ar"),
+        (Script::Thai, "th"),
+    ]
+    .into_iter()
+    .find(|&(s, _)| n(s) * 2 > letters)
+    .map(|(_, lang)| lang);
+    if lang.is_some() {
+        return lang;
+    }
+    if n(Script::Cyrillic) * 2 > letters {
+        let has = |set: &str| {
+            text.chars()
+                .any(|c| set.contains(c.to_lowercase().next().unwrap_or(c)))
+        };
+        return Some(if has("іїєґ") {
+            "uk"
+        } else if has("ъ") && !has("ыэё") {
+            "bg"
+        } else {
+            "ru"
+        });
+    }
+    None
+}
+
+/// Latin-script languages by their function words.
+fn by_words(text: &str) -> Option<&'static str> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(lang, stop)| {
+            let hits = words.iter().filter(|w| stop.contains(&w.as_str())).count();
+            (*lang, hits)
+        })
+        .collect();
+    scores.sort_by_key(|&(_, hits)| std::cmp::Reverse(hits));
+    let (lang, best) = scores[0];
+    let second = scores[1].1;
+    (best >= MIN_HITS && best * MIN_RATIO >= words.len() && best > second).then_some(lang)
+}
@@ -0,0 +1,92 @@
+// libmarlin/src/lang_tests.rs
+
+use super::lang::{detect, detect_reader};
+
+#[test]
+fn detect_tells_latin_languages_by_their_function_words() {
+    for (text, lang) in [
+        (
+            "The report for the second quarter is ready and it was sent to the board.",
+            "en",
+        ),
+        (
+            "Der Bericht für das zweite Quartal ist fertig und wird an den Vorstand geschickt.",
+            "de",
+        ),
+        (
+            "Le rapport du deuxième trimestre est prêt et il a été envoyé au conseil.",
+            "fr",
+        ),
+        (
+            "El informe del segundo trimestre está listo y se ha enviado a la junta.",
+            "es",
+        ),
+        (
+            "Il rapporto del secondo trimestre è pronto e non è stato ancora inviato al consiglio.",
+            "it",
+        ),
+        (
+            "Het rapport van het tweede kwartaal is klaar en wordt naar de raad gestuurd.",
+            "nl",
+        ),
+        (
+            "O relatório do segundo trimestre está pronto e não foi enviado para a diretoria.",
+            "pt",
+        ),
+        (
+            "Rapporten för det andra kvartalet är klar och har skickats till styrelsen.",
+            "sv",
+        ),
+    ] {
+        assert_eq!(detect(text), Some(lang), "{text}");
+    }
+}
+
+#[test]
+fn detect_tells_other_scripts_apart() {
+    assert_eq!(
+        detect("Отчёт за второй квартал готов и был отправлен."),
+        Some("ru")
+    );
+    assert_eq!(
+        detect("Звіт за другий квартал готовий і надісланий."),
+        Some("uk")
+    );
+    assert_eq!(detect("Отчетът за второто тримесечие е готов."), Some("bg"));
+    assert_eq!(
+        detect("Η αναφορά του δεύτερου τριμήνου είναι έτοιμη."),
+        Some("el")
+    );
+    assert_eq!(detect("第二季度的报告已经准备好了。"), Some("zh"));
+    assert_eq!(
+        detect("第二四半期の報告書は準備ができています。"),
+        Some("ja")
+    );
+    assert_eq!(detect("2분기 보고서가 준비되었습니다."), Some("ko"));
+    assert_eq!(detect("הדוח לרבעון השני מוכן."), Some("he"));
+}
+
+#[test]
+fn detect_gives_up_on_short_or_mixed_text() {
+    assert_eq!(detect(""), None);
+    assert_eq!(detect("fn main() { println!(\"hi\"); }"), None);
+    assert_eq!(detect("the"), None);
+    // as many English as German function words
+    assert_eq!(detect("the and of der die und"), None);
+}
+
+#[test]
+fn detect_reader_skips_binary_and_tolerates_a_cut_off_character() {
+    let mut bin = b"The report is ready and it is for the board.".to_vec();
+    bin.push(0);
+    assert_eq!(detect_reader(&bin[..]), None);
+
+    // the sample ends inside a multi-byte character
+    let mut text = "Der Bericht ist fertig und wird an den Vorstand geschickt."
+        .as_bytes()
+        .to_vec();
+    text.extend_from_slice(&"ü".as_bytes()[..1]);
+    assert_eq!(detect_reader(&text[..]), Some("de"));
+
+    assert_eq!(detect_reader(&[0xff, 0xfe, b'a'][..]), None);
+}
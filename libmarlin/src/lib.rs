@@ -5,62 +5,237 @@
 //! directly.  That gives us room to refactor internals without breaking
 //! callers.
 
+//!
+//! Optional subsystems sit behind cargo features (both on by default):
+//! `watcher` (`Marlin::watch`, pulls in `notify`/`crossbeam-channel`) and
+//! `backup` (`Marlin::backup`/`restore`, SQLite's online backup API).
+//! Tagging, attributes, search and views are always available.
+//!
+//! File access during scans and body search goes through [`vfs::Vfs`];
+//! the `_with` variants of those functions take a custom implementation.
+//! Index access for files, tags, attributes, links and search is also
+//! available through [`store::Store`], with the SQLite connection as the
+//! default backend; [`Marlin::with_store`] tags and sets attributes on
+//! any other.
+
 #![deny(warnings)]
 
+#[cfg(feature = "backup")]
 pub mod backup;
+pub mod bench;
+pub mod classify;
 pub mod config;
+pub mod conflicts;
+pub mod control;
 pub mod db;
+pub mod describe;
+pub mod embed;
 pub mod error;
+pub mod events;
+pub mod exec;
+pub mod globs;
+pub mod ignore_files;
+pub mod labels;
+pub mod lang;
+pub mod links;
+pub mod lock;
 pub mod logging;
+pub mod notify;
+pub mod organize;
+pub mod prune;
+pub mod query;
+pub mod query_cache;
+pub mod roots;
 pub mod scan;
+pub mod scan_lease;
+pub mod snapshot;
+pub mod store;
+pub mod sync;
+pub mod test_utils;
+pub mod timefmt;
+pub mod tokens;
 pub mod utils;
+pub mod validate;
+pub mod versions;
+pub mod vfs;
+#[cfg(feature = "watcher")]
 pub mod watcher;
 
+#[cfg(test)]
+mod classify_tests;
 #[cfg(test)]
 mod config_tests;
 #[cfg(test)]
+mod control_tests;
+#[cfg(test)]
 mod db_tests;
 #[cfg(test)]
+mod describe_tests;
+#[cfg(test)]
+mod embed_tests;
+#[cfg(test)]
+mod events_tests;
+#[cfg(test)]
+mod exec_tests;
+#[cfg(test)]
 mod facade_tests;
 #[cfg(test)]
+mod globs_tests;
+#[cfg(test)]
+mod ignore_files_tests;
+#[cfg(test)]
+mod labels_tests;
+#[cfg(test)]
+mod lang_tests;
+#[cfg(test)]
+mod links_tests;
+#[cfg(test)]
+mod lock_tests;
+#[cfg(test)]
 mod logging_tests;
 #[cfg(test)]
+mod notify_tests;
+#[cfg(test)]
+mod organize_tests;
+#[cfg(test)]
+mod prune_tests;
+#[cfg(test)]
+mod query_cache_tests;
+#[cfg(test)]
+mod query_tests;
+#[cfg(test)]
+mod roots_tests;
+#[cfg(test)]
+mod scan_lease_tests;
+#[cfg(test)]
 mod scan_tests;
 #[cfg(test)]
-mod test_utils;
+mod snapshot_tests;
+#[cfg(test)]
+mod store_tests;
+#[cfg(test)]
+mod sync_tests;
+#[cfg(test)]
+mod test_utils_tests;
+#[cfg(test)]
+mod timefmt_tests;
+#[cfg(test)]
+mod tokens_tests;
 #[cfg(test)]
 mod utils_tests;
 #[cfg(test)]
+mod validate_tests;
+#[cfg(test)]
+mod versions_tests;
+#[cfg(test)]
+mod vfs_tests;
+#[cfg(all(test, feature = "watcher"))]
 mod watcher_tests;
 
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use std::sync::{Arc, Mutex};
 use std::{
     fs,
-    path::Path,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
 };
+use store::Store;
+
+/// Files [`Marlin::tag`] reads and tags per transaction.
+pub const TAG_CHUNK: usize = 10_000;
 
 /// Main handle for interacting with a Marlin database.
-pub struct Marlin {
+///
+/// The connection lives behind a shared [`db::Database`], so watchers
+/// spawned by [`Marlin::watch`] write through the very same handle –
+/// which also makes `:memory:` databases watchable.
+///
+/// `S` is the [`Store`] the handle works on.  Tagging and attributes go
+/// through the trait and run on any backend; everything else needs the
+/// default, SQLite.
+pub struct Marlin<S = rusqlite::Connection> {
     cfg: config::Config,
-    conn: Connection,
+    db: Arc<Mutex<db::Database<S>>>,
+    query_cache: Mutex<Option<query_cache::QueryCache>>,
+}
+
+impl<S: Store> Marlin<S> {
+    /// Wrap an already open `store`, e.g. a remote or in-memory backend.
+    pub fn with_store(cfg: config::Config, store: S) -> Self {
+        Marlin {
+            cfg,
+            db: Arc::new(Mutex::new(db::Database::new(store))),
+            query_cache: Mutex::new(None),
+        }
+    }
+
+    /// A second handle on the same connection, e.g. for a UI thread that
+    /// reads while this one runs commands.  Both lock the one connection,
+    /// so only one of them talks to SQLite at a time; each keeps its own
+    /// query cache.
+    pub fn share(&self) -> Self {
+        Marlin {
+            cfg: self.cfg.clone(),
+            db: Arc::clone(&self.db),
+            query_cache: Mutex::new(None),
+        }
+    }
+
+    /// Borrow the store, locked like [`Marlin::conn`].
+    pub fn store(&self) -> db::ConnGuard<'_, S> {
+        db::Database::lock(&self.db)
+    }
+
+    /// Attach a hierarchical tag (`foo/bar`) to every _indexed_ file
+    /// matching the glob.  Returns the number of files actually updated.
+    ///
+    /// Missing tags are created unless the index has `tags.strict` on, in
+    /// which case an unknown tag is an error.  So is a matching locked
+    /// file (see [`lock`]), before anything is tagged.
+    ///
+    /// On SQLite, files are read and tagged [`TAG_CHUNK`] rows at a time,
+    /// one transaction per chunk, so memory stays flat however large the
+    /// index is.
+    pub fn tag(&mut self, pattern: &str, tag_path: &str) -> Result<usize> {
+        self.store().tag_glob(pattern, tag_path)
+    }
+
+    /// Set `key=value` on every indexed file matching the glob (see
+    /// [`db::match_files`]).  Returns the number of files updated.
+    pub fn attr_set(&mut self, pattern: &str, key: &str, value: &str) -> Result<usize> {
+        let store = self.store();
+        let files = store.match_files(pattern)?;
+        store.check_unlocked(&files)?;
+        for (fid, _) in &files {
+            store.upsert_attr(*fid, key, value)?;
+        }
+        Ok(files.len())
+    }
+
+    /// The resolved configuration.
+    pub fn config(&self) -> &config::Config {
+        &self.cfg
+    }
 }
 
 impl Marlin {
     /// Open using the default config (env override or XDG/CWD fallback),
     /// ensuring parent directories exist and applying migrations.
     pub fn open_default() -> Result<Self> {
-        // 1) Load configuration
-        let cfg = config::Config::load()?;
-        // 2) Ensure the DB's parent directory exists
+        Self::open_with(config::Config::load()?)
+    }
+
+    /// Open the database an already resolved `cfg` points at, keeping
+    /// its backups directory – what the CLI does after applying `--db`
+    /// and `--backup-dir`.
+    pub fn open_with(cfg: config::Config) -> Result<Self> {
+        // Ensure the DB's parent directory exists
         if let Some(parent) = cfg.db_path.parent() {
             fs::create_dir_all(parent)?;
         }
-        // 3) Open the database and run migrations
+        // Open the database and run migrations
         let conn = db::open(&cfg.db_path)
             .context(format!("opening database at {}", cfg.db_path.display()))?;
-        Ok(Marlin { cfg, conn })
+        Ok(Marlin::with_store(cfg, conn))
     }
 
     /// Open a Marlin instance at the specified database path,
@@ -72,88 +247,152 @@ impl Marlin {
             fs::create_dir_all(parent)?;
         }
         // Build a minimal Config so callers can still inspect cfg.db_path
-        let cfg = config::Config {
-            db_path: db_path.to_path_buf(),
-        };
+        let cfg = config::Config::for_db_path(db_path);
         // Open the database and run migrations
         let conn =
             db::open(db_path).context(format!("opening database at {}", db_path.display()))?;
-        Ok(Marlin { cfg, conn })
+        Ok(Marlin::with_store(cfg, conn))
     }
 
-    /// Recursively index one or more directories.
-    pub fn scan<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<usize> {
-        let mut total = 0;
-        for p in paths {
-            total += scan::scan_directory(&mut self.conn, p.as_ref())?;
+    /// Open several databases to search them together (see
+    /// [`MarlinGroup`]).  Each one is labelled with its file stem
+    /// (`work.db` → `work`), or its full path where stems clash.
+    pub fn open_many<P: AsRef<Path>>(db_paths: &[P]) -> Result<MarlinGroup> {
+        let stem = |p: &Path| {
+            p.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| p.display().to_string())
+        };
+        let mut members = Vec::with_capacity(db_paths.len());
+        for p in db_paths {
+            let p = p.as_ref();
+            let clash = db_paths
+                .iter()
+                .filter(|o| stem(o.as_ref()) == stem(p))
+                .count()
+                > 1;
+            let label = if clash {
+                p.display().to_string()
+            } else {
+                stem(p)
+            };
+            members.push((label, Marlin::open_at(p)?));
         }
-        Ok(total)
+        Ok(MarlinGroup { members })
     }
 
-    /// Attach a hierarchical tag (`foo/bar`) to every _indexed_ file
-    /// matching the glob.  Returns the number of files actually updated.
-    pub fn tag(&mut self, pattern: &str, tag_path: &str) -> Result<usize> {
-        use glob::Pattern;
-
-        // 1) ensure tag hierarchy
-        let leaf = db::ensure_tag_path(&self.conn, tag_path)?;
-
-        // 2) collect leaf + ancestors
-        let mut tag_ids = Vec::new();
-        let mut cur = Some(leaf);
-        while let Some(id) = cur {
-            tag_ids.push(id);
-            cur = self
-                .conn
-                .query_row("SELECT parent_id FROM tags WHERE id = ?1", [id], |r| {
-                    r.get::<_, Option<i64>>(0)
-                })?;
-        }
-
-        // 3) match files by glob against stored paths
-        let expanded = shellexpand::tilde(pattern).into_owned();
-        let pat = Pattern::new(&expanded)
-            .with_context(|| format!("Invalid glob pattern `{}`", expanded))?;
-
-        let mut stmt_all = self.conn.prepare("SELECT id, path FROM files")?;
-        let rows = stmt_all.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
-
-        let mut stmt_ins = self
-            .conn
-            .prepare("INSERT OR IGNORE INTO file_tags(file_id, tag_id) VALUES (?1, ?2)")?;
-
-        let mut changed = 0;
-        for row in rows {
-            let (fid, path_str): (i64, String) = row?;
-            let is_match = if expanded.contains(std::path::MAIN_SEPARATOR) {
-                pat.matches(&path_str)
-            } else {
-                Path::new(&path_str)
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| pat.matches(n))
-                    .unwrap_or(false)
-            };
-            if !is_match {
-                continue;
+    /// Recursively index one or more directories, waiting for any other
+    /// process already scanning one of them (see [`scan_with`]).
+    ///
+    /// [`scan_with`]: Marlin::scan_with
+    pub fn scan<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<usize> {
+        self.scan_with(paths, scan_lease::OnBusy::Wait)
+    }
+
+    /// [`scan`](Marlin::scan), with `on_busy` deciding what happens to
+    /// roots that overlap a scan another process has in progress.  The
+    /// roots are recorded as being scanned for as long as this one runs;
+    /// see [`scan_lease`].
+    pub fn scan_with<P: AsRef<Path>>(
+        &mut self,
+        paths: &[P],
+        on_busy: scan_lease::OnBusy,
+    ) -> Result<usize> {
+        Ok(self
+            .scan_opts(paths, on_busy, &scan::ScanOptions::default())?
+            .indexed)
+    }
+
+    /// [`scan_with`](Marlin::scan_with) with explicit
+    /// [`ScanOptions`](scan::ScanOptions), e.g. to index what ignore files
+    /// exclude or to prune files gone from disk.  Reports are summed over
+    /// the roots.
+    pub fn scan_opts<P: AsRef<Path>>(
+        &mut self,
+        paths: &[P],
+        on_busy: scan_lease::OnBusy,
+        opts: &scan::ScanOptions,
+    ) -> Result<scan::ScanReport> {
+        let paths: Vec<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+        let (todo, mut lease) = loop {
+            let todo = scan_lease::settle(|| self.conn(), &paths, on_busy)?;
+            if let Some(lease) = scan_lease::Lease::try_claim(&self.conn(), &todo)? {
+                break (todo, lease);
             }
+        };
 
-            let mut newly = false;
-            for &tid in &tag_ids {
-                if stmt_ins.execute([fid, tid])? > 0 {
-                    newly = true;
+        let mut conn = self.conn();
+        let mut total = scan::ScanReport::default();
+        let mut scanned = Ok(());
+        for p in &todo {
+            match scan::scan_directory_opts(&mut conn, &vfs::StdFs, p, opts) {
+                Ok(report) => {
+                    total.indexed += report.indexed;
+                    total.pruned += report.pruned;
+                    lease.finish(&conn, p)?;
+                }
+                Err(e) => {
+                    scanned = Err(e);
+                    break;
                 }
-            }
-            if newly {
-                changed += 1;
             }
         }
-        Ok(changed)
+        lease.release(&conn)?;
+        scanned.map(|()| total)
+    }
+
+    /// Force a metadata and hash refresh of specific files; see
+    /// [`scan::reindex_files`].
+    pub fn reindex<P: AsRef<Path>>(&self, paths: &[P]) -> Result<usize> {
+        scan::reindex_files(&mut self.conn(), paths)
+    }
+
+    /// Freeze the metadata of every indexed file matching the glob (see
+    /// [`lock`]).  Returns the number of files newly locked.
+    pub fn lock(&mut self, pattern: &str) -> Result<usize> {
+        let conn = self.conn();
+        lock::lock_files(&conn, &db::match_files(&conn, pattern)?)
+    }
+
+    /// Undo [`Marlin::lock`].  Returns the number of files unlocked.
+    pub fn unlock(&mut self, pattern: &str) -> Result<usize> {
+        let conn = self.conn();
+        lock::unlock_files(&conn, &db::match_files(&conn, pattern)?)
     }
 
-    /// Full-text search over path, tags, and attrs, with substring fallback.
+    /// Add a note to the indexed file at `path` (resolved like
+    /// [`Marlin::describe`]), about a line or byte `range` of it or the
+    /// whole file.  Returns the annotation's id.
+    pub fn annotate(
+        &mut self,
+        path: &str,
+        note: &str,
+        range: Option<db::AnnotationRange>,
+        highlight: bool,
+    ) -> Result<i64> {
+        let conn = self.conn();
+        let (fid, _) = describe::resolve(&conn, path)?;
+        lock::check(&conn, fid)?;
+        db::add_annotation(&conn, fid, note, range, highlight)
+    }
+
+    /// The annotations on the indexed file at `path`.
+    pub fn annotations_for(&self, path: &str) -> Result<Vec<db::Annotation>> {
+        let conn = self.conn();
+        let (fid, _) = describe::resolve(&conn, path)?;
+        db::annotations(&conn, fid)
+    }
+
+    /// Everything the index knows about the file at `path` (see
+    /// [`describe::describe`]).
+    pub fn describe(&self, path: &str) -> Result<describe::FileReport> {
+        describe::describe(&self.conn(), path)
+    }
+
+    /// Full-text search over path, tags, attrs and notes, with substring fallback.
     pub fn search(&self, query: &str) -> Result<Vec<String>> {
-        let mut stmt = self.conn.prepare(
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
             "SELECT f.path FROM files_fts JOIN files f ON f.rowid = files_fts.rowid WHERE files_fts MATCH ?1 ORDER BY rank",
         )?;
         let mut hits = stmt
@@ -161,55 +400,356 @@ impl Marlin {
             .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
 
         if hits.is_empty() && !query.contains(':') {
-            hits = self.fallback_search(query)?;
+            hits = fallback_search(&conn, query)?;
         }
         Ok(hits)
     }
 
-    fn fallback_search(&self, term: &str) -> Result<Vec<String>> {
-        let needle = term.to_lowercase();
-        let mut stmt = self.conn.prepare("SELECT path FROM files")?;
-        let rows = stmt.query_map([], |r| r.get(0))?;
-        let mut out = Vec::new();
-        for res in rows {
-            let p: String = res?;
-            if p.to_lowercase().contains(&needle) {
-                out.push(p.clone());
-                continue;
-            }
-            if let Ok(meta) = fs::metadata(&p) {
-                if meta.len() <= 65_536 {
-                    if let Ok(body) = fs::read_to_string(&p) {
-                        if body.to_lowercase().contains(&needle) {
-                            out.push(p.clone());
-                        }
-                    }
-                }
-            }
+    /// Run a saved view through the shared query engine – the same path
+    /// `marlin view exec` takes.
+    pub fn view_exec(
+        &self,
+        name: &str,
+        opts: query::SearchOptions,
+    ) -> Result<Vec<query::SearchHit>> {
+        let conn = self.conn();
+        match self.cache().as_mut() {
+            Some(cache) => cache.view_exec(&conn, name, &opts),
+            None => query::view_exec(&conn, name, &opts),
+        }
+    }
+
+    /// Run a raw query through the shared engine ([`query::search_hits`]),
+    /// served from the result cache when [`enable_query_cache`] was called.
+    ///
+    /// [`enable_query_cache`]: Marlin::enable_query_cache
+    pub fn search_hits(
+        &self,
+        raw: &str,
+        opts: &query::SearchOptions,
+    ) -> Result<Vec<query::SearchHit>> {
+        let conn = self.conn();
+        match self.cache().as_mut() {
+            Some(cache) => cache.search_hits(&conn, raw, opts),
+            None => query::search_hits(&conn, raw, opts),
         }
-        Ok(out)
+    }
+
+    /// Search for `new_query`, narrowing `prev_results` instead of the
+    /// whole index when the new query just appends terms to theirs – the
+    /// usual case while someone types a query in a search box.  Pass the
+    /// returned set back in for the next keystroke; see
+    /// [`query::search_incremental`] for when the full search runs.
+    pub fn search_incremental(
+        &self,
+        prev_results: Option<&query::ResultSet>,
+        new_query: &str,
+    ) -> Result<query::ResultSet> {
+        query::search_incremental(&self.conn(), prev_results, new_query)
+    }
+
+    /// Run the configured classifiers (see [`classify`]) on the indexed
+    /// files matching `pattern` and apply their suggestions below `auto/`.
+    pub fn classify(
+        &self,
+        pattern: &str,
+        opts: classify::ClassifyOptions,
+    ) -> Result<classify::ClassifyReport> {
+        let mut conn = self.conn();
+        let files = db::match_files(&conn, pattern)?;
+        classify::classify_files(&mut conn, &files, opts)
+    }
+
+    /// Store `embedder`'s vectors for the indexed files matching `pattern`
+    /// (see [`db::match_files`]), skipping those already up to date
+    /// unless `force`.
+    pub fn embed(
+        &self,
+        embedder: &dyn embed::Embedder,
+        pattern: &str,
+        force: bool,
+    ) -> Result<embed::EmbedReport> {
+        let conn = self.conn();
+        let files = db::match_files(&conn, pattern)?;
+        embed::embed_files(&conn, embedder, &files, force)
+    }
+
+    /// The `limit` files closest in meaning to `text` under `embedder`
+    /// (see [`embed::semantic_search`]).
+    pub fn search_semantic(
+        &self,
+        embedder: &dyn embed::Embedder,
+        text: &str,
+        limit: usize,
+    ) -> Result<Vec<embed::SemanticHit>> {
+        embed::semantic_search(&self.conn(), embedder, text, limit)
+    }
+
+    /// Keep up to `capacity` result sets of [`search_hits`] and
+    /// [`view_exec`] in memory; repeated identical queries then skip SQLite
+    /// until something is written.  Calling it again resets the cache.
+    ///
+    /// [`search_hits`]: Marlin::search_hits
+    /// [`view_exec`]: Marlin::view_exec
+    pub fn enable_query_cache(&self, capacity: usize) {
+        *self.cache() = Some(query_cache::QueryCache::new(capacity));
+    }
+
+    /// Hit/miss counters of the query cache, `None` while it is disabled.
+    pub fn query_cache_stats(&self) -> Option<query_cache::CacheStats> {
+        self.cache().as_ref().map(|c| c.stats())
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, Option<query_cache::QueryCache>> {
+        self.query_cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /* ── backups ─────────────────────────────────────────────── */
+
+    #[cfg(feature = "backup")]
+    fn backup_manager(&self) -> Result<backup::BackupManager> {
+        backup::BackupManager::new(&self.cfg.db_path, &self.cfg.backups_dir)
+    }
+
+    /// Snapshot the database into the backups directory.
+    #[cfg(feature = "backup")]
+    pub fn backup(&self) -> Result<backup::BackupInfo> {
+        self.backup_manager()?.create_backup()
+    }
+
+    /// All snapshots, newest first.
+    #[cfg(feature = "backup")]
+    pub fn list_backups(&self) -> Result<Vec<backup::BackupInfo>> {
+        self.backup_manager()?.list_backups()
+    }
+
+    /// Replace the live database with snapshot `backup_id`.
+    ///
+    /// Uses SQLite's online backup API through the open connection, so the
+    /// handle stays valid and no stale WAL pages survive the restore.
+    #[cfg(feature = "backup")]
+    pub fn restore(&mut self, backup_id: &str) -> Result<()> {
+        let src = self.backup_manager()?.backup_path(backup_id)?;
+        backup::BackupMeta::load(&src)?.ensure_compatible(false)?;
+        self.conn()
+            .restore(
+                rusqlite::DatabaseName::Main,
+                &src,
+                None::<fn(rusqlite::backup::Progress)>,
+            )
+            .with_context(|| format!("restoring from {}", src.display()))?;
+        Ok(())
+    }
+
+    /// Restore snapshot `backup_id` as a new database at `target`
+    /// (e.g. to fork a workspace); the live DB is left untouched.
+    #[cfg(feature = "backup")]
+    pub fn restore_to<P: AsRef<Path>>(&self, backup_id: &str, target: P) -> Result<()> {
+        self.backup_manager()?.restore_to(backup_id, target)
     }
 
     /// Borrow the raw SQLite connection.
-    pub fn conn(&self) -> &Connection {
-        &self.conn
+    ///
+    /// The guard locks the connection shared with any running watcher,
+    /// so drop it before waiting on watcher activity.  Check
+    /// [`Marlin::schema_info`] first if you rely on specific tables or
+    /// columns.
+    pub fn conn(&self) -> db::ConnGuard<'_> {
+        db::Database::lock(&self.db)
+    }
+
+    /// Schema versions, applied migrations and the tables/columns of the
+    /// open database.
+    pub fn schema_info(&self) -> Result<db::SchemaInfo> {
+        db::schema_info(&self.conn())
     }
 
     /// Spawn a file-watcher that indexes changes in real time.
+    ///
+    /// The watcher shares this handle's connection instead of opening its
+    /// own, so it works for in-memory databases too and may outlive the
+    /// borrow of `self`.  With the `backup` feature, a deletion burst (see
+    /// [`watcher::BurstConfig`]) snapshots the database unless the config
+    /// brings its own [`watcher::WatcherConfig::on_burst`].
+    #[cfg(feature = "watcher")]
     pub fn watch<P: AsRef<Path>>(
-        &mut self,
+        &self,
         path: P,
         config: Option<watcher::WatcherConfig>,
     ) -> Result<watcher::FileWatcher> {
         let cfg = config.unwrap_or_default();
+        #[cfg(feature = "backup")]
+        let cfg = self.backup_on_burst(cfg)?;
         let p = path.as_ref().to_path_buf();
-        let new_conn = db::open(&self.cfg.db_path).context("opening database for watcher")?;
-        let watcher_db = Arc::new(Mutex::new(db::Database::new(new_conn)));
 
         let mut owned_w = watcher::FileWatcher::new(vec![p], cfg)?;
-        owned_w.with_database(watcher_db)?; // Modifies owned_w in place
-        owned_w.start()?; // Start the watcher after it has been fully configured
+        owned_w.with_database(Arc::clone(&self.db))?;
+        owned_w.start()?;
+
+        Ok(owned_w)
+    }
 
-        Ok(owned_w) // Return the owned FileWatcher
+    /// `cfg` with a burst hook that snapshots the database, unless it has
+    /// one or the guard is off.
+    #[cfg(all(feature = "watcher", feature = "backup"))]
+    fn backup_on_burst(&self, mut cfg: watcher::WatcherConfig) -> Result<watcher::WatcherConfig> {
+        if cfg.burst.is_some() && cfg.on_burst.is_none() {
+            let manager = self.backup_manager()?;
+            cfg.on_burst = Some(watcher::BurstHook::new(move |_| {
+                match manager.create_backup() {
+                    Ok(info) => tracing::warn!(backup = %info.id, "safety backup created"),
+                    Err(e) => tracing::error!(error = %format!("{e:#}"), "safety backup failed"),
+                }
+            }));
+        }
+        Ok(cfg)
+    }
+}
+
+/// Several labelled databases searched as one, from [`Marlin::open_many`]
+/// – for users who keep, say, work and personal indexes apart.
+pub struct MarlinGroup {
+    members: Vec<(String, Marlin)>,
+}
+
+impl MarlinGroup {
+    /// `(label, handle)` of every database, in the order they were opened.
+    pub fn members(&self) -> &[(String, Marlin)] {
+        &self.members
+    }
+
+    /// Fan `raw` out to every database and merge the hits by rank, each
+    /// tagged with its database's label ([`query::search_across`]).
+    pub fn search_hits(
+        &self,
+        raw: &str,
+        opts: &query::SearchOptions,
+    ) -> Result<Vec<query::SourcedHit>> {
+        let guards: Vec<_> = self.members.iter().map(|(_, m)| m.conn()).collect();
+        let sources: Vec<(&str, &rusqlite::Connection)> = self
+            .members
+            .iter()
+            .zip(&guards)
+            .map(|((label, _), g)| (label.as_str(), &**g))
+            .collect();
+        query::search_across(&sources, raw, opts)
+    }
+}
+
+/// [`Marlin::tag`] on SQLite: matching files are read from `conn` `chunk`
+/// rows at a time, one savepoint per chunk, and tagged through `store`
+/// (usually `conn` itself).
+pub(crate) fn tag_in_chunks<S: Store + ?Sized>(
+    store: &S,
+    conn: &rusqlite::Connection,
+    pattern: &str,
+    tag_path: &str,
+    chunk: usize,
+) -> Result<usize> {
+    // 1) ensure tag hierarchy
+    let leaf = if store.strict_tags()? {
+        store
+            .find_tag_path(tag_path)?
+            .ok_or_else(|| anyhow::anyhow!("unknown tag `{tag_path}` (tags.strict is on)"))?
+    } else {
+        store.ensure_tag_path(tag_path)?
+    };
+
+    // 2) collect leaf + ancestors
+    let tag_ids = store.tag_ancestry(leaf)?;
+
+    // 3) match files by glob against stored paths, one id range at a time
+    let expanded = shellexpand::tilde(pattern).into_owned();
+    let pat = globs::FileGlob::new(&expanded)?;
+    let by_path = expanded.contains(std::path::MAIN_SEPARATOR);
+    let is_match = |path: &str| {
+        if by_path {
+            pat.matches(path)
+        } else {
+            Path::new(path)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| pat.matches(n))
+        }
+    };
+
+    // 4) a locked file stops the run before the first chunk is written
+    if !lock::overridden(conn)? {
+        if let Some((path, _)) = lock::locked_files(conn)?
+            .into_iter()
+            .find(|(p, _)| is_match(p))
+        {
+            anyhow::bail!("{path} is locked; unlock it or pass --ignore-locks");
+        }
+    }
+
+    let m = db::Mutation::new("tag_files");
+    let now = db::now_millis();
+    let mut changed = 0;
+    let mut tag_page = |after: i64| -> Result<Option<i64>> {
+        let mut stmt_page =
+            conn.prepare_cached("SELECT id, path FROM files WHERE id > ?1 ORDER BY id LIMIT ?2")?;
+        let mut rows = stmt_page.query(rusqlite::params![after, chunk as i64])?;
+        let mut last = None;
+        while let Some(row) = rows.next()? {
+            let fid: i64 = row.get(0)?;
+            last = Some(fid);
+            if !is_match(row.get_ref(1)?.as_str()?) {
+                continue;
+            }
+            let mut newly = false;
+            for &tid in &tag_ids {
+                if store.attach_tag(fid, tid, now)? == db::TagMerge::Changed {
+                    newly = true;
+                }
+            }
+            if newly {
+                changed += 1;
+            }
+        }
+        Ok(last)
+    };
+
+    let mut after = i64::MIN;
+    loop {
+        conn.execute_batch("SAVEPOINT tag_chunk")?;
+        let last = match tag_page(after) {
+            Ok(last) => last,
+            Err(e) => {
+                conn.execute_batch("ROLLBACK TO tag_chunk; RELEASE tag_chunk")?;
+                return Err(e);
+            }
+        };
+        conn.execute_batch("RELEASE tag_chunk")?;
+        match last {
+            Some(id) => after = id,
+            None => break,
+        }
+    }
+    Ok(m.done(changed))
+}
+
+/// Substring match on paths, then on bodies of small files.
+fn fallback_search(conn: &rusqlite::Connection, term: &str) -> Result<Vec<String>> {
+    let needle = term.to_lowercase();
+    let mut stmt = conn.prepare("SELECT path FROM files")?;
+    let rows = stmt.query_map([], |r| r.get(0))?;
+    let mut out = Vec::new();
+    for res in rows {
+        let p: String = res?;
+        if p.to_lowercase().contains(&needle) {
+            out.push(p.clone());
+            continue;
+        }
+        if let Ok(meta) = fs::metadata(&p) {
+            if meta.len() <= 65_536 {
+                if let Ok(body) = fs::read_to_string(&p) {
+                    if body.to_lowercase().contains(&needle) {
+                        out.push(p.clone());
+                    }
+                }
+            }
+        }
     }
+    Ok(out)
 }
@@ -0,0 +1,379 @@
+//! Bulk link import (`marlin link import`) and the link type registry
+//! (`marlin link type`).
+//!
+//! [`read_pairs`] reads `from,to[,type]` rows of CSV – a citation graph
+//! or the relationships exported from a DAM – and [`import_links`] adds
+//! them all in one transaction.  Paths are resolved like everywhere else
+//! (as stored, or the absolute path they name on disk, see
+//! [`describe::resolve`]); rows naming a file that is not indexed, or a
+//! locked one, are reported and skipped rather than failing the import.
+//! A row linking two files already linked with other types only is kept
+//! next to them, or settled by [`import_links_with`]'s strategy (see
+//! [`crate::conflicts`]).
+//!
+//! Link types need no registration, but a registered type can name its
+//! inverse: with `cites` / `cited-by` registered, an incoming `cites`
+//! link reads as `cited-by` ([`LinkTypes::seen_from_target`]) and
+//! [`add_with_inverse`] links both ways at once.
+
+use std::io::Read;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use tracing::{debug, info};
+
+use crate::conflicts::OnConflict;
+use crate::db::{self, Mutation};
+use crate::describe;
+use crate::lock;
+
+/// One row of an import file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkPair {
+    /// 1-based line of the row.
+    pub line: u64,
+    pub from: String,
+    pub to: String,
+    /// The row's own type; the import's default applies when `None`.
+    pub link_type: Option<String>,
+}
+
+/// A row [`import_links`] did not apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedPair {
+    pub line: u64,
+    pub reason: String,
+}
+
+/// What [`import_links`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkImport {
+    /// Links created.
+    pub added: usize,
+    /// Rows whose link was already there.
+    pub existing: usize,
+    /// Rows conflicting with the links already there that `on_conflict`
+    /// settled.
+    pub conflicts: usize,
+    /// Rows left out, in file order.
+    pub skipped: Vec<SkippedPair>,
+}
+
+/// Read `from,to[,type]` rows, one per line.  Blank lines, `#` comments
+/// and a `from,to` (or `src,dst`) header are ignored; an empty type is
+/// none.
+pub fn read_pairs(mut input: impl Read) -> Result<Vec<LinkPair>> {
+    let mut text = String::new();
+    input.read_to_string(&mut text)?;
+    let mut pairs = Vec::new();
+    let mut first = true;
+    for (n, raw) in text.lines().enumerate() {
+        let line = n as u64 + 1;
+        if raw.trim().is_empty() || raw.trim_start().starts_with('#') {
+            continue;
+        }
+        // one line at a time, so line numbers stay exact
+        let row = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(raw.as_bytes())
+            .records()
+            .next()
+            .transpose()
+            .with_context(|| format!("line {line}"))?
+            .unwrap_or_default();
+        let field = |n: usize| row.get(n).filter(|f| !f.is_empty());
+        let (Some(from), Some(to)) = (field(0), field(1)) else {
+            anyhow::bail!("line {line}: expected `from,to[,type]`");
+        };
+        if std::mem::take(&mut first) && is_header(from, to) {
+            continue;
+        }
+        pairs.push(LinkPair {
+            line,
+            from: from.to_string(),
+            to: to.to_string(),
+            link_type: field(2).map(str::to_string),
+        });
+    }
+    Ok(pairs)
+}
+
+fn is_header(from: &str, to: &str) -> bool {
+    let (from, to) = (from.to_ascii_lowercase(), to.to_ascii_lowercase());
+    matches!(
+        (from.as_str(), to.as_str()),
+        ("from", "to") | ("src", "dst") | ("source", "target")
+    )
+}
+
+/// Add every pair as a link of its own type, or else `default_type`, in
+/// one transaction.  Links that already exist are counted, not added
+/// twice.
+pub fn import_links(
+    conn: &mut Connection,
+    pairs: &[LinkPair],
+    default_type: Option<&str>,
+) -> Result<LinkImport> {
+    import_links_with(conn, pairs, default_type, OnConflict::Default)
+}
+
+/// [`import_links`], settling rows that link files already linked with
+/// other types only through `on_conflict`.
+pub fn import_links_with(
+    conn: &mut Connection,
+    pairs: &[LinkPair],
+    default_type: Option<&str>,
+    mut on_conflict: OnConflict<'_>,
+) -> Result<LinkImport> {
+    let m = Mutation::new("import_links");
+    let tx = conn.savepoint()?;
+    let mut report = LinkImport::default();
+    {
+        let mut exists = tx.prepare(
+            "SELECT 1 FROM links WHERE src_file_id = ?1 AND dst_file_id = ?2 AND type IS ?3",
+        )?;
+        let mut insert =
+            tx.prepare("INSERT INTO links(src_file_id, dst_file_id, type) VALUES (?1, ?2, ?3)")?;
+        for pair in pairs {
+            let ends = describe::resolve(&tx, &pair.from)
+                .and_then(|(src, from)| Ok((src, from, describe::resolve(&tx, &pair.to)?)))
+                .and_then(|(src, from, (dst, to))| {
+                    lock::check(&tx, src)?;
+                    lock::check(&tx, dst)?;
+                    Ok(((src, from), (dst, to)))
+                });
+            let ((src, from), (dst, to)) = match ends {
+                Ok(ends) => ends,
+                Err(e) => {
+                    debug!(line = pair.line, error = %e, "link row skipped");
+                    report.skipped.push(SkippedPair {
+                        line: pair.line,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let link_type = pair.link_type.as_deref().or(default_type);
+            if on_conflict
+                .settle_link(&tx, "link import", (src, dst), &from, &to, link_type)?
+                .is_some()
+            {
+                report.conflicts += 1;
+            } else if exists.exists(params![src, dst, link_type])? {
+                report.existing += 1;
+            } else {
+                insert
+                    .execute(params![src, dst, link_type])
+                    .with_context(|| format!("line {}", pair.line))?;
+                report.added += 1;
+            }
+        }
+    }
+    tx.commit()?;
+    m.done(report.added);
+    info!(
+        added = report.added,
+        existing = report.existing,
+        skipped = report.skipped.len(),
+        "links imported"
+    );
+    Ok(report)
+}
+
+/// A registered link type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkType {
+    pub name: String,
+    /// Name of the same relation read from the target; equal to `name`
+    /// for symmetric types.
+    pub inverse: Option<String>,
+}
+
+/// Register `name`, with `inverse` as the name of the reverse relation.
+/// Re-registering the same pair is a no-op; a name already in use by
+/// another registration is an error.
+pub fn add_type(conn: &Connection, name: &str, inverse: Option<&str>) -> Result<()> {
+    if name.trim().is_empty() || inverse.is_some_and(|i| i.trim().is_empty()) {
+        anyhow::bail!("link type names must not be empty");
+    }
+    let types = LinkTypes::load(conn)?;
+    let wanted = LinkType {
+        name: name.to_string(),
+        inverse: inverse.map(str::to_string),
+    };
+    if types.0.contains(&wanted) {
+        return Ok(());
+    }
+    for n in std::iter::once(name).chain(inverse) {
+        if let Some(t) = types.find(n) {
+            anyhow::bail!(
+                "link type `{n}` is already registered (as {}); remove it first",
+                describe_type(t)
+            );
+        }
+    }
+    let m = Mutation::new("add_link_type");
+    m.done(conn.execute(
+        "INSERT INTO link_types(name, inverse) VALUES (?1, ?2)",
+        params![name, inverse],
+    )?);
+    info!(name, inverse, "link type registered");
+    Ok(())
+}
+
+/// Forget the registration naming `name` (either side of it); links keep
+/// their types.  Returns `false` if there was none.
+pub fn remove_type(conn: &Connection, name: &str) -> Result<bool> {
+    let m = Mutation::new("remove_link_type");
+    Ok(m.done(conn.execute(
+        "DELETE FROM link_types WHERE name = ?1 OR inverse = ?1",
+        [name],
+    )?) > 0)
+}
+
+fn describe_type(t: &LinkType) -> String {
+    match &t.inverse {
+        Some(inv) => format!("`{}` / `{inv}`", t.name),
+        None => format!("`{}`", t.name),
+    }
+}
+
+/// Every registered type, by name.
+#[derive(Debug, Clone, Default)]
+pub struct LinkTypes(pub Vec<LinkType>);
+
+impl LinkTypes {
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut stmt = conn.prepare("SELECT name, inverse FROM link_types ORDER BY name")?;
+        let rows = stmt.query_map([], |r| {
+            Ok(LinkType {
+                name: r.get(0)?,
+                inverse: r.get(1)?,
+            })
+        })?;
+        Ok(Self(rows.collect::<rusqlite::Result<_>>()?))
+    }
+
+    /// The registration naming `name` on either side.
+    fn find(&self, name: &str) -> Option<&LinkType> {
+        self.0
+            .iter()
+            .find(|t| t.name == name || t.inverse.as_deref() == Some(name))
+    }
+
+    /// The name of the reverse relation of `link_type`, if registered.
+    pub fn inverse_of(&self, link_type: &str) -> Option<&str> {
+        let t = self.find(link_type)?;
+        if t.name == link_type {
+            t.inverse.as_deref()
+        } else {
+            Some(&t.name)
+        }
+    }
+
+    /// Whether `link_type` is the inverse side of a (non-symmetric)
+    /// registration, e.g. `cited-by` for `cites` / `cited-by`.
+    pub fn is_inverse(&self, link_type: &str) -> bool {
+        self.find(link_type).is_some_and(|t| t.name != link_type)
+    }
+
+    /// How a link of `link_type` reads from its target: its inverse name
+    /// where one is registered, else unchanged.
+    pub fn seen_from_target<'a>(&'a self, link_type: Option<&'a str>) -> Option<&'a str> {
+        link_type.map(|t| self.inverse_of(t).unwrap_or(t))
+    }
+}
+
+/// Link `src` to `dst` with `link_type` and `dst` back to `src` with its
+/// registered inverse, in one transaction.
+pub fn add_with_inverse(conn: &mut Connection, src: i64, dst: i64, link_type: &str) -> Result<()> {
+    let types = LinkTypes::load(conn)?;
+    let Some(inverse) = types.inverse_of(link_type) else {
+        anyhow::bail!(
+            "link type `{link_type}` has no registered inverse (see `marlin link type add`)"
+        );
+    };
+    let tx = conn.savepoint()?;
+    db::add_link(&tx, src, dst, Some(link_type))?;
+    db::add_link(&tx, dst, src, Some(inverse))?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Remove the link of `link_type` from `src` to `dst` and its inverse
+/// from `dst` back to `src`.
+pub fn remove_with_inverse(
+    conn: &mut Connection,
+    src: i64,
+    dst: i64,
+    link_type: &str,
+) -> Result<()> {
+    let types = LinkTypes::load(conn)?;
+    let Some(inverse) = types.inverse_of(link_type) else {
+        anyhow::bail!(
+            "link type `{link_type}` has no registered inverse (see `marlin link type add`)"
+        );
+    };
+    let tx = conn.savepoint()?;
+    db::remove_link(&tx, src, dst, Some(link_type))?;
+    db::remove_link(&tx, dst, src, Some(inverse))?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// An edge of the exported link graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub link_type: Option<String>,
+    /// The type is its own inverse; the edge has no direction.
+    pub symmetric: bool,
+    pub attrs: Vec<(String, String)>,
+}
+
+/// The links as graph edges, one per relation: a link of an inverse type
+/// (`cited-by`) becomes the edge of its registered type (`cites`) the
+/// other way round, and merges with that edge if it is there too, as does
+/// one of the two links of a symmetric type.  Attributes of merged links
+/// are combined, the first value of a key winning.  Edges are sorted by
+/// source, target and type.
+pub fn graph_edges(links: Vec<db::LinkInfo>, types: &LinkTypes) -> Vec<Edge> {
+    let mut edges: Vec<Edge> = Vec::new();
+    let mut seen = std::collections::HashMap::new();
+    for l in links {
+        let inverse = l.link_type.as_deref().and_then(|t| types.inverse_of(t));
+        let symmetric = inverse.is_some() && inverse == l.link_type.as_deref();
+        let (from, to, link_type) = match &l.link_type {
+            Some(t) if types.is_inverse(t) => (l.other, l.path, inverse.map(str::to_string)),
+            _ if symmetric && l.other < l.path => (l.other, l.path, l.link_type),
+            _ => (l.path, l.other, l.link_type),
+        };
+        let key = (from.clone(), to.clone(), link_type.clone());
+        match seen.get(&key) {
+            Some(&i) => {
+                let edge: &mut Edge = &mut edges[i];
+                for (k, v) in l.attrs {
+                    if !edge.attrs.iter().any(|(have, _)| *have == k) {
+                        edge.attrs.push((k, v));
+                    }
+                }
+                edge.attrs.sort();
+            }
+            None => {
+                seen.insert(key, edges.len());
+                edges.push(Edge {
+                    from,
+                    to,
+                    link_type,
+                    symmetric,
+                    attrs: l.attrs,
+                });
+            }
+        }
+    }
+    edges.sort_by(|a, b| (&a.from, &a.to, &a.link_type).cmp(&(&b.from, &b.to, &b.link_type)));
+    edges
+}
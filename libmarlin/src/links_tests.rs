@@ -0,0 +1,189 @@
+// libmarlin/src/links_tests.rs
+
+use super::db;
+use super::links::{
+    add_type, add_with_inverse, graph_edges, import_links, import_links_with, read_pairs,
+    remove_type, LinkPair, LinkTypes,
+};
+use super::test_utils::db_with_files;
+
+#[test]
+fn pairs_skip_header_comments_and_take_their_own_type() {
+    let csv = "from,to\n\
+               # a comment\n\
+               /a.md, /b.md\n\
+               \n\
+               \"/c, d.md\",/a.md,cites\n\
+               /b.md,/c.md,\n";
+    let pairs = read_pairs(csv.as_bytes()).unwrap();
+    assert_eq!(
+        pairs,
+        [
+            LinkPair {
+                line: 3,
+                from: "/a.md".into(),
+                to: "/b.md".into(),
+                link_type: None,
+            },
+            LinkPair {
+                line: 5,
+                from: "/c, d.md".into(),
+                to: "/a.md".into(),
+                link_type: Some("cites".into()),
+            },
+            LinkPair {
+                line: 6,
+                from: "/b.md".into(),
+                to: "/c.md".into(),
+                link_type: None,
+            },
+        ]
+    );
+
+    let err = read_pairs("/a.md\n".as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("line 1"), "{err}");
+}
+
+#[test]
+fn import_adds_resolvable_rows_once_and_reports_the_rest() {
+    let mut conn = db_with_files(&["/a.md", "/b.md", "/c.md"]);
+    let csv = "/a.md,/b.md\n/a.md,/c.md,see-also\n/a.md,/nope.md\n";
+    let pairs = read_pairs(csv.as_bytes()).unwrap();
+
+    let report = import_links(&mut conn, &pairs, Some("cites")).unwrap();
+    assert_eq!((report.added, report.existing), (2, 0));
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].line, 3);
+    assert!(report.skipped[0].reason.contains("/nope.md"));
+
+    let types: Vec<(String, Option<String>)> = db::list_links(&conn, "/a.md", None, None)
+        .unwrap()
+        .into_iter()
+        .map(|(_, dst, t)| (dst, t))
+        .collect();
+    assert_eq!(
+        types,
+        [
+            ("/b.md".to_string(), Some("cites".to_string())),
+            ("/c.md".to_string(), Some("see-also".to_string())),
+        ]
+    );
+
+    // a second run finds everything in place, untyped rows included
+    let again = import_links(&mut conn, &pairs, Some("cites")).unwrap();
+    assert_eq!((again.added, again.existing), (0, 2));
+    let untyped = import_links(&mut conn, &pairs[..1], None).unwrap();
+    assert_eq!(untyped.added, 1);
+    let untyped = import_links(&mut conn, &pairs[..1], None).unwrap();
+    assert_eq!((untyped.added, untyped.existing), (0, 1));
+}
+
+#[test]
+fn import_strategies_settle_links_of_another_type() {
+    use super::conflicts::{decisions, OnConflict, Resolution, Strategy};
+
+    let mut conn = db_with_files(&["/a.md", "/b.md"]);
+    let pairs = read_pairs("/a.md,/b.md\n".as_bytes()).unwrap();
+    import_links(&mut conn, &pairs, Some("cites")).unwrap();
+    let types = |conn: &rusqlite::Connection| -> Vec<Option<String>> {
+        db::list_links(conn, "/a.md", None, None)
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, t)| t)
+            .collect()
+    };
+
+    let mine = OnConflict::Strategy(Strategy::Mine);
+    let report = import_links_with(&mut conn, &pairs, Some("see-also"), mine).unwrap();
+    assert_eq!((report.added, report.conflicts), (0, 1));
+    assert_eq!(types(&conn), [Some("cites".to_string())]);
+
+    let theirs = OnConflict::Strategy(Strategy::Theirs);
+    import_links_with(&mut conn, &pairs, Some("see-also"), theirs).unwrap();
+    assert_eq!(types(&conn), [Some("see-also".to_string())]);
+
+    let both = OnConflict::Strategy(Strategy::Both);
+    import_links_with(&mut conn, &pairs, Some("cites"), both).unwrap();
+    assert_eq!(
+        types(&conn),
+        [Some("cites".to_string()), Some("see-also".to_string())]
+    );
+
+    let last = &decisions(&conn, 1).unwrap()[0];
+    assert_eq!(last.source, "link import");
+    assert_eq!(last.conflict.mine, "see-also");
+    assert_eq!(last.resolution, Resolution::Both);
+}
+
+#[test]
+fn registered_types_name_their_inverse() {
+    let mut conn = db_with_files(&["/a.md", "/b.md"]);
+    add_type(&conn, "cites", Some("cited-by")).unwrap();
+    add_type(&conn, "cites", Some("cited-by")).unwrap();
+    add_type(&conn, "related", Some("related")).unwrap();
+    add_type(&conn, "depends-on", None).unwrap();
+    let err = add_type(&conn, "cited-by", Some("cites")).unwrap_err();
+    assert!(err.to_string().contains("already registered"));
+
+    let types = LinkTypes::load(&conn).unwrap();
+    assert_eq!(types.inverse_of("cites"), Some("cited-by"));
+    assert_eq!(types.inverse_of("cited-by"), Some("cites"));
+    assert_eq!(types.inverse_of("related"), Some("related"));
+    assert_eq!(types.inverse_of("other"), None);
+    assert!(types.is_inverse("cited-by"));
+    assert!(!types.is_inverse("cites") && !types.is_inverse("related"));
+    assert_eq!(types.seen_from_target(Some("cites")), Some("cited-by"));
+    assert_eq!(types.seen_from_target(Some("other")), Some("other"));
+    assert_eq!(types.seen_from_target(None), None);
+
+    let (a, b) = (
+        db::file_id(&conn, "/a.md").unwrap(),
+        db::file_id(&conn, "/b.md").unwrap(),
+    );
+    add_with_inverse(&mut conn, a, b, "cites").unwrap();
+    assert_eq!(
+        db::list_links(&conn, "/b.md", None, None).unwrap(),
+        [("/b.md".into(), "/a.md".into(), Some("cited-by".into()))]
+    );
+    assert!(add_with_inverse(&mut conn, a, b, "other").is_err());
+
+    assert!(remove_type(&conn, "cited-by").unwrap());
+    assert!(!remove_type(&conn, "cites").unwrap());
+}
+
+#[test]
+fn graph_edges_merge_inverse_and_symmetric_links() {
+    let mut conn = db_with_files(&["/a.md", "/b.md", "/c.md"]);
+    add_type(&conn, "cites", Some("cited-by")).unwrap();
+    add_type(&conn, "related", Some("related")).unwrap();
+    let id = |p: &str| db::file_id(&conn, p).unwrap();
+    let (a, b, c) = (id("/a.md"), id("/b.md"), id("/c.md"));
+    add_with_inverse(&mut conn, a, b, "cites").unwrap();
+    db::add_link(&conn, c, a, Some("cited-by")).unwrap();
+    add_with_inverse(&mut conn, b, c, "related").unwrap();
+    let inverse = db::find_link(&conn, b, a, Some("cited-by")).unwrap();
+    db::set_link_attr(&conn, inverse, "note", "from b").unwrap();
+
+    let types = LinkTypes::load(&conn).unwrap();
+    let edges = graph_edges(db::link_details(&conn, "*", None, None).unwrap(), &types);
+    let summary: Vec<_> = edges
+        .iter()
+        .map(|e| {
+            (
+                e.from.as_str(),
+                e.to.as_str(),
+                e.link_type.as_deref(),
+                e.symmetric,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("/a.md", "/b.md", Some("cites"), false),
+            ("/a.md", "/c.md", Some("cites"), false),
+            ("/b.md", "/c.md", Some("related"), true),
+        ]
+    );
+    assert_eq!(edges[0].attrs, [("note".to_string(), "from b".to_string())]);
+}
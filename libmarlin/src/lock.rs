@@ -0,0 +1,118 @@
+//! Locked files: metadata frozen against changes (`marlin lock`).
+//!
+//! A curated archive should not lose its tags to a careless `marlin tag`
+//! or `attr set` run over `*`.  Locking a file makes the metadata helpers
+//! in [`crate::db`] refuse to change its tags, attributes, links, rating,
+//! favorite flag and colour labels; bulk helpers check every file first,
+//! so one locked file stops the whole operation before anything is
+//! written.  Scans still keep the file's own facts (size, mtime, hash,
+//! language) current.
+//!
+//! [`set_override`] lifts the check for one connection, which is how the
+//! CLI's `--ignore-locks` works.
+
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+
+use crate::db::Mutation;
+
+/// Temp table whose existence marks a connection as overriding locks.
+const OVERRIDE_TABLE: &str = "lock_override";
+
+/// Lock `files` (`(id, path)` pairs as returned by
+/// [`crate::db::match_files`]).  Returns how many were not locked yet.
+pub fn lock_files(conn: &Connection, files: &[(i64, String)]) -> Result<usize> {
+    let m = Mutation::new("lock_files");
+    let now = chrono::Utc::now().timestamp();
+    let mut stmt = conn
+        .prepare_cached("INSERT OR IGNORE INTO locked_files(file_id, locked_at) VALUES (?1, ?2)")?;
+    let mut n = 0;
+    for (id, _) in files {
+        n += stmt.execute(params![id, now])?;
+    }
+    Ok(m.done(n))
+}
+
+/// Unlock `files`.  Returns how many were locked.
+pub fn unlock_files(conn: &Connection, files: &[(i64, String)]) -> Result<usize> {
+    let m = Mutation::new("unlock_files");
+    let mut stmt = conn.prepare_cached("DELETE FROM locked_files WHERE file_id = ?1")?;
+    let mut n = 0;
+    for (id, _) in files {
+        n += stmt.execute([id])?;
+    }
+    Ok(m.done(n))
+}
+
+pub fn is_locked(conn: &Connection, file_id: i64) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM locked_files WHERE file_id = ?1)",
+        [file_id],
+        |r| r.get(0),
+    )?)
+}
+
+/// `(path, locked_at)` of every locked file, by path.
+pub fn locked_files(conn: &Connection) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.path, l.locked_at
+           FROM locked_files l JOIN files f ON f.id = l.file_id
+          ORDER BY f.path",
+    )?;
+    let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+    Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+/// Let (or stop letting) `conn` change the metadata of locked files.
+pub fn set_override(conn: &Connection, on: bool) -> Result<()> {
+    if on {
+        conn.execute_batch(&format!(
+            "CREATE TEMP TABLE IF NOT EXISTS {OVERRIDE_TABLE}(x)"
+        ))?;
+    } else {
+        conn.execute_batch(&format!("DROP TABLE IF EXISTS temp.{OVERRIDE_TABLE}"))?;
+    }
+    Ok(())
+}
+
+/// Whether [`set_override`] is on for `conn`.
+pub fn overridden(conn: &Connection) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM temp.sqlite_master WHERE type = 'table' AND name = ?1)",
+        [OVERRIDE_TABLE],
+        |r| r.get(0),
+    )?)
+}
+
+/// Fail if file `file_id` is locked and `conn` does not override locks.
+pub fn check(conn: &Connection, file_id: i64) -> Result<()> {
+    if is_locked(conn, file_id)? && !overridden(conn)? {
+        let path = crate::db::file_path(conn, file_id)?;
+        bail!("{path} is locked; unlock it or pass --ignore-locks");
+    }
+    Ok(())
+}
+
+/// [`check`] for a whole selection: fails, naming the first locked file
+/// and how many there are, before the caller changes anything.
+pub fn check_files(conn: &Connection, files: &[(i64, String)]) -> Result<()> {
+    let locked: Vec<&str> = files
+        .iter()
+        .filter_map(|(id, path)| match is_locked(conn, *id) {
+            Ok(true) => Some(Ok(path.as_str())),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<Result<_>>()?;
+    if locked.is_empty() || overridden(conn)? {
+        return Ok(());
+    }
+    match locked.len() {
+        1 => bail!("{} is locked; unlock it or pass --ignore-locks", locked[0]),
+        n => bail!(
+            "{} and {} other selected file(s) are locked; unlock them or pass --ignore-locks",
+            locked[0],
+            n - 1
+        ),
+    }
+}
@@ -0,0 +1,60 @@
+// libmarlin/src/lock_tests.rs
+
+use super::test_utils::db_with_files;
+use super::{db, lock, Marlin};
+
+#[test]
+fn locked_files_refuse_metadata_changes() {
+    let conn = db_with_files(&["/archive/a.txt", "/inbox/b.txt"]);
+    let a = db::file_id(&conn, "/archive/a.txt").unwrap();
+    let b = db::file_id(&conn, "/inbox/b.txt").unwrap();
+    db::upsert_attr(&conn, a, "owner", "ann").unwrap();
+
+    let archive = db::match_files(&conn, "/archive/*").unwrap();
+    assert_eq!(lock::lock_files(&conn, &archive).unwrap(), 1);
+    assert_eq!(lock::lock_files(&conn, &archive).unwrap(), 0);
+    assert!(lock::is_locked(&conn, a).unwrap());
+
+    let err = db::upsert_attr(&conn, a, "owner", "bob").unwrap_err();
+    assert!(
+        err.to_string().contains("/archive/a.txt is locked"),
+        "{err}"
+    );
+    assert!(db::set_rating(&conn, a, 3).is_err());
+    assert!(db::add_label(&conn, a, "red").is_err());
+    assert!(db::add_link(&conn, b, a, None).is_err());
+
+    // one locked file stops a bulk operation before anything is written
+    let all = db::match_files(&conn, "*.txt").unwrap();
+    assert!(db::tag_files(&conn, &all, "project", true).is_err());
+    assert!(db::attr_set_files(&conn, &all, "owner", "bob", None).is_err());
+    assert!(db::file_attrs(&conn, b).unwrap().is_empty());
+    assert_eq!(db::file_attrs(&conn, a).unwrap()[0].1, "ann");
+
+    lock::set_override(&conn, true).unwrap();
+    db::upsert_attr(&conn, a, "owner", "bob").unwrap();
+    lock::set_override(&conn, false).unwrap();
+    assert!(db::upsert_attr(&conn, a, "owner", "cy").is_err());
+
+    assert_eq!(lock::unlock_files(&conn, &archive).unwrap(), 1);
+    assert_eq!(db::tag_files(&conn, &all, "project", true).unwrap(), 2);
+}
+
+#[test]
+fn facade_tagging_stops_at_locked_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    for name in ["a.md", "b.md"] {
+        std::fs::write(tmp.path().join(name), "x").unwrap();
+    }
+    let mut marlin = Marlin::open_at(tmp.path().join("index.db")).unwrap();
+    marlin.scan(&[tmp.path()]).unwrap();
+
+    assert_eq!(marlin.lock("a.md").unwrap(), 1);
+    assert_eq!(lock::locked_files(&marlin.conn()).unwrap().len(), 1);
+    assert!(marlin.tag("*.md", "notes").is_err());
+    assert!(marlin.attr_set("*.md", "k", "v").is_err());
+    assert_eq!(marlin.tag("b.md", "notes").unwrap(), 1);
+
+    assert_eq!(marlin.unlock("*.md").unwrap(), 1);
+    assert_eq!(marlin.tag("*.md", "notes").unwrap(), 1);
+}
@@ -1,5 +1,22 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+#[cfg(feature = "watcher")]
+use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{fmt, EnvFilter};
 
+/// File name of the watcher daemon's log inside [`log_dir`].
+pub const DAEMON_LOG: &str = "daemon.log";
+
+/// Rotate the daemon log once it grows past this many bytes.
+pub const DAEMON_LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated files (`daemon.log.1` …) kept next to the live one.
+pub const DAEMON_LOG_KEEP: usize = 3;
+
 /// Initialise global tracing subscriber.
 ///
 /// Reads `RUST_LOG` for filtering, falls back to `info`.
@@ -15,3 +32,113 @@ pub fn init() {
         .with_writer(std::io::stderr) // <-- NEW: send to stderr
         .init();
 }
+
+/// Initialise tracing for the detached watcher daemon: everything goes to
+/// `<dir>/daemon.log`, rotated by size.
+///
+/// Keep the returned guard alive for the life of the process; dropping it
+/// flushes buffered lines.
+#[cfg(feature = "watcher")]
+pub fn init_daemon(dir: &Path) -> Result<WorkerGuard> {
+    let file = RotatingFile::open(dir.join(DAEMON_LOG), DAEMON_LOG_MAX_BYTES, DAEMON_LOG_KEEP)?;
+    let (writer, guard) = tracing_appender::non_blocking(file);
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    fmt()
+        .with_target(false)
+        .with_level(true)
+        .with_ansi(false)
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .init();
+    Ok(guard)
+}
+
+/// Directory daemon logs are written to.
+///
+/// `MARLIN_LOG_DIR` overrides; otherwise the XDG state dir
+/// (`~/.local/state/marlin/logs`), falling back to the data dir on
+/// platforms without one.
+pub fn log_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("MARLIN_LOG_DIR") {
+        return PathBuf::from(dir);
+    }
+    match ProjectDirs::from("io", "Marlin", "marlin") {
+        Some(dirs) => dirs.state_dir().unwrap_or(dirs.data_dir()).join("logs"),
+        None => PathBuf::from("logs"),
+    }
+}
+
+/// Append-only log file that rotates itself once it reaches `max_bytes`:
+/// `name` → `name.1` → … → `name.<keep>`, dropping the oldest.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    pub fn open<P: Into<PathBuf>>(path: P, max_bytes: u64, keep: usize) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating log dir {}", parent.display()))?;
+        }
+        let file = Self::append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn append(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut p = self.path.as_os_str().to_owned();
+        p.push(format!(".{n}"));
+        PathBuf::from(p)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated(self.keep));
+            for n in (1..self.keep).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+            self.file = Self::append(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -11,3 +11,28 @@ fn init_sets_up_subscriber() {
     tracing::event!(Level::INFO, "this is a test log");
     // if we made it here without panic, we’re good
 }
+
+#[test]
+fn rotating_file_rolls_over_by_size() {
+    use std::io::Write;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let log = tmp.path().join("logs").join("daemon.log");
+    let mut f = logging::RotatingFile::open(&log, 64, 2).unwrap();
+
+    for i in 0..10 {
+        writeln!(f, "line {i:02} padding padding padding").unwrap();
+    }
+    f.flush().unwrap();
+
+    let rotated = |n: usize| tmp.path().join("logs").join(format!("daemon.log.{n}"));
+    assert!(log.exists());
+    assert!(rotated(1).exists());
+    assert!(rotated(2).exists());
+    assert!(!rotated(3).exists(), "only `keep` rotated files survive");
+    for p in [log.clone(), rotated(1), rotated(2)] {
+        assert!(std::fs::metadata(&p).unwrap().len() <= 64);
+    }
+    let live = std::fs::read_to_string(&log).unwrap();
+    assert!(live.contains("line 09"));
+}
@@ -0,0 +1,190 @@
+//! Delivering due reminders: desktop notifications and a hook command.
+//!
+//! Two channels, both off until configured in the index:
+//!
+//! * `notify.desktop true` pops up a desktop notification (`notify-send`
+//!   on Linux and the BSDs, `osascript` on macOS).
+//! * `notify.command <command line>` runs a program per reminder with
+//!   `MARLIN_TITLE`, `MARLIN_BODY`, `MARLIN_PATH` and `MARLIN_DUE` (Unix
+//!   seconds) in its environment – for mail, chat webhooks, a phone push.
+//!
+//! The watcher calls [`fire_due`] periodically; `marlin remind test` sends
+//! one notification through the same channels.
+
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::db::{self, Reminder};
+
+/// Setting turning desktop notifications on.
+pub const DESKTOP_KEY: &str = "notify.desktop";
+/// Setting holding the hook command.
+pub const COMMAND_KEY: &str = "notify.command";
+
+/// What one notification says.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    /// The file it is about, if any.
+    pub path: Option<String>,
+    /// Unix seconds
+    pub due_at: Option<i64>,
+}
+
+impl Notification {
+    pub fn for_reminder(r: &Reminder) -> Self {
+        let name = std::path::Path::new(&r.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| r.path.clone());
+        Self {
+            title: format!("Reminder: {name}"),
+            body: r.note.clone(),
+            path: Some(r.path.clone()),
+            due_at: Some(r.due_at),
+        }
+    }
+}
+
+/// The configured channels.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Notifier {
+    pub desktop: bool,
+    /// Program and arguments of the hook command.
+    pub command: Option<Vec<String>>,
+}
+
+impl Notifier {
+    /// The channels configured in the index.
+    pub fn from_settings(conn: &Connection) -> Result<Self> {
+        let desktop = db::setting(conn, DESKTOP_KEY)?.is_some_and(|v| v == "true");
+        let command = db::setting(conn, COMMAND_KEY)?
+            .map(|c| parse_command(&c))
+            .transpose()?;
+        Ok(Self { desktop, command })
+    }
+
+    /// Whether any channel is on.
+    pub fn is_enabled(&self) -> bool {
+        self.desktop || self.command.is_some()
+    }
+
+    /// Names of the channels that are on, for messages.
+    pub fn channels(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.desktop {
+            names.push("desktop");
+        }
+        if self.command.is_some() {
+            names.push("command");
+        }
+        names
+    }
+
+    /// Deliver `n` through every channel.  All of them are tried; the
+    /// error names each one that failed.
+    pub fn send(&self, n: &Notification) -> Result<()> {
+        let mut failed = Vec::new();
+        if self.desktop {
+            if let Err(e) = desktop(n) {
+                failed.push(format!("desktop: {e:#}"));
+            }
+        }
+        if let Some(argv) = &self.command {
+            if let Err(e) = run_command(argv, n) {
+                failed.push(format!("command: {e:#}"));
+            }
+        }
+        if !failed.is_empty() {
+            bail!("{}", failed.join("; "));
+        }
+        Ok(())
+    }
+}
+
+/// Split a `notify.command` value into program and arguments.
+pub fn parse_command(command: &str) -> Result<Vec<String>> {
+    shlex::split(command)
+        .filter(|a| !a.is_empty())
+        .with_context(|| format!("cannot parse notify command `{command}`"))
+}
+
+fn run_command(argv: &[String], n: &Notification) -> Result<()> {
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..])
+        .env("MARLIN_TITLE", &n.title)
+        .env("MARLIN_BODY", &n.body);
+    if let Some(path) = &n.path {
+        cmd.env("MARLIN_PATH", path);
+    }
+    if let Some(due) = n.due_at {
+        cmd.env("MARLIN_DUE", due.to_string());
+    }
+    let status = cmd
+        .status()
+        .with_context(|| format!("starting `{}`", argv[0]))?;
+    if !status.success() {
+        bail!("`{}` failed ({status})", argv[0]);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn desktop(n: &Notification) -> Result<()> {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let script = format!(
+        "display notification {} with title {}",
+        quote(&n.body),
+        quote(&n.title)
+    );
+    let status = Command::new("osascript")
+        .args(["-e", &script])
+        .status()
+        .context("starting osascript")?;
+    if !status.success() {
+        bail!("osascript failed ({status})");
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop(n: &Notification) -> Result<()> {
+    let status = Command::new("notify-send")
+        .args(["--app-name=Marlin", &n.title, &n.body])
+        .status()
+        .context("starting notify-send (is libnotify installed?)")?;
+    if !status.success() {
+        bail!("notify-send failed ({status})");
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn desktop(_n: &Notification) -> Result<()> {
+    bail!("desktop notifications are not supported on this platform; use notify.command")
+}
+
+/// Notify about every reminder due at `now` that was not notified yet,
+/// and mark the delivered ones.  A reminder whose delivery failed stays
+/// pending and is retried on the next call.  Returns how many went out.
+pub fn fire_due(conn: &Connection, notifier: &Notifier, now: i64) -> Result<usize> {
+    if !notifier.is_enabled() {
+        return Ok(0);
+    }
+    let mut sent = 0;
+    for r in db::pending_reminders(conn, now)? {
+        match notifier.send(&Notification::for_reminder(&r)) {
+            Ok(()) => {
+                db::mark_reminded(conn, r.id, now)?;
+                info!("reminder #{} for {} delivered", r.id, r.path);
+                sent += 1;
+            }
+            Err(e) => warn!("reminder #{} for {}: {e:#}", r.id, r.path),
+        }
+    }
+    Ok(sent)
+}
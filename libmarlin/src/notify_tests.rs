@@ -0,0 +1,89 @@
+// libmarlin/src/notify_tests.rs
+
+use super::db;
+use super::notify::{self, Notifier, COMMAND_KEY, DESKTOP_KEY};
+use rusqlite::Connection;
+
+fn with_reminders(due: &[(&str, i64)]) -> Connection {
+    let conn = db::open(":memory:").unwrap();
+    for (path, due_at) in due {
+        conn.execute(
+            "INSERT OR IGNORE INTO files(path, size, mtime) VALUES (?1, 0, 0)",
+            [path],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO reminders(file_id, due_at, note, created_at)
+             VALUES ((SELECT id FROM files WHERE path = ?1), ?2, 'look at ' || ?1, 0)",
+            rusqlite::params![path, due_at],
+        )
+        .unwrap();
+    }
+    conn
+}
+
+#[test]
+fn channels_come_from_settings() {
+    let conn = db::open(":memory:").unwrap();
+    let off = Notifier::from_settings(&conn).unwrap();
+    assert!(!off.is_enabled());
+    assert!(off.channels().is_empty());
+
+    db::set_setting(&conn, DESKTOP_KEY, "true").unwrap();
+    db::set_setting(&conn, COMMAND_KEY, "mail -s 'due soon' me").unwrap();
+    let on = Notifier::from_settings(&conn).unwrap();
+    assert_eq!(on.channels(), ["desktop", "command"]);
+    assert_eq!(
+        on.command.unwrap(),
+        ["mail", "-s", "due soon", "me"].map(String::from)
+    );
+}
+
+#[test]
+fn nothing_fires_without_a_channel() {
+    let conn = with_reminders(&[("/a.txt", 10)]);
+    let sent = notify::fire_due(&conn, &Notifier::default(), 100).unwrap();
+    assert_eq!(sent, 0);
+    assert_eq!(db::pending_reminders(&conn, 100).unwrap().len(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn due_reminders_run_the_hook_once() {
+    let tmp = tempfile::tempdir().unwrap();
+    let log = tmp.path().join("hook.log");
+    let conn = with_reminders(&[("/a.txt", 10), ("/b.txt", 20), ("/later.txt", 500)]);
+    let notifier = Notifier {
+        desktop: false,
+        command: Some(
+            notify::parse_command(&format!(
+                r#"sh -c 'echo "$MARLIN_DUE $MARLIN_PATH $MARLIN_BODY" >> {}'"#,
+                log.display()
+            ))
+            .unwrap(),
+        ),
+    };
+
+    assert_eq!(notify::fire_due(&conn, &notifier, 100).unwrap(), 2);
+    assert_eq!(notify::fire_due(&conn, &notifier, 100).unwrap(), 0);
+    let lines = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(
+        lines,
+        "10 /a.txt look at /a.txt\n20 /b.txt look at /b.txt\n"
+    );
+    let later = db::pending_reminders(&conn, 1000).unwrap();
+    assert_eq!(later.len(), 1);
+    assert_eq!(later[0].path, "/later.txt");
+}
+
+#[cfg(unix)]
+#[test]
+fn failed_deliveries_stay_pending() {
+    let conn = with_reminders(&[("/a.txt", 10)]);
+    let notifier = Notifier {
+        desktop: false,
+        command: Some(notify::parse_command("false").unwrap()),
+    };
+    assert_eq!(notify::fire_due(&conn, &notifier, 100).unwrap(), 0);
+    assert_eq!(db::pending_reminders(&conn, 100).unwrap().len(), 1);
+}
@@ -0,0 +1,285 @@
+//! Turn metadata into a physical layout: place files into directories
+//! derived from their tags and attributes (`marlin organize`).
+//!
+//! A [`Template`] is literal text with `{tag:<prefix>}` and `{attr:<key>}`
+//! placeholders; a bare `tag:client` is short for `{tag:client}`.
+//! `{tag:client}` renders the part of the file's deepest `client/…` tag
+//! below `client` (`client/acme/q3` → `acme/q3`).  [`plan`] works out
+//! every destination without touching the disk; [`apply`] carries it
+//! out.  Moved files keep their index row, tags and attributes.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+use tracing::{debug, info};
+
+use crate::db::{self, Mutation};
+use crate::utils::{normalize_path, PathNorm};
+
+/// How files get to their destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Rename; the index follows the file.
+    #[default]
+    Move,
+    Copy,
+    /// Hard link (same file system only).
+    Link,
+    Symlink,
+}
+
+/// What to do when the destination already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnConflict {
+    #[default]
+    Skip,
+    /// Append ` (1)`, ` (2)` … to the file stem.
+    Rename,
+    Overwrite,
+}
+
+/// The fate of one file in a [`plan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Will be (or was) placed at `to`.
+    Place,
+    /// Already at its destination.
+    InPlace,
+    /// Destination taken and `OnConflict::Skip` in effect.
+    Conflict,
+    /// A placeholder had no value for this file.
+    NoValue,
+}
+
+/// One file's destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub file_id: i64,
+    pub from: PathBuf,
+    pub to: Option<PathBuf>,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Lit(String),
+    Tag(String),
+    Attr(String),
+}
+
+/// A parsed directory template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Part>);
+
+impl Template {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            bail!("empty organize template");
+        }
+        if !spec.contains('{') {
+            return Ok(Template(vec![placeholder(spec)?]));
+        }
+        let mut parts = Vec::new();
+        let mut rest = spec;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(Part::Lit(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .with_context(|| format!("unclosed `{{` in template `{spec}`"))?;
+            parts.push(placeholder(&rest[open + 1..open + close])?);
+            rest = &rest[open + close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Lit(rest.to_string()));
+        }
+        Ok(Template(parts))
+    }
+
+    /// Relative directory for `file_id`, or `None` if a placeholder has
+    /// no value.
+    pub fn render(&self, conn: &Connection, file_id: i64) -> Result<Option<PathBuf>> {
+        let mut out = String::new();
+        for part in &self.0 {
+            let value = match part {
+                Part::Lit(s) => s.clone(),
+                Part::Tag(prefix) => match tag_below(conn, file_id, prefix)? {
+                    Some(v) => v,
+                    None => return Ok(None),
+                },
+                // a value is one path component, whatever it contains
+                Part::Attr(key) => match attr_value(conn, file_id, key)? {
+                    Some(v) => v.replace(['/', '\\'], "_"),
+                    None => return Ok(None),
+                },
+            };
+            out.push_str(&value);
+        }
+        let mut dir = PathBuf::new();
+        for comp in out.split('/').map(str::trim) {
+            match comp {
+                "" | "." => {}
+                ".." => bail!("template for file {file_id} renders `..`"),
+                c => dir.push(c),
+            }
+        }
+        Ok((!dir.as_os_str().is_empty()).then_some(dir))
+    }
+}
+
+fn placeholder(spec: &str) -> Result<Part> {
+    match spec.split_once(':') {
+        Some(("tag", p)) if !p.is_empty() => Ok(Part::Tag(p.trim_matches('/').to_string())),
+        Some(("attr", k)) if !k.is_empty() => Ok(Part::Attr(k.to_string())),
+        _ => bail!("unknown placeholder `{spec}` (expected tag:<prefix> or attr:<key>)"),
+    }
+}
+
+/// The deepest tag path below `prefix` on the file, without the prefix.
+fn tag_below(conn: &Connection, file_id: i64, prefix: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "WITH RECURSIVE tag_tree(id, path) AS (
+                 SELECT id, name FROM tags WHERE parent_id IS NULL
+                 UNION ALL
+                 SELECT t.id, tt.path || '/' || t.name
+                   FROM tags t JOIN tag_tree tt ON t.parent_id = tt.id
+             )
+             SELECT substr(tt.path, length(?2) + 2)
+               FROM file_tags ft JOIN tag_tree tt ON tt.id = ft.tag_id
+              WHERE ft.file_id = ?1
+                AND lower(substr(tt.path, 1, length(?2) + 1)) = lower(?2 || '/')
+              ORDER BY length(tt.path) DESC, tt.path
+              LIMIT 1",
+            rusqlite::params![file_id, prefix],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+fn attr_value(conn: &Connection, file_id: i64, key: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT value FROM attributes WHERE file_id = ?1 AND key = ?2 AND value <> ''",
+            rusqlite::params![file_id, key],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// Work out where each of `files` (`(id, path)` pairs) goes below
+/// `into`.  Nothing on disk changes.
+pub fn plan(
+    conn: &Connection,
+    files: &[(i64, String)],
+    template: &Template,
+    into: &Path,
+    on_conflict: OnConflict,
+) -> Result<Vec<Placement>> {
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+    let mut out = Vec::with_capacity(files.len());
+    for (fid, path) in files {
+        let from = PathBuf::from(path);
+        let (to, outcome) = match (template.render(conn, *fid)?, from.file_name()) {
+            (Some(dir), Some(name)) => {
+                let mut to = into.join(dir).join(name);
+                let taken = |p: &Path| claimed.contains(p) || p.symlink_metadata().is_ok();
+                let outcome = if to == from {
+                    Outcome::InPlace
+                } else if !taken(&to) || on_conflict == OnConflict::Overwrite {
+                    Outcome::Place
+                } else if on_conflict == OnConflict::Rename {
+                    to = free_name(&to, taken);
+                    Outcome::Place
+                } else {
+                    Outcome::Conflict
+                };
+                if outcome == Outcome::Place {
+                    claimed.insert(to.clone());
+                }
+                (Some(to), outcome)
+            }
+            _ => (None, Outcome::NoValue),
+        };
+        out.push(Placement {
+            file_id: *fid,
+            from,
+            to,
+            outcome,
+        });
+    }
+    Ok(out)
+}
+
+/// `name (1).ext`, `name (2).ext` … – the first one `taken` rejects not.
+fn free_name(to: &Path, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let stem = to.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = to
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| to.with_file_name(format!("{stem} ({n}){ext}")))
+        .find(|p| !taken(p))
+        .expect("unbounded range")
+}
+
+/// Carry out every [`Outcome::Place`] entry of `plan`.  Moves update the
+/// index path so metadata follows the file.  Returns the number placed.
+pub fn apply(conn: &Connection, plan: &[Placement], mode: Mode) -> Result<usize> {
+    let m = Mutation::new("organize");
+    let mut placed = 0;
+    for p in plan.iter().filter(|p| p.outcome == Outcome::Place) {
+        let Some(to) = &p.to else { continue };
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if to.symlink_metadata().is_ok() {
+            // only reachable with OnConflict::Overwrite; the replaced
+            // file's row would otherwise keep its path
+            fs::remove_file(to).with_context(|| format!("replacing {}", to.display()))?;
+            conn.execute(
+                "DELETE FROM files WHERE path = ?1",
+                [normalize_path(to, &PathNorm::default())],
+            )?;
+        }
+        let from = &p.from;
+        let res = match mode {
+            Mode::Move => move_file(from, to),
+            Mode::Copy => fs::copy(from, to).map(|_| ()),
+            Mode::Link => fs::hard_link(from, to),
+            Mode::Symlink => symlink(from, to),
+        };
+        res.with_context(|| format!("placing {} at {}", from.display(), to.display()))?;
+        if mode == Mode::Move {
+            db::update_file_path(conn, &from.to_string_lossy(), &to.to_string_lossy())?;
+        }
+        debug!(from = %from.display(), to = %to.display(), "organized");
+        placed += 1;
+    }
+    info!(placed, "organize complete");
+    Ok(m.done(placed))
+}
+
+/// `rename`, falling back to copy + delete across file systems.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::rename(from, to).or_else(|_| {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    })
+}
+
+#[cfg(unix)]
+fn symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(from, to)
+}
+
+#[cfg(windows)]
+fn symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(from, to)
+}
@@ -0,0 +1,104 @@
+// libmarlin/src/organize_tests.rs
+
+use super::db;
+use super::organize::{self, Mode, OnConflict, Outcome, Template};
+use super::scan::scan_directory;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+fn tag(conn: &rusqlite::Connection, path: &str, tag_path: &str) {
+    let fid = db::file_id(conn, path).unwrap();
+    let mut cur = Some(db::ensure_tag_path(conn, tag_path).unwrap());
+    while let Some(id) = cur {
+        conn.execute("INSERT OR IGNORE INTO file_tags VALUES (?1, ?2)", [fid, id])
+            .unwrap();
+        cur = conn
+            .query_row("SELECT parent_id FROM tags WHERE id = ?1", [id], |r| {
+                r.get(0)
+            })
+            .unwrap();
+    }
+}
+
+#[test]
+fn template_parses_shorthand_and_placeholders() {
+    assert!(Template::parse("tag:client").is_ok());
+    assert!(Template::parse("{attr:year}/{tag:client}-x").is_ok());
+    assert!(Template::parse("{attr:year").is_err());
+    assert!(Template::parse("{size}").is_err());
+    assert!(Template::parse(" ").is_err());
+}
+
+#[test]
+fn plan_and_move_by_tag_and_attr() {
+    let tmp = tempdir().unwrap();
+    let src = tmp.path().join("in");
+    fs::create_dir(&src).unwrap();
+    for f in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(src.join(f), f).unwrap();
+    }
+    let into = tmp.path().join("Sorted");
+    fs::create_dir_all(into.join("2024/beta")).unwrap();
+    fs::write(into.join("2024/beta/b.txt"), "already here").unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, &src).unwrap();
+    let p = |f: &str| src.join(f).to_string_lossy().into_owned();
+    tag(&conn, &p("a.txt"), "client/acme/q3");
+    tag(&conn, &p("b.txt"), "client/beta");
+    for f in ["a.txt", "b.txt"] {
+        let fid = db::file_id(&conn, &p(f)).unwrap();
+        db::upsert_attr(&conn, fid, "year", "2024").unwrap();
+    }
+    let files = db::match_files(&conn, &format!("{}/*", src.display())).unwrap();
+
+    let t = Template::parse("{attr:year}/{tag:client}").unwrap();
+    let plan = organize::plan(&conn, &files, &t, &into, OnConflict::Skip).unwrap();
+    let outcome = |f: &str| plan.iter().find(|x| x.from.ends_with(f)).unwrap().clone();
+    assert_eq!(outcome("a.txt").to, Some(into.join("2024/acme/q3/a.txt")));
+    assert_eq!(outcome("b.txt").outcome, Outcome::Conflict);
+    assert_eq!(outcome("c.txt").outcome, Outcome::NoValue);
+
+    let plan = organize::plan(&conn, &files, &t, &into, OnConflict::Rename).unwrap();
+    assert_eq!(
+        plan.iter().find(|x| x.from.ends_with("b.txt")).unwrap().to,
+        Some(into.join("2024/beta/b (1).txt"))
+    );
+    assert_eq!(organize::apply(&conn, &plan, Mode::Move).unwrap(), 2);
+
+    let moved: PathBuf = into.join("2024/acme/q3/a.txt");
+    assert_eq!(fs::read_to_string(&moved).unwrap(), "a.txt");
+    assert!(!src.join("a.txt").exists());
+    // the index row (and its tags) followed the file
+    let fid = db::file_id(&conn, &moved.to_string_lossy()).unwrap();
+    let tags: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM file_tags WHERE file_id = ?1",
+            [fid],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(tags, 3);
+}
+
+#[test]
+fn copy_mode_leaves_sources_alone() {
+    let tmp = tempdir().unwrap();
+    let src = tmp.path().join("in");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("a.txt"), "a").unwrap();
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, &src).unwrap();
+    let a = src.join("a.txt").to_string_lossy().into_owned();
+    tag(&conn, &a, "client/acme");
+
+    let into = tmp.path().join("out");
+    let files = vec![(db::file_id(&conn, &a).unwrap(), a.clone())];
+    let t = Template::parse("tag:client").unwrap();
+    let plan = organize::plan(&conn, &files, &t, &into, OnConflict::Skip).unwrap();
+    assert_eq!(organize::apply(&conn, &plan, Mode::Copy).unwrap(), 1);
+    assert!(into.join("acme/a.txt").exists());
+    assert!(src.join("a.txt").exists());
+    assert!(db::file_id(&conn, &a).is_ok());
+}
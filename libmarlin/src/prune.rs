@@ -0,0 +1,178 @@
+//! Dropping files that are gone from disk (`marlin prune`, `marlin scan
+//! --prune`).
+//!
+//! Deleting a file's row takes everything attached to it along: tags,
+//! attributes, links, annotations, versions (the foreign keys cascade).
+//! [`cascade`] counts what would go, so callers can show it and ask first.
+//!
+//! With `keep_metadata` nothing is deleted: the row becomes a tombstone,
+//! `files.missing_since` is set and everything attached stays.  A file
+//! that only disappeared for a while (a sync client, a branch switch, an
+//! unmounted drive) gets its metadata back when a scan finds it again at
+//! its path, or under a new one by inode (see [`crate::scan`]).  Pruning
+//! without `keep_metadata` deletes tombstones like any other missing file.
+
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use tracing::info;
+
+use crate::db::Mutation;
+use crate::lang::LANG_KEY;
+use crate::utils::{normalize_path, PathNorm};
+use crate::vfs::Vfs;
+
+/// What deleting a set of files removes with them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cascade {
+    pub files: usize,
+    /// Tag assignments (a file's ancestor tags count too).
+    pub tags: usize,
+    /// Attributes set on the files, not counting the detected language.
+    pub attributes: usize,
+    /// Links from or to the files.
+    pub links: usize,
+    pub annotations: usize,
+    /// Stored content snapshots.
+    pub versions: usize,
+}
+
+impl std::ops::AddAssign for Cascade {
+    fn add_assign(&mut self, other: Self) {
+        self.files += other.files;
+        self.tags += other.tags;
+        self.attributes += other.attributes;
+        self.links += other.links;
+        self.annotations += other.annotations;
+        self.versions += other.versions;
+    }
+}
+
+impl Cascade {
+    /// `(name, count)` of every kind of metadata.
+    pub fn counts(&self) -> [(&'static str, usize); 5] {
+        [
+            ("tags", self.tags),
+            ("attributes", self.attributes),
+            ("links", self.links),
+            ("annotations", self.annotations),
+            ("versions", self.versions),
+        ]
+    }
+
+    /// Whether deleting the files loses anything a user added.
+    pub fn has_metadata(&self) -> bool {
+        self.counts().iter().any(|&(_, n)| n > 0)
+    }
+}
+
+/// Indexed files at or below `roots` that no longer exist on disk, as
+/// `(id, path)`.  Tombstones are only included with `tombstones`.
+pub fn missing_files(
+    conn: &Connection,
+    fs: &dyn Vfs,
+    roots: &[PathBuf],
+    tombstones: bool,
+) -> Result<Vec<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path FROM files
+          WHERE (path = ?1 OR substr(path, 1, length(?2)) = ?2)
+            AND (?3 OR missing_since IS NULL)
+          ORDER BY path",
+    )?;
+    let mut out = Vec::new();
+    for root in roots {
+        let root = normalize_path(root, &PathNorm::default());
+        let prefix = format!("{}{MAIN_SEPARATOR}", root.trim_end_matches(MAIN_SEPARATOR));
+        let rows = stmt.query_map(params![root, prefix, tombstones], |r| {
+            Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (id, path) = row?;
+            if fs.metadata(Path::new(&path)).is_err() && !out.iter().any(|(i, _)| *i == id) {
+                out.push((id, path));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Count what deleting the files `ids` would remove with them.
+pub fn cascade(conn: &Connection, ids: &[i64]) -> Result<Cascade> {
+    let ids_json = format!(
+        "[{}]",
+        ids.iter().map(i64::to_string).collect::<Vec<_>>().join(",")
+    );
+    let count = |sql: &str| -> Result<usize> {
+        let n: i64 = conn.query_row(sql, [&ids_json], |r| r.get(0))?;
+        Ok(n as usize)
+    };
+    let attributes: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM attributes
+          WHERE file_id IN (SELECT value FROM json_each(?1)) AND key <> ?2",
+        params![ids_json, LANG_KEY],
+        |r| r.get(0),
+    )?;
+    Ok(Cascade {
+        files: count("SELECT COUNT(*) FROM files WHERE id IN (SELECT value FROM json_each(?1))")?,
+        tags: count(
+            "SELECT COUNT(*) FROM file_tags WHERE file_id IN (SELECT value FROM json_each(?1))",
+        )?,
+        attributes: attributes as usize,
+        links: count(
+            "SELECT COUNT(*) FROM links
+              WHERE src_file_id IN (SELECT value FROM json_each(?1))
+                 OR dst_file_id IN (SELECT value FROM json_each(?1))",
+        )?,
+        annotations: count(
+            "SELECT COUNT(*) FROM annotations WHERE file_id IN (SELECT value FROM json_each(?1))",
+        )?,
+        versions: count(
+            "SELECT COUNT(*) FROM file_versions WHERE file_id IN (SELECT value FROM json_each(?1))",
+        )?,
+    })
+}
+
+/// Delete the files `ids` and everything attached to them, or, with
+/// `keep_metadata`, mark them as tombstones.  Returns what was deleted
+/// (or kept), counted before the change.
+pub fn drop_files(conn: &mut Connection, ids: &[i64], keep_metadata: bool) -> Result<Cascade> {
+    let m = Mutation::new(if keep_metadata {
+        "tombstone_files"
+    } else {
+        "drop_files"
+    });
+    let tx = conn.savepoint()?;
+    let report = cascade(&tx, ids)?;
+    let mut rows = 0;
+    {
+        let mut stmt = if keep_metadata {
+            tx.prepare(
+                "UPDATE files SET missing_since = IFNULL(missing_since, strftime('%s','now'))
+                  WHERE id = ?1",
+            )?
+        } else {
+            tx.prepare("DELETE FROM files WHERE id = ?1")?
+        };
+        for id in ids {
+            rows += stmt.execute([id])?;
+        }
+    }
+    tx.commit()?;
+    m.done(rows);
+    if keep_metadata {
+        info!(files = report.files, "missing files kept as tombstones");
+    } else {
+        info!(
+            files = report.files,
+            tags = report.tags,
+            attributes = report.attributes,
+            links = report.links,
+            annotations = report.annotations,
+            versions = report.versions,
+            "missing files pruned"
+        );
+    }
+    Ok(report)
+}
@@ -0,0 +1,181 @@
+// libmarlin/src/prune_tests.rs
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::db;
+use super::prune::{cascade, drop_files, missing_files, Cascade};
+use super::scan::{scan_directory_opts, scan_directory_with, ScanOptions};
+use super::test_utils::db_with_files;
+use super::vfs::MemFs;
+
+fn missing_since(conn: &rusqlite::Connection, path: &str) -> Option<i64> {
+    conn.query_row(
+        "SELECT missing_since FROM files WHERE path = ?1",
+        [path],
+        |r| r.get(0),
+    )
+    .unwrap()
+}
+
+#[test]
+fn cascade_counts_what_deleting_takes_along() {
+    let conn = db_with_files(&["/n/a.md", "/n/b.md"]);
+    let a = db::file_id(&conn, "/n/a.md").unwrap();
+    let b = db::file_id(&conn, "/n/b.md").unwrap();
+    let tag = db::ensure_tag_path(&conn, "project/alpha").unwrap();
+    conn.execute(
+        "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+        [a, tag],
+    )
+    .unwrap();
+    db::upsert_attr(&conn, a, "status", "draft").unwrap();
+    db::upsert_attr(&conn, a, super::lang::LANG_KEY, "en").unwrap();
+    db::add_link(&conn, b, a, None).unwrap();
+    conn.execute(
+        "INSERT INTO annotations(file_id, note, created_at) VALUES (?1, 'keep me', 0)",
+        [a],
+    )
+    .unwrap();
+
+    let expected = Cascade {
+        files: 1,
+        tags: 1,
+        attributes: 1,
+        links: 1,
+        annotations: 1,
+        versions: 0,
+    };
+    assert_eq!(cascade(&conn, &[a]).unwrap(), expected);
+    assert!(expected.has_metadata());
+    assert!(!cascade(&conn, &[]).unwrap().has_metadata());
+    // a link between two dropped files counts once
+    assert_eq!(cascade(&conn, &[a, b]).unwrap().links, 1);
+}
+
+#[test]
+fn tombstones_keep_metadata_until_the_file_is_back() {
+    let fs = MemFs::new();
+    fs.insert("/n/a.md", "alpha", UNIX_EPOCH);
+    fs.insert("/n/b.md", "beta", UNIX_EPOCH);
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory_with(&mut conn, &fs, Path::new("/n")).unwrap();
+    let a = db::file_id(&conn, "/n/a.md").unwrap();
+    db::upsert_attr(&conn, a, "status", "draft").unwrap();
+
+    fs.remove(Path::new("/n/a.md"));
+    let roots = [PathBuf::from("/n")];
+    let missing = missing_files(&conn, &fs, &roots, false).unwrap();
+    assert_eq!(missing, [(a, "/n/a.md".to_string())]);
+
+    let kept = drop_files(&mut conn, &[a], true).unwrap();
+    assert_eq!((kept.files, kept.attributes), (1, 1));
+    assert!(missing_since(&conn, "/n/a.md").is_some());
+    assert!(missing_files(&conn, &fs, &roots, false).unwrap().is_empty());
+    assert_eq!(missing_files(&conn, &fs, &roots, true).unwrap().len(), 1);
+
+    // the file comes back: same row, metadata intact, tombstone gone
+    fs.insert("/n/a.md", "alpha", UNIX_EPOCH);
+    scan_directory_with(&mut conn, &fs, Path::new("/n")).unwrap();
+    assert_eq!(db::file_id(&conn, "/n/a.md").unwrap(), a);
+    assert_eq!(
+        db::attr_value(&conn, a, "status").unwrap().as_deref(),
+        Some("draft")
+    );
+    assert_eq!(missing_since(&conn, "/n/a.md"), None);
+
+    fs.remove(Path::new("/n/a.md"));
+    let gone = drop_files(&mut conn, &[a], false).unwrap();
+    assert_eq!(gone.attributes, 1);
+    assert!(db::find_file_id(&conn, "/n/a.md").unwrap().is_none());
+    let attrs: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM attributes WHERE file_id = ?1",
+            [a],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(attrs, 0);
+}
+
+#[test]
+fn scan_prune_drops_what_is_gone_below_the_root() {
+    let fs = MemFs::new();
+    fs.insert("/n/a.md", "alpha", UNIX_EPOCH);
+    fs.insert("/n/b.md", "beta", UNIX_EPOCH);
+    fs.insert("/other/c.md", "gamma", UNIX_EPOCH);
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory_with(&mut conn, &fs, Path::new("/n")).unwrap();
+    scan_directory_with(&mut conn, &fs, Path::new("/other")).unwrap();
+    let a = db::file_id(&conn, "/n/a.md").unwrap();
+    let b = db::file_id(&conn, "/n/b.md").unwrap();
+    db::upsert_attr(&conn, a, "status", "draft").unwrap();
+    db::add_link(&conn, b, a, None).unwrap();
+    let fts_hits = |conn: &rusqlite::Connection| -> i64 {
+        conn.query_row(
+            "SELECT COUNT(*) FROM files_fts WHERE files_fts MATCH 'attrs_text:draft'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap()
+    };
+    assert_eq!(fts_hits(&conn), 1);
+
+    fs.remove(Path::new("/n/a.md"));
+    fs.remove(Path::new("/other/c.md"));
+    let opts = ScanOptions {
+        prune: true,
+        ..ScanOptions::default()
+    };
+    let report = scan_directory_opts(&mut conn, &fs, Path::new("/n"), &opts).unwrap();
+    assert_eq!(report.indexed, 1);
+    assert_eq!(
+        (
+            report.pruned.files,
+            report.pruned.attributes,
+            report.pruned.links
+        ),
+        (1, 1, 1)
+    );
+    assert!(db::find_file_id(&conn, "/n/a.md").unwrap().is_none());
+    assert_eq!(fts_hits(&conn), 0);
+    // outside the scanned root nothing is touched
+    assert!(db::find_file_id(&conn, "/other/c.md").unwrap().is_some());
+
+    // without `prune` missing files stay indexed
+    fs.remove(Path::new("/n/b.md"));
+    let report =
+        scan_directory_opts(&mut conn, &fs, Path::new("/n"), &ScanOptions::default()).unwrap();
+    assert_eq!(report.pruned, Cascade::default());
+    assert!(db::find_file_id(&conn, "/n/b.md").unwrap().is_some());
+}
+
+#[test]
+fn tombstones_stay_out_of_search_and_stats_unless_asked_for() {
+    let mut conn = db::open(":memory:").unwrap();
+    for p in ["/n/gone.md", "/n/here.md"] {
+        conn.execute(
+            "INSERT INTO files(path, size, mtime) VALUES (?1, 10, 0)",
+            [p],
+        )
+        .unwrap();
+    }
+    let gone = db::file_id(&conn, "/n/gone.md").unwrap();
+    let tag = db::ensure_tag_path(&conn, "project").unwrap();
+    for id in [gone, db::file_id(&conn, "/n/here.md").unwrap()] {
+        db::attach_tag(&conn, id, tag, 0).unwrap();
+    }
+    drop_files(&mut conn, &[gone], true).unwrap();
+
+    let search = |q: &str| super::query::search(&conn, q).unwrap();
+    assert_eq!(search("tag:project"), ["/n/here.md"]);
+    assert_eq!(search("gone"), Vec::<String>::new());
+    assert_eq!(search("tag:project missing:true"), ["/n/gone.md"]);
+    assert_eq!(search("tag:project missing:any").len(), 2);
+    assert!(super::query::Query::parse("missing:maybe").is_err());
+
+    let stats = db::tag_stats(&conn, 0).unwrap();
+    assert_eq!((stats[0].name.as_str(), stats[0].files), ("project", 1));
+    let dirs = db::dir_stats(&conn, Path::new("/n"), 1, 0).unwrap();
+    assert_eq!(dirs[0].files, 1);
+}
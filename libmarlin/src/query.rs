@@ -0,0 +1,1388 @@
+//! Search-string → SQL translation shared by `marlin search`, saved views
+//! and anything else that accepts a Marlin query.
+//!
+//! Plain words, `attr:`, `note:` and `content:` tokens become an FTS5
+//! `MATCH` expression; plain words also match file text, `note:` looks
+//! only at annotation notes and `content:` only at file text.
+//! Structured tokens compile to SQL predicates on the `files` row and are
+//! ANDed with it: `OR` between two of them joins those two and `NOT` in
+//! front of one negates it.  `OR` between a search term and a structured
+//! token is an error.
+//!
+//! * `tag:project` – files tagged `project` or any tag below it;
+//!   `tag:=project` only the node itself
+//! * `rating:>=4`, `fav:true`, `label:red` – per-file metadata; unrated
+//!   files count as `rating:0`
+//! * `lang:de` – text files a scan found to be written in German (see
+//!   [`crate::lang`])
+//! * `-tag:keep` – files *without* the tag `keep` (or any tag below it)
+//! * `linked_to:(<query>)` – files with a link (either direction) to a file
+//!   matching the nested query
+//! * `backlink_of:<path>` – files that link to `<path>`
+//! * `task:open`, `task:done` – files with an open (or finished) task;
+//!   `task:overdue` those with an open task past its due date
+//! * `attr:<key>~=<text>` (contains), `attr:<key>^=<text>` (prefix),
+//!   `attr:<key>>=<v>` and friends, `attr:<key>=<lo>..<hi>` (inclusive
+//!   range, either end optional; `attr:<key>..<hi>` for short) – compared
+//!   against the `attributes` table; plain `attr:<key>=<v>` stays FTS
+//! * `path:<glob>` – files whose path matches a glob such as
+//!   `**/reports/**/*.pdf` (relative globs match below any directory)
+//! * `added:` / `seen:` – when a scan first indexed the file and when one
+//!   last found it: `added:>2025-05-01`, `added:2025-05-01..2025-05-31`
+//!   (local dates, `T14:00` for a time of day), or by age – `added:7d`
+//!   (within the last 7 days), `seen:>30d` (more than 30 days ago)
+//! * `missing:true` – only tombstones, files gone from disk that kept
+//!   their metadata (see [`crate::prune`]); `missing:any` live files and
+//!   tombstones.  Without it, tombstones never match.
+//!
+//! [`lint`] checks a query before it reaches SQLite and reports unbalanced
+//! quotes, unknown fields and misplaced operators with their column;
+//! [`Query::parse`] refuses queries with errors.
+
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use rusqlite::{params_from_iter, types::Value, Connection};
+
+use crate::labels::normalize_label;
+use crate::vfs::{StdFs, Vfs};
+
+/// A parsed search query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// FTS5 `MATCH` expression (empty when the query only has filters).
+    pub fts: String,
+    /// Extra predicates on `files f`, ANDed together.
+    pub filters: Vec<Filter>,
+    /// Also consider tombstones (`missing:`); otherwise only files with
+    /// `missing_since IS NULL` can match.
+    pub tombstones: bool,
+}
+
+impl Filter {
+    /// The opposite predicate; a `NULL` result counts as false first.
+    fn not(self) -> Filter {
+        Filter {
+            sql: format!("NOT IFNULL(({}), 0)", self.sql),
+            params: self.params,
+        }
+    }
+
+    /// Either predicate.
+    fn or(self, other: Filter) -> Filter {
+        let mut params = self.params;
+        params.extend(other.params);
+        Filter {
+            sql: format!("({}) OR ({})", self.sql, other.sql),
+            params,
+        }
+    }
+}
+
+/// How [`Query::parse_as`] reads the raw input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    /// Marlin query syntax: fields, operators and quoting.
+    #[default]
+    Marlin,
+    /// The whole input is one phrase; `:`, parentheses, quotes and
+    /// operator words carry no meaning.
+    Literal,
+}
+
+/// One SQL predicate plus the values bound to its `?` placeholders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub sql: String,
+    pub params: Vec<Value>,
+}
+
+impl Query {
+    /// Parse a raw user query; fails with the first error [`lint`] finds.
+    pub fn parse(raw: &str) -> Result<Self> {
+        Self::parse_sharing(raw, QueryKind::Marlin, &[])
+    }
+
+    /// Parse `raw` as the given kind of query.
+    pub fn parse_as(raw: &str, kind: QueryKind) -> Result<Self> {
+        Self::parse_sharing(raw, kind, &[])
+    }
+
+    /// [`parse_as`](Self::parse_as), with `tag:` filters also matching the
+    /// tags of the attached databases named in `shared` (see
+    /// [`db::attach_shared`](crate::db::attach_shared)).
+    pub fn parse_sharing(raw: &str, kind: QueryKind, shared: &[String]) -> Result<Self> {
+        match kind {
+            QueryKind::Marlin => {
+                if let Some(d) = lint(raw).into_iter().find(Diagnostic::is_error) {
+                    bail!("{d}");
+                }
+                Self::parse_unchecked(raw, shared)
+            }
+            QueryKind::Literal => {
+                if raw.trim().is_empty() {
+                    bail!("literal query is empty");
+                }
+                Ok(Self {
+                    fts: format!("\"{}\"", raw.replace('"', "\"\"")),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    fn parse_unchecked(raw: &str, shared: &[String]) -> Result<Self> {
+        let mut parts: Vec<String> = Vec::new();
+        let mut filters: Vec<Filter> = Vec::new();
+        let mut tombstones = false;
+        // the last token that was not an operator became a filter
+        let mut after_filter = false;
+
+        for tok in tokenize(raw) {
+            let filter = if ["AND", "OR", "NOT"].contains(&tok.as_str()) {
+                parts.push(tok);
+                continue;
+            } else if let Some(tag) = tok.strip_prefix("tag:") {
+                match tag.strip_prefix('=') {
+                    Some(exact) => tag_filter(exact, true, shared),
+                    None => tag_filter(tag, false, shared),
+                }
+            } else if let Some(flt) = tok
+                .strip_prefix("attr:")
+                .map(attr_filter)
+                .transpose()?
+                .flatten()
+            {
+                flt
+            } else if let Some(attr) = tok.strip_prefix("attr:") {
+                let mut kv = attr.splitn(2, '=');
+                let key = kv.next().unwrap();
+                if let Some(val) = kv.next() {
+                    parts.push(format!("attrs_text:{}", escape_fts(key)));
+                    parts.push("AND".into());
+                    parts.push(format!("attrs_text:{}", escape_fts(val)));
+                } else {
+                    parts.push(format!("attrs_text:{}", escape_fts(key)));
+                }
+                after_filter = false;
+                continue;
+            } else if let Some(note) = tok.strip_prefix("note:") {
+                parts.push(format!("notes_text:{}", escape_fts(note)));
+                after_filter = false;
+                continue;
+            } else if let Some(text) = tok.strip_prefix("content:") {
+                parts.push(format!("content:{}", escape_fts(text)));
+                after_filter = false;
+                continue;
+            } else if let Some(tag) = tok.strip_prefix("-tag:") {
+                let flt = match tag.strip_prefix('=') {
+                    Some(exact) => tag_filter(exact, true, shared),
+                    None => tag_filter(tag, false, shared),
+                };
+                Filter {
+                    sql: format!("NOT {}", flt.sql),
+                    params: flt.params,
+                }
+            } else if let Some(spec) = tok.strip_prefix("rating:") {
+                rating_filter(spec)?
+            } else if let Some(spec) = tok
+                .strip_prefix("fav:")
+                .or_else(|| tok.strip_prefix("favorite:"))
+            {
+                Filter {
+                    sql: "f.favorite = ?".into(),
+                    params: vec![Value::Integer(parse_bool(spec)? as i64)],
+                }
+            } else if let Some(spec) = tok.strip_prefix("label:") {
+                Filter {
+                    sql: "EXISTS (SELECT 1 FROM file_labels fl \
+                          WHERE fl.file_id = f.id AND fl.label = ?)"
+                        .into(),
+                    params: vec![Value::Text(normalize_label(spec)?)],
+                }
+            } else if let Some(spec) = tok.strip_prefix("task:") {
+                task_filter(spec)?
+            } else if let Some(spec) = tok.strip_prefix("lang:") {
+                Filter {
+                    sql: "EXISTS (SELECT 1 FROM attributes a \
+                          WHERE a.file_id = f.id AND a.key = ? AND a.value = ?)"
+                        .into(),
+                    params: vec![
+                        Value::Text(crate::lang::LANG_KEY.into()),
+                        Value::Text(lang_code(spec)?),
+                    ],
+                }
+            } else if let Some(inner) = tok
+                .strip_prefix("linked_to:(")
+                .and_then(|t| t.strip_suffix(')'))
+            {
+                let (sub, mut params) = Query::parse_unchecked(inner, shared)?.build("f.id");
+                params.extend(params.clone());
+                Filter {
+                    sql: format!(
+                        "EXISTS (SELECT 1 FROM links l \
+                          WHERE (l.src_file_id = f.id AND l.dst_file_id IN ({sub})) \
+                             OR (l.dst_file_id = f.id AND l.src_file_id IN ({sub})))"
+                    ),
+                    params,
+                }
+            } else if let Some(spec) = tok.strip_prefix("missing:") {
+                tombstones = true;
+                Filter {
+                    sql: match missing_spec(spec)? {
+                        Some(true) => "f.missing_since IS NOT NULL",
+                        Some(false) => "f.missing_since IS NULL",
+                        None => "TRUE",
+                    }
+                    .into(),
+                    params: Vec::new(),
+                }
+            } else if let Some(glob) = tok.strip_prefix("path:") {
+                path_filter(glob)
+            } else if let Some(spec) = tok.strip_prefix("added:") {
+                time_filter("f.first_indexed_at", spec)?
+            } else if let Some(spec) = tok.strip_prefix("seen:") {
+                time_filter("f.last_seen_at", spec)?
+            } else if let Some(path) = tok.strip_prefix("backlink_of:") {
+                Filter {
+                    sql: "EXISTS (SELECT 1 FROM links l JOIN files d ON d.id = l.dst_file_id \
+                          WHERE l.src_file_id = f.id AND d.path = ?)"
+                        .into(),
+                    params: vec![Value::Text(shellexpand::tilde(path).into_owned())],
+                }
+            } else {
+                parts.push(escape_fts(&tok));
+                after_filter = false;
+                continue;
+            };
+
+            // `OR` between two filters joins them, `NOT` negates the filter
+            // and `AND` is how filters apply anyway; `OR` between a search
+            // term and a filter has no SQL form ([`lint`] rejects it)
+            match parts.last().map(String::as_str) {
+                Some("OR") if after_filter => {
+                    parts.pop();
+                    let prev = filters.pop().expect("a filter came before");
+                    filters.push(prev.or(filter));
+                }
+                Some("OR") => bail!("`OR` cannot join a search term and a filter"),
+                Some("NOT") => {
+                    parts.pop();
+                    filters.push(filter.not());
+                }
+                Some("AND") => {
+                    parts.pop();
+                    filters.push(filter);
+                }
+                _ => filters.push(filter),
+            }
+            after_filter = true;
+        }
+        drop_dangling_operator(&mut parts);
+
+        Ok(Self {
+            fts: parts.join(" "),
+            filters,
+            tombstones,
+        })
+    }
+
+    /// Build the `SELECT f.id, f.path …` statement and its parameters.
+    pub fn to_sql(&self) -> (String, Vec<Value>) {
+        self.ordered("f.id, f.path")
+    }
+
+    /// [`to_sql`](Self::to_sql) plus a third column with the FTS5 rank
+    /// (`0.0` when the query has no full-text part).
+    pub fn to_ranked_sql(&self) -> (String, Vec<Value>) {
+        self.ordered(if self.fts.is_empty() {
+            "f.id, f.path, 0.0"
+        } else {
+            "f.id, f.path, rank"
+        })
+    }
+
+    fn ordered(&self, cols: &str) -> (String, Vec<Value>) {
+        let (mut sql, params) = self.build(cols);
+        sql.push_str(if self.fts.is_empty() {
+            " ORDER BY f.path"
+        } else {
+            " ORDER BY rank"
+        });
+        (sql, params)
+    }
+
+    /// `SELECT {cols} …` without ordering, so it can also be embedded as a
+    /// sub-query (`linked_to:(…)`).
+    fn build(&self, cols: &str) -> (String, Vec<Value>) {
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+
+        let mut sql = if self.fts.is_empty() {
+            format!("SELECT {cols} FROM files f")
+        } else {
+            clauses.push("files_fts MATCH ?".to_string());
+            params.push(Value::Text(self.fts.clone()));
+            format!("SELECT {cols} FROM files_fts JOIN files f ON f.rowid = files_fts.rowid")
+        };
+
+        if !self.tombstones {
+            clauses.push("f.missing_since IS NULL".to_string());
+        }
+        for flt in &self.filters {
+            clauses.push(format!("({})", flt.sql));
+            params.extend(flt.params.iter().cloned());
+        }
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+        (sql, params)
+    }
+}
+
+/// Knobs for [`search_hits`] / [`view_exec`].
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// Return at most this many hits.
+    pub limit: Option<usize>,
+    /// Skip this many hits first (for paging).
+    pub offset: usize,
+    /// Fall back to a naive substring scan when FTS finds nothing.
+    pub fallback: bool,
+    /// How to read the query string.
+    pub kind: QueryKind,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            offset: 0,
+            fallback: true,
+            kind: QueryKind::Marlin,
+        }
+    }
+}
+
+/// One matching file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub file_id: i64,
+    pub path: String,
+}
+
+/// Run a raw query and return the matching files.
+///
+/// Rows FTS5 rejects (e.g. a bare `one.txt`) are treated as no hit; the
+/// naive substring scan over paths and small file bodies then takes over
+/// when the query contains no field tokens (always for literal queries).
+pub fn search_hits(conn: &Connection, raw: &str, opts: &SearchOptions) -> Result<Vec<SearchHit>> {
+    Ok(ranked_hits(conn, raw, opts)?
+        .into_iter()
+        .map(|(hit, _)| hit)
+        .skip(opts.offset)
+        .take(opts.limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+/// Every hit of `raw` with its rank, best first; no paging.
+fn ranked_hits(
+    conn: &Connection,
+    raw: &str,
+    opts: &SearchOptions,
+) -> Result<Vec<(SearchHit, f64)>> {
+    let shared = crate::db::shared_schemas(conn)?;
+    let q = Query::parse_sharing(raw, opts.kind, &shared)?;
+    let hits = query_hits(conn, &q)?;
+    if hits.is_empty() && falls_back(raw, opts) {
+        return fallback_hits(conn, raw);
+    }
+    Ok(hits)
+}
+
+/// Run a parsed query, best first.  Rows FTS5 rejects count as no hit.
+fn query_hits(conn: &Connection, q: &Query) -> Result<Vec<(SearchHit, f64)>> {
+    let (sql, params) = q.to_ranked_sql();
+    let mut stmt = conn.prepare(&sql)?;
+    let hits = stmt
+        .query_map(params_from_iter(params), |r| {
+            Ok((
+                SearchHit {
+                    file_id: r.get(0)?,
+                    path: r.get(1)?,
+                },
+                r.get(2)?,
+            ))
+        })?
+        .filter_map(std::result::Result::ok)
+        .collect();
+    Ok(hits)
+}
+
+/// Whether an empty FTS result for `raw` is retried as a substring scan.
+fn falls_back(raw: &str, opts: &SearchOptions) -> bool {
+    let has_fields = opts.kind == QueryKind::Marlin && raw.contains(':');
+    opts.fallback && !has_fields
+}
+
+fn fallback_hits(conn: &Connection, raw: &str) -> Result<Vec<(SearchHit, f64)>> {
+    naive_substring_search(conn, raw)?
+        .into_iter()
+        .map(|path| {
+            let file_id = crate::db::file_id(conn, &path)?;
+            Ok((SearchHit { file_id, path }, 0.0))
+        })
+        .collect()
+}
+
+/// The hits of one query, kept so that a longer query typed after it can
+/// be answered from them (see [`search_incremental`]).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResultSet {
+    /// The query, normalised (see [`query_cache::normalize`]).
+    ///
+    /// [`query_cache::normalize`]: crate::query_cache::normalize
+    pub query: String,
+    /// Every hit, best first.
+    pub hits: Vec<SearchHit>,
+    /// What the hits were read from; `None` when they cannot seed a
+    /// refinement (substring fallback hits).
+    stamp: Option<Stamp>,
+}
+
+/// Attached tag databases and the data version a [`ResultSet`] saw.
+#[derive(Debug, Clone, PartialEq)]
+struct Stamp {
+    shared: Vec<String>,
+    version: (i64, i64),
+}
+
+/// Run `raw` like [`search_hits`] with default options, reusing `prev`
+/// when `raw` only appends terms to its query: `tag:draft` followed by
+/// `tag:draft report` only looks at the drafts, and nothing at all when
+/// there were none.  The hits are the same as a fresh search's.
+///
+/// `prev` is ignored, and the query runs in full, when an appended term
+/// could widen the result (`OR`, or an operator ending `prev`), when the
+/// index was written to or shared tag databases were attached or
+/// detached since, and when `prev` came from the substring fallback.
+pub fn search_incremental(
+    conn: &Connection,
+    prev: Option<&ResultSet>,
+    raw: &str,
+) -> Result<ResultSet> {
+    let opts = SearchOptions::default();
+    let stamp = Stamp {
+        shared: crate::db::shared_schemas(conn)?,
+        version: crate::query_cache::data_version(conn)?,
+    };
+    let query = crate::query_cache::normalize(raw);
+    let q = Query::parse_sharing(&query, opts.kind, &stamp.shared)?;
+
+    let seed = prev.filter(|p| p.stamp.as_ref() == Some(&stamp) && is_refinement(&p.query, &query));
+    let hits = match seed {
+        Some(p) if p.hits.is_empty() => Vec::new(),
+        Some(p) => {
+            let ids = p.hits.iter().map(|h| h.file_id.to_string());
+            let mut within = q.clone();
+            within.filters.push(Filter {
+                sql: "f.id IN (SELECT value FROM json_each(?))".into(),
+                params: vec![Value::Text(format!(
+                    "[{}]",
+                    ids.collect::<Vec<_>>().join(",")
+                ))],
+            });
+            query_hits(conn, &within)?
+        }
+        None => query_hits(conn, &q)?,
+    };
+
+    if hits.is_empty() && falls_back(&query, &opts) {
+        return Ok(ResultSet {
+            hits: fallback_hits(conn, &query)?
+                .into_iter()
+                .map(|(h, _)| h)
+                .collect(),
+            query,
+            stamp: None,
+        });
+    }
+    Ok(ResultSet {
+        hits: hits.into_iter().map(|(h, _)| h).collect(),
+        query,
+        stamp: Some(stamp),
+    })
+}
+
+/// `new` is `prev` with more terms ANDed on, so it cannot match a file
+/// `prev` did not.
+fn is_refinement(prev: &str, new: &str) -> bool {
+    let (Ok(old), Ok(new)) = (tokens(prev), tokens(new)) else {
+        return false;
+    };
+    let is_op = |t: &Token| !t.quoted && ["AND", "OR", "NOT"].contains(&t.text.as_str());
+    let same_start = old
+        .iter()
+        .zip(&new)
+        .all(|(a, b)| a.text == b.text && a.quoted == b.quoted);
+    new.len() > old.len()
+        && same_start
+        && old.last().is_some_and(|t| !is_op(t))
+        && !new[old.len()..].iter().any(|t| is_op(t) && t.text == "OR")
+}
+
+/// A hit from one of several databases searched together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourcedHit {
+    /// Label of the database the hit came from.
+    pub source: String,
+    pub hit: SearchHit,
+    /// FTS5 rank, lower is better; `0.0` for filter-only queries and
+    /// substring fallback hits.
+    pub rank: f64,
+}
+
+/// Run `raw` against every `(label, connection)` and merge the hits: best
+/// rank first, ties by path and then by source order.  `opts` paging
+/// applies to the merged list.
+///
+/// BM25 ranks depend on each index's own statistics, so the interleaving
+/// is approximate when the databases differ a lot in size.
+pub fn search_across(
+    sources: &[(&str, &Connection)],
+    raw: &str,
+    opts: &SearchOptions,
+) -> Result<Vec<SourcedHit>> {
+    let mut merged = Vec::new();
+    for (idx, (label, conn)) in sources.iter().enumerate() {
+        for (hit, rank) in ranked_hits(conn, raw, opts)? {
+            merged.push((
+                idx,
+                SourcedHit {
+                    source: label.to_string(),
+                    hit,
+                    rank,
+                },
+            ));
+        }
+    }
+    merged.sort_by(|(ia, a), (ib, b)| {
+        a.rank
+            .total_cmp(&b.rank)
+            .then_with(|| a.hit.path.cmp(&b.hit.path))
+            .then(ia.cmp(ib))
+    });
+    Ok(merged
+        .into_iter()
+        .map(|(_, h)| h)
+        .skip(opts.offset)
+        .take(opts.limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+/// Run a raw query and return the matching paths.
+pub fn search(conn: &Connection, raw: &str) -> Result<Vec<String>> {
+    Ok(search_hits(conn, raw, &SearchOptions::default())?
+        .into_iter()
+        .map(|h| h.path)
+        .collect())
+}
+
+/// Run the saved view `name`.
+pub fn view_exec(conn: &Connection, name: &str, opts: &SearchOptions) -> Result<Vec<SearchHit>> {
+    let raw = crate::db::view_query(conn, name)?;
+    search_hits(conn, &raw, opts)
+}
+
+/// Run the saved view `name` and return the matching files as `(id, path)`,
+/// the view counterpart of [`db::match_files`](crate::db::match_files).
+pub fn view_files(conn: &Connection, name: &str) -> Result<Vec<(i64, String)>> {
+    Ok(view_exec(conn, name, &SearchOptions::default())?
+        .into_iter()
+        .map(|h| (h.file_id, h.path))
+        .collect())
+}
+
+/// Case-insensitive substring match over stored paths and the contents of
+/// files up to 64 KiB. Bodies below roots with content indexing turned off
+/// are never read.
+pub fn naive_substring_search(conn: &Connection, term: &str) -> Result<Vec<String>> {
+    naive_substring_search_with(conn, &StdFs, term)
+}
+
+/// [`naive_substring_search`] reading bodies through any [`Vfs`]; files
+/// the VFS cannot open only match on their path.
+pub fn naive_substring_search_with(
+    conn: &Connection,
+    fs: &dyn Vfs,
+    term: &str,
+) -> Result<Vec<String>> {
+    let needle = term.to_lowercase();
+    let profiles = crate::roots::list(conn)?;
+    let mut stmt = conn.prepare("SELECT path FROM files WHERE missing_since IS NULL")?;
+    let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+
+    let mut out = Vec::new();
+    for p in rows {
+        let p = p?;
+        if p.to_lowercase().contains(&needle) {
+            out.push(p);
+            continue;
+        }
+        if crate::roots::innermost(&profiles, Path::new(&p)).is_some_and(|r| !r.index_content) {
+            continue;
+        }
+        if let Ok(meta) = fs.metadata(Path::new(&p)) {
+            if meta.len > 65_536 {
+                continue;
+            }
+        }
+        let mut body = String::new();
+        let read = fs
+            .open(Path::new(&p))
+            .and_then(|mut r| r.read_to_string(&mut body));
+        if read.is_ok() && body.to_lowercase().contains(&needle) {
+            out.push(p);
+        }
+    }
+    Ok(out)
+}
+
+/// Quote a term for FTS5 if it contains whitespace, syntax characters or
+/// collides with an operator keyword.
+pub fn escape_fts(term: &str) -> String {
+    if term.contains(|c: char| c.is_whitespace() || "-:()\"".contains(c))
+        || ["AND", "OR", "NOT", "NEAR"].contains(&term.to_uppercase().as_str())
+    {
+        format!("\"{}\"", term.replace('"', "\"\""))
+    } else {
+        term.to_string()
+    }
+}
+
+/* ─── linting ──────────────────────────────────────────────────────── */
+
+/// Fields a `name:` token may use; `tag` can also be negated (`-tag:`).
+pub const FIELDS: [&str; 16] = [
+    "tag",
+    "attr",
+    "rating",
+    "fav",
+    "favorite",
+    "label",
+    "lang",
+    "linked_to",
+    "backlink_of",
+    "path",
+    "added",
+    "seen",
+    "missing",
+    "task",
+    "note",
+    "content",
+];
+
+/// `path:` globs may use at most this many `**/` (each doubles the number
+/// of `GLOB` alternatives).
+const MAX_GLOBSTARS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The query cannot run as written.
+    Error,
+    /// The query runs, but not the way it reads.
+    Warning,
+}
+
+/// One problem found in a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based character column, if the problem has a position.
+    pub column: Option<usize>,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    fn error(column: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            column: Some(column),
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warning(column: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(column, message)
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(col) = self.column {
+            write!(f, " at column {col}")?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, " ({hint})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check a raw query without touching the database.
+pub fn lint(raw: &str) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    lint_at(raw, 0, &mut out);
+    out
+}
+
+/// [`lint`] plus a dry run of the resulting FTS expression against
+/// `files_fts`, which catches whatever SQLite still rejects.
+pub fn check(conn: &Connection, raw: &str) -> Result<Vec<Diagnostic>> {
+    let mut diags = lint(raw);
+    if diags.iter().any(Diagnostic::is_error) {
+        return Ok(diags);
+    }
+    let q = Query::parse_unchecked(raw, &[])?;
+    if !q.fts.is_empty() {
+        let mut stmt = conn.prepare("SELECT 1 FROM files_fts WHERE files_fts MATCH ?1 LIMIT 1")?;
+        if let Err(e) = stmt.exists([&q.fts]) {
+            diags.push(Diagnostic {
+                severity: Severity::Error,
+                column: None,
+                message: format!("SQLite rejected the full-text expression `{}`", q.fts),
+                hint: Some(e.to_string()),
+            });
+        }
+    }
+    Ok(diags)
+}
+
+/// Lint `raw`, a query (or `linked_to:` sub-query) whose first character
+/// sits at column `offset + 1`.
+fn lint_at(raw: &str, offset: usize, out: &mut Vec<Diagnostic>) {
+    let toks = match tokens(raw) {
+        Ok(toks) => toks,
+        Err(mut d) => {
+            d.column = d.column.map(|c| c + offset);
+            out.push(d);
+            return;
+        }
+    };
+
+    // mirrors Query::parse: filters stay out of the FTS expression, `OR`
+    // joins two adjacent filters, `NOT` negates the filter after it (with
+    // or without a left-hand term) and `AND` in front of one is a no-op
+    let mut have_term = false;
+    let mut after_filter = false;
+    let mut pending: Option<(&str, usize)> = None;
+    for tok in &toks {
+        let col = tok.column + offset;
+        let text = tok.text.as_str();
+        if ["AND", "OR", "NOT"].contains(&text) {
+            if let Some((prev, _)) = pending {
+                out.push(Diagnostic::error(
+                    col,
+                    format!("`{text}` directly follows `{prev}`"),
+                ));
+            } else if !have_term && !after_filter && text != "NOT" {
+                out.push(Diagnostic::error(
+                    col,
+                    format!("`{text}` has no left-hand term"),
+                ));
+            }
+            pending = Some((text, col));
+            continue;
+        }
+        let is_filter = match field(tok) {
+            Some((name, value)) => {
+                lint_field(name, value, col, out);
+                name != "attr" || !matches!(attr_filter(value), Ok(None))
+            }
+            None => false,
+        };
+        match (pending.take(), is_filter) {
+            (Some(("OR", _)), true) if after_filter => {}
+            (Some(("AND" | "NOT", _)), true) | (None, _) => {}
+            (Some((op, op_col)), true) if have_term => out.push(
+                Diagnostic::error(
+                    op_col,
+                    format!("`{op}` cannot join a search term and `{text}`"),
+                )
+                .hint("`OR` only joins two filters or two search terms"),
+            ),
+            (Some(("OR", op_col)), false) if after_filter => out.push(
+                Diagnostic::error(op_col, format!("`OR` cannot join a filter and `{text}`"))
+                    .hint("`OR` only joins two filters or two search terms"),
+            ),
+            (Some((op, op_col)), false) if !have_term && after_filter => out.push(
+                Diagnostic::error(op_col, format!("`{op}` has no left-hand term"))
+                    .hint("filters cannot be combined with search terms by operators"),
+            ),
+            // reported when the operator was read
+            (Some(_), true) => {}
+            (Some((op, _)), false) if !have_term && op != "NOT" => {}
+            (Some((op, op_col)), false) if !have_term => out.push(Diagnostic::error(
+                op_col,
+                format!("`{op}` has no left-hand term"),
+            )),
+            (Some(_), false) => {}
+        }
+        if is_filter {
+            after_filter = true;
+        } else {
+            have_term = true;
+            after_filter = false;
+        }
+    }
+    match pending {
+        Some((op, col)) if op == "NOT" && !have_term && !after_filter => out.push(
+            Diagnostic::error(col, format!("`{op}` has no left-hand term")),
+        ),
+        Some((op, col)) => out.push(Diagnostic::warning(
+            col,
+            format!("`{op}` at the end of the query is ignored"),
+        )),
+        None => {}
+    }
+}
+
+/// Split a `name:value` token whose name looks like a field.  Quoted
+/// tokens only count when the name is a real field, so `"foo:bar"` stays
+/// a phrase; URLs and Windows paths (`C:\…`) never count.
+fn field(tok: &Token) -> Option<(&str, &str)> {
+    let (name, value) = tok.text.split_once(':')?;
+    let bare = name.strip_prefix('-').unwrap_or(name);
+    let ident = bare.starts_with(|c: char| c.is_ascii_alphabetic())
+        && bare.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !ident || value.starts_with(['/', '\\']) {
+        return None;
+    }
+    if tok.quoted && !FIELDS.contains(&bare) {
+        return None;
+    }
+    Some((name, value))
+}
+
+fn lint_field(name: &str, value: &str, col: usize, out: &mut Vec<Diagnostic>) {
+    let bare = name.strip_prefix('-').unwrap_or(name);
+    if !FIELDS.contains(&bare) {
+        let hint = match suggest_field(bare) {
+            Some(f) => format!("did you mean `{f}:`?"),
+            None => "quote the term to search for it literally".into(),
+        };
+        out.push(Diagnostic::error(col, format!("unknown field '{name}'")).hint(hint));
+        return;
+    }
+    if name.starts_with('-') && bare != "tag" {
+        out.push(Diagnostic::error(
+            col,
+            format!("`{name}:` is not supported, only `-tag:` can be negated"),
+        ));
+        return;
+    }
+    if value.trim_matches('/').is_empty() {
+        out.push(Diagnostic::error(
+            col,
+            format!("missing value after `{name}:`"),
+        ));
+        return;
+    }
+    let invalid = match bare {
+        "rating" => rating_filter(value).err(),
+        "fav" | "favorite" => parse_bool(value).err(),
+        "label" => normalize_label(value).err(),
+        "lang" => lang_code(value).err(),
+        "task" => task_filter(value).err(),
+        "attr" => attr_filter(value).err(),
+        "added" | "seen" => time_filter("", value).err(),
+        "missing" => missing_spec(value).err(),
+        "path" if value.matches("**/").count() > MAX_GLOBSTARS => Some(anyhow::anyhow!(
+            "too many `**/` in path glob (at most {MAX_GLOBSTARS})"
+        )),
+        "linked_to" => match value.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+            // the sub-query starts right after `linked_to:(`
+            Some(inner) => {
+                lint_at(inner, col + name.len() + 1, out);
+                None
+            }
+            None => Some(anyhow::anyhow!("expected `linked_to:(<query>)`")),
+        },
+        _ => None,
+    };
+    if let Some(e) = invalid {
+        out.push(Diagnostic::error(col, e.to_string()));
+    }
+}
+
+/// A `lang:` value: a two- or three-letter language code, lowercased.
+fn lang_code(spec: &str) -> Result<String> {
+    if !(2..=3).contains(&spec.len()) || !spec.chars().all(|c| c.is_ascii_alphabetic()) {
+        bail!("expected a language code such as `en` or `de`, got `{spec}`");
+    }
+    Ok(spec.to_ascii_lowercase())
+}
+
+/// The known field closest to a mistyped one (`tags` → `tag`).
+fn suggest_field(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    FIELDS
+        .iter()
+        .find(|f| name.starts_with(*f) || f.starts_with(name.as_str()))
+        .or_else(|| FIELDS.iter().find(|f| edit_distance(&name, f) <= 2))
+        .copied()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (diag + usize::from(ca != *cb))
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/* ─── structured-token helpers ─────────────────────────────────────── */
+
+/// Shell-style split (quotes group words and are stripped) that also keeps
+/// a parenthesised sub-query such as `linked_to:(tag:a OR tag:b)` in one
+/// token. Falls back to the raw string on unbalanced quotes or parens.
+fn tokenize(raw: &str) -> Vec<String> {
+    match tokens(raw) {
+        Ok(toks) => toks.into_iter().map(|t| t.text).collect(),
+        Err(_) => vec![raw.to_string()],
+    }
+}
+
+/// A token of the raw query and the (1-based) column it starts at.
+struct Token {
+    text: String,
+    column: usize,
+    /// Starts with a quote, so a `name:` prefix is not a field.
+    quoted: bool,
+}
+
+/// [`tokenize`] with positions; an unbalanced quote or sub-query
+/// parenthesis is reported at the column that opened it.
+fn tokens(raw: &str) -> std::result::Result<Vec<Token>, Diagnostic> {
+    let mut toks = Vec::new();
+    let mut cur: Option<Token> = None;
+    let mut quote: Option<(char, usize)> = None;
+    let mut parens: Vec<usize> = Vec::new();
+    let mut chars = raw.chars().zip(1..);
+
+    fn open(cur: &mut Option<Token>, column: usize, quoted: bool) -> &mut Token {
+        cur.get_or_insert_with(|| Token {
+            text: String::new(),
+            column,
+            quoted,
+        })
+    }
+
+    while let Some((c, col)) = chars.next() {
+        if !parens.is_empty() {
+            // inside a sub-query everything is kept verbatim
+            match c {
+                '(' => parens.push(col),
+                ')' => {
+                    parens.pop();
+                }
+                _ => {}
+            }
+            open(&mut cur, col, false).text.push(c);
+            continue;
+        }
+        match (quote, c) {
+            (Some((q, _)), c) if c == q => quote = None,
+            (Some(('"', _)), '\\') => {
+                if let (Some(tok), Some((n, _))) = (cur.as_mut(), chars.next()) {
+                    tok.text.push(n);
+                }
+            }
+            (Some(_), c) => cur.as_mut().unwrap().text.push(c),
+            (None, '"' | '\'') => {
+                open(&mut cur, col, true);
+                quote = Some((c, col));
+            }
+            (None, '(') if cur.as_ref().is_some_and(|t| t.text.ends_with(':')) => {
+                parens.push(col);
+                cur.as_mut().unwrap().text.push(c);
+            }
+            (None, c) if c.is_whitespace() => toks.extend(cur.take()),
+            (None, c) => open(&mut cur, col, false).text.push(c),
+        }
+    }
+    if let Some((q, col)) = quote {
+        let d = Diagnostic::error(col, "unbalanced quote");
+        // a lone apostrophe (`don't`) is common enough to only warn about
+        return Err(if q == '\'' {
+            Diagnostic {
+                severity: Severity::Warning,
+                ..d.hint("the whole query is searched as one phrase")
+            }
+        } else {
+            d
+        });
+    }
+    if let Some(&col) = parens.first() {
+        return Err(Diagnostic::error(col, "unbalanced parenthesis"));
+    }
+    toks.extend(cur);
+    Ok(toks)
+}
+
+/// A boolean operator ending the query has no right-hand side and would be
+/// an FTS5 syntax error ([`lint`] warns about it).
+fn drop_dangling_operator(parts: &mut Vec<String>) {
+    while matches!(parts.last().map(String::as_str), Some("AND" | "OR" | "NOT")) {
+        parts.pop();
+    }
+}
+
+/// Split a `>=4` style spec into an SQL comparison operator and operand.
+pub(crate) fn split_comparison(spec: &str) -> (&'static str, &str) {
+    for (prefix, op) in [
+        (">=", ">="),
+        ("<=", "<="),
+        ("!=", "!="),
+        (">", ">"),
+        ("<", "<"),
+        ("=", "="),
+    ] {
+        if let Some(rest) = spec.strip_prefix(prefix) {
+            return (op, rest);
+        }
+    }
+    ("=", spec)
+}
+
+fn rating_filter(spec: &str) -> Result<Filter> {
+    let (op, operand) = split_comparison(spec);
+    let value: u8 = match operand.parse() {
+        Ok(v) if v <= 5 => v,
+        _ => bail!("invalid rating `{spec}` (expected 0-5, optionally prefixed by <, <=, >, >=)"),
+    };
+    // unrated files count as 0
+    Ok(Filter {
+        sql: format!("IFNULL(f.rating, 0) {op} ?"),
+        params: vec![Value::Integer(value as i64)],
+    })
+}
+
+/// `EXISTS` over the tag tree: the file carries `tag` or, unless `exact`,
+/// one of its descendants (`tag:project` also finds `project/md`).  Tag
+/// paths compare case-insensitively, as the FTS column used to.  Each
+/// schema in `shared` adds the same check against its tags, matched to
+/// the file by path or content hash.
+fn tag_filter(tag: &str, exact: bool, shared: &[String]) -> Filter {
+    let tag = tag.trim_matches('/');
+    let mut matches = String::from("(tt.path = lower(?)");
+    let mut tag_params = vec![Value::Text(tag.to_string())];
+    if !exact {
+        matches.push_str(" OR substr(tt.path, 1, ?) = lower(?)");
+        tag_params.push(Value::Integer(tag.chars().count() as i64 + 1));
+        tag_params.push(Value::Text(format!("{tag}/")));
+    }
+    matches.push(')');
+
+    let tree = |schema: &str| {
+        format!(
+            "WITH RECURSIVE tag_tree(id, path) AS (\
+                 SELECT id, lower(name) FROM {schema}tags WHERE parent_id IS NULL \
+                 UNION ALL \
+                 SELECT t.id, tt.path || '/' || lower(t.name) \
+                   FROM {schema}tags t JOIN tag_tree tt ON t.parent_id = tt.id) "
+        )
+    };
+    let mut checks = vec![format!(
+        "EXISTS ({}SELECT 1 FROM file_tags ft JOIN tag_tree tt ON tt.id = ft.tag_id \
+          WHERE ft.file_id = f.id AND {matches})",
+        tree("")
+    )];
+    let mut params = tag_params.clone();
+    for name in shared {
+        let schema = format!("\"{name}\".");
+        checks.push(format!(
+            "EXISTS ({}SELECT 1 FROM {schema}files sf \
+               JOIN {schema}file_tags ft ON ft.file_id = sf.id \
+               JOIN tag_tree tt ON tt.id = ft.tag_id \
+              WHERE (sf.path = f.path OR sf.hash = f.hash) AND {matches})",
+            tree(&schema)
+        ));
+        params.extend(tag_params.iter().cloned());
+    }
+    Filter {
+        sql: if checks.len() == 1 {
+            checks.remove(0)
+        } else {
+            format!("({})", checks.join(" OR "))
+        },
+        params,
+    }
+}
+
+/// Operators an `attr:` token may use after the key, longest first.
+const ATTR_OPS: [&str; 9] = ["~=", "^=", ">=", "<=", "!=", "..", ">", "<", "="];
+
+/// `attr:<key><op><value>` as a predicate on the `attributes` table, or
+/// `None` for the FTS forms (`attr:<key>` and `attr:<key>=<value>`).
+fn attr_filter(spec: &str) -> Result<Option<Filter>> {
+    // the earliest operator wins, the longest one at that position
+    let Some((pos, op)) = ATTR_OPS
+        .iter()
+        .filter_map(|op| spec.find(op).map(|pos| (pos, *op)))
+        .min_by_key(|(pos, op)| (*pos, std::cmp::Reverse(op.len())))
+    else {
+        return Ok(None);
+    };
+    let key = &spec[..pos];
+    let value = &spec[pos + op.len()..];
+    if key.is_empty() {
+        bail!("missing attribute name in `attr:{spec}`");
+    }
+
+    let (lo, hi) = match (op, value.split_once("..")) {
+        ("=", None) => return Ok(None),
+        ("=", Some((lo, hi))) => (lo, hi),
+        ("..", _) => ("", value),
+        _ => {
+            if value.is_empty() {
+                bail!("missing value after `attr:{key}{op}`");
+            }
+            return Ok(Some(match op {
+                "~=" => attr_exists(
+                    key,
+                    "instr(lower(a.value), lower(?)) > 0",
+                    [Value::Text(value.into())],
+                ),
+                "^=" => attr_exists(
+                    key,
+                    "instr(lower(a.value), lower(?)) = 1",
+                    [Value::Text(value.into())],
+                ),
+                _ => attr_compare(key, &[(op, value)]),
+            }));
+        }
+    };
+    if lo.is_empty() && hi.is_empty() {
+        bail!("range `attr:{spec}` needs at least one bound");
+    }
+    let bounds: Vec<_> = [(">=", lo), ("<=", hi)]
+        .into_iter()
+        .filter(|(_, v)| !v.is_empty())
+        .collect();
+    Ok(Some(attr_compare(key, &bounds)))
+}
+
+/// Only values made of digits, signs, dots and exponents compare as
+/// numbers (`CAST('abc' AS REAL)` would be 0).
+const NUMERIC_VALUE: &str = "a.value GLOB '*[0-9]*' AND a.value NOT GLOB '*[^0-9.eE+-]*'";
+
+/// Files whose attribute `key` satisfies every `(op, operand)`: as
+/// numbers when all operands are numbers (`version>=1.2`), else as text,
+/// which orders ISO dates (`due..2025-07-01`) correctly.
+fn attr_compare(key: &str, bounds: &[(&str, &str)]) -> Filter {
+    let numbers: Option<Vec<f64>> = bounds.iter().map(|(_, v)| v.parse().ok()).collect();
+    match numbers {
+        Some(nums) => {
+            let mut cond = NUMERIC_VALUE.to_string();
+            for (op, _) in bounds {
+                cond.push_str(&format!(" AND CAST(a.value AS REAL) {op} ?"));
+            }
+            attr_exists(key, &cond, nums.into_iter().map(Value::Real))
+        }
+        None => {
+            let cond: Vec<String> = bounds
+                .iter()
+                .map(|(op, _)| format!("a.value {op} ?"))
+                .collect();
+            attr_exists(
+                key,
+                &cond.join(" AND "),
+                bounds.iter().map(|(_, v)| v.to_string().into()),
+            )
+        }
+    }
+}
+
+fn attr_exists(key: &str, cond: &str, params: impl IntoIterator<Item = Value>) -> Filter {
+    let mut all = vec![Value::Text(key.to_string())];
+    all.extend(params);
+    Filter {
+        sql: format!(
+            "EXISTS (SELECT 1 FROM attributes a \
+              WHERE a.file_id = f.id AND a.key = ? AND {cond})"
+        ),
+        params: all,
+    }
+}
+
+/// `path:<glob>` as SQL `GLOB`s on `f.path`.  `GLOB`'s `*` already spans
+/// directories, so `**/` only needs an extra alternative in which it
+/// matches no directory at all; relative globs get a leading `*/`.
+fn path_filter(glob: &str) -> Filter {
+    let glob = shellexpand::tilde(glob);
+    let glob = if glob.starts_with(['/', '*']) {
+        glob.into_owned()
+    } else {
+        format!("*/{glob}")
+    };
+
+    let mut alts = vec![String::new()];
+    for (i, piece) in glob.split("**/").enumerate() {
+        if i > 0 {
+            alts = alts
+                .into_iter()
+                .flat_map(|a| [format!("{a}*/"), a])
+                .collect();
+        }
+        for alt in &mut alts {
+            alt.push_str(piece);
+        }
+    }
+    alts.dedup();
+
+    Filter {
+        sql: vec!["f.path GLOB ?"; alts.len()].join(" OR "),
+        params: alts.into_iter().map(Value::Text).collect(),
+    }
+}
+
+/// `added:`/`seen:` spec as a predicate on the Unix-seconds `column`.
+/// Dates cover their whole (local) day, so `>2025-05-01` starts the day
+/// after; ages compare how long ago: `<7d` is newer than a week, a bare
+/// `7d` means `<=7d`.
+fn time_filter(column: &str, spec: &str) -> Result<Filter> {
+    let bound = |op: &str, at: i64| Filter {
+        sql: format!("{column} {op} ?"),
+        params: vec![Value::Integer(at)],
+    };
+    let both = |a: Filter, b: Filter| Filter {
+        sql: format!("{} AND {}", a.sql, b.sql),
+        params: [a.params, b.params].concat(),
+    };
+
+    if let Some((lo, hi)) = spec.split_once("..") {
+        return match (lo.is_empty(), hi.is_empty()) {
+            (true, true) => bail!("range `{spec}` needs at least one date"),
+            (false, true) => Ok(bound(">=", time_span(lo)?.0)),
+            (true, false) => Ok(bound("<", time_span(hi)?.1)),
+            (false, false) => Ok(both(
+                bound(">=", time_span(lo)?.0),
+                bound("<", time_span(hi)?.1),
+            )),
+        };
+    }
+
+    let (op, operand) = split_comparison(spec);
+    if let Ok(age) = crate::utils::parse_duration(operand) {
+        let at = chrono::Local::now().timestamp() - age.as_secs() as i64;
+        // older than the age means earlier than `at`
+        let op = match (op, spec.starts_with('=')) {
+            ("<", _) => ">",
+            ("<=", _) | ("=", false) => ">=",
+            (">", _) => "<",
+            (">=", _) => "<=",
+            _ => bail!("compare ages with <, <=, > or >= (e.g. `<7d`), not `{spec}`"),
+        };
+        return Ok(bound(op, at));
+    }
+    let (start, end) = time_span(operand)?;
+    Ok(match op {
+        ">" => bound(">=", end),
+        ">=" => bound(">=", start),
+        "<" => bound("<", start),
+        "<=" => bound("<", end),
+        "=" => both(bound(">=", start), bound("<", end)),
+        _ => bail!("`{spec}`: dates take <, <=, >, >= or a `lo..hi` range"),
+    })
+}
+
+/// The half-open span of Unix seconds a local `YYYY-MM-DD` (the whole
+/// day) or `YYYY-MM-DDTHH:MM[:SS]` (that minute or second) stands for.
+pub fn time_span(text: &str) -> Result<(i64, i64)> {
+    use chrono::{Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+    let (naive, len) = if let Ok(d) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        (d.and_hms_opt(0, 0, 0).expect("midnight"), Duration::days(1))
+    } else if let Ok(t) = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S") {
+        (t, Duration::seconds(1))
+    } else if let Ok(t) = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M") {
+        (t, Duration::minutes(1))
+    } else {
+        bail!("invalid date `{text}` (expected YYYY-MM-DD[THH:MM[:SS]] or an age like 7d)");
+    };
+    let local = |t: NaiveDateTime| {
+        Local
+            .from_local_datetime(&t)
+            .earliest()
+            // skipped by a DST change: the same wall time in UTC is close
+            .unwrap_or_else(|| Local.from_utc_datetime(&t))
+            .timestamp()
+    };
+    Ok((local(naive), local(naive + len)))
+}
+
+/// `task:` spec as a predicate on the file's tasks.
+fn task_filter(spec: &str) -> Result<Filter> {
+    if spec.eq_ignore_ascii_case("overdue") {
+        return Ok(Filter {
+            sql: "EXISTS (SELECT 1 FROM tasks t WHERE t.file_id = f.id \
+                  AND t.status = 'open' AND t.due_at < ?)"
+                .into(),
+            params: vec![Value::Integer(chrono::Utc::now().timestamp())],
+        });
+    }
+    let status: crate::db::TaskStatus = spec
+        .parse()
+        .map_err(|_| anyhow::anyhow!("expected `task:open`, `task:done` or `task:overdue`"))?;
+    Ok(Filter {
+        sql: "EXISTS (SELECT 1 FROM tasks t WHERE t.file_id = f.id AND t.status = ?)".into(),
+        params: vec![Value::Text(status.as_str().into())],
+    })
+}
+
+/// `missing:` value: whether to match only tombstones (`true`) or only
+/// live files (`false`); `None` for both (`any`).
+fn missing_spec(spec: &str) -> Result<Option<bool>> {
+    match spec.to_lowercase().as_str() {
+        "any" => Ok(None),
+        _ => parse_bool(spec)
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("invalid `missing:{spec}` (expected true, false or any)")),
+    }
+}
+
+fn parse_bool(spec: &str) -> Result<bool> {
+    match spec.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => bail!("invalid boolean `{spec}` (expected true/false)"),
+    }
+}
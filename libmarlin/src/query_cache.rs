@@ -0,0 +1,211 @@
+//! Optional in-process cache for query results.
+//!
+//! Front-ends that re-run the same searches in a loop (TUI refreshes,
+//! `marlin view watch`) can route them through a [`QueryCache`].  Entries
+//! are keyed by the normalised query text, the [`SearchOptions`] and the
+//! connection's data version, so any write – on this connection or, via
+//! `PRAGMA data_version`, on another one – makes older entries unreachable
+//! instead of returning stale results.  They then age out in LRU order.
+//!
+//! A cache must only ever be used with **one** connection: the data
+//! version counters are per connection, so sharing a cache between two of
+//! them could serve one connection's results to the other.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::query::{self, QueryKind, SearchHit, SearchOptions};
+
+/// Entries kept by [`QueryCache::default`].
+pub const DEFAULT_CAPACITY: usize = 128;
+
+/// Hit/miss counters of a [`QueryCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to make room for newer ones.
+    pub evictions: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// Share of lookups answered from the cache (0.0 before the first one).
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    query: String,
+    kind: QueryKind,
+    limit: Option<usize>,
+    offset: usize,
+    fallback: bool,
+    /// Attached shared databases feed `tag:` filters.
+    shared: Vec<String>,
+    version: (i64, i64),
+}
+
+#[derive(Debug)]
+struct Entry {
+    hits: Vec<SearchHit>,
+    last_used: u64,
+}
+
+/// Bounded LRU map from (query, options, data version) to search results.
+#[derive(Debug)]
+pub struct QueryCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, Entry>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl Default for QueryCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl QueryCache {
+    /// A cache holding at most `capacity` result sets (`0` disables
+    /// caching; every lookup is then a miss).
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            tick: 0,
+            stats: CacheStats {
+                capacity,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// [`query::search_hits`], answered from the cache when nothing was
+    /// written since the same query last ran on `conn`.
+    pub fn search_hits(
+        &mut self,
+        conn: &Connection,
+        raw: &str,
+        opts: &SearchOptions,
+    ) -> Result<Vec<SearchHit>> {
+        let key = CacheKey {
+            query: normalize(raw),
+            kind: opts.kind,
+            limit: opts.limit,
+            offset: opts.offset,
+            fallback: opts.fallback,
+            shared: crate::db::shared_schemas(conn)?,
+            version: data_version(conn)?,
+        };
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.tick;
+            self.stats.hits += 1;
+            return Ok(entry.hits.clone());
+        }
+
+        self.stats.misses += 1;
+        let hits = query::search_hits(conn, raw, opts)?;
+        if self.capacity > 0 {
+            if self.entries.len() >= self.capacity {
+                self.evict_oldest();
+            }
+            self.entries.insert(
+                key,
+                Entry {
+                    hits: hits.clone(),
+                    last_used: self.tick,
+                },
+            );
+        }
+        Ok(hits)
+    }
+
+    /// [`query::search`] through the cache.
+    pub fn search(&mut self, conn: &Connection, raw: &str) -> Result<Vec<String>> {
+        Ok(self
+            .search_hits(conn, raw, &SearchOptions::default())?
+            .into_iter()
+            .map(|h| h.path)
+            .collect())
+    }
+
+    /// [`query::view_exec`] through the cache.
+    pub fn view_exec(
+        &mut self,
+        conn: &Connection,
+        name: &str,
+        opts: &SearchOptions,
+    ) -> Result<Vec<SearchHit>> {
+        let raw = crate::db::view_query(conn, name)?;
+        self.search_hits(conn, &raw, opts)
+    }
+
+    /// Current counters.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// Drop every entry (the counters are kept).
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, e)| e.last_used)
+            .map(|(k, _)| k.clone());
+        if let Some(k) = oldest {
+            self.entries.remove(&k);
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+/// Trim the query and collapse runs of whitespace outside double quotes,
+/// so `tag:a  b` and ` tag:a b` share an entry but phrases stay intact.
+pub fn normalize(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut quoted = false;
+    let mut pending_space = false;
+    for c in raw.trim().chars() {
+        if !quoted && c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space {
+            out.push(' ');
+            pending_space = false;
+        }
+        if c == '"' {
+            quoted = !quoted;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// `PRAGMA data_version` (bumped by commits from other connections) paired
+/// with `total_changes()` (rows written through this one).
+pub(crate) fn data_version(conn: &Connection) -> Result<(i64, i64)> {
+    let external: i64 = conn.query_row("PRAGMA data_version", [], |r| r.get(0))?;
+    let own: i64 = conn.query_row("SELECT total_changes()", [], |r| r.get(0))?;
+    Ok((external, own))
+}
@@ -0,0 +1,94 @@
+// libmarlin/src/query_cache_tests.rs
+
+use super::db;
+use super::query::SearchOptions;
+use super::query_cache::{normalize, QueryCache};
+use super::test_utils::{add_file, db_with_files};
+use tempfile::tempdir;
+
+#[test]
+fn repeated_query_is_served_from_cache() {
+    let conn = db_with_files(&["/a/report.txt", "/b/notes.txt"]);
+
+    let mut cache = QueryCache::new(8);
+    let first = cache.search(&conn, "report").unwrap();
+    let second = cache.search(&conn, "  report ").unwrap();
+    assert_eq!(first, vec!["/a/report.txt".to_string()]);
+    assert_eq!(first, second);
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    assert_eq!(stats.hit_rate(), 0.5);
+
+    // different options are a different entry
+    let opts = SearchOptions {
+        limit: Some(1),
+        ..Default::default()
+    };
+    cache.search_hits(&conn, "report", &opts).unwrap();
+    assert_eq!(cache.stats().misses, 2);
+}
+
+#[test]
+fn writes_invalidate_cached_results() {
+    let conn = db_with_files(&["/a/report.txt"]);
+
+    let mut cache = QueryCache::new(8);
+    assert_eq!(cache.search(&conn, "report").unwrap().len(), 1);
+
+    add_file(&conn, "/b/report.md");
+    assert_eq!(cache.search(&conn, "report").unwrap().len(), 2);
+    assert_eq!(cache.stats().hits, 0);
+}
+
+#[test]
+fn commits_from_other_connections_invalidate() {
+    let tmp = tempdir().unwrap();
+    let path = tmp.path().join("index.db");
+    let reader = db::open(&path).unwrap();
+    let writer = db::open(&path).unwrap();
+    add_file(&writer, "/a/report.txt");
+
+    let mut cache = QueryCache::new(8);
+    assert_eq!(cache.search(&reader, "report").unwrap().len(), 1);
+    assert_eq!(cache.search(&reader, "report").unwrap().len(), 1);
+    assert_eq!(cache.stats().hits, 1);
+
+    add_file(&writer, "/b/report.md");
+    assert_eq!(cache.search(&reader, "report").unwrap().len(), 2);
+}
+
+#[test]
+fn least_recently_used_entry_is_evicted() {
+    let conn = db_with_files(&["/a/alpha.txt", "/b/beta.txt", "/c/gamma.txt"]);
+
+    let mut cache = QueryCache::new(2);
+    cache.search(&conn, "alpha").unwrap();
+    cache.search(&conn, "beta").unwrap();
+    cache.search(&conn, "alpha").unwrap(); // beta is now the oldest
+    cache.search(&conn, "gamma").unwrap();
+
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.evictions), (2, 1));
+    cache.search(&conn, "alpha").unwrap();
+    assert_eq!(cache.stats().hits, 2);
+    cache.search(&conn, "beta").unwrap();
+    assert_eq!(cache.stats().hits, 2);
+}
+
+#[test]
+fn zero_capacity_never_stores() {
+    let conn = db_with_files(&["/a/report.txt"]);
+
+    let mut cache = QueryCache::new(0);
+    cache.search(&conn, "report").unwrap();
+    cache.search(&conn, "report").unwrap();
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (0, 2, 0));
+}
+
+#[test]
+fn normalize_collapses_whitespace_outside_quotes() {
+    assert_eq!(normalize("  tag:a   b "), "tag:a b");
+    assert_eq!(normalize("\"two  words\"\tx"), "\"two  words\" x");
+}
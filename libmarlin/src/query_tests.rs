@@ -0,0 +1,530 @@
+// libmarlin/src/query_tests.rs
+
+use super::db;
+use super::query::{self, Query, QueryKind};
+use super::test_utils::db_with_files;
+
+#[test]
+fn parse_translates_tag_and_attr_tokens() {
+    let q = Query::parse("tag:project/md attr:reviewed=yes report").unwrap();
+    assert_eq!(q.fts, "attrs_text:reviewed AND attrs_text:yes report");
+    assert_eq!(q.filters.len(), 1);
+    assert!(q.filters[0].sql.contains("tag_tree"));
+}
+
+#[test]
+fn parse_rating_and_fav_become_filters() {
+    let q = Query::parse("rating:>=4 fav:true").unwrap();
+    assert!(q.fts.is_empty());
+    assert_eq!(q.filters.len(), 2);
+    assert_eq!(q.filters[0].sql, "IFNULL(f.rating, 0) >= ?");
+    assert_eq!(q.filters[1].sql, "f.favorite = ?");
+
+    assert!(Query::parse("rating:7").is_err());
+    assert!(Query::parse("fav:maybe").is_err());
+}
+
+#[test]
+fn parse_drops_operator_left_dangling_by_filter() {
+    let q = Query::parse("report AND rating:5").unwrap();
+    assert_eq!(q.fts, "report");
+    assert_eq!(q.filters.len(), 1);
+}
+
+#[test]
+fn search_combines_fts_with_rating_and_favorite() {
+    let conn = db_with_files(&["/a/report.txt", "/b/report.md", "/c/notes.txt"]);
+    let ids: Vec<i64> = ["/a/report.txt", "/b/report.md", "/c/notes.txt"]
+        .iter()
+        .map(|p| db::file_id(&conn, p).unwrap())
+        .collect();
+    db::set_rating(&conn, ids[0], 5).unwrap();
+    db::set_rating(&conn, ids[1], 2).unwrap();
+    db::set_rating(&conn, ids[2], 4).unwrap();
+    db::set_favorite(&conn, ids[2], true).unwrap();
+
+    let hits = query::search(&conn, "report rating:>=4").unwrap();
+    assert_eq!(hits, vec!["/a/report.txt".to_string()]);
+
+    let hits = query::search(&conn, "rating:>=4").unwrap();
+    assert_eq!(hits.len(), 2);
+
+    let favs = query::search(&conn, "fav:true").unwrap();
+    assert_eq!(favs, vec!["/c/notes.txt".to_string()]);
+}
+
+#[test]
+fn not_negates_a_filter_and_unrated_files_count_as_zero() {
+    let conn = db_with_files(&["/a/report.txt", "/b/report.md", "/c/notes.txt"]);
+    let a = db::file_id(&conn, "/a/report.txt").unwrap();
+    let c = db::file_id(&conn, "/c/notes.txt").unwrap();
+    db::set_rating(&conn, a, 5).unwrap();
+    db::set_rating(&conn, c, 3).unwrap();
+    db::set_favorite(&conn, c, true).unwrap();
+    let s = |q: &str| query::search(&conn, q).unwrap();
+
+    assert_eq!(s("NOT rating:5"), vec!["/b/report.md", "/c/notes.txt"]);
+    assert_eq!(s("report NOT rating:5"), vec!["/b/report.md"]);
+    assert_eq!(s("fav:true NOT rating:5"), vec!["/c/notes.txt"]);
+    assert_eq!(s("rating:0"), vec!["/b/report.md"]);
+    assert_eq!(s("rating:<1"), vec!["/b/report.md"]);
+    assert_eq!(s("rating:5 OR fav:true").len(), 2);
+
+    // no SQL form: a search term OR a filter
+    for bad in ["report OR fav:true", "report fav:true OR notes", "NOT"] {
+        assert!(Query::parse(bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn content_token_searches_only_file_text() {
+    let conn = db_with_files(&["/docs/forecast.md", "/docs/plan.md"]);
+    conn.execute(
+        "INSERT INTO file_text(file_id, body)
+         SELECT id, 'quarterly forecast numbers' FROM files WHERE path = '/docs/plan.md'",
+        [],
+    )
+    .unwrap();
+    let paths = |q: &str| -> Vec<String> {
+        query::search_hits(
+            &conn,
+            q,
+            &query::SearchOptions {
+                fallback: false,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .into_iter()
+        .map(|h| h.path)
+        .collect()
+    };
+    assert_eq!(paths("content:forecast"), ["/docs/plan.md"]);
+    assert_eq!(paths("forecast").len(), 2);
+    assert_eq!(paths("quarterly"), ["/docs/plan.md"]);
+}
+
+#[test]
+fn escape_fts_quotes_terms() {
+    assert_eq!(query::escape_fts("foo"), "foo");
+    assert_eq!(query::escape_fts("foo bar"), "\"foo bar\"");
+    assert_eq!(query::escape_fts("NEAR"), "\"NEAR\"");
+}
+
+#[test]
+fn linked_to_and_backlink_of_join_over_links() {
+    let conn = db_with_files(&["/docs/report.md", "/img/chart.png", "/img/cat.png"]);
+    let report = db::file_id(&conn, "/docs/report.md").unwrap();
+    let chart = db::file_id(&conn, "/img/chart.png").unwrap();
+    let tag = db::ensure_tag_path(&conn, "report/2025").unwrap();
+    conn.execute(
+        "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+        [report, tag],
+    )
+    .unwrap();
+    db::add_link(&conn, report, chart, Some("embeds")).unwrap();
+
+    let hits = query::search(&conn, "linked_to:(tag:report/2025)").unwrap();
+    assert_eq!(hits, vec!["/img/chart.png".to_string()]);
+
+    let hits = query::search(&conn, "backlink_of:/img/chart.png").unwrap();
+    assert_eq!(hits, vec!["/docs/report.md".to_string()]);
+
+    // nested query with whitespace stays one token
+    let q = Query::parse("linked_to:(tag:report OR tag:other) png").unwrap();
+    assert_eq!(q.fts, "png");
+    assert_eq!(q.filters.len(), 1);
+}
+
+#[test]
+fn view_files_runs_saved_query() {
+    let conn = db_with_files(&["/a/report.txt", "/b/notes.txt"]);
+    let id = db::file_id(&conn, "/a/report.txt").unwrap();
+    db::set_favorite(&conn, id, true).unwrap();
+    db::save_view(&conn, "favs", "fav:true").unwrap();
+
+    let files = query::view_files(&conn, "favs").unwrap();
+    assert_eq!(files, vec![(id, "/a/report.txt".to_string())]);
+    assert!(query::view_files(&conn, "missing").is_err());
+}
+
+#[test]
+fn tag_matches_descendants_unless_exact() {
+    let conn = db_with_files(&["/a.txt", "/b.txt", "/c.txt", "/d.txt"]);
+    for (path, tag) in [
+        ("/a.txt", "project"),
+        ("/b.txt", "project/md"),
+        ("/c.txt", "projects"),
+        ("/d.txt", "work/project"),
+    ] {
+        let tag_id = db::ensure_tag_path(&conn, tag).unwrap();
+        conn.execute(
+            "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+            [db::file_id(&conn, path).unwrap(), tag_id],
+        )
+        .unwrap();
+    }
+    let s = |q: &str| query::search(&conn, q).unwrap();
+
+    assert_eq!(s("tag:project"), vec!["/a.txt", "/b.txt"]);
+    assert_eq!(s("tag:Project/"), vec!["/a.txt", "/b.txt"]);
+    assert_eq!(s("tag:=project"), vec!["/a.txt"]);
+    assert_eq!(s("tag:project/md"), vec!["/b.txt"]);
+    assert_eq!(s("-tag:=project"), vec!["/b.txt", "/c.txt", "/d.txt"]);
+
+    // `OR` between two filters joins them
+    assert_eq!(s("tag:=project OR tag:projects"), vec!["/a.txt", "/c.txt"]);
+    assert_eq!(query::lint("tag:a OR tag:b"), vec![]);
+    assert!(query::lint("tag:a OR report")[0].is_error());
+}
+
+#[test]
+fn negated_tag_excludes_tag_and_descendants() {
+    let conn = db_with_files(&["/a.txt", "/b.txt", "/c.txt", "/d.txt"]);
+    for (path, tag) in [
+        ("/a.txt", "keep"),
+        ("/b.txt", "keep/forever"),
+        ("/c.txt", "keeper"),
+    ] {
+        let tag_id = db::ensure_tag_path(&conn, tag).unwrap();
+        conn.execute(
+            "INSERT INTO file_tags(file_id, tag_id) VALUES (?1, ?2)",
+            [db::file_id(&conn, path).unwrap(), tag_id],
+        )
+        .unwrap();
+    }
+
+    let hits = query::search(&conn, "-tag:keep").unwrap();
+    assert_eq!(hits, vec!["/c.txt".to_string(), "/d.txt".to_string()]);
+}
+
+#[test]
+fn lint_points_at_the_offending_column() {
+    let d = query::lint("report \"draft notes");
+    assert_eq!(d.len(), 1);
+    assert!(d[0].is_error());
+    assert_eq!(d[0].column, Some(8));
+    assert!(d[0].to_string().starts_with("unbalanced quote at column 8"));
+
+    let d = query::lint("png tags:report");
+    assert_eq!(
+        d[0].to_string(),
+        "unknown field 'tags' at column 5 (did you mean `tag:`?)"
+    );
+
+    let d = query::lint("rating:9");
+    assert!(d[0].message.starts_with("invalid rating `9`"));
+
+    // positions inside a sub-query are relative to the whole query
+    let d = query::lint("png linked_to:(tag:a OR lable:red)");
+    assert_eq!(d[0].column, Some(25));
+    assert_eq!(d[0].hint.as_deref(), Some("did you mean `label:`?"));
+    assert_eq!(
+        query::lint("linked_to:(tag:a").first().map(|d| d.column),
+        Some(Some(11))
+    );
+}
+
+#[test]
+fn lint_accepts_plain_terms_that_only_look_like_fields() {
+    for ok in [
+        "report",
+        "tag:project/md attr:status=new rating:>=4 -tag:keep",
+        "\"foo:bar\" https://example.com C:\\temp 10:30",
+        "png linked_to:(tag:a OR tag:b)",
+    ] {
+        assert_eq!(query::lint(ok), vec![], "{ok}");
+    }
+
+    // an apostrophe only warns; the query still runs as one phrase
+    let d = query::lint("don't panic");
+    assert_eq!(d.len(), 1);
+    assert!(!d[0].is_error());
+    assert!(Query::parse("don't panic").is_ok());
+}
+
+#[test]
+fn lint_checks_operator_placement() {
+    let d = query::lint("OR report");
+    assert_eq!(d[0].to_string(), "`OR` has no left-hand term at column 1");
+
+    let d = query::lint("report AND OR draft");
+    assert_eq!(d[0].to_string(), "`OR` directly follows `AND` at column 12");
+
+    // `OR` cannot join a term and a filter; a trailing operator is only
+    // dropped, so that is a warning
+    let d = query::lint("report OR rating:5 draft AND");
+    assert_eq!(d.len(), 2);
+    assert!(d[0].is_error());
+    assert_eq!(d[0].column, Some(8));
+    assert!(!d[1].is_error());
+    assert_eq!(d[1].column, Some(26));
+    assert_eq!(query::lint("NOT rating:5"), vec![]);
+    assert_eq!(query::lint("tag:a NOT tag:b"), vec![]);
+
+    assert!(query::lint("rating:5 OR report")[0].is_error());
+}
+
+#[test]
+fn parse_rejects_queries_with_errors() {
+    let err = Query::parse("tags:report").unwrap_err();
+    assert!(err.to_string().contains("unknown field 'tags'"));
+    assert!(Query::parse("\"foo:bar\"").is_ok());
+}
+
+#[test]
+fn check_runs_fts_expression_through_sqlite() {
+    let conn = db_with_files(&[]);
+    assert!(query::check(&conn, "report tag:draft").unwrap().is_empty());
+    assert!(query::check(&conn, "tags:x").unwrap()[0].is_error());
+
+    // passes the lint, but FTS5 has no `*` on its own
+    let d = query::check(&conn, "*").unwrap();
+    assert_eq!(d.len(), 1);
+    assert!(d[0].message.starts_with("SQLite rejected"));
+}
+
+#[test]
+fn literal_queries_ignore_query_syntax() {
+    let conn = db_with_files(&["/a/foo:bar (baz).txt", "/a/foo.txt", "/b/AND OR.md"]);
+    let literal = query::SearchOptions {
+        kind: QueryKind::Literal,
+        ..Default::default()
+    };
+    let paths = |raw: &str| -> Vec<String> {
+        query::search_hits(&conn, raw, &literal)
+            .unwrap()
+            .into_iter()
+            .map(|h| h.path)
+            .collect()
+    };
+
+    assert!(Query::parse("foo:bar (baz)").is_err());
+    assert_eq!(paths("foo:bar (baz)"), vec!["/a/foo:bar (baz).txt"]);
+    assert_eq!(paths("AND OR"), vec!["/b/AND OR.md"]);
+    assert_eq!(
+        Query::parse_as("say \"hi\"", QueryKind::Literal)
+            .unwrap()
+            .fts,
+        "\"say \"\"hi\"\"\""
+    );
+    assert!(Query::parse_as("  ", QueryKind::Literal).is_err());
+}
+
+#[test]
+fn path_glob_filters_by_location() {
+    let conn = db_with_files(&[
+        "/home/me/reports/q1.pdf",
+        "/home/me/reports/2025/q2.pdf",
+        "/home/me/reports/2025/q2.md",
+        "/home/me/other/q3.pdf",
+    ]);
+
+    let hits = query::search(&conn, "path:**/reports/**/*.pdf").unwrap();
+    assert_eq!(
+        hits,
+        vec![
+            "/home/me/reports/2025/q2.pdf".to_string(),
+            "/home/me/reports/q1.pdf".to_string(),
+        ]
+    );
+
+    // combined with FTS, and relative globs match below any directory
+    let hits = query::search(&conn, "q2 path:reports/2025/*").unwrap();
+    assert_eq!(hits.len(), 2);
+    let hits = query::search(&conn, "path:q3.pdf").unwrap();
+    assert_eq!(hits, vec!["/home/me/other/q3.pdf".to_string()]);
+
+    let q = Query::parse("path:/a/**/b/**/c").unwrap();
+    assert_eq!(q.filters[0].params.len(), 4);
+    assert!(query::lint(&format!("path:{}x", "**/".repeat(9)))[0].is_error());
+}
+
+#[test]
+fn attr_operators_compare_against_attributes_table() {
+    let conn = db_with_files(&["/a.txt", "/b.txt", "/c.txt"]);
+    for (path, key, value) in [
+        ("/a.txt", "vendor", "ACME Corp"),
+        ("/b.txt", "vendor", "Globex"),
+        ("/a.txt", "version", "1.2"),
+        ("/b.txt", "version", "1.10"),
+        ("/c.txt", "version", "n/a"),
+        ("/a.txt", "due", "2025-06-30"),
+        ("/b.txt", "due", "2025-07-02"),
+    ] {
+        db::upsert_attr(&conn, db::file_id(&conn, path).unwrap(), key, value).unwrap();
+    }
+    let s = |q: &str| query::search(&conn, q).unwrap();
+
+    assert_eq!(s("attr:vendor~=acme"), vec!["/a.txt"]);
+    assert_eq!(s("attr:vendor^=glo"), vec!["/b.txt"]);
+    assert_eq!(s("attr:vendor^=corp"), Vec::<String>::new());
+
+    // numbers compare numerically; `n/a` is not a number
+    assert_eq!(s("attr:version>=1.5"), Vec::<String>::new());
+    assert_eq!(s("attr:version<2"), vec!["/a.txt", "/b.txt"]);
+    assert_eq!(s("attr:version!=1.2"), vec!["/b.txt"]);
+
+    // dates compare as text
+    assert_eq!(s("attr:due..2025-07-01"), vec!["/a.txt"]);
+    assert_eq!(s("attr:due=2025-07-01.."), vec!["/b.txt"]);
+    assert_eq!(s("attr:due=2025-01-01..2025-12-31").len(), 2);
+
+    // `=` without a range is still the FTS form
+    let q = Query::parse("attr:vendor=acme").unwrap();
+    assert!(q.filters.is_empty());
+    let q = Query::parse("report AND attr:vendor~=acme").unwrap();
+    assert_eq!((q.fts.as_str(), q.filters.len()), ("report", 1));
+    assert!(Query::parse("report OR attr:vendor~=acme").is_err());
+
+    assert!(query::lint("attr:~=x")[0].is_error());
+    assert!(query::lint("attr:due=..")[0].is_error());
+    assert!(query::lint("attr:vendor~=")[0].is_error());
+}
+
+#[test]
+fn lang_matches_the_detected_language() {
+    let conn = db_with_files(&["/a.txt", "/b.txt"]);
+    for (path, lang) in [("/a.txt", "de"), ("/b.txt", "en")] {
+        db::upsert_attr(&conn, db::file_id(&conn, path).unwrap(), "meta/lang", lang).unwrap();
+    }
+    assert_eq!(query::search(&conn, "lang:de").unwrap(), vec!["/a.txt"]);
+    assert_eq!(query::search(&conn, "lang:EN").unwrap(), vec!["/b.txt"]);
+    assert!(query::search(&conn, "lang:fr").unwrap().is_empty());
+
+    assert!(query::lint("lang:german")[0].is_error());
+    assert!(query::lint("lang:de").is_empty());
+}
+
+#[test]
+fn search_across_merges_hits_with_source_labels() {
+    let work = db_with_files(&["/w/report.txt", "/w/notes.txt"]);
+    let home = db_with_files(&["/h/report-taxes.txt", "/h/report.txt"]);
+    let sources = [("work", &work), ("home", &home)];
+
+    let hits = query::search_across(&sources, "report", &query::SearchOptions::default()).unwrap();
+    let got: Vec<(&str, &str)> = hits
+        .iter()
+        .map(|h| (h.source.as_str(), h.hit.path.as_str()))
+        .collect();
+    assert_eq!(got.len(), 3);
+    assert!(got.contains(&("work", "/w/report.txt")));
+    assert!(got.contains(&("home", "/h/report-taxes.txt")));
+    assert!(hits.windows(2).all(|w| w[0].rank <= w[1].rank));
+
+    // paging applies to the merged list
+    let opts = query::SearchOptions {
+        offset: 1,
+        limit: Some(1),
+        ..Default::default()
+    };
+    let page = query::search_across(&sources, "report", &opts).unwrap();
+    assert_eq!(page, hits[1..2]);
+
+    // filter-only queries have no rank and merge by path
+    let hits = query::search_across(&sources, "path:**/notes.txt", &Default::default()).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].source, "work");
+}
+
+#[test]
+fn added_and_seen_filter_by_date_and_age() {
+    let conn = db_with_files(&["/old.md", "/may.md", "/new.md", "/legacy.md"]);
+    let day = |d: &str| {
+        chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .unwrap()
+            .timestamp()
+    };
+    let now = chrono::Local::now().timestamp();
+    for (path, added, seen) in [
+        ("/old.md", day("2024-01-10"), day("2024-02-01")),
+        ("/may.md", day("2025-05-01"), now),
+        ("/new.md", now - 3_600, now),
+    ] {
+        conn.execute(
+            "UPDATE files SET first_indexed_at = ?2, last_seen_at = ?3 WHERE path = ?1",
+            rusqlite::params![path, added, seen],
+        )
+        .unwrap();
+    }
+    let s = |q: &str| {
+        let mut hits = query::search(&conn, q).unwrap();
+        hits.sort();
+        hits
+    };
+
+    assert_eq!(s("added:>2025-05-01"), ["/new.md"]);
+    assert_eq!(s("added:>=2025-05-01"), ["/may.md", "/new.md"]);
+    assert_eq!(s("added:2025-05-01"), ["/may.md"]);
+    assert_eq!(s("added:<=2025-05-01"), ["/may.md", "/old.md"]);
+    assert_eq!(s("added:2024-01-01..2024-12-31"), ["/old.md"]);
+    assert_eq!(s("added:..2024-01-10"), ["/old.md"]);
+    assert_eq!(s("added:2025-05-01T12:00"), ["/may.md"]);
+    assert_eq!(s("added:1d"), ["/new.md"]);
+    assert_eq!(s("seen:>30d"), ["/old.md"]);
+    // unknown times (indexed before they were recorded) match nothing
+    assert!(!s("added:<2100-01-01").contains(&"/legacy.md".to_string()));
+}
+
+#[test]
+fn lint_rejects_malformed_times() {
+    for bad in [
+        "added:yesterday",
+        "seen:=7d",
+        "added:!=2025-01-01",
+        "added:..",
+    ] {
+        let diags = query::lint(bad);
+        assert!(diags.iter().any(|d| d.is_error()), "{bad}: {diags:?}");
+    }
+    assert!(query::lint("added:>=2025-05-01 seen:<7d").is_empty());
+}
+
+#[test]
+fn search_incremental_narrows_the_previous_hits() {
+    let conn = db_with_files(&[
+        "/docs/report-2024.md",
+        "/docs/report-2025.md",
+        "/docs/notes.md",
+    ]);
+    let paths = |rs: &query::ResultSet| {
+        let mut p: Vec<String> = rs.hits.iter().map(|h| h.path.clone()).collect();
+        p.sort();
+        p
+    };
+
+    let first = query::search_incremental(&conn, None, "docs").unwrap();
+    assert_eq!(first.hits.len(), 3);
+    let refined = query::search_incremental(&conn, Some(&first), "docs  report").unwrap();
+    assert_eq!(refined.query, "docs report");
+    assert_eq!(
+        paths(&refined),
+        ["/docs/report-2024.md", "/docs/report-2025.md"]
+    );
+
+    // the refinement only looks at what `prev` holds …
+    let mut narrowed = first.clone();
+    narrowed.hits.retain(|h| h.path.ends_with("2025.md"));
+    let r = query::search_incremental(&conn, Some(&narrowed), "docs report").unwrap();
+    assert_eq!(paths(&r), ["/docs/report-2025.md"]);
+
+    // … but `OR` can widen the result, so it runs in full
+    let r = query::search_incremental(&conn, Some(&narrowed), "docs OR notes").unwrap();
+    assert_eq!(r.hits.len(), 3);
+    // as does a query that is not an extension of the previous one
+    let r = query::search_incremental(&conn, Some(&narrowed), "report").unwrap();
+    assert_eq!(r.hits.len(), 2);
+
+    // and anything written since makes `prev` stale
+    conn.execute(
+        "INSERT INTO files(path, size, mtime) VALUES ('/docs/report-2026.md', 0, 0)",
+        [],
+    )
+    .unwrap();
+    let r = query::search_incremental(&conn, Some(&narrowed), "docs report").unwrap();
+    assert_eq!(r.hits.len(), 3);
+}
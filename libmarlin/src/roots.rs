@@ -0,0 +1,160 @@
+//! Per-root indexing profiles.
+//!
+//! A root is a directory registered with `marlin root add`; its profile
+//! controls how everything below it is indexed, so a source tree and a
+//! photo archive can share one database but behave differently:
+//!
+//! * `ignore` – glob patterns (relative to the root) that `scan` and
+//!   `watch` skip, e.g. `target/*` or `*.tmp`
+//! * `index_content` – whether file bodies may be read for search
+//! * `hash` – store a SHA-256 of every file during `scan`
+//! * `debounce_ms` – watcher debounce window for this root
+//!
+//! Paths outside every registered root use [`RootProfile::default_for`].
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::db::Mutation;
+use crate::globs::FileGlob;
+
+/// Indexing settings for one root directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootProfile {
+    pub path: PathBuf,
+    pub ignore: Vec<String>,
+    pub index_content: bool,
+    pub hash: bool,
+    pub debounce_ms: Option<u64>,
+}
+
+impl RootProfile {
+    /// The built-in behaviour: nothing ignored, content searchable, no
+    /// hashing, default debounce.
+    pub fn default_for(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ignore: Vec::new(),
+            index_content: true,
+            hash: false,
+            debounce_ms: None,
+        }
+    }
+
+    /// Check the ignore globs so bad patterns fail at `root add` time.
+    pub fn validate(&self) -> Result<()> {
+        for g in &self.ignore {
+            if let Err(e) = FileGlob::new(g) {
+                bail!("invalid ignore glob `{g}`: {e:#}");
+            }
+        }
+        Ok(())
+    }
+
+    /// `true` when `path` lies below this root and matches an ignore glob.
+    ///
+    /// Globs are matched against the path relative to the root and each of
+    /// its parent directories; `*` also crosses `/`. So `*.log` ignores log
+    /// files at any depth and `node_modules` everything below such a
+    /// directory.
+    pub fn ignores(&self, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.path) else {
+            return false;
+        };
+        self.ignore.iter().any(|g| {
+            FileGlob::new(g).is_ok_and(|p| {
+                p.matches_path(rel)
+                    || rel
+                        .ancestors()
+                        .skip(1)
+                        .any(|a| !a.as_os_str().is_empty() && p.matches_path(a))
+            })
+        })
+    }
+}
+
+/// Insert or replace the profile for `profile.path`.
+pub fn save(conn: &Connection, profile: &RootProfile) -> Result<()> {
+    profile.validate()?;
+    let m = Mutation::new("save_root").path(&profile.path.to_string_lossy());
+    let n = conn.execute(
+        "INSERT INTO roots(path, ignore_globs, index_content, hash_files, debounce_ms)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(path) DO UPDATE SET
+             ignore_globs  = excluded.ignore_globs,
+             index_content = excluded.index_content,
+             hash_files    = excluded.hash_files,
+             debounce_ms   = excluded.debounce_ms",
+        params![
+            profile.path.to_string_lossy(),
+            profile.ignore.join("\n"),
+            profile.index_content,
+            profile.hash,
+            profile.debounce_ms.map(|d| d as i64),
+        ],
+    )?;
+    m.done(n);
+    Ok(())
+}
+
+/// Forget the profile for `path`. Returns `false` if none was stored.
+pub fn remove(conn: &Connection, path: &Path) -> Result<bool> {
+    let m = Mutation::new("remove_root").path(&path.to_string_lossy());
+    Ok(m.done(conn.execute(
+        "DELETE FROM roots WHERE path = ?1",
+        [path.to_string_lossy()],
+    )?) > 0)
+}
+
+/// All registered roots, sorted by path.
+pub fn list(conn: &Connection) -> Result<Vec<RootProfile>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, ignore_globs, index_content, hash_files, debounce_ms
+           FROM roots ORDER BY path",
+    )?;
+    let rows = stmt.query_map([], row_to_profile)?;
+    Ok(rows.collect::<std::result::Result<_, _>>()?)
+}
+
+/// The stored profile for exactly `path`.
+pub fn get(conn: &Connection, path: &Path) -> Result<Option<RootProfile>> {
+    Ok(conn
+        .query_row(
+            "SELECT path, ignore_globs, index_content, hash_files, debounce_ms
+               FROM roots WHERE path = ?1",
+            [path.to_string_lossy()],
+            row_to_profile,
+        )
+        .optional()?)
+}
+
+/// The profile governing `path`: the registered root that contains it
+/// most specifically, if any.
+pub fn profile_for(conn: &Connection, path: &Path) -> Result<Option<RootProfile>> {
+    Ok(innermost(&list(conn)?, path).cloned())
+}
+
+/// Pick the innermost root containing `path` out of `roots`.
+pub fn innermost<'a>(roots: &'a [RootProfile], path: &Path) -> Option<&'a RootProfile> {
+    roots
+        .iter()
+        .filter(|r| path.starts_with(&r.path))
+        .max_by_key(|r| r.path.components().count())
+}
+
+fn row_to_profile(r: &rusqlite::Row<'_>) -> rusqlite::Result<RootProfile> {
+    let globs: String = r.get(1)?;
+    Ok(RootProfile {
+        path: PathBuf::from(r.get::<_, String>(0)?),
+        ignore: globs
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect(),
+        index_content: r.get(2)?,
+        hash: r.get(3)?,
+        debounce_ms: r.get::<_, Option<i64>>(4)?.map(|d| d as u64),
+    })
+}
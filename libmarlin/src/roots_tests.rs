@@ -0,0 +1,92 @@
+// libmarlin/src/roots_tests.rs
+
+use super::db;
+use super::roots::{self, RootProfile};
+use std::path::Path;
+
+#[test]
+fn save_list_get_and_remove_roundtrip() {
+    let conn = db::open(":memory:").unwrap();
+    let mut code = RootProfile::default_for("/src");
+    code.ignore = vec!["target".into(), "*.o".into()];
+    code.debounce_ms = Some(500);
+    let mut photos = RootProfile::default_for("/photos");
+    photos.index_content = false;
+    photos.hash = true;
+
+    roots::save(&conn, &code).unwrap();
+    roots::save(&conn, &photos).unwrap();
+    assert_eq!(
+        roots::list(&conn).unwrap(),
+        vec![photos.clone(), code.clone()]
+    );
+
+    // saving again replaces the profile
+    code.ignore.clear();
+    roots::save(&conn, &code).unwrap();
+    assert_eq!(roots::get(&conn, Path::new("/src")).unwrap(), Some(code));
+
+    assert!(roots::remove(&conn, Path::new("/photos")).unwrap());
+    assert!(!roots::remove(&conn, Path::new("/photos")).unwrap());
+    assert_eq!(roots::list(&conn).unwrap().len(), 1);
+}
+
+#[test]
+fn invalid_glob_is_rejected() {
+    let conn = db::open(":memory:").unwrap();
+    let mut p = RootProfile::default_for("/src");
+    p.ignore = vec!["[".into()];
+    assert!(roots::save(&conn, &p).is_err());
+}
+
+#[test]
+fn ignores_matches_relative_paths_and_parent_dirs() {
+    let mut p = RootProfile::default_for("/src");
+    p.ignore = vec!["target".into(), "*.o".into(), "docs/*.tmp".into()];
+
+    assert!(p.ignores(Path::new("/src/target/debug/app")));
+    assert!(p.ignores(Path::new("/src/lib/deep/x.o")));
+    assert!(p.ignores(Path::new("/src/docs/draft.tmp")));
+    assert!(!p.ignores(Path::new("/src/lib/target.rs")));
+    assert!(!p.ignores(Path::new("/src/notes.tmp")));
+    assert!(!p.ignores(Path::new("/elsewhere/target/x")));
+}
+
+#[test]
+fn innermost_root_wins() {
+    let outer = RootProfile::default_for("/home");
+    let inner = RootProfile::default_for("/home/me/src");
+    let all = [outer, inner.clone()];
+    assert_eq!(
+        roots::innermost(&all, Path::new("/home/me/src/main.rs")),
+        Some(&inner)
+    );
+    assert_eq!(
+        roots::innermost(&all, Path::new("/home/me/a.txt")).map(|r| r.path.as_path()),
+        Some(Path::new("/home"))
+    );
+    assert_eq!(roots::innermost(&all, Path::new("/tmp/x")), None);
+}
+
+#[test]
+fn content_off_root_is_not_searched_by_body() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    std::fs::write(root.join("a.txt"), "the secret word").unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    super::scan::scan_directory(&mut conn, &root).unwrap();
+    assert_eq!(
+        super::query::naive_substring_search(&conn, "secret")
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let mut p = RootProfile::default_for(&root);
+    p.index_content = false;
+    roots::save(&conn, &p).unwrap();
+    assert!(super::query::naive_substring_search(&conn, "secret")
+        .unwrap()
+        .is_empty());
+}
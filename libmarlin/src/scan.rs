@@ -1,67 +1,611 @@
 // src/scan.rs
 
-use std::fs;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
-use rusqlite::{params, Connection};
-use tracing::{debug, info};
-use walkdir::WalkDir;
+use rusqlite::{params, Connection, OptionalExtension, Statement};
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, info};
+
+use crate::db::{self, Identity, IndexOptions, Mutation};
+use crate::events::{self, Event};
+use crate::ignore_files::IgnoreFiles;
+use crate::lang;
+use crate::prune::{self, Cascade};
+use crate::roots::{self, RootProfile};
+use crate::utils::{normalize_path, read_text_sample, PathNorm};
+use crate::vfs::{FileMeta, StdFs, Vfs};
+
+/// Bytes of a file looked at before its whole text is read.
+const TEXT_PROBE_BYTES: u64 = 8 * 1024;
+
+/// Options for [`scan_directory_opts`].
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Skip what `.gitignore` and `.marlinignore` files exclude (see
+    /// [`crate::ignore_files`])
+    pub ignore_files: bool,
+
+    /// After indexing, drop the indexed files below the root that are
+    /// gone from disk, with their tags, attributes, links, … (see
+    /// [`crate::prune`])
+    pub prune: bool,
+
+    /// Prune by keeping missing files as tombstones instead
+    pub keep_metadata: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            ignore_files: true,
+            prune: false,
+            keep_metadata: false,
+        }
+    }
+}
+
+/// What [`scan_directory_opts`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanReport {
+    /// Files written or confirmed unchanged.
+    pub indexed: usize,
+    /// Files pruned (or kept as tombstones) and the metadata on them.
+    pub pruned: Cascade,
+}
 
 /// Recursively walk `root` and upsert file metadata.
 /// Triggers keep the FTS table in sync.
+///
+/// Paths excluded by `.gitignore` and `.marlinignore` files are skipped,
+/// as are `.git` directories; see [`ScanOptions`] to index them anyway,
+/// or to prune files gone from disk.
+/// Registered root profiles (see [`crate::roots`]) apply to everything
+/// below them: ignored paths are skipped and `hash` roots get a SHA-256
+/// stored in `files.hash`.  The text of UTF-8 files up to the default
+/// [`IndexOptions::max_size`] is indexed for full-text search, except
+/// below roots with content indexing turned off.  Under [`Identity::Content`] every file is
+/// hashed, since aliases are found by hash.
 pub fn scan_directory(conn: &mut Connection, root: &Path) -> Result<usize> {
-    // Begin a transaction so we batch many inserts/updates together
-    let tx = conn.transaction()?;
-
-    // Prepare the upsert statement once
-    let mut stmt = tx.prepare(
-        r#"
-        INSERT INTO files(path, size, mtime)
-        VALUES (?1, ?2, ?3)
-        ON CONFLICT(path) DO UPDATE
-            SET size  = excluded.size,
-                mtime = excluded.mtime
-        "#,
-    )?;
+    scan_directory_with(conn, &StdFs, root)
+}
+
+/// [`scan_directory`] over any [`Vfs`].
+pub fn scan_directory_with(conn: &mut Connection, fs: &dyn Vfs, root: &Path) -> Result<usize> {
+    Ok(scan_directory_opts(conn, fs, root, &ScanOptions::default())?.indexed)
+}
+
+/// [`scan_directory_with`] with explicit [`ScanOptions`].
+pub fn scan_directory_opts(
+    conn: &mut Connection,
+    fs: &dyn Vfs,
+    root: &Path,
+    opts: &ScanOptions,
+) -> Result<ScanReport> {
+    let profiles = roots::list(conn)?;
+    let by_content = db::identity(conn)? == Identity::Content;
 
+    // Batch inserts/updates in a savepoint: its own transaction, or part
+    // of the caller's (`marlin run --atomic`) if one is open
+    let tx = conn.savepoint()?;
+    let mut upsert = Upsert::new(&tx, false, IndexOptions::default().max_size)?;
     let mut count = 0usize;
 
-    // Walk the directory recursively
-    for entry in WalkDir::new(root)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
+    let files = indexable_files(fs, root, &profiles, opts.ignore_files);
+    let total = files.len();
+    events::emit(Event::ScanStarted {
+        root: root.to_path_buf(),
+        total,
+    });
+
+    for (path, profile) in &files {
+        let meta = fs.metadata(path)?;
+        let hash = by_content || profile.is_some_and(|r| r.hash);
+        let read_body = profile.is_none_or(|r| r.index_content);
+        let path_str = upsert.file(fs, path, meta, hash, read_body)?;
+        count += 1;
 
-        // Skip the database file and its WAL/SHM siblings
-        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-            if name.ends_with(".db") || name.ends_with("-wal") || name.ends_with("-shm") {
+        debug!(file = %path_str, "indexed");
+        if count.is_multiple_of(events::SCAN_PROGRESS_EVERY) {
+            events::emit(Event::ScanProgress {
+                root: root.to_path_buf(),
+                indexed: count,
+                total,
+            });
+        }
+    }
+
+    // Finalize and commit
+    drop(upsert);
+    tx.commit()?;
+
+    let mut pruned = Cascade::default();
+    if opts.prune {
+        // tombstones are only candidates when they are to be deleted
+        let missing = prune::missing_files(conn, fs, &[root.to_path_buf()], !opts.keep_metadata)?;
+        let ids: Vec<i64> = missing.iter().map(|(id, _)| *id).collect();
+        if !ids.is_empty() {
+            pruned = prune::drop_files(conn, &ids, opts.keep_metadata)?;
+        }
+    }
+
+    info!(indexed = count, pruned = pruned.files, "scan complete");
+    events::emit(Event::ScanFinished {
+        root: root.to_path_buf(),
+        indexed: count,
+    });
+    Ok(ScanReport {
+        indexed: count,
+        pruned,
+    })
+}
+
+/// Index individual files (directories are walked like a scan) with the
+/// given [`IndexOptions`]; backs [`crate::db::Database::index_files`].
+/// Ignore files apply as in a scan.
+///
+/// Paths that vanished are skipped. With `dirty_only`, only files marked
+/// by [`crate::db::mark_dirty`] are touched and their marks are cleared.
+pub(crate) fn index_files_with(
+    conn: &mut Connection,
+    fs: &dyn Vfs,
+    paths: &[PathBuf],
+    opts: &IndexOptions,
+) -> Result<usize> {
+    let profiles = roots::list(conn)?;
+    let by_content = db::identity(conn)? == Identity::Content;
+    let m = Mutation::new("index_files");
+    let tx = conn.savepoint()?;
+    let mut upsert = Upsert::new(&tx, opts.force, opts.max_size)?;
+    let mut undirty = tx.prepare(
+        "DELETE FROM file_changes WHERE file_id = (SELECT id FROM files WHERE path = ?1)",
+    )?;
+    let mut hashed = tx.prepare("SELECT 1 FROM files WHERE path = ?1 AND hash IS NOT NULL")?;
+    let mut count = 0usize;
+
+    for root in paths {
+        for (path, profile) in indexable_files(fs, root, &profiles, true) {
+            let key = normalize_path(&path, &PathNorm::default());
+            if opts.dirty_only && undirty.execute([&key])? == 0 {
                 continue;
             }
+            let Ok(meta) = fs.metadata(&path) else {
+                debug!(file = %path.display(), "vanished before indexing");
+                continue;
+            };
+            // bodies are only read when contents are wanted and small enough
+            let wants_hash = by_content
+                || profile.is_some_and(|r| r.hash)
+                || (opts.force && hashed.exists([&key])?);
+            let may_read = opts.index_contents && opts.max_size.is_none_or(|max| meta.len <= max);
+            let hash = may_read && wants_hash;
+            let read_body = may_read && profile.is_none_or(|r| r.index_content);
+            let path_str = upsert.file(fs, &path, meta, hash, read_body)?;
+            count += 1;
+            debug!(file = %path_str, "indexed");
         }
+    }
+
+    drop(undirty);
+    drop(hashed);
+    drop(upsert);
+    tx.commit()?;
+    Ok(m.done(count))
+}
+
+/// Re-read metadata and hashes of `paths` regardless of what the index
+/// already holds – after extractor upgrades or suspected corruption.
+/// Stored hashes are recomputed even outside `hash` roots and no size cap
+/// applies.  Vanished files are skipped; returns the number refreshed.
+pub fn reindex_files<P: AsRef<Path>>(conn: &mut Connection, paths: &[P]) -> Result<usize> {
+    let paths: Vec<PathBuf> = paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+    let opts = IndexOptions {
+        force: true,
+        max_size: None,
+        ..Default::default()
+    };
+    let n = index_files_with(conn, &StdFs, &paths, &opts)?;
+    info!(reindexed = n, "forced reindex");
+    Ok(n)
+}
+
+/// Every file below `root` that belongs in the index, with the root
+/// profile covering it.  Ignored sub-trees are pruned and the database
+/// file and its WAL/SHM siblings skipped.
+///
+/// With `ignore_files`, the ignore files from the top of the enclosing
+/// git work tree down apply; outside one, those from the covering root
+/// profile's directory, or else from `root`, down.
+fn indexable_files<'p>(
+    fs: &dyn Vfs,
+    root: &Path,
+    profiles: &'p [RootProfile],
+    ignore_files: bool,
+) -> Vec<(PathBuf, Option<&'p RootProfile>)> {
+    let abs_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    // Profiles are keyed by canonical path; walked entries keep the form
+    // the caller passed in.
+    let abs_of = |p: &Path| -> PathBuf {
+        p.strip_prefix(root)
+            .map(|rel| abs_root.join(rel))
+            .unwrap_or_else(|_| p.to_path_buf())
+    };
+    let profile_of = |p: &Path| -> Option<(&'p RootProfile, PathBuf)> {
+        let abs = abs_of(p);
+        roots::innermost(profiles, &abs).map(|r| (r, abs))
+    };
+
+    let ignores = ignore_files.then(|| {
+        let base = IgnoreFiles::work_tree(fs, &abs_root)
+            .or_else(|| roots::innermost(profiles, &abs_root).map(|r| r.path.clone()))
+            .unwrap_or_else(|| match abs_root.parent() {
+                Some(parent) if !fs.is_dir(&abs_root) => parent.to_path_buf(),
+                _ => abs_root.clone(),
+            });
+        IgnoreFiles::new(fs, base)
+    });
+    let prune = |p: &Path| {
+        profile_of(p).is_some_and(|(r, abs)| r.ignores(&abs))
+            || ignores
+                .as_ref()
+                .is_some_and(|i| i.is_ignored(&abs_of(p), fs.is_dir(p)))
+    };
+    fs.walk_files(root, &prune)
+        .into_iter()
+        .filter(|p| {
+            !p.file_name().and_then(|n| n.to_str()).is_some_and(|name| {
+                name.ends_with(".db") || name.ends_with("-wal") || name.ends_with("-shm")
+            })
+        })
+        .map(|p| {
+            let profile = profile_of(&p).map(|(r, _)| r);
+            (p, profile)
+        })
+        .collect()
+}
+
+/// The `files` upsert shared by scans and [`index_files_with`].
+///
+/// Where the platform reports inodes and ctimes (see [`FileMeta`]), a file
+/// whose size, mtime, inode and ctime all match the index is skipped –
+/// nothing is written and nothing re-hashed.  A new path whose inode,
+/// size and mtime match an indexed file that no longer exists is taken as
+/// a rename, so tags and attributes follow the file.  Without inode/ctime
+/// every file is written as before.
+///
+/// Every file visited gets `last_seen_at` set to the time the upsert was
+/// created, skipped or not, and loses any tombstone (see [`crate::prune`]);
+/// `first_indexed_at` is only set when the row is inserted.
+///
+/// Files written with `read_body` get their language (see [`crate::lang`])
+/// stored as the `meta/lang` attribute, or cleared if it cannot be told,
+/// and their text stored in `file_text` for full-text search.  Other files
+/// written lose any text stored before.  A file whose body has never been
+/// read is not skipped when a read is wanted.
+struct Upsert<'c> {
+    write: Statement<'c>,
+    set_text: Statement<'c>,
+    add_text: Statement<'c>,
+    clear_text: Statement<'c>,
+    set_lang: Statement<'c>,
+    clear_lang: Statement<'c>,
+    seen: Statement<'c>,
+    current: Statement<'c>,
+    same_inode: Statement<'c>,
+    rename: Statement<'c>,
+    /// Never skip (`marlin reindex`).
+    force: bool,
+    /// Only store the text of files up to this many bytes.
+    max_text: Option<u64>,
+    /// Unix seconds stored as `last_seen_at` (and `first_indexed_at`).
+    now: i64,
+}
 
-        // Gather file metadata
-        let meta = fs::metadata(path)?;
-        let size = meta.len() as i64;
+impl<'c> Upsert<'c> {
+    fn new(conn: &'c Connection, force: bool, max_text: Option<u64>) -> Result<Self> {
+        Ok(Upsert {
+            write: conn.prepare(
+                r#"
+            INSERT INTO files(path, size, mtime, hash, dev, inode, ctime,
+                              first_indexed_at, last_seen_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+            ON CONFLICT(path) DO UPDATE
+                SET size  = excluded.size,
+                    mtime = excluded.mtime,
+                    hash  = IFNULL(excluded.hash, files.hash),
+                    dev   = excluded.dev,
+                    inode = excluded.inode,
+                    ctime = excluded.ctime,
+                    last_seen_at = excluded.last_seen_at,
+                    missing_since = NULL
+            "#,
+            )?,
+            set_text: conn.prepare(
+                "UPDATE file_text SET body = ?2
+                  WHERE file_id = (SELECT id FROM files WHERE path = ?1)",
+            )?,
+            add_text: conn.prepare(
+                "INSERT INTO file_text(file_id, body) SELECT id, ?2 FROM files WHERE path = ?1",
+            )?,
+            clear_text: conn.prepare(
+                "DELETE FROM file_text WHERE file_id = (SELECT id FROM files WHERE path = ?1)",
+            )?,
+            set_lang: conn.prepare(
+                "INSERT INTO attributes(file_id, key, value)
+                 SELECT id, ?2, ?3 FROM files WHERE path = ?1
+                 ON CONFLICT(file_id, key) DO UPDATE SET value = excluded.value",
+            )?,
+            clear_lang: conn.prepare(
+                "DELETE FROM attributes
+                  WHERE key = ?2 AND file_id = (SELECT id FROM files WHERE path = ?1)",
+            )?,
+            seen: conn.prepare(
+                "UPDATE files SET last_seen_at = ?2, missing_since = NULL WHERE path = ?1",
+            )?,
+            current: conn.prepare(
+                "SELECT size, mtime, inode, ctime, hash IS NOT NULL,
+                        EXISTS (SELECT 1 FROM file_text t WHERE t.file_id = files.id)
+                   FROM files WHERE path = ?1",
+            )?,
+            same_inode: conn.prepare(
+                "SELECT id, path FROM files
+                  WHERE inode = ?1 AND dev IS ?2 AND size = ?3 AND mtime = ?4",
+            )?,
+            rename: conn.prepare("UPDATE files SET path = ?2 WHERE id = ?1")?,
+            force,
+            max_text,
+            now: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64,
+        })
+    }
+
+    /// Store `meta` (and a SHA-256 of the body if `hash`, its language and
+    /// text if `read_body`) for `path`; returns the normalised path that
+    /// was written.
+    fn file(
+        &mut self,
+        fs: &dyn Vfs,
+        path: &Path,
+        meta: FileMeta,
+        hash: bool,
+        read_body: bool,
+    ) -> Result<String> {
         let mtime = meta
-            .modified()?
+            .modified
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
+        let size = meta.len as i64;
+        let inode = meta.inode.map(|i| i as i64);
+        let dev = meta.dev.map(|d| d as i64);
+        let path_str = normalize_path(path, &PathNorm::default());
 
-        // Execute the upsert
-        let path_str = path.to_string_lossy();
-        stmt.execute(params![path_str, size, mtime])?;
-        count += 1;
+        let stored = self
+            .current
+            .query_row([&path_str], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, i64>(1)?,
+                    r.get::<_, Option<i64>>(2)?,
+                    r.get::<_, Option<i64>>(3)?,
+                    r.get::<_, bool>(4)?,
+                    r.get::<_, bool>(5)?,
+                ))
+            })
+            .optional()?;
+        match stored {
+            Some((s_size, s_mtime, s_inode, s_ctime, hashed, read))
+                if !self.force
+                    && meta.ctime_ns.is_some()
+                    && (s_size, s_mtime, s_inode, s_ctime)
+                        == (size, mtime, inode, meta.ctime_ns)
+                    && (hashed || !hash)
+                    && (read || !read_body) =>
+            {
+                debug!(file = %path_str, "unchanged, skipped");
+                self.seen.execute(params![path_str, self.now])?;
+                return Ok(path_str);
+            }
+            None if inode.is_some() => {
+                self.follow_rename(fs, &path_str, size, mtime, inode, dev)?
+            }
+            _ => {}
+        }
 
-        debug!(file = %path_str, "indexed");
+        let hash = if hash {
+            Some(sha256_reader(fs.open(path)?)?)
+        } else {
+            None
+        };
+        let m = Mutation::new("index_file").path(&path_str);
+        m.done(self.write.execute(params![
+            path_str,
+            size,
+            mtime,
+            hash,
+            dev,
+            inode,
+            meta.ctime_ns,
+            self.now
+        ])?);
+        if read_body && meta.len > 0 {
+            self.store_lang(fs, path, &path_str)?;
+        }
+        if read_body {
+            self.store_text(fs, path, &path_str, meta.len)?;
+        } else {
+            self.clear_text.execute([&path_str])?;
+        }
+        Ok(path_str)
     }
 
-    // Finalize and commit
-    drop(stmt);
+    /// Store the text of `path`, or NULL when it is too large or not
+    /// UTF-8 text; unreadable files keep whatever was stored before.  The
+    /// start is looked at first so that large binaries are not read whole.
+    fn store_text(&mut self, fs: &dyn Vfs, path: &Path, path_str: &str, len: u64) -> Result<()> {
+        let mut text = None;
+        if self.max_text.is_none_or(|max| len <= max) {
+            let Ok(head) = fs.open(path) else {
+                return Ok(());
+            };
+            if read_text_sample(head, TEXT_PROBE_BYTES).is_some() {
+                let Ok(body) = fs.open(path) else {
+                    return Ok(());
+                };
+                text = read_text_sample(body, len);
+            }
+        }
+        // not an upsert: its conflict handling would override the `OR
+        // REPLACE` of the FTS triggers and leave the old text indexed
+        if self.set_text.execute(params![path_str, text])? == 0 {
+            self.add_text.execute(params![path_str, text])?;
+        }
+        Ok(())
+    }
+
+    /// Detect the language of the start of `path`; unreadable files keep
+    /// whatever was stored before.
+    fn store_lang(&mut self, fs: &dyn Vfs, path: &Path, path_str: &str) -> Result<()> {
+        let Ok(body) = fs.open(path) else {
+            return Ok(());
+        };
+        match lang::detect_reader(body) {
+            Some(code) => {
+                self.set_lang
+                    .execute(params![path_str, lang::LANG_KEY, code])?;
+            }
+            None => {
+                self.clear_lang.execute(params![path_str, lang::LANG_KEY])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the row of a vanished file with the same inode, size and mtime
+    /// over to `new_path`.  Hard links (the old path still exists) are left
+    /// alone.
+    fn follow_rename(
+        &mut self,
+        fs: &dyn Vfs,
+        new_path: &str,
+        size: i64,
+        mtime: i64,
+        inode: Option<i64>,
+        dev: Option<i64>,
+    ) -> Result<()> {
+        let candidates = self
+            .same_inode
+            .query_map(params![inode, dev, size, mtime], |r| {
+                Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if let Some((id, old)) = candidates
+            .into_iter()
+            .find(|(_, p)| fs.metadata(Path::new(p)).is_err())
+        {
+            info!(from = %old, to = %new_path, "detected rename");
+            let m = Mutation::new("follow_rename").file_id(id).path(&old);
+            m.done(self.rename.execute(params![id, new_path])?);
+        }
+        Ok(())
+    }
+}
+
+/// Walk the file system below `pattern`'s root and return the indexed
+/// `(id, path)` pairs it matches, logging files that still need a scan.
+/// Unlike [`db::match_files`] this only sees files that exist on disk.
+pub fn glob_indexed_files(conn: &Connection, pattern: &str) -> Result<Vec<(i64, String)>> {
+    let expanded = shellexpand::tilde(pattern).into_owned();
+    let pat = crate::globs::FileGlob::new(&expanded)?;
+    let root = crate::utils::determine_scan_root(&expanded);
+
+    let mut stmt = conn.prepare("SELECT id FROM files WHERE path = ?1")?;
+    let mut out = Vec::new();
+    for entry in walkdir::WalkDir::new(&root)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let p = entry.path().to_string_lossy();
+        if !pat.matches(&p) {
+            continue;
+        }
+        match stmt.query_row([p.as_ref()], |r| r.get::<_, i64>(0)) {
+            Ok(fid) => out.push((fid, p.into_owned())),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                error!(file = %p, "not indexed – run `marlin scan` first")
+            }
+            Err(e) => error!(file = %p, error = %e, "could not lookup file ID"),
+        }
+    }
+    Ok(out)
+}
+
+/// Hex-encoded SHA-256 of a file's contents.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    sha256_reader(StdFs.open(path)?)
+}
+
+/// Hex-encoded SHA-256 of everything `r` yields.
+pub fn sha256_reader(mut r: impl Read) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut r, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Re-read size, mtime, inode and ctime of already indexed files whose
+/// on-disk metadata no longer matches the index. Files that vanished are
+/// left untouched.
+///
+/// Returns the number of rows updated.
+pub fn refresh_files<P: AsRef<str>>(conn: &mut Connection, paths: &[P]) -> Result<usize> {
+    refresh_files_with(conn, &StdFs, paths)
+}
+
+/// [`refresh_files`] over any [`Vfs`].
+pub fn refresh_files_with<P: AsRef<str>>(
+    conn: &mut Connection,
+    fs: &dyn Vfs,
+    paths: &[P],
+) -> Result<usize> {
+    let m = Mutation::new("refresh_files");
+    let tx = conn.savepoint()?;
+    let mut updated = 0usize;
+    {
+        let mut stmt = tx.prepare(
+            "UPDATE files SET size = ?2, mtime = ?3, dev = ?4, inode = ?5, ctime = ?6
+              WHERE path = ?1
+                AND (size IS NOT ?2 OR mtime IS NOT ?3 OR dev IS NOT ?4
+                     OR inode IS NOT ?5 OR ctime IS NOT ?6)",
+        )?;
+        for p in paths {
+            let p = p.as_ref();
+            let Ok(meta) = fs.metadata(Path::new(p)) else {
+                debug!(file = %p, "missing on disk, not refreshed");
+                continue;
+            };
+            let mtime = meta
+                .modified
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64;
+            updated += stmt.execute(params![
+                p,
+                meta.len as i64,
+                mtime,
+                meta.dev.map(|d| d as i64),
+                meta.inode.map(|i| i as i64),
+                meta.ctime_ns
+            ])?;
+        }
+    }
     tx.commit()?;
 
-    info!(indexed = count, "scan complete");
-    Ok(count)
+    m.done(updated);
+    info!(updated, "refreshed stale entries");
+    Ok(updated)
 }
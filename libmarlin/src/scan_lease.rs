@@ -0,0 +1,281 @@
+//! Bookkeeping of scans in progress, so two `marlin scan` runs over the
+//! same tree do not both walk it and then fight over the write lock.
+//!
+//! Before a scan starts it records every root it is going to walk in the
+//! `active_scans` table (root, pid, heartbeat).  A second scan whose roots
+//! overlap one of them – the same directory, or one inside the other –
+//! finds the row and reacts according to its [`OnBusy`] policy.  Rows of
+//! processes that died without cleaning up are ignored and replaced.
+//!
+//! A root's rows are written in their own short transaction, before the
+//! scan's, so other processes see them while the scan still runs.  Inside
+//! an outer transaction (`marlin run --atomic`) they only become visible
+//! with it, which makes the check best effort there.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
+use tracing::{info, warn};
+
+use crate::utils::{normalize_path, PathNorm};
+
+/// Without a way to ask whether the owner is alive (non-unix), a row
+/// whose heartbeat is older than this counts as abandoned.
+pub const STALE_AFTER_SECS: i64 = 60 * 60;
+
+/// How often a waiting scan looks again.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What a scan does when another process is already scanning an
+/// overlapping root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnBusy {
+    /// Wait for the other scan to finish, then scan as usual.
+    #[default]
+    Wait,
+    /// Leave the busy roots out (with a warning) and scan the rest.
+    Skip,
+    /// Wait for the other scan and take its result for the busy roots
+    /// instead of walking them again.
+    Join,
+}
+
+impl fmt::Display for OnBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OnBusy::Wait => "wait",
+            OnBusy::Skip => "skip",
+            OnBusy::Join => "join",
+        })
+    }
+}
+
+impl FromStr for OnBusy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "wait" => Ok(OnBusy::Wait),
+            "skip" => Ok(OnBusy::Skip),
+            "join" => Ok(OnBusy::Join),
+            _ => bail!("unknown --on-busy policy `{s}` (expected wait, skip or join)"),
+        }
+    }
+}
+
+/// A scan another process has in progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveScan {
+    pub root: PathBuf,
+    pub pid: u32,
+    /// Unix seconds.
+    pub started_at: i64,
+    /// Unix seconds; refreshed whenever the scan finishes one of its roots.
+    pub heartbeat_at: i64,
+}
+
+impl fmt::Display for ActiveScan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (pid {}, running for {}s)",
+            self.root.display(),
+            self.pid,
+            (now() - self.started_at).max(0)
+        )
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// The key a root is recorded under: absolute and normalised.
+pub fn root_key(root: &Path) -> String {
+    let abs = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    normalize_path(&abs, &PathNorm::default())
+}
+
+/// Whether `a` and `b` are the same directory or one contains the other.
+fn overlaps(a: &str, b: &str) -> bool {
+    let inside = |inner: &str, outer: &str| {
+        inner
+            .strip_prefix(outer)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || outer.ends_with('/'))
+    };
+    inside(a, b) || inside(b, a)
+}
+
+/// Scans of other, live processes, oldest first.  Rows left behind by
+/// processes that are gone are deleted on the way.
+pub fn active(conn: &Connection) -> Result<Vec<ActiveScan>> {
+    let mut stmt = conn.prepare(
+        "SELECT root, pid, started_at, heartbeat_at FROM active_scans ORDER BY started_at, root",
+    )?;
+    let rows = stmt
+        .query_map([], |r| {
+            Ok(ActiveScan {
+                root: PathBuf::from(r.get::<_, String>(0)?),
+                pid: r.get(1)?,
+                started_at: r.get(2)?,
+                heartbeat_at: r.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let me = std::process::id();
+    let mut live = Vec::new();
+    for scan in rows {
+        if scan.pid == me {
+            continue;
+        }
+        if is_alive(&scan) {
+            live.push(scan);
+        } else {
+            info!(root = %scan.root.display(), pid = scan.pid, "dropping abandoned scan record");
+            conn.execute(
+                "DELETE FROM active_scans WHERE root = ?1 AND pid = ?2",
+                params![scan.root.to_string_lossy(), scan.pid],
+            )?;
+        }
+    }
+    Ok(live)
+}
+
+/// The other process's scan overlapping `root`, if there is one.
+pub fn busy(conn: &Connection, root: &Path) -> Result<Option<ActiveScan>> {
+    let key = root_key(root);
+    Ok(active(conn)?
+        .into_iter()
+        .find(|s| overlaps(&s.root.to_string_lossy(), &key)))
+}
+
+#[cfg(unix)]
+fn is_alive(scan: &ActiveScan) -> bool {
+    // signal 0 only checks that the process exists (EPERM: it does, but
+    // belongs to someone else)
+    let rc = unsafe { libc::kill(scan.pid as libc::pid_t, 0) };
+    rc == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_alive(scan: &ActiveScan) -> bool {
+    now() - scan.heartbeat_at < STALE_AFTER_SECS
+}
+
+/// The roots of one scan, recorded as in progress until [`release`]d.
+///
+/// [`release`]: Lease::release
+#[derive(Debug)]
+pub struct Lease {
+    roots: Vec<String>,
+}
+
+impl Lease {
+    /// Record `roots` as being scanned by this process, unless another
+    /// process got to an overlapping root first – `Ok(None)` then.  The
+    /// check and the insert happen under one write lock.
+    pub fn try_claim(conn: &Connection, roots: &[PathBuf]) -> Result<Option<Self>> {
+        let tx = if conn.is_autocommit() {
+            Some(Transaction::new_unchecked(
+                conn,
+                TransactionBehavior::Immediate,
+            )?)
+        } else {
+            None // part of the caller's transaction
+        };
+        for root in roots {
+            if busy(conn, root)?.is_some() {
+                return Ok(None); // dropping `tx` rolls back
+            }
+        }
+        let roots: Vec<String> = roots.iter().map(|r| root_key(r)).collect();
+        let t = now();
+        for root in &roots {
+            conn.execute(
+                "INSERT OR REPLACE INTO active_scans(root, pid, started_at, heartbeat_at)
+                 VALUES (?1, ?2, ?3, ?3)",
+                params![root, std::process::id(), t],
+            )?;
+        }
+        if let Some(tx) = tx {
+            tx.commit()?;
+        }
+        Ok(Some(Self { roots }))
+    }
+
+    /// `root` is done: drop its record and refresh the heartbeat of the
+    /// others.
+    pub fn finish(&mut self, conn: &Connection, root: &Path) -> Result<()> {
+        let key = root_key(root);
+        self.roots.retain(|r| *r != key);
+        conn.execute(
+            "DELETE FROM active_scans WHERE root = ?1 AND pid = ?2",
+            params![key, std::process::id()],
+        )?;
+        conn.execute(
+            "UPDATE active_scans SET heartbeat_at = ?2 WHERE pid = ?1",
+            params![std::process::id(), now()],
+        )?;
+        Ok(())
+    }
+
+    /// Drop the records of every root not finished yet.
+    pub fn release(self, conn: &Connection) -> Result<()> {
+        for root in &self.roots {
+            conn.execute(
+                "DELETE FROM active_scans WHERE root = ?1 AND pid = ?2",
+                params![root, std::process::id()],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The roots of `roots` to scan now under `on_busy`.  `Wait` and `Join`
+/// block until nothing overlapping is in progress; `lock` is called for
+/// every look at the index, so the connection is not held while sleeping.
+pub fn settle<C>(
+    mut lock: impl FnMut() -> C,
+    roots: &[PathBuf],
+    on_busy: OnBusy,
+) -> Result<Vec<PathBuf>>
+where
+    C: std::ops::Deref<Target = Connection>,
+{
+    let mut todo = Vec::new();
+    for root in roots {
+        let Some(other) = busy(&lock(), root)? else {
+            todo.push(root.clone());
+            continue;
+        };
+        match on_busy {
+            OnBusy::Skip => {
+                warn!(
+                    "skipping {}: already being scanned by {other}",
+                    root.display()
+                );
+            }
+            OnBusy::Wait | OnBusy::Join => {
+                warn!(
+                    "{} is already being scanned by {other}; waiting",
+                    root.display()
+                );
+                while busy(&lock(), root)?.is_some() {
+                    thread::sleep(POLL_INTERVAL);
+                }
+                if on_busy == OnBusy::Join {
+                    info!("joined the scan of {}", other.root.display());
+                } else {
+                    todo.push(root.clone());
+                }
+            }
+        }
+    }
+    Ok(todo)
+}
@@ -0,0 +1,106 @@
+// libmarlin/src/scan_lease_tests.rs
+
+use super::scan_lease::{self, OnBusy};
+use super::{query, Marlin};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use tempfile::tempdir;
+
+/// Pretend process `pid` is scanning `root`.
+fn record(m: &Marlin, root: &Path, pid: u32) {
+    m.conn()
+        .execute(
+            "INSERT INTO active_scans(root, pid, started_at, heartbeat_at) VALUES (?1, ?2, 0, 0)",
+            rusqlite::params![scan_lease::root_key(root), pid],
+        )
+        .unwrap();
+}
+
+fn indexed(m: &Marlin) -> usize {
+    query::search(&m.conn(), "path:**").unwrap().len()
+}
+
+#[test]
+fn policies_round_trip() {
+    for s in ["wait", "skip", "join"] {
+        assert_eq!(s.parse::<OnBusy>().unwrap().to_string(), s);
+    }
+    assert!("later".parse::<OnBusy>().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn skip_leaves_out_roots_another_process_is_scanning() {
+    let tmp = tempdir().unwrap();
+    let (a, b) = (tmp.path().join("a"), tmp.path().join("b"));
+    fs::create_dir_all(a.join("sub")).unwrap();
+    fs::create_dir_all(&b).unwrap();
+    fs::write(a.join("sub/x.md"), "").unwrap();
+    fs::write(b.join("y.md"), "").unwrap();
+    let mut m = Marlin::open_at(":memory:").unwrap();
+
+    // pid 1 is always alive; its scan of `a` covers `a/sub` too
+    record(&m, &a, 1);
+    let busy = scan_lease::busy(&m.conn(), &a.join("sub"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(busy.pid, 1);
+    assert!(scan_lease::busy(&m.conn(), &b).unwrap().is_none());
+
+    let n = m
+        .scan_with(&[a.join("sub"), b.clone()], OnBusy::Skip)
+        .unwrap();
+    assert_eq!(n, 1);
+    // only the other process's record is left
+    assert_eq!(scan_lease::active(&m.conn()).unwrap().len(), 1);
+    let own: i64 = m
+        .conn()
+        .query_row(
+            "SELECT COUNT(*) FROM active_scans WHERE pid = ?1",
+            [std::process::id()],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(own, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn wait_and_join_block_until_the_other_scan_is_done() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("x.md"), "").unwrap();
+    let root = tmp.path().to_path_buf();
+
+    for (policy, expected) in [(OnBusy::Wait, 1), (OnBusy::Join, 0)] {
+        let mut m = Marlin::open_at(":memory:").unwrap();
+        record(&m, &root, 1);
+        let other = m.share();
+        let done = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            other
+                .conn()
+                .execute("DELETE FROM active_scans WHERE pid = 1", [])
+                .unwrap();
+        });
+        assert_eq!(m.scan_with(&[&root], policy).unwrap(), expected, "{policy}");
+        assert_eq!(indexed(&m), expected);
+        done.join().unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn records_of_dead_processes_are_dropped() {
+    let tmp = tempdir().unwrap();
+    fs::write(tmp.path().join("x.md"), "").unwrap();
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let dead = child.id();
+    child.wait().unwrap();
+
+    let mut m = Marlin::open_at(":memory:").unwrap();
+    record(&m, tmp.path(), dead);
+    assert!(scan_lease::active(&m.conn()).unwrap().is_empty());
+    assert_eq!(m.scan_with(&[tmp.path()], OnBusy::Skip).unwrap(), 1);
+}
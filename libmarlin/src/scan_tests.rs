@@ -1,7 +1,8 @@
 // libmarlin/src/scan_tests.rs
 
 use super::db;
-use super::scan::scan_directory;
+use super::roots::{self, RootProfile};
+use super::scan::{refresh_files, reindex_files, scan_directory, sha256_file};
 use std::fs::File;
 use tempfile::tempdir;
 
@@ -24,3 +25,284 @@ fn scan_directory_counts_files() {
     let total: i64 = stmt.query_row([], |r| r.get(0)).unwrap();
     assert_eq!(total, 2);
 }
+
+#[test]
+fn refresh_files_updates_only_stale_rows() {
+    let tmp = tempdir().unwrap();
+    let a = tmp.path().join("a.txt");
+    let b = tmp.path().join("b.txt");
+    std::fs::write(&a, "one").unwrap();
+    std::fs::write(&b, "two").unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+
+    std::fs::write(&a, "grown a lot").unwrap();
+    let gone = tmp.path().join("gone.txt");
+    let paths: Vec<String> = [&a, &b, &gone]
+        .iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(refresh_files(&mut conn, &paths).unwrap(), 1);
+
+    let size: i64 = conn
+        .query_row("SELECT size FROM files WHERE path = ?1", [&paths[0]], |r| {
+            r.get(0)
+        })
+        .unwrap();
+    assert_eq!(size, 11);
+}
+
+#[test]
+fn reindex_recomputes_stored_hashes() {
+    let tmp = tempdir().unwrap();
+    let a = tmp.path().join("a.txt");
+    std::fs::write(&a, "old").unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    // a hash left over from an earlier profile; plain scans keep it
+    conn.execute("UPDATE files SET hash = 'stale'", []).unwrap();
+    std::fs::write(&a, "new body").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let hash = |conn: &rusqlite::Connection| -> String {
+        conn.query_row("SELECT hash FROM files", [], |r| r.get(0))
+            .unwrap()
+    };
+    assert_eq!(hash(&conn), "stale");
+
+    assert_eq!(reindex_files(&mut conn, &[&a]).unwrap(), 1);
+    assert_eq!(hash(&conn), sha256_file(&a).unwrap());
+}
+
+#[test]
+fn scan_honours_root_profile() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("target/debug")).unwrap();
+    std::fs::write(root.join("target/debug/app"), "bin").unwrap();
+    std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+    std::fs::write(root.join("main.o"), "obj").unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    let mut p = RootProfile::default_for(&root);
+    p.ignore = vec!["target".into(), "*.o".into()];
+    p.hash = true;
+    roots::save(&conn, &p).unwrap();
+
+    assert_eq!(scan_directory(&mut conn, &root).unwrap(), 1);
+    let (path, hash): (String, Option<String>) = conn
+        .query_row("SELECT path, hash FROM files", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .unwrap();
+    assert!(path.ends_with("main.rs"));
+    assert_eq!(hash.map(|h| h.len()), Some(64));
+}
+
+#[test]
+fn scan_nests_inside_an_open_transaction() {
+    let tmp = tempdir().unwrap();
+    File::create(tmp.path().join("a.txt")).unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    conn.execute_batch("BEGIN").unwrap();
+    assert_eq!(scan_directory(&mut conn, tmp.path()).unwrap(), 1);
+    conn.execute_batch("ROLLBACK").unwrap();
+
+    let total: i64 = conn
+        .query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(total, 0);
+}
+
+#[cfg(unix)]
+fn total_changes(conn: &rusqlite::Connection) -> i64 {
+    conn.query_row("SELECT total_changes()", [], |r| r.get(0))
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn rescan_skips_files_with_unchanged_inode_and_ctime() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempdir().unwrap();
+    let a = tmp.path().join("a.txt");
+    std::fs::write(&a, "body").unwrap();
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let (inode, ctime): (Option<i64>, Option<i64>) = conn
+        .query_row("SELECT inode, ctime FROM files", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .unwrap();
+    assert!(inode.is_some() && ctime.is_some());
+
+    let before = total_changes(&conn);
+    assert_eq!(scan_directory(&mut conn, tmp.path()).unwrap(), 1);
+    assert_eq!(
+        total_changes(&conn),
+        before + 1,
+        "only last_seen_at written"
+    );
+
+    // a metadata-only change keeps size and mtime but moves the ctime
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    std::fs::set_permissions(&a, std::fs::Permissions::from_mode(0o600)).unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let new_ctime: Option<i64> = conn
+        .query_row("SELECT ctime FROM files", [], |r| r.get(0))
+        .unwrap();
+    assert_ne!(new_ctime, ctime);
+}
+
+#[cfg(unix)]
+#[test]
+fn rescan_follows_renamed_files_by_inode() {
+    let tmp = tempdir().unwrap();
+    let old = tmp.path().join("draft.md");
+    std::fs::write(&old, "text").unwrap();
+    std::fs::write(tmp.path().join("other.md"), "more").unwrap();
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let id = db::file_id(&conn, &old.to_string_lossy()).unwrap();
+    db::upsert_attr(&conn, id, "status", "review").unwrap();
+
+    let new = tmp.path().join("final.md");
+    std::fs::rename(&old, &new).unwrap();
+    // a hard link shares the inode but the original still exists
+    std::fs::hard_link(tmp.path().join("other.md"), tmp.path().join("link.md")).unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+
+    assert_eq!(db::file_id(&conn, &new.to_string_lossy()).unwrap(), id);
+    assert!(db::file_id(&conn, &old.to_string_lossy()).is_err());
+    let files: i64 = conn
+        .query_row("SELECT COUNT(*) FROM files", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(files, 3, "final.md, other.md and link.md");
+}
+
+#[test]
+fn scans_record_first_indexed_and_last_seen_times() {
+    let tmp = tempdir().unwrap();
+    let a = tmp.path().join("a.txt");
+    std::fs::write(&a, "body").unwrap();
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let id = db::file_id(&conn, &a.to_string_lossy()).unwrap();
+    let (added, seen) = db::seen_times(&conn, id).unwrap();
+    assert!(added.is_some());
+    assert_eq!(added, seen);
+
+    // pretend both happened long ago; a rescan only moves last_seen_at,
+    // whether the file changed or was skipped as unchanged
+    conn.execute(
+        "UPDATE files SET first_indexed_at = 1000, last_seen_at = 1000",
+        [],
+    )
+    .unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let (added, seen) = db::seen_times(&conn, id).unwrap();
+    assert_eq!(added, Some(1000));
+    assert!(seen.unwrap() > 1000);
+
+    std::fs::write(&a, "longer body").unwrap();
+    conn.execute("UPDATE files SET last_seen_at = 1000", [])
+        .unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let (added, seen) = db::seen_times(&conn, id).unwrap();
+    assert_eq!(added, Some(1000));
+    assert!(seen.unwrap() > 1000);
+}
+
+#[test]
+fn scan_stores_the_language_of_text_files() {
+    let tmp = tempdir().unwrap();
+    let root = tmp.path().join("docs");
+    std::fs::create_dir_all(root.join("private")).unwrap();
+    let de = "Der Bericht ist fertig und wird an den Vorstand geschickt.";
+    std::fs::write(root.join("bericht.txt"), de).unwrap();
+    std::fs::write(root.join("code.rs"), "fn main() {}").unwrap();
+    std::fs::write(root.join("private/notiz.txt"), de).unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    let mut p = RootProfile::default_for(root.join("private").canonicalize().unwrap());
+    p.index_content = false;
+    roots::save(&conn, &p).unwrap();
+    scan_directory(&mut conn, &root).unwrap();
+
+    let langs = |conn: &rusqlite::Connection| -> Vec<(String, String)> {
+        conn.prepare(
+            "SELECT f.path, a.value FROM attributes a JOIN files f ON f.id = a.file_id
+              WHERE a.key = 'meta/lang' ORDER BY f.path",
+        )
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+    };
+    let found = langs(&conn);
+    assert_eq!(found.len(), 1, "{found:?}");
+    assert!(found[0].0.ends_with("bericht.txt"));
+    assert_eq!(found[0].1, "de");
+
+    // a rewrite that no longer reads as German drops the attribute
+    std::fs::write(root.join("bericht.txt"), "1 2 3 4").unwrap();
+    reindex_files(&mut conn, &[&root]).unwrap();
+    assert!(langs(&conn).is_empty());
+}
+
+#[test]
+fn scan_indexes_the_text_of_small_utf8_files() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(tmp.path().join("notes.txt"), "the quarterly forecast").unwrap();
+    std::fs::write(tmp.path().join("blob.bin"), b"forecast\0\x01\x02").unwrap();
+    std::fs::write(tmp.path().join("huge.txt"), "forecast ".repeat(200_000)).unwrap();
+
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let hits = |conn: &rusqlite::Connection, q: &str| {
+        let mut stmt = conn
+            .prepare(
+                "SELECT f.path FROM files_fts JOIN files f ON f.id = files_fts.rowid
+                  WHERE files_fts MATCH ?1",
+            )
+            .unwrap();
+        stmt.query_map([q], |r| r.get::<_, String>(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    };
+    let found = hits(&conn, "content:forecast");
+    assert_eq!(found.len(), 1, "{found:?}");
+    assert!(found[0].ends_with("notes.txt"));
+
+    // a changed file is read again, and its old text goes
+    std::fs::write(tmp.path().join("notes.txt"), "the annual budget").unwrap();
+    reindex_files(&mut conn, &[tmp.path().join("notes.txt")]).unwrap();
+    assert!(hits(&conn, "forecast").is_empty());
+    assert_eq!(hits(&conn, "budget").len(), 1);
+}
+
+#[test]
+fn no_content_roots_keep_text_out_of_the_index() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(tmp.path().join("secret.txt"), "launch codes").unwrap();
+    let mut conn = db::open(":memory:").unwrap();
+    let root = RootProfile {
+        index_content: false,
+        ..RootProfile::default_for(tmp.path().canonicalize().unwrap())
+    };
+    roots::save(&conn, &root).unwrap();
+    scan_directory(&mut conn, tmp.path()).unwrap();
+    let n: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM files_fts WHERE files_fts MATCH 'launch'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(n, 0);
+}
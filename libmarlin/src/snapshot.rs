@@ -0,0 +1,249 @@
+//! Named snapshots of the metadata state – tags, attributes and links,
+//! never file contents – and diffs between them.
+//!
+//! Take one before a large automated retagging run and another after it
+//! (or diff against the live state) to review what changed:
+//!
+//! ```text
+//! marlin snapshot create before
+//! marlin run retag.marlin
+//! marlin snapshot diff before          # vs. the live index
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::info;
+
+use crate::db::Mutation;
+
+/// The live state as `(path, kind, key, value)` rows.
+const CURRENT_STATE: &str = r#"
+    WITH RECURSIVE tag_tree(id, path) AS (
+        SELECT id, name FROM tags WHERE parent_id IS NULL
+        UNION ALL
+        SELECT t.id, tt.path || '/' || t.name
+          FROM tags t
+          JOIN tag_tree tt ON t.parent_id = tt.id
+    )
+    SELECT f.path, 'tag', tt.path, ''
+      FROM file_tags ft
+      JOIN files    f  ON f.id  = ft.file_id
+      JOIN tag_tree tt ON tt.id = ft.tag_id
+    UNION ALL
+    SELECT f.path, 'attr', a.key, IFNULL(a.value, '')
+      FROM attributes a
+      JOIN files f ON f.id = a.file_id
+    UNION ALL
+    SELECT s.path, 'link', d.path, IFNULL(l.type, '')
+      FROM links l
+      JOIN files s ON s.id = l.src_file_id
+      JOIN files d ON d.id = l.dst_file_id
+"#;
+
+/// A stored snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub name: String,
+    /// Unix seconds.
+    pub created_at: i64,
+    pub entries: i64,
+}
+
+/// What kind of metadata a [`Change`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryKind {
+    Tag,
+    Attr,
+    Link,
+}
+
+impl EntryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryKind::Tag => "tag",
+            EntryKind::Attr => "attr",
+            EntryKind::Link => "link",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "tag" => Some(EntryKind::Tag),
+            "attr" => Some(EntryKind::Attr),
+            "link" => Some(EntryKind::Link),
+            _ => None,
+        }
+    }
+}
+
+/// One difference between two states.  `key` is the tag path, attribute
+/// key or link target; `old`/`new` hold the attribute value or link type
+/// (empty for tags) and are `None` where the entry is absent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: String,
+    pub kind: EntryKind,
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+type State = BTreeMap<(String, EntryKind, String), Vec<String>>;
+
+/// Capture the current state as `name`; returns the number of entries.
+pub fn create(conn: &mut Connection, name: &str) -> Result<usize> {
+    if name.trim().is_empty() {
+        bail!("snapshot name is empty");
+    }
+    let m = Mutation::new("create_snapshot");
+    let tx = conn.savepoint()?;
+    let exists = tx
+        .query_row(
+            "SELECT 1 FROM snapshots WHERE name = ?1",
+            [name],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if exists {
+        bail!("snapshot already exists: {name} (remove it first)");
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    tx.execute(
+        "INSERT INTO snapshots(name, created_at) VALUES (?1, ?2)",
+        params![name, now],
+    )?;
+    let id = tx.last_insert_rowid();
+    let n = tx.execute(
+        &format!(
+            "INSERT INTO snapshot_entries(snapshot_id, path, kind, key, value)
+             SELECT ?1, * FROM ({CURRENT_STATE})"
+        ),
+        [id],
+    )?;
+    tx.commit()?;
+    info!(snapshot = name, entries = n, "snapshot created");
+    Ok(m.done(n))
+}
+
+/// All snapshots, oldest first.
+pub fn list(conn: &Connection) -> Result<Vec<SnapshotInfo>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.name, s.created_at,
+                (SELECT COUNT(*) FROM snapshot_entries e WHERE e.snapshot_id = s.id)
+           FROM snapshots s
+          ORDER BY s.created_at, s.id",
+    )?;
+    let rows = stmt.query_map([], |r| {
+        Ok(SnapshotInfo {
+            name: r.get(0)?,
+            created_at: r.get(1)?,
+            entries: r.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<std::result::Result<_, _>>()?)
+}
+
+/// Delete snapshot `name`; `false` if there was none.
+pub fn remove(conn: &Connection, name: &str) -> Result<bool> {
+    let m = Mutation::new("remove_snapshot");
+    Ok(m.done(conn.execute("DELETE FROM snapshots WHERE name = ?1", [name])?) > 0)
+}
+
+/// Changes from snapshot `a` to snapshot `b`, or to the live state when
+/// `b` is `None`.  Sorted by path, kind and key.
+pub fn diff(conn: &Connection, a: &str, b: Option<&str>) -> Result<Vec<Change>> {
+    Ok(changes(&load(conn, Some(a))?, &load(conn, b)?))
+}
+
+/// Changes from the live state of database `old` to that of `new`, e.g.
+/// a backup and the index (see [`crate::db::diff_against`]).
+pub fn diff_databases(old: &Connection, new: &Connection) -> Result<Vec<Change>> {
+    Ok(changes(&load(old, None)?, &load(new, None)?))
+}
+
+fn changes(old: &State, new: &State) -> Vec<Change> {
+    let mut out = Vec::new();
+    let mut push = |(path, kind, key): &(String, EntryKind, String), o, n| {
+        out.push(Change {
+            path: path.clone(),
+            kind: *kind,
+            key: key.clone(),
+            old: o,
+            new: n,
+        })
+    };
+    for (k, ov) in old {
+        match new.get(k) {
+            // links may carry several types between the same two files
+            Some(nv) if k.1 == EntryKind::Link => {
+                for t in ov.iter().filter(|t| !nv.contains(t)) {
+                    push(k, Some(t.clone()), None);
+                }
+                for t in nv.iter().filter(|t| !ov.contains(t)) {
+                    push(k, None, Some(t.clone()));
+                }
+            }
+            Some(nv) if nv != ov => push(k, ov.first().cloned(), nv.first().cloned()),
+            Some(_) => {}
+            None => {
+                for v in ov {
+                    push(k, Some(v.clone()), None);
+                }
+            }
+        }
+    }
+    for (k, nv) in new {
+        if !old.contains_key(k) {
+            for v in nv {
+                push(k, None, Some(v.clone()));
+            }
+        }
+    }
+    out.sort_by(|x, y| {
+        (&x.path, x.kind, &x.key)
+            .cmp(&(&y.path, y.kind, &y.key))
+            .then(x.new.is_some().cmp(&y.new.is_some()))
+    });
+    out
+}
+
+/// A snapshot (or, for `None`, the live state) keyed for diffing.
+fn load(conn: &Connection, name: Option<&str>) -> Result<State> {
+    let (sql, args): (String, Vec<i64>) = match name {
+        Some(n) => {
+            let id: i64 = conn
+                .query_row("SELECT id FROM snapshots WHERE name = ?1", [n], |r| {
+                    r.get(0)
+                })
+                .optional()?
+                .ok_or_else(|| anyhow::anyhow!("snapshot not found: {n}"))?;
+            (
+                "SELECT path, kind, key, value FROM snapshot_entries WHERE snapshot_id = ?1".into(),
+                vec![id],
+            )
+        }
+        None => (CURRENT_STATE.into(), vec![]),
+    };
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(args))?;
+    let mut state = State::new();
+    while let Some(r) = rows.next()? {
+        let kind: String = r.get(1)?;
+        let Some(kind) = EntryKind::from_str(&kind) else {
+            continue;
+        };
+        state
+            .entry((r.get(0)?, kind, r.get(2)?))
+            .or_default()
+            .push(r.get(3)?);
+    }
+    for values in state.values_mut() {
+        values.sort();
+        values.dedup();
+    }
+    Ok(state)
+}
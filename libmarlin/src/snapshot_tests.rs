@@ -0,0 +1,63 @@
+// libmarlin/src/snapshot_tests.rs
+
+use super::db;
+use super::snapshot::{self, Change, EntryKind};
+use super::test_utils::db_with_files;
+
+fn change(path: &str, kind: EntryKind, key: &str, old: Option<&str>, new: Option<&str>) -> Change {
+    Change {
+        path: path.into(),
+        kind,
+        key: key.into(),
+        old: old.map(Into::into),
+        new: new.map(Into::into),
+    }
+}
+
+#[test]
+fn diff_reports_tag_attr_and_link_changes() {
+    let mut conn = db_with_files(&["/a.txt", "/b.txt"]);
+    let a = db::file_id(&conn, "/a.txt").unwrap();
+    let b = db::file_id(&conn, "/b.txt").unwrap();
+    let old_tag = db::ensure_tag_path(&conn, "inbox").unwrap();
+    conn.execute("INSERT INTO file_tags VALUES (?1, ?2)", [a, old_tag])
+        .unwrap();
+    db::upsert_attr(&conn, a, "owner", "ann").unwrap();
+    db::upsert_attr(&conn, b, "gone", "soon").unwrap();
+
+    assert_eq!(snapshot::create(&mut conn, "before").unwrap(), 3);
+    assert!(snapshot::create(&mut conn, "before").is_err());
+    assert!(snapshot::diff(&conn, "before", None).unwrap().is_empty());
+
+    conn.execute("DELETE FROM file_tags", []).unwrap();
+    let new_tag = db::ensure_tag_path(&conn, "project/x").unwrap();
+    conn.execute("INSERT INTO file_tags VALUES (?1, ?2)", [a, new_tag])
+        .unwrap();
+    db::upsert_attr(&conn, a, "owner", "bob").unwrap();
+    conn.execute("DELETE FROM attributes WHERE key = 'gone'", [])
+        .unwrap();
+    db::add_link(&conn, a, b, Some("ref")).unwrap();
+    snapshot::create(&mut conn, "after").unwrap();
+
+    let expected = vec![
+        change("/a.txt", EntryKind::Tag, "inbox", Some(""), None),
+        change("/a.txt", EntryKind::Tag, "project/x", None, Some("")),
+        change("/a.txt", EntryKind::Attr, "owner", Some("ann"), Some("bob")),
+        change("/a.txt", EntryKind::Link, "/b.txt", None, Some("ref")),
+        change("/b.txt", EntryKind::Attr, "gone", Some("soon"), None),
+    ];
+    assert_eq!(
+        snapshot::diff(&conn, "before", Some("after")).unwrap(),
+        expected
+    );
+    assert_eq!(snapshot::diff(&conn, "before", None).unwrap(), expected);
+
+    let names: Vec<_> = snapshot::list(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(names, ["before", "after"]);
+    assert!(snapshot::remove(&conn, "before").unwrap());
+    assert!(snapshot::diff(&conn, "before", None).is_err());
+}
@@ -0,0 +1,235 @@
+//! Index storage behind a trait.
+//!
+//! [`Store`] covers what most callers need from the index – files, tags,
+//! attributes, links, settings and search – as methods, so code written
+//! against it runs on any backend: a remote or libsql-backed SQLite, or
+//! an in-memory store in tests.  [`crate::Marlin`] holds one and tags and
+//! sets attributes through it; the default is the rusqlite
+//! [`Connection`], whose methods are the `db::*` helpers of the same
+//! names (tagging reads the files page by page in SQL and attaches each
+//! tag through [`Store::attach_tag`]).
+//!
+//! Everything not on the trait (migrations, FTS upkeep, scans, locks,
+//! views) still talks SQL to the connection directly, so those `Marlin`
+//! operations need the SQLite store.
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::db::{self, TagMerge};
+use crate::lock;
+use crate::query::{self, SearchHit, SearchOptions};
+
+/// Read and write access to one index.
+pub trait Store {
+    /* ─── files ─── */
+
+    /// Id of the file stored at `path`, if it is indexed.
+    fn find_file(&self, path: &str) -> Result<Option<i64>>;
+
+    /// Like [`find_file`](Store::find_file), but an unknown path is an
+    /// error.
+    fn file_id(&self, path: &str) -> Result<i64> {
+        self.find_file(path)?
+            .ok_or_else(|| anyhow::anyhow!("file not indexed: {path}"))
+    }
+
+    /// Stored path of the file with id `file_id`.
+    fn file_path(&self, file_id: i64) -> Result<String>;
+
+    /// Indexed `(id, path)` pairs matching a glob, by path; see
+    /// [`db::match_files`].
+    fn match_files(&self, pattern: &str) -> Result<Vec<(i64, String)>>;
+
+    /* ─── tags ─── */
+
+    /// Find or create every segment of a tag path; returns the leaf's id.
+    fn ensure_tag_path(&self, path: &str) -> Result<i64>;
+
+    /// Look up a tag path without creating anything.
+    fn find_tag_path(&self, path: &str) -> Result<Option<i64>>;
+
+    /// `leaf` followed by its ancestors up to the root tag.
+    fn tag_ancestry(&self, leaf: i64) -> Result<Vec<i64>>;
+
+    /// Attach a tag as of `at` (Unix milliseconds); see [`db::attach_tag`].
+    fn attach_tag(&self, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge>;
+
+    /// Detach a tag as of `at`; see [`db::detach_tag`].
+    fn detach_tag(&self, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge>;
+
+    /// Full paths of the tags on a file, sorted.
+    fn file_tag_paths(&self, file_id: i64) -> Result<Vec<String>>;
+
+    /// Attach `tag_path` and its ancestors to the files matching a glob,
+    /// creating the tag unless [`strict_tags`](Store::strict_tags) is on;
+    /// see [`crate::Marlin::tag`].  Returns the number of files that
+    /// gained a tag.
+    fn tag_glob(&self, pattern: &str, tag_path: &str) -> Result<usize> {
+        let leaf = if self.strict_tags()? {
+            self.find_tag_path(tag_path)?
+                .ok_or_else(|| anyhow::anyhow!("unknown tag `{tag_path}` (tags.strict is on)"))?
+        } else {
+            self.ensure_tag_path(tag_path)?
+        };
+        let tag_ids = self.tag_ancestry(leaf)?;
+        let files = self.match_files(pattern)?;
+        self.check_unlocked(&files)?;
+
+        let now = db::now_millis();
+        let mut changed = 0;
+        for (fid, _) in &files {
+            let mut newly = false;
+            for &tid in &tag_ids {
+                if self.attach_tag(*fid, tid, now)? == TagMerge::Changed {
+                    newly = true;
+                }
+            }
+            if newly {
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /* ─── attributes ─── */
+
+    /// The value of attribute `key` on a file, if set.
+    fn attr(&self, file_id: i64, key: &str) -> Result<Option<String>>;
+
+    /// `(key, value, expires_at)` of every attribute on a file, by key.
+    fn file_attrs(&self, file_id: i64) -> Result<Vec<(String, String, Option<i64>)>>;
+
+    /// Set attribute `key` on a file.
+    fn upsert_attr(&self, file_id: i64, key: &str, value: &str) -> Result<()>;
+
+    /* ─── links ─── */
+
+    fn add_link(&self, src_file_id: i64, dst_file_id: i64, link_type: Option<&str>) -> Result<()>;
+
+    fn remove_link(
+        &self,
+        src_file_id: i64,
+        dst_file_id: i64,
+        link_type: Option<&str>,
+    ) -> Result<()>;
+
+    /* ─── settings ─── */
+
+    /// A per-index setting such as `tags.strict`.
+    fn setting(&self, key: &str) -> Result<Option<String>>;
+
+    fn set_setting(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Whether unknown tags are an error instead of being created
+    /// (`tags.strict`).
+    fn strict_tags(&self) -> Result<bool> {
+        Ok(self.setting("tags.strict")?.as_deref() == Some("true"))
+    }
+
+    /* ─── locks ─── */
+
+    /// Fail naming the first of `files` that is locked (see
+    /// [`crate::lock`]).  Backends without locks accept everything.
+    fn check_unlocked(&self, _files: &[(i64, String)]) -> Result<()> {
+        Ok(())
+    }
+
+    /* ─── search ─── */
+
+    /// Files matching a Marlin query, best first; see
+    /// [`query::search_hits`].
+    fn search_hits(&self, raw: &str, opts: &SearchOptions) -> Result<Vec<SearchHit>>;
+}
+
+/// The SQLite index, through the `db::*` helpers.
+impl Store for Connection {
+    fn find_file(&self, path: &str) -> Result<Option<i64>> {
+        db::find_file_id(self, path)
+    }
+
+    fn file_id(&self, path: &str) -> Result<i64> {
+        db::file_id(self, path)
+    }
+
+    fn file_path(&self, file_id: i64) -> Result<String> {
+        db::file_path(self, file_id)
+    }
+
+    fn match_files(&self, pattern: &str) -> Result<Vec<(i64, String)>> {
+        db::match_files(self, pattern)
+    }
+
+    fn ensure_tag_path(&self, path: &str) -> Result<i64> {
+        db::ensure_tag_path(self, path)
+    }
+
+    fn find_tag_path(&self, path: &str) -> Result<Option<i64>> {
+        db::find_tag_path(self, path)
+    }
+
+    fn tag_ancestry(&self, leaf: i64) -> Result<Vec<i64>> {
+        db::tag_ancestry(self, leaf)
+    }
+
+    fn attach_tag(&self, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge> {
+        db::attach_tag(self, file_id, tag_id, at)
+    }
+
+    fn detach_tag(&self, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge> {
+        db::detach_tag(self, file_id, tag_id, at)
+    }
+
+    fn file_tag_paths(&self, file_id: i64) -> Result<Vec<String>> {
+        db::file_tag_paths(self, file_id)
+    }
+
+    fn tag_glob(&self, pattern: &str, tag_path: &str) -> Result<usize> {
+        crate::tag_in_chunks(self, self, pattern, tag_path, crate::TAG_CHUNK)
+    }
+
+    fn attr(&self, file_id: i64, key: &str) -> Result<Option<String>> {
+        db::attr_value(self, file_id, key)
+    }
+
+    fn file_attrs(&self, file_id: i64) -> Result<Vec<(String, String, Option<i64>)>> {
+        db::file_attrs(self, file_id)
+    }
+
+    fn upsert_attr(&self, file_id: i64, key: &str, value: &str) -> Result<()> {
+        db::upsert_attr(self, file_id, key, value)
+    }
+
+    fn add_link(&self, src_file_id: i64, dst_file_id: i64, link_type: Option<&str>) -> Result<()> {
+        db::add_link(self, src_file_id, dst_file_id, link_type)
+    }
+
+    fn remove_link(
+        &self,
+        src_file_id: i64,
+        dst_file_id: i64,
+        link_type: Option<&str>,
+    ) -> Result<()> {
+        db::remove_link(self, src_file_id, dst_file_id, link_type)
+    }
+
+    fn setting(&self, key: &str) -> Result<Option<String>> {
+        db::setting(self, key)
+    }
+
+    fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        db::set_setting(self, key, value)
+    }
+
+    fn strict_tags(&self) -> Result<bool> {
+        db::strict_tags(self)
+    }
+
+    fn check_unlocked(&self, files: &[(i64, String)]) -> Result<()> {
+        lock::check_files(self, files)
+    }
+
+    fn search_hits(&self, raw: &str, opts: &SearchOptions) -> Result<Vec<SearchHit>> {
+        query::search_hits(self, raw, opts)
+    }
+}
@@ -0,0 +1,421 @@
+// libmarlin/src/store_tests.rs
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+
+use super::db::TagMerge;
+use super::exec::ExecTemplate;
+use super::globs::FileGlob;
+use super::query::{SearchHit, SearchOptions};
+use super::store::Store;
+use super::test_utils::db_with_files;
+use super::{config::Config, tag_in_chunks, Marlin};
+
+/// A backend without SQLite: plain maps, substring search over paths.
+#[derive(Default)]
+struct MemStore(RefCell<Mem>);
+
+#[derive(Default)]
+struct Mem {
+    files: BTreeMap<i64, String>,
+    /// `(name, parent)`; a tag's id is its index plus one.
+    tags: Vec<(String, Option<i64>)>,
+    file_tags: BTreeSet<(i64, i64)>,
+    attrs: BTreeMap<(i64, String), String>,
+    links: BTreeSet<(i64, i64, Option<String>)>,
+    settings: BTreeMap<String, String>,
+}
+
+impl MemStore {
+    fn with_files(paths: &[&str]) -> Self {
+        let store = Self::default();
+        for (i, p) in paths.iter().enumerate() {
+            store
+                .0
+                .borrow_mut()
+                .files
+                .insert(i as i64 + 1, p.to_string());
+        }
+        store
+    }
+
+    fn tag_id(&self, name: &str, parent: Option<i64>) -> Option<i64> {
+        self.0
+            .borrow()
+            .tags
+            .iter()
+            .position(|(n, p)| n == name && *p == parent)
+            .map(|i| i as i64 + 1)
+    }
+
+    fn tag_path(&self, id: i64) -> String {
+        let (name, parent) = self.0.borrow().tags[id as usize - 1].clone();
+        match parent {
+            Some(p) => format!("{}/{name}", self.tag_path(p)),
+            None => name,
+        }
+    }
+}
+
+impl Store for MemStore {
+    fn find_file(&self, path: &str) -> Result<Option<i64>> {
+        let mem = self.0.borrow();
+        Ok(mem
+            .files
+            .iter()
+            .find(|(_, p)| *p == path)
+            .map(|(id, _)| *id))
+    }
+
+    fn file_path(&self, file_id: i64) -> Result<String> {
+        self.0
+            .borrow()
+            .files
+            .get(&file_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no indexed file with id {file_id}"))
+    }
+
+    fn match_files(&self, pattern: &str) -> Result<Vec<(i64, String)>> {
+        let glob = FileGlob::new(pattern)?;
+        let mut out: Vec<_> = self
+            .0
+            .borrow()
+            .files
+            .iter()
+            .filter(|(_, p)| glob.matches(p.rsplit('/').next().unwrap_or(p)))
+            .map(|(id, p)| (*id, p.clone()))
+            .collect();
+        out.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(out)
+    }
+
+    fn ensure_tag_path(&self, path: &str) -> Result<i64> {
+        let mut parent = None;
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            let id = match self.tag_id(segment, parent) {
+                Some(id) => id,
+                None => {
+                    let mut mem = self.0.borrow_mut();
+                    mem.tags.push((segment.to_string(), parent));
+                    mem.tags.len() as i64
+                }
+            };
+            parent = Some(id);
+        }
+        parent.ok_or_else(|| anyhow::anyhow!("empty tag path"))
+    }
+
+    fn find_tag_path(&self, path: &str) -> Result<Option<i64>> {
+        let mut parent = None;
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            match self.tag_id(segment, parent) {
+                Some(id) => parent = Some(id),
+                None => return Ok(None),
+            }
+        }
+        Ok(parent)
+    }
+
+    fn tag_ancestry(&self, leaf: i64) -> Result<Vec<i64>> {
+        let mut ids = vec![leaf];
+        while let Some(parent) = self.0.borrow().tags[*ids.last().unwrap() as usize - 1].1 {
+            ids.push(parent);
+        }
+        Ok(ids)
+    }
+
+    fn attach_tag(&self, file_id: i64, tag_id: i64, _at: i64) -> Result<TagMerge> {
+        Ok(if self.0.borrow_mut().file_tags.insert((file_id, tag_id)) {
+            TagMerge::Changed
+        } else {
+            TagMerge::Unchanged
+        })
+    }
+
+    fn detach_tag(&self, file_id: i64, tag_id: i64, _at: i64) -> Result<TagMerge> {
+        Ok(
+            if self.0.borrow_mut().file_tags.remove(&(file_id, tag_id)) {
+                TagMerge::Changed
+            } else {
+                TagMerge::Unchanged
+            },
+        )
+    }
+
+    fn file_tag_paths(&self, file_id: i64) -> Result<Vec<String>> {
+        let ids: Vec<i64> = self
+            .0
+            .borrow()
+            .file_tags
+            .iter()
+            .filter(|(f, _)| *f == file_id)
+            .map(|(_, t)| *t)
+            .collect();
+        let mut paths: Vec<String> = ids.into_iter().map(|t| self.tag_path(t)).collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn attr(&self, file_id: i64, key: &str) -> Result<Option<String>> {
+        Ok(self.0.borrow().attrs.get(&(file_id, key.into())).cloned())
+    }
+
+    fn file_attrs(&self, file_id: i64) -> Result<Vec<(String, String, Option<i64>)>> {
+        Ok(self
+            .0
+            .borrow()
+            .attrs
+            .iter()
+            .filter(|((f, _), _)| *f == file_id)
+            .map(|((_, k), v)| (k.clone(), v.clone(), None))
+            .collect())
+    }
+
+    fn upsert_attr(&self, file_id: i64, key: &str, value: &str) -> Result<()> {
+        self.0
+            .borrow_mut()
+            .attrs
+            .insert((file_id, key.into()), value.into());
+        Ok(())
+    }
+
+    fn add_link(&self, src: i64, dst: i64, link_type: Option<&str>) -> Result<()> {
+        self.0
+            .borrow_mut()
+            .links
+            .insert((src, dst, link_type.map(str::to_string)));
+        Ok(())
+    }
+
+    fn remove_link(&self, src: i64, dst: i64, link_type: Option<&str>) -> Result<()> {
+        self.0
+            .borrow_mut()
+            .links
+            .remove(&(src, dst, link_type.map(str::to_string)));
+        Ok(())
+    }
+
+    fn setting(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.0.borrow().settings.get(key).cloned())
+    }
+
+    fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.0
+            .borrow_mut()
+            .settings
+            .insert(key.into(), value.into());
+        Ok(())
+    }
+
+    fn search_hits(&self, raw: &str, _opts: &SearchOptions) -> Result<Vec<SearchHit>> {
+        let needle = raw.to_lowercase();
+        Ok(self
+            .0
+            .borrow()
+            .files
+            .iter()
+            .filter(|(_, p)| p.to_lowercase().contains(&needle))
+            .map(|(id, p)| SearchHit {
+                file_id: *id,
+                path: p.clone(),
+            })
+            .collect())
+    }
+}
+
+const PATHS: [&str; 2] = ["/d/notes.md", "/d/report.pdf"];
+
+/// The same checks, whatever the backend.
+fn exercise(store: &dyn Store) {
+    let notes = store.file_id("/d/notes.md").unwrap();
+    let report = store.file_id("/d/report.pdf").unwrap();
+    assert!(store.file_id("/d/missing").is_err());
+    assert_eq!(store.find_file("/d/missing").unwrap(), None);
+    assert_eq!(store.file_path(report).unwrap(), "/d/report.pdf");
+    assert_eq!(
+        store.match_files("*.md").unwrap(),
+        [(notes, "/d/notes.md".to_string())]
+    );
+
+    let leaf = store.ensure_tag_path("project/alpha").unwrap();
+    assert_eq!(store.ensure_tag_path("project/alpha").unwrap(), leaf);
+    assert_eq!(store.find_tag_path("project/alpha").unwrap(), Some(leaf));
+    assert_eq!(store.find_tag_path("project/beta").unwrap(), None);
+    let ids = store.tag_ancestry(leaf).unwrap();
+    assert_eq!(ids.len(), 2);
+    for &id in &ids {
+        assert_eq!(store.attach_tag(notes, id, 1).unwrap(), TagMerge::Changed);
+    }
+    assert_eq!(
+        store.attach_tag(notes, leaf, 2).unwrap(),
+        TagMerge::Unchanged
+    );
+    assert_eq!(
+        store.file_tag_paths(notes).unwrap(),
+        ["project", "project/alpha"]
+    );
+    assert_eq!(store.detach_tag(notes, leaf, 3).unwrap(), TagMerge::Changed);
+    assert_eq!(store.file_tag_paths(notes).unwrap(), ["project"]);
+
+    store.upsert_attr(report, "owner", "ana").unwrap();
+    store.upsert_attr(report, "owner", "bo").unwrap();
+    assert_eq!(store.attr(report, "owner").unwrap().as_deref(), Some("bo"));
+    assert_eq!(store.attr(notes, "owner").unwrap(), None);
+    assert_eq!(store.file_attrs(report).unwrap().len(), 1);
+
+    store.add_link(notes, report, Some("cites")).unwrap();
+    store.remove_link(notes, report, Some("cites")).unwrap();
+
+    store.set_setting("tags.strict", "true").unwrap();
+    assert_eq!(
+        store.setting("tags.strict").unwrap().as_deref(),
+        Some("true")
+    );
+
+    let hits = store
+        .search_hits("report", &SearchOptions::default())
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].file_id, report);
+
+    let line = ExecTemplate::parse("tool {tags} {attr:owner}")
+        .render(store, "/d/notes.md")
+        .unwrap();
+    assert_eq!(shlex::split(&line).unwrap(), ["tool", "project", ""]);
+}
+
+/// Tagging and attributes through the facade, whatever the backend.
+fn exercise_marlin<S: Store>(store: S) {
+    let mut m = Marlin::with_store(Config::for_db_path(":memory:"), store);
+    assert_eq!(m.tag("*.md", "project/alpha").unwrap(), 1);
+    assert_eq!(m.tag("*.md", "project/alpha").unwrap(), 0);
+    assert_eq!(m.attr_set("*.pdf", "owner", "ana").unwrap(), 1);
+    {
+        let store = m.store();
+        let notes = store.file_id("/d/notes.md").unwrap();
+        let report = store.file_id("/d/report.pdf").unwrap();
+        assert_eq!(
+            store.file_tag_paths(notes).unwrap(),
+            ["project", "project/alpha"]
+        );
+        assert_eq!(store.attr(report, "owner").unwrap().as_deref(), Some("ana"));
+        store.set_setting("tags.strict", "true").unwrap();
+    }
+    assert!(m.tag("*.md", "project/beta").is_err());
+}
+
+#[test]
+fn sqlite_connection_is_a_store() {
+    exercise(&db_with_files(&PATHS));
+    exercise_marlin(db_with_files(&PATHS));
+}
+
+#[test]
+fn other_backends_plug_in() {
+    exercise(&MemStore::with_files(&PATHS));
+    exercise_marlin(MemStore::with_files(&PATHS));
+}
+
+/// SQLite underneath, counting the tags attached through the trait.
+struct Counting {
+    conn: rusqlite::Connection,
+    attached: Cell<usize>,
+}
+
+impl Store for Counting {
+    fn find_file(&self, path: &str) -> Result<Option<i64>> {
+        self.conn.find_file(path)
+    }
+
+    fn file_path(&self, file_id: i64) -> Result<String> {
+        self.conn.file_path(file_id)
+    }
+
+    fn match_files(&self, pattern: &str) -> Result<Vec<(i64, String)>> {
+        self.conn.match_files(pattern)
+    }
+
+    fn ensure_tag_path(&self, path: &str) -> Result<i64> {
+        self.conn.ensure_tag_path(path)
+    }
+
+    fn find_tag_path(&self, path: &str) -> Result<Option<i64>> {
+        self.conn.find_tag_path(path)
+    }
+
+    fn tag_ancestry(&self, leaf: i64) -> Result<Vec<i64>> {
+        self.conn.tag_ancestry(leaf)
+    }
+
+    fn attach_tag(&self, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge> {
+        self.attached.set(self.attached.get() + 1);
+        self.conn.attach_tag(file_id, tag_id, at)
+    }
+
+    fn detach_tag(&self, file_id: i64, tag_id: i64, at: i64) -> Result<TagMerge> {
+        self.conn.detach_tag(file_id, tag_id, at)
+    }
+
+    fn file_tag_paths(&self, file_id: i64) -> Result<Vec<String>> {
+        self.conn.file_tag_paths(file_id)
+    }
+
+    /// The SQLite path, paging through `conn` one file at a time.
+    fn tag_glob(&self, pattern: &str, tag_path: &str) -> Result<usize> {
+        tag_in_chunks(self, &self.conn, pattern, tag_path, 1)
+    }
+
+    fn attr(&self, file_id: i64, key: &str) -> Result<Option<String>> {
+        self.conn.attr(file_id, key)
+    }
+
+    fn file_attrs(&self, file_id: i64) -> Result<Vec<(String, String, Option<i64>)>> {
+        self.conn.file_attrs(file_id)
+    }
+
+    fn upsert_attr(&self, file_id: i64, key: &str, value: &str) -> Result<()> {
+        self.conn.upsert_attr(file_id, key, value)
+    }
+
+    fn add_link(&self, src_file_id: i64, dst_file_id: i64, link_type: Option<&str>) -> Result<()> {
+        self.conn.add_link(src_file_id, dst_file_id, link_type)
+    }
+
+    fn remove_link(
+        &self,
+        src_file_id: i64,
+        dst_file_id: i64,
+        link_type: Option<&str>,
+    ) -> Result<()> {
+        self.conn.remove_link(src_file_id, dst_file_id, link_type)
+    }
+
+    fn setting(&self, key: &str) -> Result<Option<String>> {
+        self.conn.setting(key)
+    }
+
+    fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.set_setting(key, value)
+    }
+
+    fn search_hits(&self, raw: &str, opts: &SearchOptions) -> Result<Vec<SearchHit>> {
+        self.conn.search_hits(raw, opts)
+    }
+}
+
+#[test]
+fn sqlite_tagging_writes_through_the_store() {
+    let store = Counting {
+        conn: db_with_files(&PATHS),
+        attached: Cell::new(0),
+    };
+    let mut m = Marlin::with_store(Config::for_db_path(":memory:"), store);
+    assert_eq!(m.tag("*.md", "project/alpha").unwrap(), 1);
+    // one file, the tag and its parent
+    assert_eq!(m.store().attached.get(), 2);
+    assert_eq!(m.tag("*.md", "project/alpha").unwrap(), 0);
+    assert_eq!(m.store().attached.get(), 4);
+}
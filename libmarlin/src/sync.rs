@@ -0,0 +1,523 @@
+//! Two-way replication of metadata through an append-only op-log
+//! (`marlin sync`).
+//!
+//! Triggers record every change to tags, attributes and links in the
+//! `sync_ops` table, stamped with this index's replica id and the time.
+//! [`export`] reads the ops after a cursor; [`write_ops`] turns them into
+//! lines of a log file that a file synchroniser (Syncthing, Dropbox …)
+//! carries to the other machines, where [`read_ops`] and [`import`] apply
+//! them.  Databases themselves are never synchronised, so there are no
+//! conflicting copies of them.
+//!
+//! Each replica appends to a log of its own and imports everyone else's,
+//! as often as it likes: ops are identified by replica and number, so
+//! importing a log twice changes nothing.  Imported ops are kept and
+//! exported again, which lets changes hop across replicas that never see
+//! each other's logs.
+//!
+//! Concurrent changes are merged per target – a file's tag, attribute key
+//! or link – by last writer wins.  Tags go through
+//! [`db::attach_tag`]/[`db::detach_tag`], whose tombstones make the
+//! outcome independent of the order ops arrive in (ties go to the
+//! attach).  Attribute and link ops are applied only if the log holds no
+//! newer op on the same target (ties go to the larger replica id).  All
+//! replicas converge once they have seen the same ops.  [`import_with`]
+//! can settle attribute values and link types that disagree with the
+//! index by a strategy or by asking instead (see [`crate::conflicts`]);
+//! replicas then no longer necessarily converge.
+//!
+//! Paths are stored as they are on this machine.  With a `root`, exports
+//! write paths below it relative to it and skip the rest, and imports
+//! resolve relative paths against their own root, so the same folder may
+//! live in different places.
+//!
+//! Log lines are tab separated – `origin seq ts op path name value` – with
+//! `\\`, `\t`, `\n` and `\r` escaped and `\N` for "no value"; lines starting
+//! with `#` are comments.
+
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::{debug, warn};
+
+use crate::conflicts::OnConflict;
+use crate::db::{self, TagMerge};
+use crate::lock;
+
+/// Setting holding this index's replica id.
+pub const REPLICA_KEY: &str = "sync.replica";
+/// Setting that silences the op-log triggers during an [`import`].
+const APPLYING_KEY: &str = "sync.applying";
+/// First line of a log file.
+pub const LOG_HEADER: &str = "# marlin sync log v1";
+
+/// What an [`Op`] does to its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+    TagAdd,
+    TagRemove,
+    AttrSet,
+    AttrRemove,
+    LinkAdd,
+    LinkRemove,
+}
+
+impl OpKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OpKind::TagAdd => "tag+",
+            OpKind::TagRemove => "tag-",
+            OpKind::AttrSet => "attr=",
+            OpKind::AttrRemove => "attr-",
+            OpKind::LinkAdd => "link+",
+            OpKind::LinkRemove => "link-",
+        }
+    }
+
+    /// Both kinds acting on the same sort of target as `self`.
+    fn family(self) -> [OpKind; 2] {
+        match self {
+            OpKind::TagAdd | OpKind::TagRemove => [OpKind::TagAdd, OpKind::TagRemove],
+            OpKind::AttrSet | OpKind::AttrRemove => [OpKind::AttrSet, OpKind::AttrRemove],
+            OpKind::LinkAdd | OpKind::LinkRemove => [OpKind::LinkAdd, OpKind::LinkRemove],
+        }
+    }
+
+    fn is_link(self) -> bool {
+        matches!(self, OpKind::LinkAdd | OpKind::LinkRemove)
+    }
+
+    fn is_tag(self) -> bool {
+        matches!(self, OpKind::TagAdd | OpKind::TagRemove)
+    }
+}
+
+impl fmt::Display for OpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OpKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "tag+" => OpKind::TagAdd,
+            "tag-" => OpKind::TagRemove,
+            "attr=" => OpKind::AttrSet,
+            "attr-" => OpKind::AttrRemove,
+            "link+" => OpKind::LinkAdd,
+            "link-" => OpKind::LinkRemove,
+            other => bail!("unknown sync op `{other}`"),
+        })
+    }
+}
+
+/// One metadata change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Op {
+    /// Replica that made the change.
+    pub origin: String,
+    /// Its number there; increasing per origin.
+    pub seq: i64,
+    /// When the change was made, in Unix milliseconds.
+    pub ts: i64,
+    pub kind: OpKind,
+    /// The file whose metadata changed (a link's source).
+    pub path: String,
+    /// Tag path, attribute key or link target.
+    pub name: String,
+    /// Attribute value or link type.
+    pub value: Option<String>,
+}
+
+/// This index's replica id.
+pub fn replica_id(conn: &Connection) -> Result<String> {
+    db::setting(conn, REPLICA_KEY)?.ok_or_else(|| anyhow!("index has no sync replica id"))
+}
+
+/// Ops exported by [`export`].
+#[derive(Debug, Clone, Default)]
+pub struct Export {
+    pub ops: Vec<Op>,
+    /// Pass as `since` next time to get only newer ops.
+    pub cursor: i64,
+}
+
+/// Every op recorded after cursor `since` (0 for all), oldest first.  With
+/// `root`, only ops whose paths lie below it, written relative to it.
+pub fn export(conn: &Connection, since: i64, root: Option<&Path>) -> Result<Export> {
+    let mut stmt = conn.prepare(
+        "SELECT seq, origin, COALESCE(origin_seq, seq), ts, op, path, name, value
+           FROM sync_ops WHERE seq > ?1 ORDER BY seq",
+    )?;
+    let mut rows = stmt.query([since])?;
+    let mut out = Export {
+        ops: Vec::new(),
+        cursor: since,
+    };
+    while let Some(r) = rows.next()? {
+        out.cursor = r.get(0)?;
+        let kind: OpKind = r.get::<_, String>(4)?.parse()?;
+        let mut op = Op {
+            origin: r.get(1)?,
+            seq: r.get(2)?,
+            ts: r.get(3)?,
+            kind,
+            path: r.get(5)?,
+            name: r.get(6)?,
+            value: r.get(7)?,
+        };
+        if let Some(root) = root {
+            let Some(path) = relative(&op.path, root) else {
+                continue;
+            };
+            op.path = path;
+            if kind.is_link() {
+                let Some(target) = relative(&op.name, root) else {
+                    continue;
+                };
+                op.name = target;
+            }
+        }
+        out.ops.push(op);
+    }
+    Ok(out)
+}
+
+fn relative(path: &str, root: &Path) -> Option<String> {
+    Path::new(path)
+        .strip_prefix(root)
+        .ok()
+        .map(|p| p.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/"))
+}
+
+fn absolute(path: &str, root: Option<&Path>) -> Result<String> {
+    if Path::new(path).is_absolute() {
+        return Ok(path.to_string());
+    }
+    let root =
+        root.with_context(|| format!("log has relative path `{path}`; pass the folder's root"))?;
+    Ok(root
+        .join(path.replace('/', std::path::MAIN_SEPARATOR_STR))
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Outcome of [`import`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Ops applied to the index.
+    pub applied: usize,
+    /// Ops recorded but not applied because a newer op on the same target
+    /// was already known.
+    pub superseded: usize,
+    /// Ops seen before, including this replica's own.
+    pub duplicate: usize,
+    /// Ops about files (or tags to remove) not in this index.
+    pub missing: usize,
+    /// Ops refused by a validation rule or a lock.
+    pub rejected: usize,
+    /// Ops disagreeing with the index that `on_conflict` settled.
+    pub conflicts: usize,
+}
+
+/// Apply `ops` from other replicas in one transaction; see the module
+/// docs for how conflicts are resolved.  Relative paths are resolved
+/// against `root`.
+pub fn import(conn: &mut Connection, ops: &[Op], root: Option<&Path>) -> Result<ImportReport> {
+    import_with(conn, ops, root, OnConflict::Default)
+}
+
+/// [`import`], settling attribute values and link types that disagree
+/// with the index through `on_conflict` rather than by age.
+pub fn import_with(
+    conn: &mut Connection,
+    ops: &[Op],
+    root: Option<&Path>,
+    mut on_conflict: OnConflict<'_>,
+) -> Result<ImportReport> {
+    let me = replica_id(conn)?;
+    let m = db::Mutation::new("sync_import");
+    let tx = conn.savepoint()?;
+    db::set_setting(&tx, APPLYING_KEY, "1")?;
+    let mut report = ImportReport::default();
+    for op in ops {
+        if op.origin == me {
+            report.duplicate += 1;
+            continue;
+        }
+        let mut op = op.clone();
+        op.path = absolute(&op.path, root)?;
+        if op.kind.is_link() {
+            op.name = absolute(&op.name, root)?;
+        }
+
+        let new = tx.execute(
+            "INSERT OR IGNORE INTO sync_ops(origin, origin_seq, ts, op, path, name, value)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                op.origin,
+                op.seq,
+                op.ts,
+                op.kind.as_str(),
+                op.path,
+                op.name,
+                op.value
+            ],
+        )?;
+        if new == 0 {
+            report.duplicate += 1;
+            continue;
+        }
+        let seq = tx.last_insert_rowid();
+        if settle(&tx, &op, &mut on_conflict)? {
+            report.conflicts += 1;
+            continue;
+        }
+        // tags merge through their own timestamps (db::attach_tag)
+        if !op.kind.is_tag() && newer_op_known(&tx, &op, seq)? {
+            debug!(op = %op.kind, path = %op.path, name = %op.name, "superseded");
+            report.superseded += 1;
+            continue;
+        }
+        match apply(&tx, &op) {
+            Ok(Applied::Yes) => report.applied += 1,
+            Ok(Applied::Superseded) => report.superseded += 1,
+            Ok(Applied::Missing) => report.missing += 1,
+            Err(e) => {
+                warn!(op = %op.kind, path = %op.path, name = %op.name, error = %e, "sync op rejected");
+                report.rejected += 1;
+            }
+        }
+    }
+    db::unset_setting(&tx, APPLYING_KEY)?;
+    tx.commit()?;
+    m.done(report.applied);
+    Ok(report)
+}
+
+/// Whether the log holds an op on the same target as `op` (stored as
+/// `seq`) that is newer, ties going to the larger replica id.
+fn newer_op_known(conn: &Connection, op: &Op, seq: i64) -> Result<bool> {
+    let [a, b] = op.kind.family();
+    Ok(conn.query_row(
+        "SELECT EXISTS(
+             SELECT 1 FROM sync_ops
+              WHERE path = ?1 AND name = ?2 AND op IN (?3, ?4)
+                AND (?5 = 0 OR value IS ?6)
+                AND seq != ?7
+                AND (ts > ?8 OR (ts = ?8 AND origin > ?9)))",
+        params![
+            op.path,
+            op.name,
+            a.as_str(),
+            b.as_str(),
+            op.kind.is_link(),
+            op.value,
+            seq,
+            op.ts,
+            op.origin
+        ],
+        |r| r.get(0),
+    )?)
+}
+
+/// Settle `op` through `on_conflict` if it disagrees with the index;
+/// whether it did.  Locked files are left to [`apply`] to reject.
+fn settle(conn: &Connection, op: &Op, on_conflict: &mut OnConflict<'_>) -> Result<bool> {
+    if matches!(on_conflict, OnConflict::Default) {
+        return Ok(false);
+    }
+    let Some(file) = db::find_file_id(conn, &op.path)? else {
+        return Ok(false);
+    };
+    if lock::check(conn, file).is_err() {
+        return Ok(false);
+    }
+    let settled = match op.kind {
+        OpKind::AttrSet => on_conflict.settle_attr(
+            conn,
+            "sync import",
+            file,
+            &op.path,
+            &op.name,
+            op.value.as_deref().unwrap_or(""),
+        )?,
+        OpKind::LinkAdd => match db::find_file_id(conn, &op.name)? {
+            Some(target) => on_conflict.settle_link(
+                conn,
+                "sync import",
+                (file, target),
+                &op.path,
+                &op.name,
+                op.value.as_deref(),
+            )?,
+            None => None,
+        },
+        _ => None,
+    };
+    Ok(settled.is_some())
+}
+
+enum Applied {
+    Yes,
+    Superseded,
+    Missing,
+}
+
+/// Make `op` true in the index.
+fn apply(conn: &Connection, op: &Op) -> Result<Applied> {
+    let id = |path: &str| -> Result<Option<i64>> {
+        Ok(conn
+            .query_row("SELECT id FROM files WHERE path = ?1", [path], |r| r.get(0))
+            .optional()?)
+    };
+    let Some(file) = id(&op.path)? else {
+        return Ok(Applied::Missing);
+    };
+    match op.kind {
+        OpKind::TagAdd | OpKind::TagRemove => {
+            lock::check(conn, file)?;
+            // a detach of an unknown tag still leaves its tombstone
+            let tag = db::ensure_tag_path(conn, &op.name)?;
+            let merge = if op.kind == OpKind::TagAdd {
+                db::attach_tag(conn, file, tag, op.ts)?
+            } else {
+                db::detach_tag(conn, file, tag, op.ts)?
+            };
+            if merge == TagMerge::Superseded {
+                return Ok(Applied::Superseded);
+            }
+        }
+        OpKind::AttrSet => {
+            db::upsert_attr(conn, file, &op.name, op.value.as_deref().unwrap_or(""))?;
+        }
+        OpKind::AttrRemove => {
+            lock::check(conn, file)?;
+            let m = db::Mutation::new("remove_attr").file_id(file);
+            m.done(conn.execute(
+                "DELETE FROM attributes WHERE file_id = ?1 AND key = ?2",
+                params![file, op.name],
+            )?);
+        }
+        OpKind::LinkAdd | OpKind::LinkRemove => {
+            let Some(target) = id(&op.name)? else {
+                return Ok(Applied::Missing);
+            };
+            if op.kind == OpKind::LinkAdd {
+                db::add_link(conn, file, target, op.value.as_deref())?;
+            } else {
+                db::remove_link(conn, file, target, op.value.as_deref())?;
+            }
+        }
+    }
+    Ok(Applied::Yes)
+}
+
+/// Number of ops recorded and the newest seq (the cursor an export of
+/// everything ends at).
+pub fn log_stats(conn: &Connection) -> Result<(usize, i64)> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*), IFNULL(MAX(seq), 0) FROM sync_ops",
+        [],
+        |r| Ok((r.get::<_, i64>(0)? as usize, r.get(1)?)),
+    )?)
+}
+
+/* ─── log file format ─────────────────────────────────────────────── */
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next() {
+            Some('\\') => '\\',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            other => bail!(
+                "bad escape `\\{}`",
+                other.map(String::from).unwrap_or_default()
+            ),
+        });
+    }
+    Ok(out)
+}
+
+/// Write `ops` as log lines.
+pub fn write_ops(w: &mut impl Write, ops: &[Op]) -> Result<()> {
+    for op in ops {
+        let value = op
+            .value
+            .as_deref()
+            .map_or_else(|| "\\N".to_string(), escape);
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{value}",
+            escape(&op.origin),
+            op.seq,
+            op.ts,
+            op.kind,
+            escape(&op.path),
+            escape(&op.name),
+        )?;
+    }
+    Ok(())
+}
+
+/// Parse log lines; errors name the line.
+pub fn read_ops(r: impl BufRead) -> Result<Vec<Op>> {
+    let mut ops = Vec::new();
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        ops.push(parse_line(&line).with_context(|| format!("sync log line {}", i + 1))?);
+    }
+    Ok(ops)
+}
+
+fn parse_line(line: &str) -> Result<Op> {
+    let f: Vec<&str> = line.split('\t').collect();
+    let [origin, seq, ts, kind, path, name, value] = f[..] else {
+        bail!("expected 7 tab-separated fields, got {}", f.len());
+    };
+    Ok(Op {
+        origin: unescape(origin)?,
+        seq: seq.parse().context("bad seq")?,
+        ts: ts.parse().context("bad timestamp")?,
+        kind: kind.parse()?,
+        path: unescape(path)?,
+        name: unescape(name)?,
+        value: if value == "\\N" {
+            None
+        } else {
+            Some(unescape(value)?)
+        },
+    })
+}
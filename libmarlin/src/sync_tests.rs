@@ -0,0 +1,221 @@
+// libmarlin/src/sync_tests.rs
+
+use super::db;
+use super::sync::{self, Op, OpKind};
+use super::test_utils::db_with_files;
+use rusqlite::Connection;
+use std::path::Path;
+
+fn replica(root: &str) -> Connection {
+    db_with_files(&[&format!("{root}/a.txt"), &format!("{root}/b.txt")])
+}
+
+fn tags(conn: &Connection, path: &str) -> Vec<String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.name FROM file_tags ft
+               JOIN tags t ON t.id = ft.tag_id
+               JOIN files f ON f.id = ft.file_id
+              WHERE f.path = ?1 ORDER BY t.name",
+        )
+        .unwrap();
+    stmt.query_map([path], |r| r.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn changes_replicate_between_differently_rooted_replicas() {
+    let a = replica("/home/ann/docs");
+    let mut b = replica("/Users/ann/docs");
+    let root_a = Path::new("/home/ann/docs");
+    let root_b = Path::new("/Users/ann/docs");
+
+    let files = db::match_files(&a, "a.txt").unwrap();
+    db::tag_files(&a, &files, "project/alpha", true).unwrap();
+    let fa = db::file_id(&a, "/home/ann/docs/a.txt").unwrap();
+    let fb = db::file_id(&a, "/home/ann/docs/b.txt").unwrap();
+    db::upsert_attr(&a, fa, "owner", "ann").unwrap();
+    db::upsert_attr(&a, fa, "meta/lang", "en").unwrap(); // derived, not synced
+    db::add_link(&a, fa, fb, Some("ref")).unwrap();
+
+    let export = sync::export(&a, 0, Some(root_a)).unwrap();
+    assert!(export.ops.iter().all(|op| !op.path.starts_with('/')));
+    let mut log = Vec::new();
+    sync::write_ops(&mut log, &export.ops).unwrap();
+    let ops = sync::read_ops(&log[..]).unwrap();
+    assert_eq!(ops, export.ops);
+
+    let report = sync::import(&mut b, &ops, Some(root_b)).unwrap();
+    assert_eq!(report.applied, 4); // project, project/alpha, owner, link
+    assert_eq!(tags(&b, "/Users/ann/docs/a.txt"), vec!["alpha", "project"]);
+    let id = db::file_id(&b, "/Users/ann/docs/a.txt").unwrap();
+    assert_eq!(
+        db::file_attrs(&b, id).unwrap(),
+        vec![("owner".to_string(), "ann".to_string(), None)]
+    );
+    assert_eq!(
+        db::list_links(&b, "%a.txt", None, None).unwrap()[0].1,
+        "/Users/ann/docs/b.txt"
+    );
+
+    // importing again is a no-op, and B's log does not echo the ops back
+    let again = sync::import(&mut b, &ops, Some(root_b)).unwrap();
+    assert_eq!(again.duplicate, ops.len());
+    let (_, cursor) = sync::log_stats(&a).unwrap();
+    assert_eq!(cursor, export.cursor);
+    let relay = sync::export(&b, 0, Some(root_b)).unwrap();
+    let back = sync::import(&mut replica_from(&a), &relay.ops, Some(root_a)).unwrap();
+    assert_eq!(back.duplicate, relay.ops.len());
+}
+
+/// A second handle on the same in-memory state is not possible, so the
+/// echo check runs against a copy carrying A's replica id.
+fn replica_from(src: &Connection) -> Connection {
+    let conn = replica("/home/ann/docs");
+    db::set_setting(&conn, sync::REPLICA_KEY, &sync::replica_id(src).unwrap()).unwrap();
+    conn
+}
+
+#[test]
+fn last_writer_wins_per_target() {
+    let mut conn = replica("/r");
+    let op = |origin: &str, seq, ts, kind, value: Option<&str>| Op {
+        origin: origin.into(),
+        seq,
+        ts,
+        kind,
+        path: "/r/a.txt".into(),
+        name: "status".into(),
+        value: value.map(Into::into),
+    };
+    let ops = [
+        op("x", 1, 2_000, OpKind::AttrSet, Some("done")),
+        // older, arrives later: recorded but not applied
+        op("y", 1, 1_000, OpKind::AttrSet, Some("draft")),
+        // same time: the larger replica id wins
+        op("w", 2, 2_000, OpKind::AttrRemove, None),
+    ];
+    let report = sync::import(&mut conn, &ops, None).unwrap();
+    assert_eq!((report.applied, report.superseded), (1, 2));
+    let id = db::file_id(&conn, "/r/a.txt").unwrap();
+    assert_eq!(db::file_attrs(&conn, id).unwrap()[0].1, "done");
+
+    let remove = op("z", 1, 3_000, OpKind::AttrRemove, None);
+    sync::import(&mut conn, &[remove], None).unwrap();
+    assert!(db::file_attrs(&conn, id).unwrap().is_empty());
+
+    let missing = Op {
+        path: "/r/gone.txt".into(),
+        ..op("z", 2, 4_000, OpKind::TagAdd, None)
+    };
+    assert_eq!(
+        sync::import(&mut conn, &[missing], None).unwrap().missing,
+        1
+    );
+}
+
+#[test]
+fn log_lines_escape_separators() {
+    let op = Op {
+        origin: "abc".into(),
+        seq: 7,
+        ts: 1,
+        kind: OpKind::AttrSet,
+        path: "/r/tab\there.txt".into(),
+        name: "note".into(),
+        value: Some("line1\nline2 \\ end".into()),
+    };
+    let mut log = sync::LOG_HEADER.as_bytes().to_vec();
+    log.push(b'\n');
+    sync::write_ops(&mut log, std::slice::from_ref(&op)).unwrap();
+    assert_eq!(String::from_utf8_lossy(&log).lines().count(), 2);
+    assert_eq!(sync::read_ops(&log[..]).unwrap(), vec![op]);
+
+    let err = sync::read_ops(&b"abc\t1\n"[..]).unwrap_err();
+    assert!(format!("{err:#}").contains("line 1"), "{err:#}");
+}
+
+#[test]
+fn tag_ops_converge_whatever_order_they_arrive_in() {
+    let op = |origin: &str, ts, kind| Op {
+        origin: origin.into(),
+        seq: 1,
+        ts,
+        kind,
+        path: "/r/a.txt".into(),
+        name: "draft".into(),
+        value: None,
+    };
+    let ops = [
+        op("x", 2_000, OpKind::TagAdd),
+        op("y", 1_000, OpKind::TagRemove),
+    ];
+    for order in [[0, 1], [1, 0]] {
+        let mut conn = replica("/r");
+        for i in order {
+            sync::import(&mut conn, std::slice::from_ref(&ops[i]), None).unwrap();
+        }
+        assert_eq!(tags(&conn, "/r/a.txt"), vec!["draft"], "order {order:?}");
+    }
+}
+
+#[test]
+fn strategies_settle_and_record_attr_conflicts() {
+    use super::conflicts::{self, OnConflict, Resolution, Strategy};
+
+    let set = |seq, value: &str| Op {
+        origin: "x".into(),
+        seq,
+        ts: 1_000 * seq,
+        kind: OpKind::AttrSet,
+        path: "/r/a.txt".into(),
+        name: "status".into(),
+        value: Some(value.into()),
+    };
+    let mut conn = replica("/r");
+    let id = db::file_id(&conn, "/r/a.txt").unwrap();
+    db::upsert_attr(&conn, id, "status", "draft").unwrap();
+    let status = |conn: &Connection, key| db::attr_value(conn, id, key).unwrap();
+
+    let mine = OnConflict::Strategy(Strategy::Mine);
+    let report = sync::import_with(&mut conn, &[set(1, "done")], None, mine).unwrap();
+    assert_eq!((report.applied, report.conflicts), (0, 1));
+    assert_eq!(status(&conn, "status").as_deref(), Some("draft"));
+
+    let both = OnConflict::Strategy(Strategy::Both);
+    sync::import_with(&mut conn, &[set(2, "review")], None, both).unwrap();
+    assert_eq!(status(&conn, "status").as_deref(), Some("draft"));
+    assert_eq!(status(&conn, "status.theirs").as_deref(), Some("review"));
+
+    let ask = OnConflict::Ask(Box::new(|c| {
+        assert_eq!((c.mine.as_str(), c.theirs.as_str()), ("draft", "final"));
+        Ok(Resolution::Edit("final!".into()))
+    }));
+    sync::import_with(&mut conn, &[set(3, "final")], None, ask).unwrap();
+    assert_eq!(status(&conn, "status").as_deref(), Some("final!"));
+
+    // agreeing values are no conflict
+    let theirs = OnConflict::Strategy(Strategy::Theirs);
+    let report = sync::import_with(&mut conn, &[set(4, "final!")], None, theirs).unwrap();
+    assert_eq!(report.conflicts, 0);
+
+    let choices: Vec<_> = conflicts::decisions(&conn, 10)
+        .unwrap()
+        .into_iter()
+        .map(|d| (d.source, d.conflict.theirs, d.resolution))
+        .collect();
+    assert_eq!(
+        choices,
+        [
+            (
+                "sync import".into(),
+                "final".into(),
+                Resolution::Edit("final!".into())
+            ),
+            ("sync import".into(), "review".into(), Resolution::Both),
+            ("sync import".into(), "done".into(), Resolution::Mine),
+        ]
+    );
+}
@@ -1,8 +1,241 @@
-use std::sync::Mutex;
+//! Reproducible file trees for tests, benchmarks and demos.
+//!
+//! [`FixtureSpec`] describes a tree – explicit files plus any number of
+//! generated ones spread over `depth` levels of `fanout` directories – and
+//! [`fixture_tree`] writes it.  The same spec and seed always produce
+//! byte-identical trees.
+//!
+//! ```no_run
+//! use libmarlin::test_utils::{fixture_tree, FixtureSpec};
+//!
+//! let spec = FixtureSpec::demo().files(500).depth(2).fanout(4).seed(7);
+//! let paths = fixture_tree(std::path::Path::new("/tmp/demo"), &spec)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
 
+#[cfg(test)]
 use lazy_static::lazy_static;
+#[cfg(test)]
+use std::sync::Mutex;
 
+#[cfg(test)]
 lazy_static! {
     /// Global mutex to serialize environment-variable modifications in tests.
     pub static ref ENV_MUTEX: Mutex<()> = Mutex::new(());
 }
+
+/// A fresh in-memory index holding `paths` as empty files.
+#[cfg(test)]
+pub(crate) fn db_with_files(paths: &[&str]) -> rusqlite::Connection {
+    let conn = crate::db::open(":memory:").unwrap();
+    for p in paths {
+        add_file(&conn, p);
+    }
+    conn
+}
+
+/// Index `path` as an empty file; its id.
+#[cfg(test)]
+pub(crate) fn add_file(conn: &rusqlite::Connection, path: &str) -> i64 {
+    conn.execute(
+        "INSERT INTO files(path, size, mtime) VALUES (?1, 0, 0)",
+        [path],
+    )
+    .unwrap();
+    conn.last_insert_rowid()
+}
+
+/// Words generated file names start with; every one is a valid FTS token.
+pub const WORDS: [&str; 8] = [
+    "alpha", "report", "invoice", "draft", "notes", "photo", "budget", "todo",
+];
+
+/// Description of a fixture tree.
+#[derive(Debug, Clone)]
+pub struct FixtureSpec {
+    files: usize,
+    depth: usize,
+    fanout: usize,
+    min_size: usize,
+    max_size: usize,
+    types: Vec<String>,
+    seed: u64,
+    explicit: Vec<(PathBuf, String)>,
+}
+
+impl Default for FixtureSpec {
+    fn default() -> Self {
+        Self {
+            files: 0,
+            depth: 1,
+            fanout: 4,
+            min_size: 16,
+            max_size: 1024,
+            types: ["md", "txt", "log", "json"].map(String::from).to_vec(),
+            seed: 42,
+            explicit: Vec::new(),
+        }
+    }
+}
+
+impl FixtureSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The small hand-written layout used by the end-to-end tests and
+    /// `marlin demo`: projects with TODO drafts, a log and a report.
+    pub fn demo() -> Self {
+        Self::new()
+            .file("Projects/Alpha/draft1.md", "- [ ] TODO foo\n")
+            .file("Projects/Alpha/draft2.md", "- [x] TODO foo\n")
+            .file("Projects/Beta/final.md", "done\n")
+            .file("Projects/Gamma/TODO.txt", "TODO bar\n")
+            .file("Logs/app.log", "ERROR omg\n")
+            .file("Reports/Q1.pdf", "PDF\n")
+    }
+
+    /// Number of generated files (besides explicit ones).
+    pub fn files(mut self, n: usize) -> Self {
+        self.files = n;
+        self
+    }
+
+    /// Directory levels below the root for generated files (0 = flat).
+    pub fn depth(mut self, d: usize) -> Self {
+        self.depth = d;
+        self
+    }
+
+    /// Sub-directories per level.
+    pub fn fanout(mut self, f: usize) -> Self {
+        self.fanout = f;
+        self
+    }
+
+    /// Body size range in bytes (inclusive).
+    pub fn sizes(mut self, min: usize, max: usize) -> Self {
+        self.min_size = min;
+        self.max_size = max;
+        self
+    }
+
+    /// File extensions to cycle through.
+    pub fn types<S: Into<String>>(mut self, exts: impl IntoIterator<Item = S>) -> Self {
+        self.types = exts.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Seed for sizes and contents.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Add a file with fixed contents, relative to the root.
+    pub fn file(mut self, rel: impl Into<PathBuf>, body: impl Into<String>) -> Self {
+        self.explicit.push((rel.into(), body.into()));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.files > 0 && self.types.is_empty() {
+            bail!("fixture needs at least one file type");
+        }
+        if self.depth > 0 && self.fanout == 0 {
+            bail!("fanout must be at least 1 when depth > 0");
+        }
+        if self.min_size > self.max_size {
+            bail!(
+                "min size {} exceeds max size {}",
+                self.min_size,
+                self.max_size
+            );
+        }
+        for (rel, _) in &self.explicit {
+            if rel.is_absolute() || rel.components().any(|c| c.as_os_str() == "..") {
+                bail!("fixture path must stay inside the root: {}", rel.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Relative leaf directory for the random number `n`; each level
+    /// takes one base-`fanout` digit.
+    fn dir_for(&self, n: u64) -> PathBuf {
+        let mut dir = PathBuf::new();
+        let mut n = n as usize;
+        for _ in 0..self.depth {
+            dir.push(format!("d{:02}", n % self.fanout));
+            n /= self.fanout;
+        }
+        dir
+    }
+}
+
+/// SplitMix64 – tiny, fast and stable across platforms and releases.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn range(&mut self, lo: usize, hi: usize) -> usize {
+        lo + (self.next() % (hi - lo + 1) as u64) as usize
+    }
+}
+
+/// Write the tree described by `spec` below `root` (created if missing)
+/// and return every written path: explicit files first, then generated
+/// ones named `<word>-<n>.<ext>`.
+pub fn fixture_tree(root: &Path, spec: &FixtureSpec) -> Result<Vec<PathBuf>> {
+    spec.validate()?;
+    fs::create_dir_all(root)?;
+    let mut out = Vec::with_capacity(spec.explicit.len() + spec.files);
+
+    for (rel, body) in &spec.explicit {
+        let path = root.join(rel);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, body)?;
+        out.push(path);
+    }
+
+    let mut rng = Rng(spec.seed);
+    let mut made_dirs = std::collections::HashSet::new();
+    for i in 0..spec.files {
+        let dir = root.join(spec.dir_for(rng.next()));
+        if made_dirs.insert(dir.clone()) {
+            fs::create_dir_all(&dir)?;
+        }
+        let word = WORDS[i % WORDS.len()];
+        let ext = &spec.types[i % spec.types.len()];
+        let path = dir.join(format!("{word}-{i}.{ext}"));
+
+        let size = rng.range(spec.min_size, spec.max_size);
+        let mut body = format!("{word} {i}\n");
+        while body.len() < size {
+            body.push_str(WORDS[(rng.next() % WORDS.len() as u64) as usize]);
+            body.push(if rng.next().is_multiple_of(8) {
+                '\n'
+            } else {
+                ' '
+            });
+        }
+        body.truncate(size);
+        fs::write(&path, body)?;
+        out.push(path);
+    }
+    Ok(out)
+}
@@ -0,0 +1,66 @@
+// libmarlin/src/test_utils_tests.rs
+
+use super::test_utils::{fixture_tree, FixtureSpec};
+use std::fs;
+use tempfile::tempdir;
+
+fn snapshot(root: &std::path::Path, paths: &[std::path::PathBuf]) -> Vec<(String, Vec<u8>)> {
+    paths
+        .iter()
+        .map(|p| {
+            (
+                p.strip_prefix(root).unwrap().display().to_string(),
+                fs::read(p).unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn same_spec_gives_identical_trees() {
+    let spec = FixtureSpec::new()
+        .files(50)
+        .depth(2)
+        .fanout(3)
+        .sizes(0, 200);
+    let (a, b) = (tempdir().unwrap(), tempdir().unwrap());
+    let pa = fixture_tree(a.path(), &spec).unwrap();
+    let pb = fixture_tree(b.path(), &spec).unwrap();
+    assert_eq!(snapshot(a.path(), &pa), snapshot(b.path(), &pb));
+
+    let c = tempdir().unwrap();
+    let pc = fixture_tree(c.path(), &spec.clone().seed(1)).unwrap();
+    assert_ne!(snapshot(a.path(), &pa), snapshot(c.path(), &pc));
+}
+
+#[test]
+fn layout_sizes_and_types_follow_the_spec() {
+    let tmp = tempdir().unwrap();
+    let spec = FixtureSpec::demo()
+        .files(12)
+        .depth(2)
+        .fanout(2)
+        .sizes(10, 20)
+        .types(["rs"]);
+    let paths = fixture_tree(tmp.path(), &spec).unwrap();
+    assert_eq!(paths.len(), 6 + 12);
+    assert!(tmp.path().join("Projects/Gamma/TODO.txt").is_file());
+
+    for p in &paths[6..] {
+        let rel = p.strip_prefix(tmp.path()).unwrap();
+        assert_eq!(rel.components().count(), 3, "{}", rel.display());
+        assert_eq!(p.extension().unwrap(), "rs");
+        let len = fs::metadata(p).unwrap().len();
+        assert!((10..=20).contains(&len), "{} is {len} bytes", p.display());
+    }
+}
+
+#[test]
+fn invalid_specs_are_rejected() {
+    let tmp = tempdir().unwrap();
+    let escape = FixtureSpec::new().file("../outside.txt", "x");
+    assert!(fixture_tree(tmp.path(), &escape).is_err());
+    assert!(fixture_tree(tmp.path(), &FixtureSpec::new().sizes(5, 1)).is_err());
+    let no_fanout = FixtureSpec::new().files(1).depth(1).fanout(0);
+    assert!(fixture_tree(tmp.path(), &no_fanout).is_err());
+}
@@ -0,0 +1,165 @@
+//! Turning stored timestamps into text.
+//!
+//! The index keeps every time as a UTC Unix epoch; only display converts
+//! them.  [`TimeFormatter`] does that conversion for every command that
+//! prints a time, honouring the `timezone` and `time_format` settings.
+//! The CLI's `--utc` overrides the zone for one invocation.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use rusqlite::Connection;
+
+use crate::db;
+
+/// Setting holding the display [`Zone`].
+pub const ZONE_KEY: &str = "timezone";
+/// Setting holding the `strftime` pattern times are printed with.
+pub const FORMAT_KEY: &str = "time_format";
+/// Pattern used while [`FORMAT_KEY`] is unset.
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Time zone times are shown in.  Named zones such as `Europe/Berlin` are
+/// not supported; a fixed offset does not follow daylight saving time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Zone {
+    /// The system's zone (`local`).
+    #[default]
+    Local,
+    /// `utc`
+    Utc,
+    /// A fixed offset such as `+02:00` or `-0530`.
+    Fixed(FixedOffset),
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Local => f.write_str("local"),
+            Zone::Utc => f.write_str("utc"),
+            Zone::Fixed(o) => write!(f, "{o}"),
+        }
+    }
+}
+
+impl FromStr for Zone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "local" => return Ok(Zone::Local),
+            "utc" | "z" => return Ok(Zone::Utc),
+            _ => {}
+        }
+        parse_offset(s.trim()).map(Zone::Fixed).ok_or_else(|| {
+            anyhow::anyhow!(
+                "unknown time zone `{s}` (expected local, utc or an offset like +02:00)"
+            )
+        })
+    }
+}
+
+/// `+HH`, `+HHMM` or `+HH:MM` (or `-…`).
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits = match rest.split_once(':') {
+        Some((h, m)) if h.len() == 2 && m.len() == 2 => format!("{h}{m}"),
+        Some(_) => return None,
+        None => rest.to_string(),
+    };
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    if h > 23 || m > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60))
+}
+
+/// Fail unless `pattern` is a `strftime` pattern chrono understands.
+pub fn check_format(pattern: &str) -> Result<()> {
+    if pattern.is_empty() || StrftimeItems::new(pattern).any(|i| matches!(i, Item::Error)) {
+        bail!("invalid time format `{pattern}` (see strftime, e.g. {DEFAULT_FORMAT})");
+    }
+    Ok(())
+}
+
+/// Formats UTC timestamps for display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeFormatter {
+    zone: Zone,
+    format: String,
+}
+
+impl Default for TimeFormatter {
+    fn default() -> Self {
+        Self {
+            zone: Zone::Local,
+            format: DEFAULT_FORMAT.into(),
+        }
+    }
+}
+
+impl TimeFormatter {
+    pub fn new(zone: Zone, format: &str) -> Result<Self> {
+        check_format(format)?;
+        Ok(Self {
+            zone,
+            format: format.into(),
+        })
+    }
+
+    /// The formatter configured in the index.
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let zone = match db::setting(conn, ZONE_KEY)? {
+            Some(z) => z.parse()?,
+            None => Zone::default(),
+        };
+        let format = db::setting(conn, FORMAT_KEY)?;
+        Self::new(zone, format.as_deref().unwrap_or(DEFAULT_FORMAT))
+    }
+
+    /// The same pattern, shown in `zone`.
+    pub fn with_zone(mut self, zone: Zone) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    pub fn zone(&self) -> Zone {
+        self.zone
+    }
+
+    pub fn format(&self, t: DateTime<Utc>) -> String {
+        let f = self.format.as_str();
+        match self.zone {
+            Zone::Local => t.with_timezone(&Local).format(f).to_string(),
+            Zone::Utc => t.format(f).to_string(),
+            Zone::Fixed(o) => t.with_timezone(&o).format(f).to_string(),
+        }
+    }
+
+    /// A Unix epoch in seconds; `None` if it is out of range.
+    pub fn epoch(&self, secs: i64) -> Option<String> {
+        DateTime::from_timestamp(secs, 0).map(|t| self.format(t))
+    }
+
+    /// How many characters a formatted time takes, for aligning columns.
+    pub fn width(&self) -> usize {
+        self.format(Utc::now()).chars().count()
+    }
+}
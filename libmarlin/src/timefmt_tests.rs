@@ -0,0 +1,56 @@
+// libmarlin/src/timefmt_tests.rs
+
+use super::db;
+use super::timefmt::{check_format, TimeFormatter, Zone, FORMAT_KEY, ZONE_KEY};
+use chrono::FixedOffset;
+
+#[test]
+fn zones_parse_and_print_back() {
+    assert_eq!("local".parse::<Zone>().unwrap(), Zone::Local);
+    assert_eq!("UTC".parse::<Zone>().unwrap(), Zone::Utc);
+    let plus2 = Zone::Fixed(FixedOffset::east_opt(2 * 3600).unwrap());
+    assert_eq!("+02:00".parse::<Zone>().unwrap(), plus2);
+    assert_eq!("+0200".parse::<Zone>().unwrap(), plus2);
+    assert_eq!("+02".parse::<Zone>().unwrap(), plus2);
+    assert_eq!(
+        "-05:30".parse::<Zone>().unwrap(),
+        Zone::Fixed(FixedOffset::west_opt(5 * 3600 + 30 * 60).unwrap())
+    );
+    assert_eq!(plus2.to_string(), "+02:00");
+    for bad in ["Europe/Berlin", "+25:00", "+2:0", "02:00", ""] {
+        assert!(bad.parse::<Zone>().is_err(), "{bad} should be rejected");
+    }
+}
+
+#[test]
+fn formats_epochs_in_the_chosen_zone() {
+    let t = 1_700_000_000; // 2023-11-14 22:13:20 UTC
+    let utc = TimeFormatter::new(Zone::Utc, "%Y-%m-%d %H:%M").unwrap();
+    assert_eq!(utc.epoch(t).unwrap(), "2023-11-14 22:13");
+    let east = utc.clone().with_zone("+02:00".parse().unwrap());
+    assert_eq!(east.epoch(t).unwrap(), "2023-11-15 00:13");
+    assert_eq!(east.width(), "2023-11-15 00:13".len());
+    assert!(utc.epoch(i64::MAX).is_none());
+}
+
+#[test]
+fn bad_patterns_are_rejected() {
+    assert!(check_format("%Y-%m-%d").is_ok());
+    assert!(check_format("%Q").is_err());
+    assert!(check_format("").is_err());
+}
+
+#[test]
+fn loads_from_settings() {
+    let conn = db::open(":memory:").unwrap();
+    assert_eq!(
+        TimeFormatter::load(&conn).unwrap(),
+        TimeFormatter::default()
+    );
+
+    db::set_setting(&conn, ZONE_KEY, "utc").unwrap();
+    db::set_setting(&conn, FORMAT_KEY, "%d.%m.%Y").unwrap();
+    let f = TimeFormatter::load(&conn).unwrap();
+    assert_eq!(f.zone(), Zone::Utc);
+    assert_eq!(f.epoch(0).unwrap(), "01.01.1970");
+}
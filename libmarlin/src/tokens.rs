@@ -0,0 +1,214 @@
+//! Scoped access tokens for the watcher's control channel.
+//!
+//! The unix control socket is owner-only, but a loopback TCP endpoint –
+//! or a socket handed to another app – reaches anything that can connect.
+//! A daemon started with `--require-token` answers only requests that
+//! carry a token (`auth <secret> <command>`), and each token is limited to
+//! its [`Scope`]s:
+//!
+//! * `read` – status, health, events, search and other queries
+//! * `tag:<namespace>` – add tags at or below `<namespace>` (implies
+//!   `read`)
+//! * `write` – any metadata change (implies every `tag:` scope)
+//! * `admin` – everything, including stopping the watcher
+//!
+//! Secrets are shown once by [`create`]; the index only keeps their
+//! SHA-256.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+use crate::db::Mutation;
+
+/// Prefix of every secret, so leaked tokens are easy to grep for.
+pub const SECRET_PREFIX: &str = "mrl_";
+
+/// What a token may do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Read,
+    /// Add tags at or below this namespace.
+    Tags(String),
+    Write,
+    Admin,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Read => f.write_str("read"),
+            Scope::Tags(ns) => write!(f, "tag:{ns}"),
+            Scope::Write => f.write_str("write"),
+            Scope::Admin => f.write_str("admin"),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "write" => Ok(Scope::Write),
+            "admin" => Ok(Scope::Admin),
+            _ => match s.strip_prefix("tag:").map(|ns| ns.trim_matches('/')) {
+                Some(ns) if !ns.is_empty() => Ok(Scope::Tags(ns.to_string())),
+                _ => bail!("unknown scope `{s}` (expected read, tag:<namespace>, write or admin)"),
+            },
+        }
+    }
+}
+
+/// What a control request needs from its token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access<'a> {
+    Read,
+    /// Add this tag path.
+    Tag(&'a str),
+    /// Any other metadata change.
+    Write,
+    Admin,
+}
+
+impl fmt::Display for Access<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Read => f.write_str("read"),
+            Access::Tag(path) => write!(f, "tag:{path}"),
+            Access::Write => f.write_str("write"),
+            Access::Admin => f.write_str("admin"),
+        }
+    }
+}
+
+/// A stored token (without its secret).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Unix seconds.
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+}
+
+impl Token {
+    /// Whether one of the token's scopes covers `access`.
+    pub fn allows(&self, access: Access<'_>) -> bool {
+        self.scopes.iter().any(|s| match (s, access) {
+            (Scope::Admin, _) => true,
+            (Scope::Write, Access::Read | Access::Tag(_) | Access::Write) => true,
+            (Scope::Tags(_), Access::Read) => true,
+            (Scope::Tags(ns), Access::Tag(path)) => {
+                let path = path.trim_matches('/');
+                path.strip_prefix(ns.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            }
+            (Scope::Read, Access::Read) => true,
+            _ => false,
+        })
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Store a new token called `name` and return its secret, which is not
+/// kept anywhere and cannot be shown again.
+pub fn create(conn: &Connection, name: &str, scopes: &[Scope]) -> Result<String> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        bail!("invalid token name `{name}`");
+    }
+    if scopes.is_empty() {
+        bail!("a token needs at least one scope");
+    }
+    let mut bytes = [0u8; 24];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("reading random bytes for the token: {e}"))?;
+    let secret: String = std::iter::once(SECRET_PREFIX.to_string())
+        .chain(bytes.iter().map(|b| format!("{b:02x}")))
+        .collect();
+    let scopes: Vec<String> = scopes.iter().map(Scope::to_string).collect();
+    let m = Mutation::new("create_token");
+    let n = conn
+        .execute(
+            "INSERT INTO api_tokens(name, secret_hash, scopes, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, hash_secret(&secret), scopes.join(" "), now()],
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::SqliteFailure(f, _)
+                if f.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                anyhow::anyhow!("a token named `{name}` already exists")
+            }
+            e => e.into(),
+        })?;
+    m.done(n);
+    Ok(secret)
+}
+
+/// All tokens, sorted by name.
+pub fn list(conn: &Connection) -> Result<Vec<Token>> {
+    let mut stmt = conn
+        .prepare("SELECT name, scopes, created_at, last_used_at FROM api_tokens ORDER BY name")?;
+    let rows = stmt.query_map([], row_to_token)?;
+    rows.map(|r| r?).collect()
+}
+
+/// Delete the token called `name`.  Returns `false` if there was none.
+pub fn revoke(conn: &Connection, name: &str) -> Result<bool> {
+    let m = Mutation::new("revoke_token");
+    Ok(m.done(conn.execute("DELETE FROM api_tokens WHERE name = ?1", [name])?) > 0)
+}
+
+/// The token `secret` belongs to, if any; its `last_used_at` is updated.
+pub fn verify(conn: &Connection, secret: &str) -> Result<Option<Token>> {
+    let hash = hash_secret(secret);
+    let token = conn
+        .query_row(
+            "SELECT name, scopes, created_at, last_used_at FROM api_tokens
+              WHERE secret_hash = ?1",
+            [&hash],
+            row_to_token,
+        )
+        .optional()?
+        .transpose()?;
+    if token.is_some() {
+        let m = Mutation::new("use_token");
+        m.done(conn.execute(
+            "UPDATE api_tokens SET last_used_at = ?2 WHERE secret_hash = ?1",
+            params![hash, now()],
+        )?);
+    }
+    Ok(token)
+}
+
+fn row_to_token(row: &rusqlite::Row<'_>) -> rusqlite::Result<Result<Token>> {
+    let scopes: String = row.get(1)?;
+    let name: String = row.get(0)?;
+    let created_at = row.get(2)?;
+    let last_used_at = row.get(3)?;
+    Ok(scopes
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<Scope>>>()
+        .with_context(|| format!("token `{name}`"))
+        .map(|scopes| Token {
+            name,
+            scopes,
+            created_at,
+            last_used_at,
+        }))
+}
@@ -0,0 +1,77 @@
+// libmarlin/src/tokens_tests.rs
+
+use super::db;
+use super::tokens::{self, Access, Scope};
+
+#[test]
+fn scopes_round_trip_and_reject_garbage() {
+    for s in ["read", "write", "admin", "tag:projects/2025"] {
+        assert_eq!(s.parse::<Scope>().unwrap().to_string(), s);
+    }
+    assert_eq!(
+        "tag:/projects/".parse::<Scope>().unwrap(),
+        Scope::Tags("projects".into())
+    );
+    for bad in ["", "tag:", "root", "READ"] {
+        assert!(bad.parse::<Scope>().is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn created_tokens_verify_and_carry_their_scopes() {
+    let conn = db::open(":memory:").unwrap();
+    let secret = tokens::create(
+        &conn,
+        "photos-app",
+        &[Scope::Read, Scope::Tags("photos".into())],
+    )
+    .unwrap();
+    assert!(secret.starts_with(tokens::SECRET_PREFIX));
+    assert!(tokens::create(&conn, "photos-app", &[Scope::Read]).is_err());
+    assert!(tokens::create(&conn, "none", &[]).is_err());
+
+    let token = tokens::verify(&conn, &secret)
+        .unwrap()
+        .expect("known secret");
+    assert_eq!(token.name, "photos-app");
+    assert!(tokens::list(&conn).unwrap()[0].last_used_at.is_some());
+    assert!(tokens::verify(&conn, "mrl_wrong").unwrap().is_none());
+
+    // the secret itself is never stored
+    let stored: String = conn
+        .query_row("SELECT secret_hash FROM api_tokens", [], |r| r.get(0))
+        .unwrap();
+    assert_ne!(stored, secret);
+
+    assert!(tokens::revoke(&conn, "photos-app").unwrap());
+    assert!(!tokens::revoke(&conn, "photos-app").unwrap());
+    assert!(tokens::verify(&conn, &secret).unwrap().is_none());
+}
+
+#[test]
+fn scopes_limit_what_a_token_allows() {
+    let token = |scopes: &[&str]| tokens::Token {
+        name: "t".into(),
+        scopes: scopes.iter().map(|s| s.parse().unwrap()).collect(),
+        created_at: 0,
+        last_used_at: None,
+    };
+
+    let read = token(&["read"]);
+    assert!(read.allows(Access::Read));
+    assert!(!read.allows(Access::Tag("photos")));
+    assert!(!read.allows(Access::Admin));
+
+    let photos = token(&["tag:photos"]);
+    assert!(photos.allows(Access::Read));
+    assert!(photos.allows(Access::Tag("photos")));
+    assert!(photos.allows(Access::Tag("photos/2025/summer")));
+    assert!(!photos.allows(Access::Tag("photoshop")));
+    assert!(!photos.allows(Access::Tag("work/photos")));
+    assert!(!photos.allows(Access::Write));
+
+    let write = token(&["write"]);
+    assert!(write.allows(Access::Tag("anything")) && write.allows(Access::Write));
+    assert!(!write.allows(Access::Admin));
+    assert!(token(&["admin"]).allows(Access::Admin));
+}
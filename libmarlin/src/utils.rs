@@ -1,46 +1,272 @@
 //! Misc shared helpers.
 
+use std::io::Read;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
 
 /// Determine a filesystem root to limit recursive walking on glob scans.
 ///
-/// If the pattern contains any of `*?[`, we take everything up to the
-/// first such character, and then (if that still contains metacharacters)
-/// walk up until there aren’t any left.  If there are *no* metachars at
-/// all, we treat the entire string as a path and return its parent
-/// directory (or `.` if it has no parent).
+/// If the pattern contains any of `*?[{` or `!(`, we take the directories
+/// before the path segment holding the first one (`foo*/bar` starts at
+/// `.`).  If there are *no* metachars at all, we treat the entire string
+/// as a path and return its parent directory (or `.` if it has no parent).
 pub fn determine_scan_root(pattern: &str) -> PathBuf {
     // find first wildcard char
     let first_wild = pattern
-        .find(|c| ['*', '?', '['].contains(&c))
+        .find(|c| ['*', '?', '[', '{'].contains(&c))
+        .into_iter()
+        .chain(pattern.find("!("))
+        .min()
         .unwrap_or(pattern.len());
 
-    // everything up to the wildcard (or the whole string if none)
-    let prefix = &pattern[..first_wild];
-    let mut root = PathBuf::from(prefix);
-
     // If there were NO wildcards at all, just return the parent directory
     if first_wild == pattern.len() {
-        return root
+        return PathBuf::from(pattern)
             .parent()
             .map(|p| p.to_path_buf())
             .unwrap_or_else(|| PathBuf::from("."));
     }
 
-    // Otherwise, if the prefix still has any wildcards (e.g. "foo*/bar"),
-    // walk back up until it doesn’t
-    while root
-        .as_os_str()
-        .to_string_lossy()
-        .chars()
-        .any(|c| matches!(c, '*' | '?' | '['))
-    {
-        root = root.parent().map(|p| p.to_path_buf()).unwrap_or_default();
-    }
-
+    // Otherwise keep the directories before the wildcard's path segment
+    // (`docs/report.{md,txt}` starts at `docs`)
+    let prefix = &pattern[..first_wild];
+    let cut = prefix
+        .rfind(['/', std::path::MAIN_SEPARATOR])
+        .map_or(0, |i| i + 1);
+    let root = PathBuf::from(&prefix[..cut]);
     if root.as_os_str().is_empty() {
         PathBuf::from(".")
     } else {
         root
     }
 }
+
+/// Up to `limit` bytes of `r` as text, or `None` if they do not look like
+/// UTF-8 text: NUL bytes or invalid sequences (one cut off at the end of
+/// the sample is fine).
+pub fn read_text_sample(r: impl Read, limit: u64) -> Option<String> {
+    let mut buf = Vec::new();
+    r.take(limit).read_to_end(&mut buf).ok()?;
+    if buf.contains(&0) {
+        return None;
+    }
+    match String::from_utf8(buf) {
+        Ok(t) => Some(t),
+        Err(e) if e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut buf = e.into_bytes();
+            buf.truncate(valid);
+            String::from_utf8(buf).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+/// Parse a compact duration such as `90s`, `15m`, `12h`, `30d`, `2w` or
+/// `2y` (a year counts as 365 days). A bare number means days.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let Ok(n) = num.parse::<u64>() else {
+        bail!("invalid duration `{s}` (expected e.g. 30d, 12h, 2y)");
+    };
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "" | "d" => 86_400,
+        "w" => 7 * 86_400,
+        "y" => 365 * 86_400,
+        _ => bail!("unknown duration unit `{unit}` in `{s}` (use s, m, h, d, w or y)"),
+    };
+    Ok(Duration::from_secs(n.saturating_mul(secs)))
+}
+
+/// Parse a point in time given on the command line, as Unix seconds: a
+/// duration from `now` (`2h`, `+3d`), `now` itself, or a local
+/// `YYYY-MM-DD[THH:MM[:SS]]` (its first second).
+pub fn parse_when(s: &str, now: i64) -> Result<i64> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("now") {
+        return Ok(now);
+    }
+    let rel = s.strip_prefix('+').unwrap_or(s);
+    if rel.ends_with(|c: char| c.is_ascii_alphabetic()) {
+        if let Ok(d) = parse_duration(rel) {
+            return Ok(now.saturating_add(d.as_secs() as i64));
+        }
+    }
+    crate::query::time_span(s)
+        .map(|(start, _)| start)
+        .map_err(|_| {
+            anyhow::anyhow!("invalid time `{s}` (expected e.g. 2h, 3d or 2025-06-01T09:00)")
+        })
+}
+
+/// Render a byte count with binary units (`512 B`, `1.5 KiB`, `3.2 GiB`).
+pub fn human_size(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes.abs() < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut v = bytes as f64;
+    let mut unit = "B";
+    for u in UNITS {
+        if v.abs() < 1024.0 {
+            break;
+        }
+        v /= 1024.0;
+        unit = u;
+    }
+    format!("{v:.1} {unit}")
+}
+
+/// Parse a size such as `512`, `100K`, `100M`, `1.5G` or `2TiB` into bytes
+/// (binary units, case-insensitive, optional `B`/`iB` suffix).
+pub fn parse_size(s: &str) -> Result<i64> {
+    let t = s.trim();
+    let split = t
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(t.len());
+    let (num, unit) = t.split_at(split);
+    let Ok(n) = num.parse::<f64>() else {
+        bail!("invalid size `{s}` (expected e.g. 500K, 100M, 2G)");
+    };
+    let unit = unit.trim().to_ascii_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let mult: i64 = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => bail!("unknown size unit in `{s}` (use K, M, G or T)"),
+    };
+    Ok((n * mult as f64) as i64)
+}
+
+/* ─── path normalization ──────────────────────────────────────────── */
+
+/// How [`normalize_path`] treats separators.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Separator {
+    /// The platform's separator; on Windows `/` is accepted and rewritten
+    /// to `\`, elsewhere `\` is an ordinary file-name character.
+    #[default]
+    Native,
+    /// Treat both `/` and `\` as separators and emit `/` (portable keys,
+    /// exported indexes).
+    Forward,
+}
+
+/// Unicode normalization form applied by [`normalize_path`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnicodeForm {
+    /// Leave code points alone (byte-for-byte what the OS reported).
+    #[default]
+    Keep,
+    /// Composed form – what most Linux tools and users type.
+    Nfc,
+    /// Decomposed form – what older macOS file systems report.
+    Nfd,
+}
+
+/// Options for [`normalize_path`].  `PathNorm::default()` is what the
+/// index uses for stored paths: it only cleans up separators, so every
+/// stored path still names the file on disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathNorm {
+    pub separator: Separator,
+    /// Lower-case the whole path (for case-insensitive volumes).
+    pub case_fold: bool,
+    pub unicode: UnicodeForm,
+    /// Keep one trailing separator if the input had one (directories).
+    pub keep_trailing_slash: bool,
+}
+
+/// Normalize a path to the canonical string form used as an index key.
+///
+/// Purely lexical – the file system is never consulted, so `..` and
+/// symlinks are kept as written.  Always: repeated separators collapse,
+/// `.` components are dropped, and a lone root stays `/` (an empty
+/// relative path becomes `.`).  Everything else is controlled by `opts`.
+/// The result is idempotent: normalizing it again with the same options
+/// returns it unchanged.
+pub fn normalize_path<P: AsRef<std::path::Path>>(path: P, opts: &PathNorm) -> String {
+    use unicode_normalization::UnicodeNormalization;
+
+    let raw = path.as_ref().to_string_lossy();
+    let folded = if opts.case_fold {
+        raw.to_lowercase()
+    } else {
+        raw.into_owned()
+    };
+    let s: String = match opts.unicode {
+        UnicodeForm::Keep => folded,
+        UnicodeForm::Nfc => folded.nfc().collect(),
+        UnicodeForm::Nfd => folded.nfd().collect(),
+    };
+
+    let (is_sep, sep): (fn(char) -> bool, char) = match opts.separator {
+        Separator::Forward => (|c| c == '/' || c == '\\', '/'),
+        Separator::Native if cfg!(windows) => (|c| c == '/' || c == '\\', '\\'),
+        Separator::Native => (|c| c == '/', '/'),
+    };
+
+    // keep a UNC `\\server` prefix on Windows-style output
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    if sep == '\\' && s.starts_with("\\\\") {
+        out.push_str("\\\\");
+        chars.nth(1);
+    } else if s.starts_with(is_sep) {
+        out.push(sep);
+    }
+    let rest = chars.as_str();
+
+    let parts: Vec<&str> = rest
+        .split(is_sep)
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect();
+    out.push_str(&parts.join(&sep.to_string()));
+
+    if out.is_empty() {
+        out.push('.');
+    } else if opts.keep_trailing_slash && !parts.is_empty() && s.ends_with(is_sep) {
+        out.push(sep);
+    }
+    out
+}
+
+/// `path` relative to `base`, climbing with `..` where needed, e.g.
+/// `/ws/src/a.rs` from `/ws/docs` is `../src/a.rs` and `base` itself is
+/// `.`.  Purely lexical; `None` when either path is relative or they share
+/// no root (different Windows drives).
+pub fn relative_path(path: &std::path::Path, base: &std::path::Path) -> Option<PathBuf> {
+    if !path.is_absolute() || !base.is_absolute() {
+        return None;
+    }
+    let p: Vec<_> = path.components().collect();
+    let b: Vec<_> = base.components().collect();
+    let common = p.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    if common == 0 {
+        return None;
+    }
+    let mut out = PathBuf::new();
+    for _ in common..b.len() {
+        out.push("..");
+    }
+    for c in &p[common..] {
+        out.push(c);
+    }
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    Some(out)
+}
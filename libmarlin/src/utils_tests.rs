@@ -1,7 +1,10 @@
 // libmarlin/src/utils_tests.rs
 
-use super::utils::determine_scan_root;
+use super::utils::{
+    determine_scan_root, human_size, parse_duration, parse_size, parse_when, relative_path,
+};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[test]
 fn determine_scan_root_plain_path() {
@@ -15,8 +18,241 @@ fn determine_scan_root_glob() {
     assert_eq!(root, PathBuf::from("foo"));
 }
 
+#[test]
+fn determine_scan_root_stops_before_the_wild_segment() {
+    assert_eq!(
+        determine_scan_root("docs/report.{md,txt}"),
+        PathBuf::from("docs")
+    );
+    assert_eq!(
+        determine_scan_root("docs/!(draft)/a.md"),
+        PathBuf::from("docs")
+    );
+    assert_eq!(determine_scan_root("foo*/bar"), PathBuf::from("."));
+}
+
 #[test]
 fn determine_scan_root_only_wildcards() {
     let root = determine_scan_root("**/*.txt");
     assert_eq!(root, PathBuf::from("."));
 }
+
+#[test]
+fn parse_duration_units() {
+    assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7_200));
+    assert_eq!(
+        parse_duration("30").unwrap(),
+        Duration::from_secs(30 * 86_400)
+    );
+    assert_eq!(
+        parse_duration("2y").unwrap(),
+        Duration::from_secs(2 * 365 * 86_400)
+    );
+    assert!(parse_duration("3 fortnights").is_err());
+    assert!(parse_duration("d").is_err());
+}
+
+#[test]
+fn parse_when_takes_durations_and_dates() {
+    assert_eq!(parse_when("now", 1_000).unwrap(), 1_000);
+    assert_eq!(parse_when("2h", 1_000).unwrap(), 8_200);
+    assert_eq!(parse_when("+1d", 0).unwrap(), 86_400);
+    let day = parse_when("2025-06-01", 0).unwrap();
+    assert_eq!(parse_when("2025-06-01T01:00", 0).unwrap(), day + 3_600);
+    assert!(parse_when("tomorrow", 0).is_err());
+}
+
+#[test]
+fn human_size_formats() {
+    assert_eq!(human_size(0), "0 B");
+    assert_eq!(human_size(1023), "1023 B");
+    assert_eq!(human_size(1536), "1.5 KiB");
+    assert_eq!(human_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+}
+
+#[test]
+fn parse_size_units() {
+    assert_eq!(parse_size("512").unwrap(), 512);
+    assert_eq!(parse_size("100M").unwrap(), 100 << 20);
+    assert_eq!(parse_size("1.5k").unwrap(), 1536);
+    assert_eq!(parse_size("2GiB").unwrap(), 2 << 30);
+    assert_eq!(parse_size("3 MB").unwrap(), 3 << 20);
+    assert!(parse_size("lots").is_err());
+    assert!(parse_size("10X").is_err());
+}
+
+/* ─── normalize_path ──────────────────────────────────────────────── */
+
+use super::utils::{normalize_path, PathNorm, Separator, UnicodeForm};
+use proptest::prelude::*;
+use unicode_normalization::{is_nfc, is_nfd};
+
+#[test]
+fn normalize_path_default_only_cleans_separators() {
+    let n = |p: &str| normalize_path(p, &PathNorm::default());
+    assert_eq!(n("/home//me/./docs/"), "/home/me/docs");
+    assert_eq!(n("./a/b"), "a/b");
+    assert_eq!(n("a/../b"), "a/../b");
+    assert_eq!(n("/"), "/");
+    assert_eq!(n("//"), "/");
+    assert_eq!(n(""), ".");
+    assert_eq!(n("./"), ".");
+    assert_eq!(n("Café/Ü"), "Café/Ü");
+}
+
+#[test]
+fn normalize_path_options() {
+    let fwd = PathNorm {
+        separator: Separator::Forward,
+        ..Default::default()
+    };
+    assert_eq!(
+        normalize_path(r"C:\Users\me\\x.txt", &fwd),
+        "C:/Users/me/x.txt"
+    );
+
+    let trailing = PathNorm {
+        keep_trailing_slash: true,
+        ..Default::default()
+    };
+    assert_eq!(normalize_path("/a/b//", &trailing), "/a/b/");
+    assert_eq!(normalize_path("/", &trailing), "/");
+
+    let folded = PathNorm {
+        case_fold: true,
+        unicode: UnicodeForm::Nfc,
+        ..Default::default()
+    };
+    // decomposed "É" (E + U+0301) folds and composes to "é"
+    assert_eq!(
+        normalize_path("/X/E\u{301}t\u{e9}", &folded),
+        "/x/\u{e9}t\u{e9}"
+    );
+
+    let nfd = PathNorm {
+        unicode: UnicodeForm::Nfd,
+        ..Default::default()
+    };
+    assert_eq!(normalize_path("\u{e9}", &nfd), "e\u{301}");
+}
+
+/// Path-ish strings: separators of both kinds, dots, mixed case and
+/// composed/decomposed/special-cased letters.
+fn path_strategy() -> impl Strategy<Value = String> {
+    prop::collection::vec(
+        prop_oneof![
+            Just("/"),
+            Just("\\"),
+            Just("."),
+            Just(".."),
+            Just("a"),
+            Just("B"),
+            Just(" "),
+            Just("\u{e9}"),
+            Just("E\u{301}"),
+            Just("\u{130}"),
+            Just("\u{df}"),
+            Just("\u{1e9e}"),
+            Just("\u{3a3}"),
+        ],
+        0..24,
+    )
+    .prop_map(|parts| parts.concat())
+}
+
+fn norm_strategy() -> impl Strategy<Value = PathNorm> {
+    (
+        prop_oneof![Just(Separator::Native), Just(Separator::Forward)],
+        any::<bool>(),
+        prop_oneof![
+            Just(UnicodeForm::Keep),
+            Just(UnicodeForm::Nfc),
+            Just(UnicodeForm::Nfd)
+        ],
+        any::<bool>(),
+    )
+        .prop_map(
+            |(separator, case_fold, unicode, keep_trailing_slash)| PathNorm {
+                separator,
+                case_fold,
+                unicode,
+                keep_trailing_slash,
+            },
+        )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1024))]
+
+    #[test]
+    fn normalize_path_is_idempotent(p in path_strategy(), opts in norm_strategy()) {
+        let once = normalize_path(&p, &opts);
+        prop_assert_eq!(normalize_path(&once, &opts), once.clone());
+    }
+
+    #[test]
+    fn normalize_path_output_is_clean(p in path_strategy(), opts in norm_strategy()) {
+        let out = normalize_path(&p, &opts);
+        let sep = if opts.separator == Separator::Forward || cfg!(not(windows)) { '/' } else { '\\' };
+        prop_assert!(!out.is_empty());
+        let body = out.trim_start_matches(sep);
+        let doubled = format!("{sep}{sep}");
+        let dot = format!("{sep}.{sep}");
+        prop_assert!(!body.contains(&doubled), "doubled separator in {:?}", out);
+        if out != "." {
+            prop_assert!(!format!("{sep}{out}{sep}").contains(&dot), "`.` component in {:?}", out);
+        }
+        if !opts.keep_trailing_slash && out.len() > 1 {
+            prop_assert!(!out.ends_with(sep), "trailing separator in {:?}", out);
+        }
+        if opts.separator == Separator::Forward {
+            prop_assert!(!out.contains('\\'));
+        }
+        if opts.case_fold {
+            prop_assert_eq!(out.to_lowercase(), out.clone());
+        }
+        match opts.unicode {
+            UnicodeForm::Nfc => prop_assert!(is_nfc(&out)),
+            UnicodeForm::Nfd => prop_assert!(is_nfd(&out)),
+            UnicodeForm::Keep => {}
+        }
+    }
+
+    #[test]
+    fn normalize_path_keeps_clean_paths(
+        parts in prop::collection::vec("[a-zA-Z0-9_ -]{1,8}", 1..6),
+        absolute in any::<bool>(),
+    ) {
+        let p = format!("{}{}", if absolute { "/" } else { "" }, parts.join("/"));
+        prop_assert_eq!(normalize_path(&p, &PathNorm::default()), p);
+    }
+
+    #[test]
+    fn normalize_path_never_changes_the_components(p in path_strategy()) {
+        // default options: only separators and `.` go away
+        let out = normalize_path(&p, &PathNorm::default());
+        let comps = |s: &str| -> Vec<String> {
+            s.split('/').filter(|c| !c.is_empty() && *c != ".").map(String::from).collect()
+        };
+        prop_assert_eq!(comps(&out), comps(&p));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn relative_path_climbs_out_of_the_base() {
+    use std::path::Path;
+    let rel = |p: &str, b: &str| relative_path(Path::new(p), Path::new(b));
+    assert_eq!(rel("/ws/src/a.rs", "/ws"), Some(PathBuf::from("src/a.rs")));
+    assert_eq!(
+        rel("/ws/src/a.rs", "/ws/docs"),
+        Some(PathBuf::from("../src/a.rs"))
+    );
+    assert_eq!(rel("/ws", "/ws"), Some(PathBuf::from(".")));
+    assert_eq!(
+        rel("/other/b", "/ws/x"),
+        Some(PathBuf::from("../../other/b"))
+    );
+    assert_eq!(rel("a.rs", "/ws"), None);
+}
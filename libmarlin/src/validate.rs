@@ -0,0 +1,193 @@
+//! Value checks for attributes and tags, per key or tag namespace.
+//!
+//! Rules come from two places, and both are enforced by
+//! [`db::upsert_attr`](crate::db::upsert_attr) and
+//! [`db::ensure_tag_path`](crate::db::ensure_tag_path):
+//!
+//! * settings in the index (`marlin config set`), so every front-end on the
+//!   same database honours them:
+//!   * `validate.attr.<key> = <regex>` – values of attribute `<key>`; a key
+//!     ending in `/*` covers the whole namespace (`invoice/*`).
+//!   * `validate.tag.<namespace> = <regex>` – the part of a tag path below
+//!     `<namespace>`, e.g. `2024` in `year/2024`.
+//! * rules registered in-process with [`register_attr`], [`register_tag`]
+//!   and their `_regex` variants; closures return the message to show.
+//!
+//! Regexes are unanchored, as usual; write `^…$` to match the whole value.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use rusqlite::Connection;
+
+/// Settings prefix for attribute rules.
+pub const ATTR_PREFIX: &str = "validate.attr.";
+/// Settings prefix for tag rules.
+pub const TAG_PREFIX: &str = "validate.tag.";
+
+type Check = Arc<dyn Fn(&str) -> std::result::Result<(), String> + Send + Sync>;
+
+#[derive(Clone)]
+enum Rule {
+    Regex(Regex),
+    Closure(Check),
+}
+
+impl Rule {
+    /// `Err` carries the reason shown to the user.
+    fn check(&self, value: &str) -> std::result::Result<(), String> {
+        match self {
+            Rule::Regex(re) if re.is_match(value) => Ok(()),
+            Rule::Regex(re) => Err(format!("must match `{}`", re.as_str())),
+            Rule::Closure(f) => f(value),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    attrs: Vec<(String, Rule)>,
+    tags: Vec<(String, Rule)>,
+}
+
+static REGISTRY: RwLock<Registry> = RwLock::new(Registry {
+    attrs: Vec::new(),
+    tags: Vec::new(),
+});
+
+/// Compiled settings regexes, keyed by pattern text.
+static COMPILED: Mutex<Option<HashMap<String, Regex>>> = Mutex::new(None);
+
+/// Compile `pattern`, with an error that names it.
+pub fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).with_context(|| format!("invalid validation regex `{pattern}`"))
+}
+
+fn cached(pattern: &str) -> Result<Regex> {
+    let mut guard = COMPILED.lock().unwrap_or_else(|e| e.into_inner());
+    let map = guard.get_or_insert_with(HashMap::new);
+    if let Some(re) = map.get(pattern) {
+        return Ok(re.clone());
+    }
+    let re = compile(pattern)?;
+    map.insert(pattern.to_string(), re.clone());
+    Ok(re)
+}
+
+/// Check values of attribute `key` (or `ns/*`) with a closure.
+pub fn register_attr<F>(key: &str, check: F)
+where
+    F: Fn(&str) -> std::result::Result<(), String> + Send + Sync + 'static,
+{
+    write_registry()
+        .attrs
+        .push((key.to_string(), Rule::Closure(Arc::new(check))));
+}
+
+/// Values of attribute `key` (or `ns/*`) must match `pattern`.
+pub fn register_attr_regex(key: &str, pattern: &str) -> Result<()> {
+    let re = compile(pattern)?;
+    write_registry()
+        .attrs
+        .push((key.to_string(), Rule::Regex(re)));
+    Ok(())
+}
+
+/// Check the part of tag paths below `namespace` with a closure.
+pub fn register_tag<F>(namespace: &str, check: F)
+where
+    F: Fn(&str) -> std::result::Result<(), String> + Send + Sync + 'static,
+{
+    write_registry()
+        .tags
+        .push((namespace.to_string(), Rule::Closure(Arc::new(check))));
+}
+
+/// The part of tag paths below `namespace` must match `pattern`.
+pub fn register_tag_regex(namespace: &str, pattern: &str) -> Result<()> {
+    let re = compile(pattern)?;
+    write_registry()
+        .tags
+        .push((namespace.to_string(), Rule::Regex(re)));
+    Ok(())
+}
+
+/// Drop every in-process rule (settings rules are untouched).
+pub fn clear_registered() {
+    *write_registry() = Registry::default();
+}
+
+fn write_registry() -> std::sync::RwLockWriteGuard<'static, Registry> {
+    REGISTRY.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether a rule for attribute `rule_key` covers attribute `key`.
+fn covers_attr(rule_key: &str, key: &str) -> bool {
+    match rule_key.strip_suffix("/*") {
+        Some(ns) => key.strip_prefix(ns).is_some_and(|r| r.starts_with('/')),
+        None => rule_key == key,
+    }
+}
+
+/// The part of `path` below `namespace`, if `path` lies inside it.
+fn below<'p>(namespace: &str, path: &'p str) -> Option<&'p str> {
+    let rest = path.strip_prefix(namespace.trim_matches('/'))?;
+    rest.strip_prefix('/').filter(|r| !r.is_empty())
+}
+
+/// Rules stored in the settings table under `prefix`, compiled.
+fn settings_rules(conn: &Connection, prefix: &str) -> Result<Vec<(String, Rule)>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT key, value FROM settings WHERE substr(key, 1, ?2) = ?1")?;
+    let rows = stmt
+        .query_map(rusqlite::params![prefix, prefix.len() as i64], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    rows.into_iter()
+        .map(|(k, v)| Ok((k[prefix.len()..].to_string(), Rule::Regex(cached(&v)?))))
+        .collect()
+}
+
+/// Reject `value` for attribute `key` if a rule says so.
+pub fn check_attr(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    let registered = REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .attrs
+        .clone();
+    for (rule_key, rule) in settings_rules(conn, ATTR_PREFIX)?
+        .into_iter()
+        .chain(registered)
+    {
+        if covers_attr(&rule_key, key) {
+            if let Err(why) = rule.check(value) {
+                bail!("invalid value `{value}` for attribute `{key}`: {why}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reject tag `path` if a rule for one of its namespaces says so.
+pub fn check_tag(conn: &Connection, path: &str) -> Result<()> {
+    let path = path.trim_matches('/');
+    let registered = REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .tags
+        .clone();
+    for (namespace, rule) in settings_rules(conn, TAG_PREFIX)?
+        .into_iter()
+        .chain(registered)
+    {
+        if let Some(rest) = below(&namespace, path) {
+            if let Err(why) = rule.check(rest) {
+                bail!("invalid tag `{path}`: `{rest}` under `{namespace}` {why}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+// libmarlin/src/validate_tests.rs
+//
+// Registered rules are process-wide, so every test uses its own keys.
+
+use super::db;
+use super::test_utils::{add_file, db_with_files};
+use super::validate;
+use rusqlite::Connection;
+
+fn open_with_file() -> (Connection, i64) {
+    let conn = db_with_files(&[]);
+    let fid = add_file(&conn, "/docs/a.pdf");
+    (conn, fid)
+}
+
+#[test]
+fn settings_regex_guards_attribute_values() {
+    let (conn, fid) = open_with_file();
+    db::set_setting(&conn, "validate.attr.invoice/amount", r"^\d+(\.\d{2})?$").unwrap();
+
+    db::upsert_attr(&conn, fid, "invoice/amount", "12.50").unwrap();
+    let err = db::upsert_attr(&conn, fid, "invoice/amount", "12.5").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r"invalid value `12.5` for attribute `invoice/amount`: must match `^\d+(\.\d{2})?$`"
+    );
+    // other keys are not covered
+    db::upsert_attr(&conn, fid, "invoice/currency", "EUR").unwrap();
+}
+
+#[test]
+fn namespace_rules_cover_every_key_below() {
+    let (conn, fid) = open_with_file();
+    db::set_setting(&conn, "validate.attr.ticket/*", "^[A-Z]+-[0-9]+$").unwrap();
+
+    db::upsert_attr(&conn, fid, "ticket/parent", "OPS-12").unwrap();
+    assert!(db::upsert_attr(&conn, fid, "ticket/child", "twelve").is_err());
+    db::upsert_attr(&conn, fid, "tickets", "anything").unwrap();
+}
+
+#[test]
+fn tag_rules_check_the_path_below_the_namespace() {
+    let (conn, _) = open_with_file();
+    db::set_setting(&conn, "validate.tag.year", r"^\d{4}$").unwrap();
+
+    db::ensure_tag_path(&conn, "year/2024").unwrap();
+    db::ensure_tag_path(&conn, "yearly/24").unwrap();
+    let err = db::ensure_tag_path(&conn, "year/24").unwrap_err();
+    assert!(err.to_string().contains("invalid tag `year/24`"), "{err}");
+    assert!(db::find_tag_path(&conn, "year/24").unwrap().is_none());
+}
+
+#[test]
+fn registered_closures_and_regexes_apply() {
+    let (conn, fid) = open_with_file();
+    validate::register_attr("vt-closure/priority", |v| match v {
+        "low" | "high" => Ok(()),
+        _ => Err("must be low or high".into()),
+    });
+    validate::register_tag_regex("vt-regex-lang", "^[a-z]{2}$").unwrap();
+    assert!(validate::register_attr_regex("vt-bad", "(").is_err());
+
+    db::upsert_attr(&conn, fid, "vt-closure/priority", "high").unwrap();
+    let err = db::upsert_attr(&conn, fid, "vt-closure/priority", "urgent").unwrap_err();
+    assert!(err.to_string().ends_with("must be low or high"), "{err}");
+
+    db::ensure_tag_path(&conn, "vt-regex-lang/en").unwrap();
+    assert!(db::ensure_tag_path(&conn, "vt-regex-lang/english").is_err());
+}
+
+#[test]
+fn broken_settings_regex_is_reported() {
+    let (conn, fid) = open_with_file();
+    db::set_setting(&conn, "validate.attr.broken", "(").unwrap();
+    let err = db::upsert_attr(&conn, fid, "other", "x").unwrap_err();
+    assert!(
+        err.to_string().contains("invalid validation regex"),
+        "{err}"
+    );
+}
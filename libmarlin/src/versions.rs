@@ -0,0 +1,139 @@
+//! Content versions of files (`marlin version …`) – unlike the metadata
+//! snapshots of [`crate::snapshot`], these keep the file bodies.
+//!
+//! [`snapshot`] records a file's SHA-256 and, unless told not to, its
+//! zlib-compressed content as the file's next version; a file whose hash
+//! matches its latest version is left alone.  [`diff`] compares two
+//! stored versions, or one with the file on disk, as a unified diff.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{bail, Context, Result};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use rusqlite::Connection;
+use similar::TextDiff;
+
+use crate::db;
+use crate::describe::resolve;
+use crate::scan::sha256_reader;
+
+/// Files larger than this get their hash recorded but not their content.
+pub const MAX_CONTENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// How [`snapshot`] records a version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotOptions {
+    /// Store the content, not just its hash.
+    pub store_content: bool,
+    /// Only store the content of files up to this many bytes.
+    pub max_size: u64,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            store_content: true,
+            max_size: MAX_CONTENT_SIZE,
+        }
+    }
+}
+
+/// What [`snapshot`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Snapshot {
+    /// A new version, with or without its content.
+    New { version: i64, stored: bool },
+    /// The content is the same as in this, the latest version.
+    Unchanged { version: i64 },
+}
+
+/// Record the current content of the indexed file `file_id` at `path`.
+pub fn snapshot(
+    conn: &Connection,
+    file_id: i64,
+    path: &str,
+    opts: &SnapshotOptions,
+) -> Result<Snapshot> {
+    let bytes = fs::read(path).with_context(|| format!("reading {path}"))?;
+    let hash = sha256_reader(&bytes[..])?;
+    if let Some(latest) = db::versions(conn, file_id)?.pop() {
+        if latest.hash == hash {
+            return Ok(Snapshot::Unchanged {
+                version: latest.version,
+            });
+        }
+    }
+    let stored = opts.store_content && bytes.len() as u64 <= opts.max_size;
+    let content = if stored {
+        Some(compress(&bytes)?)
+    } else {
+        None
+    };
+    let mtime = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    let version = db::add_version(
+        conn,
+        file_id,
+        &hash,
+        bytes.len() as i64,
+        mtime,
+        content.as_deref(),
+    )?;
+    Ok(Snapshot::New { version, stored })
+}
+
+/// The content of `version` of file `file_id`; fails when only its hash
+/// was recorded.
+pub fn content(conn: &Connection, file_id: i64, version: i64) -> Result<Vec<u8>> {
+    match db::version_content(conn, file_id, version)? {
+        Some(blob) => {
+            let mut out = Vec::new();
+            ZlibDecoder::new(&blob[..])
+                .read_to_end(&mut out)
+                .with_context(|| format!("version {version} is corrupt"))?;
+            Ok(out)
+        }
+        None => bail!(
+            "version {version} of {} has only its hash, not its content",
+            db::file_path(conn, file_id)?
+        ),
+    }
+}
+
+/// Unified diff from version `from` of the indexed file at `path` to
+/// version `to`, or to the file on disk when `to` is `None`.  Empty when
+/// nothing changed.
+pub fn diff(conn: &Connection, path: &str, from: i64, to: Option<i64>) -> Result<String> {
+    let (fid, path) = resolve(conn, path)?;
+    let old = content(conn, fid, from)?;
+    let (new, new_label) = match to {
+        Some(v) => (content(conn, fid, v)?, format!("{path} (version {v})")),
+        None => (
+            fs::read(&path).with_context(|| format!("reading {path}"))?,
+            format!("{path} (on disk)"),
+        ),
+    };
+    let old_label = format!("{path} (version {from})");
+    if old == new {
+        return Ok(String::new());
+    }
+    match (std::str::from_utf8(&old), std::str::from_utf8(&new)) {
+        (Ok(old), Ok(new)) => Ok(TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_label, &new_label)
+            .to_string()),
+        _ => Ok(format!("Binary files {old_label} and {new_label} differ\n")),
+    }
+}
+
+fn compress(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(bytes)?;
+    Ok(enc.finish()?)
+}
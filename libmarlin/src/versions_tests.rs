@@ -0,0 +1,89 @@
+// libmarlin/src/versions_tests.rs
+
+use super::db;
+use super::test_utils::add_file;
+use super::versions::{self, Snapshot, SnapshotOptions};
+use std::fs;
+use tempfile::tempdir;
+
+#[test]
+fn snapshots_number_versions_and_skip_unchanged_content() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("notes.txt");
+    let path = file.to_string_lossy().to_string();
+    let conn = db::open(":memory:").unwrap();
+    let fid = add_file(&conn, &path);
+    let opts = SnapshotOptions::default();
+
+    fs::write(&file, "one\ntwo\nthree\n").unwrap();
+    assert_eq!(
+        versions::snapshot(&conn, fid, &path, &opts).unwrap(),
+        Snapshot::New {
+            version: 1,
+            stored: true
+        }
+    );
+    assert_eq!(
+        versions::snapshot(&conn, fid, &path, &opts).unwrap(),
+        Snapshot::Unchanged { version: 1 }
+    );
+    fs::write(&file, "one\n2\nthree\n").unwrap();
+    let hash_only = SnapshotOptions {
+        store_content: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        versions::snapshot(&conn, fid, &path, &hash_only).unwrap(),
+        Snapshot::New {
+            version: 2,
+            stored: false
+        }
+    );
+
+    let all = db::versions(&conn, fid).unwrap();
+    assert_eq!(all.len(), 2);
+    assert!(all[0].has_content && !all[1].has_content);
+    assert_eq!(all[0].size, 14);
+    assert_ne!(all[0].hash, all[1].hash);
+    assert_eq!(
+        versions::content(&conn, fid, 1).unwrap(),
+        b"one\ntwo\nthree\n"
+    );
+    assert!(versions::content(&conn, fid, 2)
+        .unwrap_err()
+        .to_string()
+        .contains("only its hash"));
+    assert!(versions::content(&conn, fid, 9)
+        .unwrap_err()
+        .to_string()
+        .contains("no version 9"));
+}
+
+#[test]
+fn diff_compares_versions_and_the_file_on_disk() {
+    let tmp = tempdir().unwrap();
+    let file = tmp.path().join("a.md");
+    let path = file.to_string_lossy().to_string();
+    let conn = db::open(":memory:").unwrap();
+    let fid = add_file(&conn, &path);
+    let opts = SnapshotOptions::default();
+
+    fs::write(&file, "alpha\nbeta\n").unwrap();
+    versions::snapshot(&conn, fid, &path, &opts).unwrap();
+    fs::write(&file, "alpha\ngamma\n").unwrap();
+    versions::snapshot(&conn, fid, &path, &opts).unwrap();
+
+    let d = versions::diff(&conn, &path, 1, Some(2)).unwrap();
+    assert!(
+        d.contains("(version 1)") && d.contains("(version 2)"),
+        "{d}"
+    );
+    assert!(d.contains("-beta\n") && d.contains("+gamma\n"), "{d}");
+    assert!(d.contains(" alpha\n"), "{d}");
+
+    assert_eq!(versions::diff(&conn, &path, 2, None).unwrap(), "");
+    fs::write(&file, [0u8, 159, 146, 150]).unwrap();
+    assert!(versions::diff(&conn, &path, 2, None)
+        .unwrap()
+        .starts_with("Binary files"));
+}
@@ -0,0 +1,175 @@
+//! File-system access behind a trait.
+//!
+//! Indexing (`scan`), stale-entry refresh and the body search fallback
+//! only touch files through [`Vfs`], so the metadata/query layer can run
+//! where there is no real file system – a browser viewer over an exported
+//! index, or tests – by plugging in [`MemFs`] or an own implementation.
+//! [`StdFs`] is the native default.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+/// The subset of file metadata Marlin stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMeta {
+    pub len: u64,
+    pub modified: SystemTime,
+    /// Device and inode number, where the platform has them (Unix).
+    pub dev: Option<u64>,
+    pub inode: Option<u64>,
+    /// Status-change time in nanoseconds since the epoch (Unix `st_ctime`).
+    /// Unlike the mtime it cannot be set by users and also moves on
+    /// metadata-only changes (permissions, owner, links).
+    pub ctime_ns: Option<i64>,
+}
+
+/// Read-only view of a file tree.
+pub trait Vfs: Send + Sync {
+    /// Metadata of a regular file.
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta>;
+
+    /// Stream a file's contents.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>>;
+
+    /// Every regular file below `root` (or `root` itself if it is a file).
+    /// Directories for which `prune` returns `true` are not descended
+    /// into; unreadable entries are skipped.
+    fn walk_files(&self, root: &Path, prune: &dyn Fn(&Path) -> bool) -> Vec<PathBuf>;
+
+    /// Whether `path` is a directory.
+    fn is_dir(&self, path: &Path) -> bool;
+}
+
+/* ─── native file system ──────────────────────────────────────────── */
+
+/// The real file system via `std::fs` and `walkdir`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdFs;
+
+impl Vfs for StdFs {
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let m = std::fs::metadata(path)?;
+        #[cfg(unix)]
+        let (dev, inode, ctime_ns) = {
+            use std::os::unix::fs::MetadataExt;
+            (
+                Some(m.dev()),
+                Some(m.ino()),
+                Some(m.ctime() * 1_000_000_000 + m.ctime_nsec()),
+            )
+        };
+        // Windows has no stable file index in std yet: size + mtime only
+        #[cfg(not(unix))]
+        let (dev, inode, ctime_ns) = (None, None, None);
+        Ok(FileMeta {
+            len: m.len(),
+            modified: m.modified()?,
+            dev,
+            inode,
+            ctime_ns,
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(std::fs::File::open(path)?))
+    }
+
+    fn walk_files(&self, root: &Path, prune: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+        walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|e| !prune(e.path()))
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .map(walkdir::DirEntry::into_path)
+            .collect()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+}
+
+/* ─── in-memory tree ──────────────────────────────────────────────── */
+
+/// A file tree held in memory (tests, wasm embedders, exported indexes).
+#[derive(Debug, Default)]
+pub struct MemFs {
+    files: RwLock<BTreeMap<PathBuf, (Vec<u8>, SystemTime)>>,
+}
+
+impl MemFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a file.
+    pub fn insert(&self, path: impl Into<PathBuf>, body: impl Into<Vec<u8>>, modified: SystemTime) {
+        self.files
+            .write()
+            .expect("MemFs lock poisoned")
+            .insert(path.into(), (body.into(), modified));
+    }
+
+    /// Remove a file; returns `false` if it did not exist.
+    pub fn remove(&self, path: &Path) -> bool {
+        self.files
+            .write()
+            .expect("MemFs lock poisoned")
+            .remove(path)
+            .is_some()
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, path.display().to_string())
+}
+
+impl Vfs for MemFs {
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let files = self.files.read().expect("MemFs lock poisoned");
+        let (body, modified) = files.get(path).ok_or_else(|| not_found(path))?;
+        Ok(FileMeta {
+            len: body.len() as u64,
+            modified: *modified,
+            dev: None,
+            inode: None,
+            ctime_ns: None,
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        let files = self.files.read().expect("MemFs lock poisoned");
+        let (body, _) = files.get(path).ok_or_else(|| not_found(path))?;
+        Ok(Box::new(io::Cursor::new(body.clone())))
+    }
+
+    fn walk_files(&self, root: &Path, prune: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+        let files = self.files.read().expect("MemFs lock poisoned");
+        files
+            .keys()
+            .filter(|p| p.starts_with(root))
+            .filter(|p| {
+                // a pruned directory hides everything below it
+                !p.ancestors()
+                    .skip(1)
+                    .take_while(|a| a.starts_with(root))
+                    .any(prune)
+                    && !prune(p)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Directories are implied: any path with a file below it.
+    fn is_dir(&self, path: &Path) -> bool {
+        let files = self.files.read().expect("MemFs lock poisoned");
+        files
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .next()
+            .is_some_and(|(p, _)| p.starts_with(path))
+    }
+}
@@ -0,0 +1,87 @@
+// libmarlin/src/vfs_tests.rs
+
+use super::db;
+use super::query::naive_substring_search_with;
+use super::roots::{self, RootProfile};
+use super::scan::{refresh_files_with, scan_directory_with};
+use super::vfs::{MemFs, Vfs};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn at(secs: u64) -> std::time::SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+#[test]
+fn memfs_walk_prunes_directories() {
+    let fs = MemFs::new();
+    fs.insert("/r/a.txt", "a", at(1));
+    fs.insert("/r/target/b.o", "b", at(1));
+    fs.insert("/other/c.txt", "c", at(1));
+
+    let prune = |p: &Path| p.ends_with("target");
+    let files = fs.walk_files(Path::new("/r"), &prune);
+    assert_eq!(files, vec![Path::new("/r/a.txt").to_path_buf()]);
+    assert_eq!(fs.metadata(Path::new("/r/a.txt")).unwrap().len, 1);
+    assert!(fs.metadata(Path::new("/missing")).is_err());
+}
+
+#[test]
+fn scan_and_refresh_without_real_files() {
+    let fs = MemFs::new();
+    fs.insert("/notes/todo.md", "buy milk", at(100));
+    fs.insert("/notes/build/out.md", "generated", at(100));
+
+    let mut conn = db::open(":memory:").unwrap();
+    let mut profile = RootProfile::default_for("/notes");
+    profile.ignore = vec!["build".into()];
+    profile.hash = true;
+    roots::save(&conn, &profile).unwrap();
+
+    assert_eq!(
+        scan_directory_with(&mut conn, &fs, Path::new("/notes")).unwrap(),
+        1
+    );
+    let (size, mtime, hash): (i64, i64, Option<String>) = conn
+        .query_row(
+            "SELECT size, mtime, hash FROM files WHERE path = '/notes/todo.md'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((size, mtime), (8, 100));
+    assert_eq!(hash.unwrap().len(), 64);
+
+    fs.insert("/notes/todo.md", "buy oat milk", at(200));
+    assert_eq!(
+        refresh_files_with(&mut conn, &fs, &["/notes/todo.md"]).unwrap(),
+        1
+    );
+    let size: i64 = conn
+        .query_row(
+            "SELECT size FROM files WHERE path = '/notes/todo.md'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(size, 12);
+}
+
+#[test]
+fn body_search_reads_through_vfs() {
+    let fs = MemFs::new();
+    fs.insert("/n/a.txt", "Hello Marlin", at(1));
+    fs.insert("/n/b.txt", "nothing here", at(1));
+
+    let mut conn = db::open(":memory:").unwrap();
+    scan_directory_with(&mut conn, &fs, Path::new("/n")).unwrap();
+
+    let hits = naive_substring_search_with(&conn, &fs, "marlin").unwrap();
+    assert_eq!(hits, vec!["/n/a.txt".to_string()]);
+
+    // once the body is gone only the path can match
+    fs.remove(Path::new("/n/a.txt"));
+    assert!(naive_substring_search_with(&conn, &fs, "marlin")
+        .unwrap()
+        .is_empty());
+}
@@ -6,20 +6,22 @@
 //! watcher can be paused, resumed and shut down cleanly.
 
 use crate::db::{self, Database};
+use crate::roots::RootProfile;
+use crate::utils::{normalize_path, PathNorm};
 use anyhow::{anyhow, Context, Result};
-use crossbeam_channel::{bounded, Receiver};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
 use notify::{
     event::{ModifyKind, RemoveKind, RenameMode},
     Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as NotifyWatcherTrait,
 };
 use same_file::Handle;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
-use std::time::{Duration, Instant};
-use tracing::info;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, warn};
 
 // ────── configuration ─────────────────────────────────────────────────────────
 #[derive(Debug, Clone)]
@@ -28,6 +30,24 @@ pub struct WatcherConfig {
     pub batch_size: usize,
     pub max_queue_size: usize,
     pub drain_timeout_ms: u64,
+    /// Events under these directories are dropped (e.g. the daemon's own
+    /// log dir, which would otherwise feed back into the watcher).
+    pub ignore: Vec<PathBuf>,
+    /// Registered root profiles; events the innermost one ignores are
+    /// dropped.
+    pub roots: Vec<RootProfile>,
+    /// Receives every debounced event; clones share it, so it survives a
+    /// watcher being recreated from the same config.
+    pub feed: EventFeed,
+    /// Thresholds of the deletion-burst guard; `None` turns it off.
+    pub burst: Option<BurstConfig>,
+    /// Called (on the watcher's thread) when a burst is detected, after
+    /// the warning and the [`crate::events::Event::DeletionBurst`] event.
+    /// [`crate::Marlin::watch`] installs one that takes a backup.
+    pub on_burst: Option<BurstHook>,
+    /// Deleted files stay in the index as tombstones, tags and attributes
+    /// included (see [`crate::prune`]), instead of leaving it with them.
+    pub keep_metadata: bool,
 }
 
 impl Default for WatcherConfig {
@@ -37,10 +57,134 @@ impl Default for WatcherConfig {
             batch_size: 1_000,
             max_queue_size: 100_000,
             drain_timeout_ms: 5_000,
+            ignore: Vec::new(),
+            roots: Vec::new(),
+            feed: EventFeed::default(),
+            burst: Some(BurstConfig::default()),
+            on_burst: None,
+            keep_metadata: true,
         }
     }
 }
 
+impl WatcherConfig {
+    /// Setting holding [`WatcherConfig::keep_metadata`].
+    pub const KEEP_METADATA_KEY: &'static str = "watch.keep_metadata";
+
+    /// [`WatcherConfig::keep_metadata`] as configured in the index's
+    /// settings; on where unset.
+    pub fn keep_metadata_from_settings(conn: &rusqlite::Connection) -> Result<bool> {
+        Ok(db::setting(conn, Self::KEEP_METADATA_KEY)?.as_deref() != Some("false"))
+    }
+}
+
+// ────── deletion-burst guard ─────────────────────────────────────────────────
+/// More than `threshold` deletions or renames within `window` look like
+/// ransomware or a mistaken `rm -rf`; the watcher then warns, emits an
+/// event and runs [`WatcherConfig::on_burst`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurstConfig {
+    pub threshold: usize,
+    pub window: Duration,
+}
+
+impl Default for BurstConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 200,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl BurstConfig {
+    /// Setting holding the threshold; `0` turns the guard off.
+    pub const THRESHOLD_KEY: &'static str = "watch.burst_threshold";
+    /// Setting holding the window, as accepted by
+    /// [`crate::utils::parse_duration`].
+    pub const WINDOW_KEY: &'static str = "watch.burst_window";
+
+    /// The guard as configured in the index's settings, defaults where
+    /// unset.
+    pub fn from_settings(conn: &rusqlite::Connection) -> Result<Option<Self>> {
+        let mut cfg = Self::default();
+        if let Some(v) = db::setting(conn, Self::THRESHOLD_KEY)? {
+            cfg.threshold = v
+                .parse()
+                .with_context(|| format!("invalid {} `{v}`", Self::THRESHOLD_KEY))?;
+        }
+        if let Some(v) = db::setting(conn, Self::WINDOW_KEY)? {
+            cfg.window = crate::utils::parse_duration(&v)?;
+        }
+        Ok((cfg.threshold > 0).then_some(cfg))
+    }
+}
+
+/// Callback run when a deletion burst is detected, with the number of
+/// deletions and renames seen in the window.
+#[derive(Clone)]
+pub struct BurstHook(Arc<dyn Fn(usize) + Send + Sync>);
+
+impl BurstHook {
+    pub fn new(f: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for BurstHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BurstHook")
+    }
+}
+
+/// Deletions and renames of the last [`BurstConfig::window`].
+#[derive(Default)]
+struct BurstGuard {
+    seen: VecDeque<(Instant, usize)>,
+    total: usize,
+}
+
+impl BurstGuard {
+    /// Count `n` more at `now`; returns the window's total once it exceeds
+    /// the threshold, and starts counting afresh.
+    fn record(&mut self, n: usize, now: Instant, cfg: &BurstConfig) -> Option<usize> {
+        self.seen.push_back((now, n));
+        self.total += n;
+        while let Some(&(t, k)) = self.seen.front() {
+            if now.duration_since(t) <= cfg.window {
+                break;
+            }
+            self.seen.pop_front();
+            self.total -= k;
+        }
+        if self.total > cfg.threshold {
+            let total = self.total;
+            self.seen.clear();
+            self.total = 0;
+            return Some(total);
+        }
+        None
+    }
+}
+
+fn deletion_burst(count: usize, config: &WatcherConfig) {
+    let window = config.burst.map_or(Duration::ZERO, |b| b.window);
+    warn!(
+        count,
+        window_secs = window.as_secs(),
+        "!!! DELETION BURST: {count} files deleted or renamed within {}s – \
+         check for ransomware or a mistaken delete !!!",
+        window.as_secs()
+    );
+    crate::events::emit(crate::events::Event::DeletionBurst {
+        count,
+        window_secs: window.as_secs(),
+    });
+    if let Some(hook) = &config.on_burst {
+        (hook.0)(count);
+    }
+}
+
 // ────── public state/useful telemetry ────────────────────────────────────────
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WatcherState {
@@ -58,6 +202,188 @@ pub struct WatcherStatus {
     pub queue_size: usize,
     pub start_time: Option<Instant>,
     pub watched_paths: Vec<PathBuf>,
+    /// Most recent error hit while applying events to the DB.
+    pub last_error: Option<String>,
+}
+
+// ────── event feed ───────────────────────────────────────────────────────────
+/// What happened to a path, as reported to [`EventFeed`] consumers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileEventKind {
+    Create,
+    Modify,
+    Remove,
+    Rename,
+    Access,
+}
+
+impl FileEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileEventKind::Create => "create",
+            FileEventKind::Modify => "modify",
+            FileEventKind::Remove => "remove",
+            FileEventKind::Rename => "rename",
+            FileEventKind::Access => "access",
+        }
+    }
+}
+
+/// One debounced change, after rename pairing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEvent {
+    pub kind: FileEventKind,
+    pub path: PathBuf,
+    /// Both set for renames.
+    pub old_path: Option<PathBuf>,
+    pub new_path: Option<PathBuf>,
+    /// When the watcher processed the event.
+    pub time: SystemTime,
+}
+
+impl FileEvent {
+    fn from_processed(ev: &ProcessedEvent) -> Self {
+        use notify::event::{AccessKind, AccessMode};
+
+        // the debouncer keeps the latest `kind` but the strongest priority,
+        // so a create followed by writes still reads as a create
+        let kind = match (ev.kind, ev.priority) {
+            _ if ev.old_path.is_some() && ev.new_path.is_some() => FileEventKind::Rename,
+            // an unpaired half of a rename moved the path out of or into
+            // the watched tree
+            (EventKind::Modify(ModifyKind::Name(RenameMode::From)), _) => FileEventKind::Remove,
+            (EventKind::Modify(ModifyKind::Name(RenameMode::To)), _) => FileEventKind::Create,
+            (_, EventPriority::Create) => FileEventKind::Create,
+            (_, EventPriority::Delete) => FileEventKind::Remove,
+            (_, EventPriority::Modify) => FileEventKind::Modify,
+            (EventKind::Access(AccessKind::Close(AccessMode::Write)), _) => FileEventKind::Modify,
+            (_, EventPriority::Access) => FileEventKind::Access,
+        };
+        Self {
+            kind,
+            path: ev.new_path.clone().unwrap_or_else(|| ev.path.clone()),
+            old_path: ev.old_path.clone(),
+            new_path: ev.new_path.clone(),
+            time: SystemTime::now(),
+        }
+    }
+
+    /// One NDJSON line (no trailing newline):
+    /// `{"kind":"rename","path":…,"old_path":…,"new_path":…,"ts":<unix ms>}`.
+    pub fn to_json(&self) -> String {
+        let path = |p: &Option<PathBuf>| {
+            p.as_ref().map_or_else(
+                || "null".to_string(),
+                |p| crate::events::json_str(&p.to_string_lossy()),
+            )
+        };
+        let ts = self
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        format!(
+            "{{\"kind\":\"{}\",\"path\":{},\"old_path\":{},\"new_path\":{},\"ts\":{ts}}}",
+            self.kind.as_str(),
+            crate::events::json_str(&self.path.to_string_lossy()),
+            path(&self.old_path),
+            path(&self.new_path),
+        )
+    }
+}
+
+/// Fan-out of processed events: the last [`EventFeed::HISTORY`] of them
+/// plus any number of live subscribers.
+#[derive(Clone, Default)]
+pub struct EventFeed {
+    inner: Arc<Mutex<FeedInner>>,
+}
+
+#[derive(Default)]
+struct FeedInner {
+    recent: VecDeque<FileEvent>,
+    subscribers: Vec<Sender<FileEvent>>,
+}
+
+impl std::fmt::Debug for EventFeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventFeed")
+    }
+}
+
+impl EventFeed {
+    /// Events kept for [`recent`](Self::recent).
+    pub const HISTORY: usize = 1_000;
+    /// Events a subscriber may fall behind before it is dropped.
+    pub const BACKLOG: usize = 4_096;
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FeedInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record `ev` and hand it to every subscriber.  A subscriber that
+    /// has fallen [`BACKLOG`](Self::BACKLOG) events behind is dropped
+    /// rather than silently missing events.
+    pub fn publish(&self, ev: FileEvent) {
+        let mut inner = self.lock();
+        inner
+            .subscribers
+            .retain(|tx| match tx.try_send(ev.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    warn!("dropping a watch event subscriber that fell behind");
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        if inner.recent.len() == Self::HISTORY {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(ev);
+    }
+
+    /// The last `n` events, oldest first.
+    pub fn recent(&self, n: usize) -> Vec<FileEvent> {
+        let inner = self.lock();
+        inner
+            .recent
+            .iter()
+            .skip(inner.recent.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+
+    /// Follow the feed, starting with the last `n` events.  The
+    /// subscription ends once it is dropped for falling behind.
+    pub fn subscribe(&self, n: usize) -> Subscription {
+        let (tx, rx) = bounded(Self::HISTORY + Self::BACKLOG);
+        let mut inner = self.lock();
+        let skip = inner.recent.len().saturating_sub(n);
+        for ev in inner.recent.iter().skip(skip) {
+            let _ = tx.try_send(ev.clone());
+        }
+        inner.subscribers.push(tx);
+        Subscription(rx)
+    }
+}
+
+/// Live events from [`EventFeed::subscribe`]; iterating blocks until the
+/// next one.
+pub struct Subscription(Receiver<FileEvent>);
+
+impl Subscription {
+    /// The next event, if one arrives within `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<FileEvent> {
+        self.0.recv_timeout(timeout).ok()
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = FileEvent;
+
+    fn next(&mut self) -> Option<FileEvent> {
+        self.0.recv().ok()
+    }
 }
 
 // ────── internal bookkeeping ─────────────────────────────────────────────────
@@ -85,6 +411,25 @@ struct EventDebouncer {
     last_flush: Instant,
 }
 
+/// Feed one modify event per entry of `paths` through a debouncer and
+/// flush it, returning the number of coalesced events.  Used by the
+/// benchmarks in [`crate::bench`] and `libmarlin/benches/`.
+pub fn debounce_burst(paths: &[PathBuf]) -> usize {
+    let mut debouncer = EventDebouncer::new(0);
+    let now = Instant::now();
+    for p in paths {
+        debouncer.add_event(ProcessedEvent {
+            path: p.clone(),
+            old_path: None,
+            new_path: None,
+            kind: EventKind::Modify(ModifyKind::Any),
+            priority: EventPriority::Modify,
+            timestamp: now,
+        });
+    }
+    debouncer.flush().len()
+}
+
 #[derive(Default)]
 struct RemoveTracker {
     map: HashMap<u64, (PathBuf, Instant)>,
@@ -105,6 +450,12 @@ impl RemoveTracker {
         }
     }
 
+    /// Like [`record`](Self::record) for a path that is already gone, with
+    /// the inode the index stored for it.
+    fn record_ino(&mut self, path: &Path, ino: u64) {
+        self.map.insert(ino, (path.to_path_buf(), Instant::now()));
+    }
+
     fn match_create(&mut self, path: &PathBuf, window: Duration) -> Option<PathBuf> {
         if let Ok(h) = Handle::from_path(path) {
             if let Some((old, ts)) = self.map.remove(&h.ino()) {
@@ -210,6 +561,7 @@ pub struct FileWatcher {
     queue_size: Arc<AtomicUsize>,
     start_time: Instant,
     db_shared: Arc<Mutex<Option<Arc<Mutex<Database>>>>>,
+    last_error: Arc<Mutex<Option<String>>>,
 }
 
 impl FileWatcher {
@@ -244,30 +596,124 @@ impl FileWatcher {
         let queue_size_clone = queue_size.clone();
         let state_clone = state.clone();
         let receiver_clone = rx.clone();
+        let last_error = Arc::new(Mutex::new(None::<String>));
+        let last_error_clone = last_error.clone();
 
         let db_shared_for_thread: Arc<Mutex<Option<Arc<Mutex<Database>>>>> =
             Arc::new(Mutex::new(None));
         let db_for_thread = db_shared_for_thread.clone();
 
+        /// Move the rows of a renamed path; returns `false` when the old
+        /// path was not indexed, so there was nothing to move.
         fn handle_db_update(
             db_mutex: &Mutex<Database>,
             old_s: &str,
             new_s: &str,
             is_dir: bool,
-        ) -> Result<()> {
+        ) -> Result<bool> {
             let mut guard = db_mutex.lock().map_err(|_| anyhow!("db mutex poisoned"))?;
             if is_dir {
                 db::rename_directory(guard.conn_mut(), old_s, new_s)?;
-            } else {
-                db::update_file_path(guard.conn_mut(), old_s, new_s)?;
+                // files moved in from outside the index are picked up below
+                return Ok(false);
+            }
+            if db::find_file_id(guard.conn(), old_s)?.is_none() {
+                return Ok(false);
             }
-            Ok(())
+            db::update_file_path(guard.conn_mut(), old_s, new_s)?;
+            Ok(true)
+        }
+
+        /// Bring the index in line with a flushed batch: renames move
+        /// rows, paths that are gone are dropped with their metadata, and
+        /// created or modified ones are indexed the way a scan would.
+        /// Errors are logged and kept as the watcher's last error; the
+        /// rest of the batch still applies.
+        fn apply_batch(
+            db_mutex: &Mutex<Database>,
+            events: &[FileEvent],
+            keep_metadata: bool,
+            last_error: &Mutex<Option<String>>,
+        ) {
+            let fail = |what: &str, e: anyhow::Error| {
+                warn!("DB {what} error: {:?}", e);
+                if let Ok(mut g) = last_error.lock() {
+                    *g = Some(format!("{e:#}"));
+                }
+            };
+            let mut index = Vec::new();
+            let mut remove = Vec::new();
+            for ev in events {
+                match ev.kind {
+                    FileEventKind::Access => continue,
+                    FileEventKind::Rename => {
+                        let (Some(old_p), Some(new_p)) = (&ev.old_path, &ev.new_path) else {
+                            continue;
+                        };
+                        let old_s = normalize_path(old_p, &PathNorm::default());
+                        let new_s = normalize_path(new_p, &PathNorm::default());
+                        match handle_db_update(db_mutex, &old_s, &new_s, new_p.is_dir()) {
+                            Ok(true) => {}
+                            Ok(false) => index.push(new_p.clone()),
+                            Err(e) => fail("rename", e),
+                        }
+                    }
+                    // a debounced create, modify or remove may be
+                    // followed by its opposite; what is on disk now counts
+                    _ if !ev.path.exists() => remove.push(ev.path.clone()),
+                    // a directory's own changes say nothing about its files
+                    FileEventKind::Modify if ev.path.is_dir() => {}
+                    _ => index.push(ev.path.clone()),
+                }
+            }
+            if index.is_empty() && remove.is_empty() {
+                return;
+            }
+            let Ok(mut guard) = db_mutex.lock() else {
+                fail("update", anyhow!("db mutex poisoned"));
+                return;
+            };
+            if !remove.is_empty() {
+                match guard.remove_files(&remove, keep_metadata) {
+                    Ok(r) => debug!(
+                        paths = remove.len(),
+                        removed = r.files,
+                        keep_metadata,
+                        "watcher removed files"
+                    ),
+                    Err(e) => fail("remove", e),
+                }
+            }
+            if !index.is_empty() {
+                match guard.index_files(&index, &db::IndexOptions::default()) {
+                    Ok(n) => debug!(paths = index.len(), indexed = n, "watcher indexed files"),
+                    Err(e) => fail("index", e),
+                }
+            }
+        }
+
+        fn indexed_inode(
+            db_slot: &Mutex<Option<Arc<Mutex<Database>>>>,
+            path: &Path,
+        ) -> Option<u64> {
+            let db_mutex = db_slot.lock().ok()?.clone()?;
+            let guard = Database::lock(&db_mutex);
+            guard
+                .query_row(
+                    "SELECT inode FROM files WHERE path = ?1",
+                    [normalize_path(path, &PathNorm::default())],
+                    |r| r.get::<_, Option<i64>>(0),
+                )
+                .ok()
+                .flatten()
+                .map(|i| i as u64)
         }
 
         let processor_thread = thread::spawn(move || {
             let mut debouncer = EventDebouncer::new(config_clone.debounce_ms);
             let mut rename_cache: HashMap<usize, PathBuf> = HashMap::new();
             let mut remove_tracker = RemoveTracker::default();
+            let mut burst_guard = BurstGuard::default();
 
             while !stop_flag_clone.load(Ordering::Relaxed) {
                 // honour current state
@@ -293,6 +739,27 @@ impl FileWatcher {
                     processed_in_batch += 1;
                     match evt_res {
                         Ok(event) => {
+                            if !event.paths.is_empty()
+                                && event.paths.iter().all(|p| {
+                                    config_clone.ignore.iter().any(|i| p.starts_with(i))
+                                        || crate::roots::innermost(&config_clone.roots, p)
+                                            .is_some_and(|r| r.ignores(p))
+                                })
+                            {
+                                continue;
+                            }
+                            let removed = match event.kind {
+                                EventKind::Remove(_) => event.paths.len(),
+                                // the `From` half already counted
+                                EventKind::Modify(ModifyKind::Name(RenameMode::To)) => 0,
+                                EventKind::Modify(ModifyKind::Name(_)) => 1,
+                                _ => 0,
+                            };
+                            if let Some(cfg) = config_clone.burst.as_ref().filter(|_| removed > 0) {
+                                if let Some(n) = burst_guard.record(removed, Instant::now(), cfg) {
+                                    deletion_burst(n, &config_clone);
+                                }
+                            }
                             let prio = match event.kind {
                                 EventKind::Create(_) => EventPriority::Create,
                                 EventKind::Remove(_) => EventPriority::Delete,
@@ -305,7 +772,14 @@ impl FileWatcher {
                             match event.kind {
                                 // 1. remove-then-create → rename heuristic using inode
                                 EventKind::Remove(_) if event.paths.len() == 1 => {
-                                    remove_tracker.record(&event.paths[0]);
+                                    // the file is gone, so only the index
+                                    // still knows its inode
+                                    match indexed_inode(&db_for_thread, &event.paths[0]) {
+                                        Some(ino) => {
+                                            remove_tracker.record_ino(&event.paths[0], ino)
+                                        }
+                                        None => remove_tracker.record(&event.paths[0]),
+                                    }
                                 }
 
                                 EventKind::Create(_) if event.paths.len() == 1 => {
@@ -458,24 +932,23 @@ impl FileWatcher {
 
                     let maybe_db = db_for_thread.lock().ok().and_then(|g| g.clone());
 
-                    for ev in &to_process {
-                        if let Some(db_mutex) = &maybe_db {
-                            // update DB for renames
-                            if let EventKind::Modify(ModifyKind::Name(_)) = ev.kind {
-                                if let (Some(old_p), Some(new_p)) = (&ev.old_path, &ev.new_path) {
-                                    let old_s = old_p.to_string_lossy();
-                                    let new_s = new_p.to_string_lossy();
-                                    let res =
-                                        handle_db_update(db_mutex, &old_s, &new_s, new_p.is_dir());
-                                    if let Err(e) = res {
-                                        eprintln!("DB rename error: {:?}", e);
-                                    }
-                                }
-                            }
+                    let events: Vec<FileEvent> =
+                        to_process.iter().map(FileEvent::from_processed).collect();
+                    if let Some(db_mutex) = &maybe_db {
+                        apply_batch(
+                            db_mutex,
+                            &events,
+                            config_clone.keep_metadata,
+                            &last_error_clone,
+                        );
+                    }
+                    for (ev, fe) in to_process.iter().zip(events) {
+                        if maybe_db.is_some() {
                             info!("processed (DB) {:?} {:?}", ev.kind, ev.path);
                         } else {
                             info!("processed       {:?} {:?}", ev.kind, ev.path);
                         }
+                        config_clone.feed.publish(fe);
                     }
                 }
 
@@ -487,8 +960,19 @@ impl FileWatcher {
             if debouncer.len() > 0 {
                 let final_evts = debouncer.flush();
                 events_processed_clone.fetch_add(final_evts.len(), Ordering::SeqCst);
-                for ev in &final_evts {
+                let events: Vec<FileEvent> =
+                    final_evts.iter().map(FileEvent::from_processed).collect();
+                if let Some(db_mutex) = db_for_thread.lock().ok().and_then(|g| g.clone()) {
+                    apply_batch(
+                        &db_mutex,
+                        &events,
+                        config_clone.keep_metadata,
+                        &last_error_clone,
+                    );
+                }
+                for (ev, fe) in final_evts.iter().zip(events) {
                     info!("processing final event {:?} {:?}", ev.kind, ev.path);
+                    config_clone.feed.publish(fe);
                 }
             }
 
@@ -510,6 +994,7 @@ impl FileWatcher {
             queue_size,
             start_time: Instant::now(),
             db_shared: db_shared_for_thread,
+            last_error,
         })
     }
 
@@ -589,8 +1074,17 @@ impl FileWatcher {
             queue_size: self.queue_size.load(Ordering::SeqCst),
             start_time: Some(self.start_time),
             watched_paths: self.watched_paths.clone(),
+            last_error: self.last_error.lock().ok().and_then(|g| g.clone()),
         })
     }
+
+    /// `false` once the processor thread has exited – normally after
+    /// [`stop`](Self::stop), otherwise because it died.
+    pub fn is_alive(&self) -> bool {
+        self.processor_thread
+            .as_ref()
+            .is_some_and(|h| !h.is_finished())
+    }
 }
 
 impl Drop for FileWatcher {
@@ -942,6 +1436,22 @@ mod file_watcher_state_tests {
         }
     }
 
+    #[test]
+    fn burst_guard_fires_once_the_window_holds_too_many() {
+        let cfg = BurstConfig {
+            threshold: 3,
+            window: Duration::from_secs(10),
+        };
+        let mut guard = BurstGuard::default();
+        let t0 = Instant::now();
+        assert_eq!(guard.record(2, t0, &cfg), None);
+        // the first two fall out of the window
+        assert_eq!(guard.record(2, t0 + Duration::from_secs(11), &cfg), None);
+        assert_eq!(guard.record(2, t0 + Duration::from_secs(12), &cfg), Some(4));
+        // counting starts afresh
+        assert_eq!(guard.record(1, t0 + Duration::from_secs(13), &cfg), None);
+    }
+
     #[test]
     fn test_watcher_default_config() {
         let cfg = WatcherConfig::default();
@@ -3,8 +3,10 @@
 #[cfg(test)]
 mod tests {
     // Updated import for BackupManager from the new backup module
+    #[cfg(feature = "backup")]
     use crate::backup::BackupManager;
     // These are still from the watcher module
+    #[cfg(feature = "backup")]
     use crate::db::open as open_marlin_db;
     use crate::watcher::{FileWatcher, WatcherConfig, WatcherState}; // Use your project's DB open function
     use crate::Marlin;
@@ -28,7 +30,7 @@ mod tests {
             let count: i64 = marlin
                 .conn()
                 .query_row(
-                    "SELECT COUNT(*) FROM files WHERE path = ?1",
+                    "SELECT COUNT(*) FROM files WHERE path = ?1 AND missing_since IS NULL",
                     [path.to_string_lossy()],
                     |r| r.get(0),
                 )
@@ -65,6 +67,12 @@ mod tests {
             batch_size: 10,
             max_queue_size: 100,
             drain_timeout_ms: 1000,
+            ignore: Vec::new(),
+            roots: Vec::new(),
+            feed: Default::default(),
+            burst: None,
+            on_burst: None,
+            keep_metadata: true,
         };
 
         let mut watcher = FileWatcher::new(vec![temp_path.to_path_buf()], config)
@@ -72,6 +80,7 @@ mod tests {
 
         watcher.start().expect("Failed to start watcher");
         assert_eq!(watcher.status().unwrap().state, WatcherState::Watching);
+        assert!(watcher.is_alive());
 
         thread::sleep(Duration::from_millis(200));
         let new_file_path = temp_path.join("new_file.txt");
@@ -81,7 +90,6 @@ mod tests {
 
         thread::sleep(Duration::from_millis(200));
         let mut existing_file_handle = fs::OpenOptions::new()
-            .write(true)
             .append(true)
             .open(&test_file_path)
             .expect("Failed to open test file for modification");
@@ -96,6 +104,8 @@ mod tests {
         watcher.stop().expect("Failed to stop watcher");
 
         assert_eq!(watcher.status().unwrap().state, WatcherState::Stopped);
+        assert!(!watcher.is_alive());
+        assert!(watcher.status().unwrap().last_error.is_none());
         assert!(
             watcher.status().unwrap().events_processed > 0,
             "Expected some file events to be processed"
@@ -103,6 +113,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "backup")]
     fn test_backup_manager_related_functionality() {
         let live_db_tmp_dir = tempdir().expect("Failed to create temp directory for live DB");
         let backups_storage_tmp_dir =
@@ -218,6 +229,188 @@ mod tests {
         assert_eq!(count, 1);
     }
 
+    #[test]
+    fn in_memory_instance_can_be_watched() {
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path();
+        let file = dir.join("a.txt");
+        fs::write(&file, b"hi").unwrap();
+        let mut marlin = Marlin::open_at(":memory:").unwrap();
+        marlin.scan(&[dir]).unwrap();
+
+        // the watcher writes through the handle's own connection, so the
+        // rename shows up in the in-memory database
+        let mut watcher = marlin
+            .watch(
+                dir,
+                Some(WatcherConfig {
+                    debounce_ms: 50,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        let new_file = dir.join("b.txt");
+        fs::rename(&file, &new_file).unwrap();
+        wait_for_row_count(&marlin, &new_file, 1, Duration::from_secs(10));
+        watcher.stop().unwrap();
+        wait_for_row_count(&marlin, &file, 0, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn creates_modifies_and_deletes_reach_the_index() {
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().canonicalize().unwrap();
+        let kept = dir.join("kept.txt");
+        fs::write(&kept, b"1").unwrap();
+        let doomed = dir.join("doomed.txt");
+        fs::write(&doomed, b"x").unwrap();
+
+        let mut marlin = Marlin::open_at(dir.join("index.db")).unwrap();
+        marlin.scan(&[&dir]).unwrap();
+        let mut watcher = marlin
+            .watch(
+                &dir,
+                Some(WatcherConfig {
+                    debounce_ms: 50,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        let created = dir.join("sub").join("new.txt");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(&created, b"new").unwrap();
+        wait_for_row_count(&marlin, &created, 1, Duration::from_secs(10));
+
+        fs::remove_file(&doomed).unwrap();
+        wait_for_row_count(&marlin, &doomed, 0, Duration::from_secs(10));
+
+        fs::write(&kept, b"longer now").unwrap();
+        let start = Instant::now();
+        loop {
+            let size: i64 = marlin
+                .conn()
+                .query_row(
+                    "SELECT size FROM files WHERE path = ?1",
+                    [kept.to_string_lossy()],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            if size == 10 {
+                break;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "modification not indexed"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+        watcher.stop().unwrap();
+        assert!(watcher.status().unwrap().last_error.is_none());
+    }
+
+    #[test]
+    fn deleted_files_keep_their_tags_unless_told_otherwise() {
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().canonicalize().unwrap();
+        let tagged = dir.join("tagged.txt");
+        fs::write(&tagged, b"x").unwrap();
+        let plain = dir.join("plain.txt");
+        fs::write(&plain, b"y").unwrap();
+
+        let mut marlin = Marlin::open_at(dir.join("index.db")).unwrap();
+        marlin.scan(&[&dir]).unwrap();
+        let tags = |marlin: &Marlin| -> i64 {
+            marlin
+                .conn()
+                .query_row("SELECT COUNT(*) FROM file_tags", [], |r| r.get(0))
+                .unwrap()
+        };
+        {
+            let conn = marlin.conn();
+            let id = crate::db::file_id(&conn, &tagged.to_string_lossy()).unwrap();
+            let tag = crate::db::ensure_tag_path(&conn, "project/alpha").unwrap();
+            crate::db::attach_tag(&conn, id, tag, 0).unwrap();
+        }
+        let before = tags(&marlin);
+        assert!(before > 0);
+
+        let mut watcher = marlin
+            .watch(
+                &dir,
+                Some(WatcherConfig {
+                    debounce_ms: 50,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        thread::sleep(Duration::from_millis(100));
+        fs::remove_file(&tagged).unwrap();
+        wait_for_row_count(&marlin, &tagged, 0, Duration::from_secs(10));
+        watcher.stop().unwrap();
+        assert_eq!(tags(&marlin), before);
+
+        // a tombstone comes back to life, tags and all
+        fs::write(&tagged, b"x").unwrap();
+        marlin.scan(&[&dir]).unwrap();
+        wait_for_row_count(&marlin, &tagged, 1, Duration::from_secs(1));
+        assert_eq!(tags(&marlin), before);
+
+        let mut watcher = marlin
+            .watch(
+                &dir,
+                Some(WatcherConfig {
+                    debounce_ms: 50,
+                    keep_metadata: false,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        thread::sleep(Duration::from_millis(100));
+        fs::remove_file(&tagged).unwrap();
+        fs::remove_file(&plain).unwrap();
+        wait_for_row_count(&marlin, &tagged, 0, Duration::from_secs(10));
+        watcher.stop().unwrap();
+        assert_eq!(tags(&marlin), 0);
+    }
+
+    #[test]
+    fn deleting_a_locked_file_leaves_its_row() {
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().canonicalize().unwrap();
+        let frozen = dir.join("frozen.txt");
+        fs::write(&frozen, b"x").unwrap();
+        let other = dir.join("other.txt");
+        fs::write(&other, b"y").unwrap();
+
+        let mut marlin = Marlin::open_at(dir.join("index.db")).unwrap();
+        marlin.scan(&[&dir]).unwrap();
+        {
+            let conn = marlin.conn();
+            let id = crate::db::file_id(&conn, &frozen.to_string_lossy()).unwrap();
+            crate::lock::lock_files(&conn, &[(id, String::new())]).unwrap();
+        }
+        let mut watcher = marlin
+            .watch(
+                &dir,
+                Some(WatcherConfig {
+                    debounce_ms: 50,
+                    keep_metadata: false,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        thread::sleep(Duration::from_millis(100));
+        fs::remove_file(&frozen).unwrap();
+        fs::remove_file(&other).unwrap();
+        wait_for_row_count(&marlin, &other, 0, Duration::from_secs(10));
+        watcher.stop().unwrap();
+        wait_for_row_count(&marlin, &frozen, 1, Duration::from_secs(1));
+    }
+
     #[test]
     fn rename_directory_updates_children() {
         let tmp = tempdir().unwrap();
@@ -269,4 +462,136 @@ mod tests {
             assert_eq!(cnt, 1, "{} missing", p.display());
         }
     }
+
+    #[test]
+    fn feed_keeps_history_and_fans_out() {
+        use crate::watcher::{EventFeed, FileEvent, FileEventKind};
+        use std::path::PathBuf;
+        use std::time::UNIX_EPOCH;
+
+        let ev = |i: u64| FileEvent {
+            kind: FileEventKind::Modify,
+            path: PathBuf::from(format!("/w/{i}.txt")),
+            old_path: None,
+            new_path: None,
+            time: UNIX_EPOCH + Duration::from_millis(i),
+        };
+        let feed = EventFeed::default();
+        for i in 0..EventFeed::HISTORY as u64 + 5 {
+            feed.publish(ev(i));
+        }
+        let recent = feed.recent(2);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1].path, PathBuf::from("/w/1004.txt"));
+        assert_eq!(feed.recent(usize::MAX).len(), EventFeed::HISTORY);
+
+        // a subscriber first gets the requested history, then live events
+        let sub = feed.subscribe(1);
+        feed.clone().publish(ev(7));
+        let got: Vec<_> = (0..2)
+            .map(|_| sub.recv_timeout(Duration::from_secs(1)).unwrap().path)
+            .collect();
+        assert_eq!(
+            got,
+            [PathBuf::from("/w/1004.txt"), PathBuf::from("/w/7.txt")]
+        );
+
+        // one that never reads is dropped instead of blocking the watcher
+        let mut stuck = feed.subscribe(0);
+        for i in 0..(EventFeed::HISTORY + EventFeed::BACKLOG) as u64 + 1 {
+            feed.publish(ev(i));
+        }
+        assert_eq!(
+            stuck.by_ref().count(),
+            EventFeed::HISTORY + EventFeed::BACKLOG
+        );
+
+        let line = FileEvent {
+            kind: FileEventKind::Rename,
+            path: PathBuf::from("/w/new"),
+            old_path: Some(PathBuf::from("/w/old")),
+            new_path: Some(PathBuf::from("/w/new")),
+            time: UNIX_EPOCH + Duration::from_millis(1500),
+        }
+        .to_json();
+        assert_eq!(
+            line,
+            "{\"kind\":\"rename\",\"path\":\"/w/new\",\"old_path\":\"/w/old\",\"new_path\":\"/w/new\",\"ts\":1500}"
+        );
+    }
+
+    #[test]
+    fn watcher_publishes_to_its_feed() {
+        use crate::watcher::{EventFeed, FileEventKind};
+
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().canonicalize().unwrap();
+        let marlin = Marlin::open_at(dir.join("feed.db")).unwrap();
+        let feed = EventFeed::default();
+        let sub = feed.subscribe(0);
+        let mut watcher = marlin
+            .watch(
+                &dir,
+                Some(WatcherConfig {
+                    debounce_ms: 50,
+                    feed,
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        let file = dir.join("new.txt");
+        fs::write(&file, b"x").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut seen = false;
+        while !seen && Instant::now() < deadline {
+            if let Some(ev) = sub.recv_timeout(Duration::from_millis(200)) {
+                seen = ev.path == file && ev.kind == FileEventKind::Create;
+            }
+        }
+        watcher.stop().unwrap();
+        assert!(seen, "no event for {}", file.display());
+    }
+
+    #[test]
+    fn deletion_burst_runs_the_hook() {
+        use crate::watcher::{BurstConfig, BurstHook};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().canonicalize().unwrap();
+        let files: Vec<_> = (0..6).map(|i| dir.join(format!("f{i}.txt"))).collect();
+        for f in &files {
+            fs::write(f, b"x").unwrap();
+        }
+        let fired = Arc::new(AtomicUsize::new(0));
+        let seen = fired.clone();
+        let config = WatcherConfig {
+            debounce_ms: 50,
+            burst: Some(BurstConfig {
+                threshold: 3,
+                window: Duration::from_secs(30),
+            }),
+            on_burst: Some(BurstHook::new(move |n| {
+                seen.store(n, Ordering::SeqCst);
+            })),
+            ..Default::default()
+        };
+        let mut watcher = FileWatcher::new(vec![dir.clone()], config).unwrap();
+        watcher.start().unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        for f in &files {
+            fs::remove_file(f).unwrap();
+        }
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while fired.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        watcher.stop().unwrap();
+        assert!(fired.load(Ordering::SeqCst) > 3);
+    }
 }
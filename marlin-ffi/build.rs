@@ -0,0 +1,23 @@
+// marlin-ffi/build.rs
+//
+// Regenerates the C header `include/marlin.h` from the `extern "C"`
+// items in src/lib.rs, so the committed header never drifts from the ABI.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml"))
+        .expect("reading cbindgen.toml");
+    match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(format!("{crate_dir}/include/marlin.h"));
+        }
+        Err(e) => {
+            eprintln!("Failed to generate marlin.h: {e}");
+            std::process::exit(1);
+        }
+    }
+}
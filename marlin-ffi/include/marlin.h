@@ -0,0 +1,96 @@
+#ifndef MARLIN_H
+#define MARLIN_H
+
+/* Generated by cbindgen from marlin-ffi/src/lib.rs – do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bumped whenever a signature or envelope shape changes incompatibly.
+ */
+#define MARLIN_ABI_VERSION 1
+
+/**
+ * An open index database.
+ */
+typedef struct MarlinHandle MarlinHandle;
+
+/**
+ * The ABI version this library implements (`MARLIN_ABI_VERSION`).
+ */
+uint32_t marlin_abi_version(void);
+
+/**
+ * Open (creating and migrating if needed) the database at `db_path`, or
+ * the default location when `db_path` is NULL.
+ *
+ * Returns NULL on failure; if `error_out` is not NULL it then receives a
+ * message to release with `marlin_string_free`.
+ *
+ * # Safety
+ * `db_path` must be NULL or a NUL-terminated string; `error_out` must be
+ * NULL or valid for a pointer write.
+ */
+struct MarlinHandle *marlin_open(const char *db_path, char **error_out);
+
+/**
+ * Close a handle.  NULL is ignored.
+ *
+ * # Safety
+ * `h` must be NULL or a pointer from `marlin_open` not closed before.
+ */
+void marlin_close(struct MarlinHandle *h);
+
+/**
+ * Release a string returned by this library.  NULL is ignored.
+ *
+ * # Safety
+ * `s` must be NULL or a string returned by this library, freed only once.
+ */
+void marlin_string_free(char *s);
+
+/**
+ * Index the directories in `paths_json`, a JSON array of strings.
+ * Result: `{"indexed": <files>}`.
+ *
+ * # Safety
+ * `h` must come from `marlin_open`; `paths_json` must be a
+ * NUL-terminated string.
+ */
+char *marlin_scan(struct MarlinHandle *h, const char *paths_json);
+
+/**
+ * Search the index.  Result: an array of matching paths.
+ *
+ * # Safety
+ * `h` must come from `marlin_open`; `query` must be a NUL-terminated
+ * string.
+ */
+char *marlin_search(struct MarlinHandle *h, const char *query);
+
+/**
+ * Tag every indexed file matching the glob `pattern` with `tag_path`
+ * (`parent/child`).  Result: `{"changed": <files>}`.
+ *
+ * # Safety
+ * `h` must come from `marlin_open`; the strings must be NUL-terminated.
+ */
+char *marlin_tag(struct MarlinHandle *h, const char *pattern, const char *tag_path);
+
+/**
+ * Set attribute `key` to `value` on every indexed file matching the glob
+ * `pattern`.  Result: `{"changed": <files>}`.
+ *
+ * # Safety
+ * `h` must come from `marlin_open`; the strings must be NUL-terminated.
+ */
+char *marlin_attr_set(struct MarlinHandle *h,
+                      const char *pattern,
+                      const char *key,
+                      const char *value);
+
+#endif  /* MARLIN_H */
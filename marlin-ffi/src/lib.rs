@@ -0,0 +1,207 @@
+//! marlin-ffi – C ABI over libmarlin.
+//!
+//! Front-ends written in Python, Swift, C# … embed the index engine
+//! through these functions instead of spawning the `marlin` CLI.  The
+//! header `include/marlin.h` is generated from this file by cbindgen.
+//!
+//! Conventions:
+//! * Strings are NUL-terminated UTF-8.
+//! * Every call except `marlin_open`/`marlin_close` returns a JSON
+//!   envelope, `{"ok":true,"result":…}` or `{"ok":false,"error":"…"}`,
+//!   that the caller releases with `marlin_string_free`.
+//! * A handle must not be used from two threads at once.
+//! * Panics never cross the boundary; they are reported as errors.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use libmarlin::Marlin;
+use serde_json::{json, Value};
+
+/// Bumped whenever a signature or envelope shape changes incompatibly.
+pub const MARLIN_ABI_VERSION: u32 = 1;
+
+/// An open index database.
+pub struct MarlinHandle {
+    inner: Marlin,
+}
+
+/* ─── helpers ─────────────────────────────────────────────────────── */
+
+fn into_c_string(s: String) -> *mut c_char {
+    // interior NULs cannot occur in serde_json output or error text we
+    // build, but never hand out a truncated string if they do
+    CString::new(s.replace('\0', "\\u0000"))
+        .expect("NULs were escaped")
+        .into_raw()
+}
+
+fn envelope(f: impl FnOnce() -> Result<Value>) -> *mut c_char {
+    let body = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(result)) => json!({ "ok": true, "result": result }),
+        Ok(Err(e)) => json!({ "ok": false, "error": format!("{e:#}") }),
+        Err(_) => json!({ "ok": false, "error": "internal panic in libmarlin" }),
+    };
+    into_c_string(body.to_string())
+}
+
+/// Borrow a C string argument.
+///
+/// # Safety
+/// `p` must be NULL or point to a NUL-terminated string that outlives `'a`.
+unsafe fn arg<'a>(p: *const c_char, name: &str) -> Result<&'a str> {
+    if p.is_null() {
+        return Err(anyhow!("`{name}` must not be NULL"));
+    }
+    CStr::from_ptr(p)
+        .to_str()
+        .map_err(|_| anyhow!("`{name}` is not valid UTF-8"))
+}
+
+/// # Safety
+/// `h` must be NULL or a live pointer returned by `marlin_open`.
+unsafe fn handle<'a>(h: *mut MarlinHandle) -> Result<&'a mut Marlin> {
+    h.as_mut()
+        .map(|h| &mut h.inner)
+        .ok_or_else(|| anyhow!("handle must not be NULL"))
+}
+
+/* ─── lifecycle ───────────────────────────────────────────────────── */
+
+/// The ABI version this library implements (`MARLIN_ABI_VERSION`).
+#[no_mangle]
+pub extern "C" fn marlin_abi_version() -> u32 {
+    MARLIN_ABI_VERSION
+}
+
+/// Open (creating and migrating if needed) the database at `db_path`, or
+/// the default location when `db_path` is NULL.
+///
+/// Returns NULL on failure; if `error_out` is not NULL it then receives a
+/// message to release with `marlin_string_free`.
+///
+/// # Safety
+/// `db_path` must be NULL or a NUL-terminated string; `error_out` must be
+/// NULL or valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn marlin_open(
+    db_path: *const c_char,
+    error_out: *mut *mut c_char,
+) -> *mut MarlinHandle {
+    let opened = catch_unwind(|| -> Result<Marlin> {
+        if db_path.is_null() {
+            Marlin::open_default()
+        } else {
+            Marlin::open_at(arg(db_path, "db_path")?)
+        }
+    });
+    let err = match opened {
+        Ok(Ok(inner)) => return Box::into_raw(Box::new(MarlinHandle { inner })),
+        Ok(Err(e)) => format!("{e:#}"),
+        Err(_) => "internal panic in libmarlin".to_string(),
+    };
+    if !error_out.is_null() {
+        *error_out = into_c_string(err);
+    }
+    ptr::null_mut()
+}
+
+/// Close a handle.  NULL is ignored.
+///
+/// # Safety
+/// `h` must be NULL or a pointer from `marlin_open` not closed before.
+#[no_mangle]
+pub unsafe extern "C" fn marlin_close(h: *mut MarlinHandle) {
+    if !h.is_null() {
+        drop(Box::from_raw(h));
+    }
+}
+
+/// Release a string returned by this library.  NULL is ignored.
+///
+/// # Safety
+/// `s` must be NULL or a string returned by this library, freed only once.
+#[no_mangle]
+pub unsafe extern "C" fn marlin_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/* ─── operations ──────────────────────────────────────────────────── */
+
+/// Index the directories in `paths_json`, a JSON array of strings.
+/// Result: `{"indexed": <files>}`.
+///
+/// # Safety
+/// `h` must come from `marlin_open`; `paths_json` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn marlin_scan(
+    h: *mut MarlinHandle,
+    paths_json: *const c_char,
+) -> *mut c_char {
+    envelope(|| {
+        let m = handle(h)?;
+        let paths: Vec<String> = serde_json::from_str(arg(paths_json, "paths_json")?)
+            .map_err(|e| anyhow!("`paths_json` must be a JSON array of strings: {e}"))?;
+        let indexed = m.scan(&paths)?;
+        Ok(json!({ "indexed": indexed }))
+    })
+}
+
+/// Search the index.  Result: an array of matching paths.
+///
+/// # Safety
+/// `h` must come from `marlin_open`; `query` must be a NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn marlin_search(h: *mut MarlinHandle, query: *const c_char) -> *mut c_char {
+    envelope(|| {
+        let m = handle(h)?;
+        Ok(json!(m.search(arg(query, "query")?)?))
+    })
+}
+
+/// Tag every indexed file matching the glob `pattern` with `tag_path`
+/// (`parent/child`).  Result: `{"changed": <files>}`.
+///
+/// # Safety
+/// `h` must come from `marlin_open`; the strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn marlin_tag(
+    h: *mut MarlinHandle,
+    pattern: *const c_char,
+    tag_path: *const c_char,
+) -> *mut c_char {
+    envelope(|| {
+        let m = handle(h)?;
+        let changed = m.tag(arg(pattern, "pattern")?, arg(tag_path, "tag_path")?)?;
+        Ok(json!({ "changed": changed }))
+    })
+}
+
+/// Set attribute `key` to `value` on every indexed file matching the glob
+/// `pattern`.  Result: `{"changed": <files>}`.
+///
+/// # Safety
+/// `h` must come from `marlin_open`; the strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn marlin_attr_set(
+    h: *mut MarlinHandle,
+    pattern: *const c_char,
+    key: *const c_char,
+    value: *const c_char,
+) -> *mut c_char {
+    envelope(|| {
+        let m = handle(h)?;
+        let changed = m.attr_set(
+            arg(pattern, "pattern")?,
+            arg(key, "key")?,
+            arg(value, "value")?,
+        )?;
+        Ok(json!({ "changed": changed }))
+    })
+}
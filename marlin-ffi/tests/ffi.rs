@@ -0,0 +1,97 @@
+// marlin-ffi/tests/ffi.rs
+//
+// Drives the C ABI the way a foreign host would: raw pointers in, JSON
+// envelopes out.
+
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::ptr;
+
+use marlin_ffi::*;
+use serde_json::Value;
+
+fn c(s: &str) -> CString {
+    CString::new(s).unwrap()
+}
+
+/// Decode and free an envelope returned by the library.
+fn take(p: *mut c_char) -> Value {
+    assert!(!p.is_null());
+    let v = serde_json::from_str(unsafe { CStr::from_ptr(p) }.to_str().unwrap()).unwrap();
+    unsafe { marlin_string_free(p) };
+    v
+}
+
+#[test]
+fn open_scan_tag_attr_search() {
+    let tmp = tempfile::tempdir().unwrap();
+    let docs = tmp.path().join("docs");
+    fs::create_dir(&docs).unwrap();
+    fs::write(docs.join("plan.md"), "quarterly plan").unwrap();
+    fs::write(docs.join("notes.txt"), "misc").unwrap();
+
+    let db = c(tmp.path().join("index.db").to_str().unwrap());
+    let h = unsafe { marlin_open(db.as_ptr(), ptr::null_mut()) };
+    assert!(!h.is_null());
+    assert_eq!(marlin_abi_version(), MARLIN_ABI_VERSION);
+
+    let paths = c(&serde_json::json!([docs]).to_string());
+    let v = take(unsafe { marlin_scan(h, paths.as_ptr()) });
+    assert_eq!(v["ok"], true);
+    assert_eq!(v["result"]["indexed"], 2);
+
+    let v = take(unsafe { marlin_tag(h, c("*.md").as_ptr(), c("project/q3").as_ptr()) });
+    assert_eq!(v["result"]["changed"], 1);
+
+    let v = take(unsafe {
+        marlin_attr_set(
+            h,
+            c("*.txt").as_ptr(),
+            c("status").as_ptr(),
+            c("draft").as_ptr(),
+        )
+    });
+    assert_eq!(v["result"]["changed"], 1);
+
+    let v = take(unsafe { marlin_search(h, c("tags_text:\"project/q3\"").as_ptr()) });
+    let hits = v["result"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert!(hits[0].as_str().unwrap().ends_with("plan.md"));
+
+    unsafe { marlin_close(h) };
+}
+
+#[test]
+fn errors_come_back_as_envelopes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let db = c(tmp.path().join("index.db").to_str().unwrap());
+    let h = unsafe { marlin_open(db.as_ptr(), ptr::null_mut()) };
+
+    let v = take(unsafe { marlin_scan(h, c("not json").as_ptr()) });
+    assert_eq!(v["ok"], false);
+    assert!(v["error"].as_str().unwrap().contains("JSON array"));
+
+    let v = take(unsafe { marlin_search(h, ptr::null()) });
+    assert_eq!(v["ok"], false);
+
+    let v = take(unsafe { marlin_search(ptr::null_mut(), c("x").as_ptr()) });
+    assert_eq!(v["error"], "handle must not be NULL");
+
+    unsafe { marlin_close(h) };
+}
+
+#[test]
+fn open_failure_reports_error() {
+    let tmp = tempfile::tempdir().unwrap();
+    // a directory where the DB file should be
+    let db = c(tmp.path().to_str().unwrap());
+    let mut err: *mut c_char = ptr::null_mut();
+    let h = unsafe { marlin_open(db.as_ptr(), &mut err) };
+    assert!(h.is_null());
+    assert!(!err.is_null());
+    let msg = unsafe { CStr::from_ptr(err) }
+        .to_string_lossy()
+        .into_owned();
+    assert!(msg.contains("opening database"), "{msg}");
+    unsafe { marlin_string_free(err) };
+}